@@ -0,0 +1,120 @@
+//! Criterion benchmarks of the modpow paths of vss: dealing shares and commitments, verifying
+//! shares and reconstructing, for several thresholds, numbers of players and sizes of q
+//!
+//! Run with `cargo bench`, or `cargo bench -- verify_share` for one group. Finding the group is
+//! left out of every measurement.
+
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
+use num_bigint::{BigUint, RandBigInt};
+use rust_vss::dealer::Dealer;
+use rust_vss::secret::Secret;
+use rust_vss::vss;
+
+/// (t, n, bits of q) of every measurement
+const CELLS: [(usize, usize, usize); 4] = [(3, 5, 64), (3, 5, 256), (10, 20, 64), (10, 20, 256)];
+
+/// A dealing of each cell along with its coefficients
+fn dealings() -> Vec<(Dealer, Vec<BigUint>)> {
+    let mut rng = rand::thread_rng();
+
+    CELLS
+        .iter()
+        .map(|&(t, n, bits)| {
+            let dealer =
+                Dealer::with_bits(n, t, Secret::new(BigUint::from(1234_u32)), bits).unwrap();
+            let a = (0..t).map(|_| rng.gen_biguint_below(&dealer.q)).collect();
+
+            (dealer, a)
+        })
+        .collect()
+}
+
+fn id(dealer: &Dealer) -> BenchmarkId {
+    let bits = dealer.q.bits();
+
+    BenchmarkId::from_parameter(format!("t={} n={} bits={}", dealer.t, dealer.n, bits))
+}
+
+fn bench(c: &mut Criterion) {
+    let dealings = dealings();
+
+    let mut group = c.benchmark_group("generate_shares");
+    for (dealer, a) in &dealings {
+        group.bench_with_input(id(dealer), a, |b, a| {
+            b.iter(|| vss::generate_shares(a, dealer.n, &dealer.q))
+        });
+    }
+    group.finish();
+
+    let mut group = c.benchmark_group("generate_commitments");
+    for (dealer, a) in &dealings {
+        group.bench_with_input(id(dealer), a, |b, a| {
+            b.iter(|| vss::generate_commitments(a, &dealer.g, &dealer.p))
+        });
+    }
+    group.finish();
+
+    // one share, then every share of the dealing as a player checking a batch would
+    let mut group = c.benchmark_group("verify_share");
+    for (dealer, _) in &dealings {
+        let share = &dealer.shares[0];
+        group.bench_with_input(id(dealer), share, |b, share| {
+            b.iter(|| {
+                vss::verify_share(
+                    share.index(),
+                    share.value(),
+                    &dealer.g,
+                    &dealer.c,
+                    &dealer.p,
+                )
+            })
+        });
+    }
+    group.finish();
+
+    let mut group = c.benchmark_group("verify_share_batch");
+    for (dealer, _) in &dealings {
+        group.bench_with_input(id(dealer), &dealer.shares, |b, shares| {
+            b.iter(|| {
+                shares.iter().all(|share| {
+                    vss::verify_share(
+                        share.index(),
+                        share.value(),
+                        &dealer.g,
+                        &dealer.c,
+                        &dealer.p,
+                    )
+                })
+            })
+        });
+    }
+    group.finish();
+
+    // the same, with powers of indices and windows of g kept between shares
+    let mut group = c.benchmark_group("verify_share_cached");
+    for (dealer, _) in &dealings {
+        let verifier =
+            vss::Verifier::new(&dealer.g, &dealer.p, dealer.n).with_windows(dealer.q.bits());
+        group.bench_with_input(id(dealer), &dealer.shares, |b, shares| {
+            let mut verifier = verifier.clone();
+            b.iter(|| {
+                shares
+                    .iter()
+                    .all(|share| verifier.verify_share(share.index(), share.value(), &dealer.c))
+            })
+        });
+    }
+    group.finish();
+
+    let mut group = c.benchmark_group("reconstruct");
+    for (dealer, _) in &dealings {
+        let shares = &dealer.shares[..dealer.t];
+        group.bench_with_input(id(dealer), shares, |b, shares| {
+            b.iter(|| vss::reconstruct(shares, &dealer.q).unwrap())
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, bench);
+criterion_main!(benches);
@@ -0,0 +1,8 @@
+fn main() {
+    #[cfg(feature = "protobuf")]
+    {
+        println!("cargo:rerun-if-changed=proto/vss.proto");
+        std::env::set_var("PROTOC", protoc_bin_vendored::protoc_bin_path().unwrap());
+        prost_build::compile_protos(&["proto/vss.proto"], &["proto/"]).unwrap();
+    }
+}
@@ -0,0 +1,26 @@
+//! Share files, binary shares and transcripts are read from disk and handed around by people, any bytes must
+//! be refused without panicking
+
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use rust_vss::binary::{BinaryShare, Integrity};
+use rust_vss::format::{self, EncryptedShareFile};
+
+fuzz_target!(|data: &[u8]| {
+    if let Ok(info) = format::read_share(data) {
+        // whatever parses must verify or not without panicking, and write back out
+        info.verify();
+        let mut out = vec![];
+        format::write_share(&mut out, &info).unwrap();
+    }
+    if let Ok(transcript) = format::read_transcript(data) {
+        transcript.fingerprint();
+    }
+    // a binary share that decodes encodes back to the same bytes
+    if let Ok(binary) = BinaryShare::decode(data, Integrity::Crc32) {
+        assert_eq!(data, &binary.encode(Integrity::Crc32).unwrap()[..]);
+    }
+    // only parsed, decrypting runs the file's own Argon2 costs
+    let _ = serde_json::from_slice::<EncryptedShareFile>(data);
+});
@@ -0,0 +1,34 @@
+//! A dealer's commitments and group come over the wire, verifying a share against any of them
+//! must answer without panicking
+
+#![no_main]
+
+use arbitrary::Arbitrary;
+use libfuzzer_sys::fuzz_target;
+use num_bigint::BigUint;
+use rust_vss::vss;
+
+/// Numbers as big endian bytes, small enough that i^j stays cheap to compute
+#[derive(Debug, Arbitrary)]
+struct Input {
+    i: [u8; 2],
+    s: Vec<u8>,
+    g: Vec<u8>,
+    c: Vec<Vec<u8>>,
+    p: Vec<u8>,
+}
+
+fuzz_target!(|input: Input| {
+    if input.c.len() > 16 {
+        return;
+    }
+    let c: Vec<BigUint> = input.c.iter().map(|c| BigUint::from_bytes_be(c)).collect();
+
+    vss::verify_share(
+        &BigUint::from_bytes_be(&input.i),
+        &BigUint::from_bytes_be(&input.s),
+        &BigUint::from_bytes_be(&input.g),
+        &c,
+        &BigUint::from_bytes_be(&input.p),
+    );
+});
@@ -0,0 +1,18 @@
+//! Every frame a peer sends is decoded before anything checks who sent it, any bytes must be
+//! refused without panicking
+
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use rust_vss::codec::{Cbor, Codec};
+use rust_vss::proto;
+use rust_vss::WireMessage;
+
+fuzz_target!(|data: &[u8]| {
+    if let Ok(msg) = Cbor.decode(data) {
+        // what decodes must encode again
+        Cbor.encode(&msg).unwrap();
+    }
+    let _ = rust_vss::codec::decode::<WireMessage>(data);
+    let _ = proto::decode(data);
+});
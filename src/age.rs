@@ -0,0 +1,166 @@
+//! Share files encrypted to age recipients, so shares go out through the same keys and tools
+//! as any other file a custodian receives and `age --decrypt` opens them
+//!
+//! An encrypted share is a share file as `format::write_share` writes it, encrypted to one or
+//! more recipients and ASCII-armored. A share may also be encrypted to an X25519 identity made
+//! for it alone, handed to its custodian along with the file.
+
+use std::io::{self, Read, Write};
+use std::path::Path;
+
+use age::armor::{ArmoredReader, ArmoredWriter, Format};
+use age::secrecy::ExposeSecret;
+use age::{x25519, DecryptError, Decryptor, EncryptError, Encryptor, Identity, Recipient};
+use zeroize::Zeroizing;
+
+use crate::format;
+use crate::rpc::ShareInfo;
+
+/// age's encryption error as an io::Error
+fn encrypt_error(err: EncryptError) -> io::Error {
+    match err {
+        EncryptError::Io(err) => err,
+        err => io::Error::new(io::ErrorKind::InvalidInput, format!("age: {}", err)),
+    }
+}
+
+/// age's decryption error as an io::Error of the closest kind
+fn decrypt_error(err: DecryptError) -> io::Error {
+    let kind = match err {
+        DecryptError::Io(err) => return err,
+        DecryptError::NoMatchingKeys => io::ErrorKind::PermissionDenied,
+        _ => io::ErrorKind::InvalidData,
+    };
+
+    io::Error::new(kind, format!("age: {}", err))
+}
+
+/// `info` as a share file encrypted to every one of `recipients`, ASCII-armored
+pub fn encrypt_share(info: &ShareInfo, recipients: &[&dyn Recipient]) -> io::Result<Vec<u8>> {
+    let mut plaintext = Zeroizing::new(vec![]);
+    format::write_share(&mut *plaintext, info)?;
+
+    let encryptor =
+        Encryptor::with_recipients(recipients.iter().copied()).map_err(encrypt_error)?;
+    let armor = ArmoredWriter::wrap_output(vec![], Format::AsciiArmor)?;
+    let mut w = encryptor.wrap_output(armor)?;
+    w.write_all(&plaintext)?;
+
+    w.finish()?.finish()
+}
+
+/// The share of an encrypted share file, decrypted with the first of `identities` it was
+/// encrypted to. Armored and binary files are both read
+pub fn decrypt_share(bytes: &[u8], identities: &[&dyn Identity]) -> io::Result<ShareInfo> {
+    let decryptor = Decryptor::new_buffered(ArmoredReader::new(bytes)).map_err(decrypt_error)?;
+    let mut r = decryptor
+        .decrypt(identities.iter().copied())
+        .map_err(decrypt_error)?;
+    let mut plaintext = Zeroizing::new(vec![]);
+    r.read_to_end(&mut plaintext)?;
+
+    format::read_share(&plaintext[..])
+}
+
+/// `info` encrypted to a new X25519 identity, returned along with it
+pub fn encrypt_to_new_identity(info: &ShareInfo) -> io::Result<(x25519::Identity, Vec<u8>)> {
+    let identity = x25519::Identity::generate();
+    let bytes = encrypt_share(info, &[&identity.to_public()])?;
+
+    Ok((identity, bytes))
+}
+
+/// The X25519 recipient of an `age1...` string
+pub fn recipient(s: &str) -> io::Result<x25519::Recipient> {
+    s.parse().map_err(|err| {
+        io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("age recipient {:?}: {}", s, err),
+        )
+    })
+}
+
+/// Every identity of the age identity file at `path`
+pub fn load_identities<P: AsRef<Path>>(path: P) -> io::Result<Vec<Box<dyn Identity>>> {
+    let path = path.as_ref().display().to_string();
+
+    age::IdentityFile::from_file(path)?
+        .into_identities()
+        .map_err(decrypt_error)
+}
+
+/// Write `identity` as an age identity file, readable by `load_identities` and age itself
+pub fn write_identity<W: Write>(w: W, identity: &x25519::Identity) -> io::Result<()> {
+    let mut w = w;
+    writeln!(w, "# public key: {}", identity.to_public())?;
+
+    writeln!(w, "{}", identity.to_string().expose_secret())
+}
+
+/// Write `info` encrypted to `recipients` at `path`
+pub fn save_share<P: AsRef<Path>>(
+    path: P,
+    info: &ShareInfo,
+    recipients: &[&dyn Recipient],
+) -> io::Result<()> {
+    std::fs::write(path, encrypt_share(info, recipients)?)
+}
+
+/// The share of the encrypted share file at `path`, decrypted with `identities`
+pub fn load_share<P: AsRef<Path>>(path: P, identities: &[&dyn Identity]) -> io::Result<ShareInfo> {
+    let path = path.as_ref();
+
+    std::fs::read(path)
+        .and_then(|bytes| decrypt_share(&bytes, identities))
+        .map_err(|err| io::Error::new(err.kind(), format!("{}: {}", path.display(), err)))
+}
+
+/// Identities borrowed as decrypt_share takes them
+pub fn identities(identities: &[Box<dyn Identity>]) -> Vec<&dyn Identity> {
+    identities
+        .iter()
+        .map(|identity| identity.as_ref())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use age::x25519;
+    use num_bigint::BigUint;
+
+    use crate::dealer::Dealer;
+    use crate::rpc::ShareInfo;
+    use crate::secret::Secret;
+
+    use super::{decrypt_share, encrypt_share, encrypt_to_new_identity, load_identities};
+
+    #[test]
+    fn shares_decrypt_with_their_recipients_identity() {
+        let dealer = Dealer::with_bits(3, 2, Secret::new(BigUint::from(42_u32)), 64).unwrap();
+        let info = ShareInfo::with_params(dealer.shares[0].clone(), dealer.params());
+        let (alice, bob) = (x25519::Identity::generate(), x25519::Identity::generate());
+
+        let bytes = encrypt_share(&info, &[&alice.to_public(), &bob.to_public()]).unwrap();
+        assert!(bytes.starts_with(b"-----BEGIN AGE ENCRYPTED FILE-----"));
+        assert_eq!(info, decrypt_share(&bytes, &[&alice]).unwrap());
+        assert_eq!(info, decrypt_share(&bytes, &[&bob]).unwrap());
+
+        let (identity, bytes) = encrypt_to_new_identity(&info).unwrap();
+        assert_eq!(info, decrypt_share(&bytes, &[&identity]).unwrap());
+        let err = decrypt_share(&bytes, &[&alice]).unwrap_err();
+        assert_eq!(std::io::ErrorKind::PermissionDenied, err.kind());
+    }
+
+    #[test]
+    fn identity_files_round_trip() {
+        let identity = x25519::Identity::generate();
+        let path = std::env::temp_dir().join(format!("rust_vss-age-{}.txt", std::process::id()));
+        let mut file = vec![];
+        super::write_identity(&mut file, &identity).unwrap();
+        std::fs::write(&path, &file).unwrap();
+
+        let identities = load_identities(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(1, identities.len());
+    }
+}
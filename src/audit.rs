@@ -0,0 +1,324 @@
+//! Tamper-evident log of the protocol events of a Dealer or Player, for compliance review of
+//! custody operations
+//!
+//! Every entry carries the SHA-256 hash of the previous one, so removing, reordering or editing
+//! an entry breaks the chain from there on, and optionally an ed25519 signature of its hash by
+//! the actor that wrote it. A log is written as JSON lines as events happen, read back with
+//! `read` and checked with `verify`.
+
+use std::fs::{File, OpenOptions};
+use std::io::{self, BufRead, BufReader, Write};
+use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use ed25519_dalek::{Signature, Signer, SigningKey, Verifier, VerifyingKey};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+/// A protocol event
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum Event {
+    /// The dealer dealt a secret to `players`, `threshold` of which reconstruct it
+    Dealt {
+        players: usize,
+        threshold: usize,
+        fingerprint: String,
+    },
+    /// A share received from `from` verified against the commitments or not
+    Verified { from: usize, valid: bool },
+    /// `accuser` complained that the dealer sent it an invalid share
+    Complained { accuser: usize },
+    /// A majority of players voted to disqualify the dealer
+    Disqualified,
+    /// The secret was reconstructed from the shares of `contributors`, or could not be
+    Reconstructed { contributors: Vec<usize>, ok: bool },
+}
+
+/// An event as logged
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Entry {
+    pub seq: u64,
+    /// Seconds since the Unix epoch
+    pub time: u64,
+    pub actor: String,
+    pub session: u64,
+    #[serde(flatten)]
+    pub event: Event,
+    /// Hex of the hash of the previous entry, zeros for the first
+    pub prev: String,
+    /// Hex of the hash of this entry
+    pub hash: String,
+    /// Hex of the actor's signature of `hash`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub signature: Option<String>,
+}
+
+/// The part of an entry its hash covers, besides the previous hash
+#[derive(Serialize)]
+struct Body<'a> {
+    seq: u64,
+    time: u64,
+    actor: &'a str,
+    session: u64,
+    event: &'a Event,
+}
+
+impl Entry {
+    /// The hash of this entry's contents chained to `prev`
+    fn digest(&self, prev: &[u8; 32]) -> io::Result<[u8; 32]> {
+        let body = Body {
+            seq: self.seq,
+            time: self.time,
+            actor: &self.actor,
+            session: self.session,
+            event: &self.event,
+        };
+        let mut hasher = Sha256::new();
+        hasher.update(prev);
+        hasher.update(serde_json::to_vec(&body)?);
+
+        Ok(hasher.finalize().into())
+    }
+}
+
+/// Appends the events of one actor to a sink
+pub struct AuditLog {
+    actor: String,
+    sink: Box<dyn Write + Send>,
+    key: Option<SigningKey>,
+    seq: u64,
+    prev: [u8; 32],
+}
+
+impl AuditLog {
+    /// A new log of `actor` written to `sink`
+    pub fn new<W: Write + Send + 'static>(actor: &str, sink: W) -> AuditLog {
+        AuditLog {
+            actor: actor.to_string(),
+            sink: Box::new(sink),
+            key: None,
+            seq: 0,
+            prev: [0; 32],
+        }
+    }
+
+    /// The log of `actor` in the file at `path`, continuing the chain of the entries already
+    /// there
+    pub fn open(actor: &str, path: &Path) -> io::Result<AuditLog> {
+        let entries = match File::open(path) {
+            Ok(file) => read(BufReader::new(file))?,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => vec![],
+            Err(err) => return Err(err),
+        };
+        let file = OpenOptions::new().create(true).append(true).open(path)?;
+        let log = AuditLog::new(actor, file);
+
+        match entries.last() {
+            Some(last) => log.after(last),
+            None => Ok(log),
+        }
+    }
+
+    /// Sign every entry with `key`
+    pub fn signed(mut self, key: SigningKey) -> AuditLog {
+        self.key = Some(key);
+        self
+    }
+
+    /// Continue the chain after `last`
+    pub fn after(mut self, last: &Entry) -> io::Result<AuditLog> {
+        self.seq = last.seq + 1;
+        self.prev = decode_hash(&last.hash)?;
+        Ok(self)
+    }
+
+    /// Log `event` of session `session`
+    pub fn append(&mut self, session: u64, event: Event) -> io::Result<Entry> {
+        let time = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |elapsed| elapsed.as_secs());
+        let mut entry = Entry {
+            seq: self.seq,
+            time,
+            actor: self.actor.clone(),
+            session,
+            event,
+            prev: hex::encode(self.prev),
+            hash: String::new(),
+            signature: None,
+        };
+        let hash = entry.digest(&self.prev)?;
+        entry.hash = hex::encode(hash);
+        entry.signature = self
+            .key
+            .as_ref()
+            .map(|key| hex::encode(key.sign(&hash).to_bytes()));
+
+        // one write of the whole line, so a reader never sees half an entry
+        let mut line = serde_json::to_vec(&entry)?;
+        line.push(b'\n');
+        self.sink.write_all(&line)?;
+        self.sink.flush()?;
+        self.seq += 1;
+        self.prev = hash;
+
+        Ok(entry)
+    }
+}
+
+/// A hash in hex as bytes
+fn decode_hash(hash: &str) -> io::Result<[u8; 32]> {
+    let mut bytes = [0; 32];
+    hex::decode_to_slice(hash, &mut bytes)
+        .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))?;
+
+    Ok(bytes)
+}
+
+/// The entries of a log written as JSON lines
+///
+/// A last line without its newline is an entry still being appended and is skipped
+pub fn read<R: BufRead>(mut reader: R) -> io::Result<Vec<Entry>> {
+    let mut entries = vec![];
+    let mut line = String::new();
+    while reader.read_line(&mut line)? > 0 {
+        if !line.ends_with('\n') {
+            break;
+        }
+        if !line.trim().is_empty() {
+            entries.push(serde_json::from_str(&line)?);
+        }
+        line.clear();
+    }
+
+    Ok(entries)
+}
+
+/// Write `entries` as one JSON array, for a reviewer
+pub fn export<W: Write>(entries: &[Entry], writer: W) -> io::Result<()> {
+    Ok(serde_json::to_writer_pretty(writer, entries)?)
+}
+
+/// Check that `entries` form an unbroken chain from the first, each signed by `key` if given
+///
+/// Fails naming the first entry that was tampered with
+pub fn verify(entries: &[Entry], key: Option<&VerifyingKey>) -> io::Result<()> {
+    let broken = |i: usize, why: &str| {
+        io::Error::new(io::ErrorKind::InvalidData, format!("entry {}: {}", i, why))
+    };
+
+    let mut prev = [0; 32];
+    for (i, entry) in entries.iter().enumerate() {
+        if entry.seq != i as u64 {
+            return Err(broken(i, "out of sequence"));
+        }
+        if decode_hash(&entry.prev)? != prev {
+            return Err(broken(i, "does not follow the previous entry"));
+        }
+        let hash = entry.digest(&prev)?;
+        if decode_hash(&entry.hash)? != hash {
+            return Err(broken(i, "contents do not match its hash"));
+        }
+        if let Some(key) = key {
+            let signature = entry
+                .signature
+                .as_deref()
+                .and_then(|signature| hex::decode(signature).ok())
+                .and_then(|bytes| Signature::from_slice(&bytes).ok());
+            match signature {
+                Some(signature) if key.verify(&hash, &signature).is_ok() => {}
+                _ => return Err(broken(i, "bad signature")),
+            }
+        }
+        prev = hash;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::{self, Write};
+    use std::sync::{Arc, Mutex};
+
+    use ed25519_dalek::SigningKey;
+
+    use super::{read, verify, AuditLog, Event};
+
+    /// A sink the test reads back
+    #[derive(Clone, Default)]
+    struct Shared(Arc<Mutex<Vec<u8>>>);
+
+    impl Write for Shared {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.0.lock().unwrap().write(buf)
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn tampering_breaks_the_chain() {
+        let key = SigningKey::from_bytes(&[7; 32]);
+        let sink = Shared::default();
+        let mut log = AuditLog::new("player-1", sink.clone()).signed(key.clone());
+        log.append(
+            1,
+            Event::Verified {
+                from: 0,
+                valid: true,
+            },
+        )
+        .unwrap();
+        log.append(1, Event::Complained { accuser: 2 }).unwrap();
+        let last = log
+            .append(
+                1,
+                Event::Reconstructed {
+                    contributors: vec![1, 2],
+                    ok: true,
+                },
+            )
+            .unwrap();
+
+        let entries = read(&sink.0.lock().unwrap()[..]).unwrap();
+        assert_eq!(3, entries.len());
+        assert_eq!(last, entries[2]);
+        verify(&entries, Some(&key.verifying_key())).unwrap();
+
+        let other = SigningKey::from_bytes(&[8; 32]).verifying_key();
+        assert!(verify(&entries, Some(&other)).is_err());
+
+        let mut edited = entries.clone();
+        edited[1].event = Event::Complained { accuser: 3 };
+        assert!(verify(&edited, None).is_err());
+        let mut dropped = entries.clone();
+        dropped.remove(1);
+        assert!(verify(&dropped, None).is_err());
+
+        // a log continued after its last entry stays one chain
+        let mut log = AuditLog::new("player-1", sink.clone())
+            .after(&last)
+            .unwrap();
+        log.append(2, Event::Disqualified).unwrap();
+        verify(&read(&sink.0.lock().unwrap()[..]).unwrap(), None).unwrap();
+    }
+
+    #[test]
+    fn read_skips_an_entry_being_written() {
+        let sink = Shared::default();
+        let mut log = AuditLog::new("player-1", sink.clone());
+        log.append(1, Event::Disqualified).unwrap();
+        log.append(2, Event::Disqualified).unwrap();
+
+        // the second entry cut short, as a reader may see it while it is appended
+        let mut bytes = sink.0.lock().unwrap().clone();
+        bytes.truncate(bytes.len() - 10);
+        let entries = read(&bytes[..]).unwrap();
+        assert_eq!(1, entries.len());
+        verify(&entries, None).unwrap();
+    }
+}
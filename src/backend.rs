@@ -0,0 +1,134 @@
+//! Where a player's share is kept and what is done with it, so a share held by an HSM or
+//! PKCS#11 token is used without ever being read into this process
+//!
+//! `InMemory` keeps the share in memory as players otherwise do. A token usually offers
+//! exponentiation only, through Diffie–Hellman key derivation, see `pkcs11`: enough to check it
+//! holds the share that was dealt, while signing needs `respond` as well. A `Player` keeps the
+//! share of a session behind one once sent `RPC::UseBackend`, and signs through it.
+
+use std::fmt;
+use std::io;
+use std::sync::Arc;
+
+use num_bigint::BigUint;
+
+use crate::rpc::{PublicParams, Share};
+use crate::secret;
+use crate::vss;
+
+/// A share x_i at index i, and the operations done with it in place of reading it
+pub trait ShareBackend: Send + Sync {
+    /// The index i of the share
+    fn index(&self) -> &BigUint;
+
+    /// base^x_i mod p
+    fn exponentiate(&self, base: &BigUint, p: &BigUint) -> io::Result<BigUint>;
+
+    /// k + c * x_i mod q, a signer's response to a challenge c weighted by its Lagrange
+    /// coefficient for its nonce k. Unsupported unless a backend can compute it
+    fn respond(&self, nonce: &BigUint, c: &BigUint, q: &BigUint) -> io::Result<BigUint> {
+        let _ = (nonce, c, q);
+
+        Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            "share backend cannot answer a signing challenge",
+        ))
+    }
+}
+
+impl fmt::Debug for dyn ShareBackend {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ShareBackend")
+            .field("index", self.index())
+            .finish_non_exhaustive()
+    }
+}
+
+/// A backend shared, as a Player shares one between its session and its signings
+impl<B: ShareBackend + ?Sized> ShareBackend for Arc<B> {
+    fn index(&self) -> &BigUint {
+        (**self).index()
+    }
+
+    fn exponentiate(&self, base: &BigUint, p: &BigUint) -> io::Result<BigUint> {
+        (**self).exponentiate(base, p)
+    }
+
+    fn respond(&self, nonce: &BigUint, c: &BigUint, q: &BigUint) -> io::Result<BigUint> {
+        (**self).respond(nonce, c, q)
+    }
+}
+
+/// A share kept in memory
+pub struct InMemory(Share);
+
+impl InMemory {
+    pub fn new(share: Share) -> InMemory {
+        InMemory(share)
+    }
+}
+
+impl ShareBackend for InMemory {
+    fn index(&self) -> &BigUint {
+        self.0.index()
+    }
+
+    fn exponentiate(&self, base: &BigUint, p: &BigUint) -> io::Result<BigUint> {
+        Ok(base.modpow(self.0.value(), p))
+    }
+
+    fn respond(&self, nonce: &BigUint, c: &BigUint, q: &BigUint) -> io::Result<BigUint> {
+        Ok((nonce + c * self.0.value()) % q)
+    }
+}
+
+/// Whether `backend` holds a share of the dealing of `params`: g^x_i is the commitment at its
+/// index
+pub fn verify(backend: &dyn ShareBackend, params: &PublicParams) -> io::Result<bool> {
+    let g_x = backend.exponentiate(&params.g, &params.p)?;
+    let expected = vss::commitment_at(backend.index(), &params.commitments, &params.p);
+
+    Ok(secret::ct_eq(&g_x, &expected))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io;
+
+    use num_bigint::BigUint;
+
+    use crate::dealer::Dealer;
+    use crate::rpc::Share;
+
+    use super::{verify, InMemory, ShareBackend};
+
+    /// Exponentiates like a token and refuses to sign
+    struct Token(InMemory);
+
+    impl ShareBackend for Token {
+        fn index(&self) -> &BigUint {
+            self.0.index()
+        }
+
+        fn exponentiate(&self, base: &BigUint, p: &BigUint) -> io::Result<BigUint> {
+            self.0.exponentiate(base, p)
+        }
+    }
+
+    #[test]
+    fn backends_verify_without_reading_the_share() {
+        let dealer = Dealer::new(3, 2, 1234).unwrap();
+        let params = dealer.params();
+        let share = &dealer.shares[1];
+        assert!(verify(&InMemory::new(share.clone()), &params).unwrap());
+        let wrong = Share::new(share.index().clone(), share.value() + 1_u32);
+        assert!(!verify(&InMemory::new(wrong), &params).unwrap());
+
+        let token = Token(InMemory::new(share.clone()));
+        assert!(verify(&token, &params).unwrap());
+        let err = token
+            .respond(&1_u32.into(), &1_u32.into(), &params.q)
+            .unwrap_err();
+        assert_eq!(io::ErrorKind::Unsupported, err.kind());
+    }
+}
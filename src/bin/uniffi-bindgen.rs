@@ -0,0 +1,6 @@
+//! Generates the Kotlin and Swift bindings of the `uniffi` feature from the built library:
+//! `cargo run --features uniffi --bin uniffi-bindgen generate --library <lib> --language kotlin`
+
+fn main() {
+    uniffi::uniffi_bindgen_main()
+}
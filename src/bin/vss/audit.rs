@@ -0,0 +1,77 @@
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use rust_vss::ceremony::{self, Acknowledgment};
+use rust_vss::format::Transcript;
+use rust_vss::Signed;
+use serde::de::DeserializeOwned;
+use serde_json::json;
+
+use crate::output::{failed, Failure, Format};
+
+#[derive(clap::Args)]
+pub struct Args {
+    /// JSON object from every participant's id, 0 for the dealer, to the hex of its ed25519
+    /// public key
+    #[arg(long)]
+    keys: PathBuf,
+    /// The dealing's transcript as signed by the dealer
+    transcript: PathBuf,
+    /// The players' signed acknowledgments of the dealing
+    #[arg(required = true)]
+    acks: Vec<PathBuf>,
+}
+
+/// Checks a ceremony from its signed transcript and acknowledgments and reports every check,
+/// failing unless all of them passed
+pub fn run(args: Args, output: Format) -> io::Result<()> {
+    let keys = ceremony::load_keys(&args.keys)?;
+    let transcript: Signed<Transcript> = load(&args.transcript)?;
+    let acks = args
+        .acks
+        .iter()
+        .map(|path| load::<Signed<Acknowledgment>>(path))
+        .collect::<io::Result<Vec<_>>>()?;
+
+    let report = ceremony::audit(&transcript, &acks, &keys);
+    let passed = report.passed();
+    output.print(json!({ "passed": passed, "report": report }), || {
+        let mut lines: Vec<String> = report
+            .findings
+            .iter()
+            .map(|finding| {
+                let verdict = if finding.passed { "pass" } else { "FAIL" };
+                format!("{}  {}: {}", verdict, finding.subject, finding.detail)
+            })
+            .collect();
+        lines.push(format!(
+            "session {} dealing {}: {}",
+            report.session,
+            report.dealing,
+            if passed { "passed" } else { "failed" }
+        ));
+
+        lines.join("\n")
+    });
+
+    if !passed {
+        let failures = report.findings.iter().filter(|f| !f.passed).count();
+        return Err(failed(
+            Failure::Verification,
+            format!("{} checks of the ceremony failed", failures),
+        ));
+    }
+
+    Ok(())
+}
+
+/// Read the signed document at `path`, naming it in any error
+fn load<T: DeserializeOwned>(path: &Path) -> io::Result<T> {
+    serde_json::from_slice(&fs::read(path)?).map_err(|err| {
+        io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("{}: {}", path.display(), err),
+        )
+    })
+}
@@ -0,0 +1,116 @@
+use std::io;
+use std::time::{Duration, Instant};
+
+use num_bigint::BigUint;
+use rust_vss::dealer::Dealer;
+use rust_vss::secret::Secret;
+use rust_vss::vss;
+use rust_vss::ShareInfo;
+use serde_json::json;
+
+use crate::output::Format;
+
+#[derive(clap::Args)]
+pub struct Args {
+    /// Thresholds to measure, comma separated
+    #[arg(short, value_delimiter = ',', default_values_t = [2, 3, 5])]
+    t: Vec<usize>,
+    /// Numbers of players to measure, comma separated
+    #[arg(short, value_delimiter = ',', default_values_t = [5, 10])]
+    n: Vec<usize>,
+    /// Bits of q to measure, comma separated
+    #[arg(long, value_delimiter = ',', default_values_t = [32, 64])]
+    bits: Vec<usize>,
+    /// Dealings measured per cell, averaged
+    #[arg(long, default_value_t = 5)]
+    rounds: u32,
+}
+
+/// Mean time of each step of a dealing at one threshold, number of players and size of q
+struct Timings {
+    deal: Duration,
+    verify: Duration,
+    reconstruct: Duration,
+}
+
+/// Times dealing, verifying and reconstructing over every (t, n, bits) and prints a table
+pub fn run(args: Args, output: Format) -> io::Result<()> {
+    if args.rounds == 0 {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "at least one round is needed",
+        ));
+    }
+
+    let mut rows = vec![];
+    for &bits in &args.bits {
+        for &n in &args.n {
+            for &t in args.t.iter().filter(|t| **t > 0 && **t <= n) {
+                rows.push((t, n, bits, measure(t, n, bits, args.rounds)?));
+            }
+        }
+    }
+
+    let micros = |d: Duration| d.as_secs_f64() * 1e6;
+    let json = rows
+        .iter()
+        .map(|(t, n, bits, timings)| {
+            json!({
+                "t": t,
+                "n": n,
+                "bits": bits,
+                "deal_us": micros(timings.deal),
+                "verify_us": micros(timings.verify),
+                "reconstruct_us": micros(timings.reconstruct),
+            })
+        })
+        .collect::<Vec<_>>();
+    output.print(json!({ "rows": json }), || {
+        let mut lines = vec![format!(
+            "{:>4} {:>4} {:>5} {:>12} {:>12} {:>12}",
+            "t", "n", "bits", "deal", "verify", "reconstruct"
+        )];
+        lines.extend(rows.iter().map(|(t, n, bits, timings)| {
+            format!(
+                "{:>4} {:>4} {:>5} {:>12.3?} {:>12.3?} {:>12.3?}",
+                t, n, bits, timings.deal, timings.verify, timings.reconstruct
+            )
+        }));
+        lines.push("deal includes finding the group, verify is per share".to_string());
+
+        lines.join("\n")
+    });
+
+    Ok(())
+}
+
+fn measure(t: usize, n: usize, bits: usize, rounds: u32) -> io::Result<Timings> {
+    let mut timings = Timings {
+        deal: Duration::ZERO,
+        verify: Duration::ZERO,
+        reconstruct: Duration::ZERO,
+    };
+    for secret in 0..rounds {
+        let start = Instant::now();
+        let dealer = Dealer::with_bits(n, t, Secret::new(BigUint::from(secret)), bits)?;
+        timings.deal += start.elapsed();
+
+        let params = dealer.params();
+        let start = Instant::now();
+        for share in &dealer.shares {
+            assert!(ShareInfo::with_params(share.clone(), params.clone()).verify());
+        }
+        timings.verify += start.elapsed() / n as u32;
+
+        let start = Instant::now();
+        let reconstructed = vss::reconstruct(&dealer.shares[..t], &dealer.q)?;
+        timings.reconstruct += start.elapsed();
+        assert_eq!(&BigUint::from(secret), reconstructed.expose());
+    }
+
+    Ok(Timings {
+        deal: timings.deal / rounds,
+        verify: timings.verify / rounds,
+        reconstruct: timings.reconstruct / rounds,
+    })
+}
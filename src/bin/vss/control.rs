@@ -0,0 +1,265 @@
+//! The control socket of a player node, one JSON request a line answered by one JSON line,
+//! so a node can be managed by systemd units and orchestration tooling
+//!
+//! The socket is a Unix socket, or on Windows a named pipe such as `\\.\pipe\vss-1` that
+//! only clients on the same host may open.
+//!
+//! ```text
+//! {"request": "status"}
+//! {"request": "reconstruct", "session": 42}
+//! {"request": "refresh", "session": 42}
+//! ```
+
+#[cfg(unix)]
+use std::fs;
+use std::io::{self, BufRead, BufReader, Read, Write};
+#[cfg(unix)]
+use std::os::unix::net::{UnixListener, UnixStream};
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::Sender;
+use std::thread;
+use std::time::Duration;
+
+use clap::Subcommand;
+use rust_vss::refresh::Renewal;
+use rust_vss::{Router, RPC};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+
+use crate::output::Format;
+
+/// How long a request waits on the player before it is answered with an error
+const ANSWER_TIMEOUT: Duration = Duration::from_secs(10);
+
+#[derive(Debug, Serialize, Deserialize, Subcommand)]
+#[serde(tag = "request", rename_all = "lowercase")]
+pub enum Request {
+    /// The sessions the node holds shares of and the peers it heard from recently
+    Status,
+    /// Reconstruct a session with the other players and answer its secret
+    Reconstruct { session: u64 },
+    /// Move the node's share of a session to the next epoch with the other players
+    Refresh { session: u64 },
+}
+
+#[derive(clap::Args)]
+pub struct Args {
+    /// Control socket or named pipe of the node, its --control
+    #[arg(long)]
+    socket: PathBuf,
+    #[command(subcommand)]
+    request: Request,
+}
+
+/// Send a request to a node's control socket and print its answer
+pub fn run(args: Args, output: Format) -> io::Result<()> {
+    let mut stream = connect(&args.socket)
+        .map_err(|err| io::Error::new(err.kind(), format!("{}: {}", args.socket.display(), err)))?;
+    writeln!(stream, "{}", serde_json::to_string(&args.request)?)?;
+    let mut line = String::new();
+    BufReader::new(stream).read_line(&mut line)?;
+    let answer: Value = serde_json::from_str(&line)?;
+
+    if let Some(error) = answer.get("error").and_then(Value::as_str) {
+        return Err(io::Error::other(error.to_string()));
+    }
+    output.print(answer.clone(), || {
+        match serde_json::to_string_pretty(&answer) {
+            Ok(text) => text,
+            Err(_) => answer.to_string(),
+        }
+    });
+
+    Ok(())
+}
+
+#[cfg(unix)]
+fn connect(path: &Path) -> io::Result<UnixStream> {
+    UnixStream::connect(path)
+}
+
+/// Answer requests on a socket at `path` with `player` until the process exits, replacing
+/// a socket left behind by an earlier run
+#[cfg(unix)]
+pub fn serve(path: &Path, player: Sender<RPC>) -> io::Result<()> {
+    if fs::symlink_metadata(path).is_ok() {
+        fs::remove_file(path)?;
+    }
+    let listener = UnixListener::bind(path)?;
+
+    thread::spawn(move || {
+        for stream in listener.incoming().flatten() {
+            let player = player.clone();
+            let writer = stream.try_clone();
+            thread::spawn(move || answer_all(stream, writer?, player));
+        }
+    });
+
+    Ok(())
+}
+
+#[cfg(windows)]
+fn connect(path: &Path) -> io::Result<std::fs::File> {
+    std::fs::OpenOptions::new()
+        .read(true)
+        .write(true)
+        .open(path)
+}
+
+/// Answer requests on a named pipe at `path` with `player` until the process exits, failing
+/// if another process serves the name
+#[cfg(windows)]
+pub fn serve(path: &Path, player: Sender<RPC>) -> io::Result<()> {
+    let mut pipe = pipe::create(path, true)?;
+    let path = path.to_path_buf();
+
+    thread::spawn(move || loop {
+        if let Err(err) = pipe::accept(&pipe) {
+            tracing::warn!(%err, "control pipe");
+            continue;
+        }
+        // the next client connects to a new instance of the pipe
+        let client = match pipe::create(&path, false) {
+            Ok(next) => std::mem::replace(&mut pipe, next),
+            Err(err) => {
+                tracing::warn!(%err, "control pipe");
+                return;
+            }
+        };
+        let player = player.clone();
+        let writer = client.try_clone();
+        thread::spawn(move || answer_all(client, writer?, player));
+    });
+
+    Ok(())
+}
+
+/// Server ends of named pipes, which std can open but not create
+#[cfg(windows)]
+mod pipe {
+    use std::fs::File;
+    use std::io;
+    use std::os::windows::ffi::OsStrExt;
+    use std::os::windows::io::{AsRawHandle, FromRawHandle};
+    use std::path::Path;
+    use std::ptr;
+
+    use windows_sys::Win32::Foundation::{ERROR_PIPE_CONNECTED, INVALID_HANDLE_VALUE};
+    use windows_sys::Win32::Storage::FileSystem::{
+        FILE_FLAG_FIRST_PIPE_INSTANCE, PIPE_ACCESS_DUPLEX,
+    };
+    use windows_sys::Win32::System::Pipes::{
+        ConnectNamedPipe, CreateNamedPipeW, PIPE_READMODE_BYTE, PIPE_REJECT_REMOTE_CLIENTS,
+        PIPE_TYPE_BYTE, PIPE_UNLIMITED_INSTANCES, PIPE_WAIT,
+    };
+
+    const BUFFER_SIZE: u32 = 4096;
+
+    /// A new instance of the pipe named `path`, the first of its name if `first`
+    pub fn create(path: &Path, first: bool) -> io::Result<File> {
+        let name: Vec<u16> = path.as_os_str().encode_wide().chain([0]).collect();
+        let mut open_mode = PIPE_ACCESS_DUPLEX;
+        if first {
+            open_mode |= FILE_FLAG_FIRST_PIPE_INSTANCE;
+        }
+        // SAFETY: name is NUL terminated and outlives the call, no security attributes are
+        // passed so the pipe gets the default ones
+        let handle = unsafe {
+            CreateNamedPipeW(
+                name.as_ptr(),
+                open_mode,
+                PIPE_TYPE_BYTE | PIPE_READMODE_BYTE | PIPE_WAIT | PIPE_REJECT_REMOTE_CLIENTS,
+                PIPE_UNLIMITED_INSTANCES,
+                BUFFER_SIZE,
+                BUFFER_SIZE,
+                0,
+                ptr::null(),
+            )
+        };
+        if handle == INVALID_HANDLE_VALUE {
+            return Err(io::Error::last_os_error());
+        }
+
+        // SAFETY: the handle was just created and nothing else owns it
+        Ok(unsafe { File::from_raw_handle(handle) })
+    }
+
+    /// Wait for a client to open `pipe`
+    pub fn accept(pipe: &File) -> io::Result<()> {
+        // SAFETY: the handle is a pipe opened for blocking, not overlapped, io
+        if unsafe { ConnectNamedPipe(pipe.as_raw_handle(), ptr::null_mut()) } != 0 {
+            return Ok(());
+        }
+        match io::Error::last_os_error() {
+            // the client opened the pipe before we waited for it
+            err if err.raw_os_error() == Some(ERROR_PIPE_CONNECTED as i32) => Ok(()),
+            err => Err(err),
+        }
+    }
+}
+
+fn answer_all<R: Read, W: Write>(stream: R, writer: W, player: Sender<RPC>) -> io::Result<()> {
+    let mut writer = writer;
+    for line in BufReader::new(stream).lines() {
+        let answer = match serde_json::from_str::<Request>(&line?) {
+            Ok(request) => answer(request, &player),
+            Err(err) => Err(err.to_string()),
+        };
+        let answer = answer.unwrap_or_else(|error| json!({ "error": error }));
+        writeln!(writer, "{}", answer)?;
+    }
+
+    Ok(())
+}
+
+fn answer(request: Request, player: &Sender<RPC>) -> Result<Value, String> {
+    let stopped = |_| "the player stopped".to_string();
+    let timed_out = |err| format!("no answer from the player: {}", err);
+
+    match request {
+        Request::Status => {
+            let mut router = Router::new();
+            player
+                .send(RPC::Status(router.request()))
+                .map_err(stopped)?;
+            let (_, status) = router.recv_timeout(ANSWER_TIMEOUT).map_err(timed_out)?;
+
+            Ok(json!({
+                "id": status.id,
+                "sessions": status
+                    .sessions
+                    .iter()
+                    .map(|(session, epoch)| json!({ "session": session, "epoch": epoch }))
+                    .collect::<Vec<_>>(),
+                "live_peers": status.live_peers,
+            }))
+        }
+        Request::Reconstruct { session } => {
+            let mut router = Router::new();
+            player
+                .send(RPC::Reconstruct(session, router.request()))
+                .map_err(stopped)?;
+            let (_, report) = router.recv_timeout(ANSWER_TIMEOUT).map_err(timed_out)?;
+
+            Ok(json!({
+                "session": session,
+                "secret": report.secret.expose().to_string(),
+                "contributors": report.contributors,
+                "verified": report.verified,
+            }))
+        }
+        Request::Refresh { session } => {
+            let mut router = Router::new();
+            player
+                .send(RPC::Refresh(session, Renewal::Refresh, router.request()))
+                .map_err(stopped)?;
+            let (_, report) = router.recv_timeout(ANSWER_TIMEOUT).map_err(timed_out)?;
+
+            Ok(json!({
+                "session": session,
+                "epoch": report.epoch,
+                "dealers": report.dealers,
+            }))
+        }
+    }
+}
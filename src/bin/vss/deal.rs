@@ -0,0 +1,222 @@
+use std::fs;
+use std::io;
+#[cfg(feature = "age")]
+use std::path::Path;
+use std::path::PathBuf;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use rust_vss::dealer::{Dealer, SAFE_P_BITS, SAFE_Q_BITS};
+use rust_vss::format::{self, Transcript};
+use rust_vss::{Metadata, ShareInfo};
+use serde_json::json;
+
+use crate::output::Format;
+use crate::secret;
+
+#[derive(clap::Args)]
+pub struct Args {
+    #[command(flatten)]
+    secret: secret::Input,
+    /// Number of shares
+    #[arg(short)]
+    n: usize,
+    /// Number of shares needed to reconstruct
+    #[arg(short)]
+    t: usize,
+    /// Bits of the prime q the polynomial is computed over, at least the default
+    #[arg(long, default_value_t = SAFE_Q_BITS)]
+    q_bits: usize,
+    /// Bits of the prime p the commitments are computed in, at least the default
+    #[arg(long, default_value_t = SAFE_P_BITS)]
+    p_bits: usize,
+    /// Directory to write share-<i>.json into
+    #[arg(long)]
+    out: PathBuf,
+    /// Encrypt every share file under its own passphrase, prompted for
+    #[arg(long)]
+    encrypt: bool,
+    /// age recipient of each share in index order, its file written as share-<i>.json.age
+    #[cfg(feature = "age")]
+    #[arg(long, conflicts_with_all = ["encrypt", "age_generate"])]
+    age_recipient: Vec<String>,
+    /// Encrypt every share file to a new age identity of its own, written to share-<i>.key
+    #[cfg(feature = "age")]
+    #[arg(long, conflicts_with = "encrypt")]
+    age_generate: bool,
+    /// Custodian of each share in index order, recorded in its share file
+    #[arg(long)]
+    custodian: Vec<String>,
+    /// What the secret is for, recorded in every share file
+    #[arg(long)]
+    purpose: Option<String>,
+    /// Id of the dealing recorded in every share file
+    #[arg(long)]
+    dealing_id: Option<String>,
+    /// KEY=VALUE label recorded in every share file
+    #[arg(long, value_parser = parse_label)]
+    label: Vec<(String, String)>,
+    /// Seconds from now after which the shares expire and players refuse to use them
+    #[arg(long)]
+    expires_in: Option<u64>,
+    /// Also write each share's QR code as share-<i>.png or .svg
+    #[cfg(feature = "qr")]
+    #[arg(long, value_enum)]
+    qr: Option<QrFormat>,
+}
+
+/// Image format of the QR codes written along with the share files
+#[cfg(feature = "qr")]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum QrFormat {
+    Png,
+    Svg,
+}
+
+/// A KEY=VALUE label
+fn parse_label(label: &str) -> Result<(String, String), String> {
+    match label.split_once('=') {
+        Some((key, value)) if !key.is_empty() => Ok((key.to_string(), value.to_string())),
+        _ => Err(format!("{:?} is not KEY=VALUE", label)),
+    }
+}
+
+/// Write `info` beside `path` as <path>.age, encrypted to `recipient` or with --age-generate
+/// to a new identity written to share-<i>.key, returning the files written if either applies
+#[cfg(feature = "age")]
+fn save_age(
+    args: &Args,
+    path: &Path,
+    recipient: Option<&age::x25519::Recipient>,
+    info: &ShareInfo,
+) -> io::Result<Option<(PathBuf, Option<PathBuf>)>> {
+    let age_path = path.with_extension("json.age");
+    if let Some(recipient) = recipient {
+        rust_vss::age::save_share(&age_path, info, &[recipient])?;
+
+        return Ok(Some((age_path, None)));
+    }
+    if !args.age_generate {
+        return Ok(None);
+    }
+
+    let (identity, bytes) = rust_vss::age::encrypt_to_new_identity(info)?;
+    fs::write(&age_path, bytes)?;
+    let key = path.with_file_name(format!("share-{}.key", info.share().index()));
+    rust_vss::age::write_identity(fs::File::create(&key)?, &identity)?;
+
+    Ok(Some((age_path, Some(key))))
+}
+
+pub fn run(args: Args, output: Format) -> io::Result<()> {
+    // anyone could take the discrete log of c_0 = g^secret in a smaller group
+    if args.q_bits < SAFE_Q_BITS || args.p_bits < SAFE_P_BITS {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!(
+                "a group of {} bit q and {} bit p gives the secret away, use at least {} and {}",
+                args.q_bits, args.p_bits, SAFE_Q_BITS, SAFE_P_BITS
+            ),
+        ));
+    }
+    if args.custodian.len() > args.n {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("{} custodians for {} shares", args.custodian.len(), args.n),
+        ));
+    }
+    #[cfg(feature = "age")]
+    let recipients = args
+        .age_recipient
+        .iter()
+        .map(|recipient| rust_vss::age::recipient(recipient))
+        .collect::<io::Result<Vec<_>>>()?;
+    #[cfg(feature = "age")]
+    if recipients.len() > args.n {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("{} age recipients for {} shares", recipients.len(), args.n),
+        ));
+    }
+    let secret = format::encode_secret(args.secret.read()?.expose());
+    let mut dealer = Dealer::with_sizes(args.n, args.t, secret, args.q_bits, args.p_bits)?;
+    fs::create_dir_all(&args.out)?;
+
+    let created = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|elapsed| elapsed.as_secs())
+        .ok();
+    dealer.not_after = created
+        .zip(args.expires_in)
+        .map(|(created, expires_in)| created.saturating_add(expires_in));
+    let (mut paths, mut shares) = (vec![], vec![]);
+    for (i, share) in dealer.shares.iter().enumerate() {
+        let metadata = Metadata {
+            custodian: args.custodian.get(i).cloned(),
+            purpose: args.purpose.clone(),
+            dealing: args.dealing_id.clone(),
+            created,
+            labels: args.label.iter().cloned().collect(),
+        };
+        let share_info = ShareInfo::with_params(share.clone(), dealer.params())
+            .with_metadata(metadata)
+            .with_validity(dealer.validity());
+        let mut path = args.out.join(format!("share-{}.json", share.index()));
+        #[cfg(feature = "age")]
+        let sealed = save_age(&args, &path, recipients.get(i), &share_info)?;
+        #[cfg(not(feature = "age"))]
+        let sealed: Option<(PathBuf, Option<PathBuf>)> = None;
+        if let Some((age_path, _)) = &sealed {
+            path = age_path.clone();
+        } else if args.encrypt {
+            let passphrase = crate::new_passphrase(&path)?;
+            format::save_encrypted_share(&path, &share_info, passphrase.as_bytes())?;
+        } else {
+            format::save_share(&path, &share_info)?;
+        }
+        let mut written = json!({ "index": share.index().to_string(), "path": path });
+        paths.push(path.clone());
+        if let Some((_, Some(key))) = sealed {
+            written["age_identity"] = json!(key);
+            paths.push(key);
+        }
+
+        #[cfg(feature = "qr")]
+        if let Some(qr) = args.qr {
+            let ext = match qr {
+                QrFormat::Png => "png",
+                QrFormat::Svg => "svg",
+            };
+            let path = path.with_extension(ext);
+            rust_vss::qr::save_share(&path, &share_info)?;
+            written["qr"] = json!(path);
+            paths.push(path);
+        }
+        shares.push(written);
+    }
+
+    let transcript = args.out.join(crate::TRANSCRIPT_FILE);
+    format::save_transcript(
+        &transcript,
+        &Transcript::new(dealer.session_id, dealer.n, &dealer.params()),
+    )?;
+    paths.push(transcript.clone());
+
+    let first = ShareInfo::with_params(dealer.shares[0].clone(), dealer.params());
+    let result = json!({
+        "session": dealer.session_id,
+        "dealing": format::fingerprint(&first),
+        "transcript": transcript,
+        "t": dealer.t,
+        "n": dealer.n,
+        "shares": shares,
+    });
+    output.print(result, || {
+        paths
+            .iter()
+            .map(|path| path.display().to_string())
+            .collect::<Vec<_>>()
+            .join("\n")
+    });
+
+    Ok(())
+}
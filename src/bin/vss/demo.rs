@@ -0,0 +1,102 @@
+use std::collections::HashMap;
+use std::io;
+use std::net::TcpListener;
+
+use num_bigint::BigUint;
+use rust_vss::{
+    coordinator::Coordinator, dealer::DEALER_ID, player::Player, secret::Secret,
+    transport::tcp::TcpTransport,
+};
+use serde_json::json;
+
+use crate::output::{failed, Failure, Format};
+
+/// How the demo's players reach each other
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum Network {
+    /// Channels between threads of this process
+    Threads,
+    /// TCP over loopback, a listener per player
+    Tcp,
+}
+
+#[derive(clap::Args)]
+pub struct Args {
+    /// Number of players
+    #[arg(short, default_value_t = 5)]
+    n: usize,
+    /// Number of players needed to reconstruct
+    #[arg(short, default_value_t = 3)]
+    t: usize,
+    /// Secret to deal
+    #[arg(long, default_value_t = 1234)]
+    secret: usize,
+    /// Bits of the prime the polynomial is computed over
+    #[arg(long, default_value_t = 32)]
+    bits: usize,
+    #[arg(long, value_enum, default_value_t = Network::Threads)]
+    transport: Network,
+}
+
+/// Deals a secret to n players with threshold t and prints what they reconstruct
+pub fn run(args: Args, output: Format) -> io::Result<()> {
+    let coordinator = match args.transport {
+        Network::Threads => Coordinator::spawn(args.n),
+        Network::Tcp => over_tcp(args.n)?,
+    };
+    let mut coordinator = coordinator.with_bits(args.bits);
+
+    let session_id = coordinator.deal(Secret::new(BigUint::from(args.secret)), args.t)?;
+    let report = coordinator
+        .reconstruct(session_id)
+        .map_err(|err| failed(Failure::Quorum, format!("could not reconstruct: {}", err)))?;
+    let rejected: Vec<_> = report
+        .rejected
+        .iter()
+        .map(|(id, reason)| json!({ "id": id, "reason": format!("{:?}", reason) }))
+        .collect();
+    output.print(
+        json!({
+            "secret": report.secret.expose().to_string(),
+            "contributors": report.contributors,
+            "rejected": rejected,
+            "verified": report.verified,
+        }),
+        || {
+            format!(
+                "Reconstructed secret! {} from {:?}, rejected {:?}",
+                report.secret.expose(),
+                report.contributors,
+                report.rejected
+            )
+        },
+    );
+
+    Ok(())
+}
+
+/// `n` players listening on loopback ports, dealt to over TCP
+fn over_tcp(n: usize) -> io::Result<Coordinator> {
+    let listeners = (1..=n)
+        .map(|_| TcpListener::bind("127.0.0.1:0"))
+        .collect::<io::Result<Vec<_>>>()?;
+    let addresses = (1..=n)
+        .zip(&listeners)
+        .map(|(id, listener)| Ok((id, listener.local_addr()?)))
+        .collect::<io::Result<HashMap<_, _>>>()?;
+
+    let senders = (1..=n)
+        .zip(listeners)
+        .map(|(id, listener)| {
+            let (sender, _) = Player::spawn(id, TcpTransport::new(id, addresses.clone()));
+            TcpTransport::listen(listener, sender.clone());
+
+            sender
+        })
+        .collect();
+
+    Ok(Coordinator::new(
+        TcpTransport::new(DEALER_ID, addresses),
+        senders,
+    ))
+}
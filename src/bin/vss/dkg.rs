@@ -0,0 +1,173 @@
+use std::collections::BTreeMap;
+use std::io;
+use std::net::{SocketAddr, TcpListener};
+use std::path::PathBuf;
+use std::sync::mpsc::{self, RecvTimeoutError};
+use std::time::{Duration, Instant};
+
+use num_bigint::BigUint;
+use rand::Rng;
+use rust_vss::config::Config;
+use rust_vss::dealer::DEALER_ID;
+use rust_vss::dkg::DkgConfig;
+use rust_vss::transport::Transport;
+use rust_vss::{Payload, WireMessage, NO_SESSION, RPC};
+use serde_json::json;
+
+use crate::output::{failed, Failure, Format};
+
+#[derive(clap::Args)]
+pub struct Args {
+    /// TOML config listing every player and the coordinator, see rust_vss::config::Config
+    #[arg(long)]
+    config: PathBuf,
+    /// Number of key shares needed to use the key, the config's threshold if not given
+    #[arg(short)]
+    t: Option<usize>,
+    /// Address to listen on for answers, the coordinator's address in the config if not given
+    #[arg(long)]
+    listen: Option<SocketAddr>,
+    /// Seconds to wait for every player to answer
+    #[arg(long, default_value_t = 60)]
+    timeout: u64,
+}
+
+/// How a player took the request to generate a key
+enum Outcome {
+    Generated(BigUint),
+    Failed,
+    Unreachable(io::Error),
+    NoAnswer,
+}
+
+/// Has every player node generate a key together, each writing its key share, and prints the
+/// group's public key
+pub fn run(args: Args, output: Format) -> io::Result<()> {
+    let config = Config::load(&args.config)?;
+    let listen = args
+        .listen
+        .or_else(|| config.coordinator.as_ref().map(|c| c.address))
+        .ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "no --listen and no coordinator in the config",
+            )
+        })?;
+    let mut players: Vec<usize> = config.addresses().into_keys().collect();
+    players.sort();
+    let t = match args.t.or(config.threshold) {
+        Some(t) if t > 0 && t <= players.len() => t,
+        _ => {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "the threshold must be between 1 and the number of players",
+            ))
+        }
+    };
+
+    let (inbox, rx) = mpsc::channel();
+    config.listen(TcpListener::bind(listen)?, inbox)?;
+    let transport = config.transport(DEALER_ID)?;
+
+    // never pick NO_SESSION, which marks messages outside of any dealing
+    let session = rand::thread_rng().gen_range(NO_SESSION + 1, u64::MAX);
+    let dkg = DkgConfig::with_bits(t, players.clone(), config.group.bits);
+    let mut outcomes = BTreeMap::new();
+    for &id in &players {
+        let msg = WireMessage::new(session, 0, Payload::DkgRequest(dkg.clone()));
+        let outcome = match transport.send(id, msg) {
+            Ok(()) => Outcome::NoAnswer,
+            Err(err) => Outcome::Unreachable(err),
+        };
+        outcomes.insert(id, outcome);
+    }
+
+    let deadline = Instant::now() + Duration::from_secs(args.timeout);
+    while outcomes
+        .values()
+        .any(|outcome| matches!(outcome, Outcome::NoAnswer))
+    {
+        let timeout = deadline.saturating_duration_since(Instant::now());
+        match rx.recv_timeout(timeout) {
+            Ok(RPC::Wire(from, msg)) if msg.session_id == session => {
+                if let (Payload::DkgResult { public_key }, Some(outcome)) =
+                    (msg.payload, outcomes.get_mut(&from))
+                {
+                    *outcome = public_key.map_or(Outcome::Failed, Outcome::Generated);
+                }
+            }
+            Ok(_) => {}
+            Err(RecvTimeoutError::Timeout | RecvTimeoutError::Disconnected) => break,
+        }
+    }
+
+    report(session, t, &outcomes, output)
+}
+
+/// Prints the key every player generated as `session`, failing unless all of them generated
+/// the same one
+fn report(
+    session: u64,
+    t: usize,
+    outcomes: &BTreeMap<usize, Outcome>,
+    output: Format,
+) -> io::Result<()> {
+    let (mut players, mut lines) = (vec![], vec![]);
+    for (id, outcome) in outcomes {
+        let error = match outcome {
+            Outcome::Generated(_) => None,
+            Outcome::Failed => Some("could not generate the key".to_string()),
+            Outcome::Unreachable(err) => Some(format!("unreachable: {}", err)),
+            Outcome::NoAnswer => Some("no answer".to_string()),
+        };
+        lines.push(match &error {
+            None => format!("player {}: generated", id),
+            Some(error) => format!("player {}: {}", id, error),
+        });
+        players.push(json!({ "id": id, "error": error }));
+    }
+
+    let mut keys: Vec<&BigUint> = outcomes
+        .values()
+        .filter_map(|outcome| match outcome {
+            Outcome::Generated(public_key) => Some(public_key),
+            _ => None,
+        })
+        .collect();
+    let generated = keys.len();
+    keys.sort();
+    keys.dedup();
+    let public_key = match keys[..] {
+        [public_key] => Some(public_key),
+        _ => None,
+    };
+    if let Some(public_key) = public_key {
+        lines.push(format!("public key {}", public_key));
+    }
+    let result = json!({
+        "session": session,
+        "t": t,
+        "public_key": public_key.map(BigUint::to_string),
+        "players": players,
+    });
+    output.print(result, || lines.join("\n"));
+
+    if keys.len() > 1 {
+        return Err(failed(
+            Failure::Verification,
+            "players generated different keys",
+        ));
+    }
+    if generated < outcomes.len() {
+        return Err(failed(
+            Failure::Quorum,
+            format!(
+                "{} of {} players did not generate the key",
+                outcomes.len() - generated,
+                outcomes.len()
+            ),
+        ));
+    }
+
+    Ok(())
+}
@@ -0,0 +1,78 @@
+use std::io;
+
+use rust_vss::format;
+use serde_json::json;
+
+use crate::output::Format;
+
+#[derive(clap::Args)]
+pub struct Args {
+    #[command(flatten)]
+    shares: crate::Shares,
+}
+
+/// Print what a share file says about itself and the dealing it comes from
+pub fn run(args: Args, output: Format) -> io::Result<()> {
+    let (mut results, mut blocks) = (vec![], vec![]);
+    for (path, share_info) in args.shares.load()? {
+        let index = share_info.share().index().to_string();
+        let (p_bits, q_bits) = (share_info.p().bits(), share_info.q().bits());
+        let dealing = format::fingerprint(&share_info);
+        let verifies = share_info.verify();
+        let metadata = share_info.metadata();
+        let validity = share_info.validity();
+        let current = validity.is_current();
+
+        let mut lines = vec![
+            path.display().to_string(),
+            format!("  index      {}", index),
+            format!("  threshold  {}", share_info.t()),
+            format!("  group      p {} bits, q {} bits", p_bits, q_bits),
+            format!("  dealing    {}", dealing),
+            format!("  verifies   {}", if verifies { "yes" } else { "NO" }),
+            format!("  epoch      {}", validity.epoch),
+        ];
+        let fields = [
+            ("custodian", metadata.custodian.clone()),
+            ("purpose", metadata.purpose.clone()),
+            ("dealing id", metadata.dealing.clone()),
+            (
+                "created",
+                metadata.created.map(|created| created.to_string()),
+            ),
+            (
+                "valid from",
+                validity.not_before.map(|time| time.to_string()),
+            ),
+            ("expires", validity.not_after.map(|time| time.to_string())),
+        ];
+        for (name, value) in fields {
+            if let Some(value) = value {
+                lines.push(format!("  {:<10} {}", name, value));
+            }
+        }
+        for (key, value) in &metadata.labels {
+            lines.push(format!("  label      {}={}", key, value));
+        }
+        if !current {
+            lines.push("  current    NO, outside its validity window".to_string());
+        }
+        blocks.push(lines.join("\n"));
+        results.push(json!({
+            "path": path,
+            "index": index,
+            "threshold": share_info.t(),
+            "p_bits": p_bits,
+            "q_bits": q_bits,
+            "dealing": dealing,
+            "verifies": verifies,
+            "metadata": metadata,
+            "validity": validity,
+            "current": current,
+        }));
+    }
+
+    output.print(json!({ "shares": results }), || blocks.join("\n"));
+
+    Ok(())
+}
@@ -0,0 +1,53 @@
+//! Diagnostics of the dealer, players and transports on stderr, apart from the results on stdout
+
+use std::io;
+
+use tracing_subscriber::EnvFilter;
+
+/// How diagnostics are written
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum LogFormat {
+    /// Lines meant for people, with the spans they happened in
+    Text,
+    /// One JSON object per event, with its spans
+    Json,
+}
+
+/// The filter for `verbose` uses of -v: warnings by default, then info, debug and trace of
+/// this crate. Other crates only ever log warnings
+fn directives(verbose: u8) -> String {
+    let level = match verbose {
+        0 => "warn",
+        1 => "info",
+        2 => "debug",
+        _ => "trace",
+    };
+
+    format!("warn,rust_vss={},vss={}", level, level)
+}
+
+/// Install the subscriber writing diagnostics to stderr, RUST_LOG overriding -v if set
+pub fn init(verbose: u8, format: LogFormat) {
+    let filter =
+        EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new(directives(verbose)));
+    let subscriber = tracing_subscriber::fmt()
+        .with_env_filter(filter)
+        .with_writer(io::stderr);
+
+    match format {
+        LogFormat::Text => subscriber.init(),
+        LogFormat::Json => subscriber.json().init(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::directives;
+
+    #[test]
+    fn verbosity_raises_the_crate_level_only() {
+        assert_eq!("warn,rust_vss=warn,vss=warn", directives(0));
+        assert_eq!("warn,rust_vss=debug,vss=debug", directives(2));
+        assert_eq!("warn,rust_vss=trace,vss=trace", directives(5));
+    }
+}
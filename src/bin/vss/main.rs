@@ -0,0 +1,239 @@
+//! `vss`: split a secret into verifiable shares and put it back together
+
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+
+use clap::{Parser, Subcommand};
+use rust_vss::format;
+use rust_vss::ShareInfo;
+
+use crate::logging::LogFormat;
+use crate::output::Format;
+
+mod audit;
+mod bench;
+#[cfg(any(unix, windows))]
+mod control;
+mod deal;
+mod demo;
+mod dkg;
+mod inspect;
+mod logging;
+mod node;
+mod output;
+mod reconstruct;
+mod recover_share;
+mod refresh;
+mod repl;
+mod secret;
+mod sign;
+mod slip39;
+mod ssss;
+mod vault;
+mod verify;
+mod verify_dealing;
+mod words;
+
+/// The file `vss deal` publishes the dealing's transcript in, next to its shares
+const TRANSCRIPT_FILE: &str = "transcript.json";
+
+#[derive(Parser)]
+#[command(name = "vss", about = "Feldman verifiable secret sharing")]
+struct Cli {
+    /// Print results and errors as text or as JSON, see the exit codes in the README
+    #[arg(long, global = true, value_enum, default_value_t = Format::Text)]
+    output: Format,
+    /// Log what the dealer, players and transports do on stderr, -vv and -vvv for more
+    #[arg(short, long, global = true, action = clap::ArgAction::Count)]
+    verbose: u8,
+    /// Write the log as text or as one JSON object per event
+    #[arg(long, global = true, value_enum, default_value_t = LogFormat::Text)]
+    log_format: LogFormat,
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Split a secret into n shares, any t of which reconstruct it
+    Deal(deal::Args),
+    /// Check shares against the commitments they carry
+    Verify(verify::Args),
+    /// Check shares against the commitments published in a dealing's transcript, offline
+    VerifyDealing(verify_dealing::Args),
+    /// Check a ceremony from the dealer's signed transcript and the players' signed
+    /// acknowledgments
+    Audit(audit::Args),
+    /// Show the index, threshold, group and dealing of share files and whether they verify
+    Inspect(inspect::Args),
+    /// Put a secret back together from share files
+    Reconstruct(reconstruct::Args),
+    /// Have t peers regenerate a lost share over the network and write it encrypted
+    RecoverShare(recover_share::Args),
+    /// Back a secret up as SLIP-0039 mnemonics and recover it from them
+    #[command(subcommand)]
+    Slip39(slip39::Command),
+    /// Bring shares of the ssss tool over and write shares it reads
+    #[command(subcommand)]
+    Ssss(ssss::Command),
+    /// Reconstruct and check Vault unseal keys and make look-alike ones for drills
+    #[command(subcommand)]
+    Vault(vault::Command),
+    /// Read a share out as words and write it back from them
+    #[command(subcommand)]
+    Words(words::Command),
+    /// Run one player, or the dealer, in this process over TCP
+    Node(node::Args),
+    /// Time dealing, verifying and reconstructing over a grid of thresholds, players and sizes
+    Bench(bench::Args),
+    /// Ask a player node for its status, or to reconstruct or refresh, over its control socket
+    #[cfg(any(unix, windows))]
+    Control(control::Args),
+    /// Have every player node generate a key together and print its public key
+    Dkg(dkg::Args),
+    /// Have t player nodes sign a message with their shares and check such signatures
+    #[command(subcommand)]
+    Sign(sign::Command),
+    /// Ask every player node to refresh a dealing and report which moved to a new epoch
+    Refresh(refresh::Args),
+    /// Deal, refresh and reconstruct one command at a time, with players on threads or nodes
+    Repl(repl::Args),
+    /// Deal a secret to players on threads or over TCP and have them reconstruct it
+    Demo(demo::Args),
+}
+
+fn main() -> ExitCode {
+    let cli = Cli::parse();
+    logging::init(cli.verbose, cli.log_format);
+
+    let format = cli.output;
+    let result = match cli.command {
+        Command::Deal(args) => deal::run(args, format),
+        Command::Verify(args) => verify::run(args, format),
+        Command::VerifyDealing(args) => verify_dealing::run(args, format),
+        Command::Audit(args) => audit::run(args, format),
+        Command::Inspect(args) => inspect::run(args, format),
+        Command::Reconstruct(args) => reconstruct::run(args, format),
+        Command::RecoverShare(args) => recover_share::run(args, format),
+        Command::Slip39(command) => slip39::run(command, format),
+        Command::Ssss(command) => ssss::run(command, format),
+        Command::Vault(command) => vault::run(command, format),
+        Command::Words(command) => words::run(command, format),
+        Command::Node(args) => node::run(args, format),
+        #[cfg(any(unix, windows))]
+        Command::Control(args) => control::run(args, format),
+        Command::Dkg(args) => dkg::run(args, format),
+        Command::Sign(command) => sign::run(command, format),
+        Command::Refresh(args) => refresh::run(args, format),
+        Command::Repl(args) => repl::run(args, format),
+        Command::Demo(args) => demo::run(args, format),
+        Command::Bench(args) => bench::run(args, format),
+    };
+
+    match result {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(err) => {
+            format.print_error(&err);
+            ExitCode::from(output::exit_code(&err))
+        }
+    }
+}
+
+/// Shares given on the command line
+#[derive(clap::Args)]
+struct Shares {
+    /// Share files, or directories of them
+    #[arg(required = true)]
+    shares: Vec<PathBuf>,
+    /// Read the shares from PNG images of their QR codes rather than share files
+    #[cfg(feature = "qr")]
+    #[arg(long)]
+    from_qr: bool,
+    /// Decrypt the shares, share-<i>.json.age in directories, with this age identity file
+    #[cfg(feature = "age")]
+    #[arg(long)]
+    age_identity: Option<PathBuf>,
+}
+
+impl Shares {
+    /// Every share given, with the file it was read from
+    fn load(&self) -> io::Result<Vec<(PathBuf, ShareInfo)>> {
+        #[cfg(feature = "qr")]
+        if self.from_qr {
+            return share_files(&self.shares, "png")?
+                .into_iter()
+                .map(|path| Ok((path.clone(), rust_vss::qr::load_share(&path)?)))
+                .collect();
+        }
+
+        #[cfg(feature = "age")]
+        if let Some(identity) = &self.age_identity {
+            let identities = rust_vss::age::load_identities(identity)?;
+            let identities = rust_vss::age::identities(&identities);
+            return share_files(&self.shares, "age")?
+                .into_iter()
+                .map(|path| Ok((path.clone(), rust_vss::age::load_share(&path, &identities)?)))
+                .collect();
+        }
+
+        share_files(&self.shares, "json")?
+            .into_iter()
+            .map(|path| Ok((path.clone(), load_share(&path)?)))
+            .collect()
+    }
+}
+
+/// Read the share file at `path`, asking for its passphrase if it is encrypted. With the
+/// keyring feature a path of keyring:<name> reads the credential store's entry of that name
+fn load_share(path: &Path) -> io::Result<ShareInfo> {
+    #[cfg(feature = "keyring")]
+    if let Some(name) = path.to_str().and_then(|path| path.strip_prefix("keyring:")) {
+        let entry = rust_vss::keyring::entry(name)?;
+        return rust_vss::keyring::load_share_with(&entry, || {
+            rpassword::prompt_password(format!("Passphrase for {}: ", path.display()))
+        });
+    }
+    format::load_share_with(path, || {
+        rpassword::prompt_password(format!("Passphrase for {}: ", path.display()))
+    })
+}
+
+/// Ask for a new passphrase for the share file at `path`, twice to catch typos
+fn new_passphrase(path: &Path) -> io::Result<String> {
+    let passphrase = rpassword::prompt_password(format!("Passphrase for {}: ", path.display()))?;
+    let again = rpassword::prompt_password("Again: ")?;
+    if passphrase != again {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "passphrases do not match",
+        ));
+    }
+
+    Ok(passphrase)
+}
+
+/// Every file in `paths`, a directory standing for the files in it with extension `ext` but
+/// the transcript
+fn share_files(paths: &[PathBuf], ext: &str) -> io::Result<Vec<PathBuf>> {
+    let mut files = vec![];
+    for path in paths {
+        if !path.is_dir() {
+            files.push(path.clone());
+            continue;
+        }
+
+        let mut entries = fs::read_dir(path)?
+            .map(|entry| entry.map(|entry| entry.path()))
+            .collect::<io::Result<Vec<PathBuf>>>()?;
+        entries.retain(|path| {
+            path.extension().is_some_and(|e| e == ext)
+                && path.file_name().is_some_and(|name| name != TRANSCRIPT_FILE)
+        });
+        entries.sort();
+        files.extend(entries);
+    }
+
+    Ok(files)
+}
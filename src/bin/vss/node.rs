@@ -0,0 +1,422 @@
+use std::fs;
+use std::io;
+use std::net::{SocketAddr, TcpListener};
+use std::path::PathBuf;
+use std::sync::mpsc::{self, Sender};
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
+
+use num_bigint::BigUint;
+use rust_vss::audit::AuditLog;
+use rust_vss::config::Config;
+use rust_vss::dealer::{Dealer, DEALER_ID};
+use rust_vss::dkg::DkgConfig;
+use rust_vss::format;
+use rust_vss::player::Player;
+use rust_vss::refresh::Renewal;
+use rust_vss::secret::Secret;
+use rust_vss::transport::tcp::TcpTransport;
+use rust_vss::transport::Transport;
+use rust_vss::{Payload, Router, ShareInfo, WireMessage, RPC};
+use serde_json::json;
+
+use crate::output::Format;
+
+/// How long a node waits after its share arrives for the other players to get theirs
+const SETTLE: Duration = Duration::from_secs(1);
+/// How long a node waits for its share to move when the coordinator asks for a refresh
+const REFRESH_TIMEOUT: Duration = Duration::from_secs(10);
+/// How long a node waits for a key to be generated when the coordinator asks for one
+const DKG_TIMEOUT: Duration = Duration::from_secs(30);
+/// How long a node coordinating a signature waits for the other signers when the coordinator
+/// asks for one
+const SIGN_TIMEOUT: Duration = Duration::from_secs(10);
+
+#[derive(clap::Args)]
+pub struct Args {
+    /// Id of this node, 0 for the dealer
+    #[arg(long)]
+    id: usize,
+    /// Address to listen on, the node's own address in the config if not given
+    #[arg(long)]
+    listen: Option<SocketAddr>,
+    /// TOML config listing every player, see rust_vss::config::Config
+    #[arg(long)]
+    config: PathBuf,
+    /// As the dealer, deal this secret to the players and exit
+    #[arg(long)]
+    deal: Option<usize>,
+    /// Number of players needed to reconstruct the dealt secret, the config's threshold if not
+    /// given
+    #[arg(short)]
+    t: Option<usize>,
+    /// Reconstruct the secret of every dealing this node gets a share of and print it
+    #[arg(long)]
+    reconstruct: bool,
+    /// Directory to write the key share of every key generated with `vss dkg` into, as
+    /// key-<session>.json
+    #[arg(long)]
+    keys: Option<PathBuf>,
+    /// Store the key share of every key generated with `vss dkg` in the OS credential store as
+    /// key-<session> rather than in a directory, read back as keyring:key-<session>
+    #[cfg(feature = "keyring")]
+    #[arg(long, conflicts_with = "keys")]
+    keyring: bool,
+    /// Answer status, reconstruct and refresh requests on a Unix socket at this path, or a
+    /// named pipe such as \\.\pipe\vss-1 on Windows, see `vss control`
+    #[cfg(any(unix, windows))]
+    #[arg(long)]
+    control: Option<PathBuf>,
+    /// Append the protocol events of this node to a hash-chained log at this path, see
+    /// rust_vss::audit
+    #[arg(long)]
+    audit_log: Option<PathBuf>,
+    /// Serve the player's Prometheus metrics over HTTP on this address
+    #[cfg(feature = "metrics")]
+    #[arg(long)]
+    metrics: Option<SocketAddr>,
+}
+
+/// Where a node writes the key shares it generates
+#[derive(Clone)]
+enum KeyStore {
+    Dir(PathBuf),
+    #[cfg(feature = "keyring")]
+    Keyring,
+}
+
+impl KeyStore {
+    /// Where `args` says to write key shares, if anywhere
+    fn of(args: &Args) -> Option<KeyStore> {
+        #[cfg(feature = "keyring")]
+        if args.keyring {
+            return Some(KeyStore::Keyring);
+        }
+
+        args.keys.clone().map(KeyStore::Dir)
+    }
+
+    /// Write the key share `info` of `session_id`, returning where it went
+    fn save(&self, session_id: u64, info: &ShareInfo) -> io::Result<String> {
+        let name = format!("key-{}", session_id);
+        match self {
+            KeyStore::Dir(dir) => {
+                let path = dir.join(format!("{}.json", name));
+                fs::create_dir_all(dir)?;
+                format::save_share(&path, info)?;
+                Ok(path.display().to_string())
+            }
+            #[cfg(feature = "keyring")]
+            KeyStore::Keyring => {
+                rust_vss::keyring::save_share(&rust_vss::keyring::entry(&name)?, info)?;
+                Ok(format!("keyring:{}", name))
+            }
+        }
+    }
+}
+
+/// Runs a player in this process until it is killed, or deals as the dealer
+pub fn run(args: Args, output: Format) -> io::Result<()> {
+    let config = Config::load(&args.config)?;
+    let addresses = config.addresses();
+
+    if args.id == DEALER_ID {
+        let (secret, t) = match (args.deal, args.t.or(config.threshold)) {
+            (Some(secret), Some(t)) => (secret, t),
+            _ => {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    "the dealer needs --deal and a threshold",
+                ))
+            }
+        };
+
+        let dealer = Dealer::with_bits(
+            addresses.len(),
+            t,
+            Secret::new(BigUint::from(secret)),
+            config.group.bits,
+        )?;
+        if let Some(path) = &args.audit_log {
+            dealer.audit(&mut AuditLog::open("dealer", path)?)?;
+        }
+        dealer.propagate(&config.transport(DEALER_ID)?);
+        output.print(
+            json!({ "event": "dealt", "session": dealer.session_id }),
+            || format!("dealt session {}", dealer.session_id),
+        );
+
+        return Ok(());
+    }
+
+    let listen = match args.listen.or_else(|| addresses.get(&args.id).copied()) {
+        Some(listen) => listen,
+        None => {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("no --listen and no address for player {}", args.id),
+            ))
+        }
+    };
+
+    let coordinator = match config.coordinator {
+        Some(_) => Some(Arc::new(config.coordinator_transport(args.id)?)),
+        None => None,
+    };
+
+    #[cfg(feature = "metrics")]
+    if let Some(address) = args.metrics {
+        rust_vss::metrics::serve(TcpListener::bind(address)?, rust_vss::metrics::install()?);
+    }
+    let (inbox, rx) = mpsc::channel();
+    config.listen(TcpListener::bind(listen)?, inbox)?;
+    let transport = config.transport(args.id)?;
+    let (player, handle) = match &args.audit_log {
+        Some(path) => {
+            let log = AuditLog::open(&format!("player-{}", args.id), path)?;
+            Player::with_audit(args.id, transport, log)
+        }
+        None => Player::spawn(args.id, transport),
+    };
+    #[cfg(any(unix, windows))]
+    if let Some(path) = &args.control {
+        crate::control::serve(path, player.clone())?;
+    }
+    output.print(
+        json!({ "event": "listening", "id": args.id, "address": listen }),
+        || format!("player {} listening on {}", args.id, listen),
+    );
+
+    for rpc in rx {
+        if let RPC::Wire(DEALER_ID, msg) = &rpc {
+            if args.reconstruct && matches!(msg.payload, Payload::RegShare(_)) {
+                reconstruct_later(player.clone(), msg.session_id, output);
+            }
+            // answer the coordinator once our share moved, rather than leaving it to the player
+            if let (Some(coordinator), Payload::RefreshRequest) = (&coordinator, &msg.payload) {
+                refresh_and_answer(player.clone(), msg.session_id, coordinator.clone(), output);
+                continue;
+            }
+            if let (Some(coordinator), Payload::DkgRequest(dkg)) = (&coordinator, &msg.payload) {
+                let keys = KeyStore::of(&args);
+                let (player, coordinator) = (player.clone(), coordinator.clone());
+                generate_and_answer(
+                    player,
+                    msg.session_id,
+                    dkg.clone(),
+                    keys,
+                    coordinator,
+                    output,
+                );
+                continue;
+            }
+            if let (Some(coordinator), Payload::SignRequest { .. }) = (&coordinator, &msg.payload) {
+                sign_and_answer(player.clone(), msg.clone(), coordinator.clone(), output);
+                continue;
+            }
+        }
+        if player.send(rpc).is_err() {
+            break;
+        }
+    }
+
+    handle
+        .join()
+        .map_err(|_| io::Error::other("player thread panicked"))
+}
+
+/// Refresh `session_id` and tell the coordinator the epoch our share moved to, if it did
+fn refresh_and_answer(
+    player: Sender<RPC>,
+    session_id: u64,
+    coordinator: Arc<TcpTransport>,
+    output: Format,
+) {
+    thread::spawn(move || {
+        let mut router = Router::new();
+        let epoch = match player.send(RPC::Refresh(session_id, Renewal::Refresh, router.request()))
+        {
+            Ok(()) => router
+                .recv_timeout(REFRESH_TIMEOUT)
+                .ok()
+                .map(|(_, report)| report.epoch),
+            Err(_) => None,
+        };
+        output.print(
+            json!({ "event": "refreshed", "session": session_id, "epoch": epoch }),
+            || match epoch {
+                Some(epoch) => format!("session {}: refreshed to epoch {}", session_id, epoch),
+                None => format!("session {}: could not refresh", session_id),
+            },
+        );
+
+        let result = Payload::RefreshResult { epoch };
+        let msg = WireMessage::new(session_id, epoch.unwrap_or_default(), result);
+        if let Err(err) = coordinator.send(DEALER_ID, msg) {
+            let error = format!("could not answer the coordinator: {}", err);
+            output.print(
+                json!({ "event": "error", "session": session_id, "message": error }),
+                || format!("session {}: {}", session_id, error),
+            );
+        }
+    });
+}
+
+/// Generate a key as `session_id`, write our key share into `keys` and tell the coordinator
+/// the key, if it was generated
+fn generate_and_answer(
+    player: Sender<RPC>,
+    session_id: u64,
+    config: DkgConfig,
+    keys: Option<KeyStore>,
+    coordinator: Arc<TcpTransport>,
+    output: Format,
+) {
+    thread::spawn(move || {
+        let mut router = Router::new();
+        let report = match player.send(RPC::Dkg(session_id, config, router.request())) {
+            Ok(()) => router
+                .recv_timeout(DKG_TIMEOUT)
+                .map(|(_, report)| report)
+                .map_err(|err| err.to_string()),
+            Err(err) => Err(err.to_string()),
+        };
+        let written = report.and_then(|report| match keys {
+            Some(keys) => keys
+                .save(session_id, &report.share_info)
+                .map(|path| (report, Some(path)))
+                .map_err(|err| format!("could not write the key share: {}", err)),
+            None => Ok((report, None)),
+        });
+
+        let public_key = written
+            .as_ref()
+            .ok()
+            .map(|(report, _)| report.public_key.clone());
+        match written {
+            Ok((report, path)) => output.print(
+                json!({
+                    "event": "generated",
+                    "session": session_id,
+                    "public_key": report.public_key.to_string(),
+                    "qualified": report.qualified,
+                    "path": path,
+                }),
+                || {
+                    format!(
+                        "session {}: generated key {}",
+                        session_id, report.public_key
+                    )
+                },
+            ),
+            Err(error) => output.print(
+                json!({ "event": "error", "session": session_id, "message": error }),
+                || format!("session {}: {}", session_id, error),
+            ),
+        }
+
+        let msg = WireMessage::new(session_id, 0, Payload::DkgResult { public_key });
+        if let Err(err) = coordinator.send(DEALER_ID, msg) {
+            let error = format!("could not answer the coordinator: {}", err);
+            output.print(
+                json!({ "event": "error", "session": session_id, "message": error }),
+                || format!("session {}: {}", session_id, error),
+            );
+        }
+    });
+}
+
+/// Coordinate the signature the coordinator asked for in `request` among its signers and send
+/// it back, if the signers combined one
+fn sign_and_answer(
+    player: Sender<RPC>,
+    request: WireMessage,
+    coordinator: Arc<TcpTransport>,
+    output: Format,
+) {
+    let session_id = request.session_id;
+    let (request, message, signers) = match request.payload {
+        Payload::SignRequest {
+            request,
+            message,
+            signers,
+        } => (request, message, signers),
+        _ => return,
+    };
+    thread::spawn(move || {
+        let mut router = Router::new();
+        let report = match player.send(RPC::Sign(session_id, message, signers, router.request())) {
+            Ok(()) => router
+                .recv_timeout(SIGN_TIMEOUT)
+                .map(|(_, report)| report)
+                .map_err(|err| format!("could not sign: {}", err)),
+            Err(err) => Err(format!("could not sign: {}", err)),
+        };
+        let report = match report {
+            Ok(report) => report,
+            Err(error) => {
+                return output.print(
+                    json!({ "event": "error", "session": session_id, "message": error }),
+                    || format!("session {}: {}", session_id, error),
+                )
+            }
+        };
+        output.print(
+            json!({ "event": "signed", "session": session_id, "signers": report.signers }),
+            || format!("session {}: signed with {:?}", session_id, report.signers),
+        );
+
+        let result = Payload::SignatureResult {
+            request,
+            signature: report.signature,
+        };
+        if let Err(err) = coordinator.send(DEALER_ID, WireMessage::new(session_id, 0, result)) {
+            let error = format!("could not answer the coordinator: {}", err);
+            output.print(
+                json!({ "event": "error", "session": session_id, "message": error }),
+                || format!("session {}: {}", session_id, error),
+            );
+        }
+    });
+}
+
+/// Reconstruct `session_id` once the other players had time to get their shares
+fn reconstruct_later(player: Sender<RPC>, session_id: u64, output: Format) {
+    thread::spawn(move || {
+        thread::sleep(SETTLE);
+
+        let mut router = Router::new();
+        if player
+            .send(RPC::Reconstruct(session_id, router.request()))
+            .is_err()
+        {
+            return;
+        }
+        match router.recv_timeout(Duration::from_secs(10)) {
+            Ok((_, report)) => output.print(
+                json!({
+                    "event": "reconstructed",
+                    "session": session_id,
+                    "secret": report.secret.expose().to_string(),
+                    "contributors": report.contributors,
+                }),
+                || {
+                    format!(
+                        "session {}: reconstructed {} from {:?}",
+                        session_id,
+                        report.secret.expose(),
+                        report.contributors
+                    )
+                },
+            ),
+            Err(err) => {
+                let error = format!("could not reconstruct: {}", err);
+                output.print(
+                    json!({ "event": "error", "session": session_id, "message": error }),
+                    || format!("session {}: {}", session_id, error),
+                );
+            }
+        }
+    });
+}
@@ -0,0 +1,136 @@
+//! How results and errors are printed, and the exit codes scripts can rely on
+
+use std::error::Error;
+use std::fmt;
+use std::io;
+
+use serde_json::{json, Value};
+
+/// Any other error: bad arguments or input, malformed files, a wrong passphrase
+pub const EXIT_ERROR: u8 = 1;
+// 2 is left to clap, which exits with it on usage errors
+/// A file, terminal or peer could not be read, written or reached
+pub const EXIT_IO: u8 = 3;
+/// A share does not verify against its commitments
+pub const EXIT_VERIFICATION: u8 = 4;
+/// Fewer shares or players than needed took part
+pub const EXIT_QUORUM: u8 = 5;
+
+/// How a command prints its results
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum Format {
+    /// Lines meant for people
+    Text,
+    /// One JSON document per result, errors included, on stdout
+    Json,
+}
+
+impl Format {
+    /// Print a result, as one line of `json` in JSON mode and as `text` otherwise
+    pub fn print<F: FnOnce() -> String>(self, json: Value, text: F) {
+        match self {
+            Format::Text => println!("{}", text()),
+            Format::Json => println!("{}", json),
+        }
+    }
+
+    /// Print `err` with its exit code, on stdout in JSON mode and stderr otherwise
+    pub fn print_error(self, err: &io::Error) {
+        match self {
+            Format::Text => eprintln!("error: {}", err),
+            Format::Json => println!(
+                "{}",
+                json!({ "error": { "code": exit_code(err), "message": err.to_string() } })
+            ),
+        }
+    }
+}
+
+/// Why a command failed when it is not for an error of IO or input
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Failure {
+    Verification,
+    Quorum,
+}
+
+#[derive(Debug)]
+struct Failed {
+    failure: Failure,
+    msg: String,
+}
+
+impl fmt::Display for Failed {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.msg)
+    }
+}
+
+impl Error for Failed {}
+
+/// An error failing the command with the exit code of `failure`
+pub fn failed<S: Into<String>>(failure: Failure, msg: S) -> io::Error {
+    let kind = match failure {
+        Failure::Verification => io::ErrorKind::InvalidData,
+        Failure::Quorum => io::ErrorKind::Other,
+    };
+
+    io::Error::new(
+        kind,
+        Failed {
+            failure,
+            msg: msg.into(),
+        },
+    )
+}
+
+/// The exit code a command failing with `err` exits with
+pub fn exit_code(err: &io::Error) -> u8 {
+    if let Some(failed) = err.get_ref().and_then(|err| err.downcast_ref::<Failed>()) {
+        return match failed.failure {
+            Failure::Verification => EXIT_VERIFICATION,
+            Failure::Quorum => EXIT_QUORUM,
+        };
+    }
+
+    match err.kind() {
+        io::ErrorKind::NotFound
+        | io::ErrorKind::PermissionDenied
+        | io::ErrorKind::ConnectionRefused
+        | io::ErrorKind::ConnectionReset
+        | io::ErrorKind::ConnectionAborted
+        | io::ErrorKind::NotConnected
+        | io::ErrorKind::AddrInUse
+        | io::ErrorKind::AddrNotAvailable
+        | io::ErrorKind::BrokenPipe
+        | io::ErrorKind::AlreadyExists
+        | io::ErrorKind::TimedOut
+        | io::ErrorKind::WriteZero
+        | io::ErrorKind::Interrupted
+        | io::ErrorKind::UnexpectedEof => EXIT_IO,
+        _ => EXIT_ERROR,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io;
+
+    use super::{exit_code, failed, Failure, EXIT_ERROR, EXIT_IO, EXIT_QUORUM, EXIT_VERIFICATION};
+
+    #[test]
+    fn exit_codes() {
+        let missing = io::Error::new(io::ErrorKind::NotFound, "share-1.json");
+        let malformed = io::Error::new(io::ErrorKind::InvalidData, "not a share file");
+
+        assert_eq!(EXIT_IO, exit_code(&missing));
+        assert_eq!(EXIT_ERROR, exit_code(&malformed));
+        assert_eq!(
+            EXIT_VERIFICATION,
+            exit_code(&failed(Failure::Verification, "share 1 does not verify"))
+        );
+        assert_eq!(
+            EXIT_QUORUM,
+            exit_code(&failed(Failure::Quorum, "2 shares, 3 needed"))
+        );
+    }
+}
@@ -0,0 +1,45 @@
+use std::collections::HashSet;
+use std::io;
+
+use rust_vss::format;
+
+use crate::output::{failed, Failure, Format};
+use crate::secret;
+
+#[derive(clap::Args)]
+pub struct Args {
+    #[command(flatten)]
+    shares: crate::Shares,
+    #[command(flatten)]
+    destination: secret::Output,
+}
+
+/// Writes out the secret of the shares, given at least t of one dealing
+pub fn run(args: Args, output: Format) -> io::Result<()> {
+    let shares = args.shares.load()?;
+
+    // tell the failures scripts act on apart, reconstruct_shares checks the rest
+    if let Some((path, _)) = shares.iter().find(|(_, info)| !info.verify()) {
+        return Err(failed(
+            Failure::Verification,
+            format!("{}: share does not verify", path.display()),
+        ));
+    }
+    if let Some((_, first)) = shares.first() {
+        let indices: HashSet<_> = shares
+            .iter()
+            .map(|(_, info)| info.share().index())
+            .collect();
+        if indices.len() < first.t() {
+            return Err(failed(
+                Failure::Quorum,
+                format!("{} shares, {} needed", indices.len(), first.t()),
+            ));
+        }
+    }
+
+    let secret = format::reconstruct_shares(&shares)?;
+
+    args.destination
+        .write(format::decode_secret(&secret)?.expose(), output)
+}
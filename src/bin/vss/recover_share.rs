@@ -0,0 +1,142 @@
+use std::collections::BTreeMap;
+use std::io;
+use std::net::{SocketAddr, TcpListener};
+use std::path::PathBuf;
+use std::sync::mpsc::{self, RecvTimeoutError};
+use std::time::{Duration, Instant};
+
+use rust_vss::config::Config;
+use rust_vss::format;
+use rust_vss::recovery;
+use rust_vss::transport::Transport;
+use rust_vss::{Payload, WireMessage, RPC};
+use serde_json::json;
+
+use crate::output::{failed, Failure, Format};
+
+#[derive(clap::Args)]
+pub struct Args {
+    /// TOML config listing every player, see rust_vss::config::Config
+    #[arg(long)]
+    config: PathBuf,
+    /// Session of the dealing the share was lost from, as printed when it was dealt
+    #[arg(long)]
+    session: u64,
+    /// Id of the player whose share was lost
+    #[arg(long)]
+    id: usize,
+    /// Ids of the players to regenerate it, at least the threshold of them
+    #[arg(long, required = true, value_delimiter = ',')]
+    helpers: Vec<usize>,
+    /// Address to listen on for the helpers' answers, the player's address in the config if
+    /// not given
+    #[arg(long)]
+    listen: Option<SocketAddr>,
+    /// Seconds to wait for every helper to answer
+    #[arg(long, default_value_t = 30)]
+    timeout: u64,
+    /// File to write the recovered share to, encrypted under a passphrase prompted for
+    #[arg(long)]
+    out: PathBuf,
+}
+
+/// Stands in for a player that lost its share and has the helpers regenerate it, without any
+/// of them learning it
+pub fn run(args: Args, output: Format) -> io::Result<()> {
+    let config = Config::load(&args.config)?;
+    let listen = match args.listen {
+        Some(listen) => listen,
+        None => *config.addresses().get(&args.id).ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("no --listen and no player {} in the config", args.id),
+            )
+        })?,
+    };
+    if args.helpers.contains(&args.id) {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "the player that lost its share cannot help recover it",
+        ));
+    }
+    if let Some(helper) = args
+        .helpers
+        .iter()
+        .find(|id| !config.addresses().contains_key(id))
+    {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("no player {} in the config", helper),
+        ));
+    }
+
+    let (inbox, rx) = mpsc::channel();
+    config.listen(TcpListener::bind(listen)?, inbox)?;
+    let transport = config.transport(args.id)?;
+
+    for &helper in &args.helpers {
+        let payload = Payload::RecoveryRequest {
+            helpers: args.helpers.clone(),
+        };
+        transport.send(helper, WireMessage::new(args.session, 0, payload))?;
+        output.print(json!({ "event": "requested", "helper": helper }), || {
+            format!("asked player {} to help", helper)
+        });
+    }
+
+    let mut sums = BTreeMap::new();
+    let deadline = Instant::now() + Duration::from_secs(args.timeout);
+    while sums.len() < args.helpers.len() {
+        let timeout = deadline.saturating_duration_since(Instant::now());
+        match rx.recv_timeout(timeout) {
+            Ok(RPC::Wire(from, msg)) if msg.session_id == args.session => {
+                if let Payload::RecoveryShare(info) = msg.payload {
+                    if args.helpers.contains(&from) && sums.insert(from, info).is_none() {
+                        output.print(json!({ "event": "answered", "helper": from }), || {
+                            format!(
+                                "player {} answered, {} of {}",
+                                from,
+                                sums.len(),
+                                args.helpers.len()
+                            )
+                        });
+                    }
+                }
+            }
+            Ok(_) => {}
+            Err(RecvTimeoutError::Timeout | RecvTimeoutError::Disconnected) => break,
+        }
+    }
+
+    let missing: Vec<usize> = args
+        .helpers
+        .iter()
+        .copied()
+        .filter(|helper| !sums.contains_key(helper))
+        .collect();
+    if !missing.is_empty() {
+        return Err(failed(
+            Failure::Quorum,
+            format!("players {:?} did not answer", missing),
+        ));
+    }
+
+    let sums: Vec<_> = sums.into_iter().collect();
+    let info = recovery::combine(args.id, &sums)
+        .map_err(|err| failed(Failure::Verification, err.to_string()))?;
+    let passphrase = crate::new_passphrase(&args.out)?;
+    format::save_encrypted_share(&args.out, &info, passphrase.as_bytes())?;
+
+    let result = json!({
+        "event": "recovered",
+        "session": args.session,
+        "index": args.id,
+        "dealing": format::fingerprint(&info),
+        "out": args.out,
+    });
+    output.print(result, || {
+        format!("recovered share {} into {}", args.id, args.out.display())
+    });
+
+    Ok(())
+}
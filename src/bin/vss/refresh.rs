@@ -0,0 +1,137 @@
+use std::collections::BTreeMap;
+use std::io;
+use std::net::{SocketAddr, TcpListener};
+use std::path::PathBuf;
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError};
+use std::time::{Duration, Instant};
+
+use rust_vss::config::Config;
+use rust_vss::dealer::DEALER_ID;
+use rust_vss::transport::Transport;
+use rust_vss::{Payload, WireMessage, RPC};
+use serde_json::json;
+
+use crate::output::{failed, Failure, Format};
+
+#[derive(clap::Args)]
+pub struct Args {
+    /// TOML config listing every player and the coordinator, see rust_vss::config::Config
+    #[arg(long)]
+    config: PathBuf,
+    /// Session of the dealing to refresh, as printed when it was dealt
+    #[arg(long)]
+    session: u64,
+    /// Address to listen on for answers, the coordinator's address in the config if not given
+    #[arg(long)]
+    listen: Option<SocketAddr>,
+    /// Seconds to wait for every player to answer
+    #[arg(long, default_value_t = 30)]
+    timeout: u64,
+}
+
+/// How a player took the request to refresh
+pub enum Outcome {
+    Refreshed(u64),
+    Failed,
+    Unreachable(io::Error),
+    NoAnswer,
+}
+
+/// Asks every player node to refresh a session and reports which moved to a new epoch
+pub fn run(args: Args, output: Format) -> io::Result<()> {
+    let config = Config::load(&args.config)?;
+    let listen = args
+        .listen
+        .or_else(|| config.coordinator.as_ref().map(|c| c.address))
+        .ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "no --listen and no coordinator in the config",
+            )
+        })?;
+
+    let (inbox, rx) = mpsc::channel();
+    config.listen(TcpListener::bind(listen)?, inbox)?;
+    let transport = config.transport(DEALER_ID)?;
+
+    let timeout = Duration::from_secs(args.timeout);
+    let outcomes = request(&config, &transport, &rx, args.session, timeout);
+
+    report(args.session, &outcomes, output)
+}
+
+/// Asks every player of `config` to refresh `session` over `transport` and waits up to
+/// `timeout` for their answers on `answers`, returns how each player took it
+pub fn request(
+    config: &Config,
+    transport: &dyn Transport,
+    answers: &Receiver<RPC>,
+    session: u64,
+    timeout: Duration,
+) -> BTreeMap<usize, Outcome> {
+    let mut outcomes = BTreeMap::new();
+    for id in config.addresses().into_keys() {
+        let msg = WireMessage::new(session, 0, Payload::RefreshRequest);
+        let outcome = match transport.send(id, msg) {
+            Ok(()) => Outcome::NoAnswer,
+            Err(err) => Outcome::Unreachable(err),
+        };
+        outcomes.insert(id, outcome);
+    }
+
+    let deadline = Instant::now() + timeout;
+    while outcomes
+        .values()
+        .any(|outcome| matches!(outcome, Outcome::NoAnswer))
+    {
+        let timeout = deadline.saturating_duration_since(Instant::now());
+        match answers.recv_timeout(timeout) {
+            Ok(RPC::Wire(from, msg)) if msg.session_id == session => {
+                if let (Payload::RefreshResult { epoch }, Some(outcome)) =
+                    (msg.payload, outcomes.get_mut(&from))
+                {
+                    *outcome = epoch.map_or(Outcome::Failed, Outcome::Refreshed);
+                }
+            }
+            Ok(_) => {}
+            Err(RecvTimeoutError::Timeout | RecvTimeoutError::Disconnected) => break,
+        }
+    }
+
+    outcomes
+}
+
+/// Prints how every player took the request to refresh `session`, failing unless all of them
+/// refreshed
+pub fn report(session: u64, outcomes: &BTreeMap<usize, Outcome>, output: Format) -> io::Result<()> {
+    let (mut players, mut lines) = (vec![], vec![]);
+    for (id, outcome) in outcomes {
+        let (epoch, error) = match outcome {
+            Outcome::Refreshed(epoch) => (Some(*epoch), None),
+            Outcome::Failed => (None, Some("could not refresh".to_string())),
+            Outcome::Unreachable(err) => (None, Some(format!("unreachable: {}", err))),
+            Outcome::NoAnswer => (None, Some("no answer".to_string())),
+        };
+        lines.push(match (epoch, &error) {
+            (Some(epoch), _) => format!("player {}: epoch {}", id, epoch),
+            (None, error) => format!("player {}: {}", id, error.as_deref().unwrap_or_default()),
+        });
+        players.push(json!({ "id": id, "epoch": epoch, "error": error }));
+    }
+    output.print(json!({ "session": session, "players": players }), || {
+        lines.join("\n")
+    });
+
+    let missing = outcomes
+        .values()
+        .filter(|outcome| !matches!(outcome, Outcome::Refreshed(_)))
+        .count();
+    if missing > 0 {
+        return Err(failed(
+            Failure::Quorum,
+            format!("{} of {} players did not refresh", missing, outcomes.len()),
+        ));
+    }
+
+    Ok(())
+}
@@ -0,0 +1,362 @@
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::io::{self, BufRead, IsTerminal, Write};
+use std::net::TcpListener;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::time::Duration;
+
+use num_bigint::BigUint;
+use rust_vss::config::Config;
+use rust_vss::dealer::{Dealer, DEALER_ID};
+use rust_vss::format;
+use rust_vss::player::Player;
+use rust_vss::refresh::Renewal;
+use rust_vss::secret::Secret;
+use rust_vss::transport::tcp::TcpTransport;
+use rust_vss::transport::ChannelNetwork;
+use rust_vss::{Router, ShareInfo, RPC};
+use serde_json::json;
+
+use crate::output::{failed, Failure, Format};
+use crate::refresh::{self, Outcome};
+
+/// How long a command waits on the players
+const TIMEOUT: Duration = Duration::from_secs(10);
+
+const HELP: &str = "\
+deal <secret> <t>       deal a number to the players, any t of which reconstruct it
+sessions                list the dealings made here
+reconstruct <session>   have the players reconstruct a dealing
+refresh <session>       move the shares of a dealing to a new epoch
+submit <share-file>...  verify share files, reconstructing once t of a dealing are in
+help                    show this
+quit                    leave";
+
+#[derive(clap::Args)]
+pub struct Args {
+    /// Number of players to run on threads of this process
+    #[arg(short, default_value_t = 5)]
+    n: usize,
+    /// TOML config of running player nodes to use instead, see rust_vss::config::Config
+    #[arg(long, conflicts_with = "n")]
+    config: Option<PathBuf>,
+    /// Bits of the prime the polynomials are computed over
+    #[arg(long, default_value_t = 32)]
+    bits: usize,
+}
+
+/// The players the REPL deals to
+enum Players {
+    /// Players on threads of this process
+    Local {
+        network: ChannelNetwork,
+        senders: Vec<Sender<RPC>>,
+    },
+    /// Player nodes of a config, dealt to as the dealer and answering the config's coordinator
+    Remote {
+        config: Box<Config>,
+        transport: TcpTransport,
+        answers: Option<Receiver<RPC>>,
+    },
+}
+
+/// A dealing made in the REPL
+struct Dealt {
+    t: usize,
+    epoch: u64,
+}
+
+struct Repl {
+    players: Players,
+    bits: usize,
+    output: Format,
+    sessions: BTreeMap<u64, Dealt>,
+    /// share files that verified, with the path they were read from
+    submitted: Vec<(PathBuf, ShareInfo)>,
+}
+
+/// Reads commands from stdin until it ends or `quit`, printing what each did
+pub fn run(args: Args, output: Format) -> io::Result<()> {
+    let players = match &args.config {
+        Some(path) => {
+            let config = Config::load(path)?;
+            let answers = match &config.coordinator {
+                Some(coordinator) => {
+                    let (inbox, rx) = mpsc::channel();
+                    config.listen(TcpListener::bind(coordinator.address)?, inbox)?;
+                    Some(rx)
+                }
+                None => None,
+            };
+
+            Players::Remote {
+                transport: config.transport(DEALER_ID)?,
+                config: Box::new(config),
+                answers,
+            }
+        }
+        None => {
+            let network = ChannelNetwork::new();
+            let senders = (1..=args.n)
+                .map(|id| {
+                    let (sender, _) = Player::spawn(id, network.transport(id));
+                    network.register(id, sender.clone());
+
+                    sender
+                })
+                .collect();
+
+            Players::Local { network, senders }
+        }
+    };
+    let mut repl = Repl {
+        players,
+        bits: args.bits,
+        output,
+        sessions: BTreeMap::new(),
+        submitted: vec![],
+    };
+
+    let prompt = output == Format::Text && io::stdin().is_terminal();
+    if prompt {
+        println!("{} players, type help for the commands", repl.n());
+    }
+    let mut lines = io::stdin().lock().lines();
+    loop {
+        if prompt {
+            print!("vss> ");
+            io::stdout().flush()?;
+        }
+        let line = match lines.next() {
+            Some(line) => line?,
+            None => return Ok(()),
+        };
+
+        let words: Vec<&str> = line.split_whitespace().collect();
+        match repl.exec(&words) {
+            Ok(true) => {}
+            Ok(false) => return Ok(()),
+            Err(err) => output.print_error(&err),
+        }
+    }
+}
+
+/// The `i`th argument of a command, parsed
+fn arg<T: FromStr>(words: &[&str], i: usize, name: &str) -> io::Result<T> {
+    let invalid = |msg: String| io::Error::new(io::ErrorKind::InvalidInput, msg);
+    let word = words
+        .get(i)
+        .ok_or_else(|| invalid(format!("{} needs a {}", words[0], name)))?;
+
+    word.parse()
+        .map_err(|_| invalid(format!("{} is not a {}", word, name)))
+}
+
+impl Repl {
+    /// Number of players
+    fn n(&self) -> usize {
+        match &self.players {
+            Players::Local { senders, .. } => senders.len(),
+            Players::Remote { config, .. } => config.players.len(),
+        }
+    }
+
+    /// Run the command of `words`, returns false to leave
+    fn exec(&mut self, words: &[&str]) -> io::Result<bool> {
+        match words.first().copied() {
+            None => {}
+            Some("deal") => self.deal(arg(words, 1, "secret")?, arg(words, 2, "threshold")?)?,
+            Some("sessions") => self.list_sessions(),
+            Some("reconstruct") => self.reconstruct(arg(words, 1, "session")?)?,
+            Some("refresh") => self.refresh(arg(words, 1, "session")?)?,
+            Some("submit") => {
+                for path in &words[1..] {
+                    self.submit(Path::new(path))?;
+                }
+            }
+            Some("help") => println!("{}", HELP),
+            Some("quit" | "exit") => return Ok(false),
+            Some(other) => {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    format!("no command {}, type help for the commands", other),
+                ))
+            }
+        }
+
+        Ok(true)
+    }
+
+    fn deal(&mut self, secret: usize, t: usize) -> io::Result<()> {
+        let n = self.n();
+        let dealer = Dealer::with_bits(n, t, Secret::new(BigUint::from(secret)), self.bits)?;
+        match &self.players {
+            Players::Local { network, .. } => dealer.propagate(&network.transport(DEALER_ID)),
+            Players::Remote { transport, .. } => dealer.propagate(transport),
+        }
+        self.sessions
+            .insert(dealer.session_id, Dealt { t, epoch: 0 });
+
+        let session = dealer.session_id;
+        self.output
+            .print(json!({ "session": session, "t": t, "n": n }), || {
+                format!("dealt session {}, {} of {} players needed", session, t, n)
+            });
+
+        Ok(())
+    }
+
+    fn list_sessions(&self) {
+        let sessions = self
+            .sessions
+            .iter()
+            .map(|(session, dealt)| json!({ "session": session, "t": dealt.t, "epoch": dealt.epoch }))
+            .collect::<Vec<_>>();
+
+        self.output.print(json!({ "sessions": sessions }), || {
+            self.sessions
+                .iter()
+                .map(|(session, dealt)| {
+                    format!("{}  t {}  epoch {}", session, dealt.t, dealt.epoch)
+                })
+                .collect::<Vec<_>>()
+                .join("\n")
+        });
+    }
+
+    fn reconstruct(&self, session: u64) -> io::Result<()> {
+        let senders = match &self.players {
+            Players::Local { senders, .. } => senders,
+            Players::Remote { .. } => {
+                return Err(io::Error::new(
+                    io::ErrorKind::Unsupported,
+                    "player nodes reconstruct on their own, run them with --reconstruct",
+                ))
+            }
+        };
+
+        let mut router = Router::new();
+        for sender in senders {
+            let _ = sender.send(RPC::Reconstruct(session, router.request()));
+        }
+        let (_, report) = router
+            .recv_timeout(TIMEOUT)
+            .map_err(|err| failed(Failure::Quorum, format!("could not reconstruct: {}", err)))?;
+
+        self.output.print(
+            json!({ "session": session, "secret": report.secret.expose().to_string(), "contributors": report.contributors }),
+            || format!("reconstructed {} from {:?}", report.secret.expose(), report.contributors),
+        );
+
+        Ok(())
+    }
+
+    fn refresh(&mut self, session: u64) -> io::Result<()> {
+        let outcomes = match &self.players {
+            Players::Local { senders, .. } => {
+                let mut router = Router::new();
+                let mut outcomes = BTreeMap::new();
+                let mut asked = HashMap::new();
+                for (sender, id) in senders.iter().zip(1..) {
+                    let reply = router.request();
+                    asked.insert(reply.id(), id);
+                    outcomes.insert(id, Outcome::NoAnswer);
+                    if let Err(err) = sender.send(RPC::Refresh(session, Renewal::Refresh, reply)) {
+                        let err = io::Error::new(io::ErrorKind::BrokenPipe, err.to_string());
+                        outcomes.insert(id, Outcome::Unreachable(err));
+                    }
+                }
+                while router.outstanding() > 0 {
+                    match router.recv_timeout(TIMEOUT) {
+                        Ok((reply, report)) => {
+                            outcomes.insert(asked[&reply], Outcome::Refreshed(report.epoch));
+                        }
+                        Err(_) => break,
+                    }
+                }
+
+                outcomes
+            }
+            Players::Remote {
+                config,
+                transport,
+                answers: Some(answers),
+            } => refresh::request(config, transport, answers, session, TIMEOUT),
+            Players::Remote { answers: None, .. } => {
+                return Err(io::Error::new(
+                    io::ErrorKind::Unsupported,
+                    "the config has no coordinator for the players to answer",
+                ))
+            }
+        };
+
+        let epoch = outcomes.values().find_map(|outcome| match outcome {
+            Outcome::Refreshed(epoch) => Some(*epoch),
+            _ => None,
+        });
+        if let (Some(epoch), Some(dealt)) = (epoch, self.sessions.get_mut(&session)) {
+            dealt.epoch = epoch;
+        }
+
+        refresh::report(session, &outcomes, self.output)
+    }
+
+    fn submit(&mut self, path: &Path) -> io::Result<()> {
+        let share_info = crate::load_share(path)?;
+        let dealing = format::fingerprint(&share_info);
+        let index = share_info.share().index().to_string();
+        if !share_info.verify() {
+            return Err(failed(
+                Failure::Verification,
+                format!("{}: share does not verify", path.display()),
+            ));
+        }
+
+        let t = share_info.t();
+        self.submitted.push((path.to_path_buf(), share_info));
+        let same: Vec<(PathBuf, ShareInfo)> = self
+            .submitted
+            .iter()
+            .filter(|(_, info)| format::fingerprint(info) == dealing)
+            .cloned()
+            .collect();
+        let indices: HashSet<_> = same.iter().map(|(_, info)| info.share().index()).collect();
+        let have = indices.len();
+
+        self.output.print(
+            json!({ "path": path, "index": index, "dealing": dealing, "have": have, "t": t }),
+            || {
+                format!(
+                    "share {} of dealing {}, {} of {} needed",
+                    index, dealing, have, t
+                )
+            },
+        );
+        if have < t {
+            return Ok(());
+        }
+
+        // the latest share of every index, so a share submitted again is taken once
+        let mut latest: HashMap<&BigUint, &(PathBuf, ShareInfo)> = HashMap::new();
+        for entry in &same {
+            latest.insert(entry.1.share().index(), entry);
+        }
+        let shares: Vec<(PathBuf, ShareInfo)> = latest.into_values().cloned().collect();
+        let secret = format::reconstruct_shares(&shares)?;
+        // shares of `vss deal` hold bytes, shares of `deal` here a number
+        let text = format::decode_secret(&secret)
+            .ok()
+            .map(|bytes| String::from_utf8_lossy(bytes.expose()).into_owned());
+
+        self.output.print(
+            json!({ "dealing": dealing, "secret": secret.expose().to_string(), "text": text }),
+            || match &text {
+                Some(text) => format!("dealing {}: secret {:?}", dealing, text),
+                None => format!("dealing {}: secret {}", dealing, secret.expose()),
+            },
+        );
+
+        Ok(())
+    }
+}
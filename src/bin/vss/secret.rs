@@ -0,0 +1,128 @@
+use std::env;
+use std::fs;
+use std::io::{self, Read, Write};
+use std::path::PathBuf;
+
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use rust_vss::secret::SecretBytes;
+use serde_json::json;
+
+use crate::output::Format;
+
+/// Where the secret to deal is read from, exactly one of them
+#[derive(clap::Args)]
+#[group(required = true, multiple = false)]
+pub struct Input {
+    /// File holding the secret, any bytes, `-` for stdin
+    #[arg(long)]
+    secret_file: Option<PathBuf>,
+    /// Environment variable holding the secret
+    #[arg(long, value_name = "VAR")]
+    secret_env: Option<String>,
+    /// Prompt for the secret without echoing it
+    #[arg(long)]
+    prompt: bool,
+}
+
+impl Input {
+    /// The bytes of the secret
+    pub fn read(&self) -> io::Result<SecretBytes> {
+        if let Some(path) = &self.secret_file {
+            if path.as_os_str() == "-" {
+                let mut secret = vec![];
+                io::stdin().read_to_end(&mut secret)?;
+
+                return Ok(SecretBytes::new(secret));
+            }
+
+            return fs::read(path).map(SecretBytes::new);
+        }
+        if let Some(var) = &self.secret_env {
+            return env::var_os(var)
+                .map(|secret| SecretBytes::new(secret.into_encoded_bytes()))
+                .ok_or_else(|| {
+                    io::Error::new(io::ErrorKind::NotFound, format!("{} is not set", var))
+                });
+        }
+
+        rpassword::prompt_password("Secret: ").map(|secret| SecretBytes::new(secret.into_bytes()))
+    }
+}
+
+/// How a reconstructed secret is written out
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum Encoding {
+    /// The bytes as they were dealt
+    Raw,
+    /// Lowercase hex on one line
+    Hex,
+    /// Standard base64 on one line
+    Base64,
+}
+
+impl Encoding {
+    fn apply(self, secret: &[u8]) -> Vec<u8> {
+        match self {
+            Encoding::Raw => secret.to_vec(),
+            Encoding::Hex => format!("{}\n", hex::encode(secret)).into_bytes(),
+            Encoding::Base64 => format!("{}\n", STANDARD.encode(secret)).into_bytes(),
+        }
+    }
+}
+
+/// Where a reconstructed secret is written to
+#[derive(clap::Args)]
+pub struct Output {
+    /// File to write the secret to, stdout if not given
+    #[arg(long)]
+    out: Option<PathBuf>,
+    #[arg(long, value_enum, default_value_t = Encoding::Raw)]
+    encoding: Encoding,
+}
+
+impl Output {
+    /// Write `secret` encoded as asked
+    ///
+    /// In JSON mode the secret on stdout is a string of hex, or base64 if asked, and a secret
+    /// written to a file is reported with its path
+    pub fn write(&self, secret: &[u8], output: Format) -> io::Result<()> {
+        let bytes = self.encoding.apply(secret);
+        match (&self.out, output) {
+            (Some(path), Format::Text) => fs::write(path, bytes),
+            (None, Format::Text) => io::stdout().write_all(&bytes),
+            (Some(path), Format::Json) => {
+                fs::write(path, bytes)?;
+                output.print(json!({ "out": path }), String::new);
+
+                Ok(())
+            }
+            (None, Format::Json) => {
+                let (encoding, secret) = match self.encoding {
+                    Encoding::Base64 => ("base64", STANDARD.encode(secret)),
+                    Encoding::Raw | Encoding::Hex => ("hex", hex::encode(secret)),
+                };
+                output.print(
+                    json!({ "secret": secret, "encoding": encoding }),
+                    String::new,
+                );
+
+                Ok(())
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Encoding;
+
+    #[test]
+    fn encodings() {
+        let secret = [0xde, 0xad, 0xbe, 0xef];
+
+        assert_eq!(secret.to_vec(), Encoding::Raw.apply(&secret));
+        assert_eq!(b"deadbeef\n".to_vec(), Encoding::Hex.apply(&secret));
+        assert_eq!(b"3q2+7w==\n".to_vec(), Encoding::Base64.apply(&secret));
+    }
+}
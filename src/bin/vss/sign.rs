@@ -0,0 +1,227 @@
+use std::fs;
+use std::io;
+use std::net::{SocketAddr, TcpListener};
+use std::path::PathBuf;
+use std::sync::mpsc::{self, RecvTimeoutError};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use rand::Rng;
+use rust_vss::config::Config;
+use rust_vss::dealer::DEALER_ID;
+use rust_vss::format::{self, SignatureFile};
+use rust_vss::transport::Transport;
+use rust_vss::{Payload, PublicParams, WireMessage, RPC};
+use serde_json::json;
+
+use crate::output::{failed, Failure, Format};
+
+#[derive(clap::Subcommand)]
+pub enum Command {
+    /// Have t player nodes sign a message file with their shares and write the signature
+    Create(CreateArgs),
+    /// Check a signature file against a message file
+    Verify(VerifyArgs),
+}
+
+/// The dealing a key is taken from, its transcript or any share of it
+#[derive(clap::Args)]
+#[group(multiple = false)]
+pub struct Dealing {
+    /// Transcript of the dealing, as `vss deal` writes it
+    #[arg(long)]
+    transcript: Option<PathBuf>,
+    /// Share file of the dealing, only its public parameters are used
+    #[arg(long)]
+    like: Option<PathBuf>,
+}
+
+impl Dealing {
+    /// The public parameters of the dealing, None if neither file was given
+    fn params(&self) -> io::Result<Option<Arc<PublicParams>>> {
+        if let Some(path) = &self.transcript {
+            return Ok(Some(Arc::new(format::load_transcript(path)?.params())));
+        }
+        if let Some(path) = &self.like {
+            return Ok(Some(crate::load_share(path)?.params().clone()));
+        }
+
+        Ok(None)
+    }
+}
+
+#[derive(clap::Args)]
+pub struct CreateArgs {
+    /// TOML config listing every player and the coordinator, see rust_vss::config::Config
+    #[arg(long)]
+    config: PathBuf,
+    /// Session of the dealing whose secret signs, as printed when it was dealt
+    #[arg(long)]
+    session: u64,
+    /// Ids of the players to sign, at least the threshold of them. The first combines the
+    /// partial signatures of the others
+    #[arg(long, required = true, value_delimiter = ',')]
+    signers: Vec<usize>,
+    /// File holding the message to sign
+    #[arg(long)]
+    message: PathBuf,
+    #[command(flatten)]
+    dealing: Dealing,
+    /// File to write the signature to
+    #[arg(long)]
+    out: PathBuf,
+    /// Address to listen on for the signature, the coordinator's address in the config if not
+    /// given
+    #[arg(long)]
+    listen: Option<SocketAddr>,
+    /// Seconds to wait for the signers
+    #[arg(long, default_value_t = 30)]
+    timeout: u64,
+}
+
+#[derive(clap::Args)]
+pub struct VerifyArgs {
+    /// Signature file, as `vss sign create` writes it
+    signature: PathBuf,
+    /// File holding the message signed
+    #[arg(long)]
+    message: PathBuf,
+    /// Also check that the signature is by the key of this dealing rather than only the key in
+    /// the file
+    #[command(flatten)]
+    dealing: Dealing,
+}
+
+pub fn run(command: Command, output: Format) -> io::Result<()> {
+    match command {
+        Command::Create(args) => create(args, output),
+        Command::Verify(args) => verify(args, output),
+    }
+}
+
+/// Asks the first signer to coordinate a signature of the message by the signers, checks it
+/// and writes it out
+fn create(args: CreateArgs, output: Format) -> io::Result<()> {
+    let config = Config::load(&args.config)?;
+    let params = args.dealing.params()?.ok_or_else(|| {
+        io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "--transcript or --like is needed for the key of the dealing",
+        )
+    })?;
+    let listen = args
+        .listen
+        .or_else(|| config.coordinator.as_ref().map(|c| c.address))
+        .ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "no --listen and no coordinator in the config",
+            )
+        })?;
+    let mut signers = args.signers.clone();
+    signers.sort();
+    signers.dedup();
+    if signers.len() < params.t {
+        return Err(failed(
+            Failure::Quorum,
+            format!("{} signers, {} needed", signers.len(), params.t),
+        ));
+    }
+    let message = fs::read(&args.message)?;
+
+    let (inbox, rx) = mpsc::channel();
+    config.listen(TcpListener::bind(listen)?, inbox)?;
+    let transport = config.transport(DEALER_ID)?;
+
+    let coordinator = args.signers[0];
+    let request = rand::thread_rng().gen();
+    let payload = Payload::SignRequest {
+        request,
+        message: message.clone(),
+        signers: signers.clone(),
+    };
+    transport.send(coordinator, WireMessage::new(args.session, 0, payload))?;
+
+    let deadline = Instant::now() + Duration::from_secs(args.timeout);
+    let signature = loop {
+        let timeout = deadline.saturating_duration_since(Instant::now());
+        match rx.recv_timeout(timeout) {
+            Ok(RPC::Wire(from, msg)) if from == coordinator && msg.session_id == args.session => {
+                match msg.payload {
+                    Payload::SignatureResult {
+                        request: answered,
+                        signature,
+                    } if answered == request => break signature,
+                    _ => {}
+                }
+            }
+            Ok(_) => {}
+            Err(RecvTimeoutError::Timeout | RecvTimeoutError::Disconnected) => {
+                return Err(failed(
+                    Failure::Quorum,
+                    format!("player {} sent no signature", coordinator),
+                ))
+            }
+        }
+    };
+
+    let file = SignatureFile::new(args.session, signers, &params, &signature);
+    if !file.verify(&message) {
+        return Err(failed(
+            Failure::Verification,
+            "the signature does not verify under the key of the dealing",
+        ));
+    }
+    format::save_signature(&args.out, &file)?;
+
+    let result = json!({
+        "session": args.session,
+        "signers": file.signers,
+        "public_key": file.public_key.to_string(),
+        "out": args.out,
+    });
+    output.print(result, || {
+        format!(
+            "signed by {:?} under {} into {}",
+            file.signers,
+            file.public_key,
+            args.out.display()
+        )
+    });
+
+    Ok(())
+}
+
+/// Checks a signature file against the message and, if given, the key of a dealing
+fn verify(args: VerifyArgs, output: Format) -> io::Result<()> {
+    let file = format::load_signature(&args.signature)?;
+    let message = fs::read(&args.message)?;
+
+    if let Some(params) = args.dealing.params()? {
+        let group = (&params.p, &params.q, &params.g);
+        if group != (&file.group.p, &file.group.q, &file.group.g)
+            || params.commitments[0] != file.public_key
+        {
+            return Err(failed(
+                Failure::Verification,
+                "the signature is not under the key of the dealing",
+            ));
+        }
+    }
+    let valid = file.verify(&message);
+    output.print(
+        json!({ "valid": valid, "public_key": file.public_key.to_string() }),
+        || match valid {
+            true => format!("valid signature under {}", file.public_key),
+            false => format!("invalid signature under {}", file.public_key),
+        },
+    );
+    if !valid {
+        return Err(failed(
+            Failure::Verification,
+            "the signature does not verify",
+        ));
+    }
+
+    Ok(())
+}
@@ -0,0 +1,127 @@
+use std::fs;
+use std::io::{self, Read};
+use std::path::PathBuf;
+use std::str::FromStr;
+
+use clap::Subcommand;
+use rust_vss::slip39;
+use serde_json::json;
+
+use crate::output::Format;
+use crate::secret;
+
+#[derive(Subcommand)]
+pub enum Command {
+    /// Split a secret into SLIP-0039 mnemonics, printed group by group
+    Export(ExportArgs),
+    /// Recover a secret from SLIP-0039 mnemonics, one per line
+    Import(ImportArgs),
+}
+
+/// A group of mnemonics, written `<threshold>/<count>`
+#[derive(Debug, Clone, Copy)]
+pub struct Group(u8, u8);
+
+impl FromStr for Group {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Group, String> {
+        let (threshold, count) = s
+            .split_once('/')
+            .ok_or_else(|| format!("{} is not <threshold>/<count>", s))?;
+        let parse = |n: &str| n.parse::<u8>().map_err(|err| format!("{}: {}", s, err));
+
+        Ok(Group(parse(threshold)?, parse(count)?))
+    }
+}
+
+#[derive(clap::Args)]
+pub struct ExportArgs {
+    #[command(flatten)]
+    secret: secret::Input,
+    /// A group of mnemonics as <threshold>/<count>, repeated for every group
+    #[arg(long = "group", required = true)]
+    groups: Vec<Group>,
+    /// Number of groups needed to recover the secret
+    #[arg(long, default_value_t = 1)]
+    group_threshold: u8,
+    #[arg(long, default_value = "")]
+    passphrase: String,
+}
+
+#[derive(clap::Args)]
+pub struct ImportArgs {
+    /// File of mnemonics, stdin if not given; blank lines and lines starting with # are skipped
+    mnemonics: Option<PathBuf>,
+    #[arg(long, default_value = "")]
+    passphrase: String,
+    #[command(flatten)]
+    destination: secret::Output,
+}
+
+pub fn run(command: Command, output: Format) -> io::Result<()> {
+    match command {
+        Command::Export(args) => export(args, output),
+        Command::Import(args) => import(args, output),
+    }
+}
+
+fn export(args: ExportArgs, output: Format) -> io::Result<()> {
+    let groups: Vec<(u8, u8)> = args.groups.iter().map(|g| (g.0, g.1)).collect();
+    let mnemonics = slip39::split(
+        args.secret.read()?.expose(),
+        args.passphrase.as_bytes(),
+        args.group_threshold,
+        &groups,
+        0,
+    )?;
+
+    let result = json!({
+        "group_threshold": args.group_threshold,
+        "groups": mnemonics
+            .iter()
+            .zip(&groups)
+            .map(|(group, (threshold, _))| json!({ "threshold": threshold, "mnemonics": group }))
+            .collect::<Vec<_>>(),
+    });
+    output.print(result, || {
+        let mut lines = vec![];
+        for (i, (group, (threshold, _))) in mnemonics.iter().zip(&groups).enumerate() {
+            if i > 0 {
+                lines.push(String::new());
+            }
+            lines.push(format!(
+                "# group {}, {} of {} needed",
+                i + 1,
+                threshold,
+                group.len()
+            ));
+            lines.extend(group.iter().cloned());
+        }
+
+        lines.join("\n")
+    });
+
+    Ok(())
+}
+
+fn import(args: ImportArgs, output: Format) -> io::Result<()> {
+    let text = match &args.mnemonics {
+        Some(path) => fs::read_to_string(path)?,
+        None => {
+            let mut text = String::new();
+            io::stdin().read_to_string(&mut text)?;
+            text
+        }
+    };
+    let mnemonics: Vec<&str> = text
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .collect();
+
+    args.destination.write(
+        &slip39::combine(&mnemonics, args.passphrase.as_bytes())?,
+        output,
+    )
+}
@@ -0,0 +1,93 @@
+use std::fs;
+use std::io::{self, Read};
+use std::path::PathBuf;
+
+use clap::Subcommand;
+use rust_vss::ssss;
+use serde_json::json;
+
+use crate::output::Format;
+use crate::secret;
+
+#[derive(Subcommand)]
+pub enum Command {
+    /// Split a secret into shares as ssss-split writes them
+    Export(ExportArgs),
+    /// Recover a secret from shares written by ssss-split, one per line
+    Import(ImportArgs),
+}
+
+#[derive(clap::Args)]
+pub struct ExportArgs {
+    #[command(flatten)]
+    secret: secret::Input,
+    /// Number of shares needed to recover the secret
+    #[arg(short, long)]
+    threshold: usize,
+    /// Number of shares
+    #[arg(short, long)]
+    number: usize,
+    /// Name starting every share, as ssss-split -w
+    #[arg(short = 'w', long)]
+    token: Option<String>,
+    /// Leave out the diffusion layer, as ssss-split -D
+    #[arg(short = 'D', long)]
+    no_diffusion: bool,
+}
+
+#[derive(clap::Args)]
+pub struct ImportArgs {
+    /// File of shares, stdin if not given; blank lines and lines starting with # are skipped
+    shares: Option<PathBuf>,
+    /// Number of shares needed to recover the secret, the first this many are used
+    #[arg(short, long)]
+    threshold: usize,
+    /// The shares were made without the diffusion layer, as ssss-combine -D
+    #[arg(short = 'D', long)]
+    no_diffusion: bool,
+    #[command(flatten)]
+    destination: secret::Output,
+}
+
+pub fn run(command: Command, output: Format) -> io::Result<()> {
+    match command {
+        Command::Export(args) => export(args, output),
+        Command::Import(args) => import(args, output),
+    }
+}
+
+fn export(args: ExportArgs, output: Format) -> io::Result<()> {
+    let shares = ssss::split(
+        args.secret.read()?.expose(),
+        args.threshold,
+        args.number,
+        args.token.as_deref(),
+        !args.no_diffusion,
+    )?;
+
+    let result = json!({ "threshold": args.threshold, "shares": shares });
+    output.print(result, || shares.join("\n"));
+
+    Ok(())
+}
+
+fn import(args: ImportArgs, output: Format) -> io::Result<()> {
+    let text = match &args.shares {
+        Some(path) => fs::read_to_string(path)?,
+        None => {
+            let mut text = String::new();
+            io::stdin().read_to_string(&mut text)?;
+            text
+        }
+    };
+    let shares: Vec<&str> = text
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .collect();
+
+    args.destination.write(
+        &ssss::combine(&shares, args.threshold, !args.no_diffusion)?,
+        output,
+    )
+}
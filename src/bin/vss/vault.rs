@@ -0,0 +1,144 @@
+use std::fs;
+use std::io::{self, Read};
+use std::path::PathBuf;
+
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use clap::Subcommand;
+use rust_vss::vault;
+use serde_json::json;
+
+use crate::output::{failed, Failure, Format};
+use crate::secret;
+
+#[derive(Subcommand)]
+pub enum Command {
+    /// Split a secret into unseal keys as vault operator init prints them
+    Export(ExportArgs),
+    /// Recover a secret from unseal keys, one per line
+    Import(ImportArgs),
+    /// Check that unseal keys are of one secret split with a threshold
+    Verify(VerifyArgs),
+}
+
+#[derive(clap::Args)]
+pub struct ExportArgs {
+    #[command(flatten)]
+    secret: secret::Input,
+    /// Number of keys needed to recover the secret
+    #[arg(short, long)]
+    threshold: usize,
+    /// Number of keys
+    #[arg(short, long)]
+    number: usize,
+}
+
+/// File of unseal keys, stdin if not given; each line is a key in hex or base64, possibly
+/// after `Unseal Key N:`, and blank lines and lines starting with # are skipped
+#[derive(clap::Args)]
+pub struct Keys {
+    keys: Option<PathBuf>,
+}
+
+#[derive(clap::Args)]
+pub struct ImportArgs {
+    #[command(flatten)]
+    keys: Keys,
+    #[command(flatten)]
+    destination: secret::Output,
+}
+
+#[derive(clap::Args)]
+pub struct VerifyArgs {
+    #[command(flatten)]
+    keys: Keys,
+    /// Number of keys needed to recover the secret
+    #[arg(short, long)]
+    threshold: usize,
+}
+
+impl Keys {
+    /// The shares of the keys, hex tried before base64 as Vault does
+    fn read(&self) -> io::Result<Vec<Vec<u8>>> {
+        let text = match &self.keys {
+            Some(path) => fs::read_to_string(path)?,
+            None => {
+                let mut text = String::new();
+                io::stdin().read_to_string(&mut text)?;
+                text
+            }
+        };
+
+        text.lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty() && !line.starts_with('#'))
+            .map(|line| {
+                let key = match line.split_once(':') {
+                    Some((label, key)) if label.starts_with("Unseal Key") => key.trim(),
+                    _ => line,
+                };
+                hex::decode(key)
+                    .or_else(|_| STANDARD.decode(key))
+                    .map_err(|_| {
+                        io::Error::new(
+                            io::ErrorKind::InvalidData,
+                            format!("{} is neither hex nor base64", key),
+                        )
+                    })
+            })
+            .collect()
+    }
+}
+
+pub fn run(command: Command, output: Format) -> io::Result<()> {
+    match command {
+        Command::Export(args) => export(args, output),
+        Command::Import(args) => args
+            .destination
+            .write(&vault::combine(&args.keys.read()?)?, output),
+        Command::Verify(args) => verify(args, output),
+    }
+}
+
+fn export(args: ExportArgs, output: Format) -> io::Result<()> {
+    let shares = vault::split(args.secret.read()?.expose(), args.number, args.threshold)?;
+
+    // the fields of vault operator init -format=json
+    let result = json!({
+        "unseal_keys_b64": shares.iter().map(|share| STANDARD.encode(share)).collect::<Vec<_>>(),
+        "unseal_keys_hex": shares.iter().map(hex::encode).collect::<Vec<_>>(),
+        "unseal_shares": args.number,
+        "unseal_threshold": args.threshold,
+    });
+    output.print(result, || {
+        shares
+            .iter()
+            .enumerate()
+            .map(|(i, share)| format!("Unseal Key {}: {}", i + 1, STANDARD.encode(share)))
+            .collect::<Vec<_>>()
+            .join("\n")
+    });
+
+    Ok(())
+}
+
+fn verify(args: VerifyArgs, output: Format) -> io::Result<()> {
+    let shares = args.keys.read()?;
+    vault::check(&shares, args.threshold).map_err(|err| match err.kind() {
+        io::ErrorKind::InvalidData => failed(Failure::Verification, err.to_string()),
+        _ => err,
+    })?;
+
+    output.print(
+        json!({ "keys": shares.len(), "threshold": args.threshold }),
+        || {
+            format!(
+                "{} keys consistent with a threshold of {}",
+                shares.len(),
+                args.threshold
+            )
+        },
+    );
+
+    Ok(())
+}
@@ -0,0 +1,53 @@
+use std::io;
+
+use serde_json::json;
+
+use crate::output::{failed, Failure, Format};
+
+#[derive(clap::Args)]
+pub struct Args {
+    #[command(flatten)]
+    shares: crate::Shares,
+}
+
+pub fn run(args: Args, output: Format) -> io::Result<()> {
+    let shares = args.shares.load()?;
+    let verdicts: Vec<bool> = shares.iter().map(|(_, info)| info.verify()).collect();
+
+    let result = shares
+        .iter()
+        .zip(&verdicts)
+        .map(|((path, share_info), verifies)| {
+            json!({
+                "path": path,
+                "index": share_info.share().index().to_string(),
+                "verifies": verifies,
+            })
+        })
+        .collect::<Vec<_>>();
+    output.print(json!({ "shares": result }), || {
+        shares
+            .iter()
+            .zip(&verdicts)
+            .map(|((path, share_info), verifies)| {
+                format!(
+                    "{}: share {} {}",
+                    path.display(),
+                    share_info.share().index(),
+                    if *verifies { "ok" } else { "INVALID" }
+                )
+            })
+            .collect::<Vec<_>>()
+            .join("\n")
+    });
+
+    let invalid = verdicts.iter().filter(|verifies| !**verifies).count();
+    if invalid > 0 {
+        return Err(failed(
+            Failure::Verification,
+            format!("{} shares do not verify", invalid),
+        ));
+    }
+
+    Ok(())
+}
@@ -0,0 +1,85 @@
+use std::collections::HashSet;
+use std::io;
+use std::path::PathBuf;
+
+use rust_vss::format;
+use serde_json::json;
+
+use crate::output::{failed, Failure, Format};
+
+#[derive(clap::Args)]
+pub struct Args {
+    /// Transcript of the dealing, as vss deal writes it next to the shares
+    transcript: PathBuf,
+    #[command(flatten)]
+    shares: crate::Shares,
+}
+
+pub fn run(args: Args, output: Format) -> io::Result<()> {
+    let transcript = format::load_transcript(&args.transcript)?;
+    let shares = args.shares.load()?;
+
+    let mut seen = HashSet::new();
+    let verdicts: Vec<Result<(), String>> = shares
+        .iter()
+        .map(|(_, info)| {
+            transcript.check(info).map_err(|err| err.to_string())?;
+            if !seen.insert(info.share().index().clone()) {
+                return Err(format!("share {} given twice", info.share().index()));
+            }
+
+            Ok(())
+        })
+        .collect();
+
+    let result = shares
+        .iter()
+        .zip(&verdicts)
+        .map(|((path, info), verdict)| {
+            json!({
+                "path": path,
+                "index": info.share().index().to_string(),
+                "verifies": verdict.is_ok(),
+                "error": verdict.as_ref().err(),
+            })
+        })
+        .collect::<Vec<_>>();
+    let missing = transcript.players.saturating_sub(seen.len());
+    output.print(
+        json!({
+            "session": transcript.session,
+            "players": transcript.players,
+            "threshold": transcript.threshold,
+            "shares": result,
+        }),
+        || {
+            let mut lines: Vec<String> = shares
+                .iter()
+                .zip(&verdicts)
+                .map(|((path, info), verdict)| match verdict {
+                    Ok(()) => format!("{}: share {} ok", path.display(), info.share().index()),
+                    Err(err) => format!("{}: INVALID, {}", path.display(), err),
+                })
+                .collect();
+            lines.push(format!(
+                "session {}: {} of the {} players' shares check out, {} not given",
+                transcript.session,
+                seen.len(),
+                transcript.players,
+                missing
+            ));
+
+            lines.join("\n")
+        },
+    );
+
+    let invalid = verdicts.iter().filter(|verdict| verdict.is_err()).count();
+    if invalid > 0 {
+        return Err(failed(
+            Failure::Verification,
+            format!("{} shares do not match the transcript", invalid),
+        ));
+    }
+
+    Ok(())
+}
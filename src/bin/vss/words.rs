@@ -0,0 +1,74 @@
+use std::io;
+use std::path::PathBuf;
+
+use clap::Subcommand;
+use rust_vss::format;
+use rust_vss::words;
+use rust_vss::ShareInfo;
+use serde_json::json;
+
+use crate::output::{failed, Failure, Format};
+
+#[derive(Subcommand)]
+pub enum Command {
+    /// Print the words of a share file's share
+    Show { share: PathBuf },
+    /// Write a share file for the share read out as words
+    Restore(RestoreArgs),
+}
+
+#[derive(clap::Args)]
+pub struct RestoreArgs {
+    /// The share's words, abbreviated to four letters or not
+    #[arg(required = true)]
+    words: Vec<String>,
+    /// Another share file of the same dealing, to take the commitments and group from
+    #[arg(long)]
+    like: PathBuf,
+    /// Share file to write
+    #[arg(long)]
+    out: PathBuf,
+}
+
+pub fn run(command: Command, output: Format) -> io::Result<()> {
+    match command {
+        Command::Show { share: path } => {
+            let share_info = format::load_share(&path)?;
+            let words = words::encode(share_info.share())?;
+
+            let result = json!({
+                "path": path,
+                "index": share_info.share().index().to_string(),
+                "words": words,
+            });
+            output.print(result, || words.clone());
+
+            Ok(())
+        }
+        Command::Restore(args) => restore(args, output),
+    }
+}
+
+fn restore(args: RestoreArgs, output: Format) -> io::Result<()> {
+    let share = words::decode(&args.words.join(" "))?;
+    let like = format::load_share(&args.like)?;
+    let share_info = ShareInfo::with_params(share, like.params().clone());
+    if !share_info.verify() {
+        return Err(failed(
+            Failure::Verification,
+            format!(
+                "share {} does not verify against {}",
+                share_info.share().index(),
+                args.like.display()
+            ),
+        ));
+    }
+
+    format::save_share(&args.out, &share_info)?;
+    if output == Format::Json {
+        let index = share_info.share().index().to_string();
+        output.print(json!({ "out": args.out, "index": index }), String::new);
+    }
+
+    Ok(())
+}
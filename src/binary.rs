@@ -0,0 +1,364 @@
+//! Shares in a compact, versioned binary layout, for QR codes, mnemonics and files to carry
+//!
+//! Numbers are big endian, index and value canonical as in `codec`:
+//!
+//! ```text
+//! magic "VSSB"        4 bytes
+//! version             1 byte, BINARY_VERSION
+//! scheme              1 byte, see Scheme
+//! integrity           1 byte, 1 for CRC-32 and 2 for HMAC-SHA256
+//! params hash         8 bytes, the fingerprint of the dealing
+//! index length        1 byte
+//! index
+//! value length        2 bytes
+//! value
+//! CRC-32 or HMAC      4 or 32 bytes, of everything before
+//! ```
+//!
+//! A CRC catches a share damaged in storage, an HMAC under a key the players share also one
+//! forged by someone without the key.
+
+use std::io;
+
+use hmac::{Hmac, Mac};
+use num::Zero;
+use sha2::Sha256;
+use thiserror::Error;
+
+use crate::codec;
+use crate::format::{self, FINGERPRINT_LEN};
+use crate::rpc::{PublicParams, Share, ShareInfo};
+
+/// Version of the binary layout this crate writes, and the only one it reads
+pub const BINARY_VERSION: u8 = 1;
+
+const MAGIC: &[u8; 4] = b"VSSB";
+const CRC_LEN: usize = 4;
+const HMAC_LEN: usize = 32;
+
+/// The sharing scheme a binary share belongs to
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Scheme {
+    /// Feldman's VSS of `vss`, checked against the dealing's commitments
+    Feldman,
+}
+
+impl Scheme {
+    fn id(self) -> u8 {
+        match self {
+            Scheme::Feldman => 1,
+        }
+    }
+
+    fn from_id(id: u8) -> Option<Scheme> {
+        match id {
+            1 => Some(Scheme::Feldman),
+            _ => None,
+        }
+    }
+}
+
+/// What a binary share ends with: a CRC, or an HMAC under a key
+#[derive(Clone, Copy)]
+pub enum Integrity<'a> {
+    Crc32,
+    Hmac(&'a [u8]),
+}
+
+impl Integrity<'_> {
+    fn id(&self) -> u8 {
+        match self {
+            Integrity::Crc32 => 1,
+            Integrity::Hmac(_) => 2,
+        }
+    }
+
+    fn name(id: u8) -> &'static str {
+        match id {
+            1 => "CRC-32",
+            _ => "HMAC-SHA256",
+        }
+    }
+
+    fn tag_len(&self) -> usize {
+        match self {
+            Integrity::Crc32 => CRC_LEN,
+            Integrity::Hmac(_) => HMAC_LEN,
+        }
+    }
+
+    fn tag(&self, bytes: &[u8]) -> Vec<u8> {
+        match self {
+            Integrity::Crc32 => crc32(bytes).to_be_bytes().to_vec(),
+            Integrity::Hmac(key) => hmac(key, bytes).finalize().into_bytes().to_vec(),
+        }
+    }
+}
+
+/// Why bytes are not a binary share
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+pub enum DecodeError {
+    #[error("binary share cut short after {0} bytes")]
+    Truncated(usize),
+    #[error("not a binary share")]
+    Magic,
+    #[error("binary share version {0} is not supported")]
+    Version(u8),
+    #[error("binary share of unknown scheme {0}")]
+    Scheme(u8),
+    #[error("binary share checked with unknown integrity {0}")]
+    UnknownIntegrity(u8),
+    #[error("binary share checked with {found}, expected {expected}")]
+    IntegrityMismatch {
+        found: &'static str,
+        expected: &'static str,
+    },
+    #[error("{0} bytes after the binary share")]
+    Trailing(usize),
+    #[error("binary share checksum does not match")]
+    Checksum,
+    #[error("binary share HMAC does not match")]
+    Mac,
+    #[error("binary share index or value is not canonical")]
+    NonCanonical,
+}
+
+impl From<DecodeError> for io::Error {
+    fn from(err: DecodeError) -> io::Error {
+        io::Error::new(io::ErrorKind::InvalidData, err)
+    }
+}
+
+/// A share along with what tells which dealing it is of
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BinaryShare {
+    pub scheme: Scheme,
+    /// The first bytes of the dealing's fingerprint, see `format::fingerprint`
+    pub params_hash: [u8; FINGERPRINT_LEN],
+    pub share: Share,
+}
+
+impl BinaryShare {
+    /// The binary share of `info`, a Feldman share
+    pub fn new(info: &ShareInfo) -> BinaryShare {
+        BinaryShare {
+            scheme: Scheme::Feldman,
+            params_hash: format::params_hash(info.params()),
+            share: info.share().clone(),
+        }
+    }
+
+    /// Whether this is a share of the dealing of `params`
+    pub fn is_of(&self, params: &PublicParams) -> bool {
+        self.params_hash == format::params_hash(params)
+    }
+
+    /// The bytes of this share ending with `integrity`, failing on an index of 0 or over 255
+    /// bytes or a value over 65535 bytes
+    pub fn encode(&self, integrity: Integrity) -> io::Result<Vec<u8>> {
+        let invalid = |msg: &str| io::Error::new(io::ErrorKind::InvalidInput, msg.to_string());
+        let index = codec::to_bytes(self.share.index());
+        let value = codec::to_bytes(self.share.value());
+        if index.is_empty() {
+            return Err(invalid("share index 0 is the secret"));
+        }
+        let index_len =
+            u8::try_from(index.len()).map_err(|_| invalid("share index longer than 255 bytes"))?;
+        let value_len = u16::try_from(value.len())
+            .map_err(|_| invalid("share value longer than 65535 bytes"))?;
+
+        let mut bytes = MAGIC.to_vec();
+        bytes.extend([BINARY_VERSION, self.scheme.id(), integrity.id()]);
+        bytes.extend(self.params_hash);
+        bytes.push(index_len);
+        bytes.extend(index);
+        bytes.extend(value_len.to_be_bytes());
+        bytes.extend(value);
+        bytes.extend(integrity.tag(&bytes));
+
+        Ok(bytes)
+    }
+
+    /// The share `bytes` encode, which must end with `integrity` and nothing after it
+    pub fn decode(bytes: &[u8], integrity: Integrity) -> Result<BinaryShare, DecodeError> {
+        let mut at = 0;
+        let mut take = |n: usize| -> Result<&[u8], DecodeError> {
+            let field = bytes
+                .get(at..at + n)
+                .ok_or(DecodeError::Truncated(bytes.len()))?;
+            at += n;
+            Ok(field)
+        };
+
+        if take(MAGIC.len())? != MAGIC {
+            return Err(DecodeError::Magic);
+        }
+        let header = take(3)?;
+        let (version, scheme, checked) = (header[0], header[1], header[2]);
+        if version != BINARY_VERSION {
+            return Err(DecodeError::Version(version));
+        }
+        let scheme = Scheme::from_id(scheme).ok_or(DecodeError::Scheme(scheme))?;
+        if checked != Integrity::Crc32.id() && checked != Integrity::Hmac(&[]).id() {
+            return Err(DecodeError::UnknownIntegrity(checked));
+        }
+        if checked != integrity.id() {
+            return Err(DecodeError::IntegrityMismatch {
+                found: Integrity::name(checked),
+                expected: Integrity::name(integrity.id()),
+            });
+        }
+        let mut params_hash = [0; FINGERPRINT_LEN];
+        params_hash.copy_from_slice(take(FINGERPRINT_LEN)?);
+        let index_len = take(1)?[0];
+        let index = take(usize::from(index_len))?;
+        let value_len = take(2)?;
+        let value = take(usize::from(u16::from_be_bytes([
+            value_len[0],
+            value_len[1],
+        ])))?;
+        let tag = take(integrity.tag_len())?;
+
+        if bytes.len() > at {
+            return Err(DecodeError::Trailing(bytes.len() - at));
+        }
+        let body = &bytes[..at - tag.len()];
+        match integrity {
+            Integrity::Crc32 if integrity.tag(body) != tag => return Err(DecodeError::Checksum),
+            Integrity::Hmac(key) if hmac(key, body).verify_slice(tag).is_err() => {
+                return Err(DecodeError::Mac)
+            }
+            _ => {}
+        }
+
+        match (codec::from_bytes(index), codec::from_bytes(value)) {
+            (Some(index), Some(value)) if !index.is_zero() => Ok(BinaryShare {
+                scheme,
+                params_hash,
+                share: Share::new(index, value),
+            }),
+            _ => Err(DecodeError::NonCanonical),
+        }
+    }
+}
+
+/// HMAC-SHA256 of `bytes` under `key`
+fn hmac(key: &[u8], bytes: &[u8]) -> Hmac<Sha256> {
+    let mut mac = Hmac::<Sha256>::new_from_slice(key).expect("HMAC takes keys of any size");
+    mac.update(bytes);
+
+    mac
+}
+
+/// CRC-32 of `bytes` as in zip and PNG, reflected polynomial 0xEDB88320
+fn crc32(bytes: &[u8]) -> u32 {
+    let mut crc = !0_u32;
+    for &byte in bytes {
+        crc ^= u32::from(byte);
+        for _ in 0..8 {
+            crc = match crc & 1 {
+                1 => (crc >> 1) ^ 0xEDB8_8320,
+                _ => crc >> 1,
+            };
+        }
+    }
+
+    !crc
+}
+
+#[cfg(test)]
+mod tests {
+    use num_bigint::BigUint;
+
+    use crate::dealer::Dealer;
+    use crate::rpc::{Share, ShareInfo};
+
+    use super::{crc32, BinaryShare, DecodeError, Integrity, Scheme};
+
+    const KEY: &[u8] = b"players' key";
+
+    fn share() -> (Dealer, BinaryShare) {
+        let dealer = Dealer::new(3, 2, 1234).unwrap();
+        let info = ShareInfo::with_params(dealer.shares[0].clone(), dealer.params());
+        let binary = BinaryShare::new(&info);
+
+        (dealer, binary)
+    }
+
+    #[test]
+    fn binary_share_round_trips() {
+        let (dealer, binary) = share();
+        assert!(binary.is_of(&dealer.params()));
+        assert_eq!(Scheme::Feldman, binary.scheme);
+
+        for integrity in [Integrity::Crc32, Integrity::Hmac(KEY)] {
+            let bytes = binary.encode(integrity).unwrap();
+            assert_eq!(&b"VSSB"[..], &bytes[..4]);
+            assert_eq!(binary, BinaryShare::decode(&bytes, integrity).unwrap());
+        }
+
+        // check value of the zip and PNG CRC
+        assert_eq!(0xCBF4_3926, crc32(b"123456789"));
+        let zero = BinaryShare {
+            share: Share::new(BigUint::from(0_u32), BigUint::from(1_u32)),
+            ..binary
+        };
+        assert!(zero.encode(Integrity::Crc32).is_err());
+    }
+
+    #[test]
+    fn every_malformed_share_is_told_apart() {
+        let (_, binary) = share();
+        let bytes = binary.encode(Integrity::Crc32).unwrap();
+        let decode = |bytes: &[u8]| BinaryShare::decode(bytes, Integrity::Crc32).unwrap_err();
+        let patched = |at: usize, byte: u8| {
+            let mut bytes = bytes.clone();
+            bytes[at] = byte;
+            bytes
+        };
+        // a header edited along with its CRC, to get past the checksum
+        let resealed = |mut bytes: Vec<u8>| {
+            let len = bytes.len() - 4;
+            bytes.truncate(len);
+            bytes.extend(crc32(&bytes).to_be_bytes());
+            bytes
+        };
+
+        for len in 0..bytes.len() {
+            assert_eq!(DecodeError::Truncated(len), decode(&bytes[..len]));
+        }
+        assert_eq!(DecodeError::Magic, decode(&patched(0, b'X')));
+        assert_eq!(DecodeError::Version(2), decode(&patched(4, 2)));
+        assert_eq!(DecodeError::Scheme(9), decode(&patched(5, 9)));
+        assert_eq!(DecodeError::UnknownIntegrity(7), decode(&patched(6, 7)));
+        assert_eq!(
+            DecodeError::IntegrityMismatch {
+                found: "CRC-32",
+                expected: "HMAC-SHA256"
+            },
+            BinaryShare::decode(&bytes, Integrity::Hmac(KEY)).unwrap_err()
+        );
+        assert_eq!(
+            DecodeError::Trailing(1),
+            decode(&[&bytes[..], &[0]].concat())
+        );
+        let last = bytes.len() - 5;
+        assert_eq!(
+            DecodeError::Checksum,
+            decode(&patched(last, bytes[last] ^ 1))
+        );
+
+        let sealed = binary.encode(Integrity::Hmac(KEY)).unwrap();
+        assert_eq!(
+            DecodeError::Mac,
+            BinaryShare::decode(&sealed, Integrity::Hmac(b"another key")).unwrap_err()
+        );
+
+        // an index of one byte 0, canonical neither as a number nor as an index
+        let index_at = 4 + 3 + 8 + 1;
+        assert_eq!(
+            DecodeError::NonCanonical,
+            decode(&resealed(patched(index_at, 0)))
+        );
+    }
+}
@@ -0,0 +1,639 @@
+use std::collections::{HashMap, HashSet};
+use std::time::{Duration, Instant};
+
+use serde::{Deserialize, Serialize};
+use tracing::{debug, warn};
+
+use crate::codec;
+use crate::dealer::DEALER_ID;
+use crate::dkg::DkgMessage;
+use crate::rpc::{Payload, WireMessage};
+
+/// Wait for an Ack before resending an acknowledged broadcast
+pub const ACK_TIMEOUT: Duration = Duration::from_millis(100);
+
+/// Transmissions of an acknowledged broadcast to a peer before giving up on it
+const MAX_TRANSMISSIONS: u32 = 5;
+
+/// Control messages of the broadcasts stronger than best effort
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum BroadcastMessage {
+    /// Acknowledged broadcast: the sender's `seq`th payload, answered with Ack
+    Request {
+        seq: u64,
+        payload: Box<Payload>,
+    },
+    Ack {
+        seq: u64,
+    },
+    /// Reliable broadcast: `origin` proposed `payload`, sent first by the origin itself and then
+    /// by every player that saw the proposal
+    Echo {
+        origin: usize,
+        payload: Box<Payload>,
+    },
+    /// Reliable broadcast: the sender saw enough echoes of `payload` to vouch for it
+    Ready {
+        origin: usize,
+        payload: Box<Payload>,
+    },
+}
+
+/// What a Broadcast wants done after a call
+#[derive(Debug, Default)]
+pub struct Step {
+    /// messages to send, with their recipient
+    pub send: Vec<(usize, WireMessage)>,
+    /// messages broadcast by others that can now be handled, with their origin
+    pub deliver: Vec<(usize, WireMessage)>,
+}
+
+impl Step {
+    fn send_all(&mut self, peers: &[usize], msg: &WireMessage) {
+        self.send.extend(peers.iter().map(|to| (*to, msg.clone())));
+    }
+}
+
+/// A way of delivering a message to every peer
+///
+/// Implementations do no IO themselves: they return the messages to send and leave it to the
+/// Player to send them over its Transport, and are fed the BroadcastMessages peers send back
+pub trait Broadcast: Send {
+    /// Start delivering `msg` to every one of `peers`
+    fn broadcast(&mut self, peers: &[usize], msg: WireMessage) -> Step;
+
+    /// Handle `msg`, carrying a BroadcastMessage, from the peer `from`
+    fn on_message(&mut self, peers: &[usize], from: usize, msg: WireMessage) -> Step;
+
+    /// Called periodically to retransmit whatever needs it
+    fn tick(&mut self) -> Step {
+        Step::default()
+    }
+}
+
+/// How strongly a message must be delivered
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Guarantee {
+    /// Sent once, lost if the transport loses it
+    BestEffort,
+    /// Resent to every peer until it acknowledges it
+    Acknowledged,
+    /// Delivered by every honest player or by none, and with the same payload
+    Reliable,
+}
+
+impl Guarantee {
+    /// The guarantee a Player broadcasts `payload` with
+    ///
+    /// DKG broadcasts decide the key so every player must see the same ones, a revealed share, a
+    /// dealing or a step of a renewal must not be lost, while a lost ping costs nothing. Revealed
+    /// shares are checked against the commitments on arrival, and need to reach a player from
+    /// only t live players where a reliable broadcast needs most of them to take part
+    pub fn of(payload: &Payload) -> Guarantee {
+        match payload {
+            Payload::Dkg(DkgMessage::DealingShare(_)) => Guarantee::BestEffort,
+            Payload::Dkg(_) => Guarantee::Reliable,
+            Payload::ReconstructShare(_)
+            | Payload::RegShare(_)
+            | Payload::RefreshAck { .. }
+            | Payload::EpochCommit { .. }
+            | Payload::Disqualify(_) => Guarantee::Acknowledged,
+            // deals, signing and refresh requests go to single players and are never broadcast,
+            // complaints must reach the dealer which takes no part in stronger broadcasts
+            Payload::Complaint { .. }
+            | Payload::ComplaintResponse(_)
+            | Payload::Ping
+            | Payload::Heartbeat
+            | Payload::ShareRequest
+            | Payload::Batch(_)
+            | Payload::Hello(_)
+            | Payload::Broadcast(_)
+            | Payload::Sequenced(..)
+            | Payload::Ack(_)
+            | Payload::Nack(_)
+            | Payload::RefreshDeal { .. }
+            | Payload::ReshareSubShare { .. }
+            | Payload::SignRequest { .. }
+            | Payload::NonceCommitment { .. }
+            | Payload::PartialSignature { .. }
+            | Payload::SignatureResult { .. }
+            | Payload::RefreshRequest
+            | Payload::RefreshResult { .. }
+            | Payload::RecoveryRequest { .. }
+            | Payload::RecoveryPiece { .. }
+            | Payload::RecoveryShare(_)
+            | Payload::DkgRequest(_)
+            | Payload::DkgResult { .. } => Guarantee::BestEffort,
+        }
+    }
+}
+
+/// Sends a message once to every peer
+#[derive(Debug, Default)]
+pub struct BestEffort;
+
+impl Broadcast for BestEffort {
+    fn broadcast(&mut self, peers: &[usize], msg: WireMessage) -> Step {
+        let mut step = Step::default();
+        step.send_all(peers, &msg);
+
+        step
+    }
+
+    fn on_message(&mut self, _peers: &[usize], _from: usize, _msg: WireMessage) -> Step {
+        Step::default()
+    }
+}
+
+struct Unacked {
+    msg: WireMessage,
+    sent_at: Instant,
+    transmissions: u32,
+}
+
+/// Numbers every message and resends it to each peer until the peer acknowledges it
+///
+/// Receivers deliver every message once however many times it is resent
+#[derive(Default)]
+pub struct Acknowledged {
+    next_seq: u64,
+    /// messages not acknowledged yet, by recipient and sequence number
+    unacked: HashMap<(usize, u64), Unacked>,
+    /// messages already delivered, by sender and sequence number
+    delivered: HashSet<(usize, u64)>,
+}
+
+impl Broadcast for Acknowledged {
+    fn broadcast(&mut self, peers: &[usize], msg: WireMessage) -> Step {
+        let seq = self.next_seq;
+        self.next_seq += 1;
+
+        let payload = Box::new(msg.payload);
+        let request = Payload::Broadcast(BroadcastMessage::Request { seq, payload });
+        let msg = WireMessage::with_version(msg.version, msg.session_id, msg.epoch, request);
+
+        let mut step = Step::default();
+        for to in peers {
+            let unacked = Unacked {
+                msg: msg.clone(),
+                sent_at: Instant::now(),
+                transmissions: 1,
+            };
+            self.unacked.insert((*to, seq), unacked);
+            step.send.push((*to, msg.clone()));
+        }
+
+        step
+    }
+
+    fn on_message(&mut self, _peers: &[usize], from: usize, msg: WireMessage) -> Step {
+        let mut step = Step::default();
+        match msg.payload {
+            Payload::Broadcast(BroadcastMessage::Request { seq, payload }) => {
+                let ack = Payload::Broadcast(BroadcastMessage::Ack { seq });
+                let ack = WireMessage::with_version(msg.version, msg.session_id, msg.epoch, ack);
+                step.send.push((from, ack));
+
+                if self.delivered.insert((from, seq)) {
+                    let msg =
+                        WireMessage::with_version(msg.version, msg.session_id, msg.epoch, *payload);
+                    step.deliver.push((from, msg));
+                }
+            }
+            Payload::Broadcast(BroadcastMessage::Ack { seq }) => {
+                self.unacked.remove(&(from, seq));
+            }
+            _ => {}
+        }
+
+        step
+    }
+
+    fn tick(&mut self) -> Step {
+        let mut step = Step::default();
+        self.unacked.retain(|(to, seq), unacked| {
+            if unacked.sent_at.elapsed() < ACK_TIMEOUT {
+                return true;
+            }
+            if unacked.transmissions >= MAX_TRANSMISSIONS {
+                warn!(to, seq, "giving up on unacknowledged broadcast");
+                return false;
+            }
+
+            debug!(to, seq, "resending broadcast");
+            step.send.push((*to, unacked.msg.clone()));
+            unacked.sent_at = Instant::now();
+            unacked.transmissions += 1;
+
+            true
+        });
+
+        step
+    }
+}
+
+/// Progress of one player's proposal in a reliable broadcast
+#[derive(Default)]
+struct Instance {
+    echoed: bool,
+    readied: bool,
+    delivered: bool,
+    /// players that echoed, and that were ready for, each payload by its encoding
+    echoes: HashMap<Vec<u8>, HashSet<usize>>,
+    readies: HashMap<Vec<u8>, HashSet<usize>>,
+}
+
+/// Whether only the dealer may send `payload`, which a reliable broadcast never carries since
+/// the dealer takes no part in one
+fn is_dealer_only(payload: &Payload) -> bool {
+    matches!(
+        payload,
+        Payload::RegShare(_)
+            | Payload::ComplaintResponse(_)
+            | Payload::RefreshRequest
+            | Payload::DkgRequest(_)
+    )
+}
+
+/// Round of a multi round protocol `payload` belongs to, so that a player's broadcasts in
+/// different rounds of a session are not taken for an equivocation
+fn round(payload: &Payload) -> u8 {
+    match payload {
+        Payload::Dkg(msg) => msg.round() as u8,
+        _ => 0,
+    }
+}
+
+/// Bracha's reliable broadcast
+///
+/// The origin sends its payload to every peer, which echo it to every other peer. A player
+/// that sees enough echoes of a payload, or enough players ready for it, is ready for it too,
+/// and delivers it once enough players are ready. With n players of which at most (n - 1) / 3
+/// are faulty, either every honest player delivers the same payload or none does, even when
+/// the origin sends different payloads to different peers
+pub struct Reliable {
+    id: usize,
+    /// proposals by session, epoch, origin and round
+    instances: HashMap<(u64, u64, usize, u8), Instance>,
+}
+
+impl Reliable {
+    /// Return a reliable broadcast taking part as the player `id`
+    pub fn new(id: usize) -> Reliable {
+        Reliable {
+            id,
+            instances: HashMap::new(),
+        }
+    }
+
+    /// Counts the vote of `from` and sends or delivers whatever the new count allows
+    fn vote(&mut self, peers: &[usize], from: usize, msg: WireMessage) -> Step {
+        let mut step = Step::default();
+        // the dealer is a peer of the transport but takes no part, neither voting nor counted
+        let peers: Vec<usize> = peers
+            .iter()
+            .copied()
+            .filter(|peer| *peer != DEALER_ID)
+            .collect();
+        let (origin, payload, is_echo) = match msg.payload {
+            Payload::Broadcast(BroadcastMessage::Echo { origin, payload }) => {
+                (origin, payload, true)
+            }
+            Payload::Broadcast(BroadcastMessage::Ready { origin, payload }) => {
+                (origin, payload, false)
+            }
+            _ => return step,
+        };
+        if from == DEALER_ID || (from != self.id && !peers.contains(&from)) {
+            debug!(from, "ignoring vote of a stranger");
+            return step;
+        }
+        // a vote delivers its payload as sent by the origin, which must be a player
+        if origin == DEALER_ID || (origin != self.id && !peers.contains(&origin)) {
+            debug!(from, origin, "ignoring vote for a proposal of no player");
+            return step;
+        }
+        if is_dealer_only(&payload) {
+            debug!(
+                from,
+                origin, "ignoring vote for a message only the dealer sends"
+            );
+            return step;
+        }
+
+        // at most f of n players are faulty with n >= 3f + 1
+        let n = peers.len() + 1;
+        let f = (n - 1) / 3;
+        let key = match codec::encode(&payload) {
+            Ok(key) => key,
+            Err(_) => return step,
+        };
+        let (version, session_id, epoch) = (msg.version, msg.session_id, msg.epoch);
+        let send_all = |step: &mut Step, msg: BroadcastMessage| {
+            let msg =
+                WireMessage::with_version(version, session_id, epoch, Payload::Broadcast(msg));
+            step.send_all(&peers, &msg);
+        };
+
+        let instance = self
+            .instances
+            .entry((session_id, epoch, origin, round(&payload)))
+            .or_default();
+        if is_echo {
+            instance.echoes.entry(key.clone()).or_default().insert(from);
+
+            // the origin's own echo is its proposal, echo the first one seen
+            if from == origin && !instance.echoed {
+                instance.echoed = true;
+                instance
+                    .echoes
+                    .entry(key.clone())
+                    .or_default()
+                    .insert(self.id);
+                let payload = payload.clone();
+                send_all(&mut step, BroadcastMessage::Echo { origin, payload });
+            }
+        } else {
+            instance
+                .readies
+                .entry(key.clone())
+                .or_default()
+                .insert(from);
+        }
+
+        let echoes = instance.echoes.get(&key).map_or(0, HashSet::len);
+        let readies = instance.readies.get(&key).map_or(0, HashSet::len);
+        if !instance.readied && (echoes >= (n + f + 2) / 2 || readies > f) {
+            instance.readied = true;
+            instance
+                .readies
+                .entry(key.clone())
+                .or_default()
+                .insert(self.id);
+            let payload = payload.clone();
+            send_all(&mut step, BroadcastMessage::Ready { origin, payload });
+        }
+
+        let readies = instance.readies.get(&key).map_or(0, HashSet::len);
+        if !instance.delivered && readies > 2 * f {
+            instance.delivered = true;
+            if origin != self.id {
+                let msg = WireMessage::with_version(version, session_id, epoch, *payload);
+                step.deliver.push((origin, msg));
+            }
+        }
+
+        step
+    }
+}
+
+impl Broadcast for Reliable {
+    fn broadcast(&mut self, peers: &[usize], msg: WireMessage) -> Step {
+        let echo = BroadcastMessage::Echo {
+            origin: self.id,
+            payload: Box::new(msg.payload),
+        };
+        let msg = WireMessage::with_version(
+            msg.version,
+            msg.session_id,
+            msg.epoch,
+            Payload::Broadcast(echo),
+        );
+
+        // our own proposal counts as the first echo
+        let mut step = self.vote(peers, self.id, msg);
+        step.deliver.clear();
+
+        step
+    }
+
+    fn on_message(&mut self, peers: &[usize], from: usize, msg: WireMessage) -> Step {
+        self.vote(peers, from, msg)
+    }
+}
+
+/// The Broadcast of each Guarantee, picking one per message by its payload
+pub struct Broadcaster {
+    best_effort: BestEffort,
+    acknowledged: Acknowledged,
+    reliable: Reliable,
+}
+
+impl Broadcaster {
+    /// Return the broadcasts of the player `id`
+    pub fn new(id: usize) -> Broadcaster {
+        Broadcaster {
+            best_effort: BestEffort,
+            acknowledged: Acknowledged::default(),
+            reliable: Reliable::new(id),
+        }
+    }
+
+    fn get(&mut self, guarantee: Guarantee) -> &mut dyn Broadcast {
+        match guarantee {
+            Guarantee::BestEffort => &mut self.best_effort,
+            Guarantee::Acknowledged => &mut self.acknowledged,
+            Guarantee::Reliable => &mut self.reliable,
+        }
+    }
+
+    /// Deliver `msg` to `peers` with the guarantee its payload calls for
+    pub fn broadcast(&mut self, peers: &[usize], msg: WireMessage) -> Step {
+        self.broadcast_with(Guarantee::of(&msg.payload), peers, msg)
+    }
+
+    /// Deliver `msg` to `peers` with `guarantee`
+    pub fn broadcast_with(
+        &mut self,
+        guarantee: Guarantee,
+        peers: &[usize],
+        msg: WireMessage,
+    ) -> Step {
+        self.get(guarantee).broadcast(peers, msg)
+    }
+
+    /// Hand `msg` from `from` to the Broadcast it belongs to
+    pub fn on_message(&mut self, peers: &[usize], from: usize, msg: WireMessage) -> Step {
+        let guarantee = match &msg.payload {
+            Payload::Broadcast(BroadcastMessage::Request { .. })
+            | Payload::Broadcast(BroadcastMessage::Ack { .. }) => Guarantee::Acknowledged,
+            Payload::Broadcast(BroadcastMessage::Echo { .. })
+            | Payload::Broadcast(BroadcastMessage::Ready { .. }) => Guarantee::Reliable,
+            _ => return Step::default(),
+        };
+
+        self.get(guarantee).on_message(peers, from, msg)
+    }
+
+    pub fn tick(&mut self) -> Step {
+        self.acknowledged.tick()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::VecDeque;
+    use std::thread;
+
+    use num_bigint::BigUint;
+
+    use crate::dealer::DEALER_ID;
+    use crate::rpc::{Payload, Share, WireMessage};
+
+    use super::{Acknowledged, Broadcast, BroadcastMessage, Reliable, Step, ACK_TIMEOUT};
+
+    fn share(value: usize) -> WireMessage {
+        let share = Share::new(BigUint::from(1_usize), BigUint::from(value));
+
+        WireMessage::new(7, 0, Payload::ReconstructShare(share))
+    }
+
+    /// Runs reliable broadcasts among players 1..=n, starting with the `sent` messages, until
+    /// no message is left, where `silent` players receive and send nothing. Returns what each
+    /// player delivered
+    fn run_reliable(
+        players: &mut [Reliable],
+        sent: Vec<(usize, usize, WireMessage)>,
+        silent: &[usize],
+    ) -> Vec<Vec<(usize, WireMessage)>> {
+        let n = players.len();
+        // the dealer is a peer of every player, as over a real transport, but sends nothing
+        let peers = |id: usize| -> Vec<usize> {
+            std::iter::once(DEALER_ID)
+                .chain(1..=n)
+                .filter(|p| *p != id)
+                .collect()
+        };
+        let mut delivered = vec![vec![]; n];
+
+        let mut queue: VecDeque<_> = sent.into();
+        while let Some((from, to, msg)) = queue.pop_front() {
+            if silent.contains(&to) {
+                continue;
+            }
+            let Step { send, deliver } = players[to - 1].on_message(&peers(to), from, msg);
+            queue.extend(send.into_iter().map(|(t, msg)| (to, t, msg)));
+            delivered[to - 1].extend(deliver);
+        }
+
+        delivered
+    }
+
+    fn players(n: usize) -> Vec<Reliable> {
+        (1..=n).map(Reliable::new).collect()
+    }
+
+    #[test]
+    fn reliable_delivers_despite_a_silent_player() {
+        let mut players = players(4);
+        let step = players[0].broadcast(&[DEALER_ID, 2, 3, 4], share(5));
+        let sent = step
+            .send
+            .into_iter()
+            .map(|(to, msg)| (1, to, msg))
+            .collect();
+        let delivered = run_reliable(&mut players, sent, &[4]);
+
+        assert!(delivered[0].is_empty());
+        assert_eq!(vec![(1, share(5))], delivered[1]);
+        assert_eq!(vec![(1, share(5))], delivered[2]);
+    }
+
+    #[test]
+    fn reliable_never_delivers_an_equivocation() {
+        // player 1 proposes one share to player 2 and another to players 3 and 4
+        let echo = |value| {
+            let payload = Box::new(share(value).payload);
+            let echo = BroadcastMessage::Echo { origin: 1, payload };
+            WireMessage::new(7, 0, Payload::Broadcast(echo))
+        };
+        let sent = vec![(1, 2, echo(5)), (1, 3, echo(6)), (1, 4, echo(6))];
+        let delivered = run_reliable(&mut players(4), sent, &[1]);
+
+        // the honest players agree on whatever they deliver
+        assert!(delivered[1..].iter().all(|d| *d == delivered[1]));
+        assert!(!delivered[1].contains(&(1, share(5))));
+    }
+
+    #[test]
+    fn reliable_drops_votes_for_the_dealer() {
+        let ready = |origin, payload| {
+            let ready = BroadcastMessage::Ready {
+                origin,
+                payload: Box::new(payload),
+            };
+            WireMessage::new(7, 0, Payload::Broadcast(ready))
+        };
+        // the dealer is a peer but no player, two readies make player 1 ready and deliver
+        let peers = [DEALER_ID, 2, 3];
+        let deliver = |origin, payload: &Payload| {
+            let mut player = Reliable::new(1);
+            [2, 3]
+                .into_iter()
+                .flat_map(|from| {
+                    let ready = ready(origin, payload.clone());
+                    player.on_message(&peers, from, ready).deliver
+                })
+                .collect::<Vec<_>>()
+        };
+
+        assert!(deliver(DEALER_ID, &share(5).payload).is_empty());
+        assert!(deliver(3, &Payload::RefreshRequest).is_empty());
+        assert_eq!(vec![(3, share(5))], deliver(3, &share(5).payload));
+    }
+
+    #[test]
+    fn reliable_counts_players_but_not_the_dealer() {
+        // 3 players tolerate no fault and 4 players one, here the silent player 4
+        for (n, silent) in [(3, vec![]), (4, vec![4])] {
+            let mut players = players(n);
+            let peers: Vec<usize> = std::iter::once(DEALER_ID).chain(2..=n).collect();
+            let step = players[0].broadcast(&peers, share(5));
+            assert!(step.send.iter().all(|(to, _)| *to != DEALER_ID));
+            let sent = step
+                .send
+                .into_iter()
+                .map(|(to, msg)| (1, to, msg))
+                .collect();
+            let delivered = run_reliable(&mut players, sent, &silent);
+
+            for id in (2..=n).filter(|id| !silent.contains(id)) {
+                assert_eq!(vec![(1, share(5))], delivered[id - 1], "{} players", n);
+            }
+        }
+
+        // among 3 players one ready is enough, unless it is the dealer's
+        let ready = BroadcastMessage::Ready {
+            origin: 2,
+            payload: Box::new(share(5).payload),
+        };
+        let ready = WireMessage::new(7, 0, Payload::Broadcast(ready));
+        let step = Reliable::new(1).on_message(&[DEALER_ID, 2, 3], DEALER_ID, ready.clone());
+        assert!(step.send.is_empty() && step.deliver.is_empty());
+        let step = Reliable::new(1).on_message(&[DEALER_ID, 2, 3], 3, ready);
+        assert_eq!(vec![(2, share(5))], step.deliver);
+    }
+
+    #[test]
+    fn acknowledged_resends_until_acked() {
+        let mut sender = Acknowledged::default();
+        let mut receiver = Acknowledged::default();
+        let step = sender.broadcast(&[2], share(5));
+        let (_, request) = step.send[0].clone();
+
+        thread::sleep(ACK_TIMEOUT);
+        let resent = sender.tick().send;
+        assert_eq!(vec![(2, request.clone())], resent);
+
+        // the receiver delivers the request once however often it arrives
+        let first = receiver.on_message(&[1], 1, request.clone());
+        let second = receiver.on_message(&[1], 1, request);
+        assert_eq!(vec![(1, share(5))], first.deliver);
+        assert!(second.deliver.is_empty());
+
+        let (_, ack) = first.send[0].clone();
+        sender.on_message(&[2], 2, ack);
+        thread::sleep(ACK_TIMEOUT);
+        assert!(sender.tick().send.is_empty());
+    }
+}
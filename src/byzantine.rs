@@ -0,0 +1,236 @@
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::thread::{self, JoinHandle};
+
+use num_bigint::BigUint;
+use tracing::{info_span, warn};
+
+use crate::broadcast::BroadcastMessage;
+use crate::rpc::{Payload, Share, ShareInfo, WireMessage, RPC};
+use crate::transport::Transport;
+
+/// Misbehavior a ByzantinePlayer injects during reconstruction
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Fault {
+    /// Never send its share
+    Withhold,
+    /// Send the same invalid share to every peer
+    WrongShare,
+    /// Send its valid share to some peers and an invalid share to others
+    Equivocate,
+    /// Resend every share it received from other players under its own id
+    Replay,
+}
+
+/// A Player that speaks the protocol but misbehaves according to a Fault
+///
+/// Used to test that honest Players blacklist faulty peers and still reconstruct the secret
+pub struct ByzantinePlayer {
+    id: usize,
+    fault: Fault,
+    rx: Receiver<RPC>,
+    transport: Box<dyn Transport>,
+    /// the dealing being attacked, with its session id and epoch
+    share_info: Option<(u64, u64, ShareInfo)>,
+}
+
+impl ByzantinePlayer {
+    pub fn spawn<T: Transport + 'static>(
+        id: usize,
+        fault: Fault,
+        transport: T,
+    ) -> (Sender<RPC>, JoinHandle<()>) {
+        let (tx, rx) = mpsc::channel::<RPC>();
+        let mut player = ByzantinePlayer {
+            id,
+            fault,
+            rx,
+            transport: Box::new(transport),
+            share_info: None,
+        };
+        let handler = thread::spawn(move || {
+            player.start();
+        });
+
+        (tx, handler)
+    }
+
+    fn start(&mut self) {
+        let span = info_span!("byzantine", id = self.id);
+        let _enter = span.enter();
+
+        while let Ok(rpc) = self.rx.recv() {
+            match rpc {
+                // accept whatever the dealer sends without verifying it
+                RPC::Wire(
+                    _,
+                    WireMessage {
+                        session_id,
+                        epoch,
+                        payload: Payload::RegShare(share_info),
+                        ..
+                    },
+                ) => self.share_info = Some((session_id, epoch, share_info)),
+                RPC::Wire(other_id, msg) if self.fault == Fault::Replay => {
+                    let payload = match msg.payload {
+                        Payload::ReconstructShare(share) => Payload::ReconstructShare(share),
+                        // shares broadcast with acknowledgements reach us as requests
+                        Payload::Broadcast(BroadcastMessage::Request { payload, .. }) => {
+                            match *payload {
+                                Payload::ReconstructShare(share) => {
+                                    Payload::ReconstructShare(share)
+                                }
+                                _ => continue,
+                            }
+                        }
+                        _ => continue,
+                    };
+                    warn!(other_id, "replaying share");
+                    self.transport
+                        .broadcast(WireMessage::new(msg.session_id, msg.epoch, payload));
+                }
+                RPC::Reconstruct(_, _) => self.misbehave(),
+                _ => {}
+            }
+        }
+    }
+
+    fn misbehave(&self) {
+        let (session_id, epoch, share_info) = match &self.share_info {
+            Some((session_id, epoch, share_info)) => (*session_id, *epoch, share_info),
+            None => return,
+        };
+        let share = share_info.share();
+        let wrong = Share::new(
+            share.index().clone(),
+            (share.value() + BigUint::from(1_usize)) % share_info.q(),
+        );
+
+        warn!(fault = ?self.fault, "misbehaving");
+        self.transport.peers().into_iter().for_each(|to| {
+            let sent = match self.fault {
+                Fault::Withhold => return,
+                Fault::WrongShare => wrong.clone(),
+                Fault::Equivocate if to % 2 == 0 => share.clone(),
+                Fault::Equivocate => wrong.clone(),
+                // replay only resends shares of other players
+                Fault::Replay => return,
+            };
+
+            let msg = WireMessage::new(session_id, epoch, Payload::ReconstructShare(sent));
+            let _ = self.transport.send(to, msg);
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::thread;
+    use std::time::Duration;
+
+    use num_bigint::BigUint;
+
+    use crate::dealer::{Dealer, DEALER_ID};
+    use crate::player::Player;
+    use crate::rpc::{ReconstructionReport, Router, RPC};
+    use crate::transport::ChannelNetwork;
+
+    use super::{ByzantinePlayer, Fault};
+
+    /// Deals to n players where the ids in `faulty` misbehave with `fault`, then asks every
+    /// player to reconstruct and returns the reports of the honest players
+    fn reconstruct_with(
+        n: usize,
+        t: usize,
+        faulty: &[usize],
+        fault: Fault,
+    ) -> Vec<ReconstructionReport> {
+        let dealer = Dealer::new(n, t, 1234).unwrap();
+        let network = ChannelNetwork::new();
+        let senders: Vec<_> = (1..=n)
+            .map(|id| {
+                let (sender, _) = if faulty.contains(&id) {
+                    ByzantinePlayer::spawn(id, fault, network.transport(id))
+                } else {
+                    Player::spawn(id, network.transport(id))
+                };
+                network.register(id, sender.clone());
+
+                sender
+            })
+            .collect();
+        dealer.propagate(&network.transport(DEALER_ID));
+
+        // wait for the players to accept their shares before reconstructing
+        thread::sleep(Duration::from_millis(100));
+
+        let mut router = Router::new();
+        senders.iter().for_each(|s| {
+            s.send(RPC::Reconstruct(dealer.session_id, router.request()))
+                .unwrap();
+        });
+
+        let mut reports = vec![];
+        while let Ok((_, report)) = router.recv_timeout(Duration::from_millis(500)) {
+            reports.push(report);
+        }
+
+        reports
+    }
+
+    /// Asserts every report recovered the secret and only rejected faulty players
+    fn assert_honest(reports: &[ReconstructionReport], t: usize, faulty: &[usize]) {
+        for report in reports {
+            assert_eq!(BigUint::from(1234_u32), *report.secret.expose());
+            assert!(report.contributors.len() >= t);
+            assert!(report.verified);
+            assert!(
+                report.rejected.iter().all(|(id, _)| faulty.contains(id)),
+                "honest player rejected in {:?}",
+                report
+            );
+        }
+    }
+
+    #[test]
+    fn withheld_shares() {
+        let reports = reconstruct_with(7, 3, &[2, 4], Fault::Withhold);
+
+        assert_eq!(5, reports.len());
+        assert_honest(&reports, 3, &[2, 4]);
+    }
+
+    #[test]
+    fn only_t_players_reveal() {
+        let reports = reconstruct_with(5, 3, &[4, 5], Fault::Withhold);
+
+        assert_eq!(3, reports.len());
+        assert_honest(&reports, 3, &[4, 5]);
+    }
+
+    #[test]
+    fn wrong_shares_are_blacklisted() {
+        let reports = reconstruct_with(7, 3, &[1, 3], Fault::WrongShare);
+
+        assert_eq!(5, reports.len());
+        assert_honest(&reports, 3, &[1, 3]);
+        for report in reports {
+            assert!(!report.contributors.contains(&1) && !report.contributors.contains(&3));
+        }
+    }
+
+    #[test]
+    fn equivocating_player_is_blacklisted() {
+        let reports = reconstruct_with(5, 3, &[5], Fault::Equivocate);
+
+        assert_eq!(4, reports.len());
+        assert_honest(&reports, 3, &[5]);
+    }
+
+    #[test]
+    fn replayed_shares_are_rejected() {
+        let reports = reconstruct_with(5, 3, &[3], Fault::Replay);
+
+        assert_eq!(4, reports.len());
+        assert_honest(&reports, 3, &[3]);
+    }
+}
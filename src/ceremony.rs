@@ -0,0 +1,334 @@
+//! Audit of a dealing ceremony from what its participants signed: the dealer's transcript and
+//! every player's acknowledgment of its share, or its complaint along with the share the dealer
+//! revealed to answer it
+//!
+//! A ceremony passes when every signature is by the key listed for its signer, p and q pass an
+//! adversarial primality test, every player of the transcript acknowledged the dealing it
+//! publishes, every complaint was answered with a share that verifies against the published
+//! commitments, and fewer than t players complained.
+
+use std::collections::{BTreeSet, HashMap};
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::sync::Arc;
+
+use ed25519_dalek::VerifyingKey;
+use num::Zero;
+use num_bigint::BigUint;
+use rand::{CryptoRng, RngCore};
+use serde::{Deserialize, Serialize};
+
+use crate::dealer::DEALER_ID;
+use crate::format::Transcript;
+use crate::primes::Primality;
+use crate::rpc::{Share, ShareInfo, Signed};
+
+/// What a player says of the share it was dealt
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "verdict", rename_all = "lowercase")]
+pub enum Verdict {
+    /// The share verified against the commitments
+    Accepted,
+    /// The share did not verify, with the share the dealer revealed to answer the complaint if
+    /// it did
+    Complained { revealed: Option<Share> },
+}
+
+/// A player's word on the dealing of a transcript, to be signed with its key
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Acknowledgment {
+    pub session: u64,
+    pub player: usize,
+    /// fingerprint of the dealing, see format::fingerprint
+    pub dealing: String,
+    #[serde(flatten)]
+    pub verdict: Verdict,
+}
+
+impl Acknowledgment {
+    /// The acknowledgment by `player` of the dealing of `transcript`
+    pub fn new(transcript: &Transcript, player: usize, verdict: Verdict) -> Acknowledgment {
+        Acknowledgment {
+            session: transcript.session,
+            player,
+            dealing: transcript.fingerprint(),
+            verdict,
+        }
+    }
+}
+
+/// One check of an audit, of the transcript, a player or the complaints
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Finding {
+    pub subject: String,
+    pub passed: bool,
+    pub detail: String,
+}
+
+/// Everything an audit checked
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Report {
+    pub session: u64,
+    pub dealing: String,
+    pub findings: Vec<Finding>,
+}
+
+impl Report {
+    /// Whether the ceremony passed every check
+    pub fn passed(&self) -> bool {
+        self.findings.iter().all(|finding| finding.passed)
+    }
+
+    fn check<S: Into<String>>(&mut self, subject: &str, passed: bool, detail: S) {
+        self.findings.push(Finding {
+            subject: subject.to_string(),
+            passed,
+            detail: detail.into(),
+        });
+    }
+}
+
+/// Audit the ceremony of `transcript`, signed by the dealer, from the signed `acks` of its
+/// players, with the verifying key of every participant by id in `keys`
+pub fn audit(
+    transcript: &Signed<Transcript>,
+    acks: &[Signed<Acknowledgment>],
+    keys: &HashMap<usize, VerifyingKey>,
+) -> Report {
+    audit_with_rng(transcript, acks, keys, &mut rand::thread_rng())
+}
+
+/// Like `audit` with the bases of the primality tests of the group drawn from `rng`
+pub fn audit_with_rng<R: RngCore + CryptoRng>(
+    transcript: &Signed<Transcript>,
+    acks: &[Signed<Acknowledgment>],
+    keys: &HashMap<usize, VerifyingKey>,
+    rng: &mut R,
+) -> Report {
+    let dealing = &transcript.payload;
+    let mut report = Report {
+        session: dealing.session,
+        dealing: dealing.fingerprint(),
+        findings: vec![],
+    };
+
+    let signed = transcript.signer_id == DEALER_ID
+        && keys
+            .get(&DEALER_ID)
+            .is_some_and(|key| transcript.verify(key));
+    report.check(
+        "transcript",
+        signed,
+        match signed {
+            true => "signed by the dealer",
+            false => "not signed by the dealer's key",
+        },
+    );
+    let agrees = dealing.threshold > 0
+        && dealing.threshold <= dealing.players
+        && dealing.commitments.len() == dealing.threshold;
+    report.check(
+        "transcript",
+        agrees,
+        format!(
+            "{} commitments for threshold {} of {} players",
+            dealing.commitments.len(),
+            dealing.threshold,
+            dealing.players
+        ),
+    );
+
+    let (p, q) = (&dealing.group.p, &dealing.group.q);
+    let primality = Primality::adversarial();
+    let group = primality.is_probable_prime(p, rng)
+        && primality.is_probable_prime(q, rng)
+        && ((p - 1_u32) % q).is_zero();
+    report.check(
+        "transcript",
+        group,
+        match group {
+            true => "p and q are prime and q divides p - 1",
+            false => "p and q are not primes with q dividing p - 1",
+        },
+    );
+
+    let params = Arc::new(dealing.params());
+    let mut complaints = 0;
+    let mut acknowledged = BTreeSet::new();
+    for signed in acks {
+        let ack = &signed.payload;
+        let subject = format!("player {}", ack.player);
+        let problem = if ack.player != signed.signer_id
+            || !keys
+                .get(&signed.signer_id)
+                .is_some_and(|key| signed.verify(key))
+        {
+            Some("acknowledgment not signed by the player's key".to_string())
+        } else if ack.player == DEALER_ID || ack.player > dealing.players {
+            Some(format!("not one of the {} players", dealing.players))
+        } else if ack.session != dealing.session || ack.dealing != report.dealing {
+            Some(format!("acknowledged another dealing, {}", ack.dealing))
+        } else if !acknowledged.insert(ack.player) {
+            Some("acknowledged twice".to_string())
+        } else {
+            None
+        };
+        if let Some(problem) = problem {
+            report.check(&subject, false, problem);
+            continue;
+        }
+
+        match &ack.verdict {
+            Verdict::Accepted => report.check(&subject, true, "accepted its share"),
+            Verdict::Complained { revealed } => {
+                complaints += 1;
+                let (passed, detail) = match revealed {
+                    None => (false, "complained and the dealer did not answer"),
+                    Some(share)
+                        if *share.index() == BigUint::from(ack.player)
+                            && ShareInfo::with_params(share.clone(), params.clone()).verify() =>
+                    {
+                        (
+                            true,
+                            "complained and the dealer revealed a share that verifies",
+                        )
+                    }
+                    Some(_) => (
+                        false,
+                        "complained and the dealer revealed a share that does not verify",
+                    ),
+                };
+                report.check(&subject, passed, detail);
+            }
+        }
+    }
+
+    for player in (1..=dealing.players).filter(|id| !acknowledged.contains(id)) {
+        report.check(&format!("player {}", player), false, "no acknowledgment");
+    }
+    report.check(
+        "complaints",
+        complaints < dealing.threshold,
+        format!(
+            "{} of {} players complained, {} disqualify the dealer",
+            complaints, dealing.players, dealing.threshold
+        ),
+    );
+
+    report
+}
+
+/// Read the verifying keys of a ceremony's participants: a JSON object from each id, 0 for the
+/// dealer, to the hex of its ed25519 public key
+pub fn load_keys<P: AsRef<Path>>(path: P) -> io::Result<HashMap<usize, VerifyingKey>> {
+    let invalid = |msg: String| io::Error::new(io::ErrorKind::InvalidData, msg);
+    let path = path.as_ref();
+    let hexes: HashMap<usize, String> = serde_json::from_slice(&fs::read(path)?)
+        .map_err(|err| invalid(format!("{}: {}", path.display(), err)))?;
+
+    hexes
+        .into_iter()
+        .map(|(id, key)| {
+            let bytes: [u8; 32] = hex::decode(&key)
+                .ok()
+                .and_then(|bytes| bytes.try_into().ok())
+                .ok_or_else(|| invalid(format!("key of {} is not 32 bytes of hex", id)))?;
+            let key = VerifyingKey::from_bytes(&bytes)
+                .map_err(|_| invalid(format!("key of {} is not an ed25519 key", id)))?;
+
+            Ok((id, key))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use ed25519_dalek::SigningKey;
+
+    use crate::dealer::{Dealer, DEALER_ID};
+    use crate::format::Transcript;
+    use crate::rpc::{generate_signing_key, Share, Signed};
+
+    use super::{audit, Acknowledgment, Verdict};
+
+    /// A dealing of 3 players with threshold 2, its signed transcript and the keys of everyone
+    fn ceremony() -> (Dealer, Signed<Transcript>, Vec<SigningKey>) {
+        let dealer = Dealer::new(3, 2, 1234).unwrap();
+        let keys: Vec<SigningKey> = (0..=3).map(|_| generate_signing_key()).collect();
+        let transcript = Transcript::new(dealer.session_id, dealer.n, &dealer.params());
+
+        let signed = Signed::sign(transcript, DEALER_ID, &keys[DEALER_ID]);
+
+        (dealer, signed, keys)
+    }
+
+    fn ack(
+        transcript: &Signed<Transcript>,
+        keys: &[SigningKey],
+        player: usize,
+        verdict: Verdict,
+    ) -> Signed<Acknowledgment> {
+        let ack = Acknowledgment::new(&transcript.payload, player, verdict);
+
+        Signed::sign(ack, player, &keys[player])
+    }
+
+    #[test]
+    fn ceremony_passes_with_every_acknowledgment() {
+        let (dealer, transcript, keys) = ceremony();
+        let verifying: HashMap<_, _> = keys
+            .iter()
+            .enumerate()
+            .map(|(id, key)| (id, key.verifying_key()))
+            .collect();
+        let revealed = Some(dealer.shares[2].clone());
+        let acks = vec![
+            ack(&transcript, &keys, 1, Verdict::Accepted),
+            ack(&transcript, &keys, 2, Verdict::Accepted),
+            ack(&transcript, &keys, 3, Verdict::Complained { revealed }),
+        ];
+
+        let report = audit(&transcript, &acks, &verifying);
+        assert!(report.passed(), "{:?}", report.findings);
+
+        // a player missing fails the ceremony, and so does an acknowledgment signed by another
+        let report = audit(&transcript, &acks[..2], &verifying);
+        assert!(!report.passed());
+        let mut forged = acks.clone();
+        forged[0] = Signed::sign(forged[0].payload.clone(), 1, &keys[2]);
+        assert!(!audit(&transcript, &forged, &verifying).passed());
+    }
+
+    #[test]
+    fn ceremony_fails_on_an_invalid_reveal() {
+        let (dealer, transcript, keys) = ceremony();
+        let verifying: HashMap<_, _> = keys
+            .iter()
+            .enumerate()
+            .map(|(id, key)| (id, key.verifying_key()))
+            .collect();
+        let share = &dealer.shares[0];
+        let forged = Share::new(share.index().clone(), share.value() + 1_u32);
+        let acks = vec![
+            ack(
+                &transcript,
+                &keys,
+                1,
+                Verdict::Complained {
+                    revealed: Some(forged),
+                },
+            ),
+            ack(&transcript, &keys, 2, Verdict::Accepted),
+            ack(&transcript, &keys, 3, Verdict::Accepted),
+        ];
+
+        let report = audit(&transcript, &acks, &verifying);
+        assert!(!report.passed());
+        let failed: Vec<_> = report.findings.iter().filter(|f| !f.passed).collect();
+        assert_eq!(1, failed.len());
+        assert_eq!("player 1", failed[0].subject);
+    }
+}
@@ -0,0 +1,559 @@
+#[cfg(feature = "serde")]
+use std::fmt;
+#[cfg(feature = "network")]
+use std::io::{self, Read, Write};
+
+use num::Zero;
+use num_bigint::BigUint;
+#[cfg(feature = "network")]
+use serde::de::DeserializeOwned;
+#[cfg(feature = "serde")]
+use serde::de::{self, SeqAccess, Visitor};
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+#[cfg(feature = "network")]
+use crate::rpc::WireMessage;
+
+/// Largest frame a peer may send, guards against allocating for a corrupt length prefix
+#[cfg(feature = "network")]
+pub(crate) const MAX_FRAME_LEN: usize = 16 * 1024 * 1024;
+
+/// Encoding of WireMessages sent between processes
+///
+/// Any Transport can be built over a Codec by sending what `encode` returns as a frame, see
+/// `write_frame` and `read_frame`. Both ends of a connection must use the same Codec
+#[cfg(feature = "network")]
+pub trait Codec: Send + Sync {
+    fn encode(&self, msg: &WireMessage) -> io::Result<Vec<u8>>;
+
+    fn decode(&self, frame: &[u8]) -> io::Result<WireMessage>;
+}
+
+/// CBOR, the default encoding of network transports
+#[cfg(feature = "network")]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Cbor;
+
+#[cfg(feature = "network")]
+impl Codec for Cbor {
+    fn encode(&self, msg: &WireMessage) -> io::Result<Vec<u8>> {
+        encode(msg)
+    }
+
+    fn decode(&self, frame: &[u8]) -> io::Result<WireMessage> {
+        decode(frame)
+    }
+}
+
+/// bincode, a faster and more compact encoding for when every player runs this crate
+///
+/// Decoding never allocates more than MAX_FRAME_LEN, however large the lengths a corrupt frame
+/// claims for a commitment vector
+#[cfg(feature = "bincode")]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Bincode;
+
+#[cfg(feature = "bincode")]
+impl Bincode {
+    fn options() -> impl bincode::Options {
+        use bincode::Options;
+
+        bincode::DefaultOptions::new().with_limit(MAX_FRAME_LEN as u64)
+    }
+}
+
+#[cfg(feature = "bincode")]
+impl Codec for Bincode {
+    fn encode(&self, msg: &WireMessage) -> io::Result<Vec<u8>> {
+        use bincode::Options;
+
+        Bincode::options()
+            .serialize(msg)
+            .map_err(|err| io::Error::new(io::ErrorKind::InvalidInput, err))
+    }
+
+    fn decode(&self, frame: &[u8]) -> io::Result<WireMessage> {
+        use bincode::Options;
+
+        Bincode::options()
+            .deserialize(frame)
+            .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))
+    }
+}
+
+/// Frames shorter than this are sent uncompressed by Zstd
+#[cfg(feature = "zstd")]
+pub const MIN_COMPRESSED_LEN: usize = 512;
+
+/// Wraps another Codec, compressing its frames of at least MIN_COMPRESSED_LEN bytes with zstd
+///
+/// ShareInfo messages of high thresholds over big moduli carry kilobytes of commitments to
+/// every player. A frame starts with a byte telling whether the rest is compressed, so short
+/// messages are not slowed down, and decompressing never produces more than MAX_FRAME_LEN
+#[cfg(feature = "zstd")]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Zstd<C> {
+    inner: C,
+    level: i32,
+}
+
+#[cfg(feature = "zstd")]
+impl<C: Codec> Zstd<C> {
+    const RAW: u8 = 0;
+    const COMPRESSED: u8 = 1;
+
+    /// Return a Codec compressing the frames of `inner` at zstd's default level
+    pub fn new(inner: C) -> Zstd<C> {
+        Zstd::with_level(inner, zstd::DEFAULT_COMPRESSION_LEVEL)
+    }
+
+    /// Return a Codec compressing the frames of `inner` at `level`, from 1 (fastest) to 22
+    pub fn with_level(inner: C, level: i32) -> Zstd<C> {
+        Zstd { inner, level }
+    }
+}
+
+#[cfg(feature = "zstd")]
+impl<C: Codec> Codec for Zstd<C> {
+    fn encode(&self, msg: &WireMessage) -> io::Result<Vec<u8>> {
+        let frame = self.inner.encode(msg)?;
+        if frame.len() < MIN_COMPRESSED_LEN {
+            return Ok([&[Zstd::<C>::RAW], &frame[..]].concat());
+        }
+
+        let compressed = zstd::bulk::compress(&frame, self.level)?;
+        Ok([&[Zstd::<C>::COMPRESSED], &compressed[..]].concat())
+    }
+
+    fn decode(&self, frame: &[u8]) -> io::Result<WireMessage> {
+        match frame.split_first() {
+            Some((&Zstd::<C>::RAW, rest)) => self.inner.decode(rest),
+            Some((&Zstd::<C>::COMPRESSED, rest)) => {
+                let frame = zstd::bulk::decompress(rest, MAX_FRAME_LEN)?;
+                self.inner.decode(&frame)
+            }
+            _ => Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "unknown compression",
+            )),
+        }
+    }
+}
+
+/// Write `frame` prefixed by its length as a big endian u32
+#[cfg(feature = "network")]
+pub fn write_frame<W: Write>(w: &mut W, frame: &[u8]) -> io::Result<()> {
+    let len = u32::try_from(frame.len())
+        .ok()
+        .filter(|len| *len as usize <= MAX_FRAME_LEN)
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "frame too large"))?;
+
+    let mut buf = Vec::with_capacity(4 + frame.len());
+    buf.extend_from_slice(&len.to_be_bytes());
+    buf.extend_from_slice(frame);
+
+    w.write_all(&buf)
+}
+
+/// Read a frame written by `write_frame`, refusing frames larger than MAX_FRAME_LEN
+#[cfg(feature = "network")]
+pub fn read_frame<R: Read>(r: &mut R) -> io::Result<Vec<u8>> {
+    let mut len = [0; 4];
+    r.read_exact(&mut len)?;
+
+    let len = u32::from_be_bytes(len) as usize;
+    if len > MAX_FRAME_LEN {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "frame too large",
+        ));
+    }
+
+    let mut frame = vec![0; len];
+    r.read_exact(&mut frame)?;
+
+    Ok(frame)
+}
+
+/// Encode `value` as CBOR, the binary framing network transports send messages in
+#[cfg(feature = "network")]
+pub fn encode<T: Serialize>(value: &T) -> io::Result<Vec<u8>> {
+    let mut buf = vec![];
+    ciborium::into_writer(value, &mut buf).map_err(io::Error::other)?;
+
+    Ok(buf)
+}
+
+/// Decode a value encoded by `encode`
+#[cfg(feature = "network")]
+pub fn decode<T: DeserializeOwned>(bytes: &[u8]) -> io::Result<T> {
+    ciborium::from_reader(bytes).map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))
+}
+
+/// Serde adapter encoding a BigUint canonically in binary formats
+///
+/// Binary formats such as CBOR get the big endian bytes of the number without leading zeros, so
+/// zero is the empty byte string and every number has exactly one encoding. Human readable
+/// formats such as JSON keep num-bigint's own encoding
+#[cfg(feature = "serde")]
+pub(crate) mod biguint {
+    use num_bigint::BigUint;
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+    use super::{to_bytes, BytesVisitor};
+
+    pub fn serialize<S: Serializer>(n: &BigUint, serializer: S) -> Result<S::Ok, S::Error> {
+        if serializer.is_human_readable() {
+            return n.serialize(serializer);
+        }
+
+        serializer.serialize_bytes(&to_bytes(n))
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<BigUint, D::Error> {
+        if deserializer.is_human_readable() {
+            return BigUint::deserialize(deserializer);
+        }
+
+        deserializer.deserialize_byte_buf(BytesVisitor)
+    }
+}
+
+/// Serde adapter encoding every BigUint of a Vec like `biguint`
+#[cfg(feature = "serde")]
+pub(crate) mod biguints {
+    use num_bigint::BigUint;
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+    use super::{to_bytes, Bytes, Canonical};
+
+    pub fn serialize<S: Serializer>(ns: &[BigUint], serializer: S) -> Result<S::Ok, S::Error> {
+        if serializer.is_human_readable() {
+            return ns.serialize(serializer);
+        }
+
+        serializer.collect_seq(ns.iter().map(|n| Bytes(to_bytes(n))))
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Vec<BigUint>, D::Error> {
+        if deserializer.is_human_readable() {
+            return Vec::deserialize(deserializer);
+        }
+
+        Ok(Vec::<Canonical>::deserialize(deserializer)?
+            .into_iter()
+            .map(|n| n.0)
+            .collect())
+    }
+}
+
+/// Serde adapter encoding a BigUint that may be missing like `biguint`
+#[cfg(feature = "network")]
+pub(crate) mod option_biguint {
+    use num_bigint::BigUint;
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+    use super::{to_bytes, Bytes, Canonical};
+
+    pub fn serialize<S: Serializer>(n: &Option<BigUint>, serializer: S) -> Result<S::Ok, S::Error> {
+        if serializer.is_human_readable() {
+            return n.serialize(serializer);
+        }
+
+        n.as_ref().map(|n| Bytes(to_bytes(n))).serialize(serializer)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Option<BigUint>, D::Error> {
+        if deserializer.is_human_readable() {
+            return Option::deserialize(deserializer);
+        }
+
+        Ok(Option::<Canonical>::deserialize(deserializer)?.map(|n| n.0))
+    }
+}
+
+/// Serde adapter for BigUints of downstream types, `#[serde(with = "rust_vss::codec::bytes")]`
+///
+/// Unlike `biguint`, a number is its canonical bytes in every format: a byte string in binary
+/// formats and the base64 of it in human readable ones, as numbers are in share files
+#[cfg(feature = "serde")]
+pub mod bytes {
+    use base64::engine::general_purpose::STANDARD;
+    use base64::Engine;
+    use num_bigint::BigUint;
+    use serde::de::Error;
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    use super::{from_bytes, to_bytes, BytesVisitor};
+
+    pub fn serialize<S: Serializer>(n: &BigUint, serializer: S) -> Result<S::Ok, S::Error> {
+        if serializer.is_human_readable() {
+            return serializer.serialize_str(&STANDARD.encode(to_bytes(n)));
+        }
+
+        serializer.serialize_bytes(&to_bytes(n))
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<BigUint, D::Error> {
+        if !deserializer.is_human_readable() {
+            return deserializer.deserialize_byte_buf(BytesVisitor);
+        }
+
+        let bytes = STANDARD
+            .decode(String::deserialize(deserializer)?)
+            .map_err(D::Error::custom)?;
+        from_bytes(&bytes)
+            .ok_or_else(|| D::Error::custom("non-canonical BigUint with leading zeros"))
+    }
+
+    /// The same for every BigUint of a Vec, `#[serde(with = "rust_vss::codec::bytes::vec")]`
+    pub mod vec {
+        use num_bigint::BigUint;
+        use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+        struct Ref<'a>(&'a BigUint);
+
+        impl Serialize for Ref<'_> {
+            fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+                super::serialize(self.0, serializer)
+            }
+        }
+
+        struct Owned(BigUint);
+
+        impl<'de> Deserialize<'de> for Owned {
+            fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Owned, D::Error> {
+                super::deserialize(deserializer).map(Owned)
+            }
+        }
+
+        pub fn serialize<S: Serializer>(ns: &[BigUint], serializer: S) -> Result<S::Ok, S::Error> {
+            serializer.collect_seq(ns.iter().map(Ref))
+        }
+
+        pub fn deserialize<'de, D: Deserializer<'de>>(
+            deserializer: D,
+        ) -> Result<Vec<BigUint>, D::Error> {
+            Ok(Vec::<Owned>::deserialize(deserializer)?
+                .into_iter()
+                .map(|n| n.0)
+                .collect())
+        }
+    }
+}
+
+/// Canonical bytes of `n`: big endian without leading zeros, zero being empty
+pub(crate) fn to_bytes(n: &BigUint) -> Vec<u8> {
+    if n.is_zero() {
+        vec![]
+    } else {
+        n.to_bytes_be()
+    }
+}
+
+/// Parse canonical bytes written by `to_bytes`, None if they have leading zeros
+pub(crate) fn from_bytes(bytes: &[u8]) -> Option<BigUint> {
+    if bytes.first() == Some(&0) {
+        return None;
+    }
+
+    Some(BigUint::from_bytes_be(bytes))
+}
+
+/// Serializes as a byte string rather than a sequence of integers
+#[cfg(feature = "serde")]
+struct Bytes(Vec<u8>);
+
+#[cfg(feature = "serde")]
+impl Serialize for Bytes {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_bytes(&self.0)
+    }
+}
+
+/// A BigUint decoded from its canonical byte string
+#[cfg(feature = "serde")]
+struct Canonical(BigUint);
+
+#[cfg(feature = "serde")]
+impl<'de> Deserialize<'de> for Canonical {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Canonical, D::Error> {
+        deserializer
+            .deserialize_byte_buf(BytesVisitor)
+            .map(Canonical)
+    }
+}
+
+#[cfg(feature = "serde")]
+struct BytesVisitor;
+
+#[cfg(feature = "serde")]
+impl<'de> Visitor<'de> for BytesVisitor {
+    type Value = BigUint;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("big endian bytes without leading zeros")
+    }
+
+    fn visit_bytes<E: de::Error>(self, bytes: &[u8]) -> Result<BigUint, E> {
+        from_bytes(bytes).ok_or_else(|| E::custom("non-canonical BigUint with leading zeros"))
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<BigUint, A::Error> {
+        let mut bytes = vec![];
+        while let Some(byte) = seq.next_element()? {
+            bytes.push(byte);
+        }
+
+        self.visit_bytes(&bytes)
+    }
+}
+
+#[cfg(all(test, feature = "network"))]
+mod tests {
+    use std::io::Cursor;
+
+    use num_bigint::ToBigUint;
+
+    use crate::rpc::{Metadata, Payload, Share, ShareInfo, WireMessage};
+
+    use super::{decode, encode, read_frame, write_frame};
+
+    fn reg_share() -> WireMessage {
+        let share = Share::new(2.to_biguint().unwrap(), 0.to_biguint().unwrap());
+        // unset fields are encoded too, for codecs that read fields by position
+        let metadata = Metadata {
+            custodian: Some("alice".to_string()),
+            labels: [("site".to_string(), "lab".to_string())].into(),
+            ..Metadata::default()
+        };
+
+        WireMessage::new(
+            7,
+            1,
+            Payload::RegShare(
+                ShareInfo::new(
+                    share,
+                    3.to_biguint().unwrap(),
+                    vec![5.to_biguint().unwrap(), 1.to_biguint().unwrap()],
+                    11.to_biguint().unwrap(),
+                    5.to_biguint().unwrap(),
+                    2,
+                )
+                .with_metadata(metadata),
+            ),
+        )
+    }
+
+    #[test]
+    fn frame_roundtrip() {
+        let mut buf = vec![];
+        write_frame(&mut buf, b"hello").unwrap();
+        write_frame(&mut buf, b"").unwrap();
+
+        let mut r = Cursor::new(buf);
+        assert_eq!(b"hello".to_vec(), read_frame(&mut r).unwrap());
+        assert!(read_frame(&mut r).unwrap().is_empty());
+        assert!(read_frame(&mut r).is_err());
+    }
+
+    #[test]
+    fn cbor_roundtrip() {
+        let msg = reg_share();
+
+        assert_eq!(msg, decode(&encode(&msg).unwrap()).unwrap());
+    }
+
+    #[cfg(feature = "bincode")]
+    #[test]
+    fn bincode_roundtrip() {
+        use super::{Bincode, Codec};
+
+        let msg = reg_share();
+        let encoded = Bincode.encode(&msg).unwrap();
+        assert_eq!(msg, Bincode.decode(&encoded).unwrap());
+
+        // p, q and t, the byte strings [11] and [5] and the varint 2, are followed by the 20 bytes
+        // of the metadata and the 3 of the validity
+        let at = encoded.len() - 28;
+        assert_eq!(&[1, 11, 1, 5, 2], &encoded[at..at + 5]);
+
+        // p claiming to be longer than any frame is refused before allocating for it
+        let mut corrupt = encoded[..at].to_vec();
+        corrupt.extend_from_slice(&[0xfd, 0, 0, 0, 0, 0, 1, 0, 0, 11]);
+        assert!(Bincode.decode(&corrupt).is_err());
+    }
+
+    #[cfg(feature = "zstd")]
+    #[test]
+    fn zstd_roundtrip() {
+        use num_bigint::BigUint;
+
+        use super::{Cbor, Codec, Zstd, MIN_COMPRESSED_LEN};
+
+        let codec = Zstd::new(Cbor);
+        let msg = reg_share();
+        let encoded = codec.encode(&msg).unwrap();
+        assert_eq!(0, encoded[0]);
+        assert_eq!(msg, codec.decode(&encoded).unwrap());
+
+        // 64 commitments of the same 1024 bit number
+        let share = Share::new(2.to_biguint().unwrap(), 0.to_biguint().unwrap());
+        let c = (BigUint::from(1_usize) << 1024) - BigUint::from(1_usize);
+        let info = ShareInfo::new(share, c.clone(), vec![c.clone(); 64], c.clone(), c, 64);
+        let msg = WireMessage::new(7, 1, Payload::RegShare(info));
+        let encoded = codec.encode(&msg).unwrap();
+        assert_eq!(1, encoded[0]);
+        assert!(encoded.len() < MIN_COMPRESSED_LEN);
+        assert_eq!(msg, codec.decode(&encoded).unwrap());
+
+        assert!(codec.decode(&[2, 0]).is_err());
+    }
+
+    #[test]
+    fn biguints_are_canonical_byte_strings() {
+        let share = Share::new(2.to_biguint().unwrap(), 0x0102.to_biguint().unwrap());
+
+        // a map of two byte strings: h'02' and h'0102'
+        let mut expected = vec![0xa2, 0x65];
+        expected.extend_from_slice(b"index");
+        expected.extend_from_slice(&[0x41, 0x02, 0x65]);
+        expected.extend_from_slice(b"value");
+        expected.extend_from_slice(&[0x42, 0x01, 0x02]);
+        assert_eq!(expected, encode(&share).unwrap());
+
+        // the same number with a leading zero is rejected
+        let len = expected.len();
+        expected[len - 3] = 0x43;
+        expected.insert(len - 2, 0x00);
+        assert!(decode::<Share>(&expected).is_err());
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn public_params_are_canonical_bytes_in_every_format() {
+        use crate::rpc::PublicParams;
+
+        let params = PublicParams {
+            g: 3.to_biguint().unwrap(),
+            commitments: vec![0.to_biguint().unwrap(), 0x0102.to_biguint().unwrap()],
+            p: 11.to_biguint().unwrap(),
+            q: 5.to_biguint().unwrap(),
+            t: 2,
+        };
+
+        let json = serde_json::to_value(&params).unwrap();
+        assert_eq!(serde_json::json!(["", "AQI="]), json["commitments"]);
+        assert_eq!(params, serde_json::from_value(json).unwrap());
+        assert_eq!(params, decode(&encode(&params).unwrap()).unwrap());
+        assert!(serde_json::from_str::<PublicParams>(
+            r#"{"g":"AAM=","commitments":[],"p":"Cw==","q":"BQ==","t":2}"#
+        )
+        .is_err());
+    }
+}
@@ -0,0 +1,362 @@
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::io;
+use std::net::{SocketAddr, TcpListener};
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::Sender;
+use std::sync::Arc;
+use std::thread::JoinHandle;
+use std::time::Duration;
+
+use serde::Deserialize;
+
+#[cfg(feature = "bincode")]
+use crate::codec::Bincode;
+use crate::codec::{Cbor, Codec};
+use crate::dealer::DEALER_ID;
+use crate::rpc::RPC;
+use crate::transport::address_book::{AddressBook, Peer, Redial};
+#[cfg(feature = "noise")]
+use crate::transport::noise::{NoiseConfig, NoiseKeypair};
+use crate::transport::tcp::TcpTransport;
+
+/// The players of a deployment and how they reach each other, read from a TOML file
+///
+/// ```toml
+/// threshold = 2
+///
+/// [group]
+/// bits = 64
+///
+/// [transport]
+/// codec = "cbor"
+/// redial_attempts = 5
+///
+/// [coordinator]
+/// address = "10.0.0.9:7000"
+///
+/// [[players]]
+/// id = 1
+/// address = "10.0.0.1:7001"
+/// public_key = "9f1c..."
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Config {
+    /// Number of players needed to reconstruct what is dealt to them
+    pub threshold: Option<usize>,
+    #[serde(default)]
+    pub group: GroupConfig,
+    #[serde(default)]
+    pub transport: TransportConfig,
+    /// Where players answer the requests of a coordinator, such as `vss refresh`
+    pub coordinator: Option<CoordinatorConfig>,
+    pub players: Vec<PlayerConfig>,
+}
+
+/// One player of a deployment
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct PlayerConfig {
+    pub id: usize,
+    pub address: SocketAddr,
+    /// Hex of the player's static Noise key, needed when the transport uses Noise
+    pub public_key: Option<String>,
+}
+
+/// The coordinator of a deployment, which sends to players as the dealer
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct CoordinatorConfig {
+    pub address: SocketAddr,
+    /// Hex of the coordinator's static Noise key, needed when the transport uses Noise
+    pub public_key: Option<String>,
+}
+
+/// Parameters of the group dealings are made in
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct GroupConfig {
+    /// Bits of the prime the polynomial is computed over
+    pub bits: usize,
+}
+
+impl Default for GroupConfig {
+    fn default() -> GroupConfig {
+        GroupConfig { bits: 32 }
+    }
+}
+
+/// Encoding of messages over TCP
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CodecChoice {
+    #[default]
+    Cbor,
+    /// Needs the `bincode` feature
+    Bincode,
+}
+
+/// How players connect to each other, always over TCP
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct TransportConfig {
+    #[serde(default)]
+    pub codec: CodecChoice,
+    /// File holding the hex of this node's private static Noise key; connections go over Noise
+    /// when set, with every player's `public_key` listed, and always encode with CBOR. Needs the
+    /// `noise` feature
+    pub noise_key_file: Option<PathBuf>,
+    /// Attempts made to deliver a message before giving up on a peer
+    pub redial_attempts: Option<u32>,
+    /// Milliseconds before redialing a peer, doubled after every failed attempt
+    pub redial_backoff_ms: Option<u64>,
+}
+
+fn invalid(msg: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg)
+}
+
+impl Config {
+    /// Read and check the config file at `path`, naming it in any error
+    pub fn load<P: AsRef<Path>>(path: P) -> io::Result<Config> {
+        let path = path.as_ref();
+
+        fs::read_to_string(path)
+            .and_then(|toml| Config::parse(&toml))
+            .map_err(|err| io::Error::new(err.kind(), format!("{}: {}", path.display(), err)))
+    }
+
+    /// Parse and check a config
+    pub fn parse(toml: &str) -> io::Result<Config> {
+        let config: Config = toml::from_str(toml).map_err(|err| invalid(err.to_string()))?;
+
+        let mut ids = HashSet::new();
+        for player in &config.players {
+            if player.id == DEALER_ID {
+                return Err(invalid(format!("player id {} is the dealer's", DEALER_ID)));
+            }
+            if !ids.insert(player.id) {
+                return Err(invalid(format!("player {} listed twice", player.id)));
+            }
+            if let Some(key) = &player.public_key {
+                hex::decode(key).map_err(|err| {
+                    invalid(format!("public key of player {}: {}", player.id, err))
+                })?;
+            }
+        }
+        if let Some(key) = config
+            .coordinator
+            .as_ref()
+            .and_then(|c| c.public_key.as_ref())
+        {
+            hex::decode(key)
+                .map_err(|err| invalid(format!("public key of the coordinator: {}", err)))?;
+        }
+        match config.threshold {
+            Some(t) if t == 0 || t > config.players.len() => Err(invalid(format!(
+                "threshold {} is not between 1 and the {} players",
+                t,
+                config.players.len()
+            ))),
+            _ => Ok(config),
+        }
+    }
+
+    /// Address of every player by id
+    pub fn addresses(&self) -> HashMap<usize, SocketAddr> {
+        self.players
+            .iter()
+            .map(|player| (player.id, player.address))
+            .collect()
+    }
+
+    /// Every player's address and public key, redialed as the transport options say
+    pub fn address_book(&self) -> AddressBook {
+        let mut book = AddressBook::new().with_redial(self.redial());
+        for player in &self.players {
+            // checked to be hex when parsed
+            let public_key = player
+                .public_key
+                .as_ref()
+                .map(|key| hex::decode(key).unwrap_or_default());
+            book.insert(
+                player.id,
+                Peer {
+                    address: player.address,
+                    public_key,
+                },
+            );
+        }
+
+        book
+    }
+
+    fn redial(&self) -> Redial {
+        let default = Redial::default();
+
+        Redial {
+            attempts: self.transport.redial_attempts.unwrap_or(default.attempts),
+            backoff: self
+                .transport
+                .redial_backoff_ms
+                .map_or(default.backoff, Duration::from_millis),
+        }
+    }
+
+    fn codec(&self) -> io::Result<Arc<dyn Codec>> {
+        match self.transport.codec {
+            CodecChoice::Cbor => Ok(Arc::new(Cbor)),
+            #[cfg(feature = "bincode")]
+            CodecChoice::Bincode => Ok(Arc::new(Bincode)),
+            #[cfg(not(feature = "bincode"))]
+            CodecChoice::Bincode => Err(io::Error::new(
+                io::ErrorKind::Unsupported,
+                "the bincode codec needs the bincode feature",
+            )),
+        }
+    }
+
+    #[cfg(feature = "noise")]
+    fn noise(&self) -> io::Result<Option<Arc<NoiseConfig>>> {
+        let key_file = match &self.transport.noise_key_file {
+            Some(key_file) => key_file,
+            None => return Ok(None),
+        };
+        let private = hex::decode(fs::read_to_string(key_file)?.trim())
+            .map_err(|err| invalid(format!("{}: {}", key_file.display(), err)))?;
+
+        let book = self.address_book();
+        let mut players = book.public_keys();
+        if let Some(id) = book.ids().into_iter().find(|id| !players.contains_key(id)) {
+            return Err(invalid(format!("no public key for player {}", id)));
+        }
+        if let Some(key) = self
+            .coordinator
+            .as_ref()
+            .and_then(|c| c.public_key.as_ref())
+        {
+            players.insert(DEALER_ID, hex::decode(key).unwrap_or_default());
+        }
+
+        Ok(Some(Arc::new(NoiseConfig::new(
+            NoiseKeypair::from_private(&private)?,
+            players,
+        ))))
+    }
+
+    #[cfg(not(feature = "noise"))]
+    fn noise(&self) -> io::Result<Option<()>> {
+        match self.transport.noise_key_file {
+            Some(_) => Err(io::Error::new(
+                io::ErrorKind::Unsupported,
+                "Noise connections need the noise feature",
+            )),
+            None => Ok(None),
+        }
+    }
+
+    /// The Transport `id` sends over, as the transport options say
+    pub fn transport(&self, id: usize) -> io::Result<TcpTransport> {
+        self.transport_to(id, self.address_book())
+    }
+
+    /// The Transport `id` answers the coordinator over, reaching it as the dealer
+    pub fn coordinator_transport(&self, id: usize) -> io::Result<TcpTransport> {
+        let coordinator = self.coordinator.as_ref().ok_or_else(|| {
+            io::Error::new(io::ErrorKind::NotFound, "no coordinator in the config")
+        })?;
+
+        let book = AddressBook::new().with_redial(self.redial()).with_peer(
+            DEALER_ID,
+            coordinator.address,
+            None,
+        );
+
+        self.transport_to(id, book)
+    }
+
+    fn transport_to(&self, id: usize, book: AddressBook) -> io::Result<TcpTransport> {
+        #[cfg(feature = "noise")]
+        if let Some(noise) = self.noise()? {
+            return Ok(TcpTransport::with_noise(id, book, noise));
+        }
+        #[cfg(not(feature = "noise"))]
+        self.noise()?;
+
+        Ok(TcpTransport::new(id, book).with_codec(self.codec()?))
+    }
+
+    /// Accept connections on `listener` as the transport options say, from the players of the
+    /// config and the dealer only, and deliver every message received into `inbox`
+    pub fn listen(&self, listener: TcpListener, inbox: Sender<RPC>) -> io::Result<JoinHandle<()>> {
+        #[cfg(feature = "noise")]
+        if let Some(noise) = self.noise()? {
+            return Ok(TcpTransport::listen_noise(listener, noise, inbox));
+        }
+        #[cfg(not(feature = "noise"))]
+        self.noise()?;
+
+        Ok(TcpTransport::listen_known(
+            listener,
+            Arc::new(self.address_book()),
+            self.codec()?,
+            inbox,
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{CodecChoice, Config};
+
+    #[test]
+    fn parses_and_checks_config() {
+        let config = Config::parse(
+            r#"
+            threshold = 2
+
+            [transport]
+            codec = "cbor"
+            redial_attempts = 5
+
+            [coordinator]
+            address = "127.0.0.1:7000"
+
+            [[players]]
+            id = 1
+            address = "127.0.0.1:7001"
+
+            [[players]]
+            id = 2
+            address = "127.0.0.1:7002"
+            public_key = "00ff"
+            "#,
+        )
+        .unwrap();
+        assert_eq!(Some(2), config.threshold);
+        assert_eq!(32, config.group.bits);
+        assert_eq!(CodecChoice::Cbor, config.transport.codec);
+        assert_eq!(2, config.addresses().len());
+        let book = config.address_book();
+        assert_eq!(vec![1, 2], book.ids());
+        assert_eq!(
+            Some(&[0, 255][..]),
+            book.get(2).unwrap().public_key.as_deref()
+        );
+        assert_eq!(5, book.redial().attempts);
+        assert!(config.coordinator_transport(1).is_ok());
+
+        let twice = r#"
+            [[players]]
+            id = 1
+            address = "127.0.0.1:7001"
+
+            [[players]]
+            id = 1
+            address = "127.0.0.1:7002"
+        "#;
+        assert!(Config::parse(twice).is_err());
+        assert!(Config::parse("threshold = 3\nplayers = []").is_err());
+    }
+}
@@ -0,0 +1,360 @@
+//! Drives the Players of one process through the protocols, so an application deals,
+//! reconstructs, refreshes and generates keys in one call each instead of sending RPCs and
+//! collecting the replies itself
+//!
+//! Every operation waits up to the Coordinator's timeout for the players to answer and tries
+//! again up to its number of retries, failing with `VssError::TimedOut` once they are spent.
+
+use std::collections::BTreeMap;
+use std::io;
+use std::sync::mpsc::{RecvTimeoutError, Sender};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use num_bigint::BigUint;
+use rand::rngs::StdRng;
+use rand::{CryptoRng, FromEntropy, Rng, RngCore, SeedableRng};
+use tracing::{debug, info, warn};
+
+use crate::dealer::{Dealer, BIT_SIZE, DEALER_ID};
+use crate::dkg::DkgConfig;
+use crate::error::VssError;
+use crate::player::Player;
+use crate::refresh::Renewal;
+use crate::rpc::{ReconstructionReport, RefreshReport, Router, StatusReport, NO_SESSION, RPC};
+use crate::secret::Secret;
+use crate::transport::{ChannelNetwork, Transport};
+
+/// How long an operation waits on the players by default
+pub const DEFAULT_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// How many times an operation is tried again by default
+pub const DEFAULT_RETRIES: usize = 2;
+
+/// How often the players are asked whether they hold a share yet
+const POLL_INTERVAL: Duration = Duration::from_millis(20);
+
+/// Owns the inboxes of players 1..=n and the transport dealing to them
+pub struct Coordinator {
+    dealer: Box<dyn Transport>,
+    /// the inbox of player i + 1 at i
+    players: Vec<Sender<RPC>>,
+    /// sessions dealt or generated here and the epoch their shares are in
+    sessions: BTreeMap<u64, u64>,
+    bits: usize,
+    timeout: Duration,
+    retries: usize,
+    /// draws the dealings and session ids
+    rng: StdRng,
+}
+
+impl Coordinator {
+    /// Coordinate the players whose inboxes are `players`, the first being player 1, dealing to
+    /// them over `dealer`
+    pub fn new<T: Transport + 'static>(dealer: T, players: Vec<Sender<RPC>>) -> Coordinator {
+        Coordinator {
+            dealer: Box::new(dealer),
+            players,
+            sessions: BTreeMap::new(),
+            bits: BIT_SIZE,
+            timeout: DEFAULT_TIMEOUT,
+            retries: DEFAULT_RETRIES,
+            rng: StdRng::from_entropy(),
+        }
+    }
+
+    /// Coordinate `n` new Players on threads of this process, talking over channels
+    pub fn spawn(n: usize) -> Coordinator {
+        Coordinator::spawn_with_rng(n, &mut StdRng::from_entropy())
+    }
+
+    /// Like `spawn` with the Coordinator and every Player drawing from generators seeded by
+    /// `rng`
+    pub fn spawn_with_rng<R: RngCore + CryptoRng>(n: usize, rng: &mut R) -> Coordinator {
+        let network = ChannelNetwork::new();
+        let players = (1..=n)
+            .map(|id| {
+                let (inbox, _) = Player::with_rng(id, network.transport(id), rng);
+                network.register(id, inbox.clone());
+
+                inbox
+            })
+            .collect();
+
+        Coordinator::new(network.transport(DEALER_ID), players).with_rng(rng)
+    }
+
+    /// Draw dealings and session ids from a generator seeded by `rng`, so a seeded rng deals
+    /// the same shares every time
+    pub fn with_rng<R: RngCore + CryptoRng>(mut self, rng: &mut R) -> Coordinator {
+        self.rng = StdRng::from_seed(rng.gen());
+        self
+    }
+
+    /// Deal over a q of at least `bits` bits
+    pub fn with_bits(mut self, bits: usize) -> Coordinator {
+        self.bits = bits;
+        self
+    }
+
+    /// Wait up to `timeout` on the players for each try of an operation
+    pub fn with_timeout(mut self, timeout: Duration) -> Coordinator {
+        self.timeout = timeout;
+        self
+    }
+
+    /// Try an operation again up to `retries` times before failing
+    pub fn with_retries(mut self, retries: usize) -> Coordinator {
+        self.retries = retries;
+        self
+    }
+
+    /// Ids of the players, 1..=n
+    pub fn players(&self) -> Vec<usize> {
+        (1..=self.players.len()).collect()
+    }
+
+    /// Sessions dealt or generated by this Coordinator and the epoch each is in
+    pub fn sessions(&self) -> Vec<(u64, u64)> {
+        self.sessions
+            .iter()
+            .map(|(session_id, epoch)| (*session_id, *epoch))
+            .collect()
+    }
+
+    /// Deal `secret` to every player, any `t` of which reconstruct it, returning the session
+    /// once every player holds its share
+    pub fn deal(&mut self, secret: Secret, t: usize) -> Result<u64, VssError> {
+        let n = self.players.len();
+        let dealer = Dealer::with_rng(n, t, secret, self.bits, &mut self.rng)?;
+        let (session_id, epoch) = (dealer.session_id, dealer.epoch);
+
+        for attempt in 0..=self.retries {
+            debug!(session = session_id, attempt, "dealing");
+            dealer.propagate(self.dealer.as_ref());
+            if self.held(session_id, epoch)? {
+                info!(session = session_id, "dealt");
+                self.sessions.insert(session_id, epoch);
+                return Ok(session_id);
+            }
+            warn!(session = session_id, attempt, "players missing their share");
+        }
+
+        Err(VssError::TimedOut(self.timeout))
+    }
+
+    /// The secret of `session` as the first player to reconstruct it reports it
+    pub fn reconstruct(&mut self, session_id: u64) -> Result<ReconstructionReport, VssError> {
+        self.retry(|players, timeout| {
+            let mut router = Router::new();
+            for player in players {
+                player
+                    .send(RPC::Reconstruct(session_id, router.request()))
+                    .map_err(|_| VssError::Disconnected)?;
+            }
+
+            let (_, report) = answered(router.recv_timeout(timeout), timeout)?;
+
+            Ok(report)
+        })
+    }
+
+    /// Move `session` to its next epoch without changing its secret, returning once every
+    /// player's share moved
+    pub fn refresh(&mut self, session_id: u64) -> Result<RefreshReport, VssError> {
+        // one player starts the refresh and the others join once its deal arrives
+        let first = self.players.first().ok_or(VssError::NoShares)?.clone();
+        let mut router = Router::new();
+        first
+            .send(RPC::Refresh(session_id, Renewal::Refresh, router.request()))
+            .map_err(|_| VssError::Disconnected)?;
+        let (_, report) = answered(router.recv_timeout(self.timeout), self.timeout)?;
+
+        if !self.held(session_id, report.epoch)? {
+            return Err(VssError::TimedOut(self.timeout));
+        }
+        info!(session = session_id, epoch = report.epoch, "refreshed");
+        self.sessions.insert(session_id, report.epoch);
+
+        Ok(report)
+    }
+
+    /// Generate a key with every player, any `t` of which use it, returning its session and
+    /// public key once every player holds its key share
+    pub fn dkg(&mut self, t: usize) -> Result<(u64, BigUint), VssError> {
+        let n = self.players.len();
+        if t == 0 || t > n {
+            return Err(VssError::InvalidThreshold { t, n });
+        }
+        let session_id = self.rng.gen_range(NO_SESSION + 1, u64::MAX);
+        let config = DkgConfig::with_rng(t, self.players(), BIT_SIZE, &mut self.rng);
+
+        let mut router = Router::new();
+        for player in &self.players {
+            player
+                .send(RPC::Dkg(session_id, config.clone(), router.request()))
+                .map_err(|_| VssError::Disconnected)?;
+        }
+        // every player waits out its own rounds, so the generation as a whole gets every try
+        let timeout = self.timeout * (self.retries + 1) as u32;
+        let deadline = Instant::now() + timeout;
+        let mut reports = vec![];
+        while router.outstanding() > 0 {
+            let left = deadline.saturating_duration_since(Instant::now());
+            let (_, report) = answered(router.recv_timeout(left), timeout)?;
+            reports.push(report);
+        }
+
+        let report = reports.first().ok_or(VssError::NoShares)?;
+        if reports
+            .iter()
+            .any(|other| other.public_key != report.public_key)
+        {
+            warn!(session = session_id, "players generated different keys");
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "players generated different keys",
+            )
+            .into());
+        }
+        info!(session = session_id, public_key = %report.public_key, "generated key");
+        self.sessions
+            .insert(session_id, report.share_info.validity().epoch);
+
+        Ok((session_id, report.public_key.clone()))
+    }
+
+    /// What every player holds
+    pub fn status(&self) -> Result<Vec<StatusReport>, VssError> {
+        let mut router = Router::new();
+        let ids: Vec<_> = self
+            .players
+            .iter()
+            .map(|player| {
+                let s = router.request();
+                let id = s.id();
+                player
+                    .send(RPC::Status(s))
+                    .map_err(|_| VssError::Disconnected)?;
+
+                Ok(id)
+            })
+            .collect::<Result<_, VssError>>()?;
+
+        ids.into_iter()
+            .map(|id| answered(router.recv_for(id, self.timeout), self.timeout))
+            .collect()
+    }
+
+    /// Whether every player holds its share of `session` in `epoch` within the timeout
+    fn held(&self, session_id: u64, epoch: u64) -> Result<bool, VssError> {
+        let deadline = Instant::now() + self.timeout;
+        while Instant::now() < deadline {
+            let holds = self
+                .status()?
+                .iter()
+                .all(|status| status.sessions.contains(&(session_id, epoch)));
+            if holds {
+                return Ok(true);
+            }
+            thread::sleep(POLL_INTERVAL);
+        }
+
+        Ok(false)
+    }
+
+    /// Try `operation` with the timeout, again while it times out and retries are left
+    fn retry<T, F>(&self, mut operation: F) -> Result<T, VssError>
+    where
+        F: FnMut(&[Sender<RPC>], Duration) -> Result<T, VssError>,
+    {
+        let mut attempt = 0;
+        loop {
+            match operation(&self.players, self.timeout) {
+                Err(VssError::TimedOut(_)) if attempt < self.retries => {
+                    attempt += 1;
+                    warn!(attempt, "players did not answer, trying again");
+                }
+                result => return result,
+            }
+        }
+    }
+}
+
+/// The answer the Router waited up to `timeout` on
+fn answered<T>(answer: Result<T, RecvTimeoutError>, timeout: Duration) -> Result<T, VssError> {
+    answer.map_err(|err| match err {
+        RecvTimeoutError::Timeout => VssError::TimedOut(timeout),
+        RecvTimeoutError::Disconnected => VssError::Disconnected,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use num_bigint::BigUint;
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    use crate::error::VssError;
+    use crate::secret::Secret;
+
+    use super::Coordinator;
+
+    #[test]
+    fn coordinator_runs_every_protocol() {
+        let mut coordinator = Coordinator::spawn(5).with_timeout(Duration::from_secs(5));
+
+        let session_id = coordinator
+            .deal(Secret::new(BigUint::from(1234_u32)), 3)
+            .unwrap();
+        let report = coordinator.reconstruct(session_id).unwrap();
+        assert_eq!(BigUint::from(1234_u32), *report.secret.expose());
+        assert!(report.verified);
+
+        let report = coordinator.refresh(session_id).unwrap();
+        assert_eq!(1, report.epoch);
+        let report = coordinator.reconstruct(session_id).unwrap();
+        assert_eq!(BigUint::from(1234_u32), *report.secret.expose());
+
+        let (key_session, _) = coordinator.dkg(3).unwrap();
+        assert_eq!(vec![(session_id, 1), (key_session, 0)], {
+            let mut sessions = coordinator.sessions();
+            sessions.sort_by_key(|(_, epoch)| std::cmp::Reverse(*epoch));
+            sessions
+        });
+    }
+
+    #[test]
+    fn seeded_coordinators_deal_the_same() {
+        let deal = |seed| {
+            let mut rng = StdRng::seed_from_u64(seed);
+            let mut coordinator = Coordinator::spawn_with_rng(3, &mut rng);
+            let session_id = coordinator
+                .deal(Secret::new(BigUint::from(1234_u32)), 2)
+                .unwrap();
+
+            (session_id, coordinator.reconstruct(session_id).unwrap())
+        };
+        let (session_id, report) = deal(7);
+
+        assert_eq!(session_id, deal(7).0);
+        assert_ne!(session_id, deal(8).0);
+        assert_eq!(BigUint::from(1234_u32), *report.secret.expose());
+    }
+
+    #[test]
+    fn coordinator_gives_up_after_its_retries() {
+        let mut coordinator = Coordinator::spawn(3)
+            .with_timeout(Duration::from_millis(100))
+            .with_retries(1);
+
+        // nobody holds a share of this session
+        assert!(matches!(
+            coordinator.reconstruct(42),
+            Err(VssError::TimedOut(_))
+        ));
+        assert!(coordinator.dkg(4).is_err());
+    }
+}
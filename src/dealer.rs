@@ -1,160 +1,489 @@
-use std::sync::mpsc::Sender;
-
-use num_bigint::BigUint;
-use num_primes::{Generator, RandBigInt, Verification};
-
-use crate::{rpc::RPC, vss};
-
-// for demonstration pick 32 bits
-const BIT_SIZE: usize = 32;
-
-#[derive(Debug)]
-pub struct Dealer {
-    pub p: BigUint,
-    pub q: BigUint,
-    pub g: BigUint,
-    pub shares: Vec<(BigUint, BigUint)>,
-    pub c: Vec<BigUint>,
-    pub t: usize,
-    pub n: usize,
-}
-
-impl Dealer {
-    /// Given a prime q, find a prime p s.t. q | (p - 1)
-    fn find_p(q: &BigUint) -> BigUint {
-        let mut k = Generator::new_uint(BIT_SIZE);
-        let mut p = k * q + (1 as usize);
-
-        while !Verification::is_prime(&p) {
-            k = Generator::new_uint(BIT_SIZE);
-            p = k * q + (1 as usize);
-        }
-
-        p
-    }
-
-    /// Find generator of order q in prime field p
-    ///
-    /// choose any b in [2, p - 2] then g = b ^((p - 1) / q) mod p
-    fn find_g(p: &BigUint, q: &BigUint) -> BigUint {
-        let b =
-            rand::thread_rng().gen_biguint_range(&BigUint::from(2 as usize), &(p - (1 as usize)));
-        let e = (p - (1 as u32)) / q;
-
-        b.modpow(&e, p)
-    }
-
-    /// Generate polynomial coefficients in primefield q
-    fn gen_a(q: &BigUint) -> BigUint {
-        rand::thread_rng().gen_biguint_below(q)
-    }
-
-    /// Return a new Dealer
-    pub fn new(n: usize, t: usize, secret: usize) -> Dealer {
-        // find two primes p, and q s.t. q | p - 1
-        let q = Generator::new_prime(BIT_SIZE);
-        let p = Dealer::find_p(&q);
-        // find generator of order q in multiplicative group p
-        let g: BigUint = Dealer::find_g(&p, &q);
-        // generate random polynomial of degree t
-        let a = [vec![BigUint::from(secret)], vec![Dealer::gen_a(&q); t - 1]].concat();
-        // generate commitments
-        let c = vss::generate_commitments(&a, &g, &p);
-        // generate shares
-        let shares = vss::generate_shares(&a, n, &q);
-
-        Dealer {
-            p,
-            q,
-            g,
-            shares,
-            c,
-            t,
-            n,
-        }
-    }
-
-    /// Propagates share secrets to players via channel
-    pub fn propagate(&self, channels: &Vec<Sender<RPC>>) {
-        channels.iter().enumerate().for_each(|(i, s)| {
-            let share = (self.shares[i].0.clone(), self.shares[i].1.clone());
-            let g = self.g.clone();
-            let c = self.c.clone();
-            let p = self.p.clone();
-            let q = self.q.clone();
-            let t = self.t.clone();
-
-            s.send(RPC::RegShare((share, g, c, p, q, t)));
-        });
-    }
-}
-
-#[cfg(test)]
-mod tests {
-    use num::ToPrimitive;
-    use num_bigint::ToBigUint;
-
-    use crate::vss;
-
-    use super::Dealer;
-
-    #[test]
-    fn dealer_verify() {
-        let n = 5;
-        let dealer = Dealer::new(n, 3, 1234);
-
-        for (i, s_i) in dealer.shares {
-            assert!(vss::verify_share(&i, &s_i, &dealer.g, &dealer.c, &dealer.p));
-        }
-    }
-
-    #[test]
-    fn dealer_reconstruct() {
-        let shares = vec![
-            (2.to_biguint().unwrap(), 1942.to_biguint().unwrap()),
-            (4.to_biguint().unwrap(), 3402.to_biguint().unwrap()),
-            (5.to_biguint().unwrap(), 4414.to_biguint().unwrap()),
-        ];
-        // random prime
-        let q = 13931.to_biguint().unwrap();
-
-        assert_eq!(1234, vss::reconstruct(&shares, &q).to_usize().unwrap());
-    }
-
-    #[test]
-    fn dealer_reconstruct_shares() {
-        let dealer = Dealer::new(5, 3, 1234);
-        let k_shares = vec![
-            vec![
-                dealer.shares[0].clone(),
-                dealer.shares[1].clone(),
-                dealer.shares[2].clone(),
-            ],
-            vec![
-                dealer.shares[0].clone(),
-                dealer.shares[2].clone(),
-                dealer.shares[3].clone(),
-            ],
-            vec![
-                dealer.shares[1].clone(),
-                dealer.shares[2].clone(),
-                dealer.shares[3].clone(),
-            ],
-            vec![
-                dealer.shares[4].clone(),
-                dealer.shares[2].clone(),
-                dealer.shares[0].clone(),
-            ],
-        ];
-
-        for shares in k_shares {
-            assert_eq!(
-                1234,
-                vss::reconstruct(&shares, &dealer.q).to_usize().unwrap(),
-                "failed: {:?}\nq: {}",
-                shares,
-                dealer.q,
-            );
-        }
-    }
-}
+#[cfg(feature = "audit")]
+use std::io;
+use std::sync::Arc;
+
+use num_bigint::{BigUint, RandBigInt};
+use rand::{CryptoRng, Rng, RngCore};
+#[cfg(feature = "network")]
+use tracing::{debug, warn};
+use tracing::{info, info_span};
+
+#[cfg(feature = "audit")]
+use crate::{
+    audit::{AuditLog, Event},
+    format,
+};
+use crate::{
+    error::VssError,
+    primes::{self, Primality},
+    rpc::{PublicParams, Share, Validity, NO_SESSION},
+    secret::{self, Secret},
+    vss,
+};
+#[cfg(feature = "network")]
+use crate::{
+    rpc::{Payload, ShareInfo, WireMessage},
+    transport::Transport,
+};
+
+// for demonstration pick 32 bits
+pub(crate) const BIT_SIZE: usize = 32;
+
+/// Bits of q for dealings that keep a secret, a discrete log in a subgroup of this order is out
+/// of reach
+pub const SAFE_Q_BITS: usize = 256;
+
+/// Bits of p for dealings that keep a secret, too many for index calculus to take a discrete
+/// log in the field whatever the size of q
+pub const SAFE_P_BITS: usize = 2048;
+
+/// Id the dealer sends as, players are numbered from 1 to match their share index
+pub const DEALER_ID: usize = 0;
+
+#[derive(Debug)]
+pub struct Dealer {
+    pub p: BigUint,
+    pub q: BigUint,
+    pub g: BigUint,
+    pub shares: Vec<Share>,
+    pub c: Vec<BigUint>,
+    pub t: usize,
+    pub n: usize,
+    /// Identifies this dealing in every message sent for it
+    pub session_id: u64,
+    pub epoch: u64,
+    /// Seconds since the Unix epoch before which the shares may not be used
+    pub not_before: Option<u64>,
+    /// Seconds since the Unix epoch from which the shares have expired
+    pub not_after: Option<u64>,
+}
+
+impl Dealer {
+    /// Given a prime q, find a prime p of at least `p_bits` bits s.t. q | (p - 1)
+    ///
+    /// p = k * q + 1 with a k of BIT_SIZE bits, or of as many more as p needs to reach `p_bits`
+    fn find_p<R: RngCore + CryptoRng>(q: &BigUint, p_bits: usize, rng: &mut R) -> BigUint {
+        let policy = Primality::default();
+        let k_bits = BIT_SIZE.max((p_bits + 1).saturating_sub(q.bits()));
+
+        loop {
+            let k = rng.gen_biguint(k_bits);
+            let p = k * q + 1_u32;
+            if p.bits() >= p_bits && policy.is_probable_prime(&p, rng) {
+                return p;
+            }
+        }
+    }
+
+    /// Find generator of order q in prime field p
+    ///
+    /// choose any b in [2, p - 2] then g = b ^((p - 1) / q) mod p
+    fn find_g<R: RngCore + CryptoRng>(p: &BigUint, q: &BigUint, rng: &mut R) -> BigUint {
+        let b = rng.gen_biguint_range(&BigUint::from(2_u32), &(p - 1_u32));
+        let e = (p - 1_u32) / q;
+
+        b.modpow(&e, p)
+    }
+
+    /// Generate polynomial coefficients in primefield q
+    fn gen_a<R: RngCore + CryptoRng>(q: &BigUint, rng: &mut R) -> BigUint {
+        rng.gen_biguint_below(q)
+    }
+
+    /// Return primes p, q s.t. q | p - 1 and a generator g of order q in primefield p
+    #[cfg(feature = "modp")]
+    pub(crate) fn group() -> (BigUint, BigUint, BigUint) {
+        Dealer::group_of(BIT_SIZE, &mut rand::thread_rng())
+    }
+
+    /// Like `group` with a q of `bits` bits, drawn from `rng`
+    pub(crate) fn group_of<R: RngCore + CryptoRng>(
+        bits: usize,
+        rng: &mut R,
+    ) -> (BigUint, BigUint, BigUint) {
+        Dealer::group_sized(bits, 0, rng)
+    }
+
+    /// Like `group_of` with a p of at least `p_bits` bits
+    pub(crate) fn group_sized<R: RngCore + CryptoRng>(
+        q_bits: usize,
+        p_bits: usize,
+        rng: &mut R,
+    ) -> (BigUint, BigUint, BigUint) {
+        // find two primes p, and q s.t. q | p - 1
+        let q = primes::random_prime(q_bits, &Primality::default(), rng);
+        let p = Dealer::find_p(&q, p_bits, rng);
+        // find generator of order q in multiplicative group p
+        let g: BigUint = Dealer::find_g(&p, &q, rng);
+
+        (p, q, g)
+    }
+
+    /// Return a new Dealer, failing unless 1 <= t <= n
+    pub fn new(n: usize, t: usize, secret: usize) -> Result<Dealer, VssError> {
+        Dealer::with_secret(n, t, Secret::new(BigUint::from(secret)))
+    }
+
+    /// Return a new Dealer of a secret of any size, over a group whose q is large enough to
+    /// hold it
+    pub fn with_secret(n: usize, t: usize, secret: Secret) -> Result<Dealer, VssError> {
+        Dealer::with_bits(n, t, secret, BIT_SIZE)
+    }
+
+    /// Like `with_secret` with a q of at least `bits` bits
+    pub fn with_bits(n: usize, t: usize, secret: Secret, bits: usize) -> Result<Dealer, VssError> {
+        Dealer::with_rng(n, t, secret, bits, &mut rand::thread_rng())
+    }
+
+    /// Like `with_bits` with the group, coefficients and session drawn from `rng`, so a seeded
+    /// rng deals the same shares every time
+    pub fn with_rng<R: RngCore + CryptoRng>(
+        n: usize,
+        t: usize,
+        secret: Secret,
+        bits: usize,
+        rng: &mut R,
+    ) -> Result<Dealer, VssError> {
+        Dealer::with_sizes_rng(n, t, secret, bits, 0, rng)
+    }
+
+    /// Return a new Dealer over a group safe to keep a secret in, with a q of `SAFE_Q_BITS`
+    /// and a p of `SAFE_P_BITS` bits at least
+    ///
+    /// The other constructors deal over a p only a few bits longer than q, small enough for
+    /// anyone to take the discrete log of c_0 = g^secret
+    pub fn with_safe_group(n: usize, t: usize, secret: Secret) -> Result<Dealer, VssError> {
+        Dealer::with_sizes(n, t, secret, SAFE_Q_BITS, SAFE_P_BITS)
+    }
+
+    /// Like `with_bits` with a p of at least `p_bits` bits
+    pub fn with_sizes(
+        n: usize,
+        t: usize,
+        secret: Secret,
+        q_bits: usize,
+        p_bits: usize,
+    ) -> Result<Dealer, VssError> {
+        Dealer::with_sizes_rng(n, t, secret, q_bits, p_bits, &mut rand::thread_rng())
+    }
+
+    /// Like `with_sizes` drawing from `rng`
+    pub fn with_sizes_rng<R: RngCore + CryptoRng>(
+        n: usize,
+        t: usize,
+        secret: Secret,
+        q_bits: usize,
+        p_bits: usize,
+        rng: &mut R,
+    ) -> Result<Dealer, VssError> {
+        if t == 0 || t > n {
+            return Err(VssError::InvalidThreshold { t, n });
+        }
+        // never pick NO_SESSION, which marks messages outside of any dealing
+        let session_id = rng.gen_range(NO_SESSION + 1, u64::MAX);
+        let epoch = 0;
+        let span = info_span!("dealer", n, t, session = session_id, epoch);
+        let _enter = span.enter();
+
+        // q has its top bit set, so one bit more than the secret makes q > secret
+        let q_bits = q_bits.max(secret.expose().bits() + 1);
+        let (p, q, g) = Dealer::group_sized(q_bits, p_bits, rng);
+        // generate random polynomial of degree t
+        let mut a: Vec<BigUint> = std::iter::once(secret.expose().clone())
+            .chain((1..t).map(|_| Dealer::gen_a(&q, rng)))
+            .collect();
+        // generate commitments
+        let c = vss::generate_commitments(&a, &g, &p);
+        // generate shares
+        let shares = vss::generate_shares(&a, n, &q);
+        // the coefficients give the secret away as well as the secret itself
+        a.iter_mut().for_each(secret::wipe);
+        info!(%p, %q, %g, "dealt shares");
+
+        Ok(Dealer {
+            p,
+            q,
+            g,
+            shares,
+            c,
+            t,
+            n,
+            session_id,
+            epoch,
+            not_before: None,
+            not_after: None,
+        })
+    }
+
+    #[cfg(feature = "network")]
+    /// Propagates share secrets to players 1..=n over `transport`
+    pub fn propagate(&self, transport: &dyn Transport) {
+        let span = info_span!(
+            "dealer",
+            n = self.n,
+            t = self.t,
+            session = self.session_id,
+            epoch = self.epoch
+        );
+        let _enter = span.enter();
+
+        let params = self.params();
+        (0..self.n).for_each(|i| {
+            let msg = self.reg_share(&params, i);
+            if let Err(err) = transport.send(i + 1, msg) {
+                warn!("error while propagating share {}: {}", i + 1, err);
+            }
+        });
+    }
+
+    #[cfg(feature = "network")]
+    /// Propagates the shares of several dealings to their players, one Batch per player
+    ///
+    /// A player accepts every share of its batch or none, so it never holds only part of a
+    /// secret dealt in several dealings
+    pub fn propagate_batch(dealers: &[Dealer], transport: &dyn Transport) {
+        let n = dealers.iter().map(|dealer| dealer.n).max().unwrap_or(0);
+        let params: Vec<_> = dealers.iter().map(Dealer::params).collect();
+
+        (0..n).for_each(|i| {
+            let msgs = dealers
+                .iter()
+                .zip(&params)
+                .filter(|(dealer, _)| i < dealer.n)
+                .map(|(dealer, params)| dealer.reg_share(params, i))
+                .collect();
+
+            debug!(to = i + 1, "Batch");
+            let msg = WireMessage::new(NO_SESSION, 0, Payload::Batch(msgs));
+            if let Err(err) = transport.send(i + 1, msg) {
+                warn!("error while propagating shares {}: {}", i + 1, err);
+            }
+        });
+    }
+
+    /// The public parameters of this dealing, shared by the ShareInfo of every player
+    pub fn params(&self) -> Arc<PublicParams> {
+        Arc::new(PublicParams {
+            g: self.g.clone(),
+            commitments: self.c.clone(),
+            p: self.p.clone(),
+            q: self.q.clone(),
+            t: self.t,
+        })
+    }
+
+    /// The epoch and window the shares of this dealing are valid in
+    pub fn validity(&self) -> Validity {
+        Validity {
+            epoch: self.epoch,
+            not_before: self.not_before,
+            not_after: self.not_after,
+        }
+    }
+
+    /// Log this dealing to `log`
+    #[cfg(feature = "audit")]
+    pub fn audit(&self, log: &mut AuditLog) -> io::Result<()> {
+        let event = Event::Dealt {
+            players: self.n,
+            threshold: self.t,
+            fingerprint: format::fingerprint_params(&self.params()),
+        };
+
+        log.append(self.session_id, event).map(drop)
+    }
+
+    /// The RegShare of the ith player
+    #[cfg(feature = "network")]
+    fn reg_share(&self, params: &Arc<PublicParams>, i: usize) -> WireMessage {
+        let share_info = ShareInfo::with_params(self.shares[i].clone(), params.clone())
+            .with_validity(self.validity());
+
+        debug!(session = self.session_id, index = %share_info.share().index(), "RegShare");
+        WireMessage::new(self.session_id, self.epoch, Payload::RegShare(share_info))
+    }
+
+    #[cfg(feature = "network")]
+    /// Answers a Complaint against this dealing by revealing the accuser's share to every player
+    ///
+    /// Players check the revealed share against the commitments and disqualify the dealer if it
+    /// does not verify. Other messages are ignored
+    pub fn respond(&self, transport: &dyn Transport, msg: &WireMessage) {
+        let accuser = match msg.payload {
+            Payload::Complaint {
+                accuser,
+                against: DEALER_ID,
+                ..
+            } if msg.session_id == self.session_id => accuser,
+            _ => return,
+        };
+        let share = match accuser.checked_sub(1).and_then(|i| self.shares.get(i)) {
+            Some(share) => share.clone(),
+            None => return warn!(accuser, "complaint from an unknown player"),
+        };
+
+        info!(accuser, session = self.session_id, "revealing share");
+        let response = WireMessage::new(
+            self.session_id,
+            self.epoch,
+            Payload::ComplaintResponse(share),
+        );
+        for (to, err) in transport.broadcast(response) {
+            warn!("error while revealing share to {}: {}", to, err);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use num::ToPrimitive;
+    use num_bigint::{BigUint, ToBigUint};
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    use crate::error::VssError;
+    use crate::rpc::Share;
+    use crate::secret::Secret;
+    use crate::vss;
+
+    use super::Dealer;
+
+    #[test]
+    fn dealer_verify() {
+        let n = 5;
+        let dealer = Dealer::new(n, 3, 1234).unwrap();
+
+        for share in dealer.shares {
+            assert!(vss::verify_share(
+                share.index(),
+                share.value(),
+                &dealer.g,
+                &dealer.c,
+                &dealer.p
+            ));
+        }
+    }
+
+    #[test]
+    fn dealer_with_seeded_rng_deals_the_same_shares() {
+        let deal = |seed| {
+            let secret = Secret::new(BigUint::from(1234_u32));
+            let mut rng = StdRng::seed_from_u64(seed);
+
+            Dealer::with_rng(5, 3, secret, 32, &mut rng).unwrap()
+        };
+        let (a, b) = (deal(7), deal(7));
+
+        assert_eq!(
+            (a.session_id, &a.p, &a.c, &a.shares),
+            (b.session_id, &b.p, &b.c, &b.shares)
+        );
+        assert_ne!(a.c, deal(8).c);
+        // every coefficient is drawn on its own
+        assert_ne!(a.c[1], a.c[2]);
+    }
+
+    #[test]
+    fn dealer_grows_p_to_its_size() {
+        let secret = Secret::new(BigUint::from(1234_u32));
+        let mut rng = StdRng::seed_from_u64(7);
+        let dealer = Dealer::with_sizes_rng(5, 3, secret, 32, 160, &mut rng).unwrap();
+
+        assert!(dealer.p.bits() >= 160);
+        assert_eq!(BigUint::from(0_u32), (&dealer.p - 1_u32) % &dealer.q);
+        for share in &dealer.shares {
+            assert!(vss::verify_share(
+                share.index(),
+                share.value(),
+                &dealer.g,
+                &dealer.c,
+                &dealer.p
+            ));
+        }
+    }
+
+    #[test]
+    fn dealer_rejects_invalid_threshold() {
+        for (n, t) in [(5, 0), (5, 6)] {
+            assert!(matches!(
+                Dealer::new(n, t, 1234),
+                Err(VssError::InvalidThreshold { .. })
+            ));
+        }
+    }
+
+    #[test]
+    fn dealer_shares_large_secret() {
+        let secret = BigUint::from_bytes_be(&[0xff; 16]);
+        let dealer = Dealer::with_secret(5, 3, Secret::new(secret.clone())).unwrap();
+
+        assert!(dealer.q > secret);
+        assert_eq!(
+            &secret,
+            vss::reconstruct(&dealer.shares[1..4], &dealer.q)
+                .unwrap()
+                .expose()
+        );
+    }
+
+    #[test]
+    fn dealer_reconstruct() {
+        let shares = vec![
+            Share::new(2.to_biguint().unwrap(), 1942.to_biguint().unwrap()),
+            Share::new(4.to_biguint().unwrap(), 3402.to_biguint().unwrap()),
+            Share::new(5.to_biguint().unwrap(), 4414.to_biguint().unwrap()),
+        ];
+        // random prime
+        let q = 13931.to_biguint().unwrap();
+
+        assert_eq!(
+            1234,
+            vss::reconstruct(&shares, &q)
+                .unwrap()
+                .expose()
+                .to_usize()
+                .unwrap()
+        );
+    }
+
+    #[test]
+    fn dealer_reconstruct_shares() {
+        let dealer = Dealer::new(5, 3, 1234).unwrap();
+        let k_shares = vec![
+            vec![
+                dealer.shares[0].clone(),
+                dealer.shares[1].clone(),
+                dealer.shares[2].clone(),
+            ],
+            vec![
+                dealer.shares[0].clone(),
+                dealer.shares[2].clone(),
+                dealer.shares[3].clone(),
+            ],
+            vec![
+                dealer.shares[1].clone(),
+                dealer.shares[2].clone(),
+                dealer.shares[3].clone(),
+            ],
+            vec![
+                dealer.shares[4].clone(),
+                dealer.shares[2].clone(),
+                dealer.shares[0].clone(),
+            ],
+        ];
+
+        for shares in k_shares {
+            assert_eq!(
+                1234,
+                vss::reconstruct(&shares, &dealer.q)
+                    .unwrap()
+                    .expose()
+                    .to_usize()
+                    .unwrap(),
+                "failed: {:?}\nq: {}",
+                shares,
+                dealer.q,
+            );
+        }
+    }
+}
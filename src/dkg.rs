@@ -0,0 +1,451 @@
+use std::collections::{HashMap, HashSet};
+
+use num::{One, Zero};
+use num_bigint::BigUint;
+use num_primes::RandBigInt;
+use rand::{CryptoRng, RngCore};
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+use tracing::{debug, warn};
+
+#[cfg(feature = "serde")]
+use crate::codec;
+use crate::dealer::Dealer;
+use crate::rpc::{Share, ShareInfo};
+use crate::vss;
+
+/// Public parameters every player of a distributed key generation agrees on
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct DkgConfig {
+    #[cfg_attr(feature = "serde", serde(with = "codec::biguint"))]
+    pub p: BigUint,
+    #[cfg_attr(feature = "serde", serde(with = "codec::biguint"))]
+    pub q: BigUint,
+    #[cfg_attr(feature = "serde", serde(with = "codec::biguint"))]
+    pub g: BigUint,
+    /// Number of key shares needed to use the key
+    pub t: usize,
+    /// ids of the players generating the key
+    pub players: Vec<usize>,
+}
+
+impl DkgConfig {
+    /// Return a config over a new group for `players` with threshold t
+    pub fn generate(t: usize, players: Vec<usize>) -> DkgConfig {
+        let (p, q, g) = Dealer::group();
+
+        DkgConfig {
+            p,
+            q,
+            g,
+            t,
+            players,
+        }
+    }
+
+    /// Like `generate` with a q of `bits` bits
+    pub fn with_bits(t: usize, players: Vec<usize>, bits: usize) -> DkgConfig {
+        DkgConfig::with_rng(t, players, bits, &mut rand::thread_rng())
+    }
+
+    /// Like `with_bits` with the group drawn from `rng`
+    pub fn with_rng<R: RngCore + CryptoRng>(
+        t: usize,
+        players: Vec<usize>,
+        bits: usize,
+        rng: &mut R,
+    ) -> DkgConfig {
+        let (p, q, g) = Dealer::group_of(bits, rng);
+
+        DkgConfig {
+            p,
+            q,
+            g,
+            t,
+            players,
+        }
+    }
+}
+
+/// Round of a distributed key generation, a player moves on once every player finished the
+/// previous one
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Round {
+    Deal = 1,
+    Complain = 2,
+    Qualify = 3,
+    Publish = 4,
+}
+
+/// Messages of a distributed key generation
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum DkgMessage {
+    /// Round 1, to every player: commitments to the sender's polynomial
+    DealingBroadcast(#[cfg_attr(feature = "serde", serde(with = "codec::biguints"))] Vec<BigUint>),
+    /// Round 1, to a single player: the receiver's point on the sender's polynomial
+    DealingShare(Share),
+    /// Round 2, to every player: dealers whose point did not verify against their commitments
+    Complaint(Vec<usize>),
+    /// Round 3, to every player: dealers nobody complained about as far as the sender saw
+    Qualified(Vec<usize>),
+    /// Round 4, to every player: g^x of the sender's key share x
+    PublicKeyShare(#[cfg_attr(feature = "serde", serde(with = "codec::biguint"))] BigUint),
+}
+
+impl DkgMessage {
+    /// The round the message belongs to
+    pub fn round(&self) -> Round {
+        match self {
+            DkgMessage::DealingBroadcast(_) | DkgMessage::DealingShare(_) => Round::Deal,
+            DkgMessage::Complaint(_) => Round::Complain,
+            DkgMessage::Qualified(_) => Round::Qualify,
+            DkgMessage::PublicKeyShare(_) => Round::Publish,
+        }
+    }
+}
+
+/// Messages a step of the DKG asks to send
+#[derive(Debug, Default)]
+pub struct Outbox {
+    /// to every other player
+    pub broadcast: Vec<DkgMessage>,
+    pub send: Vec<(usize, DkgMessage)>,
+}
+
+/// The key a player holds once the DKG completes
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DkgOutput {
+    /// The player's key share, with commitments to the joint polynomial
+    pub share_info: ShareInfo,
+    /// g^x of the group secret x, which no player knows
+    pub public_key: BigUint,
+    /// g^x_j of every player's key share x_j
+    pub public_key_shares: HashMap<usize, BigUint>,
+    /// dealers whose polynomials make up the key
+    pub qualified: Vec<usize>,
+}
+
+/// A player's part in a distributed key generation after Pedersen
+///
+/// Every player deals a random polynomial like a Feldman dealer, players complain about
+/// dealings that do not verify and the key is the sum of the polynomials nobody complained
+/// about. Each player's key share is the sum of the points it was dealt, so the group secret is
+/// never held by anyone
+pub struct Dkg {
+    id: usize,
+    config: DkgConfig,
+    round: Round,
+    commitments: HashMap<usize, Vec<BigUint>>,
+    /// points dealt to us, by dealer
+    shares: HashMap<usize, Share>,
+    complaints: HashMap<usize, Vec<usize>>,
+    qualified: HashMap<usize, Vec<usize>>,
+    public_key_shares: HashMap<usize, BigUint>,
+    share_info: Option<ShareInfo>,
+    output: Option<DkgOutput>,
+}
+
+impl Dkg {
+    /// Return a DKG taking part as the player `id`
+    pub fn new(id: usize, config: DkgConfig) -> Dkg {
+        Dkg {
+            id,
+            config,
+            round: Round::Deal,
+            commitments: HashMap::new(),
+            shares: HashMap::new(),
+            complaints: HashMap::new(),
+            qualified: HashMap::new(),
+            public_key_shares: HashMap::new(),
+            share_info: None,
+            output: None,
+        }
+    }
+
+    /// The key once every round completed
+    pub fn output(&self) -> Option<&DkgOutput> {
+        self.output.as_ref()
+    }
+
+    /// Deals this player's polynomial, its coefficients drawn from `rng`
+    pub fn start<R: RngCore + CryptoRng>(&mut self, rng: &mut R) -> Outbox {
+        let DkgConfig { p, q, g, t, .. } = &self.config;
+        let a: Vec<BigUint> = (0..*t).map(|_| rng.gen_biguint_below(q)).collect();
+        let commitments = vss::generate_commitments(&a, g, p);
+        let max = self.config.players.iter().copied().max().unwrap_or(0);
+
+        let mut outbox = Outbox::default();
+        for (share, id) in vss::generate_shares(&a, max, q).into_iter().zip(1..) {
+            if id == self.id {
+                self.shares.insert(id, share);
+            } else if self.config.players.contains(&id) {
+                outbox.send.push((id, DkgMessage::DealingShare(share)));
+            }
+        }
+        self.emit(&mut outbox, DkgMessage::DealingBroadcast(commitments));
+
+        outbox
+    }
+
+    /// Handles a message from the player `from`, returns what to send in answer
+    pub fn on_message(&mut self, from: usize, msg: DkgMessage) -> Outbox {
+        let mut outbox = Outbox::default();
+        if !self.config.players.contains(&from) {
+            debug!(from, "ignoring DKG message from a stranger");
+            return outbox;
+        }
+
+        self.record(from, msg);
+        self.advance(&mut outbox);
+
+        outbox
+    }
+
+    /// Broadcasts `msg`, counting it as received from ourselves
+    fn emit(&mut self, outbox: &mut Outbox, msg: DkgMessage) {
+        self.record(self.id, msg.clone());
+        outbox.broadcast.push(msg);
+    }
+
+    fn record(&mut self, from: usize, msg: DkgMessage) {
+        match msg {
+            DkgMessage::DealingBroadcast(commitments) => {
+                self.commitments.entry(from).or_insert(commitments);
+            }
+            DkgMessage::DealingShare(share) => {
+                self.shares.entry(from).or_insert(share);
+            }
+            DkgMessage::Complaint(against) => {
+                self.complaints.entry(from).or_insert(against);
+            }
+            DkgMessage::Qualified(dealers) => {
+                self.qualified.entry(from).or_insert(dealers);
+            }
+            DkgMessage::PublicKeyShare(y) => {
+                self.public_key_shares.entry(from).or_insert(y);
+            }
+        }
+    }
+
+    /// Whether every player sent its message of a round
+    fn all<T>(&self, received: &HashMap<usize, T>) -> bool {
+        self.config
+            .players
+            .iter()
+            .all(|id| received.contains_key(id))
+    }
+
+    /// Moves through every round the messages received so far complete
+    fn advance(&mut self, outbox: &mut Outbox) {
+        if self.round == Round::Deal && self.all(&self.commitments) && self.all(&self.shares) {
+            self.round = Round::Complain;
+            let DkgConfig {
+                p,
+                q,
+                g,
+                t,
+                players,
+                ..
+            } = &self.config;
+            let index = BigUint::from(self.id);
+            // every dealing is checked at our index, its powers and g's windows are shared
+            let n = players.iter().copied().max().unwrap_or(0);
+            let mut verifier = vss::Verifier::new(g, p, n).with_windows(q.bits());
+            let against: Vec<usize> = self
+                .config
+                .players
+                .iter()
+                .copied()
+                .filter(|dealer| {
+                    let (share, commitments) = (&self.shares[dealer], &self.commitments[dealer]);
+                    *share.index() != index
+                        || commitments.len() != *t
+                        || !verifier.verify_share(share.index(), share.value(), commitments)
+                })
+                .collect();
+            if !against.is_empty() {
+                warn!(?against, "complaining about dealings that did not verify");
+            }
+
+            self.emit(outbox, DkgMessage::Complaint(against));
+        }
+
+        if self.round == Round::Complain && self.all(&self.complaints) {
+            self.round = Round::Qualify;
+            let accused: HashSet<usize> = self.complaints.values().flatten().copied().collect();
+            let qualified = self
+                .config
+                .players
+                .iter()
+                .copied()
+                .filter(|dealer| !accused.contains(dealer))
+                .collect();
+
+            self.emit(outbox, DkgMessage::Qualified(qualified));
+        }
+
+        if self.round == Round::Qualify && self.all(&self.qualified) {
+            self.round = Round::Publish;
+            let share_info = self.key_share();
+            let y = share_info
+                .g()
+                .modpow(share_info.share().value(), share_info.p());
+            self.share_info = Some(share_info);
+
+            self.emit(outbox, DkgMessage::PublicKeyShare(y));
+        }
+
+        if self.round == Round::Publish
+            && self.output.is_none()
+            && self.all(&self.public_key_shares)
+        {
+            self.finish();
+        }
+    }
+
+    /// Dealers every player qualified
+    fn qualified(&self) -> Vec<usize> {
+        self.config
+            .players
+            .iter()
+            .copied()
+            .filter(|dealer| {
+                self.qualified
+                    .values()
+                    .all(|qualified| qualified.contains(dealer))
+            })
+            .collect()
+    }
+
+    /// Sums the points and commitments of the qualified dealers into our key share
+    fn key_share(&self) -> ShareInfo {
+        let DkgConfig { p, q, g, t, .. } = &self.config;
+        let qualified = self.qualified();
+        if qualified.len() < *t {
+            warn!(?qualified, "fewer qualified dealers than the threshold");
+        }
+
+        let value = qualified.iter().fold(BigUint::zero(), |x, dealer| {
+            (x + self.shares[dealer].value()) % q
+        });
+        let commitments = (0..*t)
+            .map(|k| {
+                qualified.iter().fold(BigUint::one(), |c, dealer| {
+                    c * &self.commitments[dealer][k] % p
+                })
+            })
+            .collect();
+
+        ShareInfo::new(
+            Share::new(BigUint::from(self.id), value),
+            g.clone(),
+            commitments,
+            p.clone(),
+            q.clone(),
+            *t,
+        )
+    }
+
+    /// Checks every published key share against the joint commitments
+    fn finish(&mut self) {
+        let share_info = match &self.share_info {
+            Some(share_info) => share_info.clone(),
+            None => return,
+        };
+
+        let (commitments, p) = (share_info.commitments(), share_info.p());
+        for (id, y) in &self.public_key_shares {
+            if *y != vss::commitment_at(&BigUint::from(*id), commitments, p) {
+                warn!(id, "published key share does not match the commitments");
+            }
+        }
+
+        self.output = Some(DkgOutput {
+            public_key: commitments[0].clone(),
+            public_key_shares: self.public_key_shares.clone(),
+            qualified: self.qualified(),
+            share_info,
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use num_bigint::BigUint;
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    use crate::dealer::BIT_SIZE;
+    use crate::rpc::Share;
+    use crate::vss;
+
+    use super::{Dkg, DkgConfig, DkgMessage, Outbox};
+
+    /// Runs a DKG among 5 players with threshold 3, delivering every message
+    fn run(corrupt: Option<usize>) -> Vec<Dkg> {
+        let mut rng = StdRng::seed_from_u64(7);
+        let config = DkgConfig::with_rng(3, (1..=5).collect(), BIT_SIZE, &mut rng);
+        let mut dkgs: Vec<Dkg> = (1..=5).map(|id| Dkg::new(id, config.clone())).collect();
+
+        let mut queue: Vec<(usize, Outbox)> = (1..=5)
+            .map(|id| (id, dkgs[id - 1].start(&mut rng)))
+            .collect();
+        while let Some((from, outbox)) = queue.pop() {
+            let mut sent: Vec<(usize, DkgMessage)> = outbox.send;
+            for msg in outbox.broadcast {
+                sent.extend((1..=5).filter(|id| *id != from).map(|id| (id, msg.clone())));
+            }
+
+            for (to, msg) in sent {
+                let msg = match msg {
+                    // the corrupt dealer deals a wrong point to player 1
+                    DkgMessage::DealingShare(share) if Some(from) == corrupt && to == 1 => {
+                        let value = share.value() + BigUint::from(1_usize);
+                        DkgMessage::DealingShare(Share::new(share.index().clone(), value))
+                    }
+                    msg => msg,
+                };
+                let outbox = dkgs[to - 1].on_message(from, msg);
+                queue.push((to, outbox));
+            }
+        }
+
+        dkgs
+    }
+
+    #[test]
+    fn dkg_generates_shared_key() {
+        let dkgs = run(None);
+        let outputs: Vec<_> = dkgs.iter().map(|dkg| dkg.output().unwrap()).collect();
+
+        let shares: Vec<Share> = outputs[1..4]
+            .iter()
+            .map(|output| output.share_info.share().clone())
+            .collect();
+        let info = &outputs[0].share_info;
+        let secret = vss::reconstruct(&shares, info.q()).unwrap();
+
+        assert_eq!(vec![1, 2, 3, 4, 5], outputs[0].qualified);
+        for output in &outputs {
+            assert!(output.share_info.verify());
+            assert_eq!(outputs[0].public_key, output.public_key);
+        }
+        assert_eq!(
+            info.g().modpow(secret.expose(), info.p()),
+            outputs[0].public_key
+        );
+    }
+
+    #[test]
+    fn dkg_disqualifies_dealer_with_invalid_share() {
+        let dkgs = run(Some(4));
+
+        for dkg in &dkgs {
+            let output = dkg.output().unwrap();
+            assert_eq!(vec![1, 2, 3, 5], output.qualified);
+            assert!(output.share_info.verify());
+        }
+    }
+}
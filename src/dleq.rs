@@ -0,0 +1,317 @@
+//! Chaum-Pedersen proofs that two numbers have the same discrete logarithm, log_g a = log_h b
+//! modulo p, without revealing it: the building block of publicly verifiable sharing, threshold
+//! decryption and VRFs over the groups of this crate
+//!
+//! The prover commits to a nonce w with t1 = g^w and t2 = h^w and answers the challenge c, a
+//! hash of the statement, the commitments and the caller's context, with z = w + c * x over q.
+//! Proofs are non-interactive and bound to their context, so a proof made for one purpose does
+//! not verify for another.
+
+use std::collections::{HashMap, HashSet};
+
+use num::{One, Zero};
+use num_bigint::BigUint;
+use num_primes::RandBigInt;
+use rand::{CryptoRng, RngCore};
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+#[cfg(feature = "serde")]
+use crate::codec;
+use crate::secret;
+
+/// Hashed ahead of every challenge, so no other protocol's hash collides with one
+const DOMAIN: &[u8] = b"rust_vss dleq v1";
+
+/// Bits of the random weights proofs are combined with in `verify_batch`
+const BATCH_WEIGHT_BITS: usize = 128;
+
+/// The claim that log_g a = log_h b modulo p
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Statement {
+    #[cfg_attr(feature = "serde", serde(with = "codec::biguint"))]
+    pub g: BigUint,
+    #[cfg_attr(feature = "serde", serde(with = "codec::biguint"))]
+    pub h: BigUint,
+    #[cfg_attr(feature = "serde", serde(with = "codec::biguint"))]
+    pub a: BigUint,
+    #[cfg_attr(feature = "serde", serde(with = "codec::biguint"))]
+    pub b: BigUint,
+}
+
+/// A proof of a Statement
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Proof {
+    /// g^w of the nonce w
+    #[cfg_attr(feature = "serde", serde(with = "codec::biguint"))]
+    pub t1: BigUint,
+    /// h^w of the nonce w
+    #[cfg_attr(feature = "serde", serde(with = "codec::biguint"))]
+    pub t2: BigUint,
+    /// w + c * x over primefield q
+    #[cfg_attr(feature = "serde", serde(with = "codec::biguint"))]
+    pub z: BigUint,
+}
+
+impl Statement {
+    /// The statement that g^x and h^x share the logarithm x
+    pub fn new(x: &BigUint, g: &BigUint, h: &BigUint, p: &BigUint) -> Statement {
+        Statement {
+            g: g.clone(),
+            h: h.clone(),
+            a: g.modpow(x, p),
+            b: h.modpow(x, p),
+        }
+    }
+}
+
+/// Hashes the statement, the nonce commitments and `context` into the challenge c
+///
+/// Numbers are padded to the length of p so the encoding is unambiguous
+pub fn challenge(
+    statement: &Statement,
+    t1: &BigUint,
+    t2: &BigUint,
+    context: &[u8],
+    p: &BigUint,
+    q: &BigUint,
+) -> BigUint {
+    let len = p.to_bytes_be().len();
+    let mut hasher = Sha256::new();
+    hasher.update(DOMAIN);
+    for x in [
+        &statement.g,
+        &statement.h,
+        &statement.a,
+        &statement.b,
+        t1,
+        t2,
+    ] {
+        let bytes = x.to_bytes_be();
+        hasher.update(vec![0; len.saturating_sub(bytes.len())]);
+        hasher.update(bytes);
+    }
+    hasher.update((context.len() as u64).to_be_bytes());
+    hasher.update(context);
+
+    BigUint::from_bytes_be(&hasher.finalize()) % q
+}
+
+/// Prove `statement` for `context` knowing its logarithm `x`
+pub fn prove<R: RngCore + CryptoRng>(
+    x: &BigUint,
+    statement: &Statement,
+    context: &[u8],
+    p: &BigUint,
+    q: &BigUint,
+    rng: &mut R,
+) -> Proof {
+    let w = rng.gen_biguint_below(q);
+    let t1 = statement.g.modpow(&w, p);
+    let t2 = statement.h.modpow(&w, p);
+    let c = challenge(statement, &t1, &t2, context, p, q);
+
+    Proof {
+        z: (w + c * x) % q,
+        t1,
+        t2,
+    }
+}
+
+/// Whether `x` is a residue modulo p other than zero
+fn is_residue(x: &BigUint, p: &BigUint) -> bool {
+    !x.is_zero() && x < p
+}
+
+/// Whether every number of the statement and proof is in range
+fn is_well_formed(statement: &Statement, proof: &Proof, p: &BigUint, q: &BigUint) -> bool {
+    let Statement { g, h, a, b } = statement;
+
+    [g, h, a, b, &proof.t1, &proof.t2]
+        .iter()
+        .all(|x| is_residue(x, p))
+        && proof.z < *q
+}
+
+/// Verify that g^z = t1 * a^c and h^z = t2 * b^c mod p for the challenge c of the proof
+pub fn verify(
+    statement: &Statement,
+    proof: &Proof,
+    context: &[u8],
+    p: &BigUint,
+    q: &BigUint,
+) -> bool {
+    if !is_well_formed(statement, proof, p, q) {
+        return false;
+    }
+    let c = challenge(statement, &proof.t1, &proof.t2, context, p, q);
+
+    secret::ct_eq(
+        &statement.g.modpow(&proof.z, p),
+        &(&proof.t1 * statement.a.modpow(&c, p) % p),
+    ) && secret::ct_eq(
+        &statement.h.modpow(&proof.z, p),
+        &(&proof.t2 * statement.b.modpow(&c, p) % p),
+    )
+}
+
+/// Verify every proof of `proofs` for `context` at once, true only if each would verify
+///
+/// Both equations of every proof are raised to random weights and multiplied together, and the
+/// exponents of bases the proofs share, such as a common g, are summed over q, so a batch costs
+/// two exponentiations per distinct base rather than four per proof. Summing over q only holds
+/// in the subgroup of order q, so a batch with any number outside it fails, the one of the two
+/// exponentiations checking that base^q = 1.
+pub fn verify_batch<R: RngCore + CryptoRng>(
+    proofs: &[(Statement, Proof)],
+    context: &[u8],
+    p: &BigUint,
+    q: &BigUint,
+    rng: &mut R,
+) -> bool {
+    // exponent of every base on either side of the combined equation
+    let mut lhs: HashMap<&BigUint, BigUint> = HashMap::new();
+    let mut rhs: HashMap<&BigUint, BigUint> = HashMap::new();
+    for (statement, proof) in proofs {
+        if !is_well_formed(statement, proof, p, q) {
+            return false;
+        }
+        let c = challenge(statement, &proof.t1, &proof.t2, context, p, q);
+        let (rho, sigma) = (
+            rng.gen_biguint(BATCH_WEIGHT_BITS),
+            rng.gen_biguint(BATCH_WEIGHT_BITS),
+        );
+
+        for (base, exponent) in [
+            (&statement.g, &rho * &proof.z),
+            (&statement.h, &sigma * &proof.z),
+        ] {
+            let sum = lhs.entry(base).or_default();
+            *sum = (&*sum + exponent) % q;
+        }
+        for (base, exponent) in [
+            (&proof.t1, rho.clone()),
+            (&statement.a, &rho * &c),
+            (&proof.t2, sigma.clone()),
+            (&statement.b, &sigma * &c),
+        ] {
+            let sum = rhs.entry(base).or_default();
+            *sum = (&*sum + exponent) % q;
+        }
+    }
+
+    // a number with a component of order other than q cancels out of exponents summed over q
+    let bases: HashSet<&BigUint> = lhs.keys().chain(rhs.keys()).copied().collect();
+    if !bases.iter().all(|base| base.modpow(q, p).is_one()) {
+        return false;
+    }
+
+    let product = |exponents: HashMap<&BigUint, BigUint>| {
+        exponents
+            .into_iter()
+            .fold(BigUint::one(), |acc, (base, exponent)| {
+                acc * base.modpow(&exponent, p) % p
+            })
+    };
+
+    secret::ct_eq(&product(lhs), &product(rhs))
+}
+
+#[cfg(test)]
+mod tests {
+    use num::Integer;
+    use num_bigint::BigUint;
+    use num_primes::RandBigInt;
+
+    use crate::dealer::Dealer;
+
+    use super::{challenge, prove, verify, verify_batch, Proof, Statement};
+
+    /// p, q, g and a second generator h of the same group
+    fn group() -> (BigUint, BigUint, BigUint, BigUint) {
+        let mut rng = rand::thread_rng();
+        let (p, q, g) = Dealer::group_of(64, &mut rng);
+        let h = g.modpow(&rng.gen_biguint_below(&q), &p);
+
+        (p, q, g, h)
+    }
+
+    #[test]
+    fn proofs_verify_for_their_statement_and_context() {
+        let (p, q, g, h) = group();
+        let mut rng = rand::thread_rng();
+        let x = rng.gen_biguint_below(&q);
+        let statement = Statement::new(&x, &g, &h, &p);
+        let proof = prove(&x, &statement, b"decrypt", &p, &q, &mut rng);
+
+        assert!(verify(&statement, &proof, b"decrypt", &p, &q));
+        assert!(!verify(&statement, &proof, b"sign", &p, &q));
+
+        // a and b of different logarithms
+        let other = Statement {
+            b: h.modpow(&(&x + 1_u32), &p),
+            ..statement.clone()
+        };
+        let proof = prove(&x, &other, b"decrypt", &p, &q, &mut rng);
+        assert!(!verify(&other, &proof, b"decrypt", &p, &q));
+
+        #[cfg(feature = "serde")]
+        {
+            let proof = prove(&x, &statement, b"decrypt", &p, &q, &mut rng);
+            let json = serde_json::to_string(&(&statement, &proof)).unwrap();
+            let (statement, proof): (Statement, Proof) = serde_json::from_str(&json).unwrap();
+            assert!(verify(&statement, &proof, b"decrypt", &p, &q));
+        }
+    }
+
+    #[test]
+    fn batch_fails_with_any_bad_proof() {
+        let (p, q, g, _) = group();
+        let mut rng = rand::thread_rng();
+        // one g and a key h per player, as a PVSS dealing has
+        let mut proofs: Vec<(Statement, Proof)> = (0..5)
+            .map(|_| {
+                let h = g.modpow(&rng.gen_biguint_below(&q), &p);
+                let x = rng.gen_biguint_below(&q);
+                let statement = Statement::new(&x, &g, &h, &p);
+                let proof = prove(&x, &statement, b"pvss", &p, &q, &mut rng);
+
+                (statement, proof)
+            })
+            .collect();
+        assert!(verify_batch(&proofs, b"pvss", &p, &q, &mut rng));
+        assert!(verify_batch(&[], b"pvss", &p, &q, &mut rng));
+
+        proofs[3].1.z = (&proofs[3].1.z + 1_u32) % &q;
+        assert!(!verify_batch(&proofs, b"pvss", &p, &q, &mut rng));
+        assert!(!verify(&proofs[3].0, &proofs[3].1, b"pvss", &p, &q));
+    }
+
+    #[test]
+    fn batch_fails_with_numbers_outside_the_subgroup() {
+        let (p, q, g, h) = group();
+        let mut rng = rand::thread_rng();
+        let x = rng.gen_biguint_below(&q);
+        // -a has a component of order 2, which the weight on a cancels half of the time
+        let statement = Statement {
+            a: &p - g.modpow(&x, &p),
+            ..Statement::new(&x, &g, &h, &p)
+        };
+        let proof = loop {
+            let proof = prove(&x, &statement, b"pvss", &p, &q, &mut rng);
+            let c = challenge(&statement, &proof.t1, &proof.t2, b"pvss", &p, &q);
+            if c.is_odd() {
+                break proof;
+            }
+        };
+        assert!(!verify(&statement, &proof, b"pvss", &p, &q));
+
+        let proofs = [(statement, proof)];
+        for _ in 0..16 {
+            assert!(!verify_batch(&proofs, b"pvss", &p, &q, &mut rng));
+        }
+    }
+}
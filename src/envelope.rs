@@ -0,0 +1,185 @@
+//! Share a data-encryption key rather than the data: `seal` encrypts a payload of any size under
+//! a random key and splits only the key, `open` combines the key back and decrypts
+//!
+//! Every shareholder keeps the `Sealed` payload and its share of the key. The ciphertext is
+//! bound to the dealing of the key, so shares of another dealing fail to open it even if they
+//! combine.
+
+use std::io;
+
+use aes_gcm::aead::{self, Aead, KeyInit, Payload};
+use aes_gcm::Aes256Gcm;
+use chacha20poly1305::ChaCha20Poly1305;
+use rand::{CryptoRng, Rng, RngCore};
+use serde::{Deserialize, Serialize};
+use zeroize::Zeroizing;
+
+use crate::format::{self, b64_bytes};
+use crate::simple::{self, EncodedShare};
+
+/// Version of the `Sealed` layout
+pub const SEALED_VERSION: u32 = 1;
+
+/// Bytes of the data-encryption key
+const KEY_LEN: usize = 32;
+
+/// Bytes of the nonce of either cipher
+const NONCE_LEN: usize = 12;
+
+/// Prefix of the associated data, followed by the fingerprint of the key's dealing
+const SEALED_AAD: &[u8] = b"rust_vss sealed v1";
+
+/// The AEAD a payload is sealed with
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Cipher {
+    #[serde(rename = "aes-256-gcm")]
+    Aes256Gcm,
+    #[serde(rename = "chacha20-poly1305")]
+    ChaCha20Poly1305,
+}
+
+impl Cipher {
+    fn encrypt(self, key: &[u8], nonce: &[u8], payload: Payload) -> Result<Vec<u8>, aead::Error> {
+        match self {
+            Cipher::Aes256Gcm => Aes256Gcm::new(key.into()).encrypt(nonce.into(), payload),
+            Cipher::ChaCha20Poly1305 => {
+                ChaCha20Poly1305::new(key.into()).encrypt(nonce.into(), payload)
+            }
+        }
+    }
+
+    fn decrypt(self, key: &[u8], nonce: &[u8], payload: Payload) -> Result<Vec<u8>, aead::Error> {
+        match self {
+            Cipher::Aes256Gcm => Aes256Gcm::new(key.into()).decrypt(nonce.into(), payload),
+            Cipher::ChaCha20Poly1305 => {
+                ChaCha20Poly1305::new(key.into()).decrypt(nonce.into(), payload)
+            }
+        }
+    }
+}
+
+/// A payload encrypted under a key that was split, see `seal`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Sealed {
+    pub version: u32,
+    pub cipher: Cipher,
+    /// Fingerprint of the dealing of the key, see `format::fingerprint`
+    pub fingerprint: String,
+    #[serde(with = "b64_bytes")]
+    pub nonce: Vec<u8>,
+    #[serde(with = "b64_bytes")]
+    pub ciphertext: Vec<u8>,
+}
+
+/// Associated data of a payload sealed under the key of the dealing `fingerprint`
+fn aad(fingerprint: &str) -> Vec<u8> {
+    [SEALED_AAD, fingerprint.as_bytes()].concat()
+}
+
+/// Encrypt `data` with `cipher` under a random key and split the key into `n` shares, any `t`
+/// of which `open` it
+pub fn seal(
+    data: &[u8],
+    n: usize,
+    t: usize,
+    cipher: Cipher,
+) -> io::Result<(Sealed, Vec<EncodedShare>)> {
+    seal_with_rng(data, n, t, cipher, &mut rand::thread_rng())
+}
+
+/// Like `seal` with the key, nonce and dealing of the key drawn from `rng`
+pub fn seal_with_rng<R: RngCore + CryptoRng>(
+    data: &[u8],
+    n: usize,
+    t: usize,
+    cipher: Cipher,
+    rng: &mut R,
+) -> io::Result<(Sealed, Vec<EncodedShare>)> {
+    let mut key = Zeroizing::new([0_u8; KEY_LEN]);
+    let mut nonce = vec![0; NONCE_LEN];
+    rng.fill(&mut key[..]);
+    rng.fill(&mut nonce[..]);
+
+    let shares = simple::split_with_rng(&key[..], n, t, rng)?;
+    let fingerprint = format::fingerprint(&shares[0].info()?);
+    let aad = aad(&fingerprint);
+    let ciphertext = cipher
+        .encrypt(
+            &key[..],
+            &nonce,
+            Payload {
+                msg: data,
+                aad: &aad,
+            },
+        )
+        .map_err(|_| io::Error::other("sealing failed"))?;
+
+    let sealed = Sealed {
+        version: SEALED_VERSION,
+        cipher,
+        fingerprint,
+        nonce,
+        ciphertext,
+    };
+
+    Ok((sealed, shares))
+}
+
+/// The payload of `sealed`, decrypted under the key `shares` combine into
+pub fn open(sealed: &Sealed, shares: &[EncodedShare]) -> io::Result<Vec<u8>> {
+    let invalid = |msg: &str| io::Error::new(io::ErrorKind::InvalidData, msg.to_string());
+    if sealed.version != SEALED_VERSION {
+        return Err(invalid("unsupported sealed payload version"));
+    }
+    if sealed.nonce.len() != NONCE_LEN {
+        return Err(invalid("sealed payload nonce has the wrong length"));
+    }
+
+    if let Some(share) = shares.first() {
+        if format::fingerprint(&share.info()?) != sealed.fingerprint {
+            return Err(invalid(
+                "shares are of another dealing than the payload's key",
+            ));
+        }
+    }
+
+    let key = simple::combine(shares)?;
+    if key.expose().len() != KEY_LEN {
+        return Err(invalid("shares are not of a sealing key"));
+    }
+    let aad = aad(&sealed.fingerprint);
+    sealed
+        .cipher
+        .decrypt(
+            key.expose(),
+            &sealed.nonce,
+            Payload {
+                msg: &sealed.ciphertext,
+                aad: &aad,
+            },
+        )
+        .map_err(|_| invalid("shares are not of this payload's key or it was tampered with"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{open, seal, Cipher};
+
+    #[test]
+    fn only_the_key_is_split() {
+        let data = vec![7_u8; 100_000];
+        for cipher in [Cipher::Aes256Gcm, Cipher::ChaCha20Poly1305] {
+            let (sealed, shares) = seal(&data, 4, 2, cipher).unwrap();
+            assert_eq!(data, open(&sealed, &shares[2..]).unwrap());
+            assert!(open(&sealed, &shares[..1]).is_err());
+
+            let mut tampered = sealed.clone();
+            tampered.ciphertext[0] ^= 1;
+            assert!(open(&tampered, &shares).is_err());
+
+            // shares of another key are refused
+            let (_, others) = seal(&data, 4, 2, cipher).unwrap();
+            assert!(open(&sealed, &others).is_err());
+        }
+    }
+}
@@ -0,0 +1,71 @@
+//! Errors of dealing, verifying and reconstructing shares
+
+use std::io;
+use std::time::Duration;
+
+use num_bigint::BigUint;
+use thiserror::Error;
+
+/// Why an operation of the crate failed
+#[derive(Debug, Error)]
+pub enum VssError {
+    /// A dealing needs 1 <= t <= n
+    #[error("threshold {t} is not between 1 and the {n} players")]
+    InvalidThreshold { t: usize, n: usize },
+    /// Interpolation needs at least one share
+    #[error("no shares to reconstruct from")]
+    NoShares,
+    /// Interpolation needs every x coordinate to be different
+    #[error("share index {0} appears more than once")]
+    DuplicateIndex(BigUint),
+    /// Public parameters that do not describe a group of prime order q mod p, see
+    /// `PublicParams::validate`
+    #[error("invalid public parameters: {0}")]
+    InvalidParams(String),
+    /// A number wider than the integers of the fixed-width backend, see `fixed`
+    #[error("{bits} bits do not fit {max} bit fixed-width integers")]
+    TooWide { bits: usize, max: usize },
+    /// Montgomery arithmetic of the fixed-width backend needs an odd modulus
+    #[error("modulus {0} is even")]
+    EvenModulus(BigUint),
+    /// A field that is not one, such as GF(p^k) modulo a reducible polynomial, see `field`
+    #[error("invalid field: {0}")]
+    InvalidField(String),
+    /// An index or number past the elements of a field
+    #[error("{0} is not the number of an element of the field")]
+    NotInField(BigUint),
+    /// An access policy that does not parse or has a threshold outside 1..=n, see `policy`
+    #[error("invalid access policy: {0}")]
+    InvalidPolicy(String),
+    /// The parties of the shares do not satisfy the access policy they were dealt under
+    #[error("shares do not satisfy the access policy")]
+    Unqualified,
+    /// Whoever waited on a response or message has gone away
+    #[error("the receiving end has disconnected")]
+    Disconnected,
+    /// Nobody answered in time, after every retry
+    #[error("no answer within {0:?}")]
+    TimedOut(Duration),
+    #[error(transparent)]
+    Io(#[from] io::Error),
+}
+
+impl From<VssError> for io::Error {
+    fn from(err: VssError) -> io::Error {
+        let kind = match err {
+            VssError::Io(err) => return err,
+            VssError::InvalidThreshold { .. } => io::ErrorKind::InvalidInput,
+            VssError::NoShares | VssError::DuplicateIndex(_) => io::ErrorKind::InvalidData,
+            VssError::InvalidParams(_) => io::ErrorKind::InvalidData,
+            VssError::TooWide { .. } | VssError::EvenModulus(_) => io::ErrorKind::InvalidInput,
+            VssError::InvalidField(_) => io::ErrorKind::InvalidInput,
+            VssError::NotInField(_) => io::ErrorKind::InvalidData,
+            VssError::InvalidPolicy(_) => io::ErrorKind::InvalidInput,
+            VssError::Unqualified => io::ErrorKind::InvalidData,
+            VssError::Disconnected => io::ErrorKind::BrokenPipe,
+            VssError::TimedOut(_) => io::ErrorKind::TimedOut,
+        };
+
+        io::Error::new(kind, err)
+    }
+}
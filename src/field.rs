@@ -0,0 +1,547 @@
+//! Shamir sharing over any finite field, not only the prime field q of `vss`
+//!
+//! `Field` is the arithmetic a sharing needs, `split` and `reconstruct` deal and interpolate
+//! over any implementation of it. `PrimeField` is GF(q) as `vss` computes in it and
+//! `ExtensionField` is GF(p^k) of a small prime p, polynomials over GF(p) modulo an irreducible
+//! one of degree k, such as GF(2^128) for compact shares or schemes defined over it.
+//!
+//! Shares are the crate's `Share`s: every element is numbered by its coefficients as the digits
+//! of a base p number, lowest first, so index i is the element numbered i and a value is the
+//! number of its element. Extension fields have no group to commit in, so their shares carry no
+//! commitments, deal the secret they recover again for a verifiable sharing.
+
+use std::collections::HashSet;
+use std::fmt;
+
+use num::{Integer, One, ToPrimitive, Zero};
+use num_bigint::{BigUint, RandBigInt};
+use rand::{CryptoRng, Rng, RngCore};
+
+use crate::error::VssError;
+use crate::rpc::Share;
+use crate::secret::Secret;
+
+/// The arithmetic of a finite field, as much of it as sharing needs
+pub trait Field {
+    type Element: Clone + PartialEq + fmt::Debug;
+
+    /// Number of elements
+    fn order(&self) -> BigUint;
+    fn zero(&self) -> Self::Element;
+    fn one(&self) -> Self::Element;
+    fn add(&self, a: &Self::Element, b: &Self::Element) -> Self::Element;
+    fn sub(&self, a: &Self::Element, b: &Self::Element) -> Self::Element;
+    fn mul(&self, a: &Self::Element, b: &Self::Element) -> Self::Element;
+    /// The inverse of a nonzero `a`
+    fn inverse(&self, a: &Self::Element) -> Self::Element;
+    fn random<R: Rng + ?Sized>(&self, rng: &mut R) -> Self::Element;
+    /// The element numbered `n`, none if n is not below the order
+    fn element(&self, n: &BigUint) -> Option<Self::Element>;
+    /// The number of `element`, below the order
+    fn number(&self, element: &Self::Element) -> BigUint;
+}
+
+/// GF(q) of a prime q, the field `vss` shares over
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PrimeField {
+    q: BigUint,
+}
+
+impl PrimeField {
+    /// GF(q), q being prime as a dealing's q is
+    pub fn new(q: BigUint) -> PrimeField {
+        PrimeField { q }
+    }
+}
+
+impl Field for PrimeField {
+    type Element = BigUint;
+
+    fn order(&self) -> BigUint {
+        self.q.clone()
+    }
+
+    fn zero(&self) -> BigUint {
+        BigUint::zero()
+    }
+
+    fn one(&self) -> BigUint {
+        BigUint::one()
+    }
+
+    fn add(&self, a: &BigUint, b: &BigUint) -> BigUint {
+        (a + b) % &self.q
+    }
+
+    fn sub(&self, a: &BigUint, b: &BigUint) -> BigUint {
+        (a + &self.q - b) % &self.q
+    }
+
+    fn mul(&self, a: &BigUint, b: &BigUint) -> BigUint {
+        (a * b) % &self.q
+    }
+
+    fn inverse(&self, a: &BigUint) -> BigUint {
+        a.modpow(&(&self.q - 2_u32), &self.q)
+    }
+
+    fn random<R: Rng + ?Sized>(&self, rng: &mut R) -> BigUint {
+        rng.gen_biguint_below(&self.q)
+    }
+
+    fn element(&self, n: &BigUint) -> Option<BigUint> {
+        Some(n.clone()).filter(|n| *n < self.q)
+    }
+
+    fn number(&self, element: &BigUint) -> BigUint {
+        element.clone()
+    }
+}
+
+/// GF(p^k): polynomials over GF(p) of degree below k, multiplied modulo a monic irreducible
+/// polynomial of degree k
+///
+/// An element is its k coefficients, lowest first. p is at most 2^32 so coefficients multiply
+/// in a u64.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ExtensionField {
+    p: u64,
+    /// the k + 1 coefficients of the monic modulus, lowest first
+    modulus: Vec<u64>,
+}
+
+impl ExtensionField {
+    /// GF(p^k) modulo the monic polynomial of the k + 1 `modulus` coefficients, lowest first
+    ///
+    /// Fails unless p is a prime of at most 32 bits and the modulus is irreducible over GF(p)
+    pub fn new(p: u64, modulus: &[u64]) -> Result<ExtensionField, VssError> {
+        let invalid = |msg: &str| VssError::InvalidField(msg.to_string());
+        if p > u64::from(u32::MAX) || !is_small_prime(p) {
+            return Err(invalid(
+                "the characteristic must be a prime of at most 32 bits",
+            ));
+        }
+        if modulus.len() < 2 || modulus.last() != Some(&1) || modulus.iter().any(|c| *c >= p) {
+            return Err(invalid(
+                "the modulus must be monic over GF(p), of degree 1 or more",
+            ));
+        }
+        let field = ExtensionField {
+            p,
+            modulus: modulus.to_vec(),
+        };
+        if !field.is_irreducible() {
+            return Err(invalid("the modulus is not irreducible"));
+        }
+
+        Ok(field)
+    }
+
+    /// GF(2^128) modulo x^128 + x^7 + x^2 + x + 1, the field of GHASH
+    pub fn gf2_128() -> ExtensionField {
+        let mut modulus = vec![0; 129];
+        for i in [0, 1, 2, 7, 128] {
+            modulus[i] = 1;
+        }
+
+        ExtensionField { p: 2, modulus }
+    }
+
+    /// The characteristic p
+    pub fn characteristic(&self) -> u64 {
+        self.p
+    }
+
+    /// The degree k over GF(p)
+    pub fn degree(&self) -> usize {
+        self.modulus.len() - 1
+    }
+
+    /// Bytes of the big endian number of an element, the size of a share value
+    pub fn element_len(&self) -> usize {
+        (self.order() - 1_u32).bits().div_ceil(8)
+    }
+
+    /// The number of `element` in exactly `element_len` big endian bytes
+    pub fn to_bytes(&self, element: &[u64]) -> Vec<u8> {
+        let bytes = self.number(&element.to_vec()).to_bytes_be();
+        let mut padded = vec![0; self.element_len()];
+        if !element.iter().all(Zero::is_zero) {
+            padded[self.element_len() - bytes.len()..].copy_from_slice(&bytes);
+        }
+
+        padded
+    }
+
+    /// The element numbered by the big endian `bytes`, none unless they are `element_len` long
+    /// and below the order
+    pub fn from_bytes(&self, bytes: &[u8]) -> Option<Vec<u64>> {
+        if bytes.len() != self.element_len() {
+            return None;
+        }
+
+        self.element(&BigUint::from_bytes_be(bytes))
+    }
+
+    /// Rabin's test: x^(p^k) = x modulo f and gcd(x^(p^(k/r)) - x, f) = 1 for every prime r
+    /// dividing k
+    fn is_irreducible(&self) -> bool {
+        let k = self.degree();
+        let x = self.reduce(vec![0, 1]);
+        // x^(p^i) for i = 0..=k
+        let mut frobenius = vec![x.clone()];
+        for i in 0..k {
+            let next = self.pow_u64(&frobenius[i], self.p);
+            frobenius.push(next);
+        }
+        if frobenius[k] != x {
+            return false;
+        }
+
+        prime_factors(k).into_iter().all(|r| {
+            let h = self.sub(&frobenius[k / r], &x);
+            poly_gcd(h, self.modulus.clone(), self.p).len() == 1
+        })
+    }
+
+    /// `poly` modulo the modulus, as k coefficients
+    fn reduce(&self, mut poly: Vec<u64>) -> Vec<u64> {
+        let k = self.degree();
+        for top in (k..poly.len()).rev() {
+            let c = poly[top];
+            if c != 0 {
+                for (j, m) in self.modulus[..k].iter().enumerate() {
+                    let i = top - k + j;
+                    poly[i] = (poly[i] + (self.p - c) * m) % self.p;
+                }
+            }
+        }
+        poly.resize(k, 0);
+
+        poly
+    }
+
+    fn pow_u64(&self, a: &[u64], exponent: u64) -> Vec<u64> {
+        let mut result = self.one();
+        for bit in (0..64 - exponent.leading_zeros()).rev() {
+            result = self.mul(&result, &result);
+            if exponent >> bit & 1 == 1 {
+                result = self.mul(&result, &a.to_vec());
+            }
+        }
+
+        result
+    }
+}
+
+impl Field for ExtensionField {
+    type Element = Vec<u64>;
+
+    fn order(&self) -> BigUint {
+        num::pow(BigUint::from(self.p), self.degree())
+    }
+
+    fn zero(&self) -> Vec<u64> {
+        vec![0; self.degree()]
+    }
+
+    fn one(&self) -> Vec<u64> {
+        let mut one = self.zero();
+        one[0] = 1;
+
+        one
+    }
+
+    fn add(&self, a: &Vec<u64>, b: &Vec<u64>) -> Vec<u64> {
+        a.iter().zip(b).map(|(a, b)| (a + b) % self.p).collect()
+    }
+
+    fn sub(&self, a: &Vec<u64>, b: &Vec<u64>) -> Vec<u64> {
+        a.iter()
+            .zip(b)
+            .map(|(a, b)| (a + self.p - b) % self.p)
+            .collect()
+    }
+
+    fn mul(&self, a: &Vec<u64>, b: &Vec<u64>) -> Vec<u64> {
+        let mut product = vec![0; 2 * self.degree()];
+        for (i, a_i) in a.iter().enumerate().filter(|(_, a_i)| **a_i != 0) {
+            for (j, b_j) in b.iter().enumerate() {
+                product[i + j] = (product[i + j] + a_i * b_j) % self.p;
+            }
+        }
+
+        self.reduce(product)
+    }
+
+    /// a^(p^k - 2)
+    fn inverse(&self, a: &Vec<u64>) -> Vec<u64> {
+        let exponent = self.order() - 2_u32;
+        let mut result = self.one();
+        for byte in exponent.to_bytes_be() {
+            for bit in (0..8).rev() {
+                result = self.mul(&result, &result);
+                if byte >> bit & 1 == 1 {
+                    result = self.mul(&result, a);
+                }
+            }
+        }
+
+        result
+    }
+
+    fn random<R: Rng + ?Sized>(&self, rng: &mut R) -> Vec<u64> {
+        (0..self.degree())
+            .map(|_| rng.gen_range(0, self.p))
+            .collect()
+    }
+
+    fn element(&self, n: &BigUint) -> Option<Vec<u64>> {
+        if *n >= self.order() {
+            return None;
+        }
+        let p = BigUint::from(self.p);
+        let mut n = n.clone();
+
+        Some(
+            (0..self.degree())
+                .map(|_| {
+                    let (rest, digit) = n.div_rem(&p);
+                    n = rest;
+                    digit.to_u64().unwrap_or_default()
+                })
+                .collect(),
+        )
+    }
+
+    fn number(&self, element: &Vec<u64>) -> BigUint {
+        element
+            .iter()
+            .rev()
+            .fold(BigUint::zero(), |n, c| n * self.p + *c)
+    }
+}
+
+/// Deal `secret`, the number of an element of `field`, as `n` shares any `t` of which
+/// reconstruct it
+pub fn split<F: Field>(
+    field: &F,
+    secret: &Secret,
+    n: usize,
+    t: usize,
+) -> Result<Vec<Share>, VssError> {
+    split_with_rng(field, secret, n, t, &mut rand::thread_rng())
+}
+
+/// Like `split` with the coefficients drawn from `rng`, so a seeded rng deals the same shares
+/// every time
+pub fn split_with_rng<F: Field, R: RngCore + CryptoRng>(
+    field: &F,
+    secret: &Secret,
+    n: usize,
+    t: usize,
+    rng: &mut R,
+) -> Result<Vec<Share>, VssError> {
+    if t == 0 || t > n {
+        return Err(VssError::InvalidThreshold { t, n });
+    }
+    let a_0 = field
+        .element(secret.expose())
+        .ok_or_else(|| VssError::InvalidField("the secret is not an element".to_string()))?;
+    let a: Vec<_> = std::iter::once(a_0)
+        .chain((1..t).map(|_| field.random(rng)))
+        .collect();
+
+    generate_shares(field, &a, n)
+}
+
+/// The shares (i, P(i)) for i = 1..=n of the polynomial with coefficients `a` over `field`,
+/// failing if n is not below the field's order
+pub fn generate_shares<F: Field>(
+    field: &F,
+    a: &[F::Element],
+    n: usize,
+) -> Result<Vec<Share>, VssError> {
+    (1..=n)
+        .map(|i| {
+            let index = BigUint::from(i);
+            let x = field
+                .element(&index)
+                .ok_or_else(|| VssError::NotInField(index.clone()))?;
+            let y = a
+                .iter()
+                .rev()
+                .fold(field.zero(), |y, a_i| field.add(&field.mul(&y, &x), a_i));
+
+            Ok(Share::new(index, field.number(&y)))
+        })
+        .collect()
+}
+
+/// The secret P(0) of `shares` over `field`, interpolating with Lagrange's formula
+///
+/// Fails without shares, on two shares with the same index, on index 0 or on indices or values
+/// that are not numbers of elements
+pub fn reconstruct<F: Field>(field: &F, shares: &[Share]) -> Result<Secret, VssError> {
+    if shares.is_empty() {
+        return Err(VssError::NoShares);
+    }
+    let mut seen = HashSet::new();
+    let mut points = vec![];
+    for share in shares {
+        if !seen.insert(share.index()) {
+            return Err(VssError::DuplicateIndex(share.index().clone()));
+        }
+        let x = field
+            .element(share.index())
+            .filter(|x| *x != field.zero())
+            .ok_or_else(|| VssError::NotInField(share.index().clone()))?;
+        // the value is not named, it is secret
+        let y = field.element(share.value()).ok_or_else(|| {
+            VssError::InvalidField(format!("share {} is not an element", share.index()))
+        })?;
+        points.push((x, y));
+    }
+
+    // every numerator and denominator multiplied out first, so there is one inversion per
+    // share
+    let mut secret = field.zero();
+    for (j, (x_j, y_j)) in points.iter().enumerate() {
+        let (mut num, mut den) = (field.one(), field.one());
+        for (m, (x_m, _)) in points.iter().enumerate() {
+            if m != j {
+                num = field.mul(&num, x_m);
+                den = field.mul(&den, &field.sub(x_m, x_j));
+            }
+        }
+        let coefficient = field.mul(&num, &field.inverse(&den));
+        secret = field.add(&secret, &field.mul(y_j, &coefficient));
+    }
+
+    Ok(Secret::new(field.number(&secret)))
+}
+
+/// Whether a p of at most 32 bits is prime, by trial division
+fn is_small_prime(p: u64) -> bool {
+    p >= 2
+        && (2..)
+            .take_while(|d| d * d <= p)
+            .all(|d| !p.is_multiple_of(d))
+}
+
+/// The distinct primes dividing k
+fn prime_factors(mut k: usize) -> Vec<usize> {
+    let mut factors = vec![];
+    let mut d = 2;
+    while d * d <= k {
+        if k.is_multiple_of(d) {
+            factors.push(d);
+            while k.is_multiple_of(d) {
+                k /= d;
+            }
+        }
+        d += 1;
+    }
+    if k > 1 {
+        factors.push(k);
+    }
+
+    factors
+}
+
+/// The monic gcd of two polynomials over GF(p), lowest coefficient first
+fn poly_gcd(mut a: Vec<u64>, mut b: Vec<u64>, p: u64) -> Vec<u64> {
+    trim(&mut a);
+    trim(&mut b);
+    while !b.is_empty() {
+        // a mod b
+        let lead = modpow_u64(b[b.len() - 1], p - 2, p);
+        while a.len() >= b.len() {
+            let c = a[a.len() - 1] * lead % p;
+            let shift = a.len() - b.len();
+            for (j, b_j) in b.iter().enumerate() {
+                a[shift + j] = (a[shift + j] + (p - c) * b_j) % p;
+            }
+            trim(&mut a);
+        }
+        std::mem::swap(&mut a, &mut b);
+    }
+
+    a
+}
+
+fn trim(poly: &mut Vec<u64>) {
+    while poly.last() == Some(&0) {
+        poly.pop();
+    }
+}
+
+fn modpow_u64(base: u64, exponent: u64, p: u64) -> u64 {
+    (0..64 - exponent.leading_zeros())
+        .rev()
+        .fold(1, |acc, bit| {
+            let acc = acc * acc % p;
+            if exponent >> bit & 1 == 1 {
+                acc * base % p
+            } else {
+                acc
+            }
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use num_bigint::BigUint;
+
+    use crate::error::VssError;
+    use crate::secret::Secret;
+    use crate::vss;
+
+    use super::{reconstruct, split, ExtensionField, Field, PrimeField};
+
+    #[test]
+    fn extension_fields_share_and_reconstruct() {
+        let gf = ExtensionField::gf2_128();
+        assert!(gf.is_irreducible());
+        assert_eq!(16, gf.element_len());
+
+        let secret = BigUint::from_bytes_be(&[0xa5; 16]);
+        let shares = split(&gf, &Secret::new(secret.clone()), 5, 3).unwrap();
+        assert!(shares.iter().all(|share| share.value().bits() <= 128));
+        assert_eq!(secret, *reconstruct(&gf, &shares[2..]).unwrap().expose());
+        assert_ne!(secret, *reconstruct(&gf, &shares[..2]).unwrap().expose());
+
+        // GF(3^5) modulo x^5 + 2x + 1
+        let gf = ExtensionField::new(3, &[1, 2, 0, 0, 0, 1]).unwrap();
+        let a = gf.element(&BigUint::from(200_u32)).unwrap();
+        assert_eq!(gf.one(), gf.mul(&a, &gf.inverse(&a)));
+        assert_eq!(a, gf.from_bytes(&gf.to_bytes(&a)).unwrap());
+        let shares = split(&gf, &Secret::new(BigUint::from(200_u32)), 7, 4).unwrap();
+        assert_eq!(
+            BigUint::from(200_u32),
+            *reconstruct(&gf, &shares[3..]).unwrap().expose()
+        );
+
+        // x^2 + 1 = (x + 1)^2 over GF(2), and 4 is not prime
+        assert!(matches!(
+            ExtensionField::new(2, &[1, 0, 1]),
+            Err(VssError::InvalidField(_))
+        ));
+        assert!(ExtensionField::new(4, &[1, 1, 1]).is_err());
+    }
+
+    #[test]
+    fn prime_field_matches_vss() {
+        let q = BigUint::from(7919_u32);
+        let field = PrimeField::new(q.clone());
+        let a = vec![BigUint::from(1234_u32), BigUint::from(17_u32)];
+
+        let shares = super::generate_shares(&field, &a, 4).unwrap();
+        assert_eq!(vss::generate_shares(&a, 4, &q), shares);
+        assert_eq!(
+            *vss::reconstruct(&shares[1..3], &q).unwrap().expose(),
+            *reconstruct(&field, &shares[1..3]).unwrap().expose()
+        );
+        assert!(split(&field, &Secret::new(q.clone()), 3, 2).is_err());
+    }
+}
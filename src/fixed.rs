@@ -0,0 +1,179 @@
+//! Constant-time dealing, verifying and reconstructing on the fixed-width integers of
+//! crypto-bigint, for shares that must not leak through timing
+//!
+//! num-bigint, which the rest of the crate uses, takes time that depends on the numbers it works
+//! on. Here every operation on a secret, a coefficient or a share value runs in Montgomery form
+//! on `Uint<LIMBS>` in time that depends on LIMBS only. The public parts, indices, Lagrange
+//! coefficients and commitments, are still computed with num-bigint. Pick LIMBS by the width of
+//! p: `{ U256::LIMBS }` holds the groups `Dealer::new` deals over, `{ U2048::LIMBS }` to
+//! `{ U4096::LIMBS }` the standard sizes. Numbers wider than that fail with `VssError::TooWide`.
+
+use crypto_bigint::modular::runtime_mod::{DynResidue, DynResidueParams};
+use crypto_bigint::subtle::ConstantTimeEq;
+use crypto_bigint::{Uint, Word};
+use num::Integer;
+use num_bigint::BigUint;
+use zeroize::Zeroizing;
+
+pub use crypto_bigint::{U1024, U2048, U256, U3072, U4096, U512};
+
+use crate::error::VssError;
+use crate::rpc::Share;
+use crate::secret::Secret;
+use crate::vss;
+
+/// n as a `Uint<LIMBS>`, failing if it is wider
+fn to_fixed<const LIMBS: usize>(n: &BigUint) -> Result<Uint<LIMBS>, VssError> {
+    let (bits, max) = (n.bits(), Uint::<LIMBS>::BITS);
+    if bits > max {
+        return Err(VssError::TooWide { bits, max });
+    }
+    let bytes = Zeroizing::new(n.to_bytes_be());
+    let mut padded = Zeroizing::new(vec![0_u8; Uint::<LIMBS>::BYTES]);
+    padded[Uint::<LIMBS>::BYTES - bytes.len()..].copy_from_slice(&bytes);
+
+    Ok(Uint::from_be_slice(&padded))
+}
+
+/// n as a `BigUint`
+fn from_fixed<const LIMBS: usize>(n: &Uint<LIMBS>) -> BigUint {
+    let bytes = Zeroizing::new(
+        n.as_words()
+            .iter()
+            .flat_map(|word: &Word| word.to_le_bytes())
+            .collect::<Vec<u8>>(),
+    );
+
+    BigUint::from_bytes_le(&bytes)
+}
+
+/// Montgomery parameters of an odd modulus
+fn params<const LIMBS: usize>(modulus: &BigUint) -> Result<DynResidueParams<LIMBS>, VssError> {
+    let fixed = to_fixed(modulus)?;
+    if modulus.is_even() {
+        return Err(VssError::EvenModulus(modulus.clone()));
+    }
+
+    Ok(DynResidueParams::new(&fixed))
+}
+
+/// `vss::generate_shares` in constant time: the polynomial with coefficients a evaluated at
+/// 1..=n over prime field q
+pub fn generate_shares<const LIMBS: usize>(
+    a: &[BigUint],
+    n: usize,
+    q: &BigUint,
+) -> Result<Vec<Share>, VssError> {
+    let params = params::<LIMBS>(q)?;
+    let a = a
+        .iter()
+        .map(|a_i| Ok(DynResidue::new(&to_fixed(a_i)?, params)))
+        .collect::<Result<Vec<_>, VssError>>()?;
+
+    (1..=n)
+        .map(|i| {
+            let x = DynResidue::new(&to_fixed(&BigUint::from(i))?, params);
+            // Horner's rule from a_t-1 down to a_0
+            let y = a
+                .iter()
+                .rev()
+                .fold(DynResidue::zero(params), |acc, a_i| acc * x + a_i);
+
+            Ok(Share::new(BigUint::from(i), from_fixed(&y.retrieve())))
+        })
+        .collect()
+}
+
+/// `vss::verify_share` in constant time: whether g^s mod p is the commitment at i. Numbers too
+/// wide for LIMBS or an even p fail
+pub fn verify_share<const LIMBS: usize>(
+    i: &BigUint,
+    s: &BigUint,
+    g: &BigUint,
+    c: &[BigUint],
+    p: &BigUint,
+) -> bool {
+    let check = || -> Result<bool, VssError> {
+        let params = params::<LIMBS>(p)?;
+        let s = Zeroizing::new(to_fixed::<LIMBS>(s)?);
+        let g_s = DynResidue::new(&to_fixed(g)?, params).pow(&*s).retrieve();
+        let expected = to_fixed::<LIMBS>(&vss::commitment_at(i, c, p))?;
+
+        Ok(g_s.ct_eq(&expected).into())
+    };
+
+    check().unwrap_or(false)
+}
+
+/// `vss::reconstruct` in constant time: the secret interpolated from shares over prime field q
+pub fn reconstruct<const LIMBS: usize>(shares: &[Share], q: &BigUint) -> Result<Secret, VssError> {
+    let params = params::<LIMBS>(q)?;
+    let xs = vss::indices(shares, q)?;
+
+    let mut secret = DynResidue::zero(params);
+    for (share, lambda) in shares.iter().zip(vss::lagrange_coefficients(&xs, q)?) {
+        let y = Zeroizing::new(to_fixed::<LIMBS>(share.value())?);
+        secret += DynResidue::new(&y, params) * DynResidue::new(&to_fixed(&lambda)?, params);
+    }
+
+    Ok(Secret::new(from_fixed(&secret.retrieve())))
+}
+
+#[cfg(test)]
+mod tests {
+    use num_bigint::BigUint;
+
+    use crate::dealer::Dealer;
+    use crate::error::VssError;
+    use crate::vss;
+
+    use super::{generate_shares, reconstruct, verify_share, U256};
+
+    const LIMBS: usize = U256::LIMBS;
+
+    #[test]
+    fn fixed_width_agrees_with_num_bigint() {
+        let dealer = Dealer::new(5, 3, 1234).unwrap();
+        let a: Vec<BigUint> = (1..=3_u32).map(|i| BigUint::from(1000 + i)).collect();
+
+        let shares = generate_shares::<LIMBS>(&a, 5, &dealer.q).unwrap();
+        assert_eq!(vss::generate_shares(&a, 5, &dealer.q), shares);
+        for share in &dealer.shares {
+            assert!(verify_share::<LIMBS>(
+                share.index(),
+                share.value(),
+                &dealer.g,
+                &dealer.c,
+                &dealer.p
+            ));
+            let wrong = share.value() + 1_u32;
+            assert!(!verify_share::<LIMBS>(
+                share.index(),
+                &wrong,
+                &dealer.g,
+                &dealer.c,
+                &dealer.p
+            ));
+        }
+
+        let secret = reconstruct::<LIMBS>(&dealer.shares[1..4], &dealer.q).unwrap();
+        assert_eq!(&BigUint::from(1234_u32), secret.expose());
+    }
+
+    #[test]
+    fn numbers_must_fit_and_moduli_be_odd() {
+        let dealer = Dealer::new(3, 2, 1234).unwrap();
+        let wide = (BigUint::from(1_u32) << 300) + 1_u32;
+        assert!(matches!(
+            reconstruct::<LIMBS>(&dealer.shares, &wide),
+            Err(VssError::TooWide {
+                bits: 301,
+                max: 256
+            })
+        ));
+        assert!(matches!(
+            generate_shares::<LIMBS>(&[BigUint::from(1_u32)], 2, &BigUint::from(16_u32)),
+            Err(VssError::EvenModulus(_))
+        ));
+    }
+}
@@ -0,0 +1,1162 @@
+use std::fs::File;
+use std::io::{self, BufReader, BufWriter, Read, Write};
+use std::path::Path;
+use std::sync::Arc;
+
+#[cfg(feature = "encryption")]
+use aes_gcm::aead::{Aead, KeyInit, Payload};
+#[cfg(feature = "encryption")]
+use aes_gcm::{Aes256Gcm, Key, Nonce};
+use num::ToPrimitive;
+use num_bigint::BigUint;
+#[cfg(feature = "encryption")]
+use rand::{CryptoRng, Rng, RngCore};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use zeroize::Zeroizing;
+
+use crate::codec;
+use crate::rpc::{Metadata, PublicParams, Share, ShareInfo, Validity};
+use crate::secret::{Secret, SecretBytes};
+#[cfg(feature = "modp")]
+use crate::sign::{self, Signature};
+use crate::vss;
+
+/// Version of the share files this crate writes, and the newest it reads
+pub const SHARE_FILE_VERSION: u32 = 1;
+/// Version of the transcripts this crate writes, and the newest it reads
+pub const TRANSCRIPT_VERSION: u32 = 1;
+/// Version of the signature files this crate writes, and the newest it reads
+#[cfg(feature = "modp")]
+pub const SIGNATURE_FILE_VERSION: u32 = 1;
+
+#[cfg(feature = "encryption")]
+const SALT_LEN: usize = 16;
+/// Bytes of the digest a fingerprint shows
+pub(crate) const FINGERPRINT_LEN: usize = 8;
+#[cfg(feature = "encryption")]
+const NONCE_LEN: usize = 12;
+/// Most Argon2 memory in KiB, passes and lanes a share file can ask for, 1 GiB in all, so a
+/// crafted file cannot make reading it take all the memory or time of the machine
+#[cfg(feature = "encryption")]
+const MAX_M_COST: u32 = 1 << 20;
+#[cfg(feature = "encryption")]
+const MAX_T_COST: u32 = 16;
+#[cfg(feature = "encryption")]
+const MAX_P_COST: u32 = 16;
+/// Binds the ciphertext of an encrypted share file to its purpose
+#[cfg(feature = "encryption")]
+const ENCRYPTED_SHARE_AAD: &[u8] = b"rust_vss encrypted share file";
+/// Marks where a secret of bytes starts, so leading zero bytes survive as part of the number
+const SECRET_SENTINEL: u8 = 1;
+
+/// The group a dealing's commitments are computed in
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Group {
+    #[serde(with = "b64")]
+    pub p: BigUint,
+    #[serde(with = "b64")]
+    pub q: BigUint,
+    #[serde(with = "b64")]
+    pub g: BigUint,
+}
+
+/// A share as kept on disk: a JSON document with every big number as base64 of its canonical
+/// big endian bytes
+///
+/// ```json
+/// {
+///   "version": 1,
+///   "index": 3,
+///   "value": "AQID",
+///   "threshold": 2,
+///   "group": { "p": "Cw==", "q": "BQ==", "g": "Aw==" },
+///   "commitments": ["BQ==", "AQ=="]
+/// }
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ShareFile {
+    pub version: u32,
+    pub index: u64,
+    #[serde(with = "b64")]
+    pub value: BigUint,
+    pub threshold: usize,
+    pub group: Group,
+    #[serde(with = "b64s")]
+    pub commitments: Vec<BigUint>,
+    /// Who keeps the share, when and what for it was dealt, left out when there is none
+    #[serde(default, skip_serializing_if = "Metadata::is_empty")]
+    pub metadata: Metadata,
+    /// Epoch of the dealing the share belongs to and the seconds since the Unix epoch it may
+    /// be used from and until, each left out when unset
+    #[serde(default, skip_serializing_if = "is_zero")]
+    pub epoch: u64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub not_before: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub not_after: Option<u64>,
+}
+
+fn is_zero(n: &u64) -> bool {
+    *n == 0
+}
+
+impl TryFrom<&ShareInfo> for ShareFile {
+    type Error = io::Error;
+
+    fn try_from(info: &ShareInfo) -> io::Result<ShareFile> {
+        let index = info.share().index().to_u64().ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "share index does not fit a u64",
+            )
+        })?;
+
+        Ok(ShareFile {
+            version: SHARE_FILE_VERSION,
+            index,
+            value: info.share().value().clone(),
+            threshold: info.t(),
+            group: Group {
+                p: info.p().clone(),
+                q: info.q().clone(),
+                g: info.g().clone(),
+            },
+            commitments: info.commitments().clone(),
+            metadata: info.metadata().clone(),
+            epoch: info.validity().epoch,
+            not_before: info.validity().not_before,
+            not_after: info.validity().not_after,
+        })
+    }
+}
+
+impl From<ShareFile> for ShareInfo {
+    fn from(file: ShareFile) -> ShareInfo {
+        let ShareFile {
+            index,
+            value,
+            threshold,
+            group: Group { p, q, g },
+            commitments,
+            metadata,
+            epoch,
+            not_before,
+            not_after,
+            ..
+        } = file;
+
+        ShareInfo::new(
+            Share::new(BigUint::from(index), value),
+            g,
+            commitments,
+            p,
+            q,
+            threshold,
+        )
+        .with_metadata(metadata)
+        .with_validity(Validity {
+            epoch,
+            not_before,
+            not_after,
+        })
+    }
+}
+
+/// Write `info` as a share file
+pub fn write_share<W: Write>(w: W, info: &ShareInfo) -> io::Result<()> {
+    let mut w = w;
+    serde_json::to_writer_pretty(&mut w, &ShareFile::try_from(info)?)?;
+
+    w.write_all(b"\n")
+}
+
+/// Read a share file, refusing versions newer than SHARE_FILE_VERSION and encrypted files
+pub fn read_share<R: Read>(r: R) -> io::Result<ShareInfo> {
+    read_share_with(r, || {
+        Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "share file is encrypted, a passphrase is needed",
+        ))
+    })
+}
+
+/// Read a share file, encrypted or not, asking `passphrase` for the passphrase of an
+/// encrypted one
+///
+/// Refuses a share whose public parameters fail `PublicParams::validate`
+pub fn read_share_with<R, F>(r: R, passphrase: F) -> io::Result<ShareInfo>
+where
+    R: Read,
+    F: FnOnce() -> io::Result<String>,
+{
+    let json: serde_json::Value = serde_json::from_reader(r)?;
+    let version = json["version"].as_u64().unwrap_or_default();
+    if version > SHARE_FILE_VERSION.into() {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("unsupported share file version {}", version),
+        ));
+    }
+
+    let info: ShareInfo = if json.get("ciphertext").is_some() {
+        decrypt(json, passphrase)?
+    } else {
+        let file: ShareFile = serde_json::from_value(json)?;
+        file.into()
+    };
+    info.params().validate()?;
+
+    Ok(info)
+}
+
+/// The share of an encrypted share file, asking `passphrase` for its passphrase
+#[cfg(feature = "encryption")]
+fn decrypt<F>(json: serde_json::Value, passphrase: F) -> io::Result<ShareInfo>
+where
+    F: FnOnce() -> io::Result<String>,
+{
+    let file: EncryptedShareFile = serde_json::from_value(json)?;
+
+    file.decrypt(passphrase()?.as_bytes())
+}
+
+#[cfg(not(feature = "encryption"))]
+fn decrypt<F>(_: serde_json::Value, _: F) -> io::Result<ShareInfo>
+where
+    F: FnOnce() -> io::Result<String>,
+{
+    Err(io::Error::new(
+        io::ErrorKind::Unsupported,
+        "share file is encrypted, reading it needs the encryption feature",
+    ))
+}
+
+/// Write `info` as a share file at `path`
+pub fn save_share<P: AsRef<Path>>(path: P, info: &ShareInfo) -> io::Result<()> {
+    let mut w = BufWriter::new(File::create(path)?);
+    write_share(&mut w, info)?;
+
+    w.flush()
+}
+
+/// Read the share file at `path`, naming it in any error
+pub fn load_share<P: AsRef<Path>>(path: P) -> io::Result<ShareInfo> {
+    let path = path.as_ref();
+
+    File::open(path)
+        .and_then(|file| read_share(BufReader::new(file)))
+        .map_err(|err| io::Error::new(err.kind(), format!("{}: {}", path.display(), err)))
+}
+
+/// Like `load_share` for a share file that may be encrypted, see `read_share_with`
+pub fn load_share_with<P, F>(path: P, passphrase: F) -> io::Result<ShareInfo>
+where
+    P: AsRef<Path>,
+    F: FnOnce() -> io::Result<String>,
+{
+    let path = path.as_ref();
+
+    File::open(path)
+        .and_then(|file| read_share_with(BufReader::new(file), passphrase))
+        .map_err(|err| io::Error::new(err.kind(), format!("{}: {}", path.display(), err)))
+}
+
+/// Argon2id costs and salt a passphrase is stretched into a key with
+#[cfg(feature = "encryption")]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Kdf {
+    /// Always "argon2id"
+    pub algorithm: String,
+    #[serde(with = "b64_bytes")]
+    pub salt: Vec<u8>,
+    /// Memory in KiB
+    pub m_cost: u32,
+    pub t_cost: u32,
+    pub p_cost: u32,
+}
+
+#[cfg(feature = "encryption")]
+impl Kdf {
+    /// Argon2id with its recommended costs and a fresh salt
+    pub fn recommended() -> Kdf {
+        Kdf::with_costs(
+            argon2::Params::DEFAULT_M_COST,
+            argon2::Params::DEFAULT_T_COST,
+            argon2::Params::DEFAULT_P_COST,
+        )
+    }
+
+    /// Argon2id with the given costs and a fresh salt
+    pub fn with_costs(m_cost: u32, t_cost: u32, p_cost: u32) -> Kdf {
+        Kdf::with_costs_rng(m_cost, t_cost, p_cost, &mut rand::thread_rng())
+    }
+
+    /// Like `with_costs` with the salt drawn from `rng`
+    pub fn with_costs_rng<R: RngCore + CryptoRng>(
+        m_cost: u32,
+        t_cost: u32,
+        p_cost: u32,
+        rng: &mut R,
+    ) -> Kdf {
+        let mut salt = vec![0; SALT_LEN];
+        rng.fill(&mut salt[..]);
+
+        Kdf {
+            algorithm: "argon2id".to_string(),
+            salt,
+            m_cost,
+            t_cost,
+            p_cost,
+        }
+    }
+
+    fn derive_key(&self, passphrase: &[u8]) -> io::Result<Zeroizing<[u8; 32]>> {
+        if self.algorithm != "argon2id" {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("unsupported key derivation {}", self.algorithm),
+            ));
+        }
+        if self.m_cost > MAX_M_COST || self.t_cost > MAX_T_COST || self.p_cost > MAX_P_COST {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!(
+                    "argon2 costs above the most of {} KiB, {} passes and {} lanes",
+                    MAX_M_COST, MAX_T_COST, MAX_P_COST
+                ),
+            ));
+        }
+
+        let invalid =
+            |err: argon2::Error| io::Error::new(io::ErrorKind::InvalidData, err.to_string());
+        let params = argon2::Params::new(self.m_cost, self.t_cost, self.p_cost, Some(32))
+            .map_err(invalid)?;
+        let mut key = Zeroizing::new([0; 32]);
+        argon2::Argon2::new(argon2::Algorithm::Argon2id, argon2::Version::V0x13, params)
+            .hash_password_into(passphrase, &self.salt, &mut *key)
+            .map_err(invalid)?;
+
+        Ok(key)
+    }
+}
+
+/// A share file encrypted at rest: the JSON of its ShareFile sealed with AES-256-GCM under a key
+/// stretched from a passphrase
+#[cfg(feature = "encryption")]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct EncryptedShareFile {
+    pub version: u32,
+    pub kdf: Kdf,
+    /// Always "aes-256-gcm"
+    pub cipher: String,
+    #[serde(with = "b64_bytes")]
+    pub nonce: Vec<u8>,
+    #[serde(with = "b64_bytes")]
+    pub ciphertext: Vec<u8>,
+}
+
+#[cfg(feature = "encryption")]
+impl EncryptedShareFile {
+    /// Encrypt `info` under a key `kdf` stretches from `passphrase`
+    pub fn encrypt(
+        info: &ShareInfo,
+        passphrase: &[u8],
+        kdf: Kdf,
+    ) -> io::Result<EncryptedShareFile> {
+        EncryptedShareFile::encrypt_with_rng(info, passphrase, kdf, &mut rand::thread_rng())
+    }
+
+    /// Like `encrypt` with the nonce drawn from `rng`
+    pub fn encrypt_with_rng<R: RngCore + CryptoRng>(
+        info: &ShareInfo,
+        passphrase: &[u8],
+        kdf: Kdf,
+        rng: &mut R,
+    ) -> io::Result<EncryptedShareFile> {
+        let plaintext = Zeroizing::new(serde_json::to_vec(&ShareFile::try_from(info)?)?);
+        let key = kdf.derive_key(passphrase)?;
+        let mut nonce = vec![0; NONCE_LEN];
+        rng.fill(&mut nonce[..]);
+
+        let ciphertext = Aes256Gcm::new(Key::<Aes256Gcm>::from_slice(&*key))
+            .encrypt(
+                Nonce::from_slice(&nonce),
+                Payload {
+                    msg: &plaintext,
+                    aad: ENCRYPTED_SHARE_AAD,
+                },
+            )
+            .map_err(|_| io::Error::other("share encryption failed"))?;
+
+        Ok(EncryptedShareFile {
+            version: SHARE_FILE_VERSION,
+            kdf,
+            cipher: "aes-256-gcm".to_string(),
+            nonce,
+            ciphertext,
+        })
+    }
+
+    /// The share sealed in the file, an error for a wrong passphrase or a tampered file
+    pub fn decrypt(&self, passphrase: &[u8]) -> io::Result<ShareInfo> {
+        let invalid = |msg: &str| io::Error::new(io::ErrorKind::InvalidData, msg.to_string());
+        if self.cipher != "aes-256-gcm" || self.nonce.len() != NONCE_LEN {
+            return Err(invalid("unsupported share file cipher"));
+        }
+
+        let key = self.kdf.derive_key(passphrase)?;
+        let plaintext = Aes256Gcm::new(Key::<Aes256Gcm>::from_slice(&*key))
+            .decrypt(
+                Nonce::from_slice(&self.nonce),
+                Payload {
+                    msg: &self.ciphertext,
+                    aad: ENCRYPTED_SHARE_AAD,
+                },
+            )
+            .map(Zeroizing::new)
+            .map_err(|_| invalid("wrong passphrase or corrupted share file"))?;
+        let file: ShareFile = serde_json::from_slice(&plaintext)?;
+
+        Ok(file.into())
+    }
+}
+
+/// Write `info` at `path` as a share file encrypted under `passphrase`
+#[cfg(feature = "encryption")]
+pub fn save_encrypted_share<P: AsRef<Path>>(
+    path: P,
+    info: &ShareInfo,
+    passphrase: &[u8],
+) -> io::Result<()> {
+    let file = EncryptedShareFile::encrypt(info, passphrase, Kdf::recommended())?;
+    let mut w = BufWriter::new(File::create(path)?);
+    serde_json::to_writer_pretty(&mut w, &file)?;
+    w.write_all(b"\n")?;
+
+    w.flush()
+}
+
+/// Hex of the first bytes of a SHA-256 over the dealing `info` is a share of: its group,
+/// threshold and commitments, the same for every share of a dealing and telling dealings apart
+pub fn fingerprint(info: &ShareInfo) -> String {
+    fingerprint_params(info.params())
+}
+
+/// The fingerprint of the dealing of `params`, see `fingerprint`
+pub(crate) fn fingerprint_params(params: &PublicParams) -> String {
+    hex::encode(params_hash(params))
+}
+
+/// The bytes of the fingerprint of the dealing of `params`
+pub(crate) fn params_hash(params: &PublicParams) -> [u8; FINGERPRINT_LEN] {
+    let mut hasher = Sha256::new();
+    let mut update = |bytes: &[u8]| {
+        hasher.update((bytes.len() as u64).to_be_bytes());
+        hasher.update(bytes);
+    };
+    for n in [&params.p, &params.q, &params.g] {
+        update(&codec::to_bytes(n));
+    }
+    update(&(params.t as u64).to_be_bytes());
+    for commitment in &params.commitments {
+        update(&codec::to_bytes(commitment));
+    }
+
+    let mut hash = [0; FINGERPRINT_LEN];
+    hash.copy_from_slice(&hasher.finalize()[..FINGERPRINT_LEN]);
+
+    hash
+}
+
+/// What a dealer publishes of a dealing, for anyone to check shares against without the
+/// network: a JSON document of its group, threshold and commitments, numbers as in share files
+///
+/// ```json
+/// {
+///   "version": 1,
+///   "session": 42,
+///   "players": 3,
+///   "threshold": 2,
+///   "group": { "p": "Cw==", "q": "BQ==", "g": "Aw==" },
+///   "commitments": ["BQ==", "AQ=="]
+/// }
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Transcript {
+    pub version: u32,
+    pub session: u64,
+    pub players: usize,
+    pub threshold: usize,
+    pub group: Group,
+    #[serde(with = "b64s")]
+    pub commitments: Vec<BigUint>,
+}
+
+impl Transcript {
+    /// The transcript of the dealing `session` of `params` to `players` players
+    pub fn new(session: u64, players: usize, params: &PublicParams) -> Transcript {
+        Transcript {
+            version: TRANSCRIPT_VERSION,
+            session,
+            players,
+            threshold: params.t,
+            group: Group {
+                p: params.p.clone(),
+                q: params.q.clone(),
+                g: params.g.clone(),
+            },
+            commitments: params.commitments.clone(),
+        }
+    }
+
+    pub fn params(&self) -> PublicParams {
+        PublicParams {
+            g: self.group.g.clone(),
+            commitments: self.commitments.clone(),
+            p: self.group.p.clone(),
+            q: self.group.q.clone(),
+            t: self.threshold,
+        }
+    }
+
+    /// The fingerprint every share of the dealing has, see `fingerprint`
+    pub fn fingerprint(&self) -> String {
+        fingerprint_params(&self.params())
+    }
+
+    /// Check that `info` is a share of this dealing, carrying its parameters at an index of
+    /// one of its players, and that it verifies against the published commitments
+    pub fn check(&self, info: &ShareInfo) -> io::Result<()> {
+        let invalid = |msg: String| io::Error::new(io::ErrorKind::InvalidData, msg);
+        let params = Arc::new(self.params());
+
+        if let Some(mismatch) = differs(&params, info.params()) {
+            return Err(invalid(format!(
+                "{} differ from the transcript's",
+                mismatch
+            )));
+        }
+        let index = info.share().index();
+        if *index == BigUint::from(0_u32) || *index > BigUint::from(self.players) {
+            return Err(invalid(format!(
+                "index {} is not one of the {} players",
+                index, self.players
+            )));
+        }
+        if !ShareInfo::with_params(info.share().clone(), params).verify() {
+            return Err(invalid(
+                "share does not verify against the commitments".to_string(),
+            ));
+        }
+
+        Ok(())
+    }
+}
+
+/// Write the transcript of a dealing at `path`
+pub fn save_transcript<P: AsRef<Path>>(path: P, transcript: &Transcript) -> io::Result<()> {
+    let mut w = BufWriter::new(File::create(path)?);
+    serde_json::to_writer_pretty(&mut w, transcript)?;
+    w.write_all(b"\n")?;
+
+    w.flush()
+}
+
+/// Read a transcript, refusing versions newer than TRANSCRIPT_VERSION and parameters that
+/// do not agree
+pub fn read_transcript<R: Read>(r: R) -> io::Result<Transcript> {
+    let transcript: Transcript = serde_json::from_reader(r)?;
+    if transcript.version > TRANSCRIPT_VERSION {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("transcript version {} is too new", transcript.version),
+        ));
+    }
+    if transcript.threshold == 0
+        || transcript.threshold > transcript.players
+        || transcript.commitments.len() != transcript.threshold
+    {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "threshold, players and commitments of the transcript do not agree",
+        ));
+    }
+
+    Ok(transcript)
+}
+
+/// Read the transcript at `path`, naming it in any error
+pub fn load_transcript<P: AsRef<Path>>(path: P) -> io::Result<Transcript> {
+    let path = path.as_ref();
+
+    File::open(path)
+        .and_then(|file| read_transcript(BufReader::new(file)))
+        .map_err(|err| io::Error::new(err.kind(), format!("{}: {}", path.display(), err)))
+}
+
+/// A threshold signature as kept on disk, with the group and public key g^x it verifies under,
+/// numbers as in share files
+///
+/// ```json
+/// {
+///   "version": 1,
+///   "session": 42,
+///   "signers": [1, 3],
+///   "group": { "p": "Cw==", "q": "BQ==", "g": "Aw==" },
+///   "public_key": "BQ==",
+///   "r": "BA==",
+///   "z": "Ag=="
+/// }
+/// ```
+#[cfg(feature = "modp")]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SignatureFile {
+    pub version: u32,
+    pub session: u64,
+    pub signers: Vec<usize>,
+    pub group: Group,
+    #[serde(with = "b64")]
+    pub public_key: BigUint,
+    #[serde(with = "b64")]
+    pub r: BigUint,
+    #[serde(with = "b64")]
+    pub z: BigUint,
+}
+
+#[cfg(feature = "modp")]
+impl SignatureFile {
+    /// The file of `signature` by `signers` with the key of the dealing `session` of `params`
+    pub fn new(
+        session: u64,
+        signers: Vec<usize>,
+        params: &PublicParams,
+        signature: &Signature,
+    ) -> SignatureFile {
+        SignatureFile {
+            version: SIGNATURE_FILE_VERSION,
+            session,
+            signers,
+            group: Group {
+                p: params.p.clone(),
+                q: params.q.clone(),
+                g: params.g.clone(),
+            },
+            public_key: params.commitments[0].clone(),
+            r: signature.r.clone(),
+            z: signature.z.clone(),
+        }
+    }
+
+    pub fn signature(&self) -> Signature {
+        Signature {
+            r: self.r.clone(),
+            z: self.z.clone(),
+        }
+    }
+
+    /// Verify the signature of `message` under the file's public key
+    pub fn verify(&self, message: &[u8]) -> bool {
+        let Group { p, q, g } = &self.group;
+
+        sign::verify(&self.public_key, message, &self.signature(), g, p, q)
+    }
+}
+
+/// Write a signature file at `path`
+#[cfg(feature = "modp")]
+pub fn save_signature<P: AsRef<Path>>(path: P, file: &SignatureFile) -> io::Result<()> {
+    let mut w = BufWriter::new(File::create(path)?);
+    serde_json::to_writer_pretty(&mut w, file)?;
+    w.write_all(b"\n")?;
+
+    w.flush()
+}
+
+/// Read the signature file at `path`, refusing versions newer than SIGNATURE_FILE_VERSION
+#[cfg(feature = "modp")]
+pub fn load_signature<P: AsRef<Path>>(path: P) -> io::Result<SignatureFile> {
+    let path = path.as_ref();
+    let read = |file: File| -> io::Result<SignatureFile> {
+        let signature: SignatureFile = serde_json::from_reader(BufReader::new(file))?;
+        if signature.version > SIGNATURE_FILE_VERSION {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("signature file version {} is too new", signature.version),
+            ));
+        }
+
+        Ok(signature)
+    };
+
+    File::open(path)
+        .and_then(read)
+        .map_err(|err| io::Error::new(err.kind(), format!("{}: {}", path.display(), err)))
+}
+
+/// What of the dealing parameters `b` differs from `a`, if any
+fn differs(a: &PublicParams, b: &PublicParams) -> Option<&'static str> {
+    if a.p != b.p || a.q != b.q || a.g != b.g {
+        Some("group")
+    } else if a.t != b.t {
+        Some("threshold")
+    } else if a.commitments != b.commitments {
+        Some("commitments")
+    } else {
+        None
+    }
+}
+
+/// Reconstruct the secret of the dealing the share files at `paths` belong to
+///
+/// Every file must verify against its commitments, be within its validity window and come from
+/// the same dealing and epoch as the first, the same group, threshold and commitments, with no
+/// index given twice. Errors name the file at fault
+pub fn reconstruct_files<P: AsRef<Path>>(paths: &[P]) -> io::Result<Secret> {
+    let shares = paths
+        .iter()
+        .map(|path| Ok((path.as_ref(), load_share(path)?)))
+        .collect::<io::Result<Vec<_>>>()?;
+
+    reconstruct_shares(&shares)
+}
+
+/// Like `reconstruct_files` with shares already read, each with the path it was read from
+pub fn reconstruct_shares<P: AsRef<Path>>(infos: &[(P, ShareInfo)]) -> io::Result<Secret> {
+    let invalid = |path: &Path, msg: String| {
+        io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("{}: {}", path.display(), msg),
+        )
+    };
+
+    let mut dealing: Option<(&Path, &ShareInfo)> = None;
+    let mut shares: Vec<Share> = vec![];
+    for (path, info) in infos {
+        let path = path.as_ref();
+        if let Err(err) = info.params().validate() {
+            return Err(invalid(path, err.to_string()));
+        }
+        if !info.verify() {
+            return Err(invalid(path, "share does not verify".to_string()));
+        }
+        if !info.validity().is_current() {
+            return Err(invalid(
+                path,
+                "share is outside its validity window".to_string(),
+            ));
+        }
+
+        if let Some((first, dealing)) = &dealing {
+            if let Some(mismatch) = differs(dealing.params(), info.params()) {
+                return Err(invalid(
+                    path,
+                    format!(
+                        "{} differ from {}, not the same dealing",
+                        mismatch,
+                        first.display()
+                    ),
+                ));
+            }
+            if dealing.validity().epoch != info.validity().epoch {
+                return Err(invalid(
+                    path,
+                    format!("epoch differs from {}", first.display()),
+                ));
+            }
+        }
+        if shares
+            .iter()
+            .any(|share| share.index() == info.share().index())
+        {
+            return Err(invalid(
+                path,
+                format!("share {} given twice", info.share().index()),
+            ));
+        }
+
+        shares.push(info.share().clone());
+        dealing.get_or_insert((path, info));
+    }
+
+    let (_, dealing) = dealing.ok_or_else(|| {
+        io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "no share files to reconstruct from",
+        )
+    })?;
+    if shares.len() < dealing.t() {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("{} shares, {} needed", shares.len(), dealing.t()),
+        ));
+    }
+
+    let secret = vss::reconstruct(&shares[..dealing.t()], dealing.q())?;
+    if !dealing.verify_secret(&secret) {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "reconstructed secret does not match the dealing's commitment c_0",
+        ));
+    }
+
+    Ok(secret)
+}
+
+/// The number standing for the bytes of a secret, the one `vss deal` shares
+pub fn encode_secret(secret: &[u8]) -> Secret {
+    let bytes = Zeroizing::new([&[SECRET_SENTINEL], secret].concat());
+
+    Secret::new(BigUint::from_bytes_be(&bytes))
+}
+
+/// The bytes of the secret `n` stands for, see `encode_secret`
+pub fn decode_secret(n: &Secret) -> io::Result<SecretBytes> {
+    match Zeroizing::new(n.expose().to_bytes_be()).split_first() {
+        Some((&SECRET_SENTINEL, secret)) => Ok(SecretBytes::new(secret.to_vec())),
+        _ => Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "reconstructed number is not a secret, are the shares from the same dealing?",
+        )),
+    }
+}
+
+/// Serde adapter encoding a BigUint as base64 of its canonical bytes
+mod b64 {
+    use base64::engine::general_purpose::STANDARD;
+    use base64::Engine;
+    use num_bigint::BigUint;
+    use serde::de::Error;
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    use crate::codec;
+
+    pub fn serialize<S: Serializer>(n: &BigUint, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&encode(n))
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<BigUint, D::Error> {
+        decode(&String::deserialize(deserializer)?).map_err(D::Error::custom)
+    }
+
+    pub fn encode(n: &BigUint) -> String {
+        STANDARD.encode(codec::to_bytes(n))
+    }
+
+    pub fn decode(s: &str) -> Result<BigUint, String> {
+        let bytes = STANDARD.decode(s).map_err(|err| err.to_string())?;
+
+        codec::from_bytes(&bytes).ok_or_else(|| "number with leading zeros".to_string())
+    }
+}
+
+/// Serde adapter encoding bytes as base64
+#[cfg(feature = "encryption")]
+pub(crate) mod b64_bytes {
+    use base64::engine::general_purpose::STANDARD;
+    use base64::Engine;
+    use serde::de::Error;
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(bytes: &[u8], serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&STANDARD.encode(bytes))
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<u8>, D::Error> {
+        STANDARD
+            .decode(String::deserialize(deserializer)?)
+            .map_err(D::Error::custom)
+    }
+}
+
+/// Serde adapter encoding every BigUint of a Vec like `b64`
+mod b64s {
+    use num_bigint::BigUint;
+    use serde::de::Error;
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(ns: &[BigUint], serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_seq(ns.iter().map(super::b64::encode))
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Vec<BigUint>, D::Error> {
+        Vec::<String>::deserialize(deserializer)?
+            .iter()
+            .map(|s| super::b64::decode(s).map_err(D::Error::custom))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+    use std::path::PathBuf;
+
+    use num_bigint::{BigUint, ToBigUint};
+
+    use crate::dealer::Dealer;
+    use crate::rpc::{Metadata, Share, ShareInfo, Validity};
+    use crate::secret::Secret;
+    #[cfg(feature = "modp")]
+    use crate::sign::{self, Signature};
+
+    use super::{
+        decode_secret, encode_secret, fingerprint, load_share, read_share, read_transcript,
+        reconstruct_files, reconstruct_shares, save_share, write_share, Transcript,
+    };
+    #[cfg(feature = "modp")]
+    use super::{load_signature, save_signature, SignatureFile};
+    #[cfg(feature = "encryption")]
+    use super::{read_share_with, EncryptedShareFile, Kdf};
+
+    fn share_info() -> ShareInfo {
+        ShareInfo::new(
+            Share::new(3.to_biguint().unwrap(), 0x010203.to_biguint().unwrap()),
+            3.to_biguint().unwrap(),
+            vec![5.to_biguint().unwrap(), 1.to_biguint().unwrap()],
+            11.to_biguint().unwrap(),
+            5.to_biguint().unwrap(),
+            2,
+        )
+    }
+
+    #[test]
+    fn share_file_roundtrip() {
+        let mut file = vec![];
+        write_share(&mut file, &share_info()).unwrap();
+
+        let json: serde_json::Value = serde_json::from_slice(&file).unwrap();
+        assert_eq!("AQID", json["value"]);
+        assert_eq!("Cw==", json["group"]["p"]);
+        assert!(json.get("metadata").is_none());
+        assert_eq!(share_info(), read_share(&file[..]).unwrap());
+    }
+
+    #[test]
+    fn metadata_rides_along() {
+        let metadata = Metadata {
+            custodian: Some("alice".to_string()),
+            created: Some(1_700_000_000),
+            ..Metadata::default()
+        };
+        let info = share_info().with_metadata(metadata);
+        let mut file = vec![];
+        write_share(&mut file, &info).unwrap();
+
+        let json: serde_json::Value = serde_json::from_slice(&file).unwrap();
+        assert_eq!(
+            serde_json::json!({ "custodian": "alice", "created": 1_700_000_000 }),
+            json["metadata"]
+        );
+        let read = read_share(&file[..]).unwrap();
+        assert_eq!(info, read);
+        assert_eq!(share_info().verify(), read.verify());
+        assert_eq!(fingerprint(&share_info()), fingerprint(&read));
+    }
+
+    #[test]
+    fn newer_versions_are_refused() {
+        let mut file = vec![];
+        write_share(&mut file, &share_info()).unwrap();
+        let mut json: serde_json::Value = serde_json::from_slice(&file).unwrap();
+        json["version"] = 2.into();
+
+        let err = read_share(json.to_string().as_bytes()).unwrap_err();
+        assert!(err.to_string().contains("version 2"));
+    }
+
+    #[test]
+    fn invalid_groups_are_refused() {
+        // a single share that verifies under p = 23 and g = 2 whatever q is
+        let crafted = |g: u32, q: u32| {
+            let c = BigUint::from(2_u32).modpow(&BigUint::from(7_u32), &BigUint::from(23_u32));
+            ShareInfo::new(
+                Share::new(1.to_biguint().unwrap(), 7.to_biguint().unwrap()),
+                g.to_biguint().unwrap(),
+                vec![c],
+                23.to_biguint().unwrap(),
+                q.to_biguint().unwrap(),
+                1,
+            )
+        };
+        assert!(crafted(2, 0).verify());
+
+        for info in [
+            crafted(2, 0),
+            crafted(2, 22),
+            crafted(1, 11),
+            crafted(5, 11),
+        ] {
+            let mut file = vec![];
+            write_share(&mut file, &info).unwrap();
+            let err = read_share(&file[..]).unwrap_err();
+            assert_eq!(std::io::ErrorKind::InvalidData, err.kind());
+            assert!(reconstruct_shares(&[("crafted", info)]).is_err());
+        }
+        let mut file = vec![];
+        write_share(&mut file, &crafted(2, 11)).unwrap();
+        assert!(read_share(&file[..]).is_ok());
+    }
+
+    #[test]
+    #[cfg(feature = "encryption")]
+    fn encrypted_share_needs_its_passphrase() {
+        let file =
+            EncryptedShareFile::encrypt(&share_info(), b"hunter2", Kdf::with_costs(64, 1, 1))
+                .unwrap();
+        let json = serde_json::to_vec(&file).unwrap();
+
+        let read = read_share_with(&json[..], || Ok("hunter2".to_string())).unwrap();
+        assert_eq!(share_info(), read);
+        assert!(read_share_with(&json[..], || Ok("hunter3".to_string())).is_err());
+        assert!(read_share(&json[..]).is_err());
+
+        // costs a crafted file asks for are capped before any memory is taken
+        let mut crafted = file.clone();
+        crafted.kdf.m_cost = u32::MAX;
+        let err = crafted.decrypt(b"hunter2").unwrap_err();
+        assert_eq!(std::io::ErrorKind::InvalidData, err.kind());
+    }
+
+    #[test]
+    fn fingerprint_tells_dealings_apart() {
+        let dealer = Dealer::new(5, 3, 1234).unwrap();
+        let other = Dealer::new(5, 3, 1234).unwrap();
+        let info = |dealer: &Dealer, i: usize| {
+            ShareInfo::with_params(dealer.shares[i].clone(), dealer.params())
+        };
+
+        assert_eq!(16, fingerprint(&info(&dealer, 0)).len());
+        assert_eq!(
+            fingerprint(&info(&dealer, 0)),
+            fingerprint(&info(&dealer, 4))
+        );
+        assert_ne!(
+            fingerprint(&info(&dealer, 0)),
+            fingerprint(&info(&other, 0))
+        );
+    }
+
+    #[test]
+    fn transcript_checks_shares() {
+        let dealer = Dealer::new(5, 3, 1234).unwrap();
+        let other = Dealer::new(5, 3, 1234).unwrap();
+        let transcript = Transcript::new(dealer.session_id, dealer.n, &dealer.params());
+        let json = serde_json::to_vec(&transcript).unwrap();
+        let transcript = read_transcript(&json[..]).unwrap();
+
+        let share = &dealer.shares[1];
+        assert!(transcript
+            .check(&ShareInfo::with_params(share.clone(), dealer.params()))
+            .is_ok());
+
+        let forged = Share::new(share.index().clone(), share.value() + 1_u32);
+        let err = transcript
+            .check(&ShareInfo::with_params(forged, dealer.params()))
+            .unwrap_err();
+        assert!(err.to_string().contains("does not verify"));
+
+        let err = transcript
+            .check(&ShareInfo::with_params(
+                other.shares[1].clone(),
+                other.params(),
+            ))
+            .unwrap_err();
+        assert!(err.to_string().contains("differ from the transcript's"));
+    }
+
+    #[test]
+    fn reconstruct_files_refuses_mixed_dealings() {
+        let dir = std::env::temp_dir().join(format!("rust_vss-format-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let save = |dealer: &Dealer, i: usize| {
+            let path = dir.join(format!("{}-{}.json", dealer.session_id, i));
+            let info = ShareInfo::with_params(dealer.shares[i].clone(), dealer.params());
+            save_share(&path, &info).unwrap();
+
+            path
+        };
+
+        let dealer = Dealer::new(5, 3, 1234).unwrap();
+        let other = Dealer::new(5, 3, 1234).unwrap();
+        let paths: Vec<PathBuf> = (0..3).map(|i| save(&dealer, i)).collect();
+        let secret = reconstruct_files(&paths).unwrap();
+        assert_eq!(1234_u32.to_biguint().as_ref(), Some(secret.expose()));
+
+        let mixed = [paths[0].clone(), paths[1].clone(), save(&other, 2)];
+        let err = reconstruct_files(&mixed).unwrap_err();
+        assert!(err.to_string().contains("not the same dealing"));
+
+        let twice = [paths[0].clone(), paths[1].clone(), paths[1].clone()];
+        let err = reconstruct_files(&twice).unwrap_err();
+        assert!(err.to_string().contains("given twice"));
+
+        // an expired share and shares of two epochs are refused too
+        let bound = |i: usize, validity: Validity| {
+            let path = dir.join(format!(
+                "{}-{}-{}.json",
+                dealer.session_id, i, validity.epoch
+            ));
+            let info = ShareInfo::with_params(dealer.shares[i].clone(), dealer.params())
+                .with_validity(validity);
+            save_share(&path, &info).unwrap();
+            assert_eq!(info, load_share(&path).unwrap());
+
+            path
+        };
+        let expired = Validity {
+            not_after: Some(1),
+            ..Validity::default()
+        };
+        let next = Validity::default().next();
+        let err = reconstruct_files(&[paths[0].clone(), paths[1].clone(), bound(2, expired)])
+            .unwrap_err();
+        assert!(err.to_string().contains("outside its validity window"));
+        let err =
+            reconstruct_files(&[paths[0].clone(), paths[1].clone(), bound(2, next)]).unwrap_err();
+        assert!(err.to_string().contains("epoch differs"));
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn secret_leading_zeros_survive() {
+        let secret = [0, 0, 7, 0];
+
+        assert_eq!(
+            &secret,
+            decode_secret(&encode_secret(&secret)).unwrap().expose()
+        );
+        assert!(decode_secret(&Secret::new(BigUint::from(0x0207_u32))).is_err());
+    }
+
+    #[test]
+    #[cfg(feature = "modp")]
+    fn signature_file_verifies_its_message() {
+        // a Schnorr signature with the dealt secret 1234 itself, as the signers would combine it
+        let dealer = Dealer::new(5, 3, 1234).unwrap();
+        let params = dealer.params();
+        let (k, x) = (7.to_biguint().unwrap(), 1234.to_biguint().unwrap());
+        let r = params.g.modpow(&k, &params.p);
+        let e = sign::challenge(&r, &params.commitments[0], b"hello", &params.p, &params.q);
+        let z = (k + e * x) % &params.q;
+        let file = SignatureFile::new(
+            dealer.session_id,
+            vec![1, 2, 3],
+            &params,
+            &Signature { r, z },
+        );
+
+        let path =
+            std::env::temp_dir().join(format!("rust_vss-signature-{}.json", std::process::id()));
+        save_signature(&path, &file).unwrap();
+        let read = load_signature(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+
+        assert_eq!(file, read);
+        assert!(read.verify(b"hello"));
+        assert!(!read.verify(b"hullo"));
+    }
+}
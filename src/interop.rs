@@ -0,0 +1,243 @@
+//! Shares and commitments in the encodings of the vsss-rs crate, to move share sets between the
+//! two crates or mix them in one deployment
+//!
+//! vsss-rs writes every number as a fixed-width element: a prime field element as its field's
+//! representation, big endian for k256 and p256 scalars and little endian for curve25519 and
+//! bls12-381 ones, and a residue or integer as big endian bytes. Up to version 3 a share is one
+//! identifier byte followed by its value, from version 4 on an identifier and a value element.
+//! A Feldman verifier set is the generator followed by the commitments, so the commitments of a
+//! dealing over a mod p group convert to one of residues modulo p. Commitments to curve points
+//! have no mod p counterpart, shares dealt under them convert but only reconstruct.
+
+use std::io;
+
+use num::{ToPrimitive, Zero};
+use num_bigint::BigUint;
+
+use crate::rpc::{PublicParams, Share};
+
+/// Byte order of an element's representation
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Endian {
+    Big,
+    Little,
+}
+
+/// How vsss-rs represents the elements of one field or group: `len` bytes in `endian` order
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Element {
+    pub len: usize,
+    pub endian: Endian,
+}
+
+impl Element {
+    /// Elements below `modulus` in as few bytes as hold it
+    pub fn of(modulus: &BigUint, endian: Endian) -> Element {
+        Element {
+            len: modulus.bits().div_ceil(8),
+            endian,
+        }
+    }
+
+    /// `n` in exactly `len` bytes
+    pub fn encode(&self, n: &BigUint) -> io::Result<Vec<u8>> {
+        let mut bytes = n.to_bytes_le();
+        if n.is_zero() {
+            bytes.clear();
+        }
+        if bytes.len() > self.len {
+            return Err(invalid(format!("number does not fit {} bytes", self.len)));
+        }
+        bytes.resize(self.len, 0);
+        if self.endian == Endian::Big {
+            bytes.reverse();
+        }
+
+        Ok(bytes)
+    }
+
+    /// The number of exactly `len` bytes
+    pub fn decode(&self, bytes: &[u8]) -> io::Result<BigUint> {
+        if bytes.len() != self.len {
+            return Err(invalid(format!(
+                "element of {} bytes, expected {}",
+                bytes.len(),
+                self.len
+            )));
+        }
+
+        Ok(match self.endian {
+            Endian::Big => BigUint::from_bytes_be(bytes),
+            Endian::Little => BigUint::from_bytes_le(bytes),
+        })
+    }
+}
+
+/// A share as vsss-rs 4 and later hold it, its identifier and value as elements
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VsssShare {
+    pub identifier: Vec<u8>,
+    pub value: Vec<u8>,
+}
+
+/// `share` in the layout of vsss-rs 3, its index as one byte followed by its value
+pub fn to_share_bytes(share: &Share, value: Element) -> io::Result<Vec<u8>> {
+    let index = share
+        .index()
+        .to_u8()
+        .filter(|index| *index != 0)
+        .ok_or_else(|| invalid("vsss-rs 3 identifiers are between 1 and 255"))?;
+
+    Ok([vec![index], value.encode(share.value())?].concat())
+}
+
+/// The share of `bytes` in the layout of vsss-rs 3
+pub fn from_share_bytes(bytes: &[u8], value: Element) -> io::Result<Share> {
+    match bytes.split_first() {
+        Some((&index, rest)) if index != 0 => {
+            Ok(Share::new(BigUint::from(index), value.decode(rest)?))
+        }
+        _ => Err(invalid("vsss-rs 3 share without an identifier")),
+    }
+}
+
+/// `share` as vsss-rs 4 and later hold it, index and value both elements of `field`
+pub fn to_vsss_share(share: &Share, field: Element) -> io::Result<VsssShare> {
+    Ok(VsssShare {
+        identifier: field.encode(share.index())?,
+        value: field.encode(share.value())?,
+    })
+}
+
+/// The share vsss-rs 4 and later hold as `share`
+pub fn from_vsss_share(share: &VsssShare, field: Element) -> io::Result<Share> {
+    let index = field.decode(&share.identifier)?;
+    if index.is_zero() {
+        return Err(invalid("share identifier is zero"));
+    }
+
+    Ok(Share::new(index, field.decode(&share.value)?))
+}
+
+/// The Feldman verifier set of `params` as residues modulo p: g, then c_0,...,c_(t-1)
+pub fn to_verifier_set(params: &PublicParams) -> io::Result<Vec<Vec<u8>>> {
+    let residue = Element::of(&params.p, Endian::Big);
+
+    std::iter::once(&params.g)
+        .chain(&params.commitments)
+        .map(|n| residue.encode(n))
+        .collect()
+}
+
+/// The public parameters of a dealing over q whose Feldman verifier set of residues modulo p
+/// is `verifiers`, checking that g has order q
+pub fn from_verifier_set(
+    verifiers: &[Vec<u8>],
+    p: &BigUint,
+    q: &BigUint,
+) -> io::Result<PublicParams> {
+    let residue = Element::of(p, Endian::Big);
+    let mut numbers = verifiers
+        .iter()
+        .map(|bytes| residue.decode(bytes))
+        .collect::<io::Result<Vec<_>>>()?;
+    if numbers.len() < 2 {
+        return Err(invalid("verifier set without commitments"));
+    }
+    if numbers.iter().any(|n| n.is_zero() || n >= p) {
+        return Err(invalid("verifier is not a residue modulo p"));
+    }
+    let g = numbers.remove(0);
+    if g.modpow(q, p) != BigUint::from(1_u32) {
+        return Err(invalid("generator is not of order q"));
+    }
+
+    Ok(PublicParams {
+        g,
+        t: numbers.len(),
+        commitments: numbers,
+        p: p.clone(),
+        q: q.clone(),
+    })
+}
+
+fn invalid<E: Into<Box<dyn std::error::Error + Send + Sync>>>(err: E) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, err)
+}
+
+#[cfg(test)]
+mod tests {
+    use num_bigint::BigUint;
+
+    use crate::dealer::Dealer;
+    use crate::rpc::Share;
+    use crate::secret::Secret;
+    use crate::vss;
+
+    use super::{
+        from_share_bytes, from_verifier_set, from_vsss_share, to_share_bytes, to_verifier_set,
+        to_vsss_share, Element, Endian,
+    };
+
+    #[test]
+    fn shares_and_verifiers_round_trip() {
+        let dealer = Dealer::with_bits(4, 2, Secret::new(BigUint::from(77_u32)), 64).unwrap();
+        let params = dealer.params();
+        for endian in [Endian::Big, Endian::Little] {
+            let field = Element::of(&params.q, endian);
+            for share in &dealer.shares {
+                let bytes = to_share_bytes(share, field).unwrap();
+                assert_eq!(1 + field.len, bytes.len());
+                assert_eq!(*share, from_share_bytes(&bytes, field).unwrap());
+
+                let vsss = to_vsss_share(share, field).unwrap();
+                assert_eq!(*share, from_vsss_share(&vsss, field).unwrap());
+            }
+        }
+
+        let verifiers = to_verifier_set(&params).unwrap();
+        let imported = from_verifier_set(&verifiers, &params.p, &params.q).unwrap();
+        assert_eq!(*params, imported);
+        for share in &dealer.shares {
+            assert!(vss::verify_share(
+                share.index(),
+                share.value(),
+                &imported.g,
+                &imported.commitments,
+                &imported.p
+            ));
+        }
+        assert!(from_verifier_set(&verifiers[..1], &params.p, &params.q).is_err());
+    }
+
+    #[test]
+    fn encodings_follow_vsss_rs() {
+        // a k256 scalar share: identifier byte, then the scalar big endian in 32 bytes
+        let k256 = Element {
+            len: 32,
+            endian: Endian::Big,
+        };
+        let share = Share::new(BigUint::from(3_u32), BigUint::from(0x0102_u32));
+        let bytes = to_share_bytes(&share, k256).unwrap();
+        assert_eq!([3, 0x01, 0x02], [bytes[0], bytes[31], bytes[32]]);
+
+        // curve25519 scalars are little endian
+        let ed25519 = Element {
+            endian: Endian::Little,
+            ..k256
+        };
+        let vsss = to_vsss_share(&share, ed25519).unwrap();
+        assert_eq!(
+            [3, 0x02, 0x01],
+            [vsss.identifier[0], vsss.value[0], vsss.value[1]]
+        );
+
+        assert!(to_share_bytes(
+            &Share::new(BigUint::from(256_u32), BigUint::from(1_u32)),
+            k256
+        )
+        .is_err());
+        assert!(from_share_bytes(&bytes[..32], k256).is_err());
+        assert!(from_share_bytes(&[0; 33], k256).is_err());
+    }
+}
@@ -0,0 +1,98 @@
+//! Shares kept in the OS credential store, the macOS Keychain, Windows Credential Manager or
+//! the Secret Service, rather than in files anyone with the disk can copy
+//!
+//! An entry holds a share file under service `SERVICE` and a name of the caller's choosing,
+//! plain or encrypted under a passphrase like a share file on disk.
+
+use std::io;
+
+use keyring::Entry;
+use zeroize::Zeroizing;
+
+use crate::format::{self, EncryptedShareFile, Kdf};
+use crate::rpc::ShareInfo;
+
+/// Service every entry of this crate is stored under
+pub const SERVICE: &str = "rust_vss";
+
+/// The credential store's error as an io::Error of the closest kind
+fn to_io(err: keyring::Error) -> io::Error {
+    let kind = match err {
+        keyring::Error::NoEntry => io::ErrorKind::NotFound,
+        keyring::Error::NoStorageAccess(_) => io::ErrorKind::PermissionDenied,
+        keyring::Error::BadEncoding(_) => io::ErrorKind::InvalidData,
+        keyring::Error::TooLong(..) | keyring::Error::Invalid(..) => io::ErrorKind::InvalidInput,
+        _ => io::ErrorKind::Other,
+    };
+
+    io::Error::new(kind, format!("credential store: {}", err))
+}
+
+/// The entry of the credential store named `name`
+pub fn entry(name: &str) -> io::Result<Entry> {
+    Entry::new(SERVICE, name).map_err(to_io)
+}
+
+/// Store `info` in `entry` as a share file
+pub fn save_share(entry: &Entry, info: &ShareInfo) -> io::Result<()> {
+    let mut bytes = Zeroizing::new(vec![]);
+    format::write_share(&mut *bytes, info)?;
+
+    entry.set_secret(&bytes).map_err(to_io)
+}
+
+/// Store `info` in `entry` as a share file encrypted under `passphrase`
+pub fn save_encrypted_share(entry: &Entry, info: &ShareInfo, passphrase: &[u8]) -> io::Result<()> {
+    let file = EncryptedShareFile::encrypt(info, passphrase, Kdf::recommended())?;
+
+    entry.set_secret(&serde_json::to_vec(&file)?).map_err(to_io)
+}
+
+/// The share stored in `entry`, asking `passphrase` for the passphrase if it is encrypted
+pub fn load_share_with<F>(entry: &Entry, passphrase: F) -> io::Result<ShareInfo>
+where
+    F: FnOnce() -> io::Result<String>,
+{
+    let bytes = Zeroizing::new(entry.get_secret().map_err(to_io)?);
+
+    format::read_share_with(&bytes[..], passphrase)
+}
+
+/// Remove the share stored in `entry`
+pub fn delete_share(entry: &Entry) -> io::Result<()> {
+    entry.delete_credential().map_err(to_io)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io;
+
+    use keyring::Entry;
+
+    use crate::dealer::Dealer;
+    use crate::rpc::ShareInfo;
+
+    use super::{delete_share, load_share_with, save_encrypted_share, save_share, SERVICE};
+
+    #[test]
+    fn share_survives_the_credential_store() {
+        keyring::set_default_credential_builder(keyring::mock::default_credential_builder());
+        // the mock store keeps a credential on the entry it was set through only
+        let entry = Entry::new(SERVICE, "player-1").unwrap();
+        let dealer = Dealer::new(3, 2, 1234).unwrap();
+        let info = ShareInfo::with_params(dealer.shares[0].clone(), dealer.params());
+        let no_passphrase = || Err(io::Error::other("asked for a passphrase"));
+
+        save_share(&entry, &info).unwrap();
+        assert_eq!(info, load_share_with(&entry, no_passphrase).unwrap());
+
+        save_encrypted_share(&entry, &info, b"passphrase").unwrap();
+        assert!(load_share_with(&entry, no_passphrase).is_err());
+        let loaded = load_share_with(&entry, || Ok("passphrase".to_string())).unwrap();
+        assert_eq!(info, loaded);
+
+        delete_share(&entry).unwrap();
+        let err = load_share_with(&entry, no_passphrase).unwrap_err();
+        assert_eq!(io::ErrorKind::NotFound, err.kind());
+    }
+}
@@ -0,0 +1,117 @@
+//! Keys derived from a shared master secret, so a reconstructed secret, or the group secret of
+//! a DKG once its players reconstruct the session, becomes keys to sign and encrypt with
+//!
+//! Every key is HKDF-SHA256 of the secret's canonical bytes, under the salt `KEYS_SALT` and an
+//! info naming the algorithm and the caller's context. Keys of different algorithms or contexts
+//! are independent of each other, and none of them gives the secret away.
+
+use hkdf::Hkdf;
+use k256::ecdsa;
+use sha2::Sha256;
+use zeroize::Zeroizing;
+
+use crate::codec;
+use crate::secret::Secret;
+
+/// HKDF salt of every key derived from a secret
+pub const KEYS_SALT: &[u8] = b"rust_vss keys v1";
+
+/// `okm.len()` bytes of key material for `algorithm` and `context`, the `counter`th try at a
+/// valid key
+fn expand(secret: &Secret, algorithm: &str, context: &[u8], counter: u8, okm: &mut [u8]) {
+    let ikm = Zeroizing::new(codec::to_bytes(secret.expose()));
+    let hkdf = Hkdf::<Sha256>::new(Some(KEYS_SALT), &ikm);
+    // the algorithm and counter are fixed length once delimited, so no context collides
+    let info = [algorithm.as_bytes(), &[0, counter], context].concat();
+
+    hkdf.expand(&info, okm)
+        .expect("key material is far shorter than 255 hashes");
+}
+
+/// Key material of any length for a use of the caller's own, named by `context`
+pub fn derive_bytes(secret: &Secret, context: &[u8], len: usize) -> Zeroizing<Vec<u8>> {
+    let mut okm = Zeroizing::new(vec![0; len]);
+    expand(secret, "bytes", context, 0, &mut okm);
+
+    okm
+}
+
+/// The ed25519 signing key of `secret` for `context`
+pub fn ed25519(secret: &Secret, context: &[u8]) -> ed25519_dalek::SigningKey {
+    let mut seed = Zeroizing::new([0; 32]);
+    expand(secret, "ed25519", context, 0, &mut seed[..]);
+
+    ed25519_dalek::SigningKey::from_bytes(&seed)
+}
+
+/// The secp256k1 ECDSA signing key of `secret` for `context`
+///
+/// Key material that is zero or not below the group order is derived again with the next
+/// counter, which happens with probability about 2^-128
+pub fn secp256k1(secret: &Secret, context: &[u8]) -> ecdsa::SigningKey {
+    let mut scalar = Zeroizing::new([0; 32]);
+    (0..=u8::MAX)
+        .find_map(|counter| {
+            expand(secret, "secp256k1", context, counter, &mut scalar[..]);
+            ecdsa::SigningKey::from_bytes(&(*scalar).into()).ok()
+        })
+        .expect("256 tries all landed outside the group order")
+}
+
+/// The x25519 key agreement secret of `secret` for `context`, to encrypt to with its public key
+pub fn x25519(secret: &Secret, context: &[u8]) -> x25519_dalek::StaticSecret {
+    let mut bytes = Zeroizing::new([0; 32]);
+    expand(secret, "x25519", context, 0, &mut bytes[..]);
+
+    x25519_dalek::StaticSecret::from(*bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use ed25519_dalek::{Signer, Verifier};
+    use num_bigint::BigUint;
+
+    use crate::secret::Secret;
+
+    use super::{derive_bytes, ed25519, secp256k1, x25519};
+
+    #[test]
+    fn keys_are_derived_per_algorithm_and_context() {
+        let secret = Secret::new(BigUint::from(1234_u32));
+        let other = Secret::new(BigUint::from(1235_u32));
+
+        // the same secret and context derive the same keys
+        assert_eq!(ed25519(&secret, b"a"), ed25519(&secret, b"a"));
+        assert_ne!(ed25519(&secret, b"a"), ed25519(&secret, b"b"));
+        assert_ne!(ed25519(&secret, b"a"), ed25519(&other, b"a"));
+        assert_ne!(
+            ed25519(&secret, b"a").to_bytes(),
+            x25519(&secret, b"a").to_bytes()
+        );
+        assert_eq!(
+            *derive_bytes(&secret, b"a", 64),
+            *derive_bytes(&secret, b"a", 64)
+        );
+        assert_ne!(
+            derive_bytes(&secret, b"a", 32)[..],
+            derive_bytes(&secret, b"b", 32)[..]
+        );
+
+        let key = ed25519(&secret, b"a");
+        let signature = key.sign(b"message");
+        assert!(key.verifying_key().verify(b"message", &signature).is_ok());
+
+        let key = secp256k1(&secret, b"a");
+        let signature: k256::ecdsa::Signature = key.sign(b"message");
+        assert!(key.verifying_key().verify(b"message", &signature).is_ok());
+        assert_eq!(key, secp256k1(&secret, b"a"));
+
+        // both sides of an exchange with a derived x25519 key agree
+        let ours = x25519(&secret, b"a");
+        let theirs = x25519(&other, b"a");
+        assert_eq!(
+            ours.diffie_hellman(&(&theirs).into()).as_bytes(),
+            theirs.diffie_hellman(&(&ours).into()).as_bytes()
+        );
+    }
+}
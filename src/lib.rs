@@ -1,4 +1,135 @@
-pub mod dealer;
-pub mod player;
-pub mod rpc;
-pub mod vss;
+//! Feldman's verifiable secret sharing: a dealer splits a secret into shares that players verify
+//! against public commitments, then reconstruct from any t of them
+//!
+//! The types most uses need are re-exported here and in `prelude`: `Dealer` deals shares,
+//! `Player` holds one and takes part in the protocol over a `Transport`, and the functions of
+//! `vss` share, verify and reconstruct without any network. The modules give everything else,
+//! from share file formats and transports to other fields and share layouts, while the wire
+//! protocol stays private to the crate: its messages and reports are re-exported here. For a
+//! secret of bytes `split` and `combine` do it all in one call each.
+//!
+//! ```
+//! use rust_vss::prelude::*;
+//!
+//! let dealer = Dealer::new(5, 3, 1234).unwrap();
+//! let shares = &dealer.shares[1..4];
+//! for share in shares {
+//!     assert!(verify_share(share.index(), share.value(), &dealer.g, &dealer.c, &dealer.p));
+//! }
+//! let secret = reconstruct(shares, &dealer.q).unwrap();
+//! assert_eq!(secret.expose(), &1234_u32.into());
+//! ```
+
+#[cfg(feature = "age")]
+pub mod age;
+#[cfg(feature = "audit")]
+pub mod audit;
+pub mod backend;
+#[cfg(feature = "binary")]
+pub mod binary;
+#[cfg(feature = "network")]
+pub(crate) mod broadcast;
+#[cfg(all(feature = "network", any(test, feature = "byzantine")))]
+pub mod byzantine;
+#[cfg(feature = "audit")]
+pub mod ceremony;
+pub mod codec;
+#[cfg(feature = "network")]
+pub mod config;
+#[cfg(feature = "network")]
+pub mod coordinator;
+pub mod dealer;
+#[cfg(feature = "modp")]
+pub mod dkg;
+#[cfg(feature = "modp")]
+pub mod dleq;
+#[cfg(feature = "encryption")]
+pub mod envelope;
+pub mod error;
+pub mod field;
+#[cfg(feature = "crypto-bigint")]
+pub mod fixed;
+#[cfg(feature = "serde")]
+pub mod format;
+pub mod interop;
+#[cfg(feature = "keyring")]
+pub mod keyring;
+#[cfg(feature = "keys")]
+pub mod keys;
+#[cfg(feature = "metrics")]
+pub mod metrics;
+#[cfg(feature = "uniffi")]
+pub mod mobile;
+#[cfg(feature = "modp")]
+pub mod pedersen;
+#[cfg(feature = "pkcs11")]
+pub mod pkcs11;
+#[cfg(feature = "network")]
+pub mod player;
+pub mod policy;
+pub mod prelude;
+pub(crate) mod primes;
+#[cfg(feature = "protobuf")]
+pub mod proto;
+#[cfg(feature = "network")]
+pub(crate) mod protocol;
+#[cfg(feature = "qr")]
+pub mod qr;
+#[cfg(feature = "modp")]
+pub mod recovery;
+#[cfg(feature = "modp")]
+pub mod refresh;
+#[cfg(feature = "ristretto")]
+pub mod ristretto;
+pub(crate) mod rpc;
+#[cfg(feature = "secp256k1")]
+pub mod secp256k1;
+pub mod secret;
+#[cfg(feature = "modp")]
+pub mod sign;
+#[cfg(feature = "network")]
+pub mod sim;
+#[cfg(feature = "serde")]
+pub mod simple;
+#[cfg(feature = "slip39")]
+pub mod slip39;
+pub mod ssss;
+#[cfg(feature = "serde")]
+pub mod testvectors;
+#[cfg(feature = "network")]
+pub mod transport;
+#[cfg(feature = "slip39")]
+pub mod vault;
+pub mod vss;
+#[cfg(feature = "wasm")]
+pub mod wasm;
+pub mod words;
+#[cfg(feature = "network")]
+pub(crate) mod worker;
+
+#[cfg(feature = "uniffi")]
+uniffi::setup_scaffolding!();
+
+#[cfg(feature = "network")]
+pub use crate::broadcast::BroadcastMessage;
+#[cfg(feature = "network")]
+pub use crate::config::Config;
+pub use crate::dealer::{Dealer, DEALER_ID};
+pub use crate::error::VssError;
+#[cfg(feature = "network")]
+pub use crate::player::Player;
+pub use crate::primes::{is_probable_prime, random_prime, Primality};
+#[cfg(feature = "audit")]
+pub use crate::rpc::{generate_signing_key, generate_signing_key_with_rng, Signed};
+#[cfg(feature = "network")]
+pub use crate::rpc::{
+    negotiate, BatchReconstructionReport, CorrelationId, DkgReport, Nack, Payload,
+    ReconstructionReport, RefreshReport, RejectReason, Reply, Router, SignatureReport,
+    StatusReport, Version, WireMessage, PROTOCOL_VERSION, RPC, SUPPORTED_VERSIONS,
+};
+pub use crate::rpc::{Metadata, PublicParams, Share, ShareInfo, Validity, NO_SESSION};
+pub use crate::secret::{Secret, SecretBytes};
+#[cfg(feature = "serde")]
+pub use crate::simple::{combine, split, EncodedShare};
+#[cfg(feature = "network")]
+pub use crate::transport::Transport;
@@ -0,0 +1,169 @@
+//! Prometheus metrics of a Player: shares verified and failing verification, how long
+//! reconstructions take and the messages received by type
+//!
+//! Players record through the `metrics` facade, which does nothing until a recorder is
+//! installed. A node installs one with `install` and serves what it recorded to Prometheus
+//! with `serve`.
+
+use std::io::{self, BufRead, BufReader, Write};
+use std::net::{TcpListener, TcpStream};
+use std::thread;
+use std::time::Duration;
+
+use metrics::{counter, histogram};
+use metrics_exporter_prometheus::{Matcher, PrometheusBuilder, PrometheusHandle};
+use tracing::warn;
+
+use crate::rpc::Payload;
+
+/// Shares checked against their dealing's commitments, valid or not
+pub const SHARES_VERIFIED: &str = "vss_shares_verified_total";
+/// Shares that failed verification
+pub const VERIFICATION_FAILURES: &str = "vss_share_verification_failures_total";
+/// Seconds from a Reconstruct request to the secret, a histogram
+pub const RECONSTRUCTION_SECONDS: &str = "vss_reconstruction_seconds";
+/// Messages received from peers, labelled by `type`
+pub const MESSAGES: &str = "vss_messages_total";
+
+/// Upper bounds of the buckets of `RECONSTRUCTION_SECONDS`
+const RECONSTRUCTION_BUCKETS: &[f64] = &[0.01, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0];
+
+/// Install the process-wide recorder every Player records to, returning the handle rendering
+/// what was recorded
+pub fn install() -> io::Result<PrometheusHandle> {
+    PrometheusBuilder::new()
+        .set_buckets_for_metric(
+            Matcher::Full(RECONSTRUCTION_SECONDS.to_string()),
+            RECONSTRUCTION_BUCKETS,
+        )
+        .and_then(PrometheusBuilder::install_recorder)
+        .map_err(io::Error::other)
+}
+
+/// Answer every HTTP request on `listener` with the metrics of `handle` until the process exits
+pub fn serve(listener: TcpListener, handle: PrometheusHandle) {
+    thread::spawn(move || {
+        for stream in listener.incoming().flatten() {
+            if let Err(err) = scrape(stream, &handle) {
+                warn!(%err, "metrics scrape failed");
+            }
+        }
+    });
+}
+
+/// Read one request off `stream` and answer it with the rendered metrics
+fn scrape(stream: TcpStream, handle: &PrometheusHandle) -> io::Result<()> {
+    let mut reader = BufReader::new(stream);
+    let mut line = String::new();
+    // skip the request line and headers, every path gets the metrics
+    while reader.read_line(&mut line)? > 0 && line != "\r\n" && line != "\n" {
+        line.clear();
+    }
+
+    let body = handle.render();
+    let mut stream = reader.into_inner();
+    write!(
+        stream,
+        "HTTP/1.1 200 OK\r\nContent-Type: text/plain; version=0.0.4\r\nContent-Length: {}\r\n\
+         Connection: close\r\n\r\n{}",
+        body.len(),
+        body
+    )?;
+
+    stream.flush()
+}
+
+/// Count a share verification
+pub(crate) fn share_verified(valid: bool) {
+    counter!(SHARES_VERIFIED).increment(1);
+    if !valid {
+        counter!(VERIFICATION_FAILURES).increment(1);
+    }
+}
+
+/// Record how long a reconstruction took
+pub(crate) fn reconstructed(elapsed: Duration) {
+    histogram!(RECONSTRUCTION_SECONDS).record(elapsed.as_secs_f64());
+}
+
+/// Count a message received from a peer
+pub(crate) fn message(payload: &Payload) {
+    counter!(MESSAGES, "type" => kind(payload)).increment(1);
+}
+
+/// The `type` label of a message
+fn kind(payload: &Payload) -> &'static str {
+    match payload {
+        Payload::Ping => "ping",
+        Payload::Hello(_) => "hello",
+        Payload::RegShare(_) => "reg_share",
+        Payload::ReconstructShare(_) => "reconstruct_share",
+        Payload::Broadcast(_) => "broadcast",
+        Payload::Sequenced(..) => "sequenced",
+        Payload::Ack(_) => "ack",
+        Payload::Nack(_) => "nack",
+        Payload::RefreshDeal { .. } => "refresh_deal",
+        Payload::RefreshAck { .. } => "refresh_ack",
+        Payload::ReshareSubShare { .. } => "reshare_sub_share",
+        Payload::EpochCommit { .. } => "epoch_commit",
+        Payload::Complaint { .. } => "complaint",
+        Payload::ComplaintResponse(_) => "complaint_response",
+        Payload::Disqualify(_) => "disqualify",
+        Payload::Dkg(_) => "dkg",
+        Payload::Heartbeat => "heartbeat",
+        Payload::ShareRequest => "share_request",
+        Payload::Batch(_) => "batch",
+        Payload::SignRequest { .. } => "sign_request",
+        Payload::NonceCommitment { .. } => "nonce_commitment",
+        Payload::PartialSignature { .. } => "partial_signature",
+        Payload::SignatureResult { .. } => "signature_result",
+        Payload::RefreshRequest => "refresh_request",
+        Payload::RefreshResult { .. } => "refresh_result",
+        Payload::RecoveryRequest { .. } => "recovery_request",
+        Payload::RecoveryPiece { .. } => "recovery_piece",
+        Payload::RecoveryShare(_) => "recovery_share",
+        Payload::DkgRequest(_) => "dkg_request",
+        Payload::DkgResult { .. } => "dkg_result",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::{Read, Write};
+    use std::net::{TcpListener, TcpStream};
+    use std::time::Duration;
+
+    use metrics_exporter_prometheus::PrometheusBuilder;
+
+    use crate::rpc::Payload;
+
+    use super::{message, reconstructed, serve, share_verified};
+
+    #[test]
+    fn recorded_metrics_are_served() {
+        let recorder = PrometheusBuilder::new().build_recorder();
+        let handle = recorder.handle();
+        metrics::with_local_recorder(&recorder, || {
+            share_verified(true);
+            share_verified(false);
+            message(&Payload::Heartbeat);
+            reconstructed(Duration::from_millis(30));
+        });
+
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap();
+        serve(listener, handle);
+        let mut stream = TcpStream::connect(address).unwrap();
+        stream
+            .write_all(b"GET /metrics HTTP/1.1\r\nHost: vss\r\n\r\n")
+            .unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).unwrap();
+
+        assert!(response.starts_with("HTTP/1.1 200 OK"));
+        assert!(response.contains("vss_shares_verified_total 2"));
+        assert!(response.contains("vss_share_verification_failures_total 1"));
+        assert!(response.contains("vss_messages_total{type=\"heartbeat\"} 1"));
+        assert!(response.contains("vss_reconstruction_seconds"));
+    }
+}
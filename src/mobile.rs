@@ -0,0 +1,119 @@
+//! UniFFI bindings for Kotlin and Swift, so a mobile app can hold a share as a shareholder of a
+//! custody setup: check it against its dealing, save it as a share file and give it to a
+//! reconstruction
+//!
+//! Bindings are generated from the built library by `uniffi-bindgen`, see the README
+
+use std::io;
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use crate::dealer::Dealer;
+use crate::format;
+use crate::rpc::ShareInfo;
+
+/// Why a call failed, with the message of the underlying error
+#[derive(Debug, thiserror::Error, uniffi::Error)]
+#[uniffi(flat_error)]
+pub enum MobileError {
+    #[error("{0}")]
+    Invalid(String),
+}
+
+impl From<io::Error> for MobileError {
+    fn from(err: io::Error) -> MobileError {
+        MobileError::Invalid(err.to_string())
+    }
+}
+
+/// A share with the public parameters of its dealing
+#[derive(Debug, uniffi::Object)]
+pub struct MobileShare(ShareInfo);
+
+#[uniffi::export]
+impl MobileShare {
+    /// Read a share file, as `vss deal` writes them
+    #[uniffi::constructor]
+    pub fn from_json(json: String) -> Result<Arc<MobileShare>, MobileError> {
+        Ok(Arc::new(MobileShare(format::read_share(json.as_bytes())?)))
+    }
+
+    /// The share as a share file
+    pub fn to_json(&self) -> Result<String, MobileError> {
+        let mut json = vec![];
+        format::write_share(&mut json, &self.0)?;
+
+        String::from_utf8(json).map_err(|err| MobileError::Invalid(err.to_string()))
+    }
+
+    /// The index of the share, also the id of the player holding it
+    pub fn index(&self) -> String {
+        self.0.share().index().to_string()
+    }
+
+    /// Number of shares needed to reconstruct the secret
+    pub fn threshold(&self) -> u64 {
+        self.0.t() as u64
+    }
+
+    /// Fingerprint of the dealing, equal for every share of it
+    pub fn fingerprint(&self) -> String {
+        format::fingerprint(&self.0)
+    }
+
+    /// Whether the share verifies against the commitments of its dealing
+    pub fn verify(&self) -> bool {
+        self.0.verify()
+    }
+}
+
+/// Split `secret` into `n` shares, any `t` of which reconstruct it
+#[uniffi::export]
+pub fn split(secret: Vec<u8>, n: u32, t: u32) -> Result<Vec<Arc<MobileShare>>, MobileError> {
+    let dealer = Dealer::with_secret(n as usize, t as usize, format::encode_secret(&secret))
+        .map_err(io::Error::from)?;
+    let params = dealer.params();
+
+    Ok(dealer
+        .shares
+        .iter()
+        .map(|share| {
+            let info = ShareInfo::with_params(share.clone(), params.clone());
+
+            Arc::new(MobileShare(info))
+        })
+        .collect())
+}
+
+/// The secret of the shares, which must all verify and come from the same dealing
+#[uniffi::export]
+pub fn reconstruct(shares: Vec<Arc<MobileShare>>) -> Result<Vec<u8>, MobileError> {
+    // errors name a share by its position, as they would name its file
+    let infos: Vec<_> = shares
+        .iter()
+        .enumerate()
+        .map(|(i, share)| (PathBuf::from(format!("share {}", i)), share.0.clone()))
+        .collect();
+
+    let secret = format::decode_secret(&format::reconstruct_shares(&infos)?)?;
+
+    Ok(secret.expose().to_vec())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{reconstruct, split, MobileShare};
+
+    #[test]
+    fn shares_cross_as_share_files() {
+        let shares = split(b"custody".to_vec(), 5, 3).unwrap();
+        let held = MobileShare::from_json(shares[0].to_json().unwrap()).unwrap();
+
+        assert!(held.verify());
+        assert_eq!("1", held.index());
+        assert_eq!(shares[4].fingerprint(), held.fingerprint());
+        let quorum = vec![held, shares[2].clone(), shares[4].clone()];
+        assert_eq!(b"custody".to_vec(), reconstruct(quorum).unwrap());
+        assert!(reconstruct(shares[..2].to_vec()).is_err());
+    }
+}
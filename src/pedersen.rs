@@ -0,0 +1,374 @@
+//! Upgrading a Feldman dealing to Pedersen commitments in place, so a deployment gains hiding
+//! commitments without redealing its secret
+//!
+//! Feldman commitments c_k = g^a_k give away g^secret, anyone may check a guess of the secret
+//! against c_0. Pedersen commitments c_k = g^a_k * h^b_k are blinded by a second polynomial b
+//! and hide the secret entirely, as long as nobody knows log_g h. The players build b together:
+//! every player deals a sharing of zero blinded by a polynomial of its own, and each adds the
+//! points it is dealt to its share and blinding. The secret stays the same but every share
+//! changes, so shares of the Feldman dealing must not be combined with upgraded ones, and its
+//! commitments should be discarded once the upgrade completes.
+
+use std::collections::{HashMap, HashSet};
+
+use num::{One, Zero};
+use num_bigint::BigUint;
+use num_primes::RandBigInt;
+use rand::{CryptoRng, RngCore};
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+#[cfg(feature = "serde")]
+use crate::codec;
+use crate::dleq;
+use crate::rpc::{Metadata, Share, ShareInfo, Validity};
+use crate::secret;
+use crate::vss;
+
+/// Hashed into the second generator h of a group
+const GENERATOR_DOMAIN: &[u8] = b"rust_vss pedersen h v1";
+
+/// Context of the proofs dealers make of their first commitment
+const PROOF_CONTEXT: &[u8] = b"rust_vss pedersen upgrade v1";
+
+/// The second generator h of the group of order q generated by g
+///
+/// h is hashed from the group, so anyone can derive it and nobody knows its logarithm to g
+pub fn generator(p: &BigUint, q: &BigUint, g: &BigUint) -> BigUint {
+    let cofactor = (p - 1_u32) / q;
+
+    (0_u32..)
+        .map(|counter| {
+            let mut hasher = Sha256::new();
+            hasher.update(GENERATOR_DOMAIN);
+            for x in [p, q, g] {
+                let bytes = x.to_bytes_be();
+                hasher.update((bytes.len() as u64).to_be_bytes());
+                hasher.update(bytes);
+            }
+            hasher.update(counter.to_be_bytes());
+
+            (BigUint::from_bytes_be(&hasher.finalize()) % p).modpow(&cofactor, p)
+        })
+        .find(|h| !h.is_zero() && !h.is_one() && h != g)
+        .expect("some hash lands outside the identity")
+}
+
+/// A player's share of a dealing with Pedersen commitments
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct PedersenShare {
+    pub share: Share,
+    /// The player's point on the blinding polynomial
+    #[cfg_attr(feature = "serde", serde(with = "codec::biguint"))]
+    pub blinding: BigUint,
+    #[cfg_attr(feature = "serde", serde(with = "codec::biguint"))]
+    pub g: BigUint,
+    #[cfg_attr(feature = "serde", serde(with = "codec::biguint"))]
+    pub h: BigUint,
+    /// g^a_k * h^b_k of every coefficient of the sharing and blinding polynomials
+    #[cfg_attr(feature = "serde", serde(with = "codec::biguints"))]
+    pub commitments: Vec<BigUint>,
+    #[cfg_attr(feature = "serde", serde(with = "codec::biguint"))]
+    pub p: BigUint,
+    #[cfg_attr(feature = "serde", serde(with = "codec::biguint"))]
+    pub q: BigUint,
+    pub t: usize,
+    pub metadata: Metadata,
+    pub validity: Validity,
+}
+
+impl PedersenShare {
+    /// Verify this player's own share
+    pub fn verify(&self) -> bool {
+        self.verify_share(&self.share, &self.blinding)
+    }
+
+    /// Verify that g^s * h^r mod p matches the commitments at the index of `share`
+    pub fn verify_share(&self, share: &Share, blinding: &BigUint) -> bool {
+        let Self { g, h, p, .. } = self;
+
+        !p.is_zero()
+            && secret::ct_eq(
+                &(g.modpow(share.value(), p) * h.modpow(blinding, p) % p),
+                &vss::commitment_at(share.index(), &self.commitments, p),
+            )
+    }
+}
+
+/// A player's point on another player's blinded sharing of zero, with commitments to it
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Deal {
+    /// The point on the sharing of zero
+    pub sub_share: Share,
+    /// The point on the dealer's blinding polynomial
+    #[cfg_attr(feature = "serde", serde(with = "codec::biguint"))]
+    pub blinding: BigUint,
+    /// g^z_k * h^b_k of every coefficient, the first is h^b_0 alone
+    #[cfg_attr(feature = "serde", serde(with = "codec::biguints"))]
+    pub commitments: Vec<BigUint>,
+    /// That the dealer knows log_h of the first commitment
+    pub proof: dleq::Proof,
+}
+
+/// Context of the proof of `dealer`, so no dealer passes another's proof off as its own
+fn proof_context(dealer: usize) -> Vec<u8> {
+    [PROOF_CONTEXT, &(dealer as u64).to_be_bytes()].concat()
+}
+
+/// The statement that `c_0` is a power of `h`
+fn first_statement(c_0: &BigUint, h: &BigUint) -> dleq::Statement {
+    dleq::Statement {
+        g: h.clone(),
+        h: h.clone(),
+        a: c_0.clone(),
+        b: c_0.clone(),
+    }
+}
+
+/// A player's progress upgrading one dealing to Pedersen commitments
+///
+/// Runs like a refresh: the upgrade completes once the player has verified a deal from every
+/// player and every player has acknowledged verifying every deal, so all of them combine the
+/// same deals
+#[derive(Debug)]
+pub struct Upgrade {
+    h: BigUint,
+    dealt: bool,
+    /// verified deals to this player, by dealer
+    deals: HashMap<usize, Deal>,
+    /// players that verified each deal, by dealer
+    acks: HashMap<usize, HashSet<usize>>,
+}
+
+impl Upgrade {
+    /// Upgrade of the dealing in `info`
+    pub fn new(info: &ShareInfo) -> Upgrade {
+        Upgrade {
+            h: generator(info.p(), info.q(), info.g()),
+            dealt: false,
+            deals: HashMap::new(),
+            acks: HashMap::new(),
+        }
+    }
+
+    /// The second generator of the upgraded dealing
+    pub fn h(&self) -> &BigUint {
+        &self.h
+    }
+
+    /// Deals this player's blinded sharing of zero to `players` once, as the player `id`,
+    /// returns each player's deal
+    ///
+    /// The coefficients are drawn from `rng`. The first commitment is h^b_0 alone, a dealer
+    /// proves knowing b_0 rather than revealing it, which would give g^secret away again
+    pub fn deal<R: RngCore + CryptoRng>(
+        &mut self,
+        info: &ShareInfo,
+        id: usize,
+        players: &[usize],
+        rng: &mut R,
+    ) -> Option<Vec<(usize, Deal)>> {
+        if self.dealt {
+            return None;
+        }
+        self.dealt = true;
+
+        let (g, h, p, q) = (info.g(), &self.h, info.p(), info.q());
+        let z: Vec<BigUint> = std::iter::once(BigUint::zero())
+            .chain((1..info.t()).map(|_| rng.gen_biguint_below(q)))
+            .collect();
+        let b: Vec<BigUint> = (0..info.t()).map(|_| rng.gen_biguint_below(q)).collect();
+        let commitments: Vec<BigUint> = z
+            .iter()
+            .zip(&b)
+            .map(|(z_k, b_k)| g.modpow(z_k, p) * h.modpow(b_k, p) % p)
+            .collect();
+        let proof = dleq::prove(
+            &b[0],
+            &first_statement(&commitments[0], h),
+            &proof_context(id),
+            p,
+            q,
+            rng,
+        );
+        let max = players.iter().copied().max().unwrap_or(0);
+
+        let deals = vss::generate_shares(&z, max, q)
+            .into_iter()
+            .zip(vss::generate_shares(&b, max, q))
+            .zip(1..)
+            .filter(|(_, id)| players.contains(id))
+            .map(|((sub_share, blinding), id)| {
+                let deal = Deal {
+                    sub_share,
+                    blinding: blinding.value().clone(),
+                    commitments: commitments.clone(),
+                    proof: proof.clone(),
+                };
+
+                (id, deal)
+            })
+            .collect();
+
+        Some(deals)
+    }
+
+    /// Verifies the deal `dealer` sent to player `id` and keeps it, returns false if it is
+    /// invalid
+    pub fn receive(&mut self, info: &ShareInfo, dealer: usize, id: usize, deal: Deal) -> bool {
+        let (g, h, p, q) = (info.g(), &self.h, info.p(), info.q());
+        let Deal {
+            sub_share,
+            blinding,
+            commitments,
+            proof,
+        } = &deal;
+
+        let valid = *sub_share.index() == BigUint::from(id)
+            && !commitments.is_empty()
+            && commitments.len() == info.t()
+            && dleq::verify(
+                &first_statement(&commitments[0], h),
+                proof,
+                &proof_context(dealer),
+                p,
+                q,
+            )
+            && secret::ct_eq(
+                &(g.modpow(sub_share.value(), p) * h.modpow(blinding, p) % p),
+                &vss::commitment_at(sub_share.index(), commitments, p),
+            );
+        if valid {
+            self.deals.insert(dealer, deal);
+        }
+
+        valid
+    }
+
+    /// Records that `from` verified the deal of `dealer`
+    pub fn ack(&mut self, dealer: usize, from: usize) {
+        self.acks.entry(dealer).or_default().insert(from);
+    }
+
+    /// Whether every player in `players` dealt and acknowledged every deal
+    pub fn is_complete(&self, players: &[usize]) -> bool {
+        players.iter().all(|dealer| {
+            self.deals.contains_key(dealer)
+                && self
+                    .acks
+                    .get(dealer)
+                    .is_some_and(|acks| players.iter().all(|id| acks.contains(id)))
+        })
+    }
+
+    /// Combines the deals of `players` with the share in `info` into its share of the upgraded
+    /// dealing, keeping its metadata and moving its validity window to the next epoch
+    ///
+    /// Must only be called once `is_complete`
+    pub fn commit(&self, info: &ShareInfo, players: &[usize]) -> PedersenShare {
+        let (p, q) = (info.p(), info.q());
+        let deals: Vec<&Deal> = players.iter().map(|dealer| &self.deals[dealer]).collect();
+
+        let value = deals
+            .iter()
+            .fold(info.share().value().clone(), |value, deal| {
+                (value + deal.sub_share.value()) % q
+            });
+        let blinding = deals.iter().fold(BigUint::zero(), |blinding, deal| {
+            (blinding + &deal.blinding) % q
+        });
+        let commitments = info
+            .commitments()
+            .iter()
+            .enumerate()
+            .map(|(k, c)| {
+                deals
+                    .iter()
+                    .fold(c.clone(), |c, deal| c * &deal.commitments[k] % p)
+            })
+            .collect();
+
+        PedersenShare {
+            share: Share::new(info.share().index().clone(), value),
+            blinding,
+            g: info.g().clone(),
+            h: self.h.clone(),
+            commitments,
+            p: p.clone(),
+            q: q.clone(),
+            t: info.t(),
+            metadata: info.metadata().clone(),
+            validity: info.validity().next(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use num::ToPrimitive;
+
+    use crate::dealer::Dealer;
+    use crate::rpc::{Share, ShareInfo};
+    use crate::vss;
+
+    use super::{PedersenShare, Upgrade};
+
+    #[test]
+    fn upgrade_keeps_secret_and_hides_it() {
+        let dealer = Dealer::new(5, 3, 1234).unwrap();
+        let players: Vec<usize> = (1..=5).collect();
+        let infos: Vec<ShareInfo> = dealer
+            .shares
+            .iter()
+            .map(|share| ShareInfo::with_params(share.clone(), dealer.params()))
+            .collect();
+
+        let mut upgrades: Vec<Upgrade> = infos.iter().map(Upgrade::new).collect();
+        for (&dealer, info) in players.iter().zip(&infos) {
+            let deals = upgrades[dealer - 1]
+                .deal(info, dealer, &players, &mut rand::thread_rng())
+                .unwrap();
+            for (id, deal) in deals {
+                // a dealer whose first commitment hides more than zero is caught
+                let mut shifted = deal.clone();
+                shifted.commitments[0] = &shifted.commitments[0] * infos[0].g() % infos[0].p();
+                assert!(!upgrades[id - 1].receive(&infos[id - 1], dealer, id, shifted));
+                let mut bad = deal.clone();
+                bad.blinding += 1_u32;
+                assert!(!upgrades[id - 1].receive(&infos[id - 1], dealer, id, bad));
+
+                assert!(upgrades[id - 1].receive(&infos[id - 1], dealer, id, deal));
+                players
+                    .iter()
+                    .for_each(|from| upgrades[id - 1].ack(dealer, *from));
+            }
+        }
+
+        let upgraded: Vec<PedersenShare> = upgrades
+            .iter()
+            .zip(&infos)
+            .map(|(upgrade, info)| {
+                assert!(upgrade.is_complete(&players));
+                upgrade.commit(info, &players)
+            })
+            .collect();
+        for (share, info) in upgraded.iter().zip(&infos) {
+            assert!(share.verify());
+            assert_ne!(info.share(), &share.share);
+            assert_ne!(share.commitments[0], dealer.c[0]);
+            #[cfg(feature = "serde")]
+            {
+                let json = serde_json::to_string(share).unwrap();
+                assert_eq!(*share, serde_json::from_str(&json).unwrap());
+            }
+        }
+        assert!(!upgraded[0].verify_share(&upgraded[1].share, &upgraded[0].blinding));
+
+        let shares: Vec<Share> = upgraded[2..].iter().map(|s| s.share.clone()).collect();
+        let secret = vss::reconstruct(&shares, &dealer.q).unwrap();
+        assert_eq!(Some(1234), secret.expose().to_usize());
+    }
+}
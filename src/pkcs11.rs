@@ -0,0 +1,413 @@
+//! A share kept in an HSM or smart card, reached through its PKCS#11 module
+//!
+//! The share x_i is stored on the token as the value of a Diffie–Hellman private key (CKK_DH)
+//! whose prime and base are the dealing's p and g. base^x_i mod p is then CKM_DH_PKCS_DERIVE
+//! with base as the other party's public value, so x_i never leaves the token. PKCS#11 has no
+//! mechanism for k + c * x_i mod q, so a share kept here verifies but cannot sign.
+//!
+//! Only the handful of functions this needs are bound, loaded from the module at run time.
+
+#![allow(non_snake_case)]
+
+use std::ffi::c_void;
+use std::io;
+use std::os::raw::c_ulong;
+use std::path::Path;
+use std::ptr;
+use std::sync::Mutex;
+
+use libloading::Library;
+use num_bigint::BigUint;
+use zeroize::Zeroizing;
+
+use crate::backend::ShareBackend;
+
+type Rv = c_ulong;
+type Handle = c_ulong;
+
+const CKR_OK: Rv = 0;
+const CKR_USER_ALREADY_LOGGED_IN: Rv = 0x100;
+const CKR_CRYPTOKI_ALREADY_INITIALIZED: Rv = 0x191;
+const CKF_RW_SESSION: c_ulong = 0x2;
+const CKF_SERIAL_SESSION: c_ulong = 0x4;
+const CKU_USER: c_ulong = 1;
+const CKO_PRIVATE_KEY: c_ulong = 3;
+const CKO_SECRET_KEY: c_ulong = 4;
+const CKK_DH: c_ulong = 2;
+const CKK_GENERIC_SECRET: c_ulong = 0x10;
+const CKM_DH_PKCS_DERIVE: c_ulong = 0x21;
+const CKA_CLASS: c_ulong = 0x0;
+const CKA_TOKEN: c_ulong = 0x1;
+const CKA_LABEL: c_ulong = 0x3;
+const CKA_VALUE: c_ulong = 0x11;
+const CKA_KEY_TYPE: c_ulong = 0x100;
+const CKA_SENSITIVE: c_ulong = 0x103;
+const CKA_PRIME: c_ulong = 0x130;
+const CKA_EXTRACTABLE: c_ulong = 0x162;
+
+/// CK_ATTRIBUTE, packed to a byte on Windows as the standard's headers do
+#[cfg_attr(windows, repr(C, packed(1)))]
+#[cfg_attr(not(windows), repr(C))]
+struct Attribute {
+    kind: c_ulong,
+    value: *mut c_void,
+    len: c_ulong,
+}
+
+impl Attribute {
+    fn new<T>(kind: c_ulong, value: &T) -> Attribute {
+        Attribute {
+            kind,
+            value: value as *const T as *mut c_void,
+            len: std::mem::size_of::<T>() as c_ulong,
+        }
+    }
+
+    fn bytes(kind: c_ulong, value: &[u8]) -> Attribute {
+        Attribute {
+            kind,
+            value: value.as_ptr() as *mut c_void,
+            len: value.len() as c_ulong,
+        }
+    }
+}
+
+/// CK_MECHANISM
+#[cfg_attr(windows, repr(C, packed(1)))]
+#[cfg_attr(not(windows), repr(C))]
+struct Mechanism {
+    kind: c_ulong,
+    parameter: *mut c_void,
+    len: c_ulong,
+}
+
+/// A function of the list this module does not call
+type Unused = Option<unsafe extern "C" fn()>;
+
+/// CK_FUNCTION_LIST of PKCS#11 2.40, every function in the standard's order
+#[repr(C)]
+struct FunctionList {
+    version: [u8; 2],
+    C_Initialize: unsafe extern "C" fn(*mut c_void) -> Rv,
+    C_Finalize: unsafe extern "C" fn(*mut c_void) -> Rv,
+    C_GetInfo: Unused,
+    C_GetFunctionList: Unused,
+    C_GetSlotList: Unused,
+    C_GetSlotInfo: Unused,
+    C_GetTokenInfo: Unused,
+    C_GetMechanismList: Unused,
+    C_GetMechanismInfo: Unused,
+    C_InitToken: Unused,
+    C_InitPIN: Unused,
+    C_SetPIN: Unused,
+    C_OpenSession:
+        unsafe extern "C" fn(Handle, c_ulong, *mut c_void, *mut c_void, *mut Handle) -> Rv,
+    C_CloseSession: unsafe extern "C" fn(Handle) -> Rv,
+    C_CloseAllSessions: Unused,
+    C_GetSessionInfo: Unused,
+    C_GetOperationState: Unused,
+    C_SetOperationState: Unused,
+    C_Login: unsafe extern "C" fn(Handle, c_ulong, *const u8, c_ulong) -> Rv,
+    C_Logout: Unused,
+    C_CreateObject: Unused,
+    C_CopyObject: Unused,
+    C_DestroyObject: unsafe extern "C" fn(Handle, Handle) -> Rv,
+    C_GetObjectSize: Unused,
+    C_GetAttributeValue: unsafe extern "C" fn(Handle, Handle, *mut Attribute, c_ulong) -> Rv,
+    C_SetAttributeValue: Unused,
+    C_FindObjectsInit: unsafe extern "C" fn(Handle, *mut Attribute, c_ulong) -> Rv,
+    C_FindObjects: unsafe extern "C" fn(Handle, *mut Handle, c_ulong, *mut c_ulong) -> Rv,
+    C_FindObjectsFinal: unsafe extern "C" fn(Handle) -> Rv,
+    C_EncryptInit: Unused,
+    C_Encrypt: Unused,
+    C_EncryptUpdate: Unused,
+    C_EncryptFinal: Unused,
+    C_DecryptInit: Unused,
+    C_Decrypt: Unused,
+    C_DecryptUpdate: Unused,
+    C_DecryptFinal: Unused,
+    C_DigestInit: Unused,
+    C_Digest: Unused,
+    C_DigestUpdate: Unused,
+    C_DigestKey: Unused,
+    C_DigestFinal: Unused,
+    C_SignInit: Unused,
+    C_Sign: Unused,
+    C_SignUpdate: Unused,
+    C_SignFinal: Unused,
+    C_SignRecoverInit: Unused,
+    C_SignRecover: Unused,
+    C_VerifyInit: Unused,
+    C_Verify: Unused,
+    C_VerifyUpdate: Unused,
+    C_VerifyFinal: Unused,
+    C_VerifyRecoverInit: Unused,
+    C_VerifyRecover: Unused,
+    C_DigestEncryptUpdate: Unused,
+    C_DecryptDigestUpdate: Unused,
+    C_SignEncryptUpdate: Unused,
+    C_DecryptVerifyUpdate: Unused,
+    C_GenerateKey: Unused,
+    C_GenerateKeyPair: Unused,
+    C_WrapKey: Unused,
+    C_UnwrapKey: Unused,
+    C_DeriveKey: unsafe extern "C" fn(
+        Handle,
+        *mut Mechanism,
+        Handle,
+        *mut Attribute,
+        c_ulong,
+        *mut Handle,
+    ) -> Rv,
+    C_SeedRandom: Unused,
+    C_GenerateRandom: Unused,
+    C_GetFunctionStatus: Unused,
+    C_CancelFunction: Unused,
+    C_WaitForSlotEvent: Unused,
+}
+
+/// `rv` of `function` as an io::Error unless it is CKR_OK
+fn check(function: &str, rv: Rv) -> io::Result<()> {
+    match rv {
+        CKR_OK => Ok(()),
+        _ => Err(io::Error::other(format!(
+            "PKCS#11 {} failed with CKR 0x{:x}",
+            function, rv
+        ))),
+    }
+}
+
+/// An open session of a token, with the key holding the share
+struct Session {
+    functions: *const FunctionList,
+    session: Handle,
+    key: Handle,
+    /// Whether this session initialized the module, and finalizes it when closed
+    initialized: bool,
+}
+
+/// A share kept on a PKCS#11 token as the value of a Diffie–Hellman private key
+pub struct Pkcs11Share {
+    index: BigUint,
+    prime: BigUint,
+    session: Mutex<Session>,
+    // declared last, so the module is unloaded after the session is closed
+    _library: Library,
+}
+
+// the module is initialized without locking callbacks, every call goes through the Mutex
+unsafe impl Send for Pkcs11Share {}
+unsafe impl Sync for Pkcs11Share {}
+
+impl Pkcs11Share {
+    /// Open the share at `index` kept in the token of `slot` as the DH private key labelled
+    /// `label`, loading the PKCS#11 module at `module` and logging in with `pin`
+    pub fn open<P: AsRef<Path>>(
+        module: P,
+        slot: u64,
+        pin: &str,
+        label: &str,
+        index: BigUint,
+    ) -> io::Result<Pkcs11Share> {
+        // SAFETY: loading a PKCS#11 module runs its initializers, which is what it is for
+        let library = unsafe { Library::new(module.as_ref()) }.map_err(io::Error::other)?;
+        let functions = unsafe {
+            let get: libloading::Symbol<unsafe extern "C" fn(*mut *const FunctionList) -> Rv> =
+                library
+                    .get(b"C_GetFunctionList\0")
+                    .map_err(io::Error::other)?;
+            let mut functions = ptr::null();
+            check("C_GetFunctionList", get(&mut functions))?;
+            functions
+        };
+        if functions.is_null() {
+            return Err(io::Error::other("PKCS#11 module has no function list"));
+        }
+
+        // SAFETY: every call below follows the standard's signatures, with buffers that
+        // outlive the call and lengths that match them
+        unsafe {
+            let f = &*functions;
+            let initialized = match (f.C_Initialize)(ptr::null_mut()) {
+                CKR_CRYPTOKI_ALREADY_INITIALIZED => false,
+                rv => check("C_Initialize", rv).map(|()| true)?,
+            };
+            let mut session = 0;
+            let opened = check(
+                "C_OpenSession",
+                (f.C_OpenSession)(
+                    slot as Handle,
+                    CKF_SERIAL_SESSION | CKF_RW_SESSION,
+                    ptr::null_mut(),
+                    ptr::null_mut(),
+                    &mut session,
+                ),
+            );
+            if let Err(err) = opened {
+                // no Session yet to finalize the module when dropped
+                if initialized {
+                    (f.C_Finalize)(ptr::null_mut());
+                }
+                return Err(err);
+            }
+            let mut session = Session {
+                functions,
+                session,
+                key: 0,
+                initialized,
+            };
+            match (f.C_Login)(
+                session.session,
+                CKU_USER,
+                pin.as_ptr(),
+                pin.len() as c_ulong,
+            ) {
+                CKR_USER_ALREADY_LOGGED_IN => {}
+                rv => check("C_Login", rv)?,
+            }
+            session.key = session.find_key(label)?;
+            let prime = BigUint::from_bytes_be(&session.attribute(session.key, CKA_PRIME)?);
+
+            Ok(Pkcs11Share {
+                index,
+                prime,
+                session: Mutex::new(session),
+                _library: library,
+            })
+        }
+    }
+}
+
+impl Session {
+    /// The DH private key labelled `label`, which must be the only one
+    unsafe fn find_key(&self, label: &str) -> io::Result<Handle> {
+        let f = &*self.functions;
+        let (class, key_type) = (CKO_PRIVATE_KEY, CKK_DH);
+        let mut template = [
+            Attribute::new(CKA_CLASS, &class),
+            Attribute::new(CKA_KEY_TYPE, &key_type),
+            Attribute::bytes(CKA_LABEL, label.as_bytes()),
+        ];
+        check(
+            "C_FindObjectsInit",
+            (f.C_FindObjectsInit)(
+                self.session,
+                template.as_mut_ptr(),
+                template.len() as c_ulong,
+            ),
+        )?;
+        let mut keys = [0; 2];
+        let mut found = 0;
+        let rv = (f.C_FindObjects)(self.session, keys.as_mut_ptr(), 2, &mut found);
+        check("C_FindObjectsFinal", (f.C_FindObjectsFinal)(self.session))?;
+        check("C_FindObjects", rv)?;
+
+        match found {
+            1 => Ok(keys[0]),
+            0 => Err(io::Error::new(
+                io::ErrorKind::NotFound,
+                format!("no DH private key labelled {} on the token", label),
+            )),
+            _ => Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("more than one DH private key labelled {}", label),
+            )),
+        }
+    }
+
+    /// The value of attribute `kind` of `object`
+    unsafe fn attribute(&self, object: Handle, kind: c_ulong) -> io::Result<Vec<u8>> {
+        let f = &*self.functions;
+        let mut template = [Attribute {
+            kind,
+            value: ptr::null_mut(),
+            len: 0,
+        }];
+        check(
+            "C_GetAttributeValue",
+            (f.C_GetAttributeValue)(self.session, object, template.as_mut_ptr(), 1),
+        )?;
+        let mut value = vec![0_u8; template[0].len as usize];
+        template[0].value = value.as_mut_ptr() as *mut c_void;
+        check(
+            "C_GetAttributeValue",
+            (f.C_GetAttributeValue)(self.session, object, template.as_mut_ptr(), 1),
+        )?;
+        value.truncate(template[0].len as usize);
+
+        Ok(value)
+    }
+
+    /// base^x mod p of the key's value x, derived as a session secret key and read back
+    unsafe fn derive(&self, base: &BigUint) -> io::Result<BigUint> {
+        let f = &*self.functions;
+        let mut public = base.to_bytes_be();
+        let mut mechanism = Mechanism {
+            kind: CKM_DH_PKCS_DERIVE,
+            parameter: public.as_mut_ptr() as *mut c_void,
+            len: public.len() as c_ulong,
+        };
+        let (class, key_type, no, yes) = (CKO_SECRET_KEY, CKK_GENERIC_SECRET, 0_u8, 1_u8);
+        let mut template = [
+            Attribute::new(CKA_CLASS, &class),
+            Attribute::new(CKA_KEY_TYPE, &key_type),
+            Attribute::new(CKA_TOKEN, &no),
+            Attribute::new(CKA_SENSITIVE, &no),
+            Attribute::new(CKA_EXTRACTABLE, &yes),
+        ];
+        let mut derived = 0;
+        check(
+            "C_DeriveKey",
+            (f.C_DeriveKey)(
+                self.session,
+                &mut mechanism,
+                self.key,
+                template.as_mut_ptr(),
+                template.len() as c_ulong,
+                &mut derived,
+            ),
+        )?;
+        let value = self.attribute(derived, CKA_VALUE).map(Zeroizing::new);
+        check(
+            "C_DestroyObject",
+            (f.C_DestroyObject)(self.session, derived),
+        )?;
+
+        Ok(BigUint::from_bytes_be(&value?))
+    }
+}
+
+impl Drop for Session {
+    fn drop(&mut self) {
+        // SAFETY: the session was opened from this function list, which is still loaded
+        unsafe {
+            let f = &*self.functions;
+            (f.C_CloseSession)(self.session);
+            if self.initialized {
+                (f.C_Finalize)(ptr::null_mut());
+            }
+        }
+    }
+}
+
+impl ShareBackend for Pkcs11Share {
+    fn index(&self) -> &BigUint {
+        &self.index
+    }
+
+    fn exponentiate(&self, base: &BigUint, p: &BigUint) -> io::Result<BigUint> {
+        if *p != self.prime {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "the token's key is over another prime",
+            ));
+        }
+        let session = self
+            .session
+            .lock()
+            .map_err(|_| io::Error::other("PKCS#11 session poisoned"))?;
+
+        // SAFETY: the session and key stay open while the lock is held
+        unsafe { session.derive(base) }
+    }
+}
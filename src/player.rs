@@ -1,103 +1,2513 @@
-use std::collections::HashMap;
-use std::sync::mpsc;
-use std::sync::mpsc::{Receiver, Sender};
-use std::thread::{self, JoinHandle};
-
-use num::ToPrimitive;
-
-use crate::rpc::{Share, ShareInfo, RPC};
-use crate::vss;
-
-pub struct Player {
-    id: usize,
-    rx: Receiver<RPC>,
-    senders: HashMap<usize, Sender<RPC>>,
-    share_info: Option<ShareInfo>,
-}
-
-impl Player {
-    pub fn new(id: usize) -> (Sender<RPC>, JoinHandle<()>) {
-        let (tx, rx) = mpsc::channel::<RPC>();
-        let mut player = Player {
-            id,
-            rx,
-            senders: HashMap::new(),
-            share_info: None,
-        };
-        let handler = thread::spawn(move || {
-            player.start();
-        });
-
-        (tx, handler)
-    }
-
-    fn start(&mut self) {
-        let mut reconstruct_send: Option<Sender<usize>> = None;
-        let mut senders_shares: HashMap<usize, Share> = HashMap::new();
-        
-        while let Ok(rpc) = self.rx.recv() {
-            match rpc {
-                RPC::Ping(other_id) => println!("{} Pong to {}", self.id, other_id),
-                RPC::RegSender(other_id, sender) => {
-                    println!("{} RegSender {}", self.id, other_id);
-                    self.senders.insert(other_id, sender);
-                }
-                RPC::RegShare(share_info) => {
-                    println!("{} RegShare", self.id);
-                    let (share, g, c, p, _, _) = &share_info;
-                    let is_verified = vss::verify_share(&share.0, &share.1, g, c, p);
-                    if !is_verified {
-                        println!("{} received an invalid share", self.id);
-                        return;
-                    }
-
-                    self.share_info = Some(share_info);
-                }
-                RPC::ReconstructShare(other_id, other_share) => {
-                    println!("{} ReconstructShare {}", self.id, other_id);
-                    if let Some((_, g, c, p, q, t)) = &self.share_info {
-                        let is_verified =
-                            vss::verify_share(&other_share.0, &other_share.1, g, c, p);
-
-                        if !is_verified {
-                            println!("{} received an invalid share for {}", self.id, other_id);
-                            return;
-                        }
-
-                        senders_shares.insert(other_id, other_share);
-
-                        if senders_shares.len() >= *t {
-                            let shares = senders_shares
-                                .values()
-                                .into_iter()
-                                .map(|x| x.clone())
-                                .collect();
-                            let reconstruct_secret = vss::reconstruct(&shares, q);
-
-                            if let Some(s) = reconstruct_send.take() {
-                                s.send(reconstruct_secret.to_usize().unwrap());
-                            }
-
-                            senders_shares.clear();
-                        }
-                    }
-                }
-                RPC::Reconstruct(s) => {
-                    println!("{} Reconstruct", self.id);
-                    if let Some((share, _, _, _, _, _)) = &self.share_info {
-                        reconstruct_send = Some(s);
-                        self.broadcast(RPC::ReconstructShare(self.id, share.clone()));
-                    }
-                }
-            }
-        }
-    }
-
-    fn broadcast(&self, rpc: RPC) {
-        self.senders.iter().for_each(|(to, s)| {
-            if let Err(res) = s.send(rpc.clone()) {
-                println!("{} error while broadcasting {}: {}", self.id, to, res);
-            }
-        });
-    }
-}
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::sync::mpsc;
+use std::sync::mpsc::{Receiver, RecvTimeoutError, Sender};
+use std::sync::Arc;
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+use num_bigint::BigUint;
+use rand::rngs::StdRng;
+use rand::{CryptoRng, FromEntropy, Rng, RngCore, SeedableRng};
+use tracing::{debug, info, info_span, warn};
+
+use crate::audit::{AuditLog, Event};
+use crate::backend::{self, InMemory, ShareBackend};
+use crate::broadcast::{Broadcaster, Step};
+use crate::dealer::DEALER_ID;
+use crate::dkg::{Dkg, DkgConfig, DkgMessage, Outbox};
+use crate::error::VssError;
+use crate::protocol::{Control, DealerToPlayer, Message, Phase, PlayerToPlayer};
+use crate::recovery::Recovery;
+use crate::refresh::{Deal, Refresh, Renewal};
+use crate::rpc::{
+    negotiate, BatchReconstructionReport, DkgReport, Nack, Payload, ReconstructionReport,
+    RefreshReport, RejectReason, Reply, Share, ShareInfo, SignatureReport, StatusReport, Version,
+    WireMessage, NO_SESSION, PROTOCOL_VERSION, RPC, SUPPORTED_VERSIONS,
+};
+use crate::secret::Secret;
+use crate::sign::{Signature, Signing};
+use crate::transport::Transport;
+use crate::vss;
+use crate::worker::WorkerPool;
+
+/// How often the message loop lets its broadcasts retransmit
+const TICK_INTERVAL: Duration = Duration::from_millis(20);
+
+/// How long the message loop waits on its inbox while shares are being verified, before it
+/// looks for their verdicts
+const VERDICT_POLL: Duration = Duration::from_millis(1);
+
+/// How often a Player tells its peers it is alive
+const HEARTBEAT_INTERVAL: Duration = Duration::from_millis(100);
+
+/// How long a peer counts as alive after we last heard from it, three missed heartbeats
+const LIVENESS_TIMEOUT: Duration = Duration::from_millis(300);
+
+/// A piece of a recovery that came before its request: the helper it is from, the helpers
+/// it names and the piece
+type PendingPiece = (usize, Vec<usize>, BigUint);
+
+/// A message carrying a share, the peer it came from and whether the share verified
+type Verdict = (usize, WireMessage, bool);
+
+/// Verifies shares off of the message loop
+///
+/// Verdicts come back over a channel of their own rather than the inbox, so the pool holds no
+/// Sender of the inbox and the Player still stops once every other Sender is dropped
+struct VerifyPool {
+    pool: WorkerPool,
+    verdicts: Sender<Verdict>,
+    rx: Receiver<Verdict>,
+    /// shares handed to the pool whose verdict has not been acted on
+    pending: usize,
+}
+
+/// A Player's state in one dealing
+struct Session {
+    epoch: u64,
+    share_info: ShareInfo,
+    /// where our share is kept for signing, in memory unless `RPC::UseBackend` gave another
+    backend: Arc<dyn ShareBackend>,
+    /// Who waits on a reconstruction and since when
+    reconstruct_send: Option<(Reply<ReconstructionReport>, Instant)>,
+    /// batch reconstruction waiting on this session, by batch id
+    batch: Option<u64>,
+    /// whether we broadcast our share of this epoch
+    revealed: bool,
+    senders_shares: HashMap<usize, Share>,
+    rejected: Vec<(usize, RejectReason)>,
+    nacks: Vec<(usize, Nack)>,
+    /// move to the next epoch in progress
+    refresh: Refresh,
+    refresh_send: Option<Reply<RefreshReport>>,
+}
+
+impl Session {
+    fn new(epoch: u64, share_info: ShareInfo) -> Session {
+        let backend = Arc::new(InMemory::new(share_info.share().clone()));
+
+        Session {
+            epoch,
+            share_info,
+            backend,
+            reconstruct_send: None,
+            batch: None,
+            revealed: false,
+            senders_shares: HashMap::new(),
+            rejected: vec![],
+            nacks: vec![],
+            refresh: Refresh::default(),
+            refresh_send: None,
+        }
+    }
+}
+
+/// Sessions reconstructed together and answered at once
+struct Batch {
+    sessions: Vec<u64>,
+    reply: Reply<BatchReconstructionReport>,
+    asked: Instant,
+    /// sessions that could not join the batch
+    failed: Vec<u64>,
+}
+
+/// A dealing's complaint phase
+#[derive(Default)]
+struct Complaints {
+    /// our share that did not verify, kept with its epoch until the dealer reveals a valid one
+    pending: Option<(u64, ShareInfo)>,
+    /// players that complained about their share
+    accusers: HashSet<usize>,
+    /// players that voted to disqualify the dealer
+    votes: HashSet<usize>,
+}
+
+pub struct Player {
+    id: usize,
+    rx: Receiver<RPC>,
+    transport: Box<dyn Transport>,
+    /// dealings this Player holds a share of, by session id
+    sessions: HashMap<u64, Session>,
+    /// reconstructions of many sessions in progress, by batch id
+    batches: HashMap<u64, Batch>,
+    next_batch: u64,
+    /// peers that proved to misbehave in a session, by session id and peer
+    blacklist: HashSet<(u64, usize)>,
+    /// complaint phase of each dealing someone complained about, by session id
+    complaints: HashMap<u64, Complaints>,
+    /// dealings whose dealer was disqualified
+    disqualified: HashSet<u64>,
+    /// distributed key generations by session id, with whoever waits on the key
+    dkgs: HashMap<u64, (Dkg, Option<Reply<DkgReport>>)>,
+    /// DKG messages that arrived before the DKG of their session was started here
+    dkg_pending: HashMap<u64, Vec<(usize, DkgMessage)>>,
+    /// threshold signatures by session id and request
+    signings: HashMap<(u64, u64), Signing>,
+    /// whoever waits on the signatures we coordinate
+    sign_send: HashMap<(u64, u64), Reply<SignatureReport>>,
+    /// nonce commitments that arrived before the request they belong to
+    nonce_pending: HashMap<(u64, u64), Vec<(usize, BigUint)>>,
+    /// recoveries of lost shares we help with, by session id and lost player
+    recoveries: HashMap<(u64, usize), Recovery>,
+    /// recovery pieces that arrived before the request they belong to
+    recovery_pending: HashMap<(u64, usize), Vec<PendingPiece>>,
+    verifier: Option<VerifyPool>,
+    /// protocol version agreed on with each peer
+    versions: HashMap<usize, Version>,
+    hello_sent: HashSet<usize>,
+    /// when we last heard from each peer
+    last_seen: HashMap<usize, Instant>,
+    last_heartbeat: Option<Instant>,
+    broadcaster: Broadcaster,
+    /// Where protocol events are logged, if anywhere
+    audit: Option<AuditLog>,
+    /// draws the nonces, polynomials and request ids of the protocols we take part in
+    rng: StdRng,
+}
+
+impl Player {
+    /// Spawn a new Player reaching its peers over `transport` on a thread of its own
+    ///
+    /// The returned Sender is the Player's inbox, which the transport delivers messages into
+    pub fn spawn<T: Transport + 'static>(id: usize, transport: T) -> (Sender<RPC>, JoinHandle<()>) {
+        Player::launch(id, Box::new(transport), 0, None, StdRng::from_entropy())
+    }
+
+    /// Return a new Player drawing its randomness from a generator seeded by `rng`, so a seeded
+    /// rng makes it draw the same every time
+    pub fn with_rng<T: Transport + 'static, R: RngCore + CryptoRng>(
+        id: usize,
+        transport: T,
+        rng: &mut R,
+    ) -> (Sender<RPC>, JoinHandle<()>) {
+        let rng = StdRng::from_seed(rng.gen());
+
+        Player::launch(id, Box::new(transport), 0, None, rng)
+    }
+
+    /// Return a new Player that verifies incoming shares on `workers` background threads
+    ///
+    /// The message loop keeps processing while a share is being verified and acts on the
+    /// verdict once it arrives
+    pub fn with_verify_workers<T: Transport + 'static>(
+        id: usize,
+        transport: T,
+        workers: usize,
+    ) -> (Sender<RPC>, JoinHandle<()>) {
+        Player::launch(
+            id,
+            Box::new(transport),
+            workers,
+            None,
+            StdRng::from_entropy(),
+        )
+    }
+
+    /// Return a new Player that logs the shares it verifies, the complaints it hears and the
+    /// secrets it reconstructs to `log`
+    pub fn with_audit<T: Transport + 'static>(
+        id: usize,
+        transport: T,
+        log: AuditLog,
+    ) -> (Sender<RPC>, JoinHandle<()>) {
+        Player::launch(
+            id,
+            Box::new(transport),
+            0,
+            Some(log),
+            StdRng::from_entropy(),
+        )
+    }
+
+    fn launch(
+        id: usize,
+        transport: Box<dyn Transport>,
+        workers: usize,
+        audit: Option<AuditLog>,
+        rng: StdRng,
+    ) -> (Sender<RPC>, JoinHandle<()>) {
+        let (mut player, tx) = Player::build(id, transport, workers, rng);
+        player.audit = audit;
+        let handler = thread::spawn(move || {
+            player.start();
+        });
+
+        (tx, handler)
+    }
+
+    /// A Player and its inbox, for a caller that runs its message loop or drives it with
+    /// `handle` and `tick` itself
+    pub(crate) fn build(
+        id: usize,
+        transport: Box<dyn Transport>,
+        workers: usize,
+        rng: StdRng,
+    ) -> (Player, Sender<RPC>) {
+        let (tx, rx) = mpsc::channel::<RPC>();
+        let verifier = (workers > 0).then(|| {
+            let (verdicts, rx) = mpsc::channel();
+            VerifyPool {
+                pool: WorkerPool::new(workers),
+                verdicts,
+                rx,
+                pending: 0,
+            }
+        });
+        let player = Player {
+            id,
+            rx,
+            transport,
+            sessions: HashMap::new(),
+            batches: HashMap::new(),
+            next_batch: 0,
+            blacklist: HashSet::new(),
+            complaints: HashMap::new(),
+            disqualified: HashSet::new(),
+            dkgs: HashMap::new(),
+            dkg_pending: HashMap::new(),
+            signings: HashMap::new(),
+            sign_send: HashMap::new(),
+            nonce_pending: HashMap::new(),
+            recoveries: HashMap::new(),
+            recovery_pending: HashMap::new(),
+            verifier,
+            versions: HashMap::new(),
+            hello_sent: HashSet::new(),
+            last_seen: HashMap::new(),
+            last_heartbeat: None,
+            broadcaster: Broadcaster::new(id),
+            audit: None,
+            rng,
+        };
+
+        (player, tx)
+    }
+
+    fn start(&mut self) {
+        let span = info_span!("player", id = self.id);
+        let _enter = span.enter();
+
+        loop {
+            if self
+                .last_heartbeat
+                .is_none_or(|sent| sent.elapsed() >= HEARTBEAT_INTERVAL)
+            {
+                self.last_heartbeat = Some(Instant::now());
+                self.broadcast(NO_SESSION, 0, Payload::Heartbeat);
+            }
+
+            let timeout = match &self.verifier {
+                Some(verifier) if verifier.pending > 0 => VERDICT_POLL,
+                _ => TICK_INTERVAL,
+            };
+            let running = match self.rx.recv_timeout(timeout) {
+                Ok(rpc) => self.handle(rpc),
+                Err(RecvTimeoutError::Timeout) => {
+                    self.tick();
+                    true
+                }
+                Err(RecvTimeoutError::Disconnected) => return,
+            };
+
+            if !running {
+                return;
+            }
+            self.take_verdicts();
+        }
+    }
+
+    /// Acts on the verdicts the worker pool has sent so far
+    fn take_verdicts(&mut self) {
+        let verdicts: Vec<Verdict> = match &mut self.verifier {
+            Some(verifier) => {
+                let verdicts: Vec<Verdict> = verifier.rx.try_iter().collect();
+                verifier.pending -= verdicts.len();
+
+                verdicts
+            }
+            None => return,
+        };
+
+        for (from, msg, is_verified) in verdicts {
+            let span = info_span!("session", session = msg.session_id, epoch = msg.epoch);
+            let _enter = span.enter();
+
+            self.on_verified(from, msg, is_verified);
+        }
+    }
+
+    /// Lets the broadcasts in progress retransmit, as the message loop does when idle
+    pub(crate) fn tick(&mut self) {
+        let step = self.broadcaster.tick();
+        self.apply(step);
+    }
+
+    /// Handles a message of the inbox, returns false if the Player should stop
+    pub(crate) fn handle(&mut self, rpc: RPC) -> bool {
+        match rpc {
+            RPC::Wire(from, msg) => {
+                let span = info_span!("session", session = msg.session_id, epoch = msg.epoch);
+                let _enter = span.enter();
+
+                self.last_seen.insert(from, Instant::now());
+                #[cfg(feature = "metrics")]
+                crate::metrics::message(&msg.payload);
+                self.on_message(from, msg)
+            }
+            RPC::Reconstruct(session_id, s) => {
+                let span = info_span!("session", session = session_id);
+                let _enter = span.enter();
+
+                info!("Reconstruct");
+                match self.sessions.get_mut(&session_id) {
+                    Some(session) if !session.share_info.validity().is_current() => {
+                        warn!("our share is outside its validity window, not reconstructing");
+                    }
+                    Some(session) => {
+                        session.reconstruct_send = Some((s, Instant::now()));
+                        // our own share counts towards the threshold
+                        let share = session.share_info.share().clone();
+                        session.senders_shares.insert(self.id, share);
+                        self.request_shares(session_id);
+                        self.reveal(session_id);
+                        self.try_reconstruct(session_id);
+                    }
+                    None => warn!("no share held for session"),
+                }
+
+                true
+            }
+            RPC::ReconstructMany(session_ids, s) => {
+                info!(sessions = session_ids.len(), "ReconstructMany");
+                self.reconstruct_many(session_ids, s);
+
+                true
+            }
+            RPC::LivePeers(s) => {
+                answer(s, self.live_peers());
+
+                true
+            }
+            RPC::Status(s) => {
+                let mut sessions: Vec<(u64, u64)> = self
+                    .sessions
+                    .iter()
+                    .map(|(session_id, session)| (*session_id, session.epoch))
+                    .collect();
+                sessions.sort();
+                answer(
+                    s,
+                    StatusReport {
+                        id: self.id,
+                        sessions,
+                        live_peers: self.live_peers(),
+                    },
+                );
+
+                true
+            }
+            RPC::Refresh(session_id, renewal, s) => {
+                let span = info_span!("session", session = session_id);
+                let _enter = span.enter();
+
+                info!(?renewal, "Refresh");
+                self.renew(session_id, renewal, Some(s));
+
+                true
+            }
+            RPC::Dkg(session_id, config, s) => {
+                let span = info_span!("session", session = session_id);
+                let _enter = span.enter();
+
+                info!("Dkg");
+                self.start_dkg(session_id, config, Some(s));
+
+                true
+            }
+            RPC::Sign(session_id, message, signers, s) => {
+                let span = info_span!("session", session = session_id);
+                let _enter = span.enter();
+
+                info!(?signers, "Sign");
+                self.request_signature(session_id, message, signers, s);
+
+                true
+            }
+            RPC::UseBackend(session_id, backend, s) => {
+                let span = info_span!("session", session = session_id);
+                let _enter = span.enter();
+
+                info!("UseBackend");
+                answer(s, self.use_backend(session_id, backend));
+
+                true
+            }
+        }
+    }
+
+    /// Keeps our share of `session_id` behind `backend` if it holds the share we were dealt,
+    /// checked against the dealing's commitments through the backend itself
+    fn use_backend(&mut self, session_id: u64, backend: Arc<dyn ShareBackend>) -> bool {
+        let session = match self.sessions.get_mut(&session_id) {
+            Some(session) => session,
+            None => {
+                warn!("no share held for session");
+                return false;
+            }
+        };
+        if backend.index() != session.share_info.share().index() {
+            warn!("backend holds the share of another index");
+            return false;
+        }
+
+        match backend::verify(&*backend, session.share_info.params()) {
+            Ok(true) => {
+                session.backend = backend;
+                true
+            }
+            Ok(false) => {
+                warn!("backend does not hold our share of the dealing");
+                false
+            }
+            Err(err) => {
+                warn!(%err, "backend could not be checked");
+                false
+            }
+        }
+    }
+
+    /// Handles a protocol message from `from`, returns false if the Player should stop
+    fn on_message(&mut self, from: usize, msg: WireMessage) -> bool {
+        if !msg.version.is_supported() {
+            warn!(from, version = ?msg.version, "unsupported protocol version");
+            self.reject(
+                msg.session_id,
+                from,
+                RejectReason::UnsupportedVersion(msg.version),
+            );
+            return true;
+        }
+
+        let WireMessage {
+            version,
+            session_id,
+            epoch,
+            payload,
+        } = msg;
+        let msg = match Message::route(from, payload) {
+            Ok(msg) => msg,
+            Err(payload) => {
+                warn!(from, ?payload, "ignoring message the sender may not send");
+                return true;
+            }
+        };
+
+        let phase = self.phase(session_id);
+        let admitted = match &msg {
+            Message::Dealer(msg) => phase.admit_dealer(msg, epoch),
+            Message::Player(msg) => phase.admit_player(msg, epoch),
+            Message::Control(_) => Ok(()),
+        };
+        if let Err(reason) = admitted {
+            debug!(from, ?phase, ?reason, "ignoring message out of phase");
+            if let (Some(nack), Message::Player(msg)) = (reason.nack(), &msg) {
+                let epoch = msg.share_epoch(epoch).unwrap_or(epoch);
+                self.nack(from, session_id, epoch, nack);
+            }
+            return true;
+        }
+
+        match msg {
+            Message::Control(msg) => self.on_control(from, version, session_id, epoch, msg),
+            Message::Dealer(msg) => self.on_dealer_message(from, version, session_id, epoch, msg),
+            Message::Player(msg) => self.on_player_message(from, version, session_id, epoch, msg),
+        }
+    }
+
+    /// Where we stand in the dealing of a session
+    fn phase(&self, session_id: u64) -> Phase {
+        if self.disqualified.contains(&session_id) {
+            return Phase::Disqualified;
+        }
+
+        match self.sessions.get(&session_id) {
+            Some(session) => Phase::Holding(session.epoch),
+            None => Phase::Registering,
+        }
+    }
+
+    /// Handles a message about the link to `from`, returns false if the Player should stop
+    fn on_control(
+        &mut self,
+        from: usize,
+        version: Version,
+        session_id: u64,
+        epoch: u64,
+        msg: Control,
+    ) -> bool {
+        match msg {
+            Control::Ping => info!(from, "Pong"),
+            Control::Heartbeat => {}
+            Control::Batch(msgs) => return self.on_batch(from, msgs),
+            Control::Hello(supported) => self.on_hello(from, &supported),
+            Control::Broadcast(broadcast) => {
+                let payload = Payload::Broadcast(broadcast);
+                let msg = WireMessage::with_version(version, session_id, epoch, payload);
+                let step = self
+                    .broadcaster
+                    .on_message(&self.transport.peers(), from, msg);
+
+                return self.apply(step);
+            }
+            Control::Nack(nack) => {
+                warn!(from, ?nack, "peer could not use our share");
+                if let Some(session) = self.sessions.get_mut(&session_id) {
+                    session.nacks.push((from, nack));
+                }
+            }
+            Control::Sequenced(..) | Control::Ack(_) => {
+                debug!(
+                    from,
+                    "ignoring retry message, the transport should handle it"
+                );
+            }
+        }
+
+        true
+    }
+
+    /// Handles a message of the dealer admitted in our phase of its dealing, returns false if
+    /// the Player should stop
+    fn on_dealer_message(
+        &mut self,
+        from: usize,
+        version: Version,
+        session_id: u64,
+        epoch: u64,
+        msg: DealerToPlayer,
+    ) -> bool {
+        match msg {
+            DealerToPlayer::ComplaintResponse(revealed) => {
+                self.on_complaint_response(session_id, epoch, revealed)
+            }
+            DealerToPlayer::RefreshRequest => {
+                info!("dealer requested a refresh");
+                self.renew(session_id, Renewal::Refresh, None);
+            }
+            DealerToPlayer::DkgRequest(config) => {
+                info!("dealer requested a key generation");
+                self.start_dkg(session_id, config, None);
+            }
+            DealerToPlayer::RegShare(share_info) => {
+                debug!("RegShare");
+                if let Err(err) = share_info.params().validate() {
+                    warn!(%err, "ignoring share dealt under invalid parameters");
+                    return true;
+                }
+                let verifier = share_info.clone();
+                let payload = Payload::RegShare(share_info);
+                let msg = WireMessage::with_version(version, session_id, epoch, payload);
+
+                return self.verify(from, msg, verifier);
+            }
+        }
+
+        true
+    }
+
+    /// Handles a message of a player admitted in our phase of its dealing, returns false if the
+    /// Player should stop
+    fn on_player_message(
+        &mut self,
+        from: usize,
+        version: Version,
+        session_id: u64,
+        epoch: u64,
+        msg: PlayerToPlayer,
+    ) -> bool {
+        match msg {
+            PlayerToPlayer::ShareRequest => match self.sessions.get(&session_id) {
+                Some(session) if !session.share_info.validity().is_current() => {
+                    warn!(from, "not revealing a share outside its validity window");
+                    self.nack(from, session_id, epoch, Nack::Expired);
+                }
+                _ => {
+                    debug!(from, "revealing share on request");
+                    self.reveal(session_id);
+                }
+            },
+            PlayerToPlayer::RefreshDeal {
+                epoch: next,
+                sub_share,
+                commitments,
+            } => {
+                let deal = Deal {
+                    sub_share,
+                    commitments,
+                };
+                self.on_deal(from, session_id, next, Renewal::Refresh, deal);
+            }
+            PlayerToPlayer::ReshareSubShare {
+                epoch: next,
+                sub_share,
+                commitments,
+                t,
+            } => {
+                let deal = Deal {
+                    sub_share,
+                    commitments,
+                };
+                self.on_deal(from, session_id, next, Renewal::Reshare(t), deal);
+            }
+            PlayerToPlayer::RefreshAck {
+                epoch: next,
+                dealer,
+            } => self.on_refresh_ack(from, session_id, next, dealer),
+            PlayerToPlayer::EpochCommit {
+                epoch: next,
+                dealers,
+            } => info!(from, epoch = next, ?dealers, "peer moved to a new epoch"),
+            PlayerToPlayer::Complaint { accuser, .. } if accuser != from => {
+                warn!(
+                    from,
+                    accuser, "ignoring complaint on behalf of another player"
+                );
+            }
+            PlayerToPlayer::Complaint { against, .. } if against != DEALER_ID => {
+                debug!(from, against, "ignoring complaint against a player");
+            }
+            PlayerToPlayer::Complaint { evidence, .. } => {
+                self.on_complaint(from, session_id, epoch, evidence)
+            }
+            PlayerToPlayer::Disqualify(against) if against != DEALER_ID => {
+                debug!(from, against, "ignoring vote against a player");
+            }
+            PlayerToPlayer::Disqualify(_) => self.on_disqualify(from, session_id),
+            PlayerToPlayer::Dkg(msg) => match self.dkgs.get_mut(&session_id) {
+                Some((dkg, _)) => {
+                    let outbox = dkg.on_message(from, msg);
+                    self.send_dkg(session_id, outbox);
+                    self.finish_dkg(session_id);
+                }
+                None => self
+                    .dkg_pending
+                    .entry(session_id)
+                    .or_default()
+                    .push((from, msg)),
+            },
+            PlayerToPlayer::SignRequest {
+                request,
+                message,
+                signers,
+            } => self.on_sign_request(from, session_id, epoch, request, message, signers),
+            PlayerToPlayer::NonceCommitment {
+                request,
+                commitment,
+            } => self.on_nonce_commitment(from, session_id, epoch, request, commitment),
+            PlayerToPlayer::PartialSignature { request, z } => {
+                self.on_partial_signature(from, session_id, epoch, request, z)
+            }
+            PlayerToPlayer::SignatureResult { request, signature } => {
+                self.on_signature_result(from, session_id, request, signature)
+            }
+            PlayerToPlayer::RefreshResult { .. } => {
+                debug!(from, "ignoring refresh result meant for the dealer")
+            }
+            PlayerToPlayer::RecoveryRequest { helpers } => {
+                self.on_recovery_request(from, session_id, helpers)
+            }
+            PlayerToPlayer::RecoveryPiece {
+                lost,
+                helpers,
+                piece,
+            } => self.on_recovery_piece(from, session_id, lost, helpers, piece),
+            PlayerToPlayer::RecoveryShare(_) => {
+                debug!(from, "ignoring recovered share meant for the lost player")
+            }
+            PlayerToPlayer::DkgResult { .. } => {
+                debug!(from, "ignoring key generation result meant for the dealer")
+            }
+            PlayerToPlayer::ReconstructShare(_) if self.blacklist.contains(&(session_id, from)) => {
+                debug!(from, "ignoring blacklisted peer");
+                self.reject(session_id, from, RejectReason::Blacklisted);
+            }
+            PlayerToPlayer::ReconstructShare(other_share) => {
+                debug!(from, "ReconstructShare");
+                if *other_share.index() != BigUint::from(from) {
+                    // a valid share replayed under another id would corrupt reconstruction
+                    let index = other_share.index().clone();
+                    warn!(from, %index, "received share for another index");
+                    self.reject(session_id, from, RejectReason::IndexMismatch(index));
+                    self.nack(from, session_id, epoch, Nack::InvalidShare);
+                    return true;
+                }
+
+                if let Some(session) = self.sessions.get(&session_id) {
+                    let verifier = session.share_info.clone();
+                    let payload = Payload::ReconstructShare(other_share);
+                    let msg = WireMessage::with_version(version, session_id, epoch, payload);
+
+                    return self.verify(from, msg, verifier);
+                }
+            }
+        }
+
+        true
+    }
+
+    /// Handles the messages of a batch in order, dropping every share of the batch if any of
+    /// them does not verify
+    fn on_batch(&mut self, from: usize, msgs: Vec<WireMessage>) -> bool {
+        let (msgs, nested): (Vec<WireMessage>, Vec<WireMessage>) = msgs
+            .into_iter()
+            .partition(|msg| !matches!(msg.payload, Payload::Batch(_)));
+        if !nested.is_empty() {
+            warn!(from, "ignoring batches nested in a batch");
+        }
+
+        let is_invalid = |msg: &WireMessage| match &msg.payload {
+            Payload::RegShare(share_info) => !share_info.verify(),
+            _ => false,
+        };
+        let msgs: Vec<WireMessage> = if msgs.iter().any(is_invalid) {
+            // complain about the invalid shares, the valid ones are dropped with them
+            warn!(from, "batch holds an invalid share, dropping its shares");
+            msgs.into_iter()
+                .filter(|msg| !matches!(msg.payload, Payload::RegShare(_)) || is_invalid(msg))
+                .collect()
+        } else {
+            msgs
+        };
+
+        let mut running = true;
+        for msg in msgs {
+            let span = info_span!("session", session = msg.session_id, epoch = msg.epoch);
+            let _enter = span.enter();
+
+            running &= self.on_message(from, msg);
+        }
+
+        running
+    }
+
+    /// Verifies the share carried by `msg` against the commitments in `verifier` inline, or
+    /// hands it to the worker pool when one is configured and acts on the verdict once it
+    /// arrives
+    fn verify(&mut self, from: usize, msg: WireMessage, verifier: ShareInfo) -> bool {
+        match &mut self.verifier {
+            Some(pool) => {
+                let verdicts = pool.verdicts.clone();
+                pool.pending += 1;
+                pool.pool.execute(move || {
+                    let is_verified = Player::verify_message(&msg, &verifier);
+                    if verdicts.send((from, msg, is_verified)).is_err() {
+                        debug!("player stopped before a verdict arrived");
+                    }
+                });
+            }
+            None => {
+                let is_verified = Player::verify_message(&msg, &verifier);
+                self.on_verified(from, msg, is_verified);
+            }
+        }
+
+        true
+    }
+
+    fn verify_message(msg: &WireMessage, verifier: &ShareInfo) -> bool {
+        match &msg.payload {
+            Payload::RegShare(share_info) => share_info.verify(),
+            Payload::ReconstructShare(share) => verifier.verify_share(share),
+            Payload::Ping
+            | Payload::Heartbeat
+            | Payload::ShareRequest
+            | Payload::Batch(_)
+            | Payload::Hello(_)
+            | Payload::Broadcast(_)
+            | Payload::Sequenced(..)
+            | Payload::Ack(_)
+            | Payload::Nack(_)
+            | Payload::RefreshDeal { .. }
+            | Payload::RefreshAck { .. }
+            | Payload::ReshareSubShare { .. }
+            | Payload::EpochCommit { .. }
+            | Payload::Complaint { .. }
+            | Payload::ComplaintResponse(_)
+            | Payload::Disqualify(_)
+            | Payload::Dkg(_)
+            | Payload::SignRequest { .. }
+            | Payload::NonceCommitment { .. }
+            | Payload::PartialSignature { .. }
+            | Payload::SignatureResult { .. }
+            | Payload::RefreshRequest
+            | Payload::RefreshResult { .. }
+            | Payload::RecoveryRequest { .. }
+            | Payload::RecoveryPiece { .. }
+            | Payload::RecoveryShare(_)
+            | Payload::DkgRequest(_)
+            | Payload::DkgResult { .. } => true,
+        }
+    }
+
+    /// Acts on a share once its verification verdict is known, inline or as it comes in over
+    /// the worker pool's verdict channel
+    ///
+    /// A peer that reveals an invalid share is rejected and blacklisted for the session, an
+    /// invalid share from the dealer is complained about to every player. No verdict stops the
+    /// Player, it stops once its inbox disconnects
+    fn on_verified(&mut self, from: usize, msg: WireMessage, is_verified: bool) {
+        let (session_id, epoch) = (msg.session_id, msg.epoch);
+        if matches!(
+            msg.payload,
+            Payload::RegShare(_) | Payload::ReconstructShare(_)
+        ) {
+            let valid = is_verified;
+            #[cfg(feature = "metrics")]
+            crate::metrics::share_verified(valid);
+            self.audit(session_id, Event::Verified { from, valid });
+        }
+        match msg.payload {
+            Payload::RegShare(share_info) if !is_verified => {
+                warn!("received an invalid share, complaining");
+                let evidence = share_info.share().clone();
+                self.complaints.entry(session_id).or_default().pending = Some((epoch, share_info));
+
+                let complaint = Payload::Complaint {
+                    accuser: self.id,
+                    against: from,
+                    evidence: evidence.clone(),
+                };
+                self.broadcast(session_id, epoch, complaint);
+                self.on_complaint(self.id, session_id, epoch, evidence);
+            }
+            Payload::RegShare(share_info) if share_info.validity().epoch != epoch => {
+                warn!(
+                    bound = share_info.validity().epoch,
+                    "ignoring share bound to another epoch"
+                );
+            }
+            Payload::RegShare(share_info) => {
+                // a newer epoch replaces the share and drops any reconstruction in progress
+                self.sessions
+                    .insert(session_id, Session::new(epoch, share_info));
+            }
+            Payload::ReconstructShare(_) if !is_verified => {
+                warn!(from, "received an invalid share");
+                self.reject(session_id, from, RejectReason::InvalidShare);
+                self.nack(from, session_id, epoch, Nack::InvalidShare);
+            }
+            Payload::ReconstructShare(_) if self.blacklist.contains(&(session_id, from)) => {}
+            Payload::ReconstructShare(other_share) => match self.sessions.get_mut(&session_id) {
+                Some(session) if session.epoch == epoch => {
+                    session.senders_shares.insert(from, other_share);
+                    self.try_reconstruct(session_id);
+                }
+                // the session moved to another epoch while the share was being verified
+                _ => {}
+            },
+            Payload::Ping
+            | Payload::Heartbeat
+            | Payload::ShareRequest
+            | Payload::Batch(_)
+            | Payload::Hello(_)
+            | Payload::Broadcast(_)
+            | Payload::Sequenced(..)
+            | Payload::Ack(_)
+            | Payload::Nack(_)
+            | Payload::RefreshDeal { .. }
+            | Payload::RefreshAck { .. }
+            | Payload::ReshareSubShare { .. }
+            | Payload::EpochCommit { .. }
+            | Payload::Complaint { .. }
+            | Payload::ComplaintResponse(_)
+            | Payload::Disqualify(_)
+            | Payload::Dkg(_)
+            | Payload::SignRequest { .. }
+            | Payload::NonceCommitment { .. }
+            | Payload::PartialSignature { .. }
+            | Payload::SignatureResult { .. }
+            | Payload::RefreshRequest
+            | Payload::RefreshResult { .. }
+            | Payload::RecoveryRequest { .. }
+            | Payload::RecoveryPiece { .. }
+            | Payload::RecoveryShare(_)
+            | Payload::DkgRequest(_)
+            | Payload::DkgResult { .. } => {}
+        }
+    }
+
+    /// Asks for the shares of every session in `session_ids` at once, to interpolate them
+    /// together and answer `s` once all of them arrived
+    fn reconstruct_many(&mut self, session_ids: Vec<u64>, s: Reply<BatchReconstructionReport>) {
+        let batch_id = self.next_batch;
+        self.next_batch += 1;
+
+        let (mut sessions, mut failed) = (vec![], vec![]);
+        for session_id in session_ids {
+            let span = info_span!("session", session = session_id);
+            let _enter = span.enter();
+
+            match self.sessions.get_mut(&session_id) {
+                Some(session) if !session.share_info.validity().is_current() => {
+                    warn!("our share is outside its validity window, not reconstructing");
+                    failed.push(session_id);
+                }
+                Some(session) if session.batch.is_none() => {
+                    session.batch = Some(batch_id);
+                    let share = session.share_info.share().clone();
+                    session.senders_shares.insert(self.id, share);
+                    sessions.push(session_id);
+                }
+                Some(_) if sessions.contains(&session_id) => {}
+                Some(_) => {
+                    warn!("session is already being reconstructed in another batch");
+                    failed.push(session_id);
+                }
+                None => {
+                    warn!("no share held for session");
+                    failed.push(session_id);
+                }
+            }
+        }
+        for &session_id in &sessions {
+            self.request_shares(session_id);
+            self.reveal(session_id);
+        }
+
+        let asked = Instant::now();
+        let batch = Batch {
+            sessions,
+            reply: s,
+            asked,
+            failed,
+        };
+        self.batches.insert(batch_id, batch);
+        self.try_reconstruct_batch(batch_id);
+    }
+
+    /// Interpolates the secrets of a batch together once each of its sessions has t shares,
+    /// so sessions of the same contributors share their Lagrange coefficients
+    fn try_reconstruct_batch(&mut self, batch_id: u64) {
+        // a session dropped or replaced by a newer epoch no longer waits on the batch
+        let waits = |session: &Session| session.batch == Some(batch_id);
+        let ready = match self.batches.get(&batch_id) {
+            Some(batch) => batch.sessions.iter().all(|session_id| {
+                self.sessions.get(session_id).is_none_or(|session| {
+                    !waits(session) || session.senders_shares.len() >= session.share_info.t()
+                })
+            }),
+            None => return,
+        };
+        if !ready {
+            return;
+        }
+        let Batch {
+            sessions,
+            reply,
+            asked,
+            mut failed,
+        } = self.batches.remove(&batch_id).expect("checked above");
+
+        let mut sets = vec![];
+        for session_id in sessions {
+            match self.sessions.get_mut(&session_id) {
+                Some(session) if waits(session) && session.share_info.validity().is_current() => {
+                    session.batch = None;
+                    let shares: Vec<Share> = session.senders_shares.values().cloned().collect();
+                    sets.push((session_id, shares, session.share_info.q().clone()));
+                }
+                Some(session) if waits(session) => {
+                    warn!(
+                        session = session_id,
+                        "our share is outside its validity window, not reconstructing"
+                    );
+                    session.batch = None;
+                    session.senders_shares.clear();
+                    failed.push(session_id);
+                }
+                _ => failed.push(session_id),
+            }
+        }
+        let secrets = vss::reconstruct_many(
+            &sets
+                .iter()
+                .map(|(_, shares, q)| (&shares[..], q))
+                .collect::<Vec<_>>(),
+        );
+
+        let mut reports = BTreeMap::new();
+        for ((session_id, ..), secret) in sets.into_iter().zip(secrets) {
+            let span = info_span!("session", session = session_id);
+            let _enter = span.enter();
+
+            let report = match self.report(session_id, secret, asked) {
+                Some(report) => report,
+                None => {
+                    failed.push(session_id);
+                    continue;
+                }
+            };
+            // someone may also wait on this session alone
+            if let Some((s, _)) = self
+                .sessions
+                .get_mut(&session_id)
+                .and_then(|session| session.reconstruct_send.take())
+            {
+                answer(s, report.clone());
+            }
+            reports.insert(session_id, report);
+        }
+        failed.sort();
+
+        info!(
+            reconstructed = reports.len(),
+            ?failed,
+            "reconstructed batch"
+        );
+        answer(reply, BatchReconstructionReport { reports, failed });
+    }
+
+    /// Interpolates the secret of a session once t shares arrived and someone waits on it
+    fn try_reconstruct(&mut self, session_id: u64) {
+        let session = match self.sessions.get_mut(&session_id) {
+            Some(session) => session,
+            None => return,
+        };
+        if let Some(batch_id) = session.batch {
+            return self.try_reconstruct_batch(batch_id);
+        }
+        if session.senders_shares.len() < session.share_info.t() {
+            return;
+        }
+        let (s, asked) = match session.reconstruct_send.take() {
+            Some(waiting) => waiting,
+            None => return,
+        };
+        if !session.share_info.validity().is_current() {
+            // the window closed while the shares were arriving
+            warn!("our share is outside its validity window, not reconstructing");
+            return session.senders_shares.clear();
+        }
+
+        let shares: Vec<Share> = session.senders_shares.values().cloned().collect();
+        let secret = vss::reconstruct(&shares, session.share_info.q());
+        if let Some(report) = self.report(session_id, secret, asked) {
+            answer(s, report);
+        }
+    }
+
+    /// The report of a session's reconstruction to `secret` from the shares it holds, which
+    /// it forgets, None if they did not interpolate. Either way the outcome is audited
+    fn report(
+        &mut self,
+        session_id: u64,
+        secret: Result<Secret, VssError>,
+        asked: Instant,
+    ) -> Option<ReconstructionReport> {
+        let session = self.sessions.get_mut(&session_id)?;
+        let mut contributors: Vec<usize> = session.senders_shares.keys().copied().collect();
+        contributors.sort();
+        let secret = match secret {
+            Ok(secret) => secret,
+            Err(err) => {
+                warn!(%err, "could not reconstruct");
+                session.senders_shares.clear();
+                let ok = false;
+                self.audit(session_id, Event::Reconstructed { contributors, ok });
+                return None;
+            }
+        };
+        let verified = session.share_info.verify_secret(&secret);
+        if !verified {
+            warn!(
+                ?contributors,
+                "reconstructed secret does not match the commitment c_0"
+            );
+        }
+        let report = ReconstructionReport {
+            secret,
+            contributors: contributors.clone(),
+            rejected: session.rejected.drain(..).collect(),
+            nacks: session.nacks.drain(..).collect(),
+            verified,
+        };
+        session.senders_shares.clear();
+
+        debug!(elapsed = ?asked.elapsed(), "reconstructed");
+        #[cfg(feature = "metrics")]
+        crate::metrics::reconstructed(asked.elapsed());
+        // logged before answering, so whoever asked finds it in the log
+        let ok = true;
+        self.audit(session_id, Event::Reconstructed { contributors, ok });
+
+        Some(report)
+    }
+
+    /// Logs `event` of a session to the audit log, if there is one
+    fn audit(&mut self, session_id: u64, event: Event) {
+        if let Some(log) = &mut self.audit {
+            if let Err(err) = log.append(session_id, event) {
+                warn!(%err, "could not write to the audit log");
+            }
+        }
+    }
+
+    /// Broadcasts our share of a session, once per epoch
+    fn reveal(&mut self, session_id: u64) {
+        let (epoch, share) = match self.sessions.get_mut(&session_id) {
+            Some(session) if !session.revealed => {
+                session.revealed = true;
+                (session.epoch, session.share_info.share().clone())
+            }
+            _ => return,
+        };
+
+        self.broadcast(session_id, epoch, Payload::ReconstructShare(share));
+    }
+
+    /// Asks the players we heard from recently to reveal their shares of a session, and every
+    /// other player too when too few of them are alive to reach the threshold
+    fn request_shares(&mut self, session_id: u64) {
+        let (epoch, t) = match self.sessions.get(&session_id) {
+            Some(session) => (session.epoch, session.share_info.t()),
+            None => return,
+        };
+        let live = self.live_peers();
+        let (mut asked, rest): (Vec<usize>, Vec<usize>) = self
+            .players()
+            .into_iter()
+            .filter(|id| *id != self.id)
+            .partition(|id| live.contains(id));
+        if asked.len() + 1 < t {
+            debug!(
+                live = asked.len(),
+                "too few live players, asking every player"
+            );
+            asked.extend(rest);
+        }
+
+        for to in asked {
+            self.send_to(to, session_id, epoch, Payload::ShareRequest);
+        }
+    }
+
+    /// Ids of the players we heard from within LIVENESS_TIMEOUT, the dealer sends no
+    /// heartbeats and is left out
+    fn live_peers(&self) -> Vec<usize> {
+        let mut live: Vec<usize> = self
+            .last_seen
+            .iter()
+            .filter(|(id, seen)| **id != DEALER_ID && seen.elapsed() < LIVENESS_TIMEOUT)
+            .map(|(id, _)| *id)
+            .collect();
+        live.sort();
+
+        live
+    }
+
+    /// The dealing parameters of a session, from our share or from the share we complained about
+    fn dealing(&self, session_id: u64) -> Option<&ShareInfo> {
+        self.sessions
+            .get(&session_id)
+            .map(|session| &session.share_info)
+            .or_else(|| {
+                self.complaints
+                    .get(&session_id)
+                    .and_then(|complaints| complaints.pending.as_ref())
+                    .map(|(_, share_info)| share_info)
+            })
+    }
+
+    /// Counts the complaint of `accuser` about the share `evidence` it received from the dealer
+    ///
+    /// A complaint whose evidence verifies is bogus. Once t players complained the dealer would
+    /// have to reveal enough shares to give the secret away, so we vote to disqualify it
+    fn on_complaint(&mut self, accuser: usize, session_id: u64, epoch: u64, evidence: Share) {
+        if self.disqualified.contains(&session_id) {
+            return;
+        }
+        let t = match self.dealing(session_id) {
+            Some(share_info) if share_info.verify_share(&evidence) => {
+                return warn!(accuser, "ignoring complaint about a valid share");
+            }
+            Some(share_info) => Some(share_info.t()),
+            None => None,
+        };
+
+        info!(accuser, "complaint against the dealer");
+        self.audit(session_id, Event::Complained { accuser });
+        let complaints = self.complaints.entry(session_id).or_default();
+        complaints.accusers.insert(accuser);
+        if t.is_some_and(|t| complaints.accusers.len() >= t) {
+            self.vote(session_id, epoch);
+        }
+    }
+
+    /// Checks the share the dealer revealed to answer a complaint, adopting it if it is ours
+    fn on_complaint_response(&mut self, session_id: u64, epoch: u64, revealed: Share) {
+        let is_valid = match self.dealing(session_id) {
+            Some(share_info) => share_info.verify_share(&revealed),
+            None => return debug!("ignoring complaint response for an unknown session"),
+        };
+        if !is_valid {
+            warn!(index = %revealed.index(), "dealer revealed an invalid share");
+            return self.vote(session_id, epoch);
+        }
+        if *revealed.index() != BigUint::from(self.id) {
+            return debug!(index = %revealed.index(), "dealer answered a complaint");
+        }
+
+        let pending = self
+            .complaints
+            .get_mut(&session_id)
+            .and_then(|complaints| complaints.pending.take());
+        if let Some((epoch, share_info)) = pending {
+            info!("adopting the share revealed by the dealer");
+            let share_info = ShareInfo::with_params(revealed, share_info.params().clone())
+                .with_metadata(share_info.metadata().clone())
+                .with_validity(share_info.validity());
+            self.sessions
+                .insert(session_id, Session::new(epoch, share_info));
+        }
+    }
+
+    /// Votes to disqualify the dealer of a session, once
+    fn vote(&mut self, session_id: u64, epoch: u64) {
+        let complaints = self.complaints.entry(session_id).or_default();
+        if complaints.votes.contains(&self.id) {
+            return;
+        }
+
+        self.broadcast(session_id, epoch, Payload::Disqualify(DEALER_ID));
+        self.on_disqualify(self.id, session_id);
+    }
+
+    /// Counts the vote of `from`, dropping the dealing once a majority of players voted
+    ///
+    /// Only players vote, and only on a dealing we hold a share of, valid or not, so votes
+    /// cannot pile up for sessions that were never dealt
+    fn on_disqualify(&mut self, from: usize, session_id: u64) {
+        if self.disqualified.contains(&session_id) {
+            return;
+        }
+        let players = self.players();
+        if !players.contains(&from) {
+            return debug!(from, "ignoring vote of a non-player");
+        }
+        if !self.sessions.contains_key(&session_id) && !self.complaints.contains_key(&session_id) {
+            return debug!(from, "ignoring vote on an unknown session");
+        }
+        let players = players.len();
+        let complaints = self.complaints.entry(session_id).or_default();
+        complaints.votes.insert(from);
+
+        if complaints.votes.len() * 2 > players && self.disqualified.insert(session_id) {
+            warn!("dealer disqualified, dropping the dealing");
+            self.audit(session_id, Event::Disqualified);
+            self.sessions.remove(&session_id);
+            self.complaints.remove(&session_id);
+        }
+    }
+
+    /// Sends the messages a step of a session's DKG asks for
+    fn send_dkg(&mut self, session_id: u64, outbox: Outbox) {
+        for msg in outbox.broadcast {
+            self.broadcast(session_id, 0, Payload::Dkg(msg));
+        }
+        for (to, msg) in outbox.send {
+            self.send_to(to, session_id, 0, Payload::Dkg(msg));
+        }
+    }
+
+    /// Generates a key with the players of `config` as a session, answering `s` with our key
+    /// share once it is generated
+    fn start_dkg(&mut self, session_id: u64, config: DkgConfig, s: Option<Reply<DkgReport>>) {
+        if self.sessions.contains_key(&session_id) || self.dkgs.contains_key(&session_id) {
+            return warn!("session already in use");
+        }
+
+        let mut dkg = Dkg::new(self.id, config);
+        let mut outboxes = vec![dkg.start(&mut self.rng)];
+        let pending = self.dkg_pending.remove(&session_id).unwrap_or_default();
+        for (from, msg) in pending {
+            outboxes.push(dkg.on_message(from, msg));
+        }
+
+        self.dkgs.insert(session_id, (dkg, s));
+        for outbox in outboxes {
+            self.send_dkg(session_id, outbox);
+        }
+        self.finish_dkg(session_id);
+    }
+
+    /// Once a session's DKG completes, holds the key share as the session's dealing and hands it
+    /// to whoever started the DKG
+    fn finish_dkg(&mut self, session_id: u64) {
+        if self.sessions.contains_key(&session_id) {
+            return;
+        }
+        let (output, s) = match self.dkgs.get_mut(&session_id) {
+            Some((dkg, s)) => match dkg.output() {
+                Some(output) => (output.clone(), s.take()),
+                None => return,
+            },
+            None => return,
+        };
+
+        info!(public_key = %output.public_key, "generated key");
+        if let Some(s) = s {
+            answer(
+                s,
+                DkgReport {
+                    public_key: output.public_key,
+                    share_info: output.share_info.clone(),
+                    qualified: output.qualified,
+                },
+            );
+        }
+        self.sessions
+            .insert(session_id, Session::new(0, output.share_info));
+    }
+
+    /// Asks `signers` to sign `message` with their shares of a session, coordinating the
+    /// signature ourselves
+    fn request_signature(
+        &mut self,
+        session_id: u64,
+        message: Vec<u8>,
+        mut signers: Vec<usize>,
+        s: Reply<SignatureReport>,
+    ) {
+        let (epoch, t) = match self.sessions.get(&session_id) {
+            Some(session) => (session.epoch, session.share_info.t()),
+            None => return warn!("no share held for session"),
+        };
+        signers.sort();
+        signers.dedup();
+        if !signers.contains(&self.id) || signers.len() < t {
+            return warn!(
+                t,
+                "signers must include us and number at least the threshold"
+            );
+        }
+
+        let request = self.rng.gen();
+        let id = self.id;
+        for &to in signers.iter().filter(|to| **to != id) {
+            let sign_request = Payload::SignRequest {
+                request,
+                message: message.clone(),
+                signers: signers.clone(),
+            };
+            self.send_to(to, session_id, epoch, sign_request);
+        }
+        self.sign_send.insert((session_id, request), s);
+        self.on_sign_request(self.id, session_id, epoch, request, message, signers);
+    }
+
+    /// Joins the signing `from` coordinates, sending our nonce commitment to the other signers
+    fn on_sign_request(
+        &mut self,
+        from: usize,
+        session_id: u64,
+        epoch: u64,
+        request: u64,
+        message: Vec<u8>,
+        signers: Vec<usize>,
+    ) {
+        let (params, backend) = match self.sessions.get(&session_id) {
+            Some(session) => (session.share_info.params().clone(), session.backend.clone()),
+            None => return,
+        };
+        let key = (session_id, request);
+        if !signers.contains(&self.id) || self.signings.contains_key(&key) {
+            return debug!(from, request, "ignoring sign request");
+        }
+
+        let signing = Signing::with_backend(
+            self.id,
+            from,
+            params,
+            Box::new(backend),
+            message,
+            signers,
+            &mut self.rng,
+        );
+        let commitment = signing.nonce_commitment().clone();
+        let others: Vec<usize> = signing
+            .signers()
+            .iter()
+            .copied()
+            .filter(|id| *id != self.id)
+            .collect();
+        self.signings.insert(key, signing);
+        for to in others {
+            let payload = Payload::NonceCommitment {
+                request,
+                commitment: commitment.clone(),
+            };
+            self.send_to(to, session_id, epoch, payload);
+        }
+
+        let pending = self.nonce_pending.remove(&key).unwrap_or_default();
+        for (from, commitment) in pending {
+            self.on_nonce_commitment(from, session_id, epoch, request, commitment);
+        }
+        // a lone signer has every commitment already
+        self.on_nonce_commitment(self.id, session_id, epoch, request, commitment);
+    }
+
+    /// Starts helping `lost` regenerate its share of a session, sending the pieces of our part
+    /// to every helper
+    fn on_recovery_request(&mut self, lost: usize, session_id: u64, helpers: Vec<usize>) {
+        let (epoch, share_info) = match self.sessions.get(&session_id) {
+            Some(session) => (session.epoch, session.share_info.clone()),
+            None => return warn!(lost, "no share held to help recover with"),
+        };
+        let key = (session_id, lost);
+        if !helpers.contains(&self.id) || self.recoveries.contains_key(&key) {
+            return debug!(lost, "ignoring recovery request");
+        }
+        let recovery = match Recovery::new(&share_info, lost, helpers) {
+            Some(recovery) => recovery,
+            None => return warn!(lost, "ignoring recovery request with unfit helpers"),
+        };
+
+        info!(lost, helpers = ?recovery.helpers(), "helping recover a share");
+        let helpers = recovery.helpers().to_vec();
+        let pieces = recovery.contribute(&share_info, lost, &mut self.rng);
+        self.recoveries.insert(key, recovery);
+        for (to, piece) in pieces {
+            if to == self.id {
+                self.on_recovery_piece(self.id, session_id, lost, helpers.clone(), piece);
+                continue;
+            }
+            let payload = Payload::RecoveryPiece {
+                lost,
+                helpers: helpers.clone(),
+                piece,
+            };
+            self.send_to(to, session_id, epoch, payload);
+        }
+
+        let pending = self.recovery_pending.remove(&key).unwrap_or_default();
+        for (from, helpers, piece) in pending {
+            self.on_recovery_piece(from, session_id, lost, helpers, piece);
+        }
+    }
+
+    /// Keeps the piece of `from`'s part of the share of `lost`, sending `lost` the sum of the
+    /// pieces once every helper's is in
+    fn on_recovery_piece(
+        &mut self,
+        from: usize,
+        session_id: u64,
+        lost: usize,
+        helpers: Vec<usize>,
+        piece: BigUint,
+    ) {
+        let key = (session_id, lost);
+        let recovery = match self.recoveries.get_mut(&key) {
+            Some(recovery) => recovery,
+            None => {
+                return self
+                    .recovery_pending
+                    .entry(key)
+                    .or_default()
+                    .push((from, helpers, piece))
+            }
+        };
+        if recovery.helpers() != helpers || !recovery.receive(from, piece) {
+            return warn!(from, lost, "ignoring recovery piece of other helpers");
+        }
+
+        let session = match self.sessions.get(&session_id) {
+            Some(session) => session,
+            None => return,
+        };
+        let sum = match recovery.sum(session.share_info.q()) {
+            Some(sum) => sum,
+            None => return,
+        };
+        let share = Share::new(BigUint::from(lost), sum);
+        let share_info = ShareInfo::with_params(share, session.share_info.params().clone())
+            .with_validity(session.share_info.validity());
+        let epoch = session.epoch;
+        self.recoveries.remove(&key);
+
+        info!(lost, "sending our sum to the lost player");
+        self.send_to(lost, session_id, epoch, Payload::RecoveryShare(share_info));
+    }
+
+    /// Records the nonce commitment of `from`, sending our partial signature to the coordinator
+    /// once every signer committed
+    fn on_nonce_commitment(
+        &mut self,
+        from: usize,
+        session_id: u64,
+        epoch: u64,
+        request: u64,
+        commitment: BigUint,
+    ) {
+        let key = (session_id, request);
+        let signing = match self.signings.get_mut(&key) {
+            Some(signing) => signing,
+            None => {
+                return self
+                    .nonce_pending
+                    .entry(key)
+                    .or_default()
+                    .push((from, commitment))
+            }
+        };
+
+        if let Some(z) = signing.commit(from, commitment) {
+            let coordinator = signing.coordinator();
+            if coordinator == self.id {
+                self.on_partial_signature(self.id, session_id, epoch, request, z);
+            } else {
+                self.send_to(
+                    coordinator,
+                    session_id,
+                    epoch,
+                    Payload::PartialSignature { request, z },
+                );
+            }
+        }
+    }
+
+    /// Collects the partial signature of `from` for a request we coordinate, combining them
+    /// once every signer sent one
+    fn on_partial_signature(
+        &mut self,
+        from: usize,
+        session_id: u64,
+        epoch: u64,
+        request: u64,
+        z: BigUint,
+    ) {
+        let key = (session_id, request);
+        let signing = match self.signings.get_mut(&key) {
+            Some(signing) if signing.coordinator() == self.id => signing,
+            _ => return debug!(from, request, "ignoring partial signature"),
+        };
+
+        signing.partial(from, z);
+        let signature = match signing.signature() {
+            Some(Ok(signature)) => signature,
+            Some(Err(invalid)) => {
+                warn!(
+                    ?invalid,
+                    "partial signatures did not verify, dropping the request"
+                );
+                self.signings.remove(&key);
+                self.sign_send.remove(&key);
+                return;
+            }
+            None => return,
+        };
+
+        let signing = match self.signings.remove(&key) {
+            Some(signing) => signing,
+            None => return,
+        };
+        info!(request, "message signed");
+        let id = self.id;
+        for &to in signing.signers().iter().filter(|to| **to != id) {
+            let result = Payload::SignatureResult {
+                request,
+                signature: signature.clone(),
+            };
+            self.send_to(to, session_id, epoch, result);
+        }
+        if let Some(s) = self.sign_send.remove(&key) {
+            answer(
+                s,
+                SignatureReport {
+                    signature,
+                    public_key: signing.public_key().clone(),
+                    signers: signing.signers().to_vec(),
+                },
+            );
+        }
+    }
+
+    /// Checks the signature the coordinator combined for a request we signed
+    fn on_signature_result(
+        &mut self,
+        from: usize,
+        session_id: u64,
+        request: u64,
+        signature: Signature,
+    ) {
+        let key = (session_id, request);
+        let is_valid = match self.signings.get(&key) {
+            Some(signing) if signing.coordinator() == from => signing.verify(&signature),
+            _ => return debug!(from, request, "ignoring signature for an unknown request"),
+        };
+
+        self.signings.remove(&key);
+        if is_valid {
+            info!(request, "message signed");
+        } else {
+            warn!(from, request, "coordinator's signature does not verify");
+        }
+    }
+
+    /// Deals this Player's renewal polynomial for the session to every player, once per epoch
+    fn deal(&mut self, session_id: u64) {
+        let players = self.players();
+        let session = match self.sessions.get_mut(&session_id) {
+            Some(session) => session,
+            None => return,
+        };
+        let (epoch, renewal) = match session.refresh.renewal() {
+            Some(renewal) => (session.epoch, renewal),
+            None => return,
+        };
+        let deals = match session
+            .refresh
+            .deal(&session.share_info, &players, &mut self.rng)
+        {
+            Some(deals) => deals,
+            None => return,
+        };
+
+        debug!(?renewal, "dealing renewal");
+        for (to, deal) in deals {
+            if to == self.id {
+                self.on_deal(self.id, session_id, epoch + 1, renewal, deal);
+            } else {
+                self.send_to(to, session_id, epoch, deal.into_payload(epoch + 1, renewal));
+            }
+        }
+    }
+
+    /// Joins `renewal` of the session and deals our part of it, answering `s` once our share
+    /// moved
+    fn renew(&mut self, session_id: u64, renewal: Renewal, s: Option<Reply<RefreshReport>>) {
+        match self.sessions.get_mut(&session_id) {
+            Some(session) if !session.share_info.validity().is_current() => {
+                warn!("our share is outside its validity window, not renewing it");
+            }
+            Some(session) => {
+                if session.refresh.start(renewal) {
+                    if s.is_some() {
+                        session.refresh_send = s;
+                    }
+                    self.deal(session_id);
+                } else {
+                    warn!("another renewal is in progress");
+                }
+            }
+            None => warn!("no share held for session"),
+        }
+    }
+
+    /// Verifies the deal `from` sent us to move the session to epoch `next`, joining the
+    /// renewal if we have not dealt yet and telling every player once it verifies
+    fn on_deal(&mut self, from: usize, session_id: u64, next: u64, renewal: Renewal, deal: Deal) {
+        let id = self.id;
+        let epoch = match self.sessions.get_mut(&session_id) {
+            Some(session) if session.epoch + 1 == next => {
+                if !session.refresh.start(renewal) {
+                    warn!(from, ?renewal, "ignoring deal for another renewal");
+                    return;
+                }
+                if !session.refresh.receive(&session.share_info, from, id, deal) {
+                    warn!(from, "received an invalid deal");
+                    let epoch = session.epoch;
+                    self.reject(session_id, from, RejectReason::InvalidShare);
+                    self.nack(from, session_id, epoch, Nack::InvalidShare);
+                    return;
+                }
+
+                session.epoch
+            }
+            _ => return debug!(from, "ignoring deal for another epoch"),
+        };
+
+        self.deal(session_id);
+        let ack = Payload::RefreshAck {
+            epoch: next,
+            dealer: from,
+        };
+        self.broadcast(session_id, epoch, ack);
+        self.on_refresh_ack(id, session_id, next, from);
+    }
+
+    /// Records that `from` verified the deal of `dealer`, moving the session to epoch `next`
+    /// once every deal is verified by every player
+    fn on_refresh_ack(&mut self, from: usize, session_id: u64, next: u64, dealer: usize) {
+        let players = self.players();
+        let session = match self.sessions.get_mut(&session_id) {
+            Some(session) if session.epoch + 1 == next => session,
+            _ => return debug!(from, "ignoring ack for another epoch"),
+        };
+
+        session.refresh.ack(dealer, from);
+        if !session.refresh.is_complete(&players) {
+            return;
+        }
+
+        // like a newer RegShare, the new epoch drops any reconstruction in progress
+        let share_info = session.refresh.commit(&session.share_info, &players);
+        let refresh_send = session.refresh_send.take();
+        self.sessions
+            .insert(session_id, Session::new(next, share_info));
+        info!(epoch = next, "moved share to a new epoch");
+
+        if let Some(s) = refresh_send {
+            answer(
+                s,
+                RefreshReport {
+                    epoch: next,
+                    dealers: players.clone(),
+                },
+            );
+        }
+        let commit = Payload::EpochCommit {
+            epoch: next,
+            dealers: players,
+        };
+        self.broadcast(session_id, next, commit);
+    }
+
+    /// Ids of every player, ourselves included, that takes part in renewals
+    fn players(&self) -> Vec<usize> {
+        let mut players: Vec<usize> = self
+            .transport
+            .peers()
+            .into_iter()
+            .filter(|id| *id != DEALER_ID)
+            .chain([self.id])
+            .collect();
+        players.sort();
+
+        players
+    }
+
+    /// Settles on the highest protocol version shared with `from`, answering with our own
+    /// Hello if we have not introduced ourselves yet
+    fn on_hello(&mut self, from: usize, supported: &[Version]) {
+        match negotiate(SUPPORTED_VERSIONS, supported) {
+            Some(version) => {
+                debug!(from, ?version, "negotiated protocol version");
+                self.versions.insert(from, version);
+                self.send_hello(from);
+            }
+            None => {
+                let version = supported.iter().max().copied().unwrap_or(PROTOCOL_VERSION);
+                warn!(from, ?supported, "no protocol version in common");
+                self.reject(NO_SESSION, from, RejectReason::UnsupportedVersion(version));
+            }
+        }
+    }
+
+    /// Introduces ourselves to `to` with the versions we support, once per peer
+    fn send_hello(&mut self, to: usize) {
+        if self.hello_sent.insert(to) {
+            let payload = Payload::Hello(SUPPORTED_VERSIONS.to_vec());
+            let hello = WireMessage::new(NO_SESSION, 0, payload);
+            if let Err(err) = self.transport.send(to, hello) {
+                warn!(to, "error while sending hello: {}", err);
+            }
+        }
+    }
+
+    /// Sends `payload` for the given session and epoch to `to` using the version negotiated
+    /// with it
+    fn send_to(&mut self, to: usize, session_id: u64, epoch: u64, payload: Payload) {
+        self.send_hello(to);
+
+        let version = self.versions.get(&to).copied().unwrap_or(PROTOCOL_VERSION);
+        let msg = WireMessage::with_version(version, session_id, epoch, payload);
+        if let Err(err) = self.transport.send(to, msg) {
+            warn!(to, "error while sending: {}", err);
+        }
+    }
+
+    /// Tells `to` why its share or deal for the given session and epoch could not be used
+    fn nack(&mut self, to: usize, session_id: u64, epoch: u64, nack: Nack) {
+        self.send_to(to, session_id, epoch, Payload::Nack(nack));
+    }
+
+    /// Records why `other_id` was rejected for the session's next ReconstructionReport, and
+    /// blacklists it in the session if the reason proves it misbehaved
+    fn reject(&mut self, session_id: u64, other_id: usize, reason: RejectReason) {
+        if reason.is_misbehavior() {
+            self.blacklist.insert((session_id, other_id));
+        }
+        if let Some(session) = self.sessions.get_mut(&session_id) {
+            session.rejected.push((other_id, reason));
+        }
+    }
+
+    /// Sends `payload` to every peer with the Guarantee it calls for
+    fn broadcast(&mut self, session_id: u64, epoch: u64, payload: Payload) {
+        let msg = WireMessage::new(session_id, epoch, payload);
+        let step = self.broadcaster.broadcast(&self.transport.peers(), msg);
+        self.apply(step);
+    }
+
+    /// Sends the messages of a broadcast step and handles the ones it delivers, returns false
+    /// if the Player should stop
+    fn apply(&mut self, step: Step) -> bool {
+        for (to, msg) in step.send {
+            self.send_to(to, msg.session_id, msg.epoch, msg.payload);
+        }
+
+        let mut running = true;
+        for (origin, msg) in step.deliver {
+            running &= self.on_message(origin, msg);
+        }
+
+        running
+    }
+}
+
+/// Answer a local request, noting when whoever asked is no longer waiting
+fn answer<T>(s: Reply<T>, response: T) {
+    let id = s.id();
+    if let Err(err) = s.send(response) {
+        debug!(request = id, %err, "response dropped");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs::{self, File};
+    use std::io::{self, BufReader};
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::mpsc::{self, Sender};
+    use std::sync::Arc;
+    use std::thread;
+    use std::time::Duration;
+
+    use num_bigint::BigUint;
+
+    use crate::audit::{self, AuditLog, Event};
+    use crate::backend::{InMemory, ShareBackend};
+    use crate::dealer::{Dealer, DEALER_ID};
+    use crate::dkg::DkgConfig;
+    use crate::refresh::Renewal;
+    use crate::rpc::{Nack, Payload, RejectReason, Router, Share, Version, WireMessage, RPC};
+    use crate::sign;
+    use crate::transport::ChannelNetwork;
+
+    use super::Player;
+
+    /// Spawns n players on a new network and returns their inboxes
+    fn players(n: usize, workers: usize) -> (ChannelNetwork, Vec<Sender<RPC>>) {
+        let network = ChannelNetwork::new();
+        let senders = (1..=n)
+            .map(|id| {
+                let (sender, _) = Player::with_verify_workers(id, network.transport(id), workers);
+                network.register(id, sender.clone());
+
+                sender
+            })
+            .collect();
+
+        (network, senders)
+    }
+
+    /// Deals a secret of 1234 to n players and returns the session id and the players' inboxes
+    /// once they hold their shares
+    fn deal(n: usize, t: usize, workers: usize) -> (u64, Vec<Sender<RPC>>) {
+        let dealer = Dealer::new(n, t, 1234).unwrap();
+        let (network, senders) = players(n, workers);
+        dealer.propagate(&network.transport(DEALER_ID));
+
+        // wait for the players to accept their shares before reconstructing
+        thread::sleep(Duration::from_millis(100));
+
+        (dealer.session_id, senders)
+    }
+
+    #[test]
+    fn player_reconstruct_with_verify_workers() {
+        let (session_id, senders) = deal(5, 3, 2);
+
+        let mut router = Router::new();
+        senders.iter().for_each(|s| {
+            s.send(RPC::Reconstruct(session_id, router.request()))
+                .unwrap();
+        });
+
+        let (_, report) = router.recv().unwrap();
+
+        assert_eq!(BigUint::from(1234_u32), *report.secret.expose());
+        assert!(report.contributors.len() >= 3);
+        assert!(report.rejected.is_empty());
+        assert!(report.verified);
+    }
+
+    #[test]
+    fn player_with_verify_workers_stops_with_its_inbox() {
+        let network = ChannelNetwork::new();
+        let (sender, handle) = Player::with_verify_workers(1, network.transport(1), 2);
+        drop(sender);
+
+        let (done, stopped) = mpsc::channel();
+        thread::spawn(move || {
+            handle.join().unwrap();
+            done.send(()).unwrap();
+        });
+        assert!(stopped.recv_timeout(Duration::from_secs(5)).is_ok());
+    }
+
+    #[test]
+    fn player_reconstructs_many_sessions_at_once() {
+        let (network, senders) = players(5, 0);
+        let dealers: Vec<Dealer> = [1234, 5678, 9012]
+            .iter()
+            .map(|&secret| Dealer::new(5, 3, secret).unwrap())
+            .collect();
+        dealers
+            .iter()
+            .for_each(|dealer| dealer.propagate(&network.transport(DEALER_ID)));
+        thread::sleep(Duration::from_millis(100));
+
+        let mut sessions: Vec<u64> = dealers.iter().map(|dealer| dealer.session_id).collect();
+        // a session nobody holds fails alone
+        sessions.push(
+            dealers
+                .iter()
+                .map(|dealer| dealer.session_id)
+                .max()
+                .unwrap()
+                + 1,
+        );
+        let mut router = Router::new();
+        senders.iter().for_each(|s| {
+            s.send(RPC::ReconstructMany(sessions.clone(), router.request()))
+                .unwrap();
+        });
+
+        let (_, batch) = router.recv_timeout(Duration::from_secs(5)).unwrap();
+        assert_eq!(vec![sessions[3]], batch.failed);
+        for (dealer, secret) in dealers.iter().zip([1234_u32, 5678, 9012]) {
+            let report = &batch.reports[&dealer.session_id];
+            assert_eq!(BigUint::from(secret), *report.secret.expose());
+            assert!(report.verified && report.contributors.len() >= 3);
+        }
+    }
+
+    #[test]
+    fn player_logs_protocol_events() {
+        let path = std::env::temp_dir().join(format!("rust_vss-audit-{}.log", std::process::id()));
+        let network = ChannelNetwork::new();
+        let mut senders = vec![];
+        let mut logging = None;
+        for id in 1..=3 {
+            let sender = if id == 1 {
+                let log = AuditLog::open("player-1", &path).unwrap();
+                let (sender, handle) = Player::with_audit(id, network.transport(id), log);
+                logging = Some(handle);
+                sender
+            } else {
+                Player::spawn(id, network.transport(id)).0
+            };
+            network.register(id, sender.clone());
+            senders.push(sender);
+        }
+        let dealer = Dealer::new(3, 2, 1234).unwrap();
+        dealer.propagate(&network.transport(DEALER_ID));
+        thread::sleep(Duration::from_millis(100));
+
+        let mut router = Router::new();
+        senders.iter().for_each(|s| {
+            s.send(RPC::Reconstruct(dealer.session_id, router.request()))
+                .unwrap();
+        });
+        for _ in &senders {
+            router.recv().unwrap();
+        }
+        // the log is complete once the player stopped
+        network.unregister(1);
+        drop(senders);
+        logging.unwrap().join().unwrap();
+
+        let entries = audit::read(BufReader::new(File::open(&path).unwrap())).unwrap();
+        fs::remove_file(&path).unwrap();
+        audit::verify(&entries, None).unwrap();
+        let valid = true;
+        assert_eq!(Event::Verified { from: 0, valid }, entries[0].event);
+        assert!(entries
+            .iter()
+            .any(|entry| matches!(entry.event, Event::Reconstructed { ok: true, .. })));
+    }
+
+    #[test]
+    fn player_rejects_unsupported_version() {
+        let (session_id, senders) = deal(5, 3, 0);
+        let version = Version { major: 9, minor: 0 };
+        let share = Share::new(BigUint::from(2_usize), BigUint::from(1_usize));
+        let payload = Payload::ReconstructShare(share);
+        let msg = WireMessage::with_version(version, session_id, 0, payload);
+        senders[0].send(RPC::Wire(2, msg)).unwrap();
+
+        let mut router = Router::new();
+        senders[0]
+            .send(RPC::Reconstruct(session_id, router.request()))
+            .unwrap();
+        let mut others = Router::new();
+        senders[1..].iter().for_each(|s| {
+            s.send(RPC::Reconstruct(session_id, others.request()))
+                .unwrap();
+        });
+
+        let (_, report) = router.recv().unwrap();
+
+        assert_eq!(BigUint::from(1234_u32), *report.secret.expose());
+        assert_eq!(
+            (2, RejectReason::UnsupportedVersion(version)),
+            report.rejected[0]
+        );
+    }
+
+    #[test]
+    fn player_ignores_votes_of_non_players_and_unknown_sessions() {
+        let (network, senders) = players(5, 0);
+        let dealer = Dealer::new(5, 3, 1234).unwrap();
+        let vote = || {
+            let payload = Payload::Disqualify(DEALER_ID);
+            WireMessage::new(dealer.session_id, 0, payload)
+        };
+
+        // a majority of players vote before the dealing exists, then the dealer and outsiders
+        for from in [2, 3, 4] {
+            senders[0].send(RPC::Wire(from, vote())).unwrap();
+        }
+        dealer.propagate(&network.transport(DEALER_ID));
+        thread::sleep(Duration::from_millis(100));
+        for from in [DEALER_ID, 6, 7, 8] {
+            senders[0].send(RPC::Wire(from, vote())).unwrap();
+        }
+
+        let mut router = Router::new();
+        senders[0]
+            .send(RPC::Reconstruct(dealer.session_id, router.request()))
+            .unwrap();
+        let mut others = Router::new();
+        senders[1..].iter().for_each(|s| {
+            s.send(RPC::Reconstruct(dealer.session_id, others.request()))
+                .unwrap();
+        });
+
+        let (_, report) = router.recv_timeout(Duration::from_secs(5)).unwrap();
+        assert_eq!(BigUint::from(1234_u32), *report.secret.expose());
+    }
+
+    #[test]
+    fn player_rejected_in_one_session_takes_part_in_the_next() {
+        let (network, senders) = players(3, 0);
+        let dealers = [
+            Dealer::new(3, 3, 1234).unwrap(),
+            Dealer::new(3, 3, 4321).unwrap(),
+        ];
+        dealers
+            .iter()
+            .for_each(|dealer| dealer.propagate(&network.transport(DEALER_ID)));
+        thread::sleep(Duration::from_millis(100));
+
+        // player 2 sends player 1 a wrong share of the first dealing
+        let wrong = Share::new(BigUint::from(2_usize), BigUint::from(1_usize));
+        let payload = Payload::ReconstructShare(wrong);
+        let msg = WireMessage::new(dealers[0].session_id, 0, payload);
+        senders[0].send(RPC::Wire(2, msg)).unwrap();
+
+        let session_id = dealers[1].session_id;
+        let mut router = Router::new();
+        senders[0]
+            .send(RPC::Reconstruct(session_id, router.request()))
+            .unwrap();
+        let mut others = Router::new();
+        senders[1..].iter().for_each(|s| {
+            s.send(RPC::Reconstruct(session_id, others.request()))
+                .unwrap();
+        });
+
+        // with t = n the second dealing needs player 2's share
+        let (_, report) = router.recv_timeout(Duration::from_secs(5)).unwrap();
+        assert_eq!(BigUint::from(4321_u32), *report.secret.expose());
+        assert!(report.contributors.contains(&2));
+        assert!(report.rejected.is_empty());
+    }
+
+    #[test]
+    fn player_requests_shares_from_live_peers() {
+        let (session_id, senders) = deal(5, 3, 0);
+        thread::sleep(Duration::from_millis(200));
+
+        let mut router = Router::new();
+        senders[0].send(RPC::LivePeers(router.request())).unwrap();
+        assert_eq!(vec![2, 3, 4, 5], router.recv().unwrap().1);
+
+        let mut router = Router::new();
+        senders[0].send(RPC::Status(router.request())).unwrap();
+        let status = router.recv().unwrap().1;
+        assert_eq!(vec![(session_id, 0)], status.sessions);
+        assert_eq!(vec![2, 3, 4, 5], status.live_peers);
+
+        // only one player is asked to reconstruct, the others reveal their shares on request
+        let mut router = Router::new();
+        senders[0]
+            .send(RPC::Reconstruct(session_id, router.request()))
+            .unwrap();
+        let (_, report) = router.recv_timeout(Duration::from_secs(5)).unwrap();
+        assert_eq!(BigUint::from(1234_u32), *report.secret.expose());
+    }
+
+    #[test]
+    fn player_nacks_unusable_shares() {
+        let (network, senders) = players(5, 0);
+        let dealer = Dealer::new(5, 3, 1234).unwrap();
+        dealer.propagate(&network.transport(DEALER_ID));
+        thread::sleep(Duration::from_millis(100));
+
+        let (inbox, rx) = mpsc::channel();
+        network.register(6, inbox);
+        let share = Share::new(BigUint::from(6_usize), BigUint::from(1_usize));
+        let sent = [
+            (
+                dealer.session_id.wrapping_add(1),
+                dealer.epoch,
+                Nack::UnknownSession,
+            ),
+            (dealer.session_id, dealer.epoch + 1, Nack::NotReady),
+            (dealer.session_id, dealer.epoch, Nack::InvalidShare),
+        ];
+
+        for (session_id, epoch, expected) in sent {
+            let payload = Payload::ReconstructShare(share.clone());
+            let msg = WireMessage::new(session_id, epoch, payload);
+            senders[0].send(RPC::Wire(6, msg)).unwrap();
+
+            let received = std::iter::from_fn(|| rx.recv_timeout(Duration::from_secs(5)).ok());
+            let nack = received.into_iter().find_map(|rpc| match rpc {
+                RPC::Wire(1, msg) => match msg.payload {
+                    Payload::Nack(nack) => Some((msg.session_id, msg.epoch, nack)),
+                    _ => None,
+                },
+                _ => None,
+            });
+            assert_eq!(Some((session_id, epoch, expected)), nack);
+        }
+    }
+
+    #[test]
+    fn player_refuses_expired_shares() {
+        let (network, senders) = players(3, 0);
+        let mut dealer = Dealer::new(3, 2, 1234).unwrap();
+        dealer.not_after = Some(1);
+        dealer.propagate(&network.transport(DEALER_ID));
+        thread::sleep(Duration::from_millis(100));
+
+        let (inbox, rx) = mpsc::channel();
+        network.register(6, inbox);
+        let msg = WireMessage::new(dealer.session_id, dealer.epoch, Payload::ShareRequest);
+        senders[0].send(RPC::Wire(6, msg)).unwrap();
+        let received = std::iter::from_fn(|| rx.recv_timeout(Duration::from_secs(5)).ok());
+        let nack = received.into_iter().find_map(|rpc| match rpc {
+            RPC::Wire(1, msg) => match msg.payload {
+                Payload::Nack(nack) => Some(nack),
+                _ => None,
+            },
+            _ => None,
+        });
+        assert_eq!(Some(Nack::Expired), nack);
+
+        let mut router = Router::new();
+        senders.iter().for_each(|s| {
+            s.send(RPC::Reconstruct(dealer.session_id, router.request()))
+                .unwrap();
+        });
+        assert!(router.recv_timeout(Duration::from_millis(300)).is_err());
+    }
+
+    #[test]
+    fn player_renews_shares() {
+        let (session_id, senders) = deal(5, 3, 0);
+
+        for (renewal, epoch) in [(Renewal::Refresh, 1), (Renewal::Reshare(2), 2)] {
+            // one player starts the renewal and the others join once its deal arrives
+            let mut router = Router::new();
+            senders[0]
+                .send(RPC::Refresh(session_id, renewal, router.request()))
+                .unwrap();
+            let (_, report) = router.recv_timeout(Duration::from_secs(5)).unwrap();
+            assert_eq!(epoch, report.epoch);
+            assert_eq!(vec![1, 2, 3, 4, 5], report.dealers);
+            thread::sleep(Duration::from_millis(100));
+
+            let mut router = Router::new();
+            senders.iter().for_each(|s| {
+                s.send(RPC::Reconstruct(session_id, router.request()))
+                    .unwrap();
+            });
+            let (_, report) = router.recv_timeout(Duration::from_secs(5)).unwrap();
+            assert_eq!(BigUint::from(1234_u32), *report.secret.expose());
+        }
+    }
+
+    /// Deals a secret of 1234 to n players with player 1's share corrupted, then answers
+    /// complaints revealing player 1's share, the honest one unless `corrupt_reveal`
+    fn deal_corrupted(n: usize, corrupt_reveal: bool) -> (u64, Vec<Sender<RPC>>) {
+        let (network, senders) = players(n, 0);
+        let mut dealer = Dealer::new(n, 3, 1234).unwrap();
+        let honest = dealer.shares[0].clone();
+        let corrupted = Share::new(
+            honest.index().clone(),
+            honest.value() + BigUint::from(1_usize),
+        );
+
+        let (inbox, rx) = mpsc::channel();
+        network.register(DEALER_ID, inbox);
+        let transport = network.transport(DEALER_ID);
+        dealer.shares[0] = corrupted;
+        dealer.propagate(&transport);
+        if !corrupt_reveal {
+            dealer.shares[0] = honest;
+        }
+
+        let session_id = dealer.session_id;
+        thread::spawn(move || {
+            for rpc in rx {
+                if let RPC::Wire(_, msg) = rpc {
+                    dealer.respond(&transport, &msg);
+                }
+            }
+        });
+        thread::sleep(Duration::from_millis(200));
+
+        (session_id, senders)
+    }
+
+    #[test]
+    fn player_adopts_revealed_share() {
+        let (session_id, senders) = deal_corrupted(5, false);
+
+        let mut router = Router::new();
+        senders[0]
+            .send(RPC::Reconstruct(session_id, router.request()))
+            .unwrap();
+        let mut others = Router::new();
+        senders[1..].iter().for_each(|s| {
+            s.send(RPC::Reconstruct(session_id, others.request()))
+                .unwrap();
+        });
+
+        let (_, report) = router.recv_timeout(Duration::from_secs(5)).unwrap();
+        assert_eq!(BigUint::from(1234_u32), *report.secret.expose());
+    }
+
+    #[test]
+    fn player_disqualifies_dealer_revealing_invalid_share() {
+        let (session_id, senders) = deal_corrupted(5, true);
+
+        // the dealing was dropped, so no player answers
+        let mut router = Router::new();
+        senders.iter().for_each(|s| {
+            s.send(RPC::Reconstruct(session_id, router.request()))
+                .unwrap();
+        });
+        assert!(router.recv_timeout(Duration::from_millis(500)).is_err());
+    }
+
+    #[test]
+    fn player_generates_key() {
+        let (_, senders) = players(5, 0);
+        let config = DkgConfig::generate(3, (1..=5).collect());
+        let session_id = 42;
+
+        let mut router = Router::new();
+        senders.iter().for_each(|s| {
+            s.send(RPC::Dkg(session_id, config.clone(), router.request()))
+                .unwrap();
+        });
+        let reports: Vec<_> = senders
+            .iter()
+            .map(|_| router.recv_timeout(Duration::from_secs(5)).unwrap().1)
+            .collect();
+        assert!(reports
+            .iter()
+            .all(|report| report.public_key == reports[0].public_key));
+
+        // the key shares are held as the session's dealing, reconstructing gives the key's secret
+        let mut router = Router::new();
+        senders.iter().for_each(|s| {
+            s.send(RPC::Reconstruct(session_id, router.request()))
+                .unwrap();
+        });
+        let secret = router
+            .recv_timeout(Duration::from_secs(5))
+            .unwrap()
+            .1
+            .secret;
+        assert_eq!(
+            reports[0].public_key,
+            config.g.modpow(secret.expose(), &config.p)
+        );
+    }
+
+    #[test]
+    fn player_signs_with_shares() {
+        let (network, senders) = players(5, 0);
+        let dealer = Dealer::new(5, 3, 1234).unwrap();
+        dealer.propagate(&network.transport(DEALER_ID));
+        thread::sleep(Duration::from_millis(100));
+
+        // any t players can sign, whichever of them coordinates
+        for (coordinator, signers) in [(2, vec![2, 3, 5]), (1, vec![1, 2, 3, 4])] {
+            let mut router = Router::new();
+            let message = b"hello".to_vec();
+            senders[coordinator - 1]
+                .send(RPC::Sign(
+                    dealer.session_id,
+                    message,
+                    signers.clone(),
+                    router.request(),
+                ))
+                .unwrap();
+            let (_, report) = router.recv_timeout(Duration::from_secs(5)).unwrap();
+
+            assert_eq!(signers, report.signers);
+            assert_eq!(dealer.c[0], report.public_key);
+            let (g, p, q) = (&dealer.g, &dealer.p, &dealer.q);
+            assert!(sign::verify(
+                &dealer.c[0],
+                b"hello",
+                &report.signature,
+                g,
+                p,
+                q
+            ));
+        }
+    }
+
+    /// Counts the signing responses of the share it keeps
+    struct Counting(InMemory, Arc<AtomicUsize>);
+
+    impl ShareBackend for Counting {
+        fn index(&self) -> &BigUint {
+            self.0.index()
+        }
+
+        fn exponentiate(&self, base: &BigUint, p: &BigUint) -> io::Result<BigUint> {
+            self.0.exponentiate(base, p)
+        }
+
+        fn respond(&self, nonce: &BigUint, c: &BigUint, q: &BigUint) -> io::Result<BigUint> {
+            self.1.fetch_add(1, Ordering::SeqCst);
+            self.0.respond(nonce, c, q)
+        }
+    }
+
+    #[test]
+    fn player_signs_through_its_backend() {
+        let (network, senders) = players(3, 0);
+        let dealer = Dealer::new(3, 2, 1234).unwrap();
+        dealer.propagate(&network.transport(DEALER_ID));
+        thread::sleep(Duration::from_millis(100));
+
+        let use_backend = |backend: Arc<dyn ShareBackend>| {
+            let mut router = Router::new();
+            senders[0]
+                .send(RPC::UseBackend(
+                    dealer.session_id,
+                    backend,
+                    router.request(),
+                ))
+                .unwrap();
+            router.recv().unwrap().1
+        };
+        let share = &dealer.shares[0];
+        let wrong = Share::new(share.index().clone(), share.value() + 1_u32);
+        assert!(!use_backend(Arc::new(InMemory::new(wrong))));
+        let responses = Arc::new(AtomicUsize::new(0));
+        let backend = Counting(InMemory::new(share.clone()), responses.clone());
+        assert!(use_backend(Arc::new(backend)));
+
+        let mut router = Router::new();
+        senders[0]
+            .send(RPC::Sign(
+                dealer.session_id,
+                b"hello".to_vec(),
+                vec![1, 2],
+                router.request(),
+            ))
+            .unwrap();
+        let (_, report) = router.recv_timeout(Duration::from_secs(5)).unwrap();
+
+        let (g, p, q) = (&dealer.g, &dealer.p, &dealer.q);
+        assert!(sign::verify(
+            &dealer.c[0],
+            b"hello",
+            &report.signature,
+            g,
+            p,
+            q
+        ));
+        assert_eq!(1, responses.load(Ordering::SeqCst));
+    }
+
+    #[test]
+    fn player_accepts_batch_whole_or_not_at_all() {
+        let (network, senders) = players(5, 0);
+        let mut dealers = [
+            Dealer::new(5, 3, 1234).unwrap(),
+            Dealer::new(5, 3, 4321).unwrap(),
+        ];
+        // player 1's share of the second dealing is corrupted, so it holds neither
+        let corrupted = &dealers[1].shares[0];
+        dealers[1].shares[0] = Share::new(
+            corrupted.index().clone(),
+            corrupted.value() + BigUint::from(1_usize),
+        );
+        Dealer::propagate_batch(&dealers, &network.transport(DEALER_ID));
+        thread::sleep(Duration::from_millis(100));
+
+        for dealer in &dealers {
+            let mut router = Router::new();
+            senders[0]
+                .send(RPC::Reconstruct(dealer.session_id, router.request()))
+                .unwrap();
+            assert!(router.recv_timeout(Duration::from_millis(300)).is_err());
+        }
+
+        let mut router = Router::new();
+        senders[1]
+            .send(RPC::Reconstruct(dealers[0].session_id, router.request()))
+            .unwrap();
+        let (_, report) = router.recv_timeout(Duration::from_secs(5)).unwrap();
+        assert_eq!(BigUint::from(1234_u32), *report.secret.expose());
+    }
+
+    #[test]
+    fn player_reconstructs_concurrent_sessions() {
+        let (network, senders) = players(5, 0);
+        let dealers = [
+            Dealer::new(5, 3, 1234).unwrap(),
+            Dealer::new(5, 2, 4321).unwrap(),
+        ];
+        dealers
+            .iter()
+            .for_each(|dealer| dealer.propagate(&network.transport(DEALER_ID)));
+        thread::sleep(Duration::from_millis(100));
+
+        for (dealer, secret) in dealers.iter().zip([1234_u32, 4321]) {
+            let mut router = Router::new();
+            senders.iter().for_each(|s| {
+                s.send(RPC::Reconstruct(dealer.session_id, router.request()))
+                    .unwrap();
+            });
+
+            assert_eq!(
+                BigUint::from(secret),
+                *router.recv().unwrap().1.secret.expose()
+            );
+        }
+    }
+}
@@ -0,0 +1,523 @@
+//! Access policies beyond t of n: AND, OR and threshold trees over named parties such as
+//! `(A AND B) OR any 3 of {C, D, E, F}`, dealt as layered Feldman sharings
+//!
+//! Every node of the tree shares its value among its children with a polynomial of degree k - 1,
+//! k its threshold, AND being n of n and OR 1 of n, down to the parties at the leaves. The root's
+//! value is the secret. Each node publishes the commitments to its polynomial, so a party checks
+//! its share and the path of nodes above it against them. A set of shares reconstructs the secret
+//! only if its parties satisfy the policy, which `Policy::is_qualified` decides up front.
+
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fmt;
+use std::iter::Peekable;
+use std::str::{CharIndices, FromStr};
+
+use num_bigint::{BigUint, RandBigInt};
+use rand::{CryptoRng, RngCore};
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+use crate::dealer::{Dealer, BIT_SIZE};
+use crate::error::VssError;
+use crate::rpc::Share;
+use crate::secret::{self, Secret};
+use crate::vss;
+
+/// Who may reconstruct a secret
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum Policy {
+    /// A party named by the caller
+    Party(String),
+    /// Any k of the policies
+    Threshold(usize, Vec<Policy>),
+}
+
+impl Policy {
+    /// A policy every one of `policies` must satisfy
+    pub fn all(policies: Vec<Policy>) -> Policy {
+        Policy::Threshold(policies.len(), policies)
+    }
+
+    /// A policy any one of `policies` satisfies
+    pub fn any(policies: Vec<Policy>) -> Policy {
+        Policy::Threshold(1, policies)
+    }
+
+    /// Fail unless every threshold is between 1 and its number of policies
+    fn check(&self) -> Result<(), VssError> {
+        match self {
+            Policy::Party(_) => Ok(()),
+            Policy::Threshold(k, of) if *k == 0 || *k > of.len() => Err(VssError::InvalidPolicy(
+                format!("threshold {} of {} policies", k, of.len()),
+            )),
+            Policy::Threshold(_, of) => of.iter().try_for_each(Policy::check),
+        }
+    }
+
+    /// Whether `parties` together satisfy this policy
+    pub fn is_qualified<'a, I: IntoIterator<Item = &'a str>>(&self, parties: I) -> bool {
+        self.satisfied_by(&parties.into_iter().collect())
+    }
+
+    fn satisfied_by(&self, parties: &HashSet<&str>) -> bool {
+        match self {
+            Policy::Party(name) => parties.contains(name.as_str()),
+            Policy::Threshold(k, of) => {
+                of.iter()
+                    .filter(|policy| policy.satisfied_by(parties))
+                    .count()
+                    >= *k
+            }
+        }
+    }
+
+    /// The policy at `path`, the positions of the children to descend into from this one
+    fn at(&self, path: &[usize]) -> Option<&Policy> {
+        match (self, path.split_first()) {
+            (_, None) => Some(self),
+            (Policy::Threshold(_, of), Some((i, rest))) => of.get(*i)?.at(rest),
+            (Policy::Party(_), Some(_)) => None,
+        }
+    }
+}
+
+impl fmt::Display for Policy {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let join = |of: &[Policy], sep: &str| {
+            of.iter()
+                .map(Policy::to_string)
+                .collect::<Vec<_>>()
+                .join(sep)
+        };
+
+        match self {
+            Policy::Party(name) => f.write_str(name),
+            Policy::Threshold(k, of) if of.len() > 1 && *k == of.len() => {
+                write!(f, "({})", join(of, " AND "))
+            }
+            Policy::Threshold(1, of) if of.len() > 1 => write!(f, "({})", join(of, " OR ")),
+            Policy::Threshold(k, of) => write!(f, "{} of {{{}}}", k, join(of, ", ")),
+        }
+    }
+}
+
+/// A token of a policy
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Token {
+    Word(String),
+    Open,
+    Close,
+    OpenSet,
+    CloseSet,
+    Comma,
+}
+
+/// Splits a policy into tokens, a word being a run of letters, digits, `_`, `-` and `.`
+fn tokenize(policy: &str) -> Result<Vec<Token>, VssError> {
+    let mut chars: Peekable<CharIndices> = policy.char_indices().peekable();
+    let mut tokens = vec![];
+    while let Some((at, c)) = chars.next() {
+        let token = match c {
+            '(' => Token::Open,
+            ')' => Token::Close,
+            '{' => Token::OpenSet,
+            '}' => Token::CloseSet,
+            ',' => Token::Comma,
+            c if c.is_whitespace() => continue,
+            c if c.is_alphanumeric() || "_-.".contains(c) => {
+                let mut word = c.to_string();
+                while let Some((_, c)) =
+                    chars.next_if(|(_, c)| c.is_alphanumeric() || "_-.".contains(*c))
+                {
+                    word.push(c);
+                }
+                Token::Word(word)
+            }
+            c => {
+                return Err(VssError::InvalidPolicy(format!(
+                    "unexpected {:?} at {}",
+                    c, at
+                )))
+            }
+        };
+        tokens.push(token);
+    }
+
+    Ok(tokens)
+}
+
+/// Recursive descent over the tokens of a policy
+///
+/// ```text
+/// or        := and ("OR" and)*
+/// and       := atom ("AND" atom)*
+/// atom      := party | "(" or ")" | ["any"] k "of" "{" or ("," or)* "}"
+/// ```
+struct Parser {
+    tokens: Vec<Token>,
+    at: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.at)
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.at).cloned();
+        self.at += 1;
+        token
+    }
+
+    fn is_keyword(&self, offset: usize, keyword: &str) -> bool {
+        matches!(self.tokens.get(self.at + offset), Some(Token::Word(word)) if word.eq_ignore_ascii_case(keyword))
+    }
+
+    fn expect(&mut self, token: Token) -> Result<(), VssError> {
+        match self.next() {
+            Some(next) if next == token => Ok(()),
+            next => Err(VssError::InvalidPolicy(format!(
+                "expected {:?}, found {:?}",
+                token, next
+            ))),
+        }
+    }
+
+    /// A run of `operand`s joined by `keyword`, combined into one policy if there are several
+    fn joined(
+        &mut self,
+        keyword: &str,
+        operand: fn(&mut Parser) -> Result<Policy, VssError>,
+        combine: fn(Vec<Policy>) -> Policy,
+    ) -> Result<Policy, VssError> {
+        let mut of = vec![operand(self)?];
+        while self.is_keyword(0, keyword) {
+            self.at += 1;
+            of.push(operand(self)?);
+        }
+
+        Ok(if of.len() == 1 {
+            of.remove(0)
+        } else {
+            combine(of)
+        })
+    }
+
+    fn or(&mut self) -> Result<Policy, VssError> {
+        self.joined("OR", Parser::and, Policy::any)
+    }
+
+    fn and(&mut self) -> Result<Policy, VssError> {
+        self.joined("AND", Parser::atom, Policy::all)
+    }
+
+    fn atom(&mut self) -> Result<Policy, VssError> {
+        if self.is_keyword(0, "any") && self.is_keyword(2, "of") {
+            self.at += 1;
+        }
+        if self.is_keyword(1, "of") {
+            return self.threshold();
+        }
+
+        match self.next() {
+            Some(Token::Open) => {
+                let policy = self.or()?;
+                self.expect(Token::Close)?;
+                Ok(policy)
+            }
+            Some(Token::Word(word))
+                if !["AND", "OR"]
+                    .iter()
+                    .any(|keyword| word.eq_ignore_ascii_case(keyword)) =>
+            {
+                Ok(Policy::Party(word))
+            }
+            token => Err(VssError::InvalidPolicy(format!(
+                "expected a party, found {:?}",
+                token
+            ))),
+        }
+    }
+
+    fn threshold(&mut self) -> Result<Policy, VssError> {
+        let k = match self.next() {
+            Some(Token::Word(k)) => k
+                .parse()
+                .map_err(|_| VssError::InvalidPolicy(format!("{} is not a threshold", k)))?,
+            token => {
+                return Err(VssError::InvalidPolicy(format!(
+                    "expected a threshold, found {:?}",
+                    token
+                )))
+            }
+        };
+        self.at += 1;
+        self.expect(Token::OpenSet)?;
+        let mut of = vec![self.or()?];
+        while self.peek() == Some(&Token::Comma) {
+            self.at += 1;
+            of.push(self.or()?);
+        }
+        self.expect(Token::CloseSet)?;
+
+        Ok(Policy::Threshold(k, of))
+    }
+}
+
+impl FromStr for Policy {
+    type Err = VssError;
+
+    fn from_str(policy: &str) -> Result<Policy, VssError> {
+        let mut parser = Parser {
+            tokens: tokenize(policy)?,
+            at: 0,
+        };
+        let parsed = parser.or()?;
+        if let Some(token) = parser.peek() {
+            return Err(VssError::InvalidPolicy(format!("unexpected {:?}", token)));
+        }
+        parsed.check()?;
+
+        Ok(parsed)
+    }
+}
+
+/// A party's share at a leaf of a policy
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PolicyShare {
+    pub party: String,
+    /// Positions of the children leading from the root to the leaf
+    pub path: Vec<usize>,
+    /// The leaf's point on the polynomial of the node above it
+    pub share: Share,
+}
+
+/// The node whose polynomial a node at `path` is a point of, and that point's x coordinate
+///
+/// A root that is a party holds the secret itself, as the one point of a polynomial of degree 0
+fn slot(path: &[usize]) -> (&[usize], usize) {
+    match path.split_last() {
+        Some((i, parent)) => (parent, i + 1),
+        None => (path, 1),
+    }
+}
+
+/// A secret dealt under a policy: the group, the commitments of every node and the parties'
+/// shares
+#[derive(Debug)]
+pub struct PolicyDealing {
+    pub policy: Policy,
+    pub p: BigUint,
+    pub q: BigUint,
+    pub g: BigUint,
+    /// The commitments to the polynomial of every node by its path
+    pub commitments: BTreeMap<Vec<usize>, Vec<BigUint>>,
+    pub shares: Vec<PolicyShare>,
+}
+
+impl PolicyDealing {
+    /// Deal `secret` under `policy` over a group whose q is large enough to hold it
+    pub fn new(policy: Policy, secret: Secret) -> Result<PolicyDealing, VssError> {
+        PolicyDealing::with_rng(policy, secret, BIT_SIZE, &mut rand::thread_rng())
+    }
+
+    /// Like `new` with a q of at least `bits` bits, the group and coefficients drawn from `rng`
+    pub fn with_rng<R: RngCore + CryptoRng>(
+        policy: Policy,
+        secret: Secret,
+        bits: usize,
+        rng: &mut R,
+    ) -> Result<PolicyDealing, VssError> {
+        policy.check()?;
+        let (p, q, g) = Dealer::group_of(bits.max(secret.expose().bits() + 1), rng);
+        let mut dealing = PolicyDealing {
+            policy: policy.clone(),
+            p,
+            q,
+            g,
+            commitments: BTreeMap::new(),
+            shares: vec![],
+        };
+        let root = Share::new(BigUint::from(1_u32), secret.expose().clone());
+        dealing.deal(&policy, vec![], root, rng);
+
+        Ok(dealing)
+    }
+
+    /// Share the value of the node `policy` at `path` among its children, down to the parties
+    fn deal<R: RngCore + CryptoRng>(
+        &mut self,
+        policy: &Policy,
+        path: Vec<usize>,
+        point: Share,
+        rng: &mut R,
+    ) {
+        match policy {
+            Policy::Party(party) => {
+                if path.is_empty() {
+                    let c_0 = self.g.modpow(point.value(), &self.p);
+                    self.commitments.insert(vec![], vec![c_0]);
+                }
+                self.shares.push(PolicyShare {
+                    party: party.clone(),
+                    path,
+                    share: point,
+                });
+            }
+            Policy::Threshold(k, of) => {
+                let mut a: Vec<BigUint> = std::iter::once(point.value().clone())
+                    .chain((1..*k).map(|_| rng.gen_biguint_below(&self.q)))
+                    .collect();
+                let c = vss::generate_commitments(&a, &self.g, &self.p);
+                let points = vss::generate_shares(&a, of.len(), &self.q);
+                a.iter_mut().for_each(secret::wipe);
+
+                self.commitments.insert(path.clone(), c);
+                for (i, (child, point)) in of.iter().zip(points).enumerate() {
+                    let mut child_path = path.clone();
+                    child_path.push(i);
+                    self.deal(child, child_path, point, rng);
+                }
+            }
+        }
+    }
+
+    /// Whether `share` is the share of its party at its leaf, checking its point and every node
+    /// above it against their commitments
+    pub fn verify(&self, share: &PolicyShare) -> bool {
+        if !matches!(self.policy.at(&share.path), Some(Policy::Party(party)) if *party == share.party)
+        {
+            return false;
+        }
+        let commitment = |path: &[usize]| {
+            let (parent, i) = slot(path);
+            let c = self.commitments.get(parent)?;
+
+            Some(vss::commitment_at(&BigUint::from(i), c, &self.p))
+        };
+
+        // each node's constant term is its point on the polynomial above it
+        let nodes_agree = (1..share.path.len()).all(|depth| {
+            let path = &share.path[..depth];
+            match (self.commitments.get(path), commitment(path)) {
+                (Some(c), Some(expected)) => secret::ct_eq(&c[0], &expected),
+                _ => false,
+            }
+        });
+        let (_, i) = slot(&share.path);
+
+        nodes_agree
+            && *share.share.index() == BigUint::from(i)
+            && commitment(&share.path).is_some_and(|expected| {
+                secret::ct_eq(&self.g.modpow(share.share.value(), &self.p), &expected)
+            })
+    }
+}
+
+/// The secret dealt under `policy` over prime field q, failing with `VssError::Unqualified`
+/// unless the parties of `shares` satisfy the policy
+pub fn reconstruct(
+    policy: &Policy,
+    shares: &[PolicyShare],
+    q: &BigUint,
+) -> Result<Secret, VssError> {
+    if !policy.is_qualified(shares.iter().map(|share| share.party.as_str())) {
+        return Err(VssError::Unqualified);
+    }
+    // only a share at a leaf of its own party counts
+    let points: HashMap<&[usize], &Share> = shares
+        .iter()
+        .filter(|share| matches!(policy.at(&share.path), Some(Policy::Party(party)) if *party == share.party))
+        .map(|share| (share.path.as_slice(), &share.share))
+        .collect();
+
+    recover(policy, &mut vec![], &points, q)?.ok_or(VssError::Unqualified)
+}
+
+/// The value of the node `policy` at `path`, if enough shares below it are at hand
+fn recover(
+    policy: &Policy,
+    path: &mut Vec<usize>,
+    points: &HashMap<&[usize], &Share>,
+    q: &BigUint,
+) -> Result<Option<Secret>, VssError> {
+    let (k, of) = match policy {
+        Policy::Party(_) => {
+            return Ok(points
+                .get(path.as_slice())
+                .map(|share| Secret::new(share.value().clone())))
+        }
+        Policy::Threshold(k, of) => (*k, of),
+    };
+
+    let mut children = vec![];
+    for (i, child) in of.iter().enumerate() {
+        path.push(i);
+        let value = recover(child, path, points, q)?;
+        path.pop();
+        if let Some(value) = value {
+            children.push(Share::new(BigUint::from(i + 1), value.expose().clone()));
+        }
+        if children.len() == k {
+            return vss::reconstruct(&children, q).map(Some);
+        }
+    }
+
+    Ok(None)
+}
+
+#[cfg(test)]
+mod tests {
+    use num_bigint::BigUint;
+
+    use crate::error::VssError;
+    use crate::secret::Secret;
+
+    use super::{reconstruct, Policy, PolicyDealing};
+
+    #[test]
+    fn policies_parse_and_print() {
+        let policy: Policy = "(A AND B) OR any 3 of {C, D, E, F}".parse().unwrap();
+        let party = |name: &str| Policy::Party(name.to_string());
+        assert_eq!(
+            Policy::any(vec![
+                Policy::all(vec![party("A"), party("B")]),
+                Policy::Threshold(3, vec![party("C"), party("D"), party("E"), party("F")]),
+            ]),
+            policy
+        );
+        assert_eq!(policy, policy.to_string().parse().unwrap());
+        assert!(policy.is_qualified(["A", "B"]));
+        assert!(policy.is_qualified(["C", "E", "F"]));
+        assert!(!policy.is_qualified(["A", "C", "D"]));
+
+        for invalid in ["A AND", "5 of {A, B}", "(A OR B", "A B", "0 of {A}"] {
+            assert!(invalid.parse::<Policy>().is_err(), "{}", invalid);
+        }
+    }
+
+    #[test]
+    fn qualified_sets_reconstruct() {
+        let policy: Policy = "(A AND B) OR 2 of {C, D, E}".parse().unwrap();
+        let dealing = PolicyDealing::new(policy.clone(), Secret::new(1234_u32.into())).unwrap();
+        assert!(dealing.shares.iter().all(|share| dealing.verify(share)));
+        let mut forged = dealing.shares[0].clone();
+        forged.party = "C".to_string();
+        assert!(!dealing.verify(&forged));
+
+        let of = |parties: &[&str]| {
+            let shares: Vec<_> = dealing
+                .shares
+                .iter()
+                .filter(|share| parties.contains(&share.party.as_str()))
+                .cloned()
+                .collect();
+            reconstruct(&policy, &shares, &dealing.q)
+        };
+        let secret = BigUint::from(1234_u32);
+        assert_eq!(&secret, of(&["A", "B"]).unwrap().expose());
+        assert_eq!(&secret, of(&["D", "E"]).unwrap().expose());
+        assert!(matches!(of(&["A", "C"]), Err(VssError::Unqualified)));
+    }
+}
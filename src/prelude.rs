@@ -0,0 +1,12 @@
+//! Everything a dealing needs, for a glob import
+//!
+//! `use rust_vss::prelude::*;`
+
+pub use crate::dealer::Dealer;
+pub use crate::error::VssError;
+#[cfg(feature = "network")]
+pub use crate::player::Player;
+pub use crate::rpc::{PublicParams, Share, ShareInfo};
+pub use crate::vss::{
+    generate_commitments, generate_shares, generate_shares_iter, reconstruct, verify_share,
+};
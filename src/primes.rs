@@ -0,0 +1,280 @@
+//! Primality testing: Miller–Rabin, optionally with the strong Lucas test that makes it
+//! Baillie–PSW, under a policy saying how hard to try
+//!
+//! Numbers the dealer generates are tested with `Primality::default()`. Parameters a peer or a
+//! file hands over were chosen by someone who may have searched for a composite passing the
+//! fixed bases, and are tested with `Primality::adversarial()`.
+
+use num::{Integer, One, Zero};
+use num_bigint::{BigUint, RandBigInt};
+use rand::{CryptoRng, RngCore};
+
+/// Primes trial division is done by, and the fixed Miller–Rabin bases in that order
+const SMALL_PRIMES: [u32; 24] = [
+    2, 3, 5, 7, 11, 13, 17, 19, 23, 29, 31, 37, 41, 43, 47, 53, 59, 61, 67, 71, 73, 79, 83, 89,
+];
+
+/// How a number is tested for primality
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Primality {
+    /// Miller–Rabin rounds, each with another base
+    pub rounds: usize,
+    /// Also run the strong Lucas test, which with Miller–Rabin to base 2 is Baillie–PSW
+    pub baillie_psw: bool,
+    /// Pick every base but 2 at random rather than the first primes, so no composite passes
+    /// for sure
+    pub adversarial: bool,
+}
+
+impl Default for Primality {
+    /// 24 rounds with the first primes as bases, for numbers picked at random
+    fn default() -> Primality {
+        Primality {
+            rounds: 24,
+            baillie_psw: false,
+            adversarial: false,
+        }
+    }
+}
+
+impl Primality {
+    /// 64 rounds with random bases and Baillie–PSW, for numbers someone else chose
+    pub fn adversarial() -> Primality {
+        Primality {
+            rounds: 64,
+            baillie_psw: true,
+            adversarial: true,
+        }
+    }
+
+    /// Whether n is prime, up to the chance of error of the policy, with random bases drawn from
+    /// `rng`
+    pub fn is_probable_prime<R: RngCore + CryptoRng>(&self, n: &BigUint, rng: &mut R) -> bool {
+        for p in SMALL_PRIMES.iter().map(|p| BigUint::from(*p)) {
+            if *n == p {
+                return true;
+            }
+            if (n % &p).is_zero() {
+                return false;
+            }
+        }
+        if *n < BigUint::from(2_u32) {
+            return false;
+        }
+
+        // n is odd and has no factor below 90 from here on
+        let n_minus_one = n - 1_u32;
+        let s = trailing_zeros(&n_minus_one);
+        let d = &n_minus_one >> s;
+        let two = BigUint::from(2_u32);
+        let passes = (0..self.rounds).all(|round| {
+            let base = match (round, SMALL_PRIMES.get(round)) {
+                (0, _) => two.clone(),
+                (_, Some(base)) if !self.adversarial => BigUint::from(*base),
+                _ => rng.gen_biguint_range(&two, &n_minus_one),
+            };
+
+            miller_rabin(n, &d, s, &base)
+        });
+
+        passes && (!self.baillie_psw || strong_lucas(n))
+    }
+}
+
+/// Whether n is prime by the default policy, see `Primality`
+pub fn is_probable_prime(n: &BigUint) -> bool {
+    Primality::default().is_probable_prime(n, &mut rand::thread_rng())
+}
+
+/// A prime of exactly `bits` bits drawn from `rng`, tested by `policy`
+pub fn random_prime<R: RngCore + CryptoRng>(
+    bits: usize,
+    policy: &Primality,
+    rng: &mut R,
+) -> BigUint {
+    loop {
+        let mut n = rng.gen_biguint(bits);
+        n |= BigUint::one() << (bits - 1);
+        n |= BigUint::one();
+        if policy.is_probable_prime(&n, rng) {
+            return n;
+        }
+    }
+}
+
+/// Zero bits below the lowest one of n, which must not be 0
+fn trailing_zeros(n: &BigUint) -> usize {
+    let digits = n.to_u32_digits();
+    let zero_digits = digits.iter().take_while(|digit| **digit == 0).count();
+
+    zero_digits * 32 + digits[zero_digits].trailing_zeros() as usize
+}
+
+/// The lowest 32 bits of n
+fn low(n: &BigUint) -> u32 {
+    n.to_u32_digits().first().copied().unwrap_or(0)
+}
+
+/// One Miller–Rabin round of odd n with n - 1 = d * 2^s, false if `base` witnesses that n is
+/// composite
+fn miller_rabin(n: &BigUint, d: &BigUint, s: usize, base: &BigUint) -> bool {
+    let n_minus_one = n - 1_u32;
+    let mut x = base.modpow(d, n);
+    if x.is_one() || x == n_minus_one {
+        return true;
+    }
+    for _ in 1..s {
+        x = &x * &x % n;
+        if x == n_minus_one {
+            return true;
+        }
+    }
+
+    false
+}
+
+/// The Jacobi symbol (a/n) of an odd n
+fn jacobi(a: &BigUint, n: &BigUint) -> i8 {
+    let (mut a, mut n) = (a % n, n.clone());
+    let mut symbol = 1;
+    while !a.is_zero() {
+        while a.is_even() {
+            a >>= 1;
+            if low(&n) % 8 == 3 || low(&n) % 8 == 5 {
+                symbol = -symbol;
+            }
+        }
+        std::mem::swap(&mut a, &mut n);
+        if low(&a) % 4 == 3 && low(&n) % 4 == 3 {
+            symbol = -symbol;
+        }
+        a %= &n;
+    }
+
+    if n.is_one() {
+        symbol
+    } else {
+        0
+    }
+}
+
+/// x / 2 mod odd n
+fn halve(x: BigUint, n: &BigUint) -> BigUint {
+    match x.is_odd() {
+        true => (x + n) >> 1,
+        false => x >> 1,
+    }
+}
+
+/// The strong Lucas probable prime test of odd n with no small factors, with the parameters
+/// of Selfridge's method A: D the first of 5, -7, 9, -11, ... with (D/n) = -1, P = 1 and
+/// Q = (1 - D) / 4
+fn strong_lucas(n: &BigUint) -> bool {
+    // a square has no D with (D/n) = -1
+    let root = n.sqrt();
+    if &root * &root == *n {
+        return false;
+    }
+
+    // D and Q mod n
+    let modulo = |x: i64| match x < 0 {
+        true => n - (BigUint::from(x.unsigned_abs()) % n),
+        false => BigUint::from(x as u64) % n,
+    };
+    let mut d = 5_i64;
+    loop {
+        match jacobi(&modulo(d), n) {
+            -1 => break,
+            0 if BigUint::from(d.unsigned_abs()) != *n => return false,
+            _ => d = if d > 0 { -(d + 2) } else { -d + 2 },
+        }
+    }
+    let (d_mod, q_mod) = (modulo(d), modulo((1 - d) / 4));
+
+    // n + 1 = k * 2^s with k odd, U_k and V_k by the bits of k from the top
+    let n_plus_one = n + 1_u32;
+    let s = trailing_zeros(&n_plus_one);
+    let k = &n_plus_one >> s;
+    let k_bytes = k.to_bytes_le();
+    let (mut u, mut v, mut q_k) = (BigUint::one(), BigUint::one(), q_mod.clone());
+    for bit in (0..k.bits() - 1).rev() {
+        // U_2j = U_j V_j, V_2j = V_j^2 - 2 Q^j
+        u = &u * &v % n;
+        v = (&v * &v + n + n - (&q_k << 1) % n) % n;
+        q_k = &q_k * &q_k % n;
+        if k_bytes[bit / 8] >> (bit % 8) & 1 == 1 {
+            // U_2j+1 = (U_2j + V_2j) / 2, V_2j+1 = (D U_2j + V_2j) / 2
+            let next_u = halve(&u + &v, n) % n;
+            v = halve(&d_mod * &u + &v, n) % n;
+            u = next_u;
+            q_k = &q_k * &q_mod % n;
+        }
+    }
+
+    if u.is_zero() {
+        return true;
+    }
+    for _ in 0..s {
+        if v.is_zero() {
+            return true;
+        }
+        v = (&v * &v + n + n - (&q_k << 1) % n) % n;
+        q_k = &q_k * &q_k % n;
+    }
+
+    false
+}
+
+#[cfg(test)]
+mod tests {
+    use num_bigint::BigUint;
+
+    use super::{is_probable_prime, random_prime, strong_lucas, Primality};
+
+    #[test]
+    fn primes_pass_and_composites_fail() {
+        let mut rng = rand::thread_rng();
+        let sieve: Vec<u32> = (0..2000)
+            .filter(|n| *n >= 2 && (2..*n).take_while(|d| d * d <= *n).all(|d| n % d != 0))
+            .collect();
+        for n in 0..2000_u32 {
+            let prime = sieve.contains(&n);
+            assert_eq!(prime, is_probable_prime(&BigUint::from(n)), "{}", n);
+            assert_eq!(
+                prime,
+                Primality::adversarial().is_probable_prime(&BigUint::from(n), &mut rng),
+                "{}",
+                n
+            );
+        }
+
+        // 2^127 - 1, and a Carmichael number that fools Fermat but neither test here
+        let mersenne = (BigUint::from(1_u32) << 127) - 1_u32;
+        assert!(Primality::adversarial().is_probable_prime(&mersenne, &mut rng));
+        assert!(!is_probable_prime(&BigUint::from(3_215_031_751_u64)));
+        assert_eq!(64, random_prime(64, &Primality::default(), &mut rng).bits());
+    }
+
+    #[test]
+    fn lucas_catches_strong_pseudoprimes() {
+        // passes Miller–Rabin to every base below 41, so only Lucas tells it apart
+        let n = BigUint::from(3_825_123_056_546_413_051_u64);
+        let fixed = Primality {
+            rounds: 9,
+            baillie_psw: false,
+            adversarial: false,
+        };
+        let mut rng = rand::thread_rng();
+        assert!(fixed.is_probable_prime(&n, &mut rng));
+        assert!(!strong_lucas(&n));
+        assert!(!Primality {
+            baillie_psw: true,
+            ..fixed
+        }
+        .is_probable_prime(&n, &mut rng));
+
+        for p in [97_u32, 101, 7919, 104_729] {
+            assert!(strong_lucas(&BigUint::from(p)), "{}", p);
+        }
+    }
+}
@@ -0,0 +1,708 @@
+use std::io;
+
+use num_bigint::BigUint;
+use prost::Message;
+
+use crate::broadcast::BroadcastMessage;
+use crate::codec;
+use crate::dkg::{DkgConfig, DkgMessage};
+use crate::rpc::{self, Nack, Payload, Share, ShareInfo, Version, WireMessage};
+use crate::sign::Signature;
+
+/// Types generated by prost from `proto/vss.proto`
+#[allow(clippy::all)]
+pub mod pb {
+    include!(concat!(env!("OUT_DIR"), "/rust_vss.v1.rs"));
+}
+
+/// Encode `msg` as protobuf following `proto/vss.proto`
+pub fn encode(msg: &WireMessage) -> Vec<u8> {
+    pb::WireMessage::from(msg).encode_to_vec()
+}
+
+/// Decode a protobuf message written by this crate or any implementation of `proto/vss.proto`
+pub fn decode(bytes: &[u8]) -> io::Result<WireMessage> {
+    let msg = pb::WireMessage::decode(bytes)
+        .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))?;
+
+    WireMessage::try_from(msg)
+}
+
+fn invalid(what: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, format!("invalid {}", what))
+}
+
+fn biguint(bytes: &[u8], what: &str) -> io::Result<BigUint> {
+    codec::from_bytes(bytes).ok_or_else(|| invalid(what))
+}
+
+fn commitments(commitments: &[Vec<u8>]) -> io::Result<Vec<BigUint>> {
+    commitments
+        .iter()
+        .map(|c| biguint(c, "commitment"))
+        .collect()
+}
+
+fn share(share: Option<pb::Share>, what: &str) -> io::Result<Share> {
+    share.ok_or_else(|| invalid(what))?.try_into()
+}
+
+fn id(id: u64, what: &str) -> io::Result<usize> {
+    usize::try_from(id).map_err(|_| invalid(what))
+}
+
+impl From<&Version> for pb::Version {
+    fn from(version: &Version) -> pb::Version {
+        pb::Version {
+            major: version.major.into(),
+            minor: version.minor.into(),
+        }
+    }
+}
+
+impl TryFrom<pb::Version> for Version {
+    type Error = io::Error;
+
+    fn try_from(version: pb::Version) -> io::Result<Version> {
+        Ok(Version {
+            major: version.major.try_into().map_err(|_| invalid("major"))?,
+            minor: version.minor.try_into().map_err(|_| invalid("minor"))?,
+        })
+    }
+}
+
+impl From<&Share> for pb::Share {
+    fn from(share: &Share) -> pb::Share {
+        pb::Share {
+            index: codec::to_bytes(share.index()),
+            value: codec::to_bytes(share.value()),
+        }
+    }
+}
+
+impl TryFrom<pb::Share> for Share {
+    type Error = io::Error;
+
+    fn try_from(share: pb::Share) -> io::Result<Share> {
+        Ok(Share::new(
+            biguint(&share.index, "share index")?,
+            biguint(&share.value, "share value")?,
+        ))
+    }
+}
+
+impl From<&ShareInfo> for pb::ShareInfo {
+    fn from(info: &ShareInfo) -> pb::ShareInfo {
+        pb::ShareInfo {
+            share: Some(info.share().into()),
+            g: codec::to_bytes(info.g()),
+            commitments: info.commitments().iter().map(codec::to_bytes).collect(),
+            p: codec::to_bytes(info.p()),
+            q: codec::to_bytes(info.q()),
+            t: info.t() as u64,
+            metadata: Some(info.metadata().into()),
+            validity: Some(info.validity().into()),
+        }
+    }
+}
+
+impl From<&rpc::Metadata> for pb::Metadata {
+    fn from(metadata: &rpc::Metadata) -> pb::Metadata {
+        pb::Metadata {
+            custodian: metadata.custodian.clone().unwrap_or_default(),
+            purpose: metadata.purpose.clone().unwrap_or_default(),
+            dealing: metadata.dealing.clone().unwrap_or_default(),
+            created: metadata.created.unwrap_or_default(),
+            labels: metadata.labels.clone().into_iter().collect(),
+        }
+    }
+}
+
+impl From<pb::Metadata> for rpc::Metadata {
+    fn from(metadata: pb::Metadata) -> rpc::Metadata {
+        let set = |s: String| Some(s).filter(|s| !s.is_empty());
+
+        rpc::Metadata {
+            custodian: set(metadata.custodian),
+            purpose: set(metadata.purpose),
+            dealing: set(metadata.dealing),
+            created: Some(metadata.created).filter(|created| *created != 0),
+            labels: metadata.labels.into_iter().collect(),
+        }
+    }
+}
+
+impl From<rpc::Validity> for pb::Validity {
+    fn from(validity: rpc::Validity) -> pb::Validity {
+        pb::Validity {
+            epoch: validity.epoch,
+            not_before: validity.not_before.unwrap_or_default(),
+            not_after: validity.not_after.unwrap_or_default(),
+        }
+    }
+}
+
+impl From<pb::Validity> for rpc::Validity {
+    fn from(validity: pb::Validity) -> rpc::Validity {
+        let set = |time: u64| Some(time).filter(|time| *time != 0);
+
+        rpc::Validity {
+            epoch: validity.epoch,
+            not_before: set(validity.not_before),
+            not_after: set(validity.not_after),
+        }
+    }
+}
+
+impl TryFrom<pb::ShareInfo> for ShareInfo {
+    type Error = io::Error;
+
+    fn try_from(info: pb::ShareInfo) -> io::Result<ShareInfo> {
+        let share = info.share.ok_or_else(|| invalid("share"))?;
+
+        Ok(ShareInfo::new(
+            share.try_into()?,
+            biguint(&info.g, "g")?,
+            commitments(&info.commitments)?,
+            biguint(&info.p, "p")?,
+            biguint(&info.q, "q")?,
+            info.t.try_into().map_err(|_| invalid("t"))?,
+        )
+        .with_metadata(info.metadata.map(Into::into).unwrap_or_default())
+        .with_validity(info.validity.map(Into::into).unwrap_or_default()))
+    }
+}
+
+impl From<&Payload> for pb::wire_message::Payload {
+    fn from(payload: &Payload) -> pb::wire_message::Payload {
+        use pb::wire_message::Payload as Pb;
+
+        match payload {
+            Payload::Ping => Pb::Ping(pb::Ping {}),
+            Payload::Heartbeat => Pb::Heartbeat(pb::Heartbeat {}),
+            Payload::ShareRequest => Pb::ShareRequest(pb::ShareRequest {}),
+            Payload::Batch(msgs) => Pb::Batch(pb::Batch {
+                messages: msgs.iter().map(pb::WireMessage::from).collect(),
+            }),
+            Payload::Hello(versions) => Pb::Hello(pb::Hello {
+                versions: versions.iter().map(pb::Version::from).collect(),
+            }),
+            Payload::RegShare(info) => Pb::RegShare(info.into()),
+            Payload::ReconstructShare(share) => Pb::ReconstructShare(share.into()),
+            Payload::Broadcast(broadcast) => Pb::Broadcast(Box::new(broadcast.into())),
+            Payload::Sequenced(seq, payload) => Pb::Sequenced(Box::new(pb::Sequenced {
+                seq: *seq,
+                message: carried(payload),
+            })),
+            Payload::Ack(seq) => Pb::Ack(pb::Ack { seq: *seq }),
+            Payload::Nack(nack) => Pb::Nack(nack.into()),
+            Payload::RefreshDeal {
+                epoch,
+                sub_share,
+                commitments,
+            } => Pb::RefreshDeal(pb::RefreshDeal {
+                epoch: *epoch,
+                sub_share: Some(sub_share.into()),
+                commitments: commitments.iter().map(codec::to_bytes).collect(),
+            }),
+            Payload::RefreshAck { epoch, dealer } => Pb::RefreshAck(pb::RefreshAck {
+                epoch: *epoch,
+                dealer: *dealer as u64,
+            }),
+            Payload::ReshareSubShare {
+                epoch,
+                sub_share,
+                commitments,
+                t,
+            } => Pb::ReshareSubShare(pb::ReshareSubShare {
+                epoch: *epoch,
+                sub_share: Some(sub_share.into()),
+                commitments: commitments.iter().map(codec::to_bytes).collect(),
+                t: *t as u64,
+            }),
+            Payload::EpochCommit { epoch, dealers } => Pb::EpochCommit(pb::EpochCommit {
+                epoch: *epoch,
+                dealers: dealers.iter().map(|id| *id as u64).collect(),
+            }),
+            Payload::Complaint {
+                accuser,
+                against,
+                evidence,
+            } => Pb::Complaint(pb::Complaint {
+                accuser: *accuser as u64,
+                against: *against as u64,
+                evidence: Some(evidence.into()),
+            }),
+            Payload::ComplaintResponse(revealed) => Pb::ComplaintResponse(pb::ComplaintResponse {
+                revealed: Some(revealed.into()),
+            }),
+            Payload::Disqualify(against) => Pb::Disqualify(pb::Disqualify {
+                against: *against as u64,
+            }),
+            Payload::Dkg(msg) => Pb::Dkg(msg.into()),
+            Payload::SignRequest {
+                request,
+                message,
+                signers,
+            } => Pb::SignRequest(pb::SignRequest {
+                request: *request,
+                message: message.clone(),
+                signers: signers.iter().map(|id| *id as u64).collect(),
+            }),
+            Payload::NonceCommitment {
+                request,
+                commitment,
+            } => Pb::NonceCommitment(pb::NonceCommitment {
+                request: *request,
+                commitment: codec::to_bytes(commitment),
+            }),
+            Payload::PartialSignature { request, z } => {
+                Pb::PartialSignature(pb::PartialSignature {
+                    request: *request,
+                    z: codec::to_bytes(z),
+                })
+            }
+            Payload::SignatureResult { request, signature } => {
+                Pb::SignatureResult(pb::SignatureResult {
+                    request: *request,
+                    signature: Some(pb::Signature {
+                        r: codec::to_bytes(&signature.r),
+                        z: codec::to_bytes(&signature.z),
+                    }),
+                })
+            }
+            Payload::RefreshRequest => Pb::RefreshRequest(pb::RefreshRequest {}),
+            Payload::RefreshResult { epoch } => Pb::RefreshResult(pb::RefreshResult {
+                moved: epoch.is_some(),
+                epoch: epoch.unwrap_or_default(),
+            }),
+            Payload::RecoveryRequest { helpers } => Pb::RecoveryRequest(pb::RecoveryRequest {
+                helpers: helpers.iter().map(|id| *id as u64).collect(),
+            }),
+            Payload::RecoveryPiece {
+                lost,
+                helpers,
+                piece,
+            } => Pb::RecoveryPiece(pb::RecoveryPiece {
+                lost: *lost as u64,
+                helpers: helpers.iter().map(|id| *id as u64).collect(),
+                piece: codec::to_bytes(piece),
+            }),
+            Payload::RecoveryShare(info) => Pb::RecoveryShare(info.into()),
+            Payload::DkgRequest(config) => Pb::DkgRequest(pb::DkgRequest {
+                p: codec::to_bytes(&config.p),
+                q: codec::to_bytes(&config.q),
+                g: codec::to_bytes(&config.g),
+                t: config.t as u64,
+                players: config.players.iter().map(|id| *id as u64).collect(),
+            }),
+            Payload::DkgResult { public_key } => Pb::DkgResult(pb::DkgResult {
+                generated: public_key.is_some(),
+                public_key: public_key.as_ref().map(codec::to_bytes).unwrap_or_default(),
+            }),
+        }
+    }
+}
+
+impl TryFrom<pb::wire_message::Payload> for Payload {
+    type Error = io::Error;
+
+    fn try_from(payload: pb::wire_message::Payload) -> io::Result<Payload> {
+        use pb::wire_message::Payload as Pb;
+
+        Ok(match payload {
+            Pb::Ping(_) => Payload::Ping,
+            Pb::Heartbeat(_) => Payload::Heartbeat,
+            Pb::ShareRequest(_) => Payload::ShareRequest,
+            Pb::Batch(batch) => Payload::Batch(
+                batch
+                    .messages
+                    .into_iter()
+                    .map(WireMessage::try_from)
+                    .collect::<io::Result<_>>()?,
+            ),
+            Pb::Hello(hello) => Payload::Hello(
+                hello
+                    .versions
+                    .into_iter()
+                    .map(Version::try_from)
+                    .collect::<io::Result<_>>()?,
+            ),
+            Pb::RegShare(info) => Payload::RegShare(info.try_into()?),
+            Pb::ReconstructShare(share) => Payload::ReconstructShare(share.try_into()?),
+            Pb::Broadcast(broadcast) => Payload::Broadcast((*broadcast).try_into()?),
+            Pb::Sequenced(sequenced) => {
+                Payload::Sequenced(sequenced.seq, uncarried(sequenced.message)?)
+            }
+            Pb::Ack(ack) => Payload::Ack(ack.seq),
+            Pb::Nack(nack) => Payload::Nack(nack.try_into()?),
+            Pb::RefreshDeal(deal) => Payload::RefreshDeal {
+                epoch: deal.epoch,
+                sub_share: share(deal.sub_share, "sub share")?,
+                commitments: commitments(&deal.commitments)?,
+            },
+            Pb::RefreshAck(ack) => Payload::RefreshAck {
+                epoch: ack.epoch,
+                dealer: id(ack.dealer, "dealer")?,
+            },
+            Pb::ReshareSubShare(deal) => Payload::ReshareSubShare {
+                epoch: deal.epoch,
+                sub_share: share(deal.sub_share, "sub share")?,
+                commitments: commitments(&deal.commitments)?,
+                t: deal.t.try_into().map_err(|_| invalid("t"))?,
+            },
+            Pb::EpochCommit(commit) => Payload::EpochCommit {
+                epoch: commit.epoch,
+                dealers: commit
+                    .dealers
+                    .into_iter()
+                    .map(|dealer| id(dealer, "dealer"))
+                    .collect::<io::Result<_>>()?,
+            },
+            Pb::Complaint(complaint) => Payload::Complaint {
+                accuser: id(complaint.accuser, "accuser")?,
+                against: id(complaint.against, "against")?,
+                evidence: share(complaint.evidence, "evidence")?,
+            },
+            Pb::ComplaintResponse(response) => {
+                Payload::ComplaintResponse(share(response.revealed, "revealed share")?)
+            }
+            Pb::Disqualify(vote) => Payload::Disqualify(id(vote.against, "against")?),
+            Pb::Dkg(msg) => Payload::Dkg(msg.try_into()?),
+            Pb::SignRequest(request) => Payload::SignRequest {
+                request: request.request,
+                message: request.message,
+                signers: request
+                    .signers
+                    .into_iter()
+                    .map(|signer| id(signer, "signer"))
+                    .collect::<io::Result<_>>()?,
+            },
+            Pb::NonceCommitment(commitment) => Payload::NonceCommitment {
+                request: commitment.request,
+                commitment: biguint(&commitment.commitment, "nonce commitment")?,
+            },
+            Pb::PartialSignature(partial) => Payload::PartialSignature {
+                request: partial.request,
+                z: biguint(&partial.z, "partial signature")?,
+            },
+            Pb::SignatureResult(result) => {
+                let signature = result.signature.ok_or_else(|| invalid("signature"))?;
+                Payload::SignatureResult {
+                    request: result.request,
+                    signature: Signature {
+                        r: biguint(&signature.r, "signature")?,
+                        z: biguint(&signature.z, "signature")?,
+                    },
+                }
+            }
+            Pb::RefreshRequest(_) => Payload::RefreshRequest,
+            Pb::RefreshResult(result) => Payload::RefreshResult {
+                epoch: result.moved.then_some(result.epoch),
+            },
+            Pb::RecoveryRequest(request) => Payload::RecoveryRequest {
+                helpers: request
+                    .helpers
+                    .into_iter()
+                    .map(|helper| id(helper, "helper"))
+                    .collect::<io::Result<_>>()?,
+            },
+            Pb::RecoveryPiece(piece) => Payload::RecoveryPiece {
+                lost: id(piece.lost, "lost player")?,
+                helpers: piece
+                    .helpers
+                    .into_iter()
+                    .map(|helper| id(helper, "helper"))
+                    .collect::<io::Result<_>>()?,
+                piece: biguint(&piece.piece, "recovery piece")?,
+            },
+            Pb::RecoveryShare(info) => Payload::RecoveryShare(info.try_into()?),
+            Pb::DkgRequest(request) => Payload::DkgRequest(DkgConfig {
+                p: biguint(&request.p, "p")?,
+                q: biguint(&request.q, "q")?,
+                g: biguint(&request.g, "g")?,
+                t: request.t.try_into().map_err(|_| invalid("t"))?,
+                players: request
+                    .players
+                    .into_iter()
+                    .map(|player| id(player, "player"))
+                    .collect::<io::Result<_>>()?,
+            }),
+            Pb::DkgResult(result) => Payload::DkgResult {
+                public_key: match result.generated {
+                    true => Some(biguint(&result.public_key, "public key")?),
+                    false => None,
+                },
+            },
+        })
+    }
+}
+
+impl From<&Nack> for pb::Nack {
+    fn from(nack: &Nack) -> pb::Nack {
+        use pb::nack::Reason;
+
+        let reason = match nack {
+            Nack::InvalidShare => Reason::InvalidShare,
+            Nack::UnknownSession => Reason::UnknownSession,
+            Nack::NotReady => Reason::NotReady,
+            Nack::Expired => Reason::Expired,
+        };
+
+        pb::Nack {
+            reason: reason.into(),
+        }
+    }
+}
+
+impl TryFrom<pb::Nack> for Nack {
+    type Error = io::Error;
+
+    fn try_from(nack: pb::Nack) -> io::Result<Nack> {
+        use pb::nack::Reason;
+
+        match Reason::try_from(nack.reason) {
+            Ok(Reason::InvalidShare) => Ok(Nack::InvalidShare),
+            Ok(Reason::UnknownSession) => Ok(Nack::UnknownSession),
+            Ok(Reason::NotReady) => Ok(Nack::NotReady),
+            Ok(Reason::Expired) => Ok(Nack::Expired),
+            Ok(Reason::Unspecified) | Err(_) => Err(invalid("nack reason")),
+        }
+    }
+}
+
+/// A WireMessage with only a payload, as carried inside a Broadcast or Sequenced
+fn carried(payload: &Payload) -> Option<Box<pb::WireMessage>> {
+    Some(Box::new(pb::WireMessage {
+        payload: Some(payload.into()),
+        ..Default::default()
+    }))
+}
+
+fn uncarried(msg: Option<Box<pb::WireMessage>>) -> io::Result<Box<Payload>> {
+    let payload = msg
+        .and_then(|msg| msg.payload)
+        .ok_or_else(|| invalid("carried payload"))?;
+
+    Ok(Box::new(payload.try_into()?))
+}
+
+impl From<&BroadcastMessage> for pb::Broadcast {
+    fn from(broadcast: &BroadcastMessage) -> pb::Broadcast {
+        use pb::broadcast::{Ack, Echo, Kind, Ready, Request};
+
+        let kind = match broadcast {
+            BroadcastMessage::Request { seq, payload } => Kind::Request(Box::new(Request {
+                seq: *seq,
+                message: carried(payload),
+            })),
+            BroadcastMessage::Ack { seq } => Kind::Ack(Ack { seq: *seq }),
+            BroadcastMessage::Echo { origin, payload } => Kind::Echo(Box::new(Echo {
+                origin: *origin as u64,
+                message: carried(payload),
+            })),
+            BroadcastMessage::Ready { origin, payload } => Kind::Ready(Box::new(Ready {
+                origin: *origin as u64,
+                message: carried(payload),
+            })),
+        };
+
+        pb::Broadcast { kind: Some(kind) }
+    }
+}
+
+impl TryFrom<pb::Broadcast> for BroadcastMessage {
+    type Error = io::Error;
+
+    fn try_from(broadcast: pb::Broadcast) -> io::Result<BroadcastMessage> {
+        use pb::broadcast::Kind;
+
+        let origin = |origin: u64| id(origin, "origin");
+        Ok(match broadcast.kind.ok_or_else(|| invalid("broadcast"))? {
+            Kind::Request(request) => BroadcastMessage::Request {
+                seq: request.seq,
+                payload: uncarried(request.message)?,
+            },
+            Kind::Ack(ack) => BroadcastMessage::Ack { seq: ack.seq },
+            Kind::Echo(echo) => BroadcastMessage::Echo {
+                origin: origin(echo.origin)?,
+                payload: uncarried(echo.message)?,
+            },
+            Kind::Ready(ready) => BroadcastMessage::Ready {
+                origin: origin(ready.origin)?,
+                payload: uncarried(ready.message)?,
+            },
+        })
+    }
+}
+
+impl From<&DkgMessage> for pb::Dkg {
+    fn from(msg: &DkgMessage) -> pb::Dkg {
+        use pb::dkg::{Complaint, DealingBroadcast, Kind, Qualified};
+
+        let ids = |ids: &[usize]| ids.iter().map(|id| *id as u64).collect();
+        let kind = match msg {
+            DkgMessage::DealingBroadcast(commitments) => Kind::DealingBroadcast(DealingBroadcast {
+                commitments: commitments.iter().map(codec::to_bytes).collect(),
+            }),
+            DkgMessage::DealingShare(share) => Kind::DealingShare(share.into()),
+            DkgMessage::Complaint(against) => Kind::Complaint(Complaint {
+                against: ids(against),
+            }),
+            DkgMessage::Qualified(dealers) => Kind::Qualified(Qualified {
+                dealers: ids(dealers),
+            }),
+            DkgMessage::PublicKeyShare(y) => Kind::PublicKeyShare(codec::to_bytes(y)),
+        };
+
+        pb::Dkg { kind: Some(kind) }
+    }
+}
+
+impl TryFrom<pb::Dkg> for DkgMessage {
+    type Error = io::Error;
+
+    fn try_from(msg: pb::Dkg) -> io::Result<DkgMessage> {
+        use pb::dkg::Kind;
+
+        let ids = |ids: Vec<u64>, what: &str| {
+            ids.into_iter()
+                .map(|i| id(i, what))
+                .collect::<io::Result<_>>()
+        };
+        Ok(match msg.kind.ok_or_else(|| invalid("dkg"))? {
+            Kind::DealingBroadcast(dealing) => {
+                DkgMessage::DealingBroadcast(commitments(&dealing.commitments)?)
+            }
+            Kind::DealingShare(share) => DkgMessage::DealingShare(share.try_into()?),
+            Kind::Complaint(complaint) => DkgMessage::Complaint(ids(complaint.against, "against")?),
+            Kind::Qualified(qualified) => DkgMessage::Qualified(ids(qualified.dealers, "dealer")?),
+            Kind::PublicKeyShare(y) => DkgMessage::PublicKeyShare(biguint(&y, "public key share")?),
+        })
+    }
+}
+
+impl From<&WireMessage> for pb::WireMessage {
+    fn from(msg: &WireMessage) -> pb::WireMessage {
+        pb::WireMessage {
+            version: Some((&msg.version).into()),
+            session_id: msg.session_id,
+            epoch: msg.epoch,
+            payload: Some((&msg.payload).into()),
+        }
+    }
+}
+
+impl TryFrom<pb::WireMessage> for WireMessage {
+    type Error = io::Error;
+
+    fn try_from(msg: pb::WireMessage) -> io::Result<WireMessage> {
+        let payload = msg.payload.ok_or_else(|| invalid("payload"))?.try_into()?;
+        let version = msg
+            .version
+            .map(Version::try_from)
+            .transpose()?
+            .unwrap_or(rpc::PROTOCOL_VERSION);
+
+        Ok(WireMessage::with_version(
+            version,
+            msg.session_id,
+            msg.epoch,
+            payload,
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use num_bigint::ToBigUint;
+
+    use crate::broadcast::BroadcastMessage;
+    use crate::dkg::DkgMessage;
+    use crate::rpc::{Metadata, Nack, Payload, Share, ShareInfo, Validity, Version, WireMessage};
+    use crate::sign::Signature;
+
+    use super::{decode, encode};
+
+    #[test]
+    fn protobuf_roundtrip() {
+        let share = Share::new(2.to_biguint().unwrap(), 0.to_biguint().unwrap());
+        let msgs = vec![
+            Payload::Ping,
+            Payload::Hello(vec![Version { major: 1, minor: 2 }]),
+            Payload::ReconstructShare(share.clone()),
+            Payload::Broadcast(BroadcastMessage::Echo {
+                origin: 3,
+                payload: Box::new(Payload::ReconstructShare(share.clone())),
+            }),
+            Payload::RegShare(
+                ShareInfo::new(
+                    share.clone(),
+                    3.to_biguint().unwrap(),
+                    vec![5.to_biguint().unwrap(), 1.to_biguint().unwrap()],
+                    11.to_biguint().unwrap(),
+                    5.to_biguint().unwrap(),
+                    2,
+                )
+                .with_metadata(Metadata {
+                    purpose: Some("backup".to_string()),
+                    created: Some(1_700_000_000),
+                    ..Metadata::default()
+                })
+                .with_validity(Validity {
+                    epoch: 2,
+                    not_before: None,
+                    not_after: Some(1_800_000_000),
+                }),
+            ),
+            Payload::Nack(Nack::UnknownSession),
+            Payload::Nack(Nack::Expired),
+            Payload::ShareRequest,
+            Payload::Batch(vec![WireMessage::new(3, 0, Payload::ShareRequest)]),
+            Payload::Dkg(DkgMessage::Qualified(vec![1, 3])),
+            Payload::Complaint {
+                accuser: 2,
+                against: 0,
+                evidence: share.clone(),
+            },
+            Payload::ReshareSubShare {
+                epoch: 2,
+                sub_share: share,
+                commitments: vec![5.to_biguint().unwrap(), 1.to_biguint().unwrap()],
+                t: 2,
+            },
+            Payload::SignatureResult {
+                request: 9,
+                signature: Signature {
+                    r: 3.to_biguint().unwrap(),
+                    z: 0.to_biguint().unwrap(),
+                },
+            },
+            Payload::RefreshResult { epoch: Some(0) },
+            Payload::DkgResult { public_key: None },
+            Payload::RecoveryPiece {
+                lost: 2,
+                helpers: vec![1, 3],
+                piece: 4.to_biguint().unwrap(),
+            },
+        ];
+
+        for msg in msgs.into_iter().map(|p| WireMessage::new(7, 1, p)) {
+            assert_eq!(msg, decode(&encode(&msg)).unwrap());
+        }
+    }
+
+    #[test]
+    fn encodes_share_as_schema_bytes() {
+        let share = Share::new(2.to_biguint().unwrap(), 0x0102.to_biguint().unwrap());
+        let msg = WireMessage::new(7, 1, Payload::ReconstructShare(share));
+
+        let expected = vec![
+            0x0a, 0x02, 0x08, 0x01, // version { major: 1 }
+            0x10, 0x07, // session_id: 7
+            0x18, 0x01, // epoch: 1
+            0x3a, 0x07, 0x0a, 0x01, 0x02, 0x12, 0x02, 0x01, 0x02, // reconstruct_share
+        ];
+        assert_eq!(expected, encode(&msg));
+    }
+}
@@ -0,0 +1,374 @@
+use num_bigint::BigUint;
+
+use crate::broadcast::BroadcastMessage;
+use crate::dealer::DEALER_ID;
+use crate::dkg::{DkgConfig, DkgMessage};
+use crate::rpc::{Nack, Payload, Share, ShareInfo, Version, WireMessage};
+use crate::sign;
+
+/// Messages only the dealer sends, to the players of its dealing
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DealerToPlayer {
+    /// A player's share of the dealing
+    RegShare(ShareInfo),
+    /// The share of a player that complained, revealed to every player
+    ComplaintResponse(Share),
+    /// Move the dealing to its next epoch with a refresh
+    RefreshRequest,
+    /// Generate a key as the session with the players of the config
+    DkgRequest(DkgConfig),
+}
+
+/// Messages players send each other within a dealing
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PlayerToPlayer {
+    ReconstructShare(Share),
+    ShareRequest,
+    RefreshDeal {
+        epoch: u64,
+        sub_share: Share,
+        commitments: Vec<BigUint>,
+    },
+    RefreshAck {
+        epoch: u64,
+        dealer: usize,
+    },
+    ReshareSubShare {
+        epoch: u64,
+        sub_share: Share,
+        commitments: Vec<BigUint>,
+        t: usize,
+    },
+    EpochCommit {
+        epoch: u64,
+        dealers: Vec<usize>,
+    },
+    Complaint {
+        accuser: usize,
+        against: usize,
+        evidence: Share,
+    },
+    Disqualify(usize),
+    Dkg(DkgMessage),
+    SignRequest {
+        request: u64,
+        message: Vec<u8>,
+        signers: Vec<usize>,
+    },
+    NonceCommitment {
+        request: u64,
+        commitment: BigUint,
+    },
+    PartialSignature {
+        request: u64,
+        z: BigUint,
+    },
+    SignatureResult {
+        request: u64,
+        signature: sign::Signature,
+    },
+    RefreshResult {
+        epoch: Option<u64>,
+    },
+    RecoveryRequest {
+        helpers: Vec<usize>,
+    },
+    RecoveryPiece {
+        lost: usize,
+        helpers: Vec<usize>,
+        piece: BigUint,
+    },
+    RecoveryShare(ShareInfo),
+    DkgResult {
+        public_key: Option<BigUint>,
+    },
+}
+
+impl PlayerToPlayer {
+    /// Epoch of the share the message needs us to hold, None if it needs no share
+    ///
+    /// Deals and their acks move a share to the epoch they carry, so they need the one before
+    pub fn share_epoch(&self, epoch: u64) -> Option<u64> {
+        match self {
+            PlayerToPlayer::ReconstructShare(_)
+            | PlayerToPlayer::ShareRequest
+            | PlayerToPlayer::SignRequest { .. }
+            | PlayerToPlayer::RecoveryPiece { .. } => Some(epoch),
+            PlayerToPlayer::RefreshDeal { epoch: next, .. }
+            | PlayerToPlayer::ReshareSubShare { epoch: next, .. }
+            | PlayerToPlayer::RefreshAck { epoch: next, .. } => Some(next.saturating_sub(1)),
+            PlayerToPlayer::EpochCommit { .. }
+            | PlayerToPlayer::Complaint { .. }
+            | PlayerToPlayer::Disqualify(_)
+            | PlayerToPlayer::Dkg(_)
+            | PlayerToPlayer::NonceCommitment { .. }
+            | PlayerToPlayer::PartialSignature { .. }
+            | PlayerToPlayer::SignatureResult { .. }
+            | PlayerToPlayer::RefreshResult { .. }
+            | PlayerToPlayer::RecoveryRequest { .. }
+            | PlayerToPlayer::RecoveryShare(_)
+            | PlayerToPlayer::DkgResult { .. } => None,
+        }
+    }
+}
+
+/// Messages about the link between two peers rather than any dealing, sent by anyone
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Control {
+    Ping,
+    Heartbeat,
+    Hello(Vec<Version>),
+    Broadcast(BroadcastMessage),
+    Sequenced(u64, Box<Payload>),
+    Ack(u64),
+    Nack(Nack),
+    Batch(Vec<WireMessage>),
+}
+
+/// A protocol message sorted by who may send it
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Message {
+    Dealer(DealerToPlayer),
+    Player(PlayerToPlayer),
+    Control(Control),
+}
+
+impl Message {
+    /// Sorts a payload `from` sent, giving it back if `from` may not send it: the dealer's
+    /// messages from a player or a player's messages from the dealer
+    pub fn route(from: usize, payload: Payload) -> Result<Message, Payload> {
+        let is_dealer = from == DEALER_ID;
+        let msg = match payload {
+            Payload::Ping => Message::Control(Control::Ping),
+            Payload::Heartbeat => Message::Control(Control::Heartbeat),
+            Payload::Hello(supported) => Message::Control(Control::Hello(supported)),
+            Payload::Broadcast(broadcast) => Message::Control(Control::Broadcast(broadcast)),
+            Payload::Sequenced(seq, payload) => Message::Control(Control::Sequenced(seq, payload)),
+            Payload::Ack(seq) => Message::Control(Control::Ack(seq)),
+            Payload::Nack(nack) => Message::Control(Control::Nack(nack)),
+            Payload::Batch(msgs) => Message::Control(Control::Batch(msgs)),
+            payload @ (Payload::RegShare(_)
+            | Payload::ComplaintResponse(_)
+            | Payload::RefreshRequest
+            | Payload::DkgRequest(_))
+                if !is_dealer =>
+            {
+                return Err(payload)
+            }
+            Payload::RegShare(share_info) => Message::Dealer(DealerToPlayer::RegShare(share_info)),
+            Payload::ComplaintResponse(share) => {
+                Message::Dealer(DealerToPlayer::ComplaintResponse(share))
+            }
+            Payload::RefreshRequest => Message::Dealer(DealerToPlayer::RefreshRequest),
+            Payload::DkgRequest(config) => Message::Dealer(DealerToPlayer::DkgRequest(config)),
+            payload if is_dealer => return Err(payload),
+            Payload::ReconstructShare(share) => {
+                Message::Player(PlayerToPlayer::ReconstructShare(share))
+            }
+            Payload::ShareRequest => Message::Player(PlayerToPlayer::ShareRequest),
+            Payload::RefreshDeal {
+                epoch,
+                sub_share,
+                commitments,
+            } => Message::Player(PlayerToPlayer::RefreshDeal {
+                epoch,
+                sub_share,
+                commitments,
+            }),
+            Payload::RefreshAck { epoch, dealer } => {
+                Message::Player(PlayerToPlayer::RefreshAck { epoch, dealer })
+            }
+            Payload::ReshareSubShare {
+                epoch,
+                sub_share,
+                commitments,
+                t,
+            } => Message::Player(PlayerToPlayer::ReshareSubShare {
+                epoch,
+                sub_share,
+                commitments,
+                t,
+            }),
+            Payload::EpochCommit { epoch, dealers } => {
+                Message::Player(PlayerToPlayer::EpochCommit { epoch, dealers })
+            }
+            Payload::Complaint {
+                accuser,
+                against,
+                evidence,
+            } => Message::Player(PlayerToPlayer::Complaint {
+                accuser,
+                against,
+                evidence,
+            }),
+            Payload::Disqualify(against) => Message::Player(PlayerToPlayer::Disqualify(against)),
+            Payload::Dkg(msg) => Message::Player(PlayerToPlayer::Dkg(msg)),
+            Payload::SignRequest {
+                request,
+                message,
+                signers,
+            } => Message::Player(PlayerToPlayer::SignRequest {
+                request,
+                message,
+                signers,
+            }),
+            Payload::NonceCommitment {
+                request,
+                commitment,
+            } => Message::Player(PlayerToPlayer::NonceCommitment {
+                request,
+                commitment,
+            }),
+            Payload::PartialSignature { request, z } => {
+                Message::Player(PlayerToPlayer::PartialSignature { request, z })
+            }
+            Payload::SignatureResult { request, signature } => {
+                Message::Player(PlayerToPlayer::SignatureResult { request, signature })
+            }
+            Payload::RefreshResult { epoch } => {
+                Message::Player(PlayerToPlayer::RefreshResult { epoch })
+            }
+            Payload::RecoveryRequest { helpers } => {
+                Message::Player(PlayerToPlayer::RecoveryRequest { helpers })
+            }
+            Payload::RecoveryPiece {
+                lost,
+                helpers,
+                piece,
+            } => Message::Player(PlayerToPlayer::RecoveryPiece {
+                lost,
+                helpers,
+                piece,
+            }),
+            Payload::RecoveryShare(share_info) => {
+                Message::Player(PlayerToPlayer::RecoveryShare(share_info))
+            }
+            Payload::DkgResult { public_key } => {
+                Message::Player(PlayerToPlayer::DkgResult { public_key })
+            }
+        };
+
+        Ok(msg)
+    }
+}
+
+/// Where a Player stands in one dealing, deciding which of the dealing's messages it takes
+///
+/// ```text
+/// Registering --RegShare--> Holding(e) --renewal--> Holding(e + 1)
+///      \                        |
+///       +------disqualify-------+--> Disqualified
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Phase {
+    /// No share of the dealing held yet
+    Registering,
+    /// Holding our share of the given epoch
+    Holding(u64),
+    /// The dealer was disqualified and the dealing dropped
+    Disqualified,
+}
+
+/// Why a message was rejected for the phase of its dealing
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OutOfPhase {
+    /// The message needs a share we do not hold
+    UnknownSession,
+    /// The message needs a share of an epoch after the one we hold
+    NotReady { held: u64 },
+    /// The message is for an epoch we hold already or moved past
+    Stale { held: u64 },
+    /// The dealer of the dealing was disqualified
+    Disqualified,
+}
+
+impl OutOfPhase {
+    /// The Nack telling the sender, if it is worth telling
+    pub fn nack(&self) -> Option<Nack> {
+        match self {
+            OutOfPhase::UnknownSession | OutOfPhase::Disqualified => Some(Nack::UnknownSession),
+            OutOfPhase::NotReady { .. } => Some(Nack::NotReady),
+            OutOfPhase::Stale { .. } => None,
+        }
+    }
+}
+
+impl Phase {
+    /// Takes a message of the dealer for `epoch`: a share is taken until the dealer is
+    /// disqualified, replacing any share of an older epoch
+    pub fn admit_dealer(&self, msg: &DealerToPlayer, epoch: u64) -> Result<(), OutOfPhase> {
+        match (self, msg) {
+            (Phase::Disqualified, _) => Err(OutOfPhase::Disqualified),
+            (Phase::Holding(held), DealerToPlayer::RegShare(_)) if *held >= epoch => {
+                Err(OutOfPhase::Stale { held: *held })
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Takes a message of a player for `epoch` if it needs no share or the share it needs is
+    /// the one we hold
+    pub fn admit_player(&self, msg: &PlayerToPlayer, epoch: u64) -> Result<(), OutOfPhase> {
+        let needed = match msg.share_epoch(epoch) {
+            Some(needed) => needed,
+            None => return Ok(()),
+        };
+
+        match *self {
+            Phase::Holding(held) if held == needed => Ok(()),
+            Phase::Holding(held) if held < needed => Err(OutOfPhase::NotReady { held }),
+            Phase::Holding(held) => Err(OutOfPhase::Stale { held }),
+            Phase::Registering | Phase::Disqualified => Err(OutOfPhase::UnknownSession),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use num_bigint::BigUint;
+
+    use crate::dealer::DEALER_ID;
+    use crate::rpc::{Nack, Payload, Share};
+
+    use super::{Message, OutOfPhase, Phase, PlayerToPlayer};
+
+    #[test]
+    fn route_by_sender() {
+        let share = Share::new(BigUint::from(2_usize), BigUint::from(7_usize));
+
+        assert!(Message::route(DEALER_ID, Payload::ComplaintResponse(share.clone())).is_ok());
+        assert!(Message::route(2, Payload::ComplaintResponse(share.clone())).is_err());
+        assert!(Message::route(2, Payload::ReconstructShare(share.clone())).is_ok());
+        assert!(Message::route(DEALER_ID, Payload::ReconstructShare(share)).is_err());
+        assert!(Message::route(DEALER_ID, Payload::Ping).is_ok());
+        assert!(Message::route(DEALER_ID, Payload::RefreshRequest).is_ok());
+        assert!(Message::route(2, Payload::RefreshRequest).is_err());
+    }
+
+    #[test]
+    fn phase_rejects_out_of_phase_messages() {
+        let share = Share::new(BigUint::from(2_usize), BigUint::from(7_usize));
+        let reveal = PlayerToPlayer::ReconstructShare(share.clone());
+        let deal = PlayerToPlayer::RefreshDeal {
+            epoch: 2,
+            sub_share: share,
+            commitments: vec![],
+        };
+
+        assert_eq!(
+            Err(OutOfPhase::UnknownSession),
+            Phase::Registering.admit_player(&reveal, 0)
+        );
+        assert_eq!(Ok(()), Phase::Holding(1).admit_player(&reveal, 1));
+        assert_eq!(
+            Err(OutOfPhase::NotReady { held: 1 }),
+            Phase::Holding(1).admit_player(&reveal, 2)
+        );
+        assert_eq!(
+            Err(OutOfPhase::Stale { held: 1 }),
+            Phase::Holding(1).admit_player(&reveal, 0)
+        );
+        assert_eq!(Ok(()), Phase::Holding(1).admit_player(&deal, 1));
+        assert_eq!(Some(Nack::UnknownSession), OutOfPhase::Disqualified.nack());
+    }
+}
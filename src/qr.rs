@@ -0,0 +1,108 @@
+//! Share files as QR codes, for handing shares out on paper to machines that stay offline
+//!
+//! A code holds the share file of `format` as compact JSON, so a scanned code is a complete
+//! share file.
+
+use std::fs;
+use std::io::{self, Cursor};
+use std::path::Path;
+
+use image::{ImageFormat, Luma};
+use qrcode::render::svg;
+use qrcode::QrCode;
+
+use crate::format::{self, ShareFile};
+use crate::rpc::ShareInfo;
+
+/// Pixels of a module, the smallest square of a code
+const MODULE_SIZE: u32 = 8;
+
+fn invalid_data<E: ToString>(err: E) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, err.to_string())
+}
+
+fn code_of(info: &ShareInfo) -> io::Result<QrCode> {
+    let json = serde_json::to_string(&ShareFile::try_from(info)?)?;
+
+    QrCode::new(json).map_err(invalid_data)
+}
+
+/// The QR code of `info` as an SVG document
+pub fn to_svg(info: &ShareInfo) -> io::Result<String> {
+    Ok(code_of(info)?
+        .render::<svg::Color>()
+        .module_dimensions(MODULE_SIZE, MODULE_SIZE)
+        .build())
+}
+
+/// The QR code of `info` as a PNG image
+pub fn to_png(info: &ShareInfo) -> io::Result<Vec<u8>> {
+    let image = code_of(info)?
+        .render::<Luma<u8>>()
+        .module_dimensions(MODULE_SIZE, MODULE_SIZE)
+        .build();
+
+    let mut png = vec![];
+    image
+        .write_to(&mut Cursor::new(&mut png), ImageFormat::Png)
+        .map_err(invalid_data)?;
+
+    Ok(png)
+}
+
+/// The share in the first QR code found in a PNG image
+pub fn from_png(png: &[u8]) -> io::Result<ShareInfo> {
+    let image = image::load_from_memory_with_format(png, ImageFormat::Png)
+        .map_err(invalid_data)?
+        .to_luma8();
+    let mut prepared = rqrr::PreparedImage::prepare_from_greyscale(
+        image.width() as usize,
+        image.height() as usize,
+        |x, y| image.get_pixel(x as u32, y as u32)[0],
+    );
+
+    let grid = prepared
+        .detect_grids()
+        .into_iter()
+        .next()
+        .ok_or_else(|| invalid_data("no QR code found"))?;
+    let (_, json) = grid.decode().map_err(invalid_data)?;
+
+    format::read_share(json.as_bytes())
+}
+
+/// Write the QR code of `info` at `path`, as SVG if its extension is svg and PNG otherwise
+pub fn save_share<P: AsRef<Path>>(path: P, info: &ShareInfo) -> io::Result<()> {
+    let path = path.as_ref();
+    if path.extension().is_some_and(|ext| ext == "svg") {
+        fs::write(path, to_svg(info)?)
+    } else {
+        fs::write(path, to_png(info)?)
+    }
+}
+
+/// Read the share in the PNG image at `path`, naming it in any error
+pub fn load_share<P: AsRef<Path>>(path: P) -> io::Result<ShareInfo> {
+    let path = path.as_ref();
+
+    fs::read(path)
+        .and_then(|png| from_png(&png))
+        .map_err(|err| io::Error::new(err.kind(), format!("{}: {}", path.display(), err)))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::dealer::Dealer;
+    use crate::rpc::ShareInfo;
+
+    use super::{from_png, to_png, to_svg};
+
+    #[test]
+    fn share_survives_qr_code() {
+        let dealer = Dealer::new(5, 3, 1234).unwrap();
+        let info = ShareInfo::with_params(dealer.shares[1].clone(), dealer.params());
+
+        assert_eq!(info, from_png(&to_png(&info).unwrap()).unwrap());
+        assert!(to_svg(&info).unwrap().starts_with("<?xml"));
+    }
+}
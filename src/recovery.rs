@@ -0,0 +1,201 @@
+//! Peer-assisted recovery of a lost share: helpers holding shares of a dealing regenerate the
+//! share of a player that lost its own, without any of them learning it or each other's
+//!
+//! Every helper j weighs its share s_j by its Lagrange coefficient at the lost index i over the
+//! helpers, splits λ_j(i) * s_j into random pieces summing to it and sends one to each helper.
+//! Each helper sends the sum of the pieces it got to the lost player, and those sums add up to
+//! s_i, which the lost player checks against the dealing's commitments.
+
+use std::collections::{HashMap, HashSet};
+use std::io;
+
+use num_bigint::BigUint;
+use num_primes::RandBigInt;
+use rand::{CryptoRng, RngCore};
+
+use crate::rpc::{Share, ShareInfo};
+use crate::vss;
+
+/// A helper's part in regenerating the share of a lost player
+#[derive(Debug)]
+pub struct Recovery {
+    helpers: Vec<usize>,
+    /// pieces of every helper's contribution sent to us, by helper
+    pieces: HashMap<usize, BigUint>,
+}
+
+impl Recovery {
+    /// Return the recovery of the share of `lost` with `helpers`, None unless they are at least
+    /// the threshold of the dealing in `info`, distinct, and without `lost`
+    pub fn new(info: &ShareInfo, lost: usize, helpers: Vec<usize>) -> Option<Recovery> {
+        let distinct: HashSet<&usize> = helpers.iter().collect();
+        if lost == 0
+            || helpers.contains(&lost)
+            || helpers.contains(&0)
+            || distinct.len() != helpers.len()
+            || helpers.len() < info.t()
+        {
+            return None;
+        }
+
+        Some(Recovery {
+            helpers,
+            pieces: HashMap::new(),
+        })
+    }
+
+    pub fn helpers(&self) -> &[usize] {
+        &self.helpers
+    }
+
+    /// The pieces of this helper's contribution to the share of `lost`, one per helper, masked
+    /// with randomness from `rng`
+    pub fn contribute<R: RngCore + CryptoRng>(
+        &self,
+        info: &ShareInfo,
+        lost: usize,
+        rng: &mut R,
+    ) -> Vec<(usize, BigUint)> {
+        let q = info.q();
+        let x = info.share().index();
+        let lambda = lagrange_coefficient_at(&BigUint::from(lost), x, &self.helpers, q);
+        let contribution = info.share().value() * lambda % q;
+
+        // every piece but the last is random, the last makes them sum to the contribution
+        let mut pieces: Vec<BigUint> = (1..self.helpers.len())
+            .map(|_| rng.gen_biguint_below(q))
+            .collect();
+        let sum = pieces.iter().sum::<BigUint>() % q;
+        pieces.push((contribution + q - sum) % q);
+
+        self.helpers.iter().copied().zip(pieces).collect()
+    }
+
+    /// Keeps the piece `from` sent, returns false if `from` is not a helper
+    pub fn receive(&mut self, from: usize, piece: BigUint) -> bool {
+        if !self.helpers.contains(&from) {
+            return false;
+        }
+        self.pieces.insert(from, piece);
+
+        true
+    }
+
+    /// The sum of the pieces of every helper, None until all of them arrived
+    pub fn sum(&self, q: &BigUint) -> Option<BigUint> {
+        if self.pieces.len() < self.helpers.len() {
+            return None;
+        }
+
+        Some(self.pieces.values().sum::<BigUint>() % q)
+    }
+}
+
+/// The Lagrange coefficient of `x_j` among the points `ids` at `x` over primefield q
+///
+/// Moving every point by -x makes it the coefficient at 0
+fn lagrange_coefficient_at(x: &BigUint, x_j: &BigUint, ids: &[usize], q: &BigUint) -> BigUint {
+    let shift = |id: &BigUint| (id + q - x % q) % q;
+    let xs: Vec<BigUint> = ids.iter().map(|id| shift(&BigUint::from(*id))).collect();
+
+    vss::lagrange_coefficient(&shift(x_j), &xs, q)
+}
+
+/// The share of `lost` from the sums helpers sent it, each as a share of `lost` along with the
+/// helper's public parameters
+///
+/// Every helper must send the same parameters, and the share must verify against their
+/// commitments
+pub fn combine(lost: usize, sums: &[(usize, ShareInfo)]) -> io::Result<ShareInfo> {
+    let invalid = |msg: String| io::Error::new(io::ErrorKind::InvalidData, msg);
+    let (_, first) = sums
+        .first()
+        .ok_or_else(|| invalid("no helper answered".to_string()))?;
+
+    for (helper, info) in sums {
+        if info.params() != first.params() {
+            return Err(invalid(format!(
+                "helper {} sent parameters of another dealing",
+                helper
+            )));
+        }
+        if *info.share().index() != BigUint::from(lost) {
+            return Err(invalid(format!(
+                "helper {} sent a share of index {}",
+                helper,
+                info.share().index()
+            )));
+        }
+    }
+    if sums.len() < first.t() {
+        return Err(invalid(format!(
+            "{} helpers answered, {} needed",
+            sums.len(),
+            first.t()
+        )));
+    }
+
+    let value = sums
+        .iter()
+        .map(|(_, info)| info.share().value())
+        .sum::<BigUint>()
+        % first.q();
+    let recovered = ShareInfo::with_params(
+        Share::new(BigUint::from(lost), value),
+        first.params().clone(),
+    );
+    if !recovered.verify() {
+        return Err(invalid(
+            "recovered share does not verify, a helper sent a wrong sum".to_string(),
+        ));
+    }
+
+    Ok(recovered)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::dealer::Dealer;
+    use crate::rpc::{Share, ShareInfo};
+
+    use super::{combine, Recovery};
+
+    #[test]
+    fn helpers_regenerate_a_lost_share() {
+        let dealer = Dealer::new(5, 3, 1234).unwrap();
+        let info =
+            |id: usize| ShareInfo::with_params(dealer.shares[id - 1].clone(), dealer.params());
+        let (lost, helpers) = (2, vec![1, 4, 5]);
+        assert!(Recovery::new(&info(1), lost, vec![1, 4]).is_none());
+        assert!(Recovery::new(&info(1), lost, vec![1, 2, 4]).is_none());
+
+        let mut recoveries: Vec<Recovery> = helpers
+            .iter()
+            .map(|_| Recovery::new(&info(1), lost, helpers.clone()).unwrap())
+            .collect();
+        for &from in &helpers {
+            let pieces = recoveries[0].contribute(&info(from), lost, &mut rand::thread_rng());
+            for (to, piece) in pieces {
+                let i = helpers.iter().position(|id| *id == to).unwrap();
+                assert!(recoveries[i].receive(from, piece));
+            }
+        }
+
+        let sums: Vec<(usize, ShareInfo)> = helpers
+            .iter()
+            .zip(&recoveries)
+            .map(|(&id, recovery)| {
+                let sum = recovery.sum(&dealer.params().q).unwrap();
+                let share = Share::new(lost.into(), sum);
+
+                (id, ShareInfo::with_params(share, dealer.params()))
+            })
+            .collect();
+        assert_eq!(info(lost), combine(lost, &sums).unwrap());
+
+        let mut wrong = sums.clone();
+        let share = Share::new(lost.into(), wrong[0].1.share().value() + 1_u32);
+        wrong[0].1 = ShareInfo::with_params(share, dealer.params());
+        assert!(combine(lost, &wrong).is_err());
+    }
+}
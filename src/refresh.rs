@@ -0,0 +1,347 @@
+use std::collections::{HashMap, HashSet};
+
+use num::{One, Zero};
+use num_bigint::BigUint;
+use num_primes::RandBigInt;
+use rand::{CryptoRng, RngCore};
+
+#[cfg(feature = "network")]
+use crate::rpc::Payload;
+use crate::rpc::{Share, ShareInfo};
+use crate::vss;
+
+/// How a dealing moves to its next epoch
+///
+/// Either way every player deals a polynomial to every other player and combines the points it
+/// receives into its new share, so shares leaked in an older epoch are of no use with newer ones
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Renewal {
+    /// Every player adds a sharing of zero to its share, keeping the secret and threshold
+    Refresh,
+    /// Every player reshares its own share, the new shares interpolate to the same secret with
+    /// threshold t
+    Reshare(usize),
+}
+
+impl Renewal {
+    /// Threshold of the dealing in `info` once renewed
+    fn t(&self, info: &ShareInfo) -> usize {
+        match self {
+            Renewal::Refresh => info.t(),
+            Renewal::Reshare(t) => *t,
+        }
+    }
+}
+
+/// A player's point on another player's renewal polynomial, with commitments to that polynomial
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Deal {
+    pub sub_share: Share,
+    pub commitments: Vec<BigUint>,
+}
+
+impl Deal {
+    /// The message carrying this deal to its player, moving the dealing to `epoch`
+    #[cfg(feature = "network")]
+    pub fn into_payload(self, epoch: u64, renewal: Renewal) -> Payload {
+        let Deal {
+            sub_share,
+            commitments,
+        } = self;
+
+        match renewal {
+            Renewal::Refresh => Payload::RefreshDeal {
+                epoch,
+                sub_share,
+                commitments,
+            },
+            Renewal::Reshare(t) => Payload::ReshareSubShare {
+                epoch,
+                sub_share,
+                commitments,
+                t,
+            },
+        }
+    }
+}
+
+/// A player's progress moving one dealing to its next epoch
+///
+/// The move completes once the player has verified a deal from every player and every player
+/// has acknowledged verifying every deal, so all of them combine the same deals
+#[derive(Debug, Default)]
+pub struct Refresh {
+    renewal: Option<Renewal>,
+    dealt: bool,
+    /// verified deals to this player, by dealer
+    deals: HashMap<usize, Deal>,
+    /// players that verified each deal, by dealer
+    acks: HashMap<usize, HashSet<usize>>,
+}
+
+impl Refresh {
+    /// The renewal in progress, if any
+    pub fn renewal(&self) -> Option<Renewal> {
+        self.renewal
+    }
+
+    /// Join `renewal`, returns false if another renewal is already in progress
+    pub fn start(&mut self, renewal: Renewal) -> bool {
+        *self.renewal.get_or_insert(renewal) == renewal
+    }
+
+    /// Deals this player's polynomial to `players` once, returns each player's deal
+    ///
+    /// For a refresh the polynomial hides zero, for a reshare the share held in `info`. The other
+    /// coefficients are drawn from `rng`
+    pub fn deal<R: RngCore + CryptoRng>(
+        &mut self,
+        info: &ShareInfo,
+        players: &[usize],
+        rng: &mut R,
+    ) -> Option<Vec<(usize, Deal)>> {
+        let renewal = self.renewal?;
+        if self.dealt {
+            return None;
+        }
+        self.dealt = true;
+
+        let a_0 = match renewal {
+            Renewal::Refresh => BigUint::zero(),
+            Renewal::Reshare(_) => info.share().value().clone(),
+        };
+        let a: Vec<BigUint> = std::iter::once(a_0)
+            .chain((1..renewal.t(info)).map(|_| rng.gen_biguint_below(info.q())))
+            .collect();
+        let commitments = vss::generate_commitments(&a, info.g(), info.p());
+        let max = players.iter().copied().max().unwrap_or(0);
+
+        let deals = vss::generate_shares(&a, max, info.q())
+            .into_iter()
+            .zip(1..)
+            .filter(|(_, id)| players.contains(id))
+            .map(|(sub_share, id)| {
+                let commitments = commitments.clone();
+                (
+                    id,
+                    Deal {
+                        sub_share,
+                        commitments,
+                    },
+                )
+            })
+            .collect();
+
+        Some(deals)
+    }
+
+    /// Verifies the deal `dealer` sent to player `id` and keeps it, returns false if it is
+    /// invalid
+    ///
+    /// A refresh deal must commit to zero, a reshare deal to the dealer's own share under the
+    /// commitments in `info`
+    pub fn receive(&mut self, info: &ShareInfo, dealer: usize, id: usize, deal: Deal) -> bool {
+        let renewal = match self.renewal {
+            Some(renewal) => renewal,
+            None => return false,
+        };
+        let hidden = match renewal {
+            Renewal::Refresh => BigUint::one(),
+            Renewal::Reshare(_) => {
+                vss::commitment_at(&BigUint::from(dealer), info.commitments(), info.p())
+            }
+        };
+
+        let Deal {
+            sub_share,
+            commitments,
+        } = &deal;
+        let valid = *sub_share.index() == BigUint::from(id)
+            && commitments.len() == renewal.t(info)
+            && commitments.first() == Some(&hidden)
+            && vss::verify_share(
+                sub_share.index(),
+                sub_share.value(),
+                info.g(),
+                commitments,
+                info.p(),
+            );
+        if valid {
+            self.deals.insert(dealer, deal);
+        }
+
+        valid
+    }
+
+    /// Records that `from` verified the deal of `dealer`
+    pub fn ack(&mut self, dealer: usize, from: usize) {
+        self.acks.entry(dealer).or_default().insert(from);
+    }
+
+    /// Whether every player in `players` dealt and acknowledged every deal
+    pub fn is_complete(&self, players: &[usize]) -> bool {
+        players.iter().all(|dealer| {
+            self.deals.contains_key(dealer)
+                && self
+                    .acks
+                    .get(dealer)
+                    .is_some_and(|acks| players.iter().all(|id| acks.contains(id)))
+        })
+    }
+
+    /// Combines the deals of `players` with the share in `info` into the share of the next
+    /// epoch, keeping its metadata and validity window
+    ///
+    /// Must only be called once `is_complete`
+    pub fn commit(&self, info: &ShareInfo, players: &[usize]) -> ShareInfo {
+        let (p, q) = (info.p(), info.q());
+        let deals: Vec<&Deal> = players.iter().map(|dealer| &self.deals[dealer]).collect();
+
+        let (value, commitments, t) = match self.renewal {
+            Some(Renewal::Reshare(t)) => {
+                // the new share is the dealers' sub shares interpolated at 0
+                let xs: Vec<BigUint> = players.iter().map(|&id| BigUint::from(id)).collect();
+                let lambdas =
+                    vss::lagrange_coefficients(&xs, q).expect("shares are dealt over a prime q");
+
+                let value = deals
+                    .iter()
+                    .zip(&lambdas)
+                    .map(|(deal, lambda)| deal.sub_share.value() * lambda)
+                    .sum::<BigUint>()
+                    % q;
+                let commitments = (0..t)
+                    .map(|k| {
+                        deals
+                            .iter()
+                            .zip(&lambdas)
+                            .fold(BigUint::one(), |c, (deal, lambda)| {
+                                c * deal.commitments[k].modpow(lambda, p) % p
+                            })
+                    })
+                    .collect();
+
+                (value, commitments, t)
+            }
+            _ => {
+                // adding sharings of zero changes every share but not the secret
+                let value = deals
+                    .iter()
+                    .fold(info.share().value().clone(), |value, deal| {
+                        (value + deal.sub_share.value()) % q
+                    });
+                let commitments = info
+                    .commitments()
+                    .iter()
+                    .enumerate()
+                    .map(|(k, c)| {
+                        deals
+                            .iter()
+                            .fold(c.clone(), |c, deal| c * &deal.commitments[k] % p)
+                    })
+                    .collect();
+
+                (value, commitments, info.t())
+            }
+        };
+
+        ShareInfo::new(
+            Share::new(info.share().index().clone(), value),
+            info.g().clone(),
+            commitments,
+            p.clone(),
+            q.clone(),
+            t,
+        )
+        .with_metadata(info.metadata().clone())
+        .with_validity(info.validity().next())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use num::ToPrimitive;
+
+    use crate::dealer::Dealer;
+    use crate::rpc::{Share, ShareInfo};
+    use crate::vss;
+
+    use super::{Refresh, Renewal};
+
+    /// Renews every share of a dealing of 1234 to 5 players with threshold 3
+    fn renew(renewal: Renewal) -> Vec<ShareInfo> {
+        let dealer = Dealer::new(5, 3, 1234).unwrap();
+        let players: Vec<usize> = (1..=5).collect();
+        let infos: Vec<ShareInfo> = dealer
+            .shares
+            .iter()
+            .map(|share| {
+                ShareInfo::new(
+                    share.clone(),
+                    dealer.g.clone(),
+                    dealer.c.clone(),
+                    dealer.p.clone(),
+                    dealer.q.clone(),
+                    dealer.t,
+                )
+            })
+            .collect();
+
+        let mut refreshes: Vec<Refresh> = players.iter().map(|_| Refresh::default()).collect();
+        refreshes
+            .iter_mut()
+            .for_each(|refresh| assert!(refresh.start(renewal)));
+        for (dealer, info) in players.iter().zip(&infos) {
+            for (id, deal) in refreshes[dealer - 1]
+                .deal(info, &players, &mut rand::thread_rng())
+                .unwrap()
+            {
+                assert!(refreshes[id - 1].receive(&infos[id - 1], *dealer, id, deal));
+                players
+                    .iter()
+                    .for_each(|from| refreshes[id - 1].ack(*dealer, *from));
+            }
+        }
+
+        refreshes
+            .iter()
+            .zip(&infos)
+            .map(|(refresh, info)| {
+                assert!(refresh.is_complete(&players));
+                let renewed = refresh.commit(info, &players);
+                assert!(renewed.verify());
+                assert_ne!(info.share(), renewed.share());
+                assert_eq!(info.validity().epoch + 1, renewed.validity().epoch);
+
+                renewed
+            })
+            .collect()
+    }
+
+    fn secret(infos: &[ShareInfo]) -> usize {
+        let shares: Vec<Share> = infos.iter().map(|info| info.share().clone()).collect();
+
+        vss::reconstruct(&shares, infos[0].q())
+            .unwrap()
+            .expose()
+            .to_usize()
+            .unwrap()
+    }
+
+    #[test]
+    fn refresh_keeps_secret() {
+        let infos = renew(Renewal::Refresh);
+
+        assert_eq!(1234, secret(&infos[..3]));
+        assert_eq!(1234, secret(&infos[2..]));
+    }
+
+    #[test]
+    fn reshare_changes_threshold() {
+        let infos = renew(Renewal::Reshare(2));
+
+        assert_eq!(2, infos[0].t());
+        assert_eq!(1234, secret(&infos[1..3]));
+        assert_eq!(1234, secret(&infos[3..]));
+    }
+}
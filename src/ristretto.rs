@@ -0,0 +1,105 @@
+//! Feldman commitments on the Ristretto255 group
+//!
+//! A dealing over Ristretto255 is a polynomial over the group's prime order l, so its shares
+//! are dealt and reconstructed by `vss::generate_shares` and `vss::reconstruct` with q = l.
+//! Only the commitments differ: the points a_k * B of the basepoint B instead of g^a_k mod p,
+//! 32 bytes each rather than the size of p.
+
+use curve25519_dalek::ristretto::RistrettoPoint;
+use curve25519_dalek::scalar::Scalar;
+use curve25519_dalek::traits::MultiscalarMul;
+use num_bigint::BigUint;
+
+/// l = 2^252 + 27742317777372353535851937790883648493 in hex
+const ORDER_HEX: &[u8] = b"1000000000000000000000000000000014def9dea2f79cd65812631a5cf5d3ed";
+
+/// The prime order l of Ristretto255, the q the shares of a dealing over it are computed in
+pub fn order() -> BigUint {
+    // the constant is valid hex
+    BigUint::parse_bytes(ORDER_HEX, 16).unwrap()
+}
+
+/// `n` mod l as a scalar
+pub fn scalar(n: &BigUint) -> Scalar {
+    let mut bytes = [0; 32];
+    let le = (n % order()).to_bytes_le();
+    bytes[..le.len()].copy_from_slice(&le);
+
+    Scalar::from_bytes_mod_order(bytes)
+}
+
+/// Generate commitments c given the polynomial a over l
+///
+/// Commitments are of the form a_0 * B,a_1 * B,...,a_n * B
+pub fn generate_commitments(a: &[BigUint]) -> Vec<RistrettoPoint> {
+    a.iter()
+        .map(|a_i| RistrettoPoint::mul_base(&scalar(a_i)))
+        .collect()
+}
+
+/// Evaluates commitments c at i, which is P(i) * B, without knowing the polynomial P
+pub fn commitment_at(i: &BigUint, c: &[RistrettoPoint]) -> RistrettoPoint {
+    let i = scalar(i);
+    let powers: Vec<Scalar> = c
+        .iter()
+        .scan(Scalar::ONE, |power, _| {
+            let current = *power;
+            *power *= i;
+            Some(current)
+        })
+        .collect();
+
+    RistrettoPoint::multiscalar_mul(&powers, c)
+}
+
+/// Verify a particular share (i, s) against the commitments c
+///
+/// Verifies that s * B equals the sum of c_0,c_1 * i^1,c_2 * i^2,...,c_n * i^n
+pub fn verify_share(i: &BigUint, s: &BigUint, c: &[RistrettoPoint]) -> bool {
+    !c.is_empty() && RistrettoPoint::mul_base(&scalar(s)) == commitment_at(i, c)
+}
+
+/// Checks a reconstructed secret against the commitment c_0 = a_0 * B to the dealt one
+pub fn verify_secret(secret: &BigUint, c: &[RistrettoPoint]) -> bool {
+    c.first()
+        .is_some_and(|c_0| RistrettoPoint::mul_base(&scalar(secret)) == *c_0)
+}
+
+#[cfg(test)]
+mod tests {
+    use num_bigint::{BigUint, RandBigInt};
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    use crate::vss;
+
+    use super::{generate_commitments, order, verify_secret, verify_share};
+
+    #[test]
+    fn shares_verify_and_reconstruct() {
+        let mut rng = StdRng::seed_from_u64(7);
+        let l = order();
+        let a: Vec<BigUint> = (0..3).map(|_| rng.gen_biguint_below(&l)).collect();
+        let c = generate_commitments(&a);
+        let shares = vss::generate_shares(&a, 5, &l);
+
+        for share in &shares {
+            assert!(verify_share(share.index(), share.value(), &c));
+        }
+        let secret = vss::reconstruct(&shares[1..4], &l).unwrap();
+        assert_eq!(&a[0], secret.expose());
+        assert!(verify_secret(secret.expose(), &c));
+    }
+
+    #[test]
+    fn tampered_shares_fail() {
+        let l = order();
+        let a = vec![BigUint::from(1234_u32), BigUint::from(99_u32)];
+        let c = generate_commitments(&a);
+        let share = &vss::generate_shares(&a, 2, &l)[1];
+
+        assert!(!verify_share(share.index(), &(share.value() + 1_u32), &c));
+        assert!(!verify_share(&BigUint::from(3_u32), share.value(), &c));
+        assert!(!verify_secret(&BigUint::from(1235_u32), &c));
+    }
+}
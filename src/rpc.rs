@@ -1,18 +1,1136 @@
-use std::sync::mpsc::Sender;
-
-use num_bigint::BigUint;
-
-/// (i, P(i))
-pub type Share = (BigUint, BigUint);
-
-/// Share, g, c, p, q, t
-pub type ShareInfo = (Share, BigUint, Vec<BigUint>, BigUint, BigUint, usize);
-
-#[derive(Debug, Clone)]
-pub enum RPC {
-    Ping(usize),
-    RegSender(usize, Sender<RPC>),
-    RegShare(ShareInfo),
-    ReconstructShare(usize, Share),
-    Reconstruct(Sender<usize>),
-}
+use std::collections::BTreeMap;
+#[cfg(feature = "network")]
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::hash::{Hash, Hasher};
+#[cfg(feature = "network")]
+use std::sync::mpsc::{self, Receiver, RecvError, RecvTimeoutError, Sender};
+use std::sync::Arc;
+#[cfg(feature = "network")]
+use std::time::{Duration, Instant};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+#[cfg(feature = "audit")]
+use ed25519_dalek::{Signature, Signer, SigningKey, Verifier, VerifyingKey};
+use num::{One, Zero};
+use num_bigint::BigUint;
+#[cfg(feature = "audit")]
+use rand::{CryptoRng, Rng, RngCore};
+#[cfg(feature = "serde")]
+use serde::ser::SerializeStruct;
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize, Serializer};
+
+#[cfg(feature = "network")]
+use crate::backend::ShareBackend;
+#[cfg(feature = "network")]
+use crate::broadcast::BroadcastMessage;
+#[cfg(feature = "serde")]
+use crate::codec;
+#[cfg(feature = "network")]
+use crate::dkg::{DkgConfig, DkgMessage};
+use crate::error::VssError;
+#[cfg(feature = "network")]
+use crate::refresh::Renewal;
+use crate::secret::{self, Secret};
+#[cfg(feature = "network")]
+use crate::sign;
+use crate::vss;
+
+/// A point (i, P(i)) on the dealer's polynomial, its value wiped on drop, left out of Debug and
+/// compared in constant time
+#[derive(Clone, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Share {
+    #[cfg_attr(feature = "serde", serde(with = "codec::biguint"))]
+    index: BigUint,
+    #[cfg_attr(feature = "serde", serde(with = "codec::biguint"))]
+    value: BigUint,
+}
+
+impl Share {
+    pub fn new(index: BigUint, value: BigUint) -> Share {
+        Share { index, value }
+    }
+
+    /// The x coordinate i, which is also the id of the player holding the share
+    pub fn index(&self) -> &BigUint {
+        &self.index
+    }
+
+    /// The y coordinate P(i) over primefield q
+    pub fn value(&self) -> &BigUint {
+        &self.value
+    }
+}
+
+impl PartialEq for Share {
+    fn eq(&self, other: &Share) -> bool {
+        self.index == other.index && secret::ct_eq(&self.value, &other.value)
+    }
+}
+
+impl Hash for Share {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.index.hash(state);
+        self.value.hash(state);
+    }
+}
+
+impl Drop for Share {
+    fn drop(&mut self) {
+        secret::wipe(&mut self.value);
+    }
+}
+
+impl fmt::Debug for Share {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Share")
+            .field("index", &self.index)
+            .field("value", &format_args!("***"))
+            .finish()
+    }
+}
+
+/// The public parameters of a dealing, shared by every player's ShareInfo of it
+#[derive(Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct PublicParams {
+    /// Generator of order q over primefield p
+    #[cfg_attr(feature = "serde", serde(with = "codec::bytes"))]
+    pub g: BigUint,
+    /// Commitments g^a_0,...,g^a_n mod p to the dealer's polynomial
+    #[cfg_attr(feature = "serde", serde(with = "codec::bytes::vec"))]
+    pub commitments: Vec<BigUint>,
+    /// Prime the commitments are computed over
+    #[cfg_attr(feature = "serde", serde(with = "codec::bytes"))]
+    pub p: BigUint,
+    /// Prime the polynomial is computed over
+    #[cfg_attr(feature = "serde", serde(with = "codec::bytes"))]
+    pub q: BigUint,
+    /// Number of shares needed to reconstruct the secret
+    pub t: usize,
+}
+
+impl PublicParams {
+    /// Fails unless g generates a group of prime order q mod p
+    ///
+    /// Parameters read from a file or received from a peer may be anything, and a zero or
+    /// composite q crashes or skews the arithmetic over it. Checks that p and q are not zero,
+    /// that q is a prime dividing p - 1, and that g is not 1 and g^q = 1 mod p
+    pub fn validate(&self) -> Result<(), VssError> {
+        let PublicParams { g, p, q, .. } = self;
+        let invalid = |reason: &str| Err(VssError::InvalidParams(reason.to_string()));
+
+        if p.is_zero() || q.is_zero() {
+            return invalid("p and q must not be zero");
+        }
+        if !((p - 1_u32) % q).is_zero() {
+            return invalid("q does not divide p - 1");
+        }
+        vss::check_prime(q)?;
+        if g.is_zero() || g.is_one() || g >= p {
+            return invalid("g must be between 2 and p - 1");
+        }
+        if !g.modpow(q, p).is_one() {
+            return invalid("g is not of order q");
+        }
+
+        Ok(())
+    }
+}
+
+/// What a share says about itself besides its cryptographic payload, who keeps it and when and
+/// what for it was dealt. Verifying and reconstructing never look at it
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Deserialize), serde(default))]
+pub struct Metadata {
+    pub custodian: Option<String>,
+    pub purpose: Option<String>,
+    /// Identifies the dealing, chosen by whoever dealt it
+    pub dealing: Option<String>,
+    /// Seconds since the Unix epoch
+    pub created: Option<u64>,
+    pub labels: BTreeMap<String, String>,
+}
+
+impl Metadata {
+    pub fn is_empty(&self) -> bool {
+        *self == Metadata::default()
+    }
+}
+
+#[cfg(feature = "serde")]
+impl Serialize for Metadata {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        // binary codecs read every field by position in declaration order, text formats leave
+        // out the unset ones
+        let text = serializer.is_human_readable();
+        let mut state = serializer.serialize_struct("Metadata", 5)?;
+        let optional = [
+            ("custodian", &self.custodian),
+            ("purpose", &self.purpose),
+            ("dealing", &self.dealing),
+        ];
+        for (name, value) in optional {
+            match value {
+                None if text => state.skip_field(name)?,
+                value => state.serialize_field(name, value)?,
+            }
+        }
+        match self.created {
+            None if text => state.skip_field("created")?,
+            created => state.serialize_field("created", &created)?,
+        }
+        match &self.labels {
+            labels if text && labels.is_empty() => state.skip_field("labels")?,
+            labels => state.serialize_field("labels", labels)?,
+        }
+
+        state.end()
+    }
+}
+
+/// Which epoch of its dealing a share belongs to and when it may be used, so shares can be
+/// rotated out
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize), serde(default))]
+pub struct Validity {
+    /// Epoch of the dealing, every refresh moves its shares to the next
+    pub epoch: u64,
+    /// Seconds since the Unix epoch before which the share may not be used
+    pub not_before: Option<u64>,
+    /// Seconds since the Unix epoch from which the share has expired
+    pub not_after: Option<u64>,
+}
+
+impl Validity {
+    pub fn is_default(&self) -> bool {
+        *self == Validity::default()
+    }
+
+    /// Whether the share may be used at `time`, in seconds since the Unix epoch
+    pub fn allows(&self, time: u64) -> bool {
+        self.not_before.is_none_or(|not_before| time >= not_before)
+            && self.not_after.is_none_or(|not_after| time < not_after)
+    }
+
+    /// Whether the share may be used now
+    pub fn is_current(&self) -> bool {
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |elapsed| elapsed.as_secs());
+
+        self.allows(now)
+    }
+
+    /// The validity of the share a refresh moves this one to, the next epoch in the same window
+    pub fn next(self) -> Validity {
+        Validity {
+            epoch: self.epoch + 1,
+            ..self
+        }
+    }
+}
+
+/// A player's share together with the public parameters needed to verify and reconstruct it
+///
+/// The parameters are behind an Arc so the ShareInfos of one dealing share a single copy of its
+/// commitments
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ShareInfo {
+    share: Share,
+    params: Arc<PublicParams>,
+    // boxed, it is rarely set and messages carrying a ShareInfo stay small
+    metadata: Box<Metadata>,
+    validity: Validity,
+}
+
+/// How a ShareInfo is encoded, its share and parameters in one flat map
+#[cfg(feature = "serde")]
+#[derive(Serialize)]
+struct ShareInfoRef<'a> {
+    share: &'a Share,
+    #[serde(with = "codec::biguint")]
+    g: &'a BigUint,
+    #[serde(with = "codec::biguints")]
+    commitments: &'a Vec<BigUint>,
+    #[serde(with = "codec::biguint")]
+    p: &'a BigUint,
+    #[serde(with = "codec::biguint")]
+    q: &'a BigUint,
+    t: usize,
+    metadata: &'a Metadata,
+    validity: Validity,
+}
+
+/// How a ShareInfo is decoded, see ShareInfoRef
+#[cfg(feature = "serde")]
+#[derive(Deserialize)]
+struct ShareInfoRepr {
+    share: Share,
+    #[serde(with = "codec::biguint")]
+    g: BigUint,
+    #[serde(with = "codec::biguints")]
+    commitments: Vec<BigUint>,
+    #[serde(with = "codec::biguint")]
+    p: BigUint,
+    #[serde(with = "codec::biguint")]
+    q: BigUint,
+    t: usize,
+    #[serde(default)]
+    metadata: Metadata,
+    #[serde(default)]
+    validity: Validity,
+}
+
+#[cfg(feature = "serde")]
+impl Serialize for ShareInfo {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let PublicParams {
+            g,
+            commitments,
+            p,
+            q,
+            t,
+        } = &*self.params;
+
+        ShareInfoRef {
+            share: &self.share,
+            g,
+            commitments,
+            p,
+            q,
+            t: *t,
+            metadata: &self.metadata,
+            validity: self.validity,
+        }
+        .serialize(serializer)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> Deserialize<'de> for ShareInfo {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<ShareInfo, D::Error> {
+        let ShareInfoRepr {
+            share,
+            g,
+            commitments,
+            p,
+            q,
+            t,
+            metadata,
+            validity,
+        } = ShareInfoRepr::deserialize(deserializer)?;
+
+        Ok(ShareInfo::new(share, g, commitments, p, q, t)
+            .with_metadata(metadata)
+            .with_validity(validity))
+    }
+}
+
+impl ShareInfo {
+    pub fn new(
+        share: Share,
+        g: BigUint,
+        commitments: Vec<BigUint>,
+        p: BigUint,
+        q: BigUint,
+        t: usize,
+    ) -> ShareInfo {
+        let params = PublicParams {
+            g,
+            commitments,
+            p,
+            q,
+            t,
+        };
+
+        ShareInfo::with_params(share, Arc::new(params))
+    }
+
+    /// Return the ShareInfo of `share` under parameters already held by another ShareInfo
+    pub fn with_params(share: Share, params: Arc<PublicParams>) -> ShareInfo {
+        ShareInfo {
+            share,
+            params,
+            metadata: Box::default(),
+            validity: Validity::default(),
+        }
+    }
+
+    /// This share carrying `metadata`
+    pub fn with_metadata(mut self, metadata: Metadata) -> ShareInfo {
+        self.metadata = Box::new(metadata);
+        self
+    }
+
+    /// This share bound to the epoch and window of `validity`
+    pub fn with_validity(mut self, validity: Validity) -> ShareInfo {
+        self.validity = validity;
+        self
+    }
+
+    pub fn share(&self) -> &Share {
+        &self.share
+    }
+
+    pub fn params(&self) -> &Arc<PublicParams> {
+        &self.params
+    }
+
+    pub fn metadata(&self) -> &Metadata {
+        &self.metadata
+    }
+
+    pub fn validity(&self) -> Validity {
+        self.validity
+    }
+
+    /// Generator of order q over primefield p
+    pub fn g(&self) -> &BigUint {
+        &self.params.g
+    }
+
+    /// Commitments g^a_0,...,g^a_n mod p to the dealer's polynomial
+    pub fn commitments(&self) -> &Vec<BigUint> {
+        &self.params.commitments
+    }
+
+    /// Prime the commitments are computed over
+    pub fn p(&self) -> &BigUint {
+        &self.params.p
+    }
+
+    /// Prime the polynomial is computed over
+    pub fn q(&self) -> &BigUint {
+        &self.params.q
+    }
+
+    /// Number of shares needed to reconstruct the secret
+    pub fn t(&self) -> usize {
+        self.params.t
+    }
+
+    /// Verify that the held share is consistent with the commitments
+    pub fn verify(&self) -> bool {
+        self.verify_share(&self.share)
+    }
+
+    /// Verify another player's share against this dealing's commitments
+    pub fn verify_share(&self, share: &Share) -> bool {
+        vss::verify_share(
+            share.index(),
+            share.value(),
+            self.g(),
+            self.commitments(),
+            self.p(),
+        )
+    }
+
+    /// Check a secret reconstructed from shares of this dealing against its commitment c_0
+    pub fn verify_secret(&self, secret: &Secret) -> bool {
+        vss::verify_secret(secret.expose(), self.g(), self.commitments(), self.p())
+    }
+}
+
+/// Why a peer's share was excluded from reconstruction
+#[cfg(feature = "network")]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RejectReason {
+    /// The share did not verify against the dealer's commitments
+    InvalidShare,
+    /// The share's index did not match the id of the peer that sent it
+    IndexMismatch(BigUint),
+    /// The peer was already blacklisted for an earlier rejected share of the session
+    Blacklisted,
+    /// The peer's message used a protocol major this crate does not speak
+    UnsupportedVersion(Version),
+}
+
+#[cfg(feature = "network")]
+impl RejectReason {
+    /// Whether the rejected message proves its sender misbehaved, rather than that it runs
+    /// another version or was already blacklisted
+    pub fn is_misbehavior(&self) -> bool {
+        match self {
+            RejectReason::InvalidShare | RejectReason::IndexMismatch(_) => true,
+            RejectReason::Blacklisted | RejectReason::UnsupportedVersion(_) => false,
+        }
+    }
+}
+
+/// Why a peer could not use the share or deal we sent it, sent back so we need not wait on it
+/// blind
+#[cfg(feature = "network")]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Nack {
+    /// Our share or deal did not verify against the peer's commitments, or was for another index
+    InvalidShare,
+    /// The peer holds no share of the session
+    UnknownSession,
+    /// The peer holds an older epoch of the session than our message, its new share has not
+    /// arrived yet
+    NotReady,
+    /// The peer's share is outside its validity window, it will not reveal it
+    Expired,
+}
+
+/// Outcome of a reconstruction: the secret and which peers were used or excluded
+#[cfg(feature = "network")]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ReconstructionReport {
+    pub secret: Secret,
+    /// ids of the peers whose shares were interpolated
+    pub contributors: Vec<usize>,
+    pub rejected: Vec<(usize, RejectReason)>,
+    /// peers that could not use our share and why
+    pub nacks: Vec<(usize, Nack)>,
+    /// whether g^secret is the dealer's commitment c_0, false if shares of another dealing or
+    /// a corrupted share slipped through
+    pub verified: bool,
+}
+
+/// Outcome of reconstructing many sessions at once
+#[cfg(feature = "network")]
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct BatchReconstructionReport {
+    /// report of every session reconstructed, by session id
+    pub reports: BTreeMap<u64, ReconstructionReport>,
+    /// sessions not reconstructed: no share of them is held, it is outside its validity window,
+    /// another batch is reconstructing it or its shares did not interpolate
+    pub failed: Vec<u64>,
+}
+
+/// Outcome of moving a dealing to a new epoch
+#[cfg(feature = "network")]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RefreshReport {
+    /// epoch the player's share moved to
+    pub epoch: u64,
+    /// ids of the players whose deals were combined into the new share
+    pub dealers: Vec<usize>,
+}
+
+/// Outcome of a distributed key generation
+#[cfg(feature = "network")]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DkgReport {
+    /// g^x of the group secret x
+    pub public_key: BigUint,
+    /// the player's key share, held as the dealing of the DKG's session from then on
+    pub share_info: ShareInfo,
+    /// ids of the players whose polynomials make up the key
+    pub qualified: Vec<usize>,
+}
+
+/// Outcome of a threshold signature
+#[cfg(feature = "network")]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SignatureReport {
+    pub signature: sign::Signature,
+    /// g^x of the session's secret x, the key the signature verifies under
+    pub public_key: BigUint,
+    /// ids of the players whose shares signed
+    pub signers: Vec<usize>,
+}
+
+/// What a Player holds, answering a local status request
+#[cfg(feature = "network")]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StatusReport {
+    pub id: usize,
+    /// every session the player holds a share of and the epoch of that share, by session id
+    pub sessions: Vec<(u64, u64)>,
+    /// ids of the peers heard from recently
+    pub live_peers: Vec<usize>,
+}
+
+/// Version of the wire protocol, peers speaking different majors cannot understand each other
+#[cfg(feature = "network")]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub struct Version {
+    pub major: u16,
+    pub minor: u16,
+}
+
+/// Version this crate sends by default
+#[cfg(feature = "network")]
+pub const PROTOCOL_VERSION: Version = Version { major: 1, minor: 0 };
+
+/// Every version this crate can speak, the highest minor of each supported major
+#[cfg(feature = "network")]
+pub const SUPPORTED_VERSIONS: &[Version] = &[PROTOCOL_VERSION];
+
+#[cfg(feature = "network")]
+impl Version {
+    /// Whether messages of this version can be understood by this crate
+    pub fn is_supported(&self) -> bool {
+        SUPPORTED_VERSIONS.iter().any(|v| v.major == self.major)
+    }
+}
+
+/// Agree on the highest version both `ours` and `theirs` support
+///
+/// Peers settle on the highest common major and the lower of the two minors within it, since
+/// minors of the same major only add to the protocol
+#[cfg(feature = "network")]
+pub fn negotiate(ours: &[Version], theirs: &[Version]) -> Option<Version> {
+    ours.iter()
+        .filter_map(|o| {
+            theirs
+                .iter()
+                .filter(|t| t.major == o.major)
+                .map(|t| t.minor)
+                .max()
+                .map(|minor| Version {
+                    major: o.major,
+                    minor: minor.min(o.minor),
+                })
+        })
+        .max()
+}
+
+/// Session id carried by messages that do not belong to any dealing, such as Hello
+pub const NO_SESSION: u64 = 0;
+
+/// A protocol message exchanged between the dealer and players
+///
+/// Unlike RPC these carry no channels so they can be serialized and sent between processes.
+/// The sender of a message is identified by the transport that delivers it
+#[cfg(feature = "network")]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct WireMessage {
+    pub version: Version,
+    /// Dealing the message belongs to, so a Player can take part in several at once
+    pub session_id: u64,
+    /// Round of the dealing, a share from an older epoch is never mixed with a newer one
+    pub epoch: u64,
+    pub payload: Payload,
+}
+
+#[cfg(feature = "network")]
+impl WireMessage {
+    /// Wrap `payload` in a message of the default PROTOCOL_VERSION
+    pub fn new(session_id: u64, epoch: u64, payload: Payload) -> WireMessage {
+        WireMessage::with_version(PROTOCOL_VERSION, session_id, epoch, payload)
+    }
+
+    pub fn with_version(
+        version: Version,
+        session_id: u64,
+        epoch: u64,
+        payload: Payload,
+    ) -> WireMessage {
+        WireMessage {
+            version,
+            session_id,
+            epoch,
+            payload,
+        }
+    }
+}
+
+#[cfg(feature = "network")]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Payload {
+    Ping,
+    /// Handshake: the versions the sender supports, answered with the receiver's own Hello
+    Hello(Vec<Version>),
+    /// Dealer to player: the player's share and the public dealing parameters
+    RegShare(ShareInfo),
+    /// Player to player: the sender's share, used to reconstruct the secret
+    ReconstructShare(Share),
+    /// Part of a broadcast stronger than best effort, see broadcast.rs
+    Broadcast(BroadcastMessage),
+    /// The sender's nth message to us, to be answered with Ack(n), see transport/retry.rs
+    Sequenced(u64, Box<Payload>),
+    Ack(u64),
+    /// Player to player: why the receiver could not use the sender's share for the session
+    Nack(Nack),
+    /// Player to player: the receiver's point on the sender's polynomial hiding zero, moving the
+    /// dealing to `epoch` with the same secret and threshold, see refresh.rs
+    RefreshDeal {
+        epoch: u64,
+        sub_share: Share,
+        #[serde(with = "codec::biguints")]
+        commitments: Vec<BigUint>,
+    },
+    /// Player to every player: the sender verified the deal of `dealer` for `epoch`
+    RefreshAck {
+        epoch: u64,
+        dealer: usize,
+    },
+    /// Player to player: the receiver's point on the sender's polynomial hiding the sender's
+    /// share, resharing the dealing at `epoch` with threshold `t`
+    ReshareSubShare {
+        epoch: u64,
+        sub_share: Share,
+        #[serde(with = "codec::biguints")]
+        commitments: Vec<BigUint>,
+        t: usize,
+    },
+    /// Player to every player: the sender moved its share to `epoch` combining the deals of
+    /// `dealers`
+    EpochCommit {
+        epoch: u64,
+        dealers: Vec<usize>,
+    },
+    /// Player to every player: the share `accuser` received from `against` did not verify
+    Complaint {
+        accuser: usize,
+        against: usize,
+        evidence: Share,
+    },
+    /// Dealer to every player: the share of an accuser, revealed to answer its complaint
+    ComplaintResponse(Share),
+    /// Player to every player: the sender votes to disqualify the given dealer
+    Disqualify(usize),
+    /// Part of a distributed key generation, see dkg.rs
+    Dkg(DkgMessage),
+    /// Player to every peer: the sender is alive, sent every HEARTBEAT_INTERVAL
+    Heartbeat,
+    /// Player to player: reveal your share of the session to reconstruct its secret
+    ShareRequest,
+    /// Several messages in one frame, each with its own session, handled in order. A player
+    /// accepts every share of a batch or none
+    Batch(Vec<WireMessage>),
+    /// Coordinator to signer: sign `message` with the session's shares of `signers`, see
+    /// sign.rs
+    SignRequest {
+        request: u64,
+        message: Vec<u8>,
+        signers: Vec<usize>,
+    },
+    /// Signer to every other signer: g^k of the sender's nonce k for the request
+    NonceCommitment {
+        request: u64,
+        #[serde(with = "codec::biguint")]
+        commitment: BigUint,
+    },
+    /// Signer to coordinator: the sender's partial signature for the request
+    PartialSignature {
+        request: u64,
+        #[serde(with = "codec::biguint")]
+        z: BigUint,
+    },
+    /// Coordinator to signer: the signature the partial signatures of the request combined into
+    SignatureResult {
+        request: u64,
+        signature: sign::Signature,
+    },
+    /// Dealer to player: move the session to its next epoch with a refresh, see refresh.rs
+    RefreshRequest,
+    /// Player to dealer: the epoch the player's share moved to after a RefreshRequest, None if
+    /// it could not move
+    RefreshResult {
+        epoch: Option<u64>,
+    },
+    /// Lost player to helper: regenerate the sender's share of the session with `helpers`, see
+    /// recovery.rs
+    RecoveryRequest {
+        helpers: Vec<usize>,
+    },
+    /// Helper to helper: the receiver's piece of the sender's part of the share of `lost`
+    RecoveryPiece {
+        lost: usize,
+        helpers: Vec<usize>,
+        #[serde(with = "codec::biguint")]
+        piece: BigUint,
+    },
+    /// Helper to lost player: the sum of the pieces the sender got, as a share of the lost
+    /// player's index with the dealing's public parameters
+    RecoveryShare(ShareInfo),
+    /// Dealer to player: generate a key with the players of the config as the session, see
+    /// dkg.rs
+    DkgRequest(DkgConfig),
+    /// Player to dealer: g^x of the key generated after a DkgRequest, None if it could not be
+    /// generated
+    DkgResult {
+        #[serde(with = "codec::option_biguint")]
+        public_key: Option<BigUint>,
+    },
+}
+
+/// A message authenticated by the id and signature of whoever sent it
+///
+/// The signature covers both the payload and `signer_id`, so a peer cannot pass off a message
+/// it relays as its own or as someone else's without going through the transport
+#[cfg(feature = "audit")]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Signed<T> {
+    pub payload: T,
+    pub signer_id: usize,
+    pub signature: Signature,
+}
+
+#[cfg(feature = "audit")]
+impl<T: Serialize> Signed<T> {
+    /// Sign `payload` as `signer_id` with `key`
+    pub fn sign(payload: T, signer_id: usize, key: &SigningKey) -> Signed<T> {
+        let signature = key.sign(&Signed::signed_bytes(&payload, signer_id));
+
+        Signed {
+            payload,
+            signer_id,
+            signature,
+        }
+    }
+
+    /// Verify the signature against the public key of `signer_id`
+    pub fn verify(&self, key: &VerifyingKey) -> bool {
+        key.verify(
+            &Signed::signed_bytes(&self.payload, self.signer_id),
+            &self.signature,
+        )
+        .is_ok()
+    }
+
+    fn signed_bytes(payload: &T, signer_id: usize) -> Vec<u8> {
+        // serializing our own message types cannot fail
+        serde_json::to_vec(&(signer_id, payload)).unwrap()
+    }
+}
+
+/// Return a new random key for signing messages
+#[cfg(feature = "audit")]
+pub fn generate_signing_key() -> SigningKey {
+    generate_signing_key_with_rng(&mut rand::thread_rng())
+}
+
+/// Like `generate_signing_key` with the key drawn from `rng`
+#[cfg(feature = "audit")]
+pub fn generate_signing_key_with_rng<R: RngCore + CryptoRng>(rng: &mut R) -> SigningKey {
+    SigningKey::from_bytes(&rng.gen())
+}
+
+/// Identifies a local request, so its response can be told apart from the responses to others
+#[cfg(feature = "network")]
+pub type CorrelationId = u64;
+
+/// Where the response to a local request goes, answered at most once
+#[cfg(feature = "network")]
+#[derive(Debug)]
+pub struct Reply<T> {
+    id: CorrelationId,
+    tx: Sender<(CorrelationId, T)>,
+}
+
+#[cfg(feature = "network")]
+impl<T> Reply<T> {
+    pub fn id(&self) -> CorrelationId {
+        self.id
+    }
+
+    /// Answer the request, failing if nobody waits on it anymore
+    pub fn send(self, response: T) -> Result<(), VssError> {
+        self.tx
+            .send((self.id, response))
+            .map_err(|_| VssError::Disconnected)
+    }
+}
+
+/// Matches the responses of a Player to the local requests awaiting them
+///
+/// Every request sent to a Player carries a Reply with its own correlation id, responses to
+/// requests that were cancelled are dropped
+#[cfg(feature = "network")]
+#[derive(Debug)]
+pub struct Router<T> {
+    next_id: CorrelationId,
+    outstanding: HashSet<CorrelationId>,
+    /// responses that arrived while waiting on another request
+    early: HashMap<CorrelationId, T>,
+    tx: Sender<(CorrelationId, T)>,
+    rx: Receiver<(CorrelationId, T)>,
+}
+
+#[cfg(feature = "network")]
+impl<T> Default for Router<T> {
+    fn default() -> Router<T> {
+        let (tx, rx) = mpsc::channel();
+
+        Router {
+            next_id: 0,
+            outstanding: HashSet::new(),
+            early: HashMap::new(),
+            tx,
+            rx,
+        }
+    }
+}
+
+#[cfg(feature = "network")]
+impl<T> Router<T> {
+    pub fn new() -> Router<T> {
+        Router::default()
+    }
+
+    /// Return the Reply of a new request
+    pub fn request(&mut self) -> Reply<T> {
+        let id = self.next_id;
+        self.next_id += 1;
+        self.outstanding.insert(id);
+
+        Reply {
+            id,
+            tx: self.tx.clone(),
+        }
+    }
+
+    /// Stop waiting on the request `id`, its response is dropped if it still arrives
+    pub fn cancel(&mut self, id: CorrelationId) {
+        self.outstanding.remove(&id);
+        self.early.remove(&id);
+    }
+
+    /// Number of requests not answered yet
+    pub fn outstanding(&self) -> usize {
+        self.outstanding.len()
+    }
+
+    /// Block until any outstanding request is answered
+    pub fn recv(&mut self) -> Result<(CorrelationId, T), RecvError> {
+        if let Some(response) = self.take_early(None) {
+            return Ok(response);
+        }
+
+        loop {
+            let (id, response) = self.rx.recv()?;
+            if self.outstanding.remove(&id) {
+                return Ok((id, response));
+            }
+        }
+    }
+
+    /// Wait up to `timeout` for any outstanding request to be answered
+    pub fn recv_timeout(
+        &mut self,
+        timeout: Duration,
+    ) -> Result<(CorrelationId, T), RecvTimeoutError> {
+        if let Some(response) = self.take_early(None) {
+            return Ok(response);
+        }
+
+        let deadline = Instant::now() + timeout;
+        loop {
+            let timeout = deadline.saturating_duration_since(Instant::now());
+            let (id, response) = self.rx.recv_timeout(timeout)?;
+            if self.outstanding.remove(&id) {
+                return Ok((id, response));
+            }
+        }
+    }
+
+    /// Wait up to `timeout` for the response to the request `id`, keeping the responses to
+    /// other requests for later
+    pub fn recv_for(
+        &mut self,
+        id: CorrelationId,
+        timeout: Duration,
+    ) -> Result<T, RecvTimeoutError> {
+        if let Some((_, response)) = self.take_early(Some(id)) {
+            return Ok(response);
+        }
+        if !self.outstanding.contains(&id) {
+            return Err(RecvTimeoutError::Disconnected);
+        }
+
+        let deadline = Instant::now() + timeout;
+        loop {
+            let timeout = deadline.saturating_duration_since(Instant::now());
+            let (other, response) = self.rx.recv_timeout(timeout)?;
+            if other == id {
+                self.outstanding.remove(&id);
+                return Ok(response);
+            }
+            if self.outstanding.contains(&other) {
+                self.early.insert(other, response);
+            }
+        }
+    }
+
+    /// A response that arrived early, to the request `id` or to any
+    fn take_early(&mut self, id: Option<CorrelationId>) -> Option<(CorrelationId, T)> {
+        let id = id.or_else(|| self.early.keys().next().copied())?;
+        let response = self.early.remove(&id)?;
+        self.outstanding.remove(&id);
+
+        Some((id, response))
+    }
+}
+
+/// Messages handled by a Player's message loop
+#[cfg(feature = "network")]
+#[derive(Debug)]
+pub enum RPC {
+    /// A protocol message from the player (or dealer) with the given id
+    Wire(usize, WireMessage),
+    /// Local request to reconstruct the secret of a session, answered through the given Reply
+    Reconstruct(u64, Reply<ReconstructionReport>),
+    /// Local request to reconstruct the secrets of many sessions at once, answered through the
+    /// given Reply once every one of them is reconstructed
+    ReconstructMany(Vec<u64>, Reply<BatchReconstructionReport>),
+    /// Local request to move a session to its next epoch, answered through the given Reply once
+    /// this Player's share has moved
+    Refresh(u64, Renewal, Reply<RefreshReport>),
+    /// Local request to generate a key with the players of the config as the given session,
+    /// answered through the given Reply with this Player's key share
+    Dkg(u64, DkgConfig, Reply<DkgReport>),
+    /// Local request for the ids of the peers heard from recently, answered through the given
+    /// Reply
+    LivePeers(Reply<Vec<usize>>),
+    /// Local request to sign a message with the shares of the given players of a session,
+    /// coordinated by this Player and answered through the given Reply
+    Sign(u64, Vec<u8>, Vec<usize>, Reply<SignatureReport>),
+    /// Local request for what this Player holds, answered through the given Reply
+    Status(Reply<StatusReport>),
+    /// Local request to keep this Player's share of a session behind the given backend, such
+    /// as a PKCS#11 token, answered through the given Reply with whether the backend holds the
+    /// share of the session's dealing
+    UseBackend(u64, Arc<dyn ShareBackend>, Reply<bool>),
+}
+
+#[cfg(test)]
+mod tests {
+    use num_bigint::ToBigUint;
+
+    use std::sync::Arc;
+    #[cfg(feature = "network")]
+    use std::time::Duration;
+
+    #[cfg(feature = "network")]
+    use super::{
+        generate_signing_key, negotiate, Nack, Payload, Router, Signed, Version, WireMessage,
+    };
+    use super::{Share, ShareInfo};
+
+    #[test]
+    #[cfg(feature = "network")]
+    fn router_matches_responses_to_requests() {
+        let mut router = Router::new();
+        let (first, second, cancelled) = (router.request(), router.request(), router.request());
+        let ids = (first.id(), second.id(), cancelled.id());
+        router.cancel(ids.2);
+
+        assert!(cancelled.send(3).is_ok());
+        assert!(second.send(2).is_ok());
+        assert!(first.send(1).is_ok());
+        assert_eq!(Ok(1), router.recv_for(ids.0, Duration::from_secs(1)));
+        assert_eq!(Ok((ids.1, 2)), router.recv_timeout(Duration::from_secs(1)));
+        assert!(router.recv_timeout(Duration::from_millis(50)).is_err());
+        assert_eq!(0, router.outstanding());
+    }
+
+    #[test]
+    #[cfg(feature = "network")]
+    fn wire_message_roundtrip() {
+        let share = Share::new(2.to_biguint().unwrap(), 1942.to_biguint().unwrap());
+        let msgs = vec![
+            Payload::Ping,
+            Payload::Hello(vec![Version { major: 1, minor: 2 }]),
+            Payload::ReconstructShare(share.clone()),
+            Payload::RegShare(ShareInfo::new(
+                share,
+                3.to_biguint().unwrap(),
+                vec![5.to_biguint().unwrap(), 1.to_biguint().unwrap()],
+                11.to_biguint().unwrap(),
+                5.to_biguint().unwrap(),
+                2,
+            )),
+            Payload::Nack(Nack::NotReady),
+            Payload::Heartbeat,
+            Payload::Batch(vec![WireMessage::new(3, 0, Payload::Ping)]),
+            Payload::EpochCommit {
+                epoch: 2,
+                dealers: vec![1, 2, 3],
+            },
+            Payload::SignRequest {
+                request: 9,
+                message: b"hello".to_vec(),
+                signers: vec![1, 3],
+            },
+        ];
+
+        for msg in msgs.into_iter().map(|p| WireMessage::new(7, 1, p)) {
+            let encoded = serde_json::to_string(&msg).unwrap();
+
+            assert_eq!(msg, serde_json::from_str(&encoded).unwrap());
+        }
+    }
+
+    #[test]
+    fn share_infos_share_params() {
+        let share = Share::new(2.to_biguint().unwrap(), 1942.to_biguint().unwrap());
+        let info = ShareInfo::new(
+            share.clone(),
+            3.to_biguint().unwrap(),
+            vec![5.to_biguint().unwrap(), 1.to_biguint().unwrap()],
+            11.to_biguint().unwrap(),
+            5.to_biguint().unwrap(),
+            2,
+        );
+        let other = ShareInfo::with_params(share, info.params().clone());
+        assert!(Arc::ptr_eq(info.params(), other.clone().params()));
+
+        // the parameters are encoded next to the share, not nested
+        #[cfg(feature = "serde")]
+        {
+            let encoded = serde_json::to_value(&info).unwrap();
+            assert_eq!(
+                vec![
+                    "commitments",
+                    "g",
+                    "metadata",
+                    "p",
+                    "q",
+                    "share",
+                    "t",
+                    "validity"
+                ],
+                encoded.as_object().unwrap().keys().collect::<Vec<_>>()
+            );
+        }
+    }
+
+    #[test]
+    #[cfg(feature = "network")]
+    fn negotiate_versions() {
+        let v = |major, minor| Version { major, minor };
+
+        assert_eq!(Some(v(1, 0)), negotiate(&[v(1, 0)], &[v(1, 3)]));
+        assert_eq!(
+            Some(v(2, 1)),
+            negotiate(&[v(1, 4), v(2, 1)], &[v(1, 2), v(2, 5)])
+        );
+        assert_eq!(
+            Some(v(1, 2)),
+            negotiate(&[v(1, 4), v(2, 1)], &[v(1, 2), v(3, 0)])
+        );
+        assert_eq!(None, negotiate(&[v(1, 0)], &[v(2, 0)]));
+    }
+
+    #[test]
+    #[cfg(feature = "network")]
+    fn signed_message_verifies() {
+        let key = generate_signing_key();
+        let other = generate_signing_key();
+        let share = Share::new(2.to_biguint().unwrap(), 1942.to_biguint().unwrap());
+        let signed = Signed::sign(Payload::ReconstructShare(share), 2, &key);
+
+        assert!(signed.verify(&key.verifying_key()));
+        assert!(!signed.verify(&other.verifying_key()));
+
+        let mut forged = signed.clone();
+        forged.signer_id = 3;
+        assert!(!forged.verify(&key.verifying_key()));
+
+        let mut tampered = signed.clone();
+        tampered.payload = Payload::Ping;
+        assert!(!tampered.verify(&key.verifying_key()));
+
+        let encoded = serde_json::to_string(&signed).unwrap();
+        let decoded: Signed<Payload> = serde_json::from_str(&encoded).unwrap();
+        assert!(decoded.verify(&key.verifying_key()));
+    }
+}
@@ -0,0 +1,100 @@
+//! Feldman commitments on the secp256k1 curve
+//!
+//! A dealing over secp256k1 is a polynomial over the curve's prime order n, so its shares are
+//! dealt and reconstructed by `vss::generate_shares` and `vss::reconstruct` with q = n, and its
+//! secret is a private key of the curve with c_0 its public key. Only the commitments differ:
+//! the points a_k * G of the generator G instead of g^a_k mod p.
+
+use k256::elliptic_curve::ops::Reduce;
+use k256::{FieldBytes, ProjectivePoint, Scalar, U256};
+use num_bigint::BigUint;
+
+/// n in hex
+const ORDER_HEX: &[u8] = b"fffffffffffffffffffffffffffffffebaaedce6af48a03bbfd25e8cd0364141";
+
+/// The prime order n of secp256k1, the q the shares of a dealing over it are computed in
+pub fn order() -> BigUint {
+    // the constant is valid hex
+    BigUint::parse_bytes(ORDER_HEX, 16).unwrap()
+}
+
+/// `n` mod the curve's order as a scalar
+pub fn scalar(n: &BigUint) -> Scalar {
+    let mut bytes = FieldBytes::default();
+    let be = (n % order()).to_bytes_be();
+    bytes[32 - be.len()..].copy_from_slice(&be);
+
+    <Scalar as Reduce<U256>>::reduce_bytes(&bytes)
+}
+
+/// Generate commitments c given the polynomial a over n
+///
+/// Commitments are of the form a_0 * G,a_1 * G,...,a_n * G
+pub fn generate_commitments(a: &[BigUint]) -> Vec<ProjectivePoint> {
+    a.iter()
+        .map(|a_i| ProjectivePoint::GENERATOR * scalar(a_i))
+        .collect()
+}
+
+/// Evaluates commitments c at i, which is P(i) * G, without knowing the polynomial P
+///
+/// Horner's rule from c_n down, one multiplication by i per commitment
+pub fn commitment_at(i: &BigUint, c: &[ProjectivePoint]) -> ProjectivePoint {
+    let i = scalar(i);
+
+    c.iter()
+        .rev()
+        .fold(ProjectivePoint::IDENTITY, |acc, c_k| acc * i + c_k)
+}
+
+/// Verify a particular share (i, s) against the commitments c
+///
+/// Verifies that s * G equals the sum of c_0,c_1 * i^1,c_2 * i^2,...,c_n * i^n
+pub fn verify_share(i: &BigUint, s: &BigUint, c: &[ProjectivePoint]) -> bool {
+    !c.is_empty() && ProjectivePoint::GENERATOR * scalar(s) == commitment_at(i, c)
+}
+
+/// Checks a reconstructed secret against the commitment c_0 = a_0 * G to the dealt one
+pub fn verify_secret(secret: &BigUint, c: &[ProjectivePoint]) -> bool {
+    c.first()
+        .is_some_and(|c_0| ProjectivePoint::GENERATOR * scalar(secret) == *c_0)
+}
+
+#[cfg(test)]
+mod tests {
+    use num_bigint::{BigUint, RandBigInt};
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    use crate::vss;
+
+    use super::{generate_commitments, order, verify_secret, verify_share};
+
+    #[test]
+    fn shares_verify_and_reconstruct() {
+        let mut rng = StdRng::seed_from_u64(7);
+        let n = order();
+        let a: Vec<BigUint> = (0..3).map(|_| rng.gen_biguint_below(&n)).collect();
+        let c = generate_commitments(&a);
+        let shares = vss::generate_shares(&a, 5, &n);
+
+        for share in &shares {
+            assert!(verify_share(share.index(), share.value(), &c));
+        }
+        let secret = vss::reconstruct(&shares[1..4], &n).unwrap();
+        assert_eq!(&a[0], secret.expose());
+        assert!(verify_secret(secret.expose(), &c));
+    }
+
+    #[test]
+    fn tampered_shares_fail() {
+        let n = order();
+        let a = vec![BigUint::from(1234_u32), BigUint::from(99_u32)];
+        let c = generate_commitments(&a);
+        let share = &vss::generate_shares(&a, 2, &n)[1];
+
+        assert!(!verify_share(share.index(), &(share.value() + 1_u32), &c));
+        assert!(!verify_share(&BigUint::from(3_u32), share.value(), &c));
+        assert!(!verify_secret(&BigUint::from(1235_u32), &c));
+    }
+}
@@ -0,0 +1,164 @@
+//! Secrets that wipe their memory when dropped and never show in Debug or Display output, so
+//! they leak neither through logs nor core dumps
+//!
+//! Read one with `expose`, which is the only way to get at its value and easy to look for in a
+//! review. A copy taken out of it is not wiped. Secrets, and the values checked against them,
+//! are compared with `ct_eq` in constant time.
+
+use std::fmt;
+use std::hint::black_box;
+
+use num_bigint::BigUint;
+use subtle::ConstantTimeEq;
+use zeroize::{Zeroize, ZeroizeOnDrop, Zeroizing};
+
+/// Overwrite the digits of `n` where they are stored and leave it 0
+pub(crate) fn wipe(n: &mut BigUint) {
+    let digits = n.bits().div_ceil(32);
+    n.assign_from_slice(&vec![0; digits]);
+    // keep the zeros from being optimized away as stores to memory about to be freed
+    black_box(n);
+}
+
+/// Whether a == b, in a time that depends on their lengths only
+///
+/// The comparison is constant time, the BigUint arithmetic computing a and b is not.
+pub fn ct_eq(a: &BigUint, b: &BigUint) -> bool {
+    let (a, b) = (a.to_bytes_le(), b.to_bytes_le());
+    let len = a.len().max(b.len());
+    let pad = |bytes: Vec<u8>| {
+        let mut padded = Zeroizing::new(bytes);
+        padded.resize(len, 0);
+
+        padded
+    };
+
+    pad(a).ct_eq(&pad(b)).into()
+}
+
+/// A secret number: a dealt or reconstructed secret, wiped on drop
+#[derive(Clone, Eq)]
+pub struct Secret(BigUint);
+
+impl Secret {
+    pub fn new(secret: BigUint) -> Secret {
+        Secret(secret)
+    }
+
+    /// The secret itself
+    pub fn expose(&self) -> &BigUint {
+        &self.0
+    }
+}
+
+impl From<BigUint> for Secret {
+    fn from(secret: BigUint) -> Secret {
+        Secret(secret)
+    }
+}
+
+impl PartialEq for Secret {
+    fn eq(&self, other: &Secret) -> bool {
+        ct_eq(&self.0, &other.0)
+    }
+}
+
+impl Zeroize for Secret {
+    fn zeroize(&mut self) {
+        wipe(&mut self.0);
+    }
+}
+
+impl Drop for Secret {
+    fn drop(&mut self) {
+        self.zeroize();
+    }
+}
+
+impl ZeroizeOnDrop for Secret {}
+
+impl fmt::Debug for Secret {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("Secret(***)")
+    }
+}
+
+impl fmt::Display for Secret {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("Secret(***)")
+    }
+}
+
+/// The bytes of a secret, such as a file shared by `vss deal`, wiped on drop
+#[derive(Clone, PartialEq, Eq, Zeroize, ZeroizeOnDrop)]
+pub struct SecretBytes(Vec<u8>);
+
+impl SecretBytes {
+    pub fn new(secret: Vec<u8>) -> SecretBytes {
+        SecretBytes(secret)
+    }
+
+    /// The bytes themselves
+    pub fn expose(&self) -> &[u8] {
+        &self.0
+    }
+}
+
+impl From<Vec<u8>> for SecretBytes {
+    fn from(secret: Vec<u8>) -> SecretBytes {
+        SecretBytes(secret)
+    }
+}
+
+impl fmt::Debug for SecretBytes {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("SecretBytes(***)")
+    }
+}
+
+impl fmt::Display for SecretBytes {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("SecretBytes(***)")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use num_bigint::BigUint;
+    use zeroize::Zeroize;
+
+    use super::{ct_eq, Secret, SecretBytes};
+
+    #[test]
+    fn secrets_are_redacted() {
+        let secret = Secret::new(BigUint::from(1234_u32));
+        assert_eq!("Secret(***)", format!("{:?}", secret));
+        assert_eq!("Secret(***)", secret.to_string());
+        assert_eq!(&BigUint::from(1234_u32), secret.expose());
+
+        let bytes = SecretBytes::new(b"hunter2".to_vec());
+        assert_eq!("SecretBytes(***)", format!("{:?}", bytes));
+        assert_eq!(b"hunter2", bytes.expose());
+    }
+
+    #[test]
+    fn ct_eq_ignores_leading_zero_bytes() {
+        let a = BigUint::from(0x0102_u32);
+
+        assert!(ct_eq(&a, &BigUint::from_bytes_be(&[0, 0, 1, 2])));
+        assert!(!ct_eq(&a, &BigUint::from(0x0103_u32)));
+        assert!(!ct_eq(&a, &(&a << 64)));
+        assert!(ct_eq(&BigUint::from(0_u32), &BigUint::from(0_u32)));
+    }
+
+    #[test]
+    fn zeroize_clears_the_secret() {
+        let mut secret = Secret::new(BigUint::from(u128::MAX));
+        secret.zeroize();
+        assert_eq!(&BigUint::from(0_u32), secret.expose());
+
+        let mut bytes = SecretBytes::new(vec![7; 4]);
+        bytes.zeroize();
+        assert!(bytes.expose().is_empty());
+    }
+}
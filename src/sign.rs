@@ -0,0 +1,344 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use num::One;
+use num_bigint::BigUint;
+use num_primes::RandBigInt;
+use rand::{CryptoRng, RngCore};
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use tracing::warn;
+
+use crate::backend::{InMemory, ShareBackend};
+#[cfg(feature = "serde")]
+use crate::codec;
+use crate::rpc::{PublicParams, ShareInfo};
+use crate::secret;
+use crate::vss;
+
+/// A Schnorr signature (R, z) under the public key g^x of a dealing's secret x
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Signature {
+    /// g^k of the nonce k
+    #[cfg_attr(feature = "serde", serde(with = "codec::biguint"))]
+    pub r: BigUint,
+    /// k + e * x over primefield q
+    #[cfg_attr(feature = "serde", serde(with = "codec::biguint"))]
+    pub z: BigUint,
+}
+
+/// Hashes the nonce commitment R, the public key y and the message into the challenge e
+///
+/// R and y are padded to the length of p so the encoding is unambiguous
+pub fn challenge(r: &BigUint, y: &BigUint, message: &[u8], p: &BigUint, q: &BigUint) -> BigUint {
+    let len = p.to_bytes_be().len();
+    let mut hasher = Sha256::new();
+    for x in [r, y] {
+        let bytes = x.to_bytes_be();
+        hasher.update(vec![0; len.saturating_sub(bytes.len())]);
+        hasher.update(bytes);
+    }
+    hasher.update(message);
+
+    BigUint::from_bytes_be(&hasher.finalize()) % q
+}
+
+/// Verify that g^z = R * y^e mod p for the challenge e of the signature
+pub fn verify(
+    y: &BigUint,
+    message: &[u8],
+    signature: &Signature,
+    g: &BigUint,
+    p: &BigUint,
+    q: &BigUint,
+) -> bool {
+    let e = challenge(&signature.r, y, message, p, q);
+
+    secret::ct_eq(
+        &g.modpow(&signature.z, p),
+        &(&signature.r * y.modpow(&e, p) % p),
+    )
+}
+
+/// A signer's part in signing a message with a dealing's secret, without the secret ever being
+/// reconstructed
+///
+/// Every signer commits to a random nonce k_i with R_i = g^k_i. Once every commitment is known
+/// each signer answers the challenge e for R = R_1 * ... * R_n with z_i = k_i + e * λ_i * x_i,
+/// its share x_i weighted by its Lagrange coefficient among the signers. The coordinator checks
+/// every partial against the commitments of the dealing and sums them into z
+pub struct Signing {
+    id: usize,
+    coordinator: usize,
+    params: Arc<PublicParams>,
+    backend: Box<dyn ShareBackend>,
+    message: Vec<u8>,
+    signers: Vec<usize>,
+    nonce: BigUint,
+    /// nonce commitments R_i, by signer
+    commitments: HashMap<usize, BigUint>,
+    /// partial signatures z_i received by the coordinator, by signer
+    partials: HashMap<usize, BigUint>,
+    signed: bool,
+}
+
+impl Signing {
+    /// Return a signing of `message` as the player holding the share in `info`, drawing a new
+    /// nonce from `rng`
+    pub fn new<R: RngCore + CryptoRng>(
+        id: usize,
+        coordinator: usize,
+        info: ShareInfo,
+        message: Vec<u8>,
+        signers: Vec<usize>,
+        rng: &mut R,
+    ) -> Signing {
+        let backend = Box::new(InMemory::new(info.share().clone()));
+
+        Signing::with_backend(
+            id,
+            coordinator,
+            info.params().clone(),
+            backend,
+            message,
+            signers,
+            rng,
+        )
+    }
+
+    /// Like `new` with the share of the dealing of `params` behind `backend`, which must be
+    /// able to `respond`
+    pub fn with_backend<R: RngCore + CryptoRng>(
+        id: usize,
+        coordinator: usize,
+        params: Arc<PublicParams>,
+        backend: Box<dyn ShareBackend>,
+        message: Vec<u8>,
+        signers: Vec<usize>,
+        rng: &mut R,
+    ) -> Signing {
+        let nonce = rng.gen_biguint_below(&params.q);
+        let commitment = params.g.modpow(&nonce, &params.p);
+        let commitments = HashMap::from([(id, commitment)]);
+
+        Signing {
+            id,
+            coordinator,
+            params,
+            backend,
+            message,
+            signers,
+            nonce,
+            commitments,
+            partials: HashMap::new(),
+            signed: false,
+        }
+    }
+
+    /// The player combining the partial signatures
+    pub fn coordinator(&self) -> usize {
+        self.coordinator
+    }
+
+    pub fn signers(&self) -> &[usize] {
+        &self.signers
+    }
+
+    /// g^x of the dealing's secret x, the key the signature verifies under
+    pub fn public_key(&self) -> &BigUint {
+        &self.params.commitments[0]
+    }
+
+    /// Verify a signature of the message under the dealing's public key
+    pub fn verify(&self, signature: &Signature) -> bool {
+        let params = &self.params;
+
+        verify(
+            self.public_key(),
+            &self.message,
+            signature,
+            &params.g,
+            &params.p,
+            &params.q,
+        )
+    }
+
+    /// Our nonce commitment R_i, to be sent to every other signer
+    pub fn nonce_commitment(&self) -> &BigUint {
+        &self.commitments[&self.id]
+    }
+
+    /// Records the nonce commitment of `from`, returns our partial signature once every signer
+    /// committed, unless the backend could not answer
+    pub fn commit(&mut self, from: usize, commitment: BigUint) -> Option<BigUint> {
+        if !self.signers.contains(&from) {
+            return None;
+        }
+        self.commitments.entry(from).or_insert(commitment);
+        if self.signed || self.commitments.len() < self.signers.len() {
+            return None;
+        }
+        self.signed = true;
+
+        let c = self.challenge() * self.lambda(self.id) % &self.params.q;
+        match self.backend.respond(&self.nonce, &c, &self.params.q) {
+            Ok(z) => Some(z),
+            Err(err) => {
+                warn!(%err, "share backend could not sign");
+                None
+            }
+        }
+    }
+
+    /// Keeps the partial signature of `from`, checked once every partial arrived
+    pub fn partial(&mut self, from: usize, z: BigUint) {
+        if self.signers.contains(&from) {
+            self.partials.entry(from).or_insert(z);
+        }
+    }
+
+    /// The signature once every signer sent a partial signature, or the signers whose partial
+    /// does not match their share's commitment
+    pub fn signature(&self) -> Option<Result<Signature, Vec<usize>>> {
+        if self.commitments.len() < self.signers.len() || self.partials.len() < self.signers.len() {
+            return None;
+        }
+
+        let (g, p, q) = (&self.params.g, &self.params.p, &self.params.q);
+        let e = self.challenge();
+        let invalid: Vec<usize> = self
+            .signers
+            .iter()
+            .copied()
+            .filter(|id| {
+                // g^z_i = R_i * (g^x_i)^(e * λ_i)
+                let y_i = vss::commitment_at(&BigUint::from(*id), &self.params.commitments, p);
+                let exponent = &e * self.lambda(*id) % q;
+                !secret::ct_eq(
+                    &g.modpow(&self.partials[id], p),
+                    &(&self.commitments[id] * y_i.modpow(&exponent, p) % p),
+                )
+            })
+            .collect();
+        if !invalid.is_empty() {
+            return Some(Err(invalid));
+        }
+
+        let z = self.partials.values().sum::<BigUint>() % q;
+
+        Some(Ok(Signature { r: self.r(), z }))
+    }
+
+    /// The product R of every signer's nonce commitment
+    fn r(&self) -> BigUint {
+        let p = &self.params.p;
+
+        self.commitments
+            .values()
+            .fold(BigUint::one(), |r, r_i| r * r_i % p)
+    }
+
+    fn challenge(&self) -> BigUint {
+        challenge(
+            &self.r(),
+            self.public_key(),
+            &self.message,
+            &self.params.p,
+            &self.params.q,
+        )
+    }
+
+    /// Lagrange coefficient of `id` among the signers
+    fn lambda(&self, id: usize) -> BigUint {
+        let xs: Vec<BigUint> = self.signers.iter().map(|&id| BigUint::from(id)).collect();
+
+        vss::lagrange_coefficient(&BigUint::from(id), &xs, &self.params.q)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use num_bigint::BigUint;
+
+    use crate::dealer::Dealer;
+    use crate::rpc::ShareInfo;
+
+    use super::{verify, Signing};
+
+    /// Starts a signing of `message` by `signers` of a dealing of 1234 to 5 players with
+    /// threshold 3, exchanging every nonce commitment, returns each signer's partial signature
+    fn sign(message: &[u8], signers: &[usize]) -> (Dealer, Vec<Signing>, Vec<BigUint>) {
+        let dealer = Dealer::new(5, 3, 1234).unwrap();
+        let mut signings: Vec<Signing> = signers
+            .iter()
+            .map(|&id| {
+                let info = ShareInfo::new(
+                    dealer.shares[id - 1].clone(),
+                    dealer.g.clone(),
+                    dealer.c.clone(),
+                    dealer.p.clone(),
+                    dealer.q.clone(),
+                    dealer.t,
+                );
+                Signing::new(
+                    id,
+                    signers[0],
+                    info,
+                    message.to_vec(),
+                    signers.to_vec(),
+                    &mut rand::thread_rng(),
+                )
+            })
+            .collect();
+
+        let commitments: Vec<(usize, BigUint)> = signers
+            .iter()
+            .zip(&signings)
+            .map(|(id, signing)| (*id, signing.nonce_commitment().clone()))
+            .collect();
+        let partials = signings
+            .iter_mut()
+            .map(|signing| {
+                commitments
+                    .iter()
+                    .filter_map(|(from, r)| signing.commit(*from, r.clone()))
+                    .last()
+                    .unwrap()
+            })
+            .collect();
+
+        (dealer, signings, partials)
+    }
+
+    #[test]
+    fn signers_produce_valid_signature() {
+        let signers = [2, 4, 5];
+        let (dealer, mut signings, partials) = sign(b"hello", &signers);
+        for (id, z) in signers.iter().zip(partials) {
+            signings[0].partial(*id, z);
+        }
+
+        let signature = signings[0].signature().unwrap().unwrap();
+        let y = &dealer.c[0];
+        assert!(verify(
+            y, b"hello", &signature, &dealer.g, &dealer.p, &dealer.q
+        ));
+        assert!(!verify(
+            y, b"hellp", &signature, &dealer.g, &dealer.p, &dealer.q
+        ));
+    }
+
+    #[test]
+    fn coordinator_detects_invalid_partial() {
+        let signers = [1, 2, 3];
+        let (_, mut signings, partials) = sign(b"hello", &signers);
+        for (id, z) in signers.iter().zip(partials) {
+            let z = if *id == 3 { z + 1_u32 } else { z };
+            signings[0].partial(*id, z);
+        }
+
+        assert_eq!(Some(Err(vec![3])), signings[0].signature());
+    }
+}
@@ -0,0 +1,226 @@
+//! Deterministic simulation of the protocol: the dealer and every Player run in the calling
+//! thread and a seeded scheduler picks which message in flight is delivered next
+//!
+//! Unlike transport::sim, which delays messages between threads, nothing here depends on
+//! timing. The same seed deals the same shares and delivers their messages in the same order, so
+//! an interleaving that breaks a property test replays exactly. Players verify shares inline and
+//! send no heartbeats.
+
+use std::collections::BTreeMap;
+use std::io;
+use std::sync::{Arc, Mutex};
+
+use num_bigint::BigUint;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+
+use crate::dealer::{Dealer, BIT_SIZE, DEALER_ID};
+use crate::error::VssError;
+use crate::player::Player;
+use crate::rpc::{WireMessage, RPC};
+use crate::secret::Secret;
+use crate::transport::Transport;
+
+/// Messages sent but not delivered yet, with their sender and recipient, in the order sent
+type InFlight = Arc<Mutex<Vec<(usize, usize, WireMessage)>>>;
+
+/// Players 1..=n exchanging messages in an order chosen by a seed
+pub struct Simulation {
+    rng: StdRng,
+    players: BTreeMap<usize, Player>,
+    in_flight: InFlight,
+    /// sender and recipient of every message delivered, in order
+    history: Vec<(usize, usize)>,
+}
+
+impl Simulation {
+    /// Players 1..=n with nothing in flight, whose messages are scheduled by `seed`
+    pub fn new(n: usize, seed: u64) -> Simulation {
+        let mut rng = StdRng::seed_from_u64(seed);
+        let in_flight = InFlight::default();
+        let players = (1..=n)
+            .map(|id| {
+                let transport = SimulatedTransport {
+                    id,
+                    n,
+                    in_flight: in_flight.clone(),
+                };
+                // players draw from the seed too, so their protocols replay as well
+                let player_rng = StdRng::from_seed(rng.gen());
+                let (player, _) = Player::build(id, Box::new(transport), 0, player_rng);
+
+                (id, player)
+            })
+            .collect();
+
+        Simulation {
+            rng,
+            players,
+            in_flight,
+            history: vec![],
+        }
+    }
+
+    /// A Transport sending into the simulation as `id`, for the dealer or a faulty participant
+    pub fn transport(&self, id: usize) -> SimulatedTransport {
+        SimulatedTransport {
+            id,
+            n: self.players.keys().max().copied().unwrap_or(0),
+            in_flight: self.in_flight.clone(),
+        }
+    }
+
+    /// Deal `secret` to every player with threshold `t`, its shares in flight once this returns.
+    /// The dealing is drawn from the seed too, so the same seed deals the same shares
+    pub fn deal(&mut self, t: usize, secret: usize) -> Result<Dealer, VssError> {
+        let secret = Secret::new(BigUint::from(secret));
+        let dealer = Dealer::with_rng(self.players.len(), t, secret, BIT_SIZE, &mut self.rng)?;
+        dealer.propagate(&self.transport(DEALER_ID));
+
+        Ok(dealer)
+    }
+
+    /// Hand a local request such as RPC::Reconstruct to player `id`, returns false if there is
+    /// no such player. Its Reply is answered once the messages it needs are delivered
+    pub fn request(&mut self, id: usize, rpc: RPC) -> bool {
+        match self.players.get_mut(&id) {
+            Some(player) => {
+                if !player.handle(rpc) {
+                    self.players.remove(&id);
+                }
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Take player `id` out, every message to it is lost from then on
+    pub fn crash(&mut self, id: usize) {
+        self.players.remove(&id);
+    }
+
+    /// Deliver the message in flight the seed picks, returns false if none is in flight
+    pub fn step(&mut self) -> bool {
+        let (from, to, msg) = {
+            let mut in_flight = self.in_flight.lock().unwrap();
+            if in_flight.is_empty() {
+                return false;
+            }
+            let next = self.rng.gen_range(0, in_flight.len());
+
+            in_flight.remove(next)
+        };
+
+        self.history.push((from, to));
+        if let Some(player) = self.players.get_mut(&to) {
+            if !player.handle(RPC::Wire(from, msg)) {
+                self.players.remove(&to);
+            }
+        }
+
+        true
+    }
+
+    /// Deliver messages until none is in flight or `max_steps` were, returns how many were
+    pub fn run(&mut self, max_steps: usize) -> usize {
+        (0..max_steps).take_while(|_| self.step()).count()
+    }
+
+    /// Let every player retransmit its broadcasts in progress, as it would when idle
+    pub fn tick(&mut self) {
+        self.players.values_mut().for_each(Player::tick);
+    }
+
+    /// How many messages are in flight
+    pub fn in_flight(&self) -> usize {
+        self.in_flight.lock().unwrap().len()
+    }
+
+    /// Sender and recipient of every message delivered so far, in order
+    pub fn history(&self) -> &[(usize, usize)] {
+        &self.history
+    }
+}
+
+/// Transport of a Simulation, queueing every message until the scheduler delivers it
+pub struct SimulatedTransport {
+    id: usize,
+    n: usize,
+    in_flight: InFlight,
+}
+
+impl Transport for SimulatedTransport {
+    fn send(&self, to: usize, msg: WireMessage) -> io::Result<()> {
+        self.in_flight.lock().unwrap().push((self.id, to, msg));
+
+        Ok(())
+    }
+
+    fn peers(&self) -> Vec<usize> {
+        (1..=self.n).filter(|id| *id != self.id).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use num_bigint::BigUint;
+
+    use crate::rpc::{Router, RPC};
+
+    use super::Simulation;
+
+    /// Deal to 5 players, have 3 of them reconstruct and return what they got and the history
+    fn run(seed: u64) -> (Vec<BigUint>, Vec<(usize, usize)>) {
+        let mut sim = Simulation::new(5, seed);
+        let dealer = sim.deal(3, 1234).unwrap();
+        sim.run(1000);
+
+        let mut router = Router::new();
+        for id in 1..=3 {
+            assert!(sim.request(id, RPC::Reconstruct(dealer.session_id, router.request())));
+        }
+        sim.run(10_000);
+        assert_eq!(0, sim.in_flight());
+
+        let secrets = (0..3)
+            .map(|_| {
+                let report = router.recv_timeout(Duration::ZERO).unwrap().1;
+
+                report.secret.expose().clone()
+            })
+            .collect();
+
+        (secrets, sim.history().to_vec())
+    }
+
+    #[test]
+    fn reconstructs_with_only_t_live_players() {
+        let mut sim = Simulation::new(5, 3);
+        let dealer = sim.deal(3, 1234).unwrap();
+        sim.run(1000);
+        sim.crash(4);
+        sim.crash(5);
+
+        let mut router = Router::new();
+        for id in 1..=3 {
+            assert!(sim.request(id, RPC::Reconstruct(dealer.session_id, router.request())));
+        }
+        sim.run(10_000);
+
+        for _ in 1..=3 {
+            let report = router.recv_timeout(Duration::ZERO).unwrap().1;
+            assert_eq!(&BigUint::from(1234_u32), report.secret.expose());
+        }
+    }
+
+    #[test]
+    fn simulation_replays_its_seed() {
+        let (secrets, history) = run(7);
+        assert_eq!(vec![BigUint::from(1234_u32); 3], secrets);
+
+        assert_eq!(history, run(7).1);
+        assert_ne!(history, run(8).1);
+    }
+}
@@ -0,0 +1,123 @@
+//! Split a secret of bytes and combine it back in one call, for applications that need no
+//! dealer, group or polynomial of their own
+//!
+//! Shares are the text of share files as `vss deal` writes them, so `vss verify` and
+//! `vss reconstruct` read them too. `split` deals over a q of at least `SPLIT_BITS` bits in a
+//! p of at least `SAFE_P_BITS`, so the commitment c_0 = g^secret keeps the secret.
+
+use std::fmt;
+use std::io;
+use std::path::PathBuf;
+
+use rand::{CryptoRng, RngCore};
+use zeroize::Zeroizing;
+
+use crate::dealer::{Dealer, SAFE_P_BITS};
+use crate::format;
+use crate::rpc::ShareInfo;
+use crate::secret::SecretBytes;
+
+/// Fewest bits of the q `split` deals over
+pub const SPLIT_BITS: usize = 256;
+
+/// A share of a secret split by `split`, the text of its share file
+///
+/// It holds a share value, so it is wiped when dropped and prints as `EncodedShare(***)`
+#[derive(Clone, PartialEq, Eq)]
+pub struct EncodedShare(Zeroizing<String>);
+
+impl EncodedShare {
+    /// The text of the share file
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+
+    /// The share file this share is
+    pub fn info(&self) -> io::Result<ShareInfo> {
+        format::read_share(self.0.as_bytes())
+    }
+}
+
+impl From<String> for EncodedShare {
+    fn from(text: String) -> EncodedShare {
+        EncodedShare(Zeroizing::new(text))
+    }
+}
+
+impl AsRef<str> for EncodedShare {
+    fn as_ref(&self) -> &str {
+        self.as_str()
+    }
+}
+
+impl fmt::Debug for EncodedShare {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("EncodedShare(***)")
+    }
+}
+
+/// Split `secret` into `n` shares, any `t` of which `combine` back into it
+pub fn split(secret: &[u8], n: usize, t: usize) -> io::Result<Vec<EncodedShare>> {
+    split_with_rng(secret, n, t, &mut rand::thread_rng())
+}
+
+/// Like `split` with the group, coefficients and session drawn from `rng`
+pub fn split_with_rng<R: RngCore + CryptoRng>(
+    secret: &[u8],
+    n: usize,
+    t: usize,
+    rng: &mut R,
+) -> io::Result<Vec<EncodedShare>> {
+    let secret = format::encode_secret(secret);
+    let dealer = Dealer::with_sizes_rng(n, t, secret, SPLIT_BITS, SAFE_P_BITS, rng)?;
+    let params = dealer.params();
+
+    dealer
+        .shares
+        .iter()
+        .map(|share| {
+            let mut json = Zeroizing::new(vec![]);
+            format::write_share(
+                &mut *json,
+                &ShareInfo::with_params(share.clone(), params.clone()),
+            )?;
+            let text = String::from_utf8(json.to_vec())
+                .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))?;
+
+            Ok(EncodedShare::from(text))
+        })
+        .collect()
+}
+
+/// The secret of `shares`, which must all verify, come from the same dealing and be at least
+/// its threshold
+pub fn combine(shares: &[EncodedShare]) -> io::Result<SecretBytes> {
+    // errors name a share by its position, as they would name its file
+    let infos = shares
+        .iter()
+        .enumerate()
+        .map(|(i, share)| Ok((PathBuf::from(format!("share {}", i)), share.info()?)))
+        .collect::<io::Result<Vec<_>>>()?;
+    format::decode_secret(&format::reconstruct_shares(&infos)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{combine, split, EncodedShare};
+
+    #[test]
+    fn any_t_shares_combine() {
+        let secret = b"\x00one shot";
+        let shares = split(secret, 5, 3).unwrap();
+
+        assert_eq!(secret, combine(&shares[2..]).unwrap().expose());
+        assert_eq!(secret, combine(&shares[..3]).unwrap().expose());
+        assert!(combine(&shares[..2]).is_err());
+        assert_eq!("EncodedShare(***)", format!("{:?}", shares[0]));
+
+        let mut forged = shares[..3].to_vec();
+        forged[0] = EncodedShare::from(forged[0].as_str().replacen('"', "'", 1));
+        assert!(combine(&forged).is_err());
+        assert!(split(secret, 2, 3).is_err());
+    }
+}
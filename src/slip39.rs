@@ -0,0 +1,561 @@
+//! SLIP-0039 mnemonic shares, so a secret can be backed up on paper and restored with any
+//! wallet tooling that reads SLIP-0039
+//!
+//! The mnemonics are a sharing of their own over GF(256), two levels deep: the secret is split
+//! among groups and each group's share among its members. They carry no commitments, deal the
+//! secret they recover again for a verifiable sharing.
+
+use std::collections::BTreeMap;
+use std::io;
+
+use hmac::{Hmac, Mac};
+use rand::{CryptoRng, Rng, RngCore};
+use sha2::Sha256;
+
+const WORDS: &str = include_str!("slip39/words.txt");
+
+/// Bits of a word's index
+const RADIX_BITS: usize = 10;
+/// Words of the checksum ending every mnemonic
+const CHECKSUM_WORDS: usize = 3;
+/// Words of the id, exponent, group and member fields starting every mnemonic
+const HEADER_WORDS: usize = 4;
+/// A mnemonic of a 128 bit secret, the shortest allowed
+const MIN_WORDS: usize = 20;
+const MIN_SECRET_BYTES: usize = 16;
+const MAX_SHARES: usize = 16;
+
+/// x coordinates of the secret and of its digest, never given to a share
+const SECRET_INDEX: u8 = 255;
+const DIGEST_INDEX: u8 = 254;
+const DIGEST_BYTES: usize = 4;
+
+const BASE_ITERATION_COUNT: u32 = 10000;
+const ROUND_COUNT: u8 = 4;
+
+/// Shares of one level of a split, each its x coordinate and value
+type Shares = Vec<(u8, Vec<u8>)>;
+
+fn invalid_input(msg: impl Into<String>) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidInput, msg.into())
+}
+
+fn invalid_data(msg: impl Into<String>) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg.into())
+}
+
+/// Split `secret` into mnemonics of `groups`, each a (member threshold, member count), any
+/// `group_threshold` of which recover it with `passphrase`
+///
+/// The secret is encrypted with the passphrase, 10000 << `iteration_exponent` PBKDF2 rounds
+/// in all. Returns the mnemonics of every group in turn
+pub fn split(
+    secret: &[u8],
+    passphrase: &[u8],
+    group_threshold: u8,
+    groups: &[(u8, u8)],
+    iteration_exponent: u8,
+) -> io::Result<Vec<Vec<String>>> {
+    split_with_rng(
+        secret,
+        passphrase,
+        group_threshold,
+        groups,
+        iteration_exponent,
+        &mut rand::thread_rng(),
+    )
+}
+
+/// Like `split` with the identifier and shares drawn from `rng`, so a seeded rng writes the
+/// same mnemonics every time
+pub fn split_with_rng<R: RngCore + CryptoRng>(
+    secret: &[u8],
+    passphrase: &[u8],
+    group_threshold: u8,
+    groups: &[(u8, u8)],
+    iteration_exponent: u8,
+    rng: &mut R,
+) -> io::Result<Vec<Vec<String>>> {
+    if secret.len() < MIN_SECRET_BYTES || !secret.len().is_multiple_of(2) {
+        return Err(invalid_input(format!(
+            "the secret must be at least {} bytes of even length",
+            MIN_SECRET_BYTES
+        )));
+    }
+    if group_threshold == 0 || group_threshold as usize > groups.len() {
+        return Err(invalid_input(
+            "the group threshold must be between 1 and the groups",
+        ));
+    }
+    if groups.len() > MAX_SHARES {
+        return Err(invalid_input(format!("at most {} groups", MAX_SHARES)));
+    }
+    for &(threshold, count) in groups {
+        if threshold == 0 || threshold > count || count as usize > MAX_SHARES {
+            return Err(invalid_input(format!(
+                "a group's threshold must be between 1 and its count, at most {}",
+                MAX_SHARES
+            )));
+        }
+        if threshold == 1 && count > 1 {
+            return Err(invalid_input(
+                "a group with threshold 1 has a single member",
+            ));
+        }
+    }
+    if iteration_exponent > 15 {
+        return Err(invalid_input("the iteration exponent is at most 15"));
+    }
+
+    let id = rng.gen_range(0, 1 << 15);
+    let encrypted = crypt(secret, passphrase, iteration_exponent, id, true);
+    let group_shares = split_secret(group_threshold, groups.len() as u8, &encrypted, rng);
+
+    Ok(groups
+        .iter()
+        .zip(group_shares)
+        .map(
+            |(&(member_threshold, member_count), (group_index, group_share))| {
+                split_secret(member_threshold, member_count, &group_share, rng)
+                    .into_iter()
+                    .map(|(member_index, value)| {
+                        Mnemonic {
+                            id,
+                            iteration_exponent,
+                            group_index,
+                            group_threshold,
+                            group_count: groups.len() as u8,
+                            member_index,
+                            member_threshold,
+                            value,
+                        }
+                        .encode()
+                    })
+                    .collect()
+            },
+        )
+        .collect())
+}
+
+/// Recover the secret of `mnemonics` with the `passphrase` they were split with
+///
+/// Any mnemonics of the same split will do as long as enough groups have enough members.
+/// A wrong passphrase is not detected, it gives a different secret
+pub fn combine<S: AsRef<str>>(mnemonics: &[S], passphrase: &[u8]) -> io::Result<Vec<u8>> {
+    let mnemonics = mnemonics
+        .iter()
+        .map(|mnemonic| Mnemonic::decode(mnemonic.as_ref()))
+        .collect::<io::Result<Vec<_>>>()?;
+    let first = mnemonics
+        .first()
+        .ok_or_else(|| invalid_input("no mnemonics to combine"))?;
+
+    let mut groups: BTreeMap<u8, (u8, Shares)> = BTreeMap::new();
+    for mnemonic in &mnemonics {
+        if (mnemonic.id, mnemonic.iteration_exponent) != (first.id, first.iteration_exponent) {
+            return Err(invalid_data("mnemonics from different splits"));
+        }
+        if (mnemonic.group_threshold, mnemonic.group_count)
+            != (first.group_threshold, first.group_count)
+        {
+            return Err(invalid_data("mnemonics disagree on the groups"));
+        }
+        if mnemonic.group_threshold > mnemonic.group_count {
+            return Err(invalid_data("group threshold above the number of groups"));
+        }
+
+        let (member_threshold, members) = groups
+            .entry(mnemonic.group_index)
+            .or_insert((mnemonic.member_threshold, vec![]));
+        if *member_threshold != mnemonic.member_threshold {
+            return Err(invalid_data(format!(
+                "mnemonics of group {} disagree on its threshold",
+                mnemonic.group_index + 1
+            )));
+        }
+        match members
+            .iter()
+            .find(|(index, _)| *index == mnemonic.member_index)
+        {
+            Some((_, value)) if *value != mnemonic.value => {
+                return Err(invalid_data(format!(
+                    "two mnemonics for member {} of group {}",
+                    mnemonic.member_index + 1,
+                    mnemonic.group_index + 1
+                )))
+            }
+            Some(_) => {}
+            None => members.push((mnemonic.member_index, mnemonic.value.clone())),
+        }
+    }
+
+    let group_shares = groups
+        .into_iter()
+        .filter(|(_, (threshold, members))| members.len() >= *threshold as usize)
+        .take(first.group_threshold as usize)
+        .map(|(index, (threshold, members))| {
+            Ok((
+                index,
+                recover_secret(threshold, &members[..threshold as usize])?,
+            ))
+        })
+        .collect::<io::Result<Vec<_>>>()?;
+    if group_shares.len() < first.group_threshold as usize {
+        return Err(invalid_input(format!(
+            "{} complete groups, {} needed",
+            group_shares.len(),
+            first.group_threshold
+        )));
+    }
+
+    let encrypted = recover_secret(first.group_threshold, &group_shares)?;
+
+    Ok(crypt(
+        &encrypted,
+        passphrase,
+        first.iteration_exponent,
+        first.id,
+        false,
+    ))
+}
+
+/// One mnemonic share, as its fields
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Mnemonic {
+    id: u16,
+    iteration_exponent: u8,
+    group_index: u8,
+    group_threshold: u8,
+    group_count: u8,
+    member_index: u8,
+    member_threshold: u8,
+    value: Vec<u8>,
+}
+
+impl Mnemonic {
+    fn encode(&self) -> String {
+        // the extendable flag between id and exponent stays clear
+        let id_exp = (u32::from(self.id) << 5) | u32::from(self.iteration_exponent);
+        let share_params = (u32::from(self.group_index) << 16)
+            | (u32::from(self.group_threshold - 1) << 12)
+            | (u32::from(self.group_count - 1) << 8)
+            | (u32::from(self.member_index) << 4)
+            | u32::from(self.member_threshold - 1);
+
+        let mut data = vec![
+            id_exp >> RADIX_BITS,
+            id_exp & 1023,
+            share_params >> RADIX_BITS,
+            share_params & 1023,
+        ];
+        data.extend(to_words(&self.value));
+        let checksum = rs1024_checksum(customization(false), &data);
+        data.extend(checksum);
+
+        let words: Vec<&str> = WORDS.lines().collect();
+        data.iter()
+            .map(|&index| words[index as usize])
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+
+    fn decode(mnemonic: &str) -> io::Result<Mnemonic> {
+        let words: Vec<&str> = WORDS.lines().collect();
+        let data = mnemonic
+            .split_whitespace()
+            .map(|word| {
+                words
+                    .binary_search(&word.to_lowercase().as_str())
+                    .map(|index| index as u32)
+                    .map_err(|_| invalid_data(format!("{} is not a SLIP-0039 word", word)))
+            })
+            .collect::<io::Result<Vec<u32>>>()?;
+        if data.len() < MIN_WORDS {
+            return Err(invalid_data(format!(
+                "a mnemonic has at least {} words",
+                MIN_WORDS
+            )));
+        }
+
+        let id_exp = (data[0] << RADIX_BITS) | data[1];
+        let extendable = (id_exp >> 4) & 1 == 1;
+        if rs1024_polymod(customization(extendable), &data) != 1 {
+            return Err(invalid_data("mnemonic checksum does not match"));
+        }
+        if extendable {
+            return Err(invalid_data("extendable backups are not supported"));
+        }
+
+        let share_params = (data[2] << RADIX_BITS) | data[3];
+        let value = from_words(&data[HEADER_WORDS..data.len() - CHECKSUM_WORDS])?;
+        if value.len() < MIN_SECRET_BYTES {
+            return Err(invalid_data("mnemonic too short for a secret"));
+        }
+
+        Ok(Mnemonic {
+            id: (id_exp >> 5) as u16,
+            iteration_exponent: (id_exp & 15) as u8,
+            group_index: (share_params >> 16) as u8,
+            group_threshold: ((share_params >> 12) & 15) as u8 + 1,
+            group_count: ((share_params >> 8) & 15) as u8 + 1,
+            member_index: ((share_params >> 4) & 15) as u8,
+            member_threshold: (share_params & 15) as u8 + 1,
+            value,
+        })
+    }
+}
+
+/// The words of `bytes`, left padded with zero bits to a whole number of words
+fn to_words(bytes: &[u8]) -> Vec<u32> {
+    let bits = bytes.len() * 8;
+    let padding = (RADIX_BITS - bits % RADIX_BITS) % RADIX_BITS;
+
+    let mut words = vec![];
+    let (mut acc, mut acc_bits) = (0_u32, padding);
+    for &byte in bytes {
+        acc = (acc << 8) | u32::from(byte);
+        acc_bits += 8;
+        if acc_bits >= RADIX_BITS {
+            acc_bits -= RADIX_BITS;
+            words.push((acc >> acc_bits) & 1023);
+            acc &= (1 << acc_bits) - 1;
+        }
+    }
+
+    words
+}
+
+/// The bytes of a secret padded into `words`, which are a whole number of 16 bit halves
+fn from_words(words: &[u32]) -> io::Result<Vec<u8>> {
+    let padding = words.len() * RADIX_BITS % 16;
+    if padding > 8 {
+        return Err(invalid_data("mnemonic has the wrong number of words"));
+    }
+
+    let mut bytes = vec![];
+    let (mut acc, mut acc_bits) = (0_u32, 0);
+    for (i, &word) in words.iter().enumerate() {
+        acc = (acc << RADIX_BITS) | word;
+        acc_bits += RADIX_BITS;
+        // the padding is at the top of the first word
+        if i == 0 {
+            acc_bits -= padding;
+            if acc >> acc_bits != 0 {
+                return Err(invalid_data("mnemonic padding is not zero"));
+            }
+        }
+        while acc_bits >= 8 {
+            acc_bits -= 8;
+            bytes.push((acc >> acc_bits) as u8);
+            acc &= (1 << acc_bits) - 1;
+        }
+    }
+
+    Ok(bytes)
+}
+
+fn customization(extendable: bool) -> &'static [u8] {
+    if extendable {
+        b"shamir_extendable"
+    } else {
+        b"shamir"
+    }
+}
+
+/// Reed-Solomon remainder over GF(1024) of the customization string and `data`, 1 for a
+/// valid mnemonic
+fn rs1024_polymod(customization: &[u8], data: &[u32]) -> u32 {
+    const GEN: [u32; 10] = [
+        0x00e0_e040,
+        0x01c1_c080,
+        0x0383_8100,
+        0x0707_0200,
+        0x0e0e_0009,
+        0x1c0c_2412,
+        0x3808_6c24,
+        0x3090_fc48,
+        0x21b1_f890,
+        0x03f3_f120,
+    ];
+
+    let values = customization
+        .iter()
+        .map(|&b| u32::from(b))
+        .chain(data.iter().copied());
+    values.fold(1, |chk, value| {
+        let b = chk >> 20;
+        let chk = ((chk & 0xfffff) << RADIX_BITS) ^ value;
+
+        GEN.iter()
+            .enumerate()
+            .filter(|(i, _)| (b >> i) & 1 == 1)
+            .fold(chk, |chk, (_, gen)| chk ^ gen)
+    })
+}
+
+fn rs1024_checksum(customization: &[u8], data: &[u32]) -> Vec<u32> {
+    let padded = [data, &[0; CHECKSUM_WORDS]].concat();
+    let polymod = rs1024_polymod(customization, &padded) ^ 1;
+
+    (0..CHECKSUM_WORDS)
+        .map(|i| (polymod >> (RADIX_BITS * (CHECKSUM_WORDS - 1 - i))) & 1023)
+        .collect()
+}
+
+/// Exponent and logarithm tables of GF(256) with the AES polynomial, generator 3
+fn gf256_tables() -> ([u8; 255], [u8; 256]) {
+    let mut exp = [0; 255];
+    let mut log = [0; 256];
+    let mut x: u8 = 1;
+    for (i, e) in exp.iter_mut().enumerate() {
+        *e = x;
+        log[x as usize] = i as u8;
+        // x * 3 = x * 2 + x
+        let doubled = (x << 1) ^ if x & 0x80 != 0 { 0x1b } else { 0 };
+        x ^= doubled;
+    }
+
+    (exp, log)
+}
+
+/// The value at `x` of the polynomials through `shares`, one per byte, over GF(256) with the
+/// AES polynomial
+pub(crate) fn interpolate(shares: &[(u8, Vec<u8>)], x: u8) -> Vec<u8> {
+    if let Some((_, value)) = shares.iter().find(|(index, _)| *index == x) {
+        return value.clone();
+    }
+
+    let (exp, log) = gf256_tables();
+    let log_of = |v: u8| i32::from(log[v as usize]);
+    let log_prod: i32 = shares.iter().map(|(index, _)| log_of(index ^ x)).sum();
+
+    let mut result = vec![0; shares[0].1.len()];
+    for (index, value) in shares {
+        let log_basis = (log_prod
+            - log_of(index ^ x)
+            - shares
+                .iter()
+                .filter(|(other, _)| other != index)
+                .map(|(other, _)| log_of(index ^ other))
+                .sum::<i32>())
+        .rem_euclid(255);
+
+        for (r, &v) in result.iter_mut().zip(value) {
+            if v != 0 {
+                *r ^= exp[((log_of(v) + log_basis) % 255) as usize];
+            }
+        }
+    }
+
+    result
+}
+
+fn digest(random: &[u8], secret: &[u8]) -> Vec<u8> {
+    let mut mac = Hmac::<Sha256>::new_from_slice(random).expect("HMAC takes keys of any size");
+    mac.update(secret);
+
+    mac.finalize().into_bytes()[..DIGEST_BYTES].to_vec()
+}
+
+/// `count` shares of `secret`, any `threshold` of which recover it along with a digest
+fn split_secret<R: Rng>(threshold: u8, count: u8, secret: &[u8], rng: &mut R) -> Shares {
+    if threshold == 1 {
+        return (0..count).map(|index| (index, secret.to_vec())).collect();
+    }
+
+    let mut random = |len| {
+        let mut bytes = vec![0; len];
+        rng.fill(&mut bytes[..]);
+
+        bytes
+    };
+
+    let random_count = threshold - 2;
+    let mut shares: Shares = (0..random_count)
+        .map(|index| (index, random(secret.len())))
+        .collect();
+    let random_part = random(secret.len() - DIGEST_BYTES);
+    let digest_share = [digest(&random_part, secret), random_part].concat();
+
+    let mut base = shares.clone();
+    base.push((DIGEST_INDEX, digest_share));
+    base.push((SECRET_INDEX, secret.to_vec()));
+    shares.extend((random_count..count).map(|index| (index, interpolate(&base, index))));
+
+    shares
+}
+
+/// The secret of `threshold` shares, checked against the digest shared along with it
+fn recover_secret(threshold: u8, shares: &[(u8, Vec<u8>)]) -> io::Result<Vec<u8>> {
+    if threshold == 1 {
+        return Ok(shares[0].1.clone());
+    }
+
+    let secret = interpolate(shares, SECRET_INDEX);
+    let digest_share = interpolate(shares, DIGEST_INDEX);
+    let (expected, random) = digest_share.split_at(DIGEST_BYTES);
+    if digest(random, &secret) != expected {
+        return Err(invalid_data("shares do not match their digest"));
+    }
+
+    Ok(secret)
+}
+
+/// Encrypt or decrypt `input` with a four round Feistel network keyed by the passphrase
+fn crypt(input: &[u8], passphrase: &[u8], exponent: u8, id: u16, encrypt: bool) -> Vec<u8> {
+    let salt = [b"shamir".as_slice(), &id.to_be_bytes()].concat();
+    let iterations = (BASE_ITERATION_COUNT / u32::from(ROUND_COUNT)) << exponent;
+
+    let (l, r) = input.split_at(input.len() / 2);
+    let (mut l, mut r) = (l.to_vec(), r.to_vec());
+    let rounds: Vec<u8> = if encrypt {
+        (0..ROUND_COUNT).collect()
+    } else {
+        (0..ROUND_COUNT).rev().collect()
+    };
+    for round in rounds {
+        let password = [&[round], passphrase].concat();
+        let mut f = vec![0; r.len()];
+        pbkdf2::pbkdf2_hmac::<Sha256>(&password, &[&salt, &r[..]].concat(), iterations, &mut f);
+
+        let next = l.iter().zip(&f).map(|(a, b)| a ^ b).collect();
+        l = std::mem::replace(&mut r, next);
+    }
+
+    [r, l].concat()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{combine, split};
+
+    #[test]
+    fn reference_vectors() {
+        let vectors: Vec<(String, Vec<String>, String)> =
+            serde_json::from_str(include_str!("slip39/vectors.json")).unwrap();
+
+        for (description, mnemonics, secret) in vectors {
+            match combine(&mnemonics, b"TREZOR") {
+                Ok(recovered) => assert_eq!(secret, hex::encode(recovered), "{}", description),
+                Err(err) => assert!(secret.is_empty(), "{}: {}", description, err),
+            }
+        }
+    }
+
+    #[test]
+    fn groups_roundtrip() {
+        let secret = b"sixteen byte key";
+        let groups = split(secret, b"pass", 2, &[(1, 1), (2, 3), (3, 5)], 0).unwrap();
+        assert_eq!(
+            vec![1, 3, 5],
+            groups.iter().map(Vec::len).collect::<Vec<_>>()
+        );
+
+        let mnemonics = [&groups[1][..2], &groups[2][1..4]].concat();
+        assert_eq!(secret.to_vec(), combine(&mnemonics, b"pass").unwrap());
+
+        // one member short in the second group
+        let mnemonics = [&groups[1][..2], &groups[2][..2]].concat();
+        assert!(combine(&mnemonics, b"pass").is_err());
+    }
+}
@@ -0,0 +1,533 @@
+//! Shares in the format of the `ssss` tool, `ssss-split` and `ssss-combine`, so share sets
+//! made with it can be brought over
+//!
+//! A share is `[token-]<index>-<hex>`, the value at its index of a polynomial over GF(2^m),
+//! m eight times the bytes of the secret. Like `ssss`, secrets of 8 bytes and more go through
+//! its XTEA diffusion layer unless it is turned off, as `-D` does. The shares carry no
+//! commitments, deal the secret they recover again for a verifiable sharing.
+
+use std::io;
+
+use rand::{CryptoRng, Rng, RngCore};
+
+const MAX_SECRET_BYTES: usize = 128;
+/// Bytes of the shortest secret the diffusion layer applies to, one XTEA block
+const MIN_DIFFUSION_BYTES: usize = 8;
+/// Rounds of the diffusion layer per byte of the secret
+const DIFFUSION_ROUNDS: usize = 40;
+const XTEA_DELTA: u32 = 0x9e37_79b9;
+const XTEA_CYCLES: u32 = 32;
+
+/// The middle exponents a, b, c of the pentanomial x^m + x^a + x^b + x^c + 1 that `ssss`
+/// reduces by, for m from 8 to 1024 in steps of 8
+const IRREDUCIBLE: [[u8; 3]; MAX_SECRET_BYTES] = [
+    [4, 3, 1],
+    [5, 3, 1],
+    [4, 3, 1],
+    [7, 3, 2],
+    [5, 4, 3],
+    [5, 3, 2],
+    [7, 4, 2],
+    [4, 3, 1],
+    [10, 9, 3],
+    [9, 4, 2],
+    [7, 6, 2],
+    [10, 9, 6],
+    [4, 3, 1],
+    [5, 4, 3],
+    [4, 3, 1],
+    [7, 2, 1],
+    [5, 3, 2],
+    [7, 4, 2],
+    [6, 3, 2],
+    [5, 3, 2],
+    [15, 3, 2],
+    [11, 3, 2],
+    [9, 8, 7],
+    [7, 2, 1],
+    [5, 3, 2],
+    [9, 3, 1],
+    [7, 3, 1],
+    [9, 8, 3],
+    [9, 4, 2],
+    [8, 5, 3],
+    [15, 14, 10],
+    [10, 5, 2],
+    [9, 6, 2],
+    [9, 3, 2],
+    [9, 5, 2],
+    [11, 10, 1],
+    [7, 3, 2],
+    [11, 2, 1],
+    [9, 7, 4],
+    [4, 3, 1],
+    [8, 3, 1],
+    [7, 4, 1],
+    [7, 2, 1],
+    [13, 11, 6],
+    [5, 3, 2],
+    [7, 3, 2],
+    [8, 7, 5],
+    [12, 3, 2],
+    [13, 10, 6],
+    [5, 3, 2],
+    [5, 3, 2],
+    [9, 5, 2],
+    [9, 7, 2],
+    [13, 4, 3],
+    [4, 3, 1],
+    [11, 6, 4],
+    [18, 9, 6],
+    [19, 18, 13],
+    [11, 3, 2],
+    [15, 9, 6],
+    [4, 3, 1],
+    [16, 5, 2],
+    [15, 14, 6],
+    [8, 5, 2],
+    [15, 11, 2],
+    [11, 6, 2],
+    [7, 5, 3],
+    [8, 3, 1],
+    [19, 16, 9],
+    [11, 9, 6],
+    [15, 7, 6],
+    [13, 4, 3],
+    [14, 13, 3],
+    [13, 6, 3],
+    [9, 5, 2],
+    [19, 13, 6],
+    [19, 10, 3],
+    [11, 6, 5],
+    [9, 2, 1],
+    [14, 3, 2],
+    [13, 3, 1],
+    [7, 5, 4],
+    [11, 9, 8],
+    [11, 6, 5],
+    [23, 16, 9],
+    [19, 14, 6],
+    [23, 10, 2],
+    [8, 3, 2],
+    [5, 4, 3],
+    [9, 6, 4],
+    [4, 3, 2],
+    [13, 8, 6],
+    [13, 11, 1],
+    [13, 10, 3],
+    [11, 6, 5],
+    [19, 17, 4],
+    [15, 14, 7],
+    [13, 9, 6],
+    [9, 7, 3],
+    [9, 7, 1],
+    [14, 3, 2],
+    [11, 8, 2],
+    [11, 6, 4],
+    [13, 5, 2],
+    [11, 5, 1],
+    [11, 4, 1],
+    [19, 10, 3],
+    [21, 10, 6],
+    [13, 3, 1],
+    [15, 7, 5],
+    [19, 18, 10],
+    [7, 5, 3],
+    [12, 7, 2],
+    [7, 5, 1],
+    [14, 9, 6],
+    [10, 3, 2],
+    [15, 13, 12],
+    [12, 11, 9],
+    [16, 9, 7],
+    [12, 9, 3],
+    [9, 5, 2],
+    [17, 10, 6],
+    [24, 9, 3],
+    [17, 15, 13],
+    [5, 4, 3],
+    [19, 17, 8],
+    [15, 6, 3],
+    [19, 6, 1],
+];
+
+/// An element of GF(2^m), its bits as little endian limbs with room for bit m
+type Element = Vec<u64>;
+
+fn invalid_input(msg: impl Into<String>) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidInput, msg.into())
+}
+
+fn invalid_data(msg: impl Into<String>) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg.into())
+}
+
+/// Split `secret` into `count` shares as `ssss-split` writes them, any `threshold` of which
+/// recover it, each starting with `token-` if given
+pub fn split(
+    secret: &[u8],
+    threshold: usize,
+    count: usize,
+    token: Option<&str>,
+    diffusion: bool,
+) -> io::Result<Vec<String>> {
+    split_with_rng(
+        secret,
+        threshold,
+        count,
+        token,
+        diffusion,
+        &mut rand::thread_rng(),
+    )
+}
+
+/// Like `split` with the coefficients drawn from `rng`, so a seeded rng writes the same
+/// shares every time
+pub fn split_with_rng<R: RngCore + CryptoRng>(
+    secret: &[u8],
+    threshold: usize,
+    count: usize,
+    token: Option<&str>,
+    diffusion: bool,
+    rng: &mut R,
+) -> io::Result<Vec<String>> {
+    let field = Field::new(secret.len())?;
+    if threshold < 2 || threshold > count {
+        return Err(invalid_input(
+            "the threshold must be between 2 and the number of shares",
+        ));
+    }
+    if field.degree < 64 && count >> field.degree != 0 {
+        return Err(invalid_input(format!(
+            "a {} bit secret has at most {} shares",
+            field.degree,
+            (1_u64 << field.degree) - 1
+        )));
+    }
+    if token.is_some_and(|token| token.is_empty() || token.contains('-')) {
+        return Err(invalid_input("the token must be non-empty and without -"));
+    }
+
+    let mut coefficients = vec![field.import(secret)];
+    if diffusion {
+        coefficients[0] = field.diffuse(&coefficients[0], true);
+    }
+    for _ in 1..threshold {
+        let mut bytes = vec![0; secret.len()];
+        rng.fill(&mut bytes[..]);
+        coefficients.push(field.import(&bytes));
+    }
+
+    let width = count.to_string().len();
+    Ok((1..=count)
+        .map(|index| {
+            let value = field.export(&field.evaluate(&coefficients, index as u64));
+            let prefix = token.map(|token| format!("{}-", token)).unwrap_or_default();
+
+            format!("{}{:0width$}-{}", prefix, index, hex::encode(value))
+        })
+        .collect())
+}
+
+/// The secret of shares as `ssss-split` writes them, from the first `threshold` as
+/// `ssss-combine` does
+pub fn combine<S: AsRef<str>>(
+    shares: &[S],
+    threshold: usize,
+    diffusion: bool,
+) -> io::Result<Vec<u8>> {
+    if threshold < 2 || shares.len() < threshold {
+        return Err(invalid_input(format!(
+            "{} shares given, the threshold of at least 2 needs {}",
+            shares.len(),
+            threshold.max(2)
+        )));
+    }
+
+    let mut parsed: Vec<(u64, Vec<u8>)> = vec![];
+    for share in &shares[..threshold] {
+        let (index, value) = parse(share.as_ref())?;
+        if parsed.iter().any(|(other, _)| *other == index) {
+            return Err(invalid_data(format!("share {} given twice", index)));
+        }
+        if parsed
+            .first()
+            .is_some_and(|(_, first)| first.len() != value.len())
+        {
+            return Err(invalid_data("shares are of secrets of different lengths"));
+        }
+        parsed.push((index, value));
+    }
+
+    let field = Field::new(parsed[0].1.len())?;
+    // ssss's polynomial is monic, take its leading x^t off before interpolating at zero
+    let points: Vec<(Element, Element)> = parsed
+        .iter()
+        .map(|(index, value)| {
+            let x = field.constant(*index);
+            let mut y = field.import(value);
+            add(&mut y, &field.power(&x, threshold));
+
+            (x, y)
+        })
+        .collect();
+
+    let mut secret = field.zero();
+    for (i, (x_i, y_i)) in points.iter().enumerate() {
+        let (mut numerator, mut denominator) = (field.constant(1), field.constant(1));
+        for (_, (x_j, _)) in points.iter().enumerate().filter(|(j, _)| *j != i) {
+            numerator = field.mul(&numerator, x_j);
+            let mut difference = x_j.clone();
+            add(&mut difference, x_i);
+            denominator = field.mul(&denominator, &difference);
+        }
+        let basis = field.mul(&numerator, &field.inverse(&denominator));
+        add(&mut secret, &field.mul(y_i, &basis));
+    }
+    if diffusion {
+        secret = field.diffuse(&secret, false);
+    }
+
+    Ok(field.export(&secret))
+}
+
+/// Index and value of a `[token-]<index>-<hex>` share
+fn parse(share: &str) -> io::Result<(u64, Vec<u8>)> {
+    let share = share.trim();
+    let mut parts: Vec<&str> = share.rsplitn(3, '-').collect();
+    parts.reverse();
+    let (index, value) = match parts[..] {
+        [_, index, value] | [index, value] => (index, value),
+        _ => return Err(invalid_data(format!("{} is not [token-]index-hex", share))),
+    };
+
+    let index: u64 = index
+        .parse()
+        .map_err(|err| invalid_data(format!("index of {}: {}", share, err)))?;
+    let value =
+        hex::decode(value).map_err(|err| invalid_data(format!("value of {}: {}", share, err)))?;
+    let fits = value.len() >= 8 || index >> (8 * value.len()) == 0;
+    if index == 0 || !fits {
+        return Err(invalid_data(format!("{} has an index out of range", share)));
+    }
+
+    Ok((index, value))
+}
+
+/// GF(2^m) as `ssss` computes in it
+struct Field {
+    degree: usize,
+    taps: [usize; 3],
+}
+
+impl Field {
+    /// The field of secrets `bytes` long
+    fn new(bytes: usize) -> io::Result<Field> {
+        if bytes == 0 || bytes > MAX_SECRET_BYTES {
+            return Err(invalid_input(format!(
+                "the secret must be 1 to {} bytes",
+                MAX_SECRET_BYTES
+            )));
+        }
+
+        Ok(Field {
+            degree: 8 * bytes,
+            taps: IRREDUCIBLE[bytes - 1].map(usize::from),
+        })
+    }
+
+    fn zero(&self) -> Element {
+        vec![0; self.degree / 64 + 1]
+    }
+
+    fn constant(&self, n: u64) -> Element {
+        let mut element = self.zero();
+        element[0] = n;
+
+        element
+    }
+
+    /// The element of big endian `bytes`, at most m bits
+    fn import(&self, bytes: &[u8]) -> Element {
+        let mut element = self.zero();
+        for (i, byte) in bytes.iter().rev().enumerate() {
+            element[i / 8] |= u64::from(*byte) << (8 * (i % 8));
+        }
+
+        element
+    }
+
+    /// The m / 8 big endian bytes of `element`
+    fn export(&self, element: &Element) -> Vec<u8> {
+        (0..self.degree / 8)
+            .rev()
+            .map(|i| (element[i / 8] >> (8 * (i % 8))) as u8)
+            .collect()
+    }
+
+    /// Multiply `element` by x in place
+    fn times_x(&self, element: &mut Element) {
+        let mut carry = 0;
+        for limb in element.iter_mut() {
+            let next = *limb >> 63;
+            *limb = (*limb << 1) | carry;
+            carry = next;
+        }
+
+        if element[self.degree / 64] >> (self.degree % 64) & 1 == 1 {
+            for bit in [self.degree, 0].into_iter().chain(self.taps) {
+                element[bit / 64] ^= 1 << (bit % 64);
+            }
+        }
+    }
+
+    fn mul(&self, a: &Element, b: &Element) -> Element {
+        let mut product = self.zero();
+        for bit in (0..self.degree).rev() {
+            self.times_x(&mut product);
+            if b[bit / 64] >> (bit % 64) & 1 == 1 {
+                add(&mut product, a);
+            }
+        }
+
+        product
+    }
+
+    fn power(&self, element: &Element, exponent: usize) -> Element {
+        (0..exponent).fold(self.constant(1), |acc, _| self.mul(&acc, element))
+    }
+
+    /// The inverse of a nonzero `element`, its 2^m - 2th power
+    fn inverse(&self, element: &Element) -> Element {
+        let mut square = element.clone();
+        let mut inverse = self.constant(1);
+        for _ in 1..self.degree {
+            square = self.mul(&square, &square);
+            inverse = self.mul(&inverse, &square);
+        }
+
+        inverse
+    }
+
+    /// The value at `index` of x^t plus the polynomial of the t `coefficients`, lowest first
+    fn evaluate(&self, coefficients: &[Element], index: u64) -> Element {
+        let x = self.constant(index);
+        let mut y = x.clone();
+        for coefficient in coefficients[1..].iter().rev() {
+            add(&mut y, coefficient);
+            y = self.mul(&y, &x);
+        }
+        add(&mut y, &coefficients[0]);
+
+        y
+    }
+
+    /// `ssss`'s diffusion layer over `secret` or, unless `forward`, its inverse; secrets
+    /// shorter than a block are left as they are
+    fn diffuse(&self, secret: &Element, forward: bool) -> Element {
+        let len = self.degree / 8;
+        if len < MIN_DIFFUSION_BYTES {
+            return secret.clone();
+        }
+
+        // the bytes as ssss lays them out, 16 bit words least significant first, each big
+        // endian, with the lone byte of an odd length moved down next to the others
+        let mut bytes = self.export(secret);
+        bytes.reverse();
+        bytes.resize(len.next_multiple_of(2), 0);
+        let mut data: Vec<u8> = bytes.chunks(2).flat_map(|w| [w[1], w[0]]).collect();
+        if len % 2 == 1 {
+            data[len - 1] = data[len];
+        }
+
+        let starts = (0..DIFFUSION_ROUNDS * len).step_by(2);
+        if forward {
+            starts.for_each(|start| diffuse_block(&mut data[..len], start, true));
+        } else {
+            starts
+                .rev()
+                .for_each(|start| diffuse_block(&mut data[..len], start, false));
+        }
+
+        if len % 2 == 1 {
+            data[len] = data[len - 1];
+            data[len - 1] = 0;
+        }
+        let mut bytes: Vec<u8> = data.chunks(2).flat_map(|w| [w[1], w[0]]).collect();
+        bytes.truncate(len);
+        bytes.reverse();
+
+        self.import(&bytes)
+    }
+}
+
+fn add(a: &mut Element, b: &Element) {
+    a.iter_mut().zip(b).for_each(|(a, b)| *a ^= b);
+}
+
+/// Encipher or decipher the 8 bytes of `data` from `start` on, wrapping around, with XTEA
+/// under an all zero key
+fn diffuse_block(data: &mut [u8], start: usize, encipher: bool) {
+    let len = data.len();
+    let at = |i: usize| (start + i) % len;
+    let word = |offset: usize| u32::from_be_bytes([0, 1, 2, 3].map(|i| data[at(offset + i)]));
+    let mut v = [word(0), word(4)];
+
+    let mix = |w: u32| ((w << 4) ^ (w >> 5)).wrapping_add(w);
+    if encipher {
+        let mut sum = 0_u32;
+        for _ in 0..XTEA_CYCLES {
+            v[0] = v[0].wrapping_add(mix(v[1]) ^ sum);
+            sum = sum.wrapping_add(XTEA_DELTA);
+            v[1] = v[1].wrapping_add(mix(v[0]) ^ sum);
+        }
+    } else {
+        let mut sum = XTEA_DELTA.wrapping_mul(XTEA_CYCLES);
+        for _ in 0..XTEA_CYCLES {
+            v[1] = v[1].wrapping_sub(mix(v[0]) ^ sum);
+            sum = sum.wrapping_sub(XTEA_DELTA);
+            v[0] = v[0].wrapping_sub(mix(v[1]) ^ sum);
+        }
+    }
+
+    for (offset, word) in [0, 4].into_iter().zip(v) {
+        for (i, byte) in word.to_be_bytes().into_iter().enumerate() {
+            data[at(offset + i)] = byte;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{combine, split, Field};
+
+    #[test]
+    fn field_is_the_aes_one_at_a_byte() {
+        let field = Field::new(1).unwrap();
+        assert_eq!(field.constant(0xca), field.inverse(&field.constant(0x53)));
+
+        let field = Field::new(9).unwrap();
+        let element = field.import(b"nine byte");
+        assert_eq!(
+            element,
+            field.diffuse(&field.diffuse(&element, true), false)
+        );
+        assert_ne!(element, field.diffuse(&element, true));
+    }
+
+    #[test]
+    fn shares_roundtrip() {
+        let shares = split(b"a ssss secret", 3, 10, Some("name"), true).unwrap();
+        assert_eq!(10, shares.len());
+        assert!(shares[0].starts_with("name-01-"));
+        assert_eq!(8 + 2 * 13, shares[0].len());
+
+        let some = [&shares[7], &shares[2], &shares[4]];
+        assert_eq!(b"a ssss secret".to_vec(), combine(&some, 3, true).unwrap());
+        assert_ne!(b"a ssss secret".to_vec(), combine(&some, 3, false).unwrap());
+        assert!(combine(&shares[..2], 3, true).is_err());
+
+        let short = split(b"k", 2, 3, None, true).unwrap();
+        assert_eq!(b"k".to_vec(), combine(&short[1..], 2, true).unwrap());
+    }
+}
@@ -0,0 +1,268 @@
+//! Known-answer test vectors: dealings written out in full, their group, polynomial,
+//! commitments and shares, to check this crate or another VSS implementation against
+//!
+//! Fixture files keep vectors as JSON with every number as the base64 of its canonical big
+//! endian bytes, as `codec::bytes` writes it. `check` recomputes a vector's commitments, shares
+//! and secret from its polynomial, so a fixture written by another implementation is checked the
+//! same way.
+//!
+//! `generate` derives a vector from its `seed`, the same seed giving the same vector on every
+//! platform and release: the 32 byte key of a ChaCha20 stream is the seed in 8 little endian
+//! bytes followed by 24 zero bytes, the stream draws the group with `Dealer::group_of` and then
+//! a_0,...,a_(t-1) in order, each with `gen_biguint_below(q)`.
+
+use std::fs::File;
+use std::io::{self, BufReader, BufWriter, Read, Write};
+use std::path::Path;
+
+use num::{One, Zero};
+use num_bigint::{BigUint, RandBigInt};
+use rand::prng::ChaChaRng;
+use rand::SeedableRng;
+use serde::{Deserialize, Serialize};
+
+use crate::codec;
+use crate::dealer::Dealer;
+use crate::error::VssError;
+use crate::rpc::{PublicParams, Share};
+use crate::vss;
+
+/// Version of the fixture files this crate writes, and the newest it reads
+pub const FIXTURE_VERSION: u32 = 1;
+
+/// A dealing of `polynomial`'s constant term, the secret, with every value derived from it
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Vector {
+    pub name: String,
+    /// Seed `generate` derived the vector from, none for a vector written by hand
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub seed: Option<u64>,
+    #[serde(with = "codec::bytes")]
+    pub p: BigUint,
+    #[serde(with = "codec::bytes")]
+    pub q: BigUint,
+    #[serde(with = "codec::bytes")]
+    pub g: BigUint,
+    /// Coefficients a_0,...,a_(t-1) over q, a_0 being the secret
+    #[serde(with = "codec::bytes::vec")]
+    pub polynomial: Vec<BigUint>,
+    /// g^a_0,...,g^a_(t-1) mod p
+    #[serde(with = "codec::bytes::vec")]
+    pub commitments: Vec<BigUint>,
+    pub shares: Vec<VectorShare>,
+}
+
+/// A share of a Vector, P(index) mod q
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct VectorShare {
+    pub index: u64,
+    #[serde(with = "codec::bytes")]
+    pub value: BigUint,
+}
+
+/// A fixture file
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Fixture {
+    pub version: u32,
+    pub vectors: Vec<Vector>,
+}
+
+impl Vector {
+    /// Number of shares needed to reconstruct the secret
+    pub fn t(&self) -> usize {
+        self.polynomial.len()
+    }
+
+    /// The public parameters of the dealing
+    pub fn params(&self) -> PublicParams {
+        PublicParams {
+            g: self.g.clone(),
+            commitments: self.commitments.clone(),
+            p: self.p.clone(),
+            q: self.q.clone(),
+            t: self.t(),
+        }
+    }
+
+    pub fn shares(&self) -> Vec<Share> {
+        self.shares
+            .iter()
+            .map(|share| Share::new(BigUint::from(share.index), share.value.clone()))
+            .collect()
+    }
+}
+
+/// The vector of a dealing of `n` shares with threshold `t` over a q of `bits` bits, its group
+/// and polynomial drawn from the ChaCha20 stream keyed with `seed`
+pub fn generate(seed: u64, bits: usize, n: usize, t: usize) -> Result<Vector, VssError> {
+    if t == 0 || t > n {
+        return Err(VssError::InvalidThreshold { t, n });
+    }
+    let mut key = [0; 32];
+    key[..8].copy_from_slice(&seed.to_le_bytes());
+    let mut rng = ChaChaRng::from_seed(key);
+    let (p, q, g) = Dealer::group_of(bits, &mut rng);
+    let polynomial: Vec<BigUint> = (0..t).map(|_| rng.gen_biguint_below(&q)).collect();
+    let commitments = vss::generate_commitments(&polynomial, &g, &p);
+    let shares = vss::generate_shares(&polynomial, n, &q)
+        .into_iter()
+        .zip(1..)
+        .map(|(share, index)| VectorShare {
+            index,
+            value: share.value().clone(),
+        })
+        .collect();
+
+    Ok(Vector {
+        name: format!("seed {}, {} of {} over {} bits", seed, t, n, bits),
+        seed: Some(seed),
+        p,
+        q,
+        g,
+        polynomial,
+        commitments,
+        shares,
+    })
+}
+
+/// Check that every value of `vector` follows from its group and polynomial: g has order q
+/// modulo p, the commitments and shares are those of the polynomial, every share verifies and
+/// the first t reconstruct the secret
+///
+/// Errors name the vector and the first value that does not follow
+pub fn check(vector: &Vector) -> io::Result<()> {
+    let wrong = |what: String| {
+        io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("{}: {}", vector.name, what),
+        )
+    };
+    let (p, q, g) = (&vector.p, &vector.q, &vector.g);
+
+    if q.is_one() || p <= q || !((p - 1_u32) % q).is_zero() {
+        return Err(wrong("q does not divide p - 1".to_string()));
+    }
+    if g.is_one() || g.modpow(q, p) != BigUint::one() {
+        return Err(wrong("g is not of order q".to_string()));
+    }
+    if vector.t() == 0 || vector.polynomial.iter().any(|a| a >= q) {
+        return Err(wrong("polynomial is not over q".to_string()));
+    }
+    if vector.commitments != vss::generate_commitments(&vector.polynomial, g, p) {
+        return Err(wrong(
+            "commitments are not those of the polynomial".to_string(),
+        ));
+    }
+
+    let expected = vss::generate_shares(&vector.polynomial, vector.shares.len(), q);
+    let shares = vector.shares();
+    for (share, expected) in shares.iter().zip(&expected) {
+        if share != expected {
+            return Err(wrong(format!(
+                "share {} is not P({})",
+                share.index(),
+                share.index()
+            )));
+        }
+        if !vss::verify_share(share.index(), share.value(), g, &vector.commitments, p) {
+            return Err(wrong(format!("share {} does not verify", share.index())));
+        }
+    }
+
+    if shares.len() >= vector.t() {
+        let secret = vss::reconstruct(&shares[..vector.t()], q)?;
+        if *secret.expose() != vector.polynomial[0]
+            || !vss::verify_secret(secret.expose(), g, &vector.commitments, p)
+        {
+            return Err(wrong("shares do not reconstruct the secret".to_string()));
+        }
+    }
+
+    Ok(())
+}
+
+/// Write `vectors` as a fixture file
+pub fn write<W: Write>(w: W, vectors: &[Vector]) -> io::Result<()> {
+    let mut w = w;
+    let fixture = Fixture {
+        version: FIXTURE_VERSION,
+        vectors: vectors.to_vec(),
+    };
+    serde_json::to_writer_pretty(&mut w, &fixture)?;
+
+    w.write_all(b"\n")
+}
+
+/// The vectors of a fixture file, refusing versions newer than FIXTURE_VERSION
+pub fn read<R: Read>(r: R) -> io::Result<Vec<Vector>> {
+    let fixture: Fixture = serde_json::from_reader(r)?;
+    if fixture.version > FIXTURE_VERSION {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("unsupported fixture version {}", fixture.version),
+        ));
+    }
+
+    Ok(fixture.vectors)
+}
+
+/// Write `vectors` as a fixture file at `path`
+pub fn save<P: AsRef<Path>>(path: P, vectors: &[Vector]) -> io::Result<()> {
+    let mut w = BufWriter::new(File::create(path)?);
+    write(&mut w, vectors)?;
+
+    w.flush()
+}
+
+/// The vectors of the fixture file at `path`
+pub fn load<P: AsRef<Path>>(path: P) -> io::Result<Vec<Vector>> {
+    read(BufReader::new(File::open(path)?))
+}
+
+#[cfg(test)]
+mod tests {
+    use num_bigint::BigUint;
+
+    use super::{check, generate, read, write};
+
+    #[test]
+    fn fixture_vectors_are_known_answers() {
+        let vectors = read(include_str!("testvectors/vectors.json").as_bytes()).unwrap();
+        assert!(!vectors.is_empty());
+
+        for vector in &vectors {
+            check(vector).unwrap();
+            // the generator still derives the same vector from its seed
+            let (n, t) = (vector.shares.len(), vector.t());
+            let bits = vector.q.bits();
+            let seed = vector.seed.unwrap();
+            assert_eq!(
+                *vector,
+                generate(seed, bits, n, t).unwrap(),
+                "{}",
+                vector.name
+            );
+        }
+
+        let mut file = vec![];
+        write(&mut file, &vectors).unwrap();
+        assert_eq!(vectors, read(&file[..]).unwrap());
+    }
+
+    #[test]
+    fn check_names_what_does_not_follow() {
+        let vector = generate(1, 32, 4, 2).unwrap();
+
+        let mut wrong = vector.clone();
+        wrong.shares[2].value += BigUint::from(1_u32);
+        assert!(check(&wrong)
+            .unwrap_err()
+            .to_string()
+            .contains("share 3 is not P(3)"));
+
+        let mut wrong = vector.clone();
+        wrong.commitments.swap(0, 1);
+        assert!(check(&wrong).is_err());
+        assert!(generate(1, 32, 2, 3).is_err());
+    }
+}
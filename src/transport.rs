@@ -0,0 +1,157 @@
+use std::collections::{BTreeSet, HashMap};
+use std::io;
+use std::sync::mpsc::Sender;
+use std::sync::{Arc, Mutex};
+
+use crate::rpc::{WireMessage, RPC};
+
+pub mod address_book;
+#[cfg(feature = "noise")]
+pub mod noise;
+#[cfg(feature = "libp2p")]
+pub mod p2p;
+#[cfg(feature = "quic")]
+pub mod quic;
+pub mod retry;
+pub mod routed;
+pub mod sim;
+pub mod tcp;
+#[cfg(feature = "tls")]
+pub mod tls;
+pub mod trace;
+pub mod udp;
+#[cfg(feature = "websocket")]
+pub mod websocket;
+
+/// Sequence numbers already delivered from one peer
+#[derive(Default)]
+pub(crate) struct Received {
+    /// every sequence number below this one was delivered
+    contiguous: u64,
+    /// delivered sequence numbers above `contiguous`
+    ahead: BTreeSet<u64>,
+}
+
+impl Received {
+    /// Record `seq`, returns false if it was delivered before
+    pub(crate) fn insert(&mut self, seq: u64) -> bool {
+        if seq < self.contiguous || !self.ahead.insert(seq) {
+            return false;
+        }
+
+        while self.ahead.remove(&self.contiguous) {
+            self.contiguous += 1;
+        }
+
+        true
+    }
+}
+
+/// Name the certificate of the player `id` is issued for
+#[cfg(any(feature = "quic", feature = "tls"))]
+pub(crate) fn server_name(id: usize) -> String {
+    format!("player-{}", id)
+}
+
+/// Delivers protocol messages between the dealer and players
+///
+/// Incoming messages are handed to the receiving Player's inbox as `RPC::Wire(from, msg)`, so
+/// a Player only ever deals with its own channel no matter how its peers are reached
+pub trait Transport: Send {
+    /// Send `msg` to the player with id `to`
+    fn send(&self, to: usize, msg: WireMessage) -> io::Result<()>;
+
+    /// Ids of every peer this transport can currently reach
+    fn peers(&self) -> Vec<usize>;
+
+    /// Send `msg` to every peer, returning the ids that could not be reached
+    fn broadcast(&self, msg: WireMessage) -> Vec<(usize, io::Error)> {
+        self.peers()
+            .into_iter()
+            .filter_map(|to| self.send(to, msg.clone()).err().map(|err| (to, err)))
+            .collect()
+    }
+}
+
+/// Registry of in-process Player inboxes shared by every ChannelTransport
+#[derive(Clone, Default)]
+pub struct ChannelNetwork {
+    inboxes: Arc<Mutex<HashMap<usize, Sender<RPC>>>>,
+}
+
+impl ChannelNetwork {
+    pub fn new() -> ChannelNetwork {
+        ChannelNetwork::default()
+    }
+
+    /// Make the Player `id` reachable through its `inbox`
+    pub fn register(&self, id: usize, inbox: Sender<RPC>) {
+        self.inboxes.lock().unwrap().insert(id, inbox);
+    }
+
+    /// Make the Player `id` unreachable, dropping the network's sender to its inbox
+    pub fn unregister(&self, id: usize) {
+        self.inboxes.lock().unwrap().remove(&id);
+    }
+
+    /// Return a Transport sending as `id` to every registered Player
+    pub fn transport(&self, id: usize) -> ChannelTransport {
+        ChannelTransport {
+            id,
+            network: self.clone(),
+        }
+    }
+}
+
+/// Transport between threads of the same process over mpsc channels
+#[derive(Clone)]
+pub struct ChannelTransport {
+    id: usize,
+    network: ChannelNetwork,
+}
+
+impl Transport for ChannelTransport {
+    fn send(&self, to: usize, msg: WireMessage) -> io::Result<()> {
+        let inboxes = self.network.inboxes.lock().unwrap();
+        let inbox = inboxes.get(&to).ok_or_else(|| {
+            io::Error::new(io::ErrorKind::NotFound, format!("unknown player {}", to))
+        })?;
+
+        inbox
+            .send(RPC::Wire(self.id, msg))
+            .map_err(|err| io::Error::new(io::ErrorKind::BrokenPipe, err.to_string()))
+    }
+
+    fn peers(&self) -> Vec<usize> {
+        let mut peers: Vec<usize> = self
+            .network
+            .inboxes
+            .lock()
+            .unwrap()
+            .keys()
+            .copied()
+            .filter(|id| *id != self.id)
+            .collect();
+        peers.sort();
+
+        peers
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Received;
+
+    #[test]
+    fn received_drops_duplicates() {
+        let mut received = Received::default();
+
+        assert!(received.insert(1));
+        assert!(received.insert(0));
+        assert!(!received.insert(1));
+        assert!(!received.insert(0));
+        assert!(received.insert(3));
+        assert!(!received.insert(3));
+        assert_eq!(2, received.contiguous);
+    }
+}
@@ -0,0 +1,184 @@
+use std::collections::HashMap;
+use std::io;
+use std::net::SocketAddr;
+use std::time::Duration;
+
+use crate::dealer::DEALER_ID;
+
+/// Attempts made to deliver a message before giving up on a peer
+pub const REDIAL_ATTEMPTS: u32 = 3;
+
+/// Wait before redialing a peer, doubled after every failed attempt
+pub const REDIAL_BACKOFF: Duration = Duration::from_millis(50);
+
+/// Where a player listens and, for transports that authenticate peers, its public key
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Peer {
+    pub address: SocketAddr,
+    pub public_key: Option<Vec<u8>>,
+}
+
+/// How often and how patiently a peer is dialed again after a connection fails
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Redial {
+    /// Attempts made to deliver a message, the first dial included
+    pub attempts: u32,
+    /// Wait before the second attempt, doubled before every one after it
+    pub backoff: Duration,
+}
+
+impl Default for Redial {
+    fn default() -> Redial {
+        Redial {
+            attempts: REDIAL_ATTEMPTS,
+            backoff: REDIAL_BACKOFF,
+        }
+    }
+}
+
+/// The fixed set of players of a deployment by id, as its config lists them
+///
+/// Transports dial a player at its address, redial it as the book's Redial says when a
+/// connection fails, and refuse connections from ids the book does not list. The dealer is
+/// always accepted, it deals from wherever it runs.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct AddressBook {
+    peers: HashMap<usize, Peer>,
+    redial: Redial,
+}
+
+impl AddressBook {
+    pub fn new() -> AddressBook {
+        AddressBook::default()
+    }
+
+    /// List `id` at `address`, with its public key if any
+    pub fn with_peer(
+        mut self,
+        id: usize,
+        address: SocketAddr,
+        public_key: Option<Vec<u8>>,
+    ) -> AddressBook {
+        self.insert(
+            id,
+            Peer {
+                address,
+                public_key,
+            },
+        );
+        self
+    }
+
+    /// Redial peers as `redial` says rather than the default
+    pub fn with_redial(mut self, redial: Redial) -> AddressBook {
+        self.redial = redial;
+        self
+    }
+
+    /// List `id` as `peer`, returning what it was listed as before
+    pub fn insert(&mut self, id: usize, peer: Peer) -> Option<Peer> {
+        self.peers.insert(id, peer)
+    }
+
+    pub fn get(&self, id: usize) -> Option<&Peer> {
+        self.peers.get(&id)
+    }
+
+    pub fn redial(&self) -> Redial {
+        self.redial
+    }
+
+    /// Address to dial `id` at, NotFound if the book does not list it
+    pub fn address(&self, id: usize) -> io::Result<SocketAddr> {
+        self.get(id).map(|peer| peer.address).ok_or_else(|| {
+            io::Error::new(io::ErrorKind::NotFound, format!("unknown player {}", id))
+        })
+    }
+
+    /// Whether a connection from `id` is let in
+    pub fn accepts(&self, id: usize) -> bool {
+        id == DEALER_ID || self.peers.contains_key(&id)
+    }
+
+    /// Ids listed, in order
+    pub fn ids(&self) -> Vec<usize> {
+        let mut ids: Vec<usize> = self.peers.keys().copied().collect();
+        ids.sort();
+
+        ids
+    }
+
+    /// Address of every player by id
+    pub fn addresses(&self) -> HashMap<usize, SocketAddr> {
+        self.peers
+            .iter()
+            .map(|(id, peer)| (*id, peer.address))
+            .collect()
+    }
+
+    /// Public key of every player listed with one, by id
+    pub fn public_keys(&self) -> HashMap<usize, Vec<u8>> {
+        self.peers
+            .iter()
+            .filter_map(|(id, peer)| Some((*id, peer.public_key.clone()?)))
+            .collect()
+    }
+}
+
+impl From<HashMap<usize, SocketAddr>> for AddressBook {
+    fn from(addresses: HashMap<usize, SocketAddr>) -> AddressBook {
+        addresses
+            .into_iter()
+            .fold(AddressBook::new(), |book, (id, address)| {
+                book.with_peer(id, address, None)
+            })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::net::TcpListener;
+    use std::sync::{mpsc, Arc};
+    use std::time::Duration;
+
+    use crate::codec::Cbor;
+    use crate::rpc::{Payload, WireMessage, NO_SESSION, RPC};
+    use crate::transport::tcp::TcpTransport;
+    use crate::transport::Transport;
+
+    use super::AddressBook;
+
+    #[test]
+    fn book_dials_its_peers_and_refuses_strangers() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        // player 2 is listed at an address nobody listens on any more
+        let closed = TcpListener::bind("127.0.0.1:0")
+            .unwrap()
+            .local_addr()
+            .unwrap();
+        let book = AddressBook::new()
+            .with_peer(1, listener.local_addr().unwrap(), None)
+            .with_peer(2, closed, None)
+            .with_peer(3, closed, None);
+        let (inbox, rx) = mpsc::channel();
+        TcpTransport::listen_known(listener, Arc::new(book.clone()), Arc::new(Cbor), inbox);
+
+        let known = TcpTransport::new(3, book.clone());
+        assert_eq!(vec![2], known.bootstrap(Duration::from_millis(100)));
+
+        let ping = WireMessage::new(NO_SESSION, 0, Payload::Ping);
+        known.send(1, ping.clone()).unwrap();
+        match rx.recv_timeout(Duration::from_secs(5)).unwrap() {
+            RPC::Wire(from, msg) => assert_eq!((3, ping.clone()), (from, msg)),
+            rpc => panic!("unexpected {:?}", rpc),
+        }
+
+        // player 4 is not in the book, its connection is dropped unread
+        TcpTransport::new(4, book.clone()).send(1, ping).unwrap();
+        assert!(rx.recv_timeout(Duration::from_millis(200)).is_err());
+        assert!(book.accepts(0) && !book.accepts(4));
+        assert!(known
+            .send(5, WireMessage::new(NO_SESSION, 0, Payload::Ping))
+            .is_err());
+    }
+}
@@ -0,0 +1,305 @@
+use std::collections::HashMap;
+use std::io::{self, Read, Write};
+use std::net::TcpStream;
+
+use snow::params::DHChoice;
+use snow::resolvers::{CryptoResolver, DefaultResolver};
+use snow::{Builder, HandshakeState, TransportState};
+
+/// Handshake and ciphers every player uses
+const NOISE_PARAMS: &str = "Noise_XX_25519_ChaChaPoly_BLAKE2s";
+
+/// Largest Noise message, ciphertext included
+const MAX_NOISE_LEN: usize = 65_535;
+
+/// Authentication tag added to every encrypted Noise message
+const TAG_LEN: usize = 16;
+
+/// A player's static Noise keypair, the public key doubles as its identity
+#[derive(Clone)]
+pub struct NoiseKeypair {
+    private: Vec<u8>,
+    public: Vec<u8>,
+}
+
+impl NoiseKeypair {
+    /// Return a new random keypair
+    pub fn generate() -> io::Result<NoiseKeypair> {
+        let keypair = Builder::new(NOISE_PARAMS.parse().unwrap())
+            .generate_keypair()
+            .map_err(io::Error::other)?;
+
+        Ok(NoiseKeypair {
+            private: keypair.private,
+            public: keypair.public,
+        })
+    }
+
+    /// Return the keypair of a private static key kept from an earlier run
+    pub fn from_private(private: &[u8]) -> io::Result<NoiseKeypair> {
+        let mut dh = DefaultResolver
+            .resolve_dh(&DHChoice::Curve25519)
+            .ok_or_else(|| io::Error::other("no Curve25519 implementation"))?;
+        if private.len() != dh.priv_len() {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("a private key is {} bytes", dh.priv_len()),
+            ));
+        }
+        dh.set(private);
+
+        Ok(NoiseKeypair {
+            private: private.to_vec(),
+            public: dh.pubkey().to_vec(),
+        })
+    }
+
+    /// The key peers list for this player
+    pub fn public(&self) -> &[u8] {
+        &self.public
+    }
+}
+
+/// Noise XX settings for the TCP transport
+///
+/// A lighter alternative to mutual TLS: both ends prove they hold a static key and the peer is
+/// identified by which player the key table lists its key for, no certificates involved
+pub struct NoiseConfig {
+    keypair: NoiseKeypair,
+    /// public static key of every player
+    players: HashMap<usize, Vec<u8>>,
+}
+
+impl NoiseConfig {
+    /// Return the settings of a player holding `keypair` and knowing the keys of `players`
+    pub fn new(keypair: NoiseKeypair, players: HashMap<usize, Vec<u8>>) -> NoiseConfig {
+        NoiseConfig { keypair, players }
+    }
+
+    /// Handshake as the dialer of a connection to the player `to`
+    pub(crate) fn connect(&self, to: usize, mut stream: TcpStream) -> io::Result<NoiseStream> {
+        let mut handshake = self.builder().build_initiator().map_err(io::Error::other)?;
+
+        write_handshake(&mut stream, &mut handshake)?;
+        read_handshake(&mut stream, &mut handshake)?;
+        write_handshake(&mut stream, &mut handshake)?;
+
+        let peer = self.player_of(&handshake)?;
+        if peer != to {
+            return Err(io::Error::new(
+                io::ErrorKind::PermissionDenied,
+                format!("expected player {} but reached player {}", to, peer),
+            ));
+        }
+
+        NoiseStream::new(stream, handshake)
+    }
+
+    /// Handshake as the listener of an accepted connection, returning it with the id of the
+    /// player that dialed it
+    ///
+    /// The dialer's claimed id is read from the connection and must match its static key
+    pub(crate) fn accept(&self, mut stream: TcpStream) -> io::Result<(NoiseStream, usize)> {
+        let mut handshake = self.builder().build_responder().map_err(io::Error::other)?;
+
+        read_handshake(&mut stream, &mut handshake)?;
+        write_handshake(&mut stream, &mut handshake)?;
+        read_handshake(&mut stream, &mut handshake)?;
+
+        let from = self.player_of(&handshake)?;
+        let mut stream = NoiseStream::new(stream, handshake)?;
+
+        let mut preamble = [0; 8];
+        stream.read_exact(&mut preamble)?;
+        let claimed = u64::from_be_bytes(preamble) as usize;
+        if claimed != from {
+            return Err(io::Error::new(
+                io::ErrorKind::PermissionDenied,
+                format!("player {} claims to be player {}", from, claimed),
+            ));
+        }
+
+        Ok((stream, from))
+    }
+
+    fn builder(&self) -> Builder<'_> {
+        Builder::new(NOISE_PARAMS.parse().unwrap()).local_private_key(&self.keypair.private)
+    }
+
+    /// Id of the player whose static key the peer proved it holds
+    fn player_of(&self, handshake: &HandshakeState) -> io::Result<usize> {
+        let remote = handshake.get_remote_static().unwrap_or_default();
+
+        self.players
+            .iter()
+            .find(|(_, key)| key.as_slice() == remote)
+            .map(|(id, _)| *id)
+            .ok_or_else(|| io::Error::new(io::ErrorKind::PermissionDenied, "unknown static key"))
+    }
+}
+
+/// A connection encrypted with the keys of a completed Noise handshake
+///
+/// Bytes written are sent as Noise messages of at most MAX_NOISE_LEN bytes, each prefixed by
+/// its length as a big endian u16
+pub(crate) struct NoiseStream {
+    stream: TcpStream,
+    transport: TransportState,
+    /// decrypted bytes not read yet
+    plaintext: Vec<u8>,
+    pos: usize,
+}
+
+impl NoiseStream {
+    fn new(stream: TcpStream, handshake: HandshakeState) -> io::Result<NoiseStream> {
+        Ok(NoiseStream {
+            stream,
+            transport: handshake.into_transport_mode().map_err(io::Error::other)?,
+            plaintext: vec![],
+            pos: 0,
+        })
+    }
+}
+
+impl Read for NoiseStream {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if self.pos == self.plaintext.len() {
+            let msg = read_message(&mut self.stream)?;
+            self.plaintext.resize(msg.len(), 0);
+            let len = self
+                .transport
+                .read_message(&msg, &mut self.plaintext)
+                .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))?;
+            self.plaintext.truncate(len);
+            self.pos = 0;
+        }
+
+        let len = buf.len().min(self.plaintext.len() - self.pos);
+        buf[..len].copy_from_slice(&self.plaintext[self.pos..self.pos + len]);
+        self.pos += len;
+
+        Ok(len)
+    }
+}
+
+impl Write for NoiseStream {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let len = buf.len().min(MAX_NOISE_LEN - TAG_LEN);
+        let mut msg = vec![0; len + TAG_LEN];
+        let msg_len = self
+            .transport
+            .write_message(&buf[..len], &mut msg)
+            .map_err(io::Error::other)?;
+        write_message(&mut self.stream, &msg[..msg_len])?;
+
+        Ok(len)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.stream.flush()
+    }
+}
+
+fn write_handshake(stream: &mut TcpStream, handshake: &mut HandshakeState) -> io::Result<()> {
+    let mut msg = vec![0; MAX_NOISE_LEN];
+    let len = handshake
+        .write_message(&[], &mut msg)
+        .map_err(io::Error::other)?;
+
+    write_message(stream, &msg[..len])
+}
+
+fn read_handshake(stream: &mut TcpStream, handshake: &mut HandshakeState) -> io::Result<()> {
+    let msg = read_message(stream)?;
+    let mut payload = vec![0; MAX_NOISE_LEN];
+    handshake
+        .read_message(&msg, &mut payload)
+        .map_err(|err| io::Error::new(io::ErrorKind::PermissionDenied, err))?;
+
+    Ok(())
+}
+
+fn write_message(stream: &mut TcpStream, msg: &[u8]) -> io::Result<()> {
+    let mut buf = Vec::with_capacity(2 + msg.len());
+    buf.extend_from_slice(&(msg.len() as u16).to_be_bytes());
+    buf.extend_from_slice(msg);
+
+    stream.write_all(&buf)
+}
+
+fn read_message(stream: &mut TcpStream) -> io::Result<Vec<u8>> {
+    let mut len = [0; 2];
+    stream.read_exact(&mut len)?;
+
+    let mut msg = vec![0; u16::from_be_bytes(len) as usize];
+    stream.read_exact(&mut msg)?;
+
+    Ok(msg)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+    use std::net::TcpListener;
+    use std::sync::{mpsc, Arc};
+    use std::time::Duration;
+
+    use crate::rpc::{Payload, Share, WireMessage, NO_SESSION, RPC};
+    use crate::transport::tcp::TcpTransport;
+    use crate::transport::Transport;
+
+    use super::{NoiseConfig, NoiseKeypair};
+
+    /// Sends `msg` as player 2 holding `key` to a listening player 1 and returns what player 1
+    /// received
+    fn send_as(key: Option<NoiseKeypair>, msg: WireMessage) -> Option<RPC> {
+        let keys: Vec<_> = (0..2).map(|_| NoiseKeypair::generate().unwrap()).collect();
+        let players: HashMap<_, _> = keys
+            .iter()
+            .enumerate()
+            .map(|(i, key)| (i + 1, key.public().to_vec()))
+            .collect();
+        let key = key.unwrap_or_else(|| keys[1].clone());
+
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addresses = HashMap::from([(1, listener.local_addr().unwrap())]);
+        let (inbox, rx) = mpsc::channel();
+        let noise = Arc::new(NoiseConfig::new(keys[0].clone(), players.clone()));
+        TcpTransport::listen_noise(listener, noise, inbox);
+
+        let noise = Arc::new(NoiseConfig::new(key, players));
+        let _ = TcpTransport::with_noise(2, addresses, noise).send(1, msg);
+
+        rx.recv_timeout(Duration::from_millis(500)).ok()
+    }
+
+    #[test]
+    fn delivers_from_known_key() {
+        let ping = WireMessage::new(NO_SESSION, 0, Payload::Ping);
+
+        match send_as(None, ping.clone()) {
+            Some(RPC::Wire(from, msg)) => assert_eq!((2, ping), (from, msg)),
+            rpc => panic!("unexpected {:?}", rpc),
+        }
+    }
+
+    #[test]
+    fn delivers_messages_larger_than_a_noise_message() {
+        let value = num_bigint::BigUint::from_bytes_be(&[7; 100_000]);
+        let share = Share::new(2_usize.into(), value);
+        let msg = WireMessage::new(7, 0, Payload::ReconstructShare(share));
+
+        match send_as(None, msg.clone()) {
+            Some(RPC::Wire(_, received)) => assert_eq!(msg, received),
+            rpc => panic!("unexpected {:?}", rpc),
+        }
+    }
+
+    #[test]
+    fn rejects_unknown_key() {
+        let stranger = NoiseKeypair::generate().unwrap();
+        let ping = WireMessage::new(NO_SESSION, 0, Payload::Ping);
+
+        assert!(send_as(Some(stranger), ping).is_none());
+    }
+}
@@ -0,0 +1,378 @@
+use std::collections::HashMap;
+use std::io;
+use std::sync::mpsc::Sender;
+use std::time::Duration;
+
+use futures::StreamExt;
+use libp2p::gossipsub::{self, IdentTopic, MessageAuthenticity};
+use libp2p::identity::Keypair;
+use libp2p::kad::{self, store::MemoryStore};
+use libp2p::request_response::{self, ProtocolSupport};
+use libp2p::swarm::{NetworkBehaviour, SwarmEvent};
+use libp2p::{identify, noise, tcp, yamux, Multiaddr, PeerId, StreamProtocol, Swarm};
+use tokio::runtime::Runtime;
+use tokio::sync::{mpsc, oneshot};
+use tracing::{debug, warn};
+
+use crate::rpc::{WireMessage, RPC};
+use crate::transport::Transport;
+
+/// Topic every player subscribes to for broadcasts
+const BROADCAST_TOPIC: &str = "rust-vss/broadcast";
+
+#[derive(NetworkBehaviour)]
+struct Behaviour {
+    identify: identify::Behaviour,
+    kademlia: kad::Behaviour<MemoryStore>,
+    gossipsub: gossipsub::Behaviour,
+    /// direct messages, answered with an empty acknowledgment
+    direct: request_response::json::Behaviour<WireMessage, ()>,
+}
+
+/// Requests from a P2pTransport to the task driving its swarm
+enum Command {
+    Send(PeerId, WireMessage),
+    Publish(WireMessage, oneshot::Sender<io::Result<()>>),
+    Deliver(Sender<RPC>),
+}
+
+/// Transport over a libp2p swarm
+///
+/// Players are known by the PeerId of their key rather than an address: addresses are learned
+/// through identify and a kademlia lookup starting from the bootstrap peers. Messages to a
+/// single player go over a request-response protocol, broadcasts over gossipsub where the
+/// sender is authenticated by the message signature
+pub struct P2pTransport {
+    id: usize,
+    players: HashMap<usize, PeerId>,
+    listen_addr: Multiaddr,
+    commands: mpsc::UnboundedSender<Command>,
+    runtime: Runtime,
+}
+
+/// Hands a P2pTransport's incoming messages to a Player
+pub struct P2pListener {
+    commands: mpsc::UnboundedSender<Command>,
+}
+
+impl P2pTransport {
+    /// Return a Transport for the player `id` with `keypair`, listening on `listen_addr`
+    ///
+    /// `players` maps the id of every player to its PeerId, messages from peers not listed are
+    /// dropped. `bootstrap` lists peers known to be reachable to discover the others from
+    pub fn new(
+        id: usize,
+        keypair: Keypair,
+        players: HashMap<usize, PeerId>,
+        listen_addr: Multiaddr,
+        bootstrap: Vec<(PeerId, Multiaddr)>,
+    ) -> io::Result<P2pTransport> {
+        let runtime = Runtime::new()?;
+        let mut swarm = runtime.block_on(async { P2pTransport::build(keypair) })?;
+
+        for (peer, addr) in bootstrap {
+            swarm.behaviour_mut().kademlia.add_address(&peer, addr);
+        }
+        if swarm.behaviour_mut().kademlia.bootstrap().is_err() {
+            debug!("no bootstrap peers, waiting to be discovered");
+        }
+
+        let listen_addr = runtime.block_on(async {
+            swarm.listen_on(listen_addr).map_err(io::Error::other)?;
+            loop {
+                if let SwarmEvent::NewListenAddr { address, .. } = swarm.select_next_some().await {
+                    return Ok::<_, io::Error>(address);
+                }
+            }
+        })?;
+
+        let (commands, rx) = mpsc::unbounded_channel();
+        let peers = players.iter().map(|(id, peer)| (*peer, *id)).collect();
+        runtime.spawn(drive(swarm, peers, rx));
+
+        Ok(P2pTransport {
+            id,
+            players,
+            listen_addr,
+            commands,
+            runtime,
+        })
+    }
+
+    /// Address other players can bootstrap from
+    pub fn listen_addr(&self) -> &Multiaddr {
+        &self.listen_addr
+    }
+
+    /// Return a listener delivering incoming messages into a Player's inbox
+    pub fn listener(&self) -> P2pListener {
+        P2pListener {
+            commands: self.commands.clone(),
+        }
+    }
+
+    fn build(keypair: Keypair) -> io::Result<Swarm<Behaviour>> {
+        let swarm = libp2p::SwarmBuilder::with_existing_identity(keypair)
+            .with_tokio()
+            .with_tcp(
+                tcp::Config::default(),
+                noise::Config::new,
+                yamux::Config::default,
+            )
+            .map_err(io::Error::other)?
+            .with_behaviour(|key| {
+                let peer_id = key.public().to_peer_id();
+
+                let identify = identify::Behaviour::new(identify::Config::new(
+                    "/rust-vss/1.0.0".to_string(),
+                    key.public(),
+                ));
+
+                let kad_config = kad::Config::new(StreamProtocol::new("/rust-vss/kad/1.0.0"));
+                let mut kademlia =
+                    kad::Behaviour::with_config(peer_id, MemoryStore::new(peer_id), kad_config);
+                // players rarely have a confirmed external address, answer queries regardless
+                kademlia.set_mode(Some(kad::Mode::Server));
+
+                let mut gossipsub = gossipsub::Behaviour::new(
+                    MessageAuthenticity::Signed(key.clone()),
+                    gossipsub::Config::default(),
+                )?;
+                gossipsub.subscribe(&IdentTopic::new(BROADCAST_TOPIC))?;
+
+                let direct = request_response::json::Behaviour::new(
+                    [(
+                        StreamProtocol::new("/rust-vss/direct/1.0.0"),
+                        ProtocolSupport::Full,
+                    )],
+                    request_response::Config::default(),
+                );
+
+                Ok(Behaviour {
+                    identify,
+                    kademlia,
+                    gossipsub,
+                    direct,
+                })
+            })
+            .map_err(io::Error::other)?
+            .with_swarm_config(|c| c.with_idle_connection_timeout(Duration::from_secs(60)))
+            .build();
+
+        Ok(swarm)
+    }
+
+    fn command(&self, command: Command) -> io::Result<()> {
+        self.commands
+            .send(command)
+            .map_err(|_| io::Error::new(io::ErrorKind::BrokenPipe, "swarm stopped"))
+    }
+}
+
+impl Transport for P2pTransport {
+    fn send(&self, to: usize, msg: WireMessage) -> io::Result<()> {
+        let peer = self.players.get(&to).ok_or_else(|| {
+            io::Error::new(io::ErrorKind::NotFound, format!("unknown player {}", to))
+        })?;
+
+        self.command(Command::Send(*peer, msg))
+    }
+
+    fn peers(&self) -> Vec<usize> {
+        let mut peers: Vec<usize> = self
+            .players
+            .keys()
+            .copied()
+            .filter(|id| *id != self.id)
+            .collect();
+        peers.sort();
+
+        peers
+    }
+
+    /// Publish `msg` over gossipsub, falling back to sending it to every peer directly
+    fn broadcast(&self, msg: WireMessage) -> Vec<(usize, io::Error)> {
+        let (reply, published) = oneshot::channel();
+        let published = self
+            .command(Command::Publish(msg.clone(), reply))
+            .and_then(|_| {
+                self.runtime
+                    .block_on(published)
+                    .map_err(|_| io::Error::new(io::ErrorKind::BrokenPipe, "swarm stopped"))?
+            });
+
+        match published {
+            Ok(()) => vec![],
+            Err(err) => {
+                debug!("falling back to direct messages: {}", err);
+                self.peers()
+                    .into_iter()
+                    .filter_map(|to| self.send(to, msg.clone()).err().map(|err| (to, err)))
+                    .collect()
+            }
+        }
+    }
+}
+
+impl P2pListener {
+    /// Deliver every message received from a known player into `inbox`
+    pub fn listen(self, inbox: Sender<RPC>) {
+        let _ = self.commands.send(Command::Deliver(inbox));
+    }
+}
+
+/// Drives the swarm, executing commands and delivering incoming messages
+async fn drive(
+    mut swarm: Swarm<Behaviour>,
+    peers: HashMap<PeerId, usize>,
+    mut commands: mpsc::UnboundedReceiver<Command>,
+) {
+    let topic = IdentTopic::new(BROADCAST_TOPIC);
+    let mut inbox: Option<Sender<RPC>> = None;
+    let deliver = |inbox: &Option<Sender<RPC>>, peer: PeerId, msg: WireMessage| match (
+        peers.get(&peer),
+        inbox,
+    ) {
+        (Some(from), Some(inbox)) => {
+            let _ = inbox.send(RPC::Wire(*from, msg));
+        }
+        (None, _) => warn!(%peer, "dropping message from unknown peer"),
+        (_, None) => warn!(%peer, "dropping message, not listening"),
+    };
+
+    loop {
+        tokio::select! {
+            command = commands.recv() => match command {
+                Some(Command::Send(peer, msg)) => {
+                    if !swarm.is_connected(&peer) {
+                        // learn the peer's address if we do not know it yet
+                        swarm.behaviour_mut().kademlia.get_closest_peers(peer);
+                    }
+                    swarm.behaviour_mut().direct.send_request(&peer, msg);
+                }
+                Some(Command::Publish(msg, reply)) => {
+                    let result = serde_json::to_vec(&msg)
+                        .map_err(io::Error::from)
+                        .and_then(|data| {
+                            swarm
+                                .behaviour_mut()
+                                .gossipsub
+                                .publish(topic.clone(), data)
+                                .map_err(io::Error::other)
+                        });
+                    let _ = reply.send(result.map(|_| ()));
+                }
+                Some(Command::Deliver(sender)) => inbox = Some(sender),
+                // the transport was dropped
+                None => return,
+            },
+            event = swarm.select_next_some() => match event {
+                SwarmEvent::Behaviour(BehaviourEvent::Identify(identify::Event::Received {
+                    peer_id,
+                    info,
+                    ..
+                })) => {
+                    for addr in info.listen_addrs {
+                        swarm.behaviour_mut().kademlia.add_address(&peer_id, addr);
+                    }
+                }
+                SwarmEvent::Behaviour(BehaviourEvent::Direct(request_response::Event::Message {
+                    peer,
+                    message: request_response::Message::Request { request, channel, .. },
+                })) => {
+                    let _ = swarm.behaviour_mut().direct.send_response(channel, ());
+                    deliver(&inbox, peer, request);
+                }
+                SwarmEvent::Behaviour(BehaviourEvent::Direct(
+                    request_response::Event::OutboundFailure { peer, error, .. },
+                )) => warn!(%peer, "error while sending: {}", error),
+                SwarmEvent::Behaviour(BehaviourEvent::Gossipsub(gossipsub::Event::Message {
+                    message,
+                    ..
+                })) => match (message.source, serde_json::from_slice(&message.data)) {
+                    (Some(source), Ok(msg)) => deliver(&inbox, source, msg),
+                    _ => warn!("dropping malformed broadcast"),
+                },
+                _ => {}
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+    use std::thread;
+    use std::time::Duration;
+
+    use libp2p::identity::Keypair;
+    use num_bigint::BigUint;
+
+    use crate::dealer::{Dealer, DEALER_ID};
+    use crate::player::Player;
+    use crate::rpc::{Router, RPC};
+
+    use super::P2pTransport;
+
+    #[test]
+    fn p2p_reconstruct_with_discovery() {
+        let n = 5;
+        let keys: Vec<_> = (0..=n).map(|_| Keypair::generate_ed25519()).collect();
+        let players: HashMap<_, _> = keys
+            .iter()
+            .enumerate()
+            .map(|(id, key)| (id, key.public().to_peer_id()))
+            .collect();
+        let localhost = "/ip4/127.0.0.1/tcp/0".parse().unwrap();
+
+        // everyone only knows how to reach player 1 and discovers the rest
+        let mut keys = keys.into_iter();
+        let dealer_key = keys.next().unwrap();
+        let first =
+            P2pTransport::new(1, keys.next().unwrap(), players.clone(), localhost, vec![]).unwrap();
+        let bootstrap = vec![(players[&1], first.listen_addr().clone())];
+
+        let transports: Vec<_> = std::iter::once(first)
+            .chain(keys.enumerate().map(|(i, key)| {
+                let localhost = "/ip4/127.0.0.1/tcp/0".parse().unwrap();
+                P2pTransport::new(i + 2, key, players.clone(), localhost, bootstrap.clone())
+                    .unwrap()
+            }))
+            .collect();
+        let senders: Vec<_> = transports
+            .into_iter()
+            .enumerate()
+            .map(|(i, transport)| {
+                let listener = transport.listener();
+                let (sender, _) = Player::spawn(i + 1, transport);
+                listener.listen(sender.clone());
+
+                sender
+            })
+            .collect();
+
+        let dealer_transport = P2pTransport::new(
+            DEALER_ID,
+            dealer_key,
+            players,
+            "/ip4/127.0.0.1/tcp/0".parse().unwrap(),
+            bootstrap,
+        )
+        .unwrap();
+        // let discovery settle
+        thread::sleep(Duration::from_secs(1));
+
+        let dealer = Dealer::new(n, 3, 1234).unwrap();
+        dealer.propagate(&dealer_transport);
+        thread::sleep(Duration::from_secs(1));
+
+        let mut router = Router::new();
+        senders.iter().for_each(|s| {
+            s.send(RPC::Reconstruct(dealer.session_id, router.request()))
+                .unwrap();
+        });
+
+        let (_, report) = router.recv_timeout(Duration::from_secs(10)).unwrap();
+        assert_eq!(BigUint::from(1234_u32), *report.secret.expose());
+    }
+}
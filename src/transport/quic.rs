@@ -0,0 +1,356 @@
+use std::collections::hash_map::Entry;
+use std::collections::HashMap;
+use std::io;
+use std::net::{SocketAddr, UdpSocket};
+use std::sync::mpsc::Sender;
+use std::sync::Arc;
+
+use quinn::rustls::pki_types::{CertificateDer, PrivateKeyDer, PrivatePkcs8KeyDer};
+use quinn::rustls::RootCertStore;
+use quinn::{
+    ClientConfig, Connection, Endpoint, EndpointConfig, RecvStream, SendStream, ServerConfig,
+    TokioRuntime,
+};
+use tokio::runtime::Runtime;
+use tokio::sync::Mutex;
+use tracing::{debug, warn};
+
+use crate::codec::{self, MAX_FRAME_LEN};
+use crate::rpc::{WireMessage, RPC};
+use crate::transport::{server_name, Transport};
+
+/// Attempts made to deliver a message before giving up on a peer
+const SEND_ATTEMPTS: u32 = 3;
+
+/// Self-signed certificate a player presents to its peers
+///
+/// Peers trust exactly the certificates listed for each id in their address book
+#[derive(Clone)]
+pub struct QuicIdentity {
+    cert: CertificateDer<'static>,
+    key: Vec<u8>,
+}
+
+impl QuicIdentity {
+    /// Return a new certificate for the player `id`
+    pub fn generate(id: usize) -> io::Result<QuicIdentity> {
+        let certified =
+            rcgen::generate_simple_self_signed(vec![server_name(id)]).map_err(io::Error::other)?;
+
+        Ok(QuicIdentity {
+            cert: certified.cert.der().clone(),
+            key: certified.key_pair.serialize_der(),
+        })
+    }
+
+    /// The certificate peers need in their address book to reach this player
+    pub fn cert(&self) -> &CertificateDer<'static> {
+        &self.cert
+    }
+}
+
+/// Transport between processes over QUIC
+///
+/// Peers are authenticated by the certificate the address book lists for them and every
+/// session gets its own stream to each peer, so a slow dealing does not hold up the messages
+/// of another. A stream starts with the sender's id as a big endian u64, followed by length
+/// prefixed frames of CBOR encoded WireMessages
+pub struct QuicTransport {
+    id: usize,
+    runtime: Arc<Runtime>,
+    endpoint: Endpoint,
+    addresses: HashMap<usize, SocketAddr>,
+    connections: Mutex<HashMap<usize, Connection>>,
+    /// open stream to each peer by session id
+    streams: Mutex<HashMap<(usize, u64), SendStream>>,
+}
+
+/// Accepts the connections of a QuicTransport's endpoint
+pub struct QuicListener {
+    runtime: Arc<Runtime>,
+    endpoint: Endpoint,
+}
+
+impl QuicTransport {
+    /// Return a Transport for the player `id` over `socket`, trusting the peers listed in
+    /// `peers`
+    pub fn new(
+        id: usize,
+        socket: UdpSocket,
+        identity: &QuicIdentity,
+        peers: HashMap<usize, (SocketAddr, CertificateDer<'static>)>,
+    ) -> io::Result<QuicTransport> {
+        let runtime = Arc::new(Runtime::new()?);
+
+        let key = PrivateKeyDer::Pkcs8(PrivatePkcs8KeyDer::from(identity.key.clone()));
+        let server_config = ServerConfig::with_single_cert(vec![identity.cert.clone()], key)
+            .map_err(io::Error::other)?;
+
+        let mut roots = RootCertStore::empty();
+        for (_, cert) in peers.values() {
+            roots.add(cert.clone()).map_err(io::Error::other)?;
+        }
+        let client_config =
+            ClientConfig::with_root_certificates(Arc::new(roots)).map_err(io::Error::other)?;
+
+        let mut endpoint = {
+            let _enter = runtime.enter();
+            Endpoint::new(
+                EndpointConfig::default(),
+                Some(server_config),
+                socket,
+                Arc::new(TokioRuntime),
+            )?
+        };
+        endpoint.set_default_client_config(client_config);
+
+        Ok(QuicTransport {
+            id,
+            runtime,
+            endpoint,
+            addresses: peers
+                .into_iter()
+                .map(|(id, (addr, _))| (id, addr))
+                .collect(),
+            connections: Mutex::new(HashMap::new()),
+            streams: Mutex::new(HashMap::new()),
+        })
+    }
+
+    /// Return a listener for the connections peers make to this transport
+    pub fn listener(&self) -> QuicListener {
+        QuicListener {
+            runtime: Arc::clone(&self.runtime),
+            endpoint: self.endpoint.clone(),
+        }
+    }
+
+    async fn connection(&self, to: usize) -> io::Result<Connection> {
+        let mut connections = self.connections.lock().await;
+        if let Some(connection) = connections.get(&to) {
+            if connection.close_reason().is_none() {
+                return Ok(connection.clone());
+            }
+        }
+
+        let addr = self.addresses.get(&to).ok_or_else(|| {
+            io::Error::new(io::ErrorKind::NotFound, format!("unknown player {}", to))
+        })?;
+        let connection = self
+            .endpoint
+            .connect(*addr, &server_name(to))
+            .map_err(io::Error::other)?
+            .await?;
+        connections.insert(to, connection.clone());
+
+        Ok(connection)
+    }
+
+    async fn try_send(&self, to: usize, session_id: u64, frame: &[u8]) -> io::Result<()> {
+        let mut streams = self.streams.lock().await;
+        let stream = match streams.entry((to, session_id)) {
+            Entry::Occupied(entry) => entry.into_mut(),
+            Entry::Vacant(entry) => {
+                let mut stream = self.connection(to).await?.open_uni().await?;
+                stream.write_all(&(self.id as u64).to_be_bytes()).await?;
+                entry.insert(stream)
+            }
+        };
+
+        let result = write_frame(stream, frame).await;
+        if result.is_err() {
+            // reopen the stream, and the connection if it was lost, on the next attempt
+            streams.remove(&(to, session_id));
+        }
+
+        result
+    }
+}
+
+impl Transport for QuicTransport {
+    fn send(&self, to: usize, msg: WireMessage) -> io::Result<()> {
+        let frame = codec::encode(&msg)?;
+
+        self.runtime.block_on(async {
+            let mut attempt = 1;
+            loop {
+                match self.try_send(to, msg.session_id, &frame).await {
+                    Err(err)
+                        if err.kind() != io::ErrorKind::NotFound && attempt < SEND_ATTEMPTS =>
+                    {
+                        debug!(to, attempt, "error while sending, retrying: {}", err);
+                        attempt += 1;
+                    }
+                    result => return result,
+                }
+            }
+        })
+    }
+
+    fn peers(&self) -> Vec<usize> {
+        let mut peers: Vec<usize> = self
+            .addresses
+            .keys()
+            .copied()
+            .filter(|id| *id != self.id)
+            .collect();
+        peers.sort();
+
+        peers
+    }
+}
+
+impl QuicListener {
+    /// Accept connections and deliver every message received on any of their streams into
+    /// `inbox`
+    pub fn listen(self, inbox: Sender<RPC>) {
+        let QuicListener { runtime, endpoint } = self;
+
+        runtime.spawn(async move {
+            while let Some(incoming) = endpoint.accept().await {
+                let inbox = inbox.clone();
+                tokio::spawn(async move {
+                    let connection = match incoming.await {
+                        Ok(connection) => connection,
+                        Err(err) => return warn!("error while accepting connection: {}", err),
+                    };
+
+                    while let Ok(stream) = connection.accept_uni().await {
+                        let inbox = inbox.clone();
+                        tokio::spawn(async move {
+                            if let Err(err) = receive(stream, inbox).await {
+                                debug!("stream closed: {}", err);
+                            }
+                        });
+                    }
+                });
+            }
+        });
+    }
+}
+
+async fn receive(mut stream: RecvStream, inbox: Sender<RPC>) -> io::Result<()> {
+    let mut preamble = [0; 8];
+    stream
+        .read_exact(&mut preamble)
+        .await
+        .map_err(io::Error::other)?;
+    let from = u64::from_be_bytes(preamble) as usize;
+
+    loop {
+        let frame = read_frame(&mut stream).await?;
+        let msg: WireMessage = codec::decode(&frame)?;
+
+        if inbox.send(RPC::Wire(from, msg)).is_err() {
+            // the player has stopped
+            return Ok(());
+        }
+    }
+}
+
+async fn write_frame(stream: &mut SendStream, frame: &[u8]) -> io::Result<()> {
+    if frame.len() > MAX_FRAME_LEN {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "frame too large",
+        ));
+    }
+
+    let mut buf = Vec::with_capacity(4 + frame.len());
+    buf.extend_from_slice(&(frame.len() as u32).to_be_bytes());
+    buf.extend_from_slice(frame);
+
+    Ok(stream.write_all(&buf).await?)
+}
+
+async fn read_frame(stream: &mut RecvStream) -> io::Result<Vec<u8>> {
+    let mut len = [0; 4];
+    stream
+        .read_exact(&mut len)
+        .await
+        .map_err(io::Error::other)?;
+
+    let len = u32::from_be_bytes(len) as usize;
+    if len > MAX_FRAME_LEN {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "frame too large",
+        ));
+    }
+
+    let mut frame = vec![0; len];
+    stream
+        .read_exact(&mut frame)
+        .await
+        .map_err(io::Error::other)?;
+
+    Ok(frame)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+    use std::net::UdpSocket;
+    use std::thread;
+    use std::time::Duration;
+
+    use num_bigint::BigUint;
+
+    use crate::dealer::{Dealer, DEALER_ID};
+    use crate::player::Player;
+    use crate::rpc::{Router, RPC};
+
+    use super::{QuicIdentity, QuicTransport};
+
+    #[test]
+    fn quic_reconstruct() {
+        let n = 5;
+        let sockets: Vec<_> = (0..=n)
+            .map(|_| UdpSocket::bind("127.0.0.1:0").unwrap())
+            .collect();
+        let identities: Vec<_> = (0..=n)
+            .map(|id| QuicIdentity::generate(id).unwrap())
+            .collect();
+        let peers: HashMap<_, _> = (1..=n)
+            .map(|id| {
+                let addr = sockets[id].local_addr().unwrap();
+                (id, (addr, identities[id].cert().clone()))
+            })
+            .collect();
+
+        let mut transports: Vec<_> = sockets
+            .into_iter()
+            .zip(&identities)
+            .enumerate()
+            .map(|(id, (socket, identity))| {
+                QuicTransport::new(id, socket, identity, peers.clone()).unwrap()
+            })
+            .collect();
+
+        let dealer_transport = transports.remove(DEALER_ID);
+        let senders: Vec<_> = transports
+            .into_iter()
+            .enumerate()
+            .map(|(i, transport)| {
+                let listener = transport.listener();
+                let (sender, _) = Player::spawn(i + 1, transport);
+                listener.listen(sender.clone());
+
+                sender
+            })
+            .collect();
+
+        let dealer = Dealer::new(n, 3, 1234).unwrap();
+        dealer.propagate(&dealer_transport);
+        thread::sleep(Duration::from_millis(500));
+
+        let mut router = Router::new();
+        senders.iter().for_each(|s| {
+            s.send(RPC::Reconstruct(dealer.session_id, router.request()))
+                .unwrap();
+        });
+
+        let (_, report) = router.recv_timeout(Duration::from_secs(10)).unwrap();
+        assert_eq!(BigUint::from(1234_u32), *report.secret.expose());
+    }
+}
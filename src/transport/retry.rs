@@ -0,0 +1,309 @@
+use std::collections::HashMap;
+use std::io;
+use std::sync::mpsc::{self, Sender};
+use std::sync::{Arc, Mutex, Weak};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use tracing::{debug, warn};
+
+use crate::rpc::{Payload, WireMessage, RPC};
+use crate::transport::{Received, Transport};
+
+/// Wait for an Ack before the first resend, doubled after every resend
+const INITIAL_BACKOFF: Duration = Duration::from_millis(50);
+
+/// Transmissions of a message before giving up on it
+const MAX_TRANSMISSIONS: u32 = 6;
+
+/// How often the retry thread checks for messages to resend
+const POLL_INTERVAL: Duration = Duration::from_millis(10);
+
+struct Unacked {
+    msg: WireMessage,
+    resend_at: Instant,
+    backoff: Duration,
+    transmissions: u32,
+}
+
+#[derive(Default)]
+struct State {
+    next_seq: HashMap<usize, u64>,
+    /// messages sent but not acknowledged yet, by peer id and sequence number
+    unacked: HashMap<(usize, u64), Unacked>,
+    received: HashMap<usize, Received>,
+}
+
+struct Shared<T> {
+    inner: T,
+    state: Mutex<State>,
+}
+
+/// Adds acknowledgments and retries to any Transport
+///
+/// Every message is numbered per peer and sent as `Payload::Sequenced`. The receiving side
+/// answers each with `Payload::Ack` and delivers it once, however many times it arrives, while
+/// the sender resends it with exponential backoff until it is acknowledged or
+/// MAX_TRANSMISSIONS is reached. Both ends must use a RetryTransport
+pub struct RetryTransport<T> {
+    shared: Arc<Shared<T>>,
+}
+
+/// Strips acknowledgments from the messages delivered to a Player
+pub struct RetryListener<T> {
+    shared: Arc<Shared<T>>,
+}
+
+impl<T: Transport + Sync + 'static> RetryTransport<T> {
+    /// Return a Transport retrying the messages it sends over `inner`
+    pub fn new(inner: T) -> RetryTransport<T> {
+        let shared = Arc::new(Shared {
+            inner,
+            state: Mutex::new(State::default()),
+        });
+
+        let weak = Arc::downgrade(&shared);
+        thread::spawn(move || retry(weak));
+
+        RetryTransport { shared }
+    }
+
+    /// Return a listener filtering the messages the inner transport delivers
+    pub fn listener(&self) -> RetryListener<T> {
+        RetryListener {
+            shared: Arc::clone(&self.shared),
+        }
+    }
+}
+
+impl<T: Transport + Sync> Transport for RetryTransport<T> {
+    fn send(&self, to: usize, msg: WireMessage) -> io::Result<()> {
+        let mut state = self.shared.state.lock().unwrap();
+        let seq = *state.next_seq.get(&to).unwrap_or(&0);
+        let payload = Payload::Sequenced(seq, Box::new(msg.payload));
+        let msg = WireMessage::with_version(msg.version, msg.session_id, msg.epoch, payload);
+
+        // a failed first attempt is retried like a lost one
+        if let Err(err) = self.shared.inner.send(to, msg.clone()) {
+            if err.kind() == io::ErrorKind::NotFound {
+                return Err(err);
+            }
+            debug!(to, seq, "error while sending, will retry: {}", err);
+        }
+
+        state.next_seq.insert(to, seq + 1);
+        state.unacked.insert(
+            (to, seq),
+            Unacked {
+                msg,
+                resend_at: Instant::now() + INITIAL_BACKOFF,
+                backoff: INITIAL_BACKOFF,
+                transmissions: 1,
+            },
+        );
+
+        Ok(())
+    }
+
+    fn peers(&self) -> Vec<usize> {
+        self.shared.inner.peers()
+    }
+}
+
+impl<T: Transport + Sync + 'static> RetryListener<T> {
+    /// Return the inbox the inner transport should deliver into
+    ///
+    /// Acks are consumed, every other message is acknowledged and handed to `inbox` once
+    pub fn listen(self, inbox: Sender<RPC>) -> Sender<RPC> {
+        let (tx, rx) = mpsc::channel();
+
+        thread::spawn(move || {
+            while let Ok(rpc) = rx.recv() {
+                let rpc = match rpc {
+                    RPC::Wire(from, msg) => match self.receive(from, msg) {
+                        Some(msg) => RPC::Wire(from, msg),
+                        None => continue,
+                    },
+                    rpc => rpc,
+                };
+
+                if inbox.send(rpc).is_err() {
+                    // the player has stopped
+                    return;
+                }
+            }
+        });
+
+        tx
+    }
+
+    /// Returns the message to deliver for `msg`, if any
+    fn receive(&self, from: usize, msg: WireMessage) -> Option<WireMessage> {
+        let (seq, payload) = match msg.payload {
+            Payload::Sequenced(seq, payload) => (seq, payload),
+            Payload::Ack(seq) => {
+                self.shared
+                    .state
+                    .lock()
+                    .unwrap()
+                    .unacked
+                    .remove(&(from, seq));
+                return None;
+            }
+            // the peer does not retry this message
+            _ => return Some(msg),
+        };
+
+        let ack =
+            WireMessage::with_version(msg.version, msg.session_id, msg.epoch, Payload::Ack(seq));
+        if let Err(err) = self.shared.inner.send(from, ack) {
+            debug!(from, seq, "error while acknowledging: {}", err);
+        }
+
+        let mut state = self.shared.state.lock().unwrap();
+        if !state.received.entry(from).or_default().insert(seq) {
+            debug!(from, seq, "dropping duplicate");
+            return None;
+        }
+
+        Some(WireMessage::with_version(
+            msg.version,
+            msg.session_id,
+            msg.epoch,
+            *payload,
+        ))
+    }
+}
+
+/// Resends messages that were not acknowledged in time until the transport is dropped
+fn retry<T: Transport>(shared: Weak<Shared<T>>) {
+    loop {
+        thread::sleep(POLL_INTERVAL);
+        let shared = match shared.upgrade() {
+            Some(shared) => shared,
+            None => return,
+        };
+
+        let mut state = shared.state.lock().unwrap();
+        let now = Instant::now();
+        state.unacked.retain(|(to, seq), unacked| {
+            if now < unacked.resend_at {
+                return true;
+            }
+            if unacked.transmissions >= MAX_TRANSMISSIONS {
+                warn!(to, seq, "giving up on unacknowledged message");
+                return false;
+            }
+
+            debug!(to, seq, "resending");
+            if let Err(err) = shared.inner.send(*to, unacked.msg.clone()) {
+                debug!(to, seq, "error while resending: {}", err);
+            }
+            unacked.backoff *= 2;
+            unacked.resend_at = now + unacked.backoff;
+            unacked.transmissions += 1;
+
+            true
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::mpsc;
+    use std::thread;
+    use std::time::Duration;
+
+    use num_bigint::BigUint;
+
+    use crate::dealer::{Dealer, DEALER_ID};
+    use crate::player::Player;
+    use crate::rpc::{Payload, Router, WireMessage, NO_SESSION, RPC};
+    use crate::transport::{ChannelNetwork, ChannelTransport, Transport};
+
+    use super::RetryTransport;
+
+    /// Loses every other message it is asked to send
+    struct Lossy {
+        inner: ChannelTransport,
+        sent: AtomicUsize,
+    }
+
+    impl Lossy {
+        fn new(inner: ChannelTransport) -> Lossy {
+            Lossy {
+                inner,
+                sent: AtomicUsize::new(0),
+            }
+        }
+    }
+
+    impl Transport for Lossy {
+        fn send(&self, to: usize, msg: WireMessage) -> io::Result<()> {
+            if self.sent.fetch_add(1, Ordering::SeqCst).is_multiple_of(2) {
+                return Ok(());
+            }
+
+            self.inner.send(to, msg)
+        }
+
+        fn peers(&self) -> Vec<usize> {
+            self.inner.peers()
+        }
+    }
+
+    #[test]
+    fn delivers_once_over_lossy_transport() {
+        let network = ChannelNetwork::new();
+        let receiver = RetryTransport::new(Lossy::new(network.transport(1)));
+        let (inbox, rx) = mpsc::channel();
+        network.register(1, receiver.listener().listen(inbox));
+        let sender = RetryTransport::new(Lossy::new(network.transport(2)));
+        let (sender_inbox, sender_rx) = mpsc::channel();
+        network.register(2, sender.listener().listen(sender_inbox));
+
+        let ping = WireMessage::new(NO_SESSION, 0, Payload::Ping);
+        sender.send(1, ping.clone()).unwrap();
+
+        match rx.recv_timeout(Duration::from_secs(5)).unwrap() {
+            RPC::Wire(from, msg) => assert_eq!((2, ping), (from, msg)),
+            rpc => panic!("unexpected {:?}", rpc),
+        }
+        // resends past the first delivery are dropped, and the sender only ever sees acks
+        assert!(rx.recv_timeout(Duration::from_millis(500)).is_err());
+        assert!(sender_rx.try_recv().is_err());
+    }
+
+    #[test]
+    fn reconstructs_over_lossy_transport() {
+        let n = 5;
+        let network = ChannelNetwork::new();
+        let senders: Vec<_> = (1..=n)
+            .map(|id| {
+                let transport = RetryTransport::new(Lossy::new(network.transport(id)));
+                let listener = transport.listener();
+                let (sender, _) = Player::spawn(id, transport);
+                network.register(id, listener.listen(sender.clone()));
+
+                sender
+            })
+            .collect();
+
+        let dealer = Dealer::new(n, 3, 1234).unwrap();
+        // the dealer never hears the acks and resends every share until it gives up
+        let dealer_transport = RetryTransport::new(Lossy::new(network.transport(DEALER_ID)));
+        dealer.propagate(&dealer_transport);
+        thread::sleep(Duration::from_millis(200));
+
+        let mut router = Router::new();
+        senders.iter().for_each(|s| {
+            s.send(RPC::Reconstruct(dealer.session_id, router.request()))
+                .unwrap();
+        });
+
+        let (_, report) = router.recv_timeout(Duration::from_secs(5)).unwrap();
+        assert_eq!(BigUint::from(1234_u32), *report.secret.expose());
+    }
+}
@@ -0,0 +1,147 @@
+use std::collections::HashMap;
+use std::io;
+
+use crate::rpc::WireMessage;
+use crate::transport::Transport;
+
+/// Sends every message over the transport its recipient is routed to, so one dealing reaches
+/// players on different transports
+///
+/// A hybrid deployment routes its native nodes over TCP, the players of this process over
+/// channels and a browser shareholder over a WebSocket, and the dealer propagates over the
+/// RoutedTransport as over any other. Players reach each other the same way. Ids no route names
+/// go over the fallback transport, if there is one
+#[derive(Default)]
+pub struct RoutedTransport {
+    transports: Vec<Box<dyn Transport>>,
+    /// index into `transports` of every routed player, by id
+    routes: HashMap<usize, usize>,
+    fallback: Option<Box<dyn Transport>>,
+}
+
+impl RoutedTransport {
+    pub fn new() -> RoutedTransport {
+        RoutedTransport::default()
+    }
+
+    /// Route the players `ids` over `transport`, replacing any route they had
+    pub fn route<T, I>(mut self, ids: I, transport: T) -> RoutedTransport
+    where
+        T: Transport + 'static,
+        I: IntoIterator<Item = usize>,
+    {
+        let index = self.transports.len();
+        self.transports.push(Box::new(transport));
+        self.routes.extend(ids.into_iter().map(|id| (id, index)));
+
+        self
+    }
+
+    /// Send to players no route names over `transport`
+    pub fn with_fallback<T: Transport + 'static>(mut self, transport: T) -> RoutedTransport {
+        self.fallback = Some(Box::new(transport));
+        self
+    }
+
+    /// The transport messages to `id` go over, None if neither a route nor a fallback has it
+    pub fn transport_to(&self, id: usize) -> Option<&dyn Transport> {
+        match self.routes.get(&id) {
+            Some(&index) => Some(self.transports[index].as_ref()),
+            None => self.fallback.as_deref(),
+        }
+    }
+}
+
+impl Transport for RoutedTransport {
+    fn send(&self, to: usize, msg: WireMessage) -> io::Result<()> {
+        self.transport_to(to)
+            .ok_or_else(|| {
+                io::Error::new(
+                    io::ErrorKind::NotFound,
+                    format!("no route to player {}", to),
+                )
+            })?
+            .send(to, msg)
+    }
+
+    /// Every peer a routed transport reaches among the ids routed to it, and every peer the
+    /// fallback reaches that no route names
+    fn peers(&self) -> Vec<usize> {
+        let routed = self
+            .transports
+            .iter()
+            .enumerate()
+            .flat_map(|(index, transport)| {
+                transport
+                    .peers()
+                    .into_iter()
+                    .filter(move |id| self.routes.get(id) == Some(&index))
+            });
+        let fallback = self.fallback.iter().flat_map(|transport| {
+            transport
+                .peers()
+                .into_iter()
+                .filter(|id| !self.routes.contains_key(id))
+        });
+        let mut peers: Vec<usize> = routed.chain(fallback).collect();
+        peers.sort();
+        peers.dedup();
+
+        peers
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+    use std::net::TcpListener;
+    use std::thread;
+    use std::time::Duration;
+
+    use crate::dealer::{Dealer, DEALER_ID};
+    use crate::player::Player;
+    use crate::rpc::{Payload, Router, WireMessage, NO_SESSION, RPC};
+    use crate::transport::tcp::TcpTransport;
+    use crate::transport::{ChannelNetwork, Transport};
+
+    use super::RoutedTransport;
+
+    #[test]
+    fn dealing_reaches_players_over_every_route() {
+        // players 1 and 2 run in this process, player 3 on the other end of a TCP connection
+        let network = ChannelNetwork::new();
+        let mut senders: Vec<_> = (1..=2)
+            .map(|id| {
+                let (sender, _) = Player::spawn(id, network.transport(id));
+                network.register(id, sender.clone());
+
+                sender
+            })
+            .collect();
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addresses = HashMap::from([(3, listener.local_addr().unwrap())]);
+        let (sender, _) = Player::spawn(3, TcpTransport::new(3, addresses.clone()));
+        TcpTransport::listen(listener, sender.clone());
+        senders.push(sender);
+
+        let routed = RoutedTransport::new()
+            .route([1, 2], network.transport(DEALER_ID))
+            .route([3], TcpTransport::new(DEALER_ID, addresses));
+        assert_eq!(vec![1, 2, 3], routed.peers());
+        let ping = WireMessage::new(NO_SESSION, 0, Payload::Ping);
+        assert!(routed.send(4, ping).is_err());
+
+        let dealer = Dealer::new(3, 2, 1234).unwrap();
+        dealer.propagate(&routed);
+        thread::sleep(Duration::from_millis(200));
+
+        let mut router = Router::new();
+        senders
+            .iter()
+            .for_each(|s| s.send(RPC::Status(router.request())).unwrap());
+        for _ in &senders {
+            let (_, status) = router.recv_timeout(Duration::from_secs(5)).unwrap();
+            assert_eq!(vec![(dealer.session_id, 0)], status.sessions);
+        }
+    }
+}
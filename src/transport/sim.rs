@@ -0,0 +1,386 @@
+use std::cmp::{Ordering, Reverse};
+use std::collections::{BinaryHeap, HashSet};
+use std::io;
+use std::sync::{Arc, Mutex, Weak};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use rand::distributions::Exp1;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use tracing::debug;
+
+use crate::rpc::WireMessage;
+use crate::transport::Transport;
+
+/// How often the delivery thread checks for messages whose delay is over
+const POLL_INTERVAL: Duration = Duration::from_millis(1);
+
+/// How long a message spends in flight
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Delay {
+    None,
+    Fixed(Duration),
+    /// Anywhere between the two bounds, which reorders messages sent close together
+    Uniform(Duration, Duration),
+    /// Exponentially distributed with the given mean, mostly short with a long tail
+    Exponential(Duration),
+}
+
+impl Delay {
+    fn sample<R: Rng>(&self, rng: &mut R) -> Duration {
+        match *self {
+            Delay::None => Duration::ZERO,
+            Delay::Fixed(delay) => delay,
+            Delay::Uniform(min, max) if min < max => rng.gen_range(min, max),
+            Delay::Uniform(min, _) => min,
+            Delay::Exponential(mean) => mean.mul_f64(rng.sample(Exp1)),
+        }
+    }
+}
+
+/// Misbehavior a SimTransport injects into every message it sends
+#[derive(Debug, Clone, PartialEq)]
+pub struct SimConfig {
+    pub delay: Delay,
+    /// Probability a message is lost
+    pub drop_rate: f64,
+    /// Probability a message that is not lost arrives twice, each copy with its own delay
+    pub duplicate_rate: f64,
+    /// Seed of the rng every loss, duplicate and delay is drawn from, mixed with the id of the
+    /// transport, so a run can be replayed. A random one if none
+    pub seed: Option<u64>,
+}
+
+impl Default for SimConfig {
+    fn default() -> SimConfig {
+        SimConfig {
+            delay: Delay::None,
+            drop_rate: 0.0,
+            duplicate_rate: 0.0,
+            seed: None,
+        }
+    }
+}
+
+impl SimConfig {
+    /// Check that both rates are probabilities
+    pub fn validate(&self) -> io::Result<()> {
+        for (name, rate) in [
+            ("drop_rate", self.drop_rate),
+            ("duplicate_rate", self.duplicate_rate),
+        ] {
+            if !(0.0..=1.0).contains(&rate) {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    format!("{} must be between 0 and 1, not {}", name, rate),
+                ));
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Groups of ids cut off from each other, shared by the SimTransports of one network
+///
+/// Two ids in different groups cannot reach each other, an id in no group reaches everyone
+#[derive(Clone, Default)]
+pub struct Partitions {
+    groups: Arc<Mutex<Vec<HashSet<usize>>>>,
+}
+
+impl Partitions {
+    pub fn new() -> Partitions {
+        Partitions::default()
+    }
+
+    /// Cut the network into `groups`, replacing any earlier partition
+    pub fn split(&self, groups: &[&[usize]]) {
+        *self.groups.lock().unwrap() = groups
+            .iter()
+            .map(|group| group.iter().copied().collect())
+            .collect();
+    }
+
+    /// Let every id reach every other again
+    pub fn heal(&self) {
+        self.groups.lock().unwrap().clear();
+    }
+
+    /// Whether `from` and `to` are in different groups
+    pub fn is_cut(&self, from: usize, to: usize) -> bool {
+        let groups = self.groups.lock().unwrap();
+        let group_of = |id| groups.iter().position(|group| group.contains(&id));
+
+        match (group_of(from), group_of(to)) {
+            (Some(a), Some(b)) => a != b,
+            _ => false,
+        }
+    }
+}
+
+/// A message waiting out its delay
+struct InFlight {
+    deliver_at: Instant,
+    /// breaks ties in send order
+    seq: u64,
+    to: usize,
+    msg: WireMessage,
+}
+
+impl PartialEq for InFlight {
+    fn eq(&self, other: &InFlight) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for InFlight {}
+
+impl PartialOrd for InFlight {
+    fn partial_cmp(&self, other: &InFlight) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for InFlight {
+    fn cmp(&self, other: &InFlight) -> Ordering {
+        (self.deliver_at, self.seq).cmp(&(other.deliver_at, other.seq))
+    }
+}
+
+struct State {
+    next_seq: u64,
+    in_flight: BinaryHeap<Reverse<InFlight>>,
+    rng: StdRng,
+}
+
+struct Shared<T> {
+    inner: T,
+    state: Mutex<State>,
+}
+
+/// Wraps a Transport, usually the in-memory one, with the misbehavior of a real network
+///
+/// Every message is delayed, lost, duplicated or cut off by a partition as `config` and
+/// `partitions` say, so the protocol can be tested against the networks it claims to survive.
+/// A lost message still counts as sent, as it would over a real network
+pub struct SimTransport<T> {
+    id: usize,
+    config: SimConfig,
+    partitions: Partitions,
+    shared: Arc<Shared<T>>,
+}
+
+impl<T: Transport + Sync + 'static> SimTransport<T> {
+    /// Return a Transport sending as `id` over `inner`, an error if `config` is not valid
+    pub fn new(
+        id: usize,
+        inner: T,
+        config: SimConfig,
+        partitions: Partitions,
+    ) -> io::Result<SimTransport<T>> {
+        config.validate()?;
+        let rng = match config.seed {
+            Some(seed) => StdRng::seed_from_u64(seed ^ (id as u64).rotate_left(32)),
+            None => StdRng::from_rng(rand::thread_rng()).map_err(io::Error::other)?,
+        };
+        let shared = Arc::new(Shared {
+            inner,
+            state: Mutex::new(State {
+                next_seq: 0,
+                in_flight: BinaryHeap::new(),
+                rng,
+            }),
+        });
+
+        let weak = Arc::downgrade(&shared);
+        thread::spawn(move || deliver(weak));
+
+        Ok(SimTransport {
+            id,
+            config,
+            partitions,
+            shared,
+        })
+    }
+}
+
+impl<T: Transport + Sync> Transport for SimTransport<T> {
+    fn send(&self, to: usize, msg: WireMessage) -> io::Result<()> {
+        if !self.shared.inner.peers().contains(&to) {
+            return Err(io::Error::new(
+                io::ErrorKind::NotFound,
+                format!("unknown player {}", to),
+            ));
+        }
+
+        if self.partitions.is_cut(self.id, to) {
+            debug!(to, "partitioned, dropping message");
+            return Ok(());
+        }
+
+        let now = Instant::now();
+        let mut state = self.shared.state.lock().unwrap();
+        if state.rng.gen_bool(self.config.drop_rate) {
+            debug!(to, "dropping message");
+            return Ok(());
+        }
+        let copies = if state.rng.gen_bool(self.config.duplicate_rate) {
+            2
+        } else {
+            1
+        };
+
+        for _ in 0..copies {
+            let seq = state.next_seq;
+            state.next_seq += 1;
+            let delay = self.config.delay.sample(&mut state.rng);
+            state.in_flight.push(Reverse(InFlight {
+                deliver_at: now + delay,
+                seq,
+                to,
+                msg: msg.clone(),
+            }));
+        }
+
+        Ok(())
+    }
+
+    fn peers(&self) -> Vec<usize> {
+        self.shared.inner.peers()
+    }
+}
+
+/// Hands messages to the inner transport once their delay is over until the transport is
+/// dropped, losing whatever is still in flight
+fn deliver<T: Transport>(shared: Weak<Shared<T>>) {
+    loop {
+        thread::sleep(POLL_INTERVAL);
+        let shared = match shared.upgrade() {
+            Some(shared) => shared,
+            None => return,
+        };
+
+        let now = Instant::now();
+        let due: Vec<InFlight> = {
+            let mut state = shared.state.lock().unwrap();
+            std::iter::from_fn(|| match state.in_flight.peek() {
+                Some(Reverse(next)) if next.deliver_at <= now => {
+                    state.in_flight.pop().map(|Reverse(due)| due)
+                }
+                _ => None,
+            })
+            .collect()
+        };
+
+        for InFlight { to, msg, .. } in due {
+            if let Err(err) = shared.inner.send(to, msg) {
+                debug!(to, "error while delivering: {}", err);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::mpsc;
+    use std::time::Duration;
+
+    use num_bigint::BigUint;
+
+    use crate::dealer::{Dealer, DEALER_ID};
+    use crate::player::Player;
+    use crate::rpc::{Payload, Router, WireMessage, NO_SESSION, RPC};
+    use crate::transport::{ChannelNetwork, Transport};
+
+    use super::{Delay, Partitions, SimConfig, SimTransport};
+
+    #[test]
+    fn reconstructs_over_reordering_duplicating_network() {
+        let n = 5;
+        let network = ChannelNetwork::new();
+        let partitions = Partitions::new();
+        let config = SimConfig {
+            delay: Delay::Uniform(Duration::ZERO, Duration::from_millis(20)),
+            drop_rate: 0.0,
+            duplicate_rate: 0.3,
+            seed: Some(7),
+        };
+        let sim = |id| {
+            SimTransport::new(
+                id,
+                network.transport(id),
+                config.clone(),
+                partitions.clone(),
+            )
+            .unwrap()
+        };
+        let senders: Vec<_> = (1..=n)
+            .map(|id| {
+                let (sender, _) = Player::spawn(id, sim(id));
+                network.register(id, sender.clone());
+
+                sender
+            })
+            .collect();
+
+        let dealer = Dealer::new(n, 3, 1234).unwrap();
+        // keep the dealer's transport until its shares are delivered
+        let dealer_transport = sim(DEALER_ID);
+        dealer.propagate(&dealer_transport);
+        std::thread::sleep(Duration::from_millis(200));
+
+        let mut router = Router::new();
+        senders.iter().for_each(|s| {
+            s.send(RPC::Reconstruct(dealer.session_id, router.request()))
+                .unwrap();
+        });
+
+        let (_, report) = router.recv_timeout(Duration::from_secs(5)).unwrap();
+        assert_eq!(BigUint::from(1234_u32), *report.secret.expose());
+    }
+
+    #[test]
+    fn partition_cuts_messages_until_healed() {
+        let network = ChannelNetwork::new();
+        let partitions = Partitions::new();
+        let (inbox, rx) = mpsc::channel();
+        network.register(2, inbox);
+        let sender = SimTransport::new(
+            1,
+            network.transport(1),
+            SimConfig::default(),
+            partitions.clone(),
+        )
+        .unwrap();
+        let ping = WireMessage::new(NO_SESSION, 0, Payload::Ping);
+
+        partitions.split(&[&[1], &[2, 3]]);
+        sender.send(2, ping.clone()).unwrap();
+        assert!(rx.recv_timeout(Duration::from_millis(100)).is_err());
+
+        partitions.heal();
+        sender.send(2, ping.clone()).unwrap();
+        match rx.recv_timeout(Duration::from_secs(1)).unwrap() {
+            RPC::Wire(from, msg) => assert_eq!((1, ping), (from, msg)),
+            rpc => panic!("unexpected {:?}", rpc),
+        }
+    }
+
+    #[test]
+    fn rates_outside_zero_to_one_are_refused() {
+        let network = ChannelNetwork::new();
+        for (drop_rate, duplicate_rate) in [(1.5, 0.0), (0.0, -0.1), (f64::NAN, 0.0)] {
+            let config = SimConfig {
+                drop_rate,
+                duplicate_rate,
+                ..SimConfig::default()
+            };
+            let err = SimTransport::new(1, network.transport(1), config, Partitions::new())
+                .err()
+                .unwrap();
+            assert_eq!(std::io::ErrorKind::InvalidInput, err.kind());
+        }
+    }
+}
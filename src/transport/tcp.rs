@@ -0,0 +1,432 @@
+use std::collections::hash_map::Entry;
+use std::collections::HashMap;
+use std::io::{self, Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::sync::mpsc::Sender;
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+use tracing::{debug, warn};
+
+use crate::codec::{self, Cbor, Codec};
+use crate::rpc::{WireMessage, RPC};
+use crate::transport::address_book::AddressBook;
+#[cfg(feature = "noise")]
+use crate::transport::noise::NoiseConfig;
+#[cfg(feature = "tls")]
+use crate::transport::tls::TlsConfig;
+use crate::transport::Transport;
+
+/// A connection, in the clear or secured with TLS or Noise
+trait Stream: Read + Write + Send {}
+
+impl<T: Read + Write + Send> Stream for T {}
+
+/// A dialed connection with a handle on its socket to tell whether the peer hung up
+struct Connection {
+    socket: TcpStream,
+    stream: Box<dyn Stream>,
+}
+
+impl Connection {
+    /// Whether the peer closed the connection, as a peer that restarted on the same address
+    /// does. A write to it would still succeed once, losing the frame
+    fn is_closed(&self) -> bool {
+        let mut byte = [0];
+        if self.socket.set_nonblocking(true).is_err() {
+            return true;
+        }
+        let closed = match self.socket.peek(&mut byte) {
+            Ok(n) => n == 0,
+            Err(err) => err.kind() != io::ErrorKind::WouldBlock,
+        };
+
+        self.socket.set_nonblocking(false).is_err() || closed
+    }
+}
+
+/// Read the id a connection in the clear starts with
+fn preamble(mut stream: TcpStream) -> io::Result<(Box<dyn Stream>, usize)> {
+    let mut preamble = [0; 8];
+    stream.read_exact(&mut preamble)?;
+    let from = u64::from_be_bytes(preamble) as usize;
+
+    Ok((Box::new(stream), from))
+}
+
+/// Transport between processes over TCP
+///
+/// Every player listens on the address the AddressBook lists for it. A connection starts
+/// with the dialer's id as a big endian u64, followed by length prefixed frames of CBOR encoded
+/// WireMessages. Connections are dialed on first use or by `bootstrap`, and dropped and
+/// redialed as the book's Redial says when a send fails. With the `tls` feature connections can be secured with mutual TLS, see TlsConfig, and
+/// with the `noise` feature with a Noise handshake, see NoiseConfig. Another Codec than CBOR can
+/// be used with `with_codec` and `listen_with_codec`
+pub struct TcpTransport {
+    id: usize,
+    book: AddressBook,
+    connections: Mutex<HashMap<usize, Connection>>,
+    codec: Arc<dyn Codec>,
+    #[cfg(feature = "noise")]
+    noise: Option<Arc<NoiseConfig>>,
+    #[cfg(feature = "tls")]
+    tls: Option<Arc<TlsConfig>>,
+}
+
+impl TcpTransport {
+    /// Return a Transport sending as `id` to the players listed in `book`, an AddressBook or
+    /// the address of every player by id
+    pub fn new<B: Into<AddressBook>>(id: usize, book: B) -> TcpTransport {
+        TcpTransport {
+            id,
+            book: book.into(),
+            connections: Mutex::new(HashMap::new()),
+            codec: Arc::new(Cbor),
+            #[cfg(feature = "noise")]
+            noise: None,
+            #[cfg(feature = "tls")]
+            tls: None,
+        }
+    }
+
+    /// Return a Transport sending as `id` to the players listed in `book` over mutual TLS
+    #[cfg(feature = "tls")]
+    pub fn with_tls<B: Into<AddressBook>>(id: usize, book: B, tls: Arc<TlsConfig>) -> TcpTransport {
+        TcpTransport {
+            tls: Some(tls),
+            ..TcpTransport::new(id, book)
+        }
+    }
+
+    /// Return a Transport sending as `id` to the players listed in `book` over Noise
+    #[cfg(feature = "noise")]
+    pub fn with_noise<B: Into<AddressBook>>(
+        id: usize,
+        book: B,
+        noise: Arc<NoiseConfig>,
+    ) -> TcpTransport {
+        TcpTransport {
+            noise: Some(noise),
+            ..TcpTransport::new(id, book)
+        }
+    }
+
+    /// Encode the messages this transport sends with `codec` rather than CBOR
+    pub fn with_codec(self, codec: Arc<dyn Codec>) -> TcpTransport {
+        TcpTransport { codec, ..self }
+    }
+
+    /// Accept connections on `listener` and deliver every message received into `inbox`
+    ///
+    /// Each connection is read on its own thread until the peer disconnects or the inbox is
+    /// closed
+    pub fn listen(listener: TcpListener, inbox: Sender<RPC>) -> JoinHandle<()> {
+        TcpTransport::listen_with_codec(listener, Arc::new(Cbor), inbox)
+    }
+
+    /// Accept connections on `listener` whose messages are encoded with `codec` and deliver
+    /// every message received into `inbox`
+    pub fn listen_with_codec(
+        listener: TcpListener,
+        codec: Arc<dyn Codec>,
+        inbox: Sender<RPC>,
+    ) -> JoinHandle<()> {
+        TcpTransport::accept(listener, codec, inbox, preamble)
+    }
+
+    /// Accept connections on `listener` whose messages are encoded with `codec`, refusing
+    /// those of peers `book` does not accept, and deliver every message received into `inbox`
+    pub fn listen_known(
+        listener: TcpListener,
+        book: Arc<AddressBook>,
+        codec: Arc<dyn Codec>,
+        inbox: Sender<RPC>,
+    ) -> JoinHandle<()> {
+        TcpTransport::accept(listener, codec, inbox, move |stream| {
+            let (stream, from) = preamble(stream)?;
+            if !book.accepts(from) {
+                return Err(io::Error::new(
+                    io::ErrorKind::PermissionDenied,
+                    format!("unknown player {}", from),
+                ));
+            }
+
+            Ok((stream, from))
+        })
+    }
+
+    /// Accept mutual TLS connections on `listener` and deliver every message received into
+    /// `inbox`
+    ///
+    /// Connections from a peer whose certificate was not issued for the id it claims are
+    /// dropped
+    #[cfg(feature = "tls")]
+    pub fn listen_tls(
+        listener: TcpListener,
+        tls: Arc<TlsConfig>,
+        inbox: Sender<RPC>,
+    ) -> JoinHandle<()> {
+        TcpTransport::accept(listener, Arc::new(Cbor), inbox, move |stream| {
+            let (stream, from) = tls.accept(stream)?;
+
+            Ok((Box::new(stream), from))
+        })
+    }
+
+    /// Accept Noise connections on `listener` and deliver every message received into `inbox`
+    ///
+    /// Connections from a peer whose static key is not listed for the id it claims are dropped
+    #[cfg(feature = "noise")]
+    pub fn listen_noise(
+        listener: TcpListener,
+        noise: Arc<NoiseConfig>,
+        inbox: Sender<RPC>,
+    ) -> JoinHandle<()> {
+        TcpTransport::accept(listener, Arc::new(Cbor), inbox, move |stream| {
+            let (stream, from) = noise.accept(stream)?;
+
+            Ok((Box::new(stream), from))
+        })
+    }
+
+    /// Accept connections on `listener`, `handshake` returns the connection ready to read
+    /// frames from along with the id of the peer that dialed it
+    fn accept<F>(
+        listener: TcpListener,
+        codec: Arc<dyn Codec>,
+        inbox: Sender<RPC>,
+        handshake: F,
+    ) -> JoinHandle<()>
+    where
+        F: Fn(TcpStream) -> io::Result<(Box<dyn Stream>, usize)> + Clone + Send + 'static,
+    {
+        thread::spawn(move || {
+            for stream in listener.incoming() {
+                match stream {
+                    Ok(stream) => {
+                        let codec = Arc::clone(&codec);
+                        let inbox = inbox.clone();
+                        let handshake = handshake.clone();
+                        thread::spawn(move || {
+                            let result = handshake(stream).and_then(|(stream, from)| {
+                                TcpTransport::receive(stream, from, &*codec, inbox)
+                            });
+                            if let Err(err) = result {
+                                debug!("connection closed: {}", err);
+                            }
+                        });
+                    }
+                    Err(err) => warn!("error while accepting connection: {}", err),
+                }
+            }
+        })
+    }
+
+    fn receive(
+        mut stream: Box<dyn Stream>,
+        from: usize,
+        codec: &dyn Codec,
+        inbox: Sender<RPC>,
+    ) -> io::Result<()> {
+        loop {
+            let frame = codec::read_frame(&mut stream)?;
+            let msg = codec.decode(&frame)?;
+
+            if inbox.send(RPC::Wire(from, msg)).is_err() {
+                // the player has stopped
+                return Ok(());
+            }
+        }
+    }
+
+    fn connect(&self, to: usize) -> io::Result<Connection> {
+        let stream = TcpStream::connect(self.book.address(to)?)?;
+        stream.set_nodelay(true)?;
+        let socket = stream.try_clone()?;
+        let mut stream = self.secure(to, stream)?;
+        stream.write_all(&(self.id as u64).to_be_bytes())?;
+
+        Ok(Connection { socket, stream })
+    }
+
+    /// Dial every peer not yet connected, redialing those that do not answer with the book's
+    /// backoff until `timeout` passes, returning the peers still unreachable by then
+    pub fn bootstrap(&self, timeout: Duration) -> Vec<usize> {
+        let deadline = Instant::now() + timeout;
+        let mut backoff = self.book.redial().backoff;
+        let mut unreachable = self.peers();
+        loop {
+            unreachable.retain(|&to| {
+                let mut connections = self.connections.lock().unwrap();
+                if connections.contains_key(&to) {
+                    return false;
+                }
+                match self.connect(to) {
+                    Ok(connection) => {
+                        connections.insert(to, connection);
+                        false
+                    }
+                    Err(err) => {
+                        debug!(to, "peer unreachable: {}", err);
+                        true
+                    }
+                }
+            });
+
+            let left = deadline.saturating_duration_since(Instant::now());
+            if unreachable.is_empty() || left.is_zero() {
+                return unreachable;
+            }
+            thread::sleep(backoff.min(left));
+            backoff *= 2;
+        }
+    }
+
+    #[cfg(any(feature = "noise", feature = "tls"))]
+    fn secure(&self, to: usize, stream: TcpStream) -> io::Result<Box<dyn Stream>> {
+        #[cfg(feature = "tls")]
+        if let Some(tls) = &self.tls {
+            return Ok(Box::new(tls.connect(to, stream)?));
+        }
+        #[cfg(feature = "noise")]
+        if let Some(noise) = &self.noise {
+            return Ok(Box::new(noise.connect(to, stream)?));
+        }
+
+        Ok(Box::new(stream))
+    }
+
+    #[cfg(not(any(feature = "noise", feature = "tls")))]
+    fn secure(&self, _to: usize, stream: TcpStream) -> io::Result<Box<dyn Stream>> {
+        Ok(Box::new(stream))
+    }
+
+    fn try_send(&self, to: usize, frame: &[u8]) -> io::Result<()> {
+        let mut connections = self.connections.lock().unwrap();
+        if connections.get(&to).is_some_and(Connection::is_closed) {
+            connections.remove(&to);
+        }
+        let connection = match connections.entry(to) {
+            Entry::Occupied(entry) => entry.into_mut(),
+            Entry::Vacant(entry) => entry.insert(self.connect(to)?),
+        };
+
+        let result = codec::write_frame(&mut connection.stream, frame);
+        if result.is_err() {
+            // redial on the next attempt
+            connections.remove(&to);
+        }
+
+        result
+    }
+}
+
+impl Transport for TcpTransport {
+    fn send(&self, to: usize, msg: WireMessage) -> io::Result<()> {
+        let frame = self.codec.encode(&msg)?;
+
+        let redial = self.book.redial();
+        let mut backoff = redial.backoff;
+        let mut attempt = 1;
+        loop {
+            match self.try_send(to, &frame) {
+                Err(err) if err.kind() != io::ErrorKind::NotFound && attempt < redial.attempts => {
+                    debug!(to, attempt, "error while sending, retrying: {}", err);
+                    thread::sleep(backoff);
+                    backoff *= 2;
+                    attempt += 1;
+                }
+                result => return result,
+            }
+        }
+    }
+
+    fn peers(&self) -> Vec<usize> {
+        self.book
+            .ids()
+            .into_iter()
+            .filter(|id| *id != self.id)
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+    use std::net::TcpListener;
+    use std::thread;
+    use std::time::Duration;
+
+    use num_bigint::BigUint;
+
+    use crate::dealer::{Dealer, DEALER_ID};
+    use crate::player::Player;
+    use crate::rpc::{Router, RPC};
+
+    use super::TcpTransport;
+
+    #[cfg(feature = "bincode")]
+    #[test]
+    fn delivers_with_bincode_codec() {
+        use std::sync::{mpsc, Arc};
+
+        use crate::codec::Bincode;
+        use crate::rpc::{Payload, WireMessage, NO_SESSION};
+        use crate::transport::Transport;
+
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addresses = HashMap::from([(1, listener.local_addr().unwrap())]);
+        let (inbox, rx) = mpsc::channel();
+        TcpTransport::listen_with_codec(listener, Arc::new(Bincode), inbox);
+
+        let ping = WireMessage::new(NO_SESSION, 0, Payload::Ping);
+        TcpTransport::new(2, addresses)
+            .with_codec(Arc::new(Bincode))
+            .send(1, ping.clone())
+            .unwrap();
+
+        match rx.recv_timeout(Duration::from_secs(5)).unwrap() {
+            RPC::Wire(from, msg) => assert_eq!((2, ping), (from, msg)),
+            rpc => panic!("unexpected {:?}", rpc),
+        }
+    }
+
+    #[test]
+    fn tcp_reconstruct() {
+        let n = 5;
+        let listeners: Vec<_> = (1..=n)
+            .map(|_| TcpListener::bind("127.0.0.1:0").unwrap())
+            .collect();
+        let addresses: HashMap<_, _> = listeners
+            .iter()
+            .enumerate()
+            .map(|(i, l)| (i + 1, l.local_addr().unwrap()))
+            .collect();
+
+        let senders: Vec<_> = listeners
+            .into_iter()
+            .enumerate()
+            .map(|(i, listener)| {
+                let id = i + 1;
+                let (sender, _) = Player::spawn(id, TcpTransport::new(id, addresses.clone()));
+                TcpTransport::listen(listener, sender.clone());
+
+                sender
+            })
+            .collect();
+
+        let dealer = Dealer::new(n, 3, 1234).unwrap();
+        dealer.propagate(&TcpTransport::new(DEALER_ID, addresses));
+        thread::sleep(Duration::from_millis(200));
+
+        let mut router = Router::new();
+        senders.iter().for_each(|s| {
+            s.send(RPC::Reconstruct(dealer.session_id, router.request()))
+                .unwrap();
+        });
+
+        let (_, report) = router.recv_timeout(Duration::from_secs(5)).unwrap();
+        assert_eq!(BigUint::from(1234_u32), *report.secret.expose());
+    }
+}
@@ -0,0 +1,216 @@
+use std::io::{self, Read};
+use std::net::TcpStream;
+use std::sync::Arc;
+
+use rcgen::{
+    BasicConstraints, Certificate, CertificateParams, ExtendedKeyUsagePurpose, IsCa, KeyPair,
+};
+use rustls::client::danger::ServerCertVerifier;
+use rustls::client::WebPkiServerVerifier;
+use rustls::crypto::ring;
+use rustls::pki_types::{CertificateDer, PrivateKeyDer, PrivatePkcs8KeyDer, ServerName, UnixTime};
+use rustls::server::WebPkiClientVerifier;
+use rustls::{
+    ClientConfig, ClientConnection, RootCertStore, ServerConfig, ServerConnection, StreamOwned,
+};
+
+use crate::transport::server_name;
+
+/// Certificate authority issuing the certificates players authenticate each other with
+pub struct TlsCa {
+    cert: Certificate,
+    key: KeyPair,
+}
+
+impl TlsCa {
+    /// Return a new self-signed certificate authority
+    pub fn generate() -> io::Result<TlsCa> {
+        let mut params = CertificateParams::new(Vec::<String>::new()).map_err(io::Error::other)?;
+        params.is_ca = IsCa::Ca(BasicConstraints::Unconstrained);
+        let key = KeyPair::generate().map_err(io::Error::other)?;
+        let cert = params.self_signed(&key).map_err(io::Error::other)?;
+
+        Ok(TlsCa { cert, key })
+    }
+
+    /// The certificate every player trusts
+    pub fn cert(&self) -> CertificateDer<'static> {
+        self.cert.der().clone()
+    }
+
+    /// Issue the certificate identifying the player `id`
+    ///
+    /// The certificate is valid both for accepting and dialing connections
+    pub fn issue(&self, id: usize) -> io::Result<TlsIdentity> {
+        let mut params = CertificateParams::new(vec![server_name(id)]).map_err(io::Error::other)?;
+        params.extended_key_usages = vec![
+            ExtendedKeyUsagePurpose::ServerAuth,
+            ExtendedKeyUsagePurpose::ClientAuth,
+        ];
+        let key = KeyPair::generate().map_err(io::Error::other)?;
+        let cert = params
+            .signed_by(&key, &self.cert, &self.key)
+            .map_err(io::Error::other)?;
+
+        Ok(TlsIdentity {
+            cert_chain: vec![cert.der().clone()],
+            key: PrivatePkcs8KeyDer::from(key.serialize_der()).into(),
+        })
+    }
+}
+
+/// A player's certificate chain and private key
+pub struct TlsIdentity {
+    cert_chain: Vec<CertificateDer<'static>>,
+    key: PrivateKeyDer<'static>,
+}
+
+impl TlsIdentity {
+    pub fn new(
+        cert_chain: Vec<CertificateDer<'static>>,
+        key: PrivateKeyDer<'static>,
+    ) -> TlsIdentity {
+        TlsIdentity { cert_chain, key }
+    }
+}
+
+/// Mutual TLS settings for the TCP transport
+///
+/// Both ends of a connection present a certificate issued by the CA. A dialer only accepts the
+/// certificate of the player it dials, and a listener only accepts a dialer whose certificate
+/// was issued for the id it claims
+pub struct TlsConfig {
+    client: Arc<ClientConfig>,
+    server: Arc<ServerConfig>,
+    peer_verifier: Arc<WebPkiServerVerifier>,
+}
+
+impl TlsConfig {
+    /// Return the settings of a player presenting `identity` and trusting `ca`
+    pub fn new(ca: CertificateDer<'static>, identity: TlsIdentity) -> io::Result<TlsConfig> {
+        let provider = Arc::new(ring::default_provider());
+        let mut roots = RootCertStore::empty();
+        roots.add(ca).map_err(io::Error::other)?;
+        let roots = Arc::new(roots);
+
+        let client = ClientConfig::builder_with_provider(Arc::clone(&provider))
+            .with_safe_default_protocol_versions()
+            .map_err(io::Error::other)?
+            .with_root_certificates(Arc::clone(&roots))
+            .with_client_auth_cert(identity.cert_chain.clone(), identity.key.clone_key())
+            .map_err(io::Error::other)?;
+
+        let client_verifier =
+            WebPkiClientVerifier::builder_with_provider(Arc::clone(&roots), Arc::clone(&provider))
+                .build()
+                .map_err(io::Error::other)?;
+        let server = ServerConfig::builder_with_provider(Arc::clone(&provider))
+            .with_safe_default_protocol_versions()
+            .map_err(io::Error::other)?
+            .with_client_cert_verifier(client_verifier)
+            .with_single_cert(identity.cert_chain, identity.key)
+            .map_err(io::Error::other)?;
+
+        let peer_verifier = WebPkiServerVerifier::builder_with_provider(roots, provider)
+            .build()
+            .map_err(io::Error::other)?;
+
+        Ok(TlsConfig {
+            client: Arc::new(client),
+            server: Arc::new(server),
+            peer_verifier,
+        })
+    }
+
+    /// Secure a connection dialed to the player `to`
+    pub(crate) fn connect(
+        &self,
+        to: usize,
+        stream: TcpStream,
+    ) -> io::Result<StreamOwned<ClientConnection, TcpStream>> {
+        let name = ServerName::try_from(server_name(to)).map_err(io::Error::other)?;
+        let conn =
+            ClientConnection::new(Arc::clone(&self.client), name).map_err(io::Error::other)?;
+
+        Ok(StreamOwned::new(conn, stream))
+    }
+
+    /// Secure an accepted connection, returning it with the id of the player that dialed it
+    ///
+    /// The dialer's claimed id is read from the connection and must match its certificate
+    pub(crate) fn accept(
+        &self,
+        stream: TcpStream,
+    ) -> io::Result<(StreamOwned<ServerConnection, TcpStream>, usize)> {
+        let conn = ServerConnection::new(Arc::clone(&self.server)).map_err(io::Error::other)?;
+        let mut stream = StreamOwned::new(conn, stream);
+
+        // reading drives the handshake, after which the dialer's certificate is known
+        let mut preamble = [0; 8];
+        stream.read_exact(&mut preamble)?;
+        let from = u64::from_be_bytes(preamble) as usize;
+
+        let certs = stream.conn.peer_certificates().unwrap_or_default();
+        let (end_entity, intermediates) = certs.split_first().ok_or_else(|| {
+            io::Error::new(io::ErrorKind::PermissionDenied, "no client certificate")
+        })?;
+        let name = ServerName::try_from(server_name(from)).map_err(io::Error::other)?;
+        self.peer_verifier
+            .verify_server_cert(end_entity, intermediates, &name, &[], UnixTime::now())
+            .map_err(|err| {
+                io::Error::new(
+                    io::ErrorKind::PermissionDenied,
+                    format!("certificate is not for player {}: {}", from, err),
+                )
+            })?;
+
+        Ok((stream, from))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+    use std::net::TcpListener;
+    use std::sync::{mpsc, Arc};
+    use std::time::Duration;
+
+    use crate::rpc::{Payload, WireMessage, NO_SESSION, RPC};
+    use crate::transport::tcp::TcpTransport;
+    use crate::transport::Transport;
+
+    use super::{TlsCa, TlsConfig};
+
+    fn ping() -> WireMessage {
+        WireMessage::new(NO_SESSION, 0, Payload::Ping)
+    }
+
+    /// Sends a ping as player 2 with a certificate issued for `cert_id` to a listening player 1
+    /// and returns what player 1 received
+    fn ping_as(cert_id: usize) -> Option<RPC> {
+        let ca = TlsCa::generate().unwrap();
+        let tls = |id| Arc::new(TlsConfig::new(ca.cert(), ca.issue(id).unwrap()).unwrap());
+
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addresses = HashMap::from([(1, listener.local_addr().unwrap())]);
+        let (inbox, rx) = mpsc::channel();
+        TcpTransport::listen_tls(listener, tls(1), inbox);
+
+        let _ = TcpTransport::with_tls(2, addresses, tls(cert_id)).send(1, ping());
+
+        rx.recv_timeout(Duration::from_millis(500)).ok()
+    }
+
+    #[test]
+    fn accepts_certificate_of_claimed_player() {
+        match ping_as(2) {
+            Some(RPC::Wire(from, msg)) => assert_eq!((2, ping()), (from, msg)),
+            rpc => panic!("unexpected {:?}", rpc),
+        }
+    }
+
+    #[test]
+    fn rejects_certificate_of_another_player() {
+        assert!(ping_as(3).is_none());
+    }
+}
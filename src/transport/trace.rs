@@ -0,0 +1,533 @@
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, BufWriter, Read, Write};
+use std::path::Path;
+use std::sync::mpsc::Sender;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use num_bigint::{BigUint, RandBigInt};
+use rand::rngs::StdRng;
+use rand::{CryptoRng, RngCore, SeedableRng};
+use serde::{Deserialize, Serialize};
+
+use crate::broadcast::BroadcastMessage;
+use crate::codec;
+use crate::player::Player;
+use crate::rpc::{
+    Metadata, Payload, PublicParams, ReconstructionReport, Router, Share, ShareInfo, WireMessage,
+    NO_SESSION, RPC,
+};
+use crate::transport::Transport;
+use crate::vss;
+
+/// Version of the trace exports this crate writes, and the newest it reads
+pub const EXPORT_VERSION: u32 = 1;
+
+/// One message as it was sent, written as a line of JSON
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TraceEvent {
+    /// microseconds since the recorder was created
+    pub at: u64,
+    pub from: usize,
+    pub to: usize,
+    pub msg: WireMessage,
+}
+
+struct Recorder {
+    started: Instant,
+    writer: Box<dyn Write + Send>,
+}
+
+/// Where the RecordingTransports of one run write their messages, in the order they were sent
+#[derive(Clone)]
+pub struct TraceRecorder {
+    recorder: Arc<Mutex<Recorder>>,
+}
+
+impl TraceRecorder {
+    /// Return a recorder writing to `writer`
+    pub fn new<W: Write + Send + 'static>(writer: W) -> TraceRecorder {
+        let recorder = Recorder {
+            started: Instant::now(),
+            writer: Box::new(writer),
+        };
+
+        TraceRecorder {
+            recorder: Arc::new(Mutex::new(recorder)),
+        }
+    }
+
+    /// Return a recorder writing to a new file at `path`
+    pub fn create<P: AsRef<Path>>(path: P) -> io::Result<TraceRecorder> {
+        Ok(TraceRecorder::new(BufWriter::new(File::create(path)?)))
+    }
+
+    /// Write out every event recorded so far
+    pub fn flush(&self) -> io::Result<()> {
+        self.recorder.lock().unwrap().writer.flush()
+    }
+}
+
+/// Records every message sent over an inner Transport
+///
+/// The recorder stays locked while the inner transport sends, so over the in-memory transport
+/// a trace holds messages in the order each Player received them
+pub struct RecordingTransport<T> {
+    id: usize,
+    inner: T,
+    recorder: TraceRecorder,
+}
+
+impl<T: Transport> RecordingTransport<T> {
+    /// Return a Transport sending as `id` over `inner` and recording into `recorder`
+    pub fn new(id: usize, inner: T, recorder: TraceRecorder) -> RecordingTransport<T> {
+        RecordingTransport {
+            id,
+            inner,
+            recorder,
+        }
+    }
+}
+
+impl<T: Transport> Transport for RecordingTransport<T> {
+    fn send(&self, to: usize, msg: WireMessage) -> io::Result<()> {
+        let mut recorder = self.recorder.recorder.lock().unwrap();
+        let event = TraceEvent {
+            at: recorder.started.elapsed().as_micros() as u64,
+            from: self.id,
+            to,
+            msg,
+        };
+        self.inner.send(to, event.msg.clone())?;
+
+        serde_json::to_writer(&mut recorder.writer, &event)?;
+        recorder.writer.write_all(b"\n")
+    }
+
+    fn peers(&self) -> Vec<usize> {
+        self.inner.peers()
+    }
+}
+
+/// Read the events of a trace written by a TraceRecorder
+pub fn read_trace<R: BufRead>(reader: R) -> io::Result<Vec<TraceEvent>> {
+    reader
+        .lines()
+        .filter(|line| !matches!(line, Ok(line) if line.trim().is_empty()))
+        .map(|line| Ok(serde_json::from_str(&line?)?))
+        .collect()
+}
+
+/// Read the events of the trace file at `path`
+pub fn load_trace<P: AsRef<Path>>(path: P) -> io::Result<Vec<TraceEvent>> {
+    read_trace(BufReader::new(File::open(path)?))
+}
+
+/// Stands in for the peers of a Player replaying a trace, dropping whatever it sends
+///
+/// It reports the same peers as in the recorded run, every id the trace delivered a message to,
+/// so thresholds that depend on the number of peers come out the same
+pub struct ReplayTransport {
+    peers: Vec<usize>,
+}
+
+impl ReplayTransport {
+    /// Return a Transport for `id` replaying `events`
+    pub fn new(id: usize, events: &[TraceEvent]) -> ReplayTransport {
+        let mut peers: Vec<usize> = events
+            .iter()
+            .map(|event| event.to)
+            .filter(|to| *to != id)
+            .collect();
+        peers.sort();
+        peers.dedup();
+
+        ReplayTransport { peers }
+    }
+}
+
+impl Transport for ReplayTransport {
+    fn send(&self, _: usize, _: WireMessage) -> io::Result<()> {
+        Ok(())
+    }
+
+    fn peers(&self) -> Vec<usize> {
+        self.peers.clone()
+    }
+}
+
+/// Feeds the messages `id` received in a trace into its inbox in their recorded order,
+/// returns how many were fed
+///
+/// A Player verifying shares inline handles the same messages in the same order as in the
+/// recorded run, so a failure seen there can be stepped through again. Its own sends should go
+/// to a ReplayTransport so they do not disturb anyone
+pub fn replay(events: &[TraceEvent], id: usize, inbox: &Sender<RPC>) -> usize {
+    events
+        .iter()
+        .filter(|event| event.to == id)
+        .take_while(|event| inbox.send(RPC::Wire(event.from, event.msg.clone())).is_ok())
+        .count()
+}
+
+/// The messages of one session at one epoch, with every share masked so the export gives
+/// nothing of the secret away and can be attached to a bug report
+///
+/// Every share is moved by a random polynomial over q whose commitments are folded into the
+/// dealing's: a masked share verifies against `commitments` exactly when the original verified
+/// against the dealing's, and masked shares reconstruct the secret plus the mask's random
+/// constant, so a replay fails as the recorded run did without revealing what it reconstructed.
+/// Messages of other protocols, such as refreshes and key generations, are left out along with
+/// share metadata.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TraceExport {
+    pub version: u32,
+    pub session: u64,
+    pub epoch: u64,
+    /// The dealing's commitments with the mask's folded in
+    #[serde(with = "codec::biguints")]
+    pub commitments: Vec<BigUint>,
+    /// Messages of the session and messages of no session, in their recorded order
+    pub events: Vec<TraceEvent>,
+}
+
+/// A random polynomial over q hiding the shares of one dealing
+struct Mask {
+    params: Arc<PublicParams>,
+    coefficients: Vec<BigUint>,
+}
+
+impl Mask {
+    fn new<R: RngCore + CryptoRng>(params: &PublicParams, rng: &mut R) -> Mask {
+        let coefficients: Vec<BigUint> = (0..params.t)
+            .map(|_| rng.gen_biguint_below(&params.q))
+            .collect();
+        let commitments = vss::generate_commitments(&coefficients, &params.g, &params.p)
+            .iter()
+            .zip(&params.commitments)
+            .map(|(mask, c)| mask * c % &params.p)
+            .collect();
+
+        Mask {
+            params: Arc::new(PublicParams {
+                g: params.g.clone(),
+                commitments,
+                p: params.p.clone(),
+                q: params.q.clone(),
+                t: params.t,
+            }),
+            coefficients,
+        }
+    }
+
+    fn share(&self, share: &Share) -> Share {
+        let q = &self.params.q;
+        let mask = self
+            .coefficients
+            .iter()
+            .rev()
+            .fold(BigUint::from(0_u32), |acc, a| (acc * share.index() + a) % q);
+
+        Share::new(share.index().clone(), (share.value() + mask) % q)
+    }
+
+    fn share_info(&self, info: &ShareInfo) -> ShareInfo {
+        ShareInfo::with_params(self.share(info.share()), self.params.clone())
+            .with_metadata(Metadata::default())
+            .with_validity(info.validity())
+    }
+
+    /// `payload` with its shares masked, None if it is left out of exports
+    fn payload(&self, payload: &Payload) -> Option<Payload> {
+        let masked = match payload {
+            Payload::RegShare(info) => Payload::RegShare(self.share_info(info)),
+            Payload::ReconstructShare(share) => Payload::ReconstructShare(self.share(share)),
+            Payload::Complaint {
+                accuser,
+                against,
+                evidence,
+            } => Payload::Complaint {
+                accuser: *accuser,
+                against: *against,
+                evidence: self.share(evidence),
+            },
+            Payload::ComplaintResponse(share) => Payload::ComplaintResponse(self.share(share)),
+            Payload::Sequenced(n, payload) => {
+                Payload::Sequenced(*n, Box::new(self.payload(payload)?))
+            }
+            Payload::Broadcast(broadcast) => Payload::Broadcast(self.broadcast(broadcast)?),
+            Payload::Batch(msgs) => Payload::Batch(
+                msgs.iter()
+                    .filter_map(|msg| self.message(msg, msg.session_id, msg.epoch))
+                    .collect(),
+            ),
+            Payload::RefreshDeal { .. }
+            | Payload::ReshareSubShare { .. }
+            | Payload::RecoveryPiece { .. }
+            | Payload::RecoveryShare(_)
+            | Payload::Dkg(_)
+            | Payload::DkgRequest(_)
+            | Payload::NonceCommitment { .. }
+            | Payload::PartialSignature { .. } => return None,
+            payload => payload.clone(),
+        };
+
+        Some(masked)
+    }
+
+    fn broadcast(&self, broadcast: &BroadcastMessage) -> Option<BroadcastMessage> {
+        Some(match broadcast {
+            BroadcastMessage::Request { seq, payload } => BroadcastMessage::Request {
+                seq: *seq,
+                payload: Box::new(self.payload(payload)?),
+            },
+            BroadcastMessage::Echo { origin, payload } => BroadcastMessage::Echo {
+                origin: *origin,
+                payload: Box::new(self.payload(payload)?),
+            },
+            BroadcastMessage::Ready { origin, payload } => BroadcastMessage::Ready {
+                origin: *origin,
+                payload: Box::new(self.payload(payload)?),
+            },
+            ack => ack.clone(),
+        })
+    }
+
+    /// `msg` masked if it belongs to `session` at `epoch` or to no session
+    fn message(&self, msg: &WireMessage, session: u64, epoch: u64) -> Option<WireMessage> {
+        if msg.session_id == NO_SESSION || (msg.session_id, msg.epoch) == (session, epoch) {
+            Some(WireMessage {
+                payload: self.payload(&msg.payload)?,
+                ..msg.clone()
+            })
+        } else {
+            None
+        }
+    }
+}
+
+/// Export the messages of `session` at `epoch` out of `events`, masking the shares of its
+/// dealing, whose public parameters are `params`
+pub fn export(
+    events: &[TraceEvent],
+    session: u64,
+    epoch: u64,
+    params: &PublicParams,
+) -> TraceExport {
+    export_with_rng(events, session, epoch, params, &mut rand::thread_rng())
+}
+
+/// Like `export` with the mask drawn from `rng`
+pub fn export_with_rng<R: RngCore + CryptoRng>(
+    events: &[TraceEvent],
+    session: u64,
+    epoch: u64,
+    params: &PublicParams,
+    rng: &mut R,
+) -> TraceExport {
+    let mask = Mask::new(params, rng);
+    let events = events
+        .iter()
+        .filter_map(|event| {
+            Some(TraceEvent {
+                msg: mask.message(&event.msg, session, epoch)?,
+                ..event.clone()
+            })
+        })
+        .collect();
+
+    TraceExport {
+        version: EXPORT_VERSION,
+        session,
+        epoch,
+        commitments: mask.params.commitments.clone(),
+        events,
+    }
+}
+
+/// Write `export` as JSON
+pub fn write_export<W: Write>(w: W, export: &TraceExport) -> io::Result<()> {
+    let mut w = w;
+    serde_json::to_writer_pretty(&mut w, export)?;
+
+    w.write_all(b"\n")
+}
+
+/// The export written by `write_export`, refusing versions newer than EXPORT_VERSION
+pub fn read_export<R: Read>(r: R) -> io::Result<TraceExport> {
+    let export: TraceExport = serde_json::from_reader(r)?;
+    if export.version > EXPORT_VERSION {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("unsupported trace export version {}", export.version),
+        ));
+    }
+
+    Ok(export)
+}
+
+/// Replay the reconstruction of player `id` in `export` in the calling thread, returning its
+/// report, or None if the player could not reconstruct with the messages it got
+///
+/// The player is handed every message the export delivered to it in order, and asked to
+/// reconstruct where it asked its peers for their shares in the recorded run, or after the last
+/// message if it never did. Nothing depends on timing, so the same export replays the same way
+/// every time.
+pub fn replay_reconstruction(export: &TraceExport, id: usize) -> Option<ReconstructionReport> {
+    let transport = ReplayTransport::new(id, &export.events);
+    // a reconstruction draws nothing, the seed only keeps the replay free of entropy
+    let rng = StdRng::seed_from_u64(0);
+    let (mut player, _inbox) = Player::build(id, Box::new(transport), 0, rng);
+    let mut router = Router::new();
+    let mut asked = false;
+
+    for event in &export.events {
+        let asks = event.from == id
+            && event.msg.session_id == export.session
+            && matches!(event.msg.payload, Payload::ShareRequest);
+        if asks && !asked {
+            asked = true;
+            player.handle(RPC::Reconstruct(export.session, router.request()));
+        }
+        if event.to == id {
+            player.handle(RPC::Wire(event.from, event.msg.clone()));
+        }
+    }
+    if !asked {
+        player.handle(RPC::Reconstruct(export.session, router.request()));
+    }
+
+    router
+        .recv_timeout(Duration::ZERO)
+        .ok()
+        .map(|(_, report)| report)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::{self, Write};
+    use std::sync::{Arc, Mutex};
+    use std::thread;
+    use std::time::Duration;
+
+    use num_bigint::BigUint;
+
+    use crate::broadcast::BroadcastMessage;
+    use crate::dealer::{Dealer, DEALER_ID};
+    use crate::player::Player;
+    use crate::rpc::{Payload, RejectReason, Router, Share, RPC};
+    use crate::transport::ChannelNetwork;
+
+    use super::{
+        export, read_export, read_trace, replay, replay_reconstruction, write_export,
+        RecordingTransport, ReplayTransport, TraceEvent, TraceRecorder,
+    };
+
+    /// A writer whose bytes can be read back once the run is over
+    #[derive(Clone, Default)]
+    struct Buffer(Arc<Mutex<Vec<u8>>>);
+
+    impl Write for Buffer {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.0.lock().unwrap().write(buf)
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    /// Deal 1234 to `n` players with threshold 3 and have all of them reconstruct it, returning
+    /// the dealer and the trace of the run
+    fn record(n: usize) -> (Dealer, Vec<TraceEvent>) {
+        let buffer = Buffer::default();
+        let recorder = TraceRecorder::new(buffer.clone());
+        let network = ChannelNetwork::new();
+        let senders: Vec<_> = (1..=n)
+            .map(|id| {
+                let transport =
+                    RecordingTransport::new(id, network.transport(id), recorder.clone());
+                let (sender, _) = Player::spawn(id, transport);
+                network.register(id, sender.clone());
+
+                sender
+            })
+            .collect();
+
+        let dealer = Dealer::new(n, 3, 1234).unwrap();
+        dealer.propagate(&RecordingTransport::new(
+            DEALER_ID,
+            network.transport(DEALER_ID),
+            recorder.clone(),
+        ));
+        thread::sleep(Duration::from_millis(100));
+        let mut router = Router::new();
+        senders.iter().for_each(|s| {
+            s.send(RPC::Reconstruct(dealer.session_id, router.request()))
+                .unwrap();
+        });
+        router.recv_timeout(Duration::from_secs(5)).unwrap();
+        thread::sleep(Duration::from_millis(100));
+
+        let events = read_trace(&buffer.0.lock().unwrap()[..]).unwrap();
+
+        (dealer, events)
+    }
+
+    #[test]
+    fn replayed_trace_reconstructs_the_same_secret() {
+        let (dealer, events) = record(5);
+        assert!(events.windows(2).all(|pair| pair[0].at <= pair[1].at));
+
+        // player 1 alone, its peers only heard through the trace
+        let (sender, _) = Player::spawn(1, ReplayTransport::new(1, &events));
+        assert!(replay(&events, 1, &sender) > 0);
+        let mut router = Router::new();
+        sender
+            .send(RPC::Reconstruct(dealer.session_id, router.request()))
+            .unwrap();
+        let (_, report) = router.recv_timeout(Duration::from_secs(5)).unwrap();
+        assert_eq!(BigUint::from(1234_u32), *report.secret.expose());
+    }
+
+    #[test]
+    fn exported_reconstruction_replays_its_failure_without_the_secret() {
+        let (dealer, events) = record(5);
+        let params = dealer.params();
+        let mut exported = export(&events, dealer.session_id, dealer.epoch, &params);
+        assert_ne!(params.commitments, exported.commitments);
+
+        // a peer whose share player 1 interpolates broadcast a wrong one instead, every
+        // retransmission of it carrying the same one
+        let report = replay_reconstruction(&exported, 1).unwrap();
+        let bad = *report.contributors.iter().find(|&&id| id != 1).unwrap();
+        for event in &mut exported.events {
+            if let Payload::Broadcast(BroadcastMessage::Request { payload, .. }) =
+                &mut event.msg.payload
+            {
+                if let (true, Payload::ReconstructShare(share)) =
+                    (event.from == bad, payload.as_mut())
+                {
+                    *share = Share::new(share.index().clone(), share.value() + 1_u32);
+                }
+            }
+        }
+
+        let mut file = vec![];
+        write_export(&mut file, &exported).unwrap();
+        let exported = read_export(&file[..]).unwrap();
+        for event in &exported.events {
+            if let Payload::RegShare(info) = &event.msg.payload {
+                assert!(info.verify());
+                assert!(!dealer.shares.contains(info.share()));
+            }
+        }
+
+        let report = replay_reconstruction(&exported, 1).unwrap();
+        assert_eq!(vec![(bad, RejectReason::InvalidShare)], report.rejected);
+        assert!(report.verified);
+        assert_ne!(BigUint::from(1234_u32), *report.secret.expose());
+        // nothing depends on timing
+        assert_eq!(report, replay_reconstruction(&exported, 1).unwrap());
+    }
+}
@@ -0,0 +1,334 @@
+use std::collections::HashMap;
+use std::io;
+use std::net::{SocketAddr, UdpSocket};
+use std::sync::mpsc::Sender;
+use std::sync::{Arc, Mutex, Weak};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use serde::{Deserialize, Serialize};
+use tracing::{debug, warn};
+
+use crate::codec;
+use crate::rpc::{WireMessage, RPC};
+use crate::transport::{Received, Transport};
+
+/// Largest payload of a UDP datagram over IPv4
+const MAX_DATAGRAM_LEN: usize = 65_507;
+
+/// Wait for an ack before retransmitting a datagram
+const RETRANSMIT_TIMEOUT: Duration = Duration::from_millis(100);
+
+/// Transmissions of a datagram before giving up on it
+const MAX_TRANSMISSIONS: u32 = 10;
+
+/// How often the background threads check whether the transport was dropped
+const POLL_INTERVAL: Duration = Duration::from_millis(20);
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+enum Datagram {
+    Data {
+        from: usize,
+        seq: u64,
+        msg: WireMessage,
+    },
+    Ack {
+        from: usize,
+        seq: u64,
+    },
+}
+
+struct Unacked {
+    to: SocketAddr,
+    datagram: Vec<u8>,
+    sent_at: Instant,
+    transmissions: u32,
+}
+
+#[derive(Default)]
+struct State {
+    next_seq: HashMap<usize, u64>,
+    /// datagrams sent but not acknowledged yet, by peer id and sequence number
+    unacked: HashMap<(usize, u64), Unacked>,
+    received: HashMap<usize, Received>,
+    inbox: Option<Sender<RPC>>,
+}
+
+struct Shared {
+    id: usize,
+    socket: UdpSocket,
+    state: Mutex<State>,
+}
+
+/// Loss tolerant transport over UDP for small networks such as a lab cluster
+///
+/// Every message is sent as a single datagram of CBOR carrying the sender's id and a per peer
+/// sequence number. Receivers acknowledge every datagram and drop duplicates, while senders
+/// retransmit unacknowledged datagrams up to MAX_TRANSMISSIONS times. Messages may be
+/// delivered out of order
+pub struct UdpTransport {
+    addresses: HashMap<usize, SocketAddr>,
+    shared: Arc<Shared>,
+}
+
+/// Hands a UdpTransport's incoming messages to a Player
+pub struct UdpListener {
+    shared: Arc<Shared>,
+}
+
+impl UdpTransport {
+    /// Return a Transport sending as `id` from `socket` to the players listed in `addresses`
+    pub fn new(
+        id: usize,
+        socket: UdpSocket,
+        addresses: HashMap<usize, SocketAddr>,
+    ) -> io::Result<UdpTransport> {
+        socket.set_read_timeout(Some(POLL_INTERVAL))?;
+        let shared = Arc::new(Shared {
+            id,
+            socket,
+            state: Mutex::new(State::default()),
+        });
+
+        let weak = Arc::downgrade(&shared);
+        thread::spawn(move || receive(weak));
+        let weak = Arc::downgrade(&shared);
+        thread::spawn(move || retransmit(weak));
+
+        Ok(UdpTransport { addresses, shared })
+    }
+
+    /// Return a listener delivering incoming messages into a Player's inbox
+    pub fn listener(&self) -> UdpListener {
+        UdpListener {
+            shared: Arc::clone(&self.shared),
+        }
+    }
+}
+
+impl Transport for UdpTransport {
+    fn send(&self, to: usize, msg: WireMessage) -> io::Result<()> {
+        let addr = *self.addresses.get(&to).ok_or_else(|| {
+            io::Error::new(io::ErrorKind::NotFound, format!("unknown player {}", to))
+        })?;
+
+        let mut state = self.shared.state.lock().unwrap();
+        let seq = *state.next_seq.get(&to).unwrap_or(&0);
+        let datagram = codec::encode(&Datagram::Data {
+            from: self.shared.id,
+            seq,
+            msg,
+        })?;
+        if datagram.len() > MAX_DATAGRAM_LEN {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "message does not fit in a datagram",
+            ));
+        }
+
+        self.shared.socket.send_to(&datagram, addr)?;
+        state.next_seq.insert(to, seq + 1);
+        state.unacked.insert(
+            (to, seq),
+            Unacked {
+                to: addr,
+                datagram,
+                sent_at: Instant::now(),
+                transmissions: 1,
+            },
+        );
+
+        Ok(())
+    }
+
+    fn peers(&self) -> Vec<usize> {
+        let mut peers: Vec<usize> = self
+            .addresses
+            .keys()
+            .copied()
+            .filter(|id| *id != self.shared.id)
+            .collect();
+        peers.sort();
+
+        peers
+    }
+}
+
+impl UdpListener {
+    /// Deliver every message received into `inbox`
+    pub fn listen(self, inbox: Sender<RPC>) {
+        self.shared.state.lock().unwrap().inbox = Some(inbox);
+    }
+}
+
+/// Acknowledges and delivers incoming datagrams until the transport is dropped
+fn receive(shared: Weak<Shared>) {
+    let mut buf = vec![0; MAX_DATAGRAM_LEN];
+
+    while let Some(shared) = shared.upgrade() {
+        let (len, addr) = match shared.socket.recv_from(&mut buf) {
+            Ok(received) => received,
+            Err(err)
+                if err.kind() == io::ErrorKind::WouldBlock
+                    || err.kind() == io::ErrorKind::TimedOut =>
+            {
+                continue
+            }
+            Err(err) => {
+                warn!("error while receiving: {}", err);
+                continue;
+            }
+        };
+
+        match codec::decode(&buf[..len]) {
+            Ok(Datagram::Data { from, seq, msg }) => {
+                let ack = Datagram::Ack {
+                    from: shared.id,
+                    seq,
+                };
+                if let Err(err) = shared.socket.send_to(&codec::encode(&ack).unwrap(), addr) {
+                    debug!(from, seq, "error while acknowledging: {}", err);
+                }
+
+                let mut state = shared.state.lock().unwrap();
+                if !state.received.entry(from).or_default().insert(seq) {
+                    debug!(from, seq, "dropping duplicate");
+                    continue;
+                }
+                match &state.inbox {
+                    Some(inbox) => {
+                        let _ = inbox.send(RPC::Wire(from, msg));
+                    }
+                    None => warn!(from, "dropping message, not listening"),
+                }
+            }
+            Ok(Datagram::Ack { from, seq }) => {
+                shared.state.lock().unwrap().unacked.remove(&(from, seq));
+            }
+            Err(err) => warn!(%addr, "dropping malformed datagram: {}", err),
+        }
+    }
+}
+
+/// Resends datagrams that were not acknowledged in time until the transport is dropped
+fn retransmit(shared: Weak<Shared>) {
+    loop {
+        thread::sleep(POLL_INTERVAL);
+        let shared = match shared.upgrade() {
+            Some(shared) => shared,
+            None => return,
+        };
+
+        let mut state = shared.state.lock().unwrap();
+        state.unacked.retain(|(to, seq), unacked| {
+            if unacked.sent_at.elapsed() < RETRANSMIT_TIMEOUT {
+                return true;
+            }
+            if unacked.transmissions >= MAX_TRANSMISSIONS {
+                warn!(to, seq, "giving up on unacknowledged message");
+                return false;
+            }
+
+            debug!(to, seq, "retransmitting");
+            let _ = shared.socket.send_to(&unacked.datagram, unacked.to);
+            unacked.sent_at = Instant::now();
+            unacked.transmissions += 1;
+
+            true
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+    use std::net::UdpSocket;
+    use std::thread;
+    use std::time::Duration;
+
+    use num_bigint::BigUint;
+
+    use crate::codec;
+    use crate::dealer::{Dealer, DEALER_ID};
+    use crate::player::Player;
+    use crate::rpc::{Payload, Router, WireMessage, NO_SESSION, RPC};
+    use crate::transport::Transport;
+
+    use super::{Datagram, UdpTransport};
+
+    #[test]
+    fn retransmits_until_acknowledged() {
+        let peer = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let addresses = HashMap::from([(1, peer.local_addr().unwrap())]);
+        let transport =
+            UdpTransport::new(2, UdpSocket::bind("127.0.0.1:0").unwrap(), addresses).unwrap();
+        let ping = WireMessage::new(NO_SESSION, 0, Payload::Ping);
+        transport.send(1, ping.clone()).unwrap();
+
+        // drop the first transmission, acknowledge the second
+        let mut buf = vec![0; 1024];
+        let mut received = vec![];
+        for _ in 0..2 {
+            let (len, from) = peer.recv_from(&mut buf).unwrap();
+            received.push(codec::decode::<Datagram>(&buf[..len]).unwrap());
+
+            if received.len() == 2 {
+                let ack = codec::encode(&Datagram::Ack { from: 1, seq: 0 }).unwrap();
+                peer.send_to(&ack, from).unwrap();
+            }
+        }
+
+        let data = Datagram::Data {
+            from: 2,
+            seq: 0,
+            msg: ping,
+        };
+        assert_eq!(vec![data.clone(), data], received);
+
+        peer.set_read_timeout(Some(Duration::from_millis(300)))
+            .unwrap();
+        assert!(peer.recv_from(&mut buf).is_err(), "retransmitted after ack");
+    }
+
+    #[test]
+    fn udp_reconstruct() {
+        let n = 5;
+        let sockets: Vec<_> = (1..=n)
+            .map(|_| UdpSocket::bind("127.0.0.1:0").unwrap())
+            .collect();
+        let addresses: HashMap<_, _> = sockets
+            .iter()
+            .enumerate()
+            .map(|(i, s)| (i + 1, s.local_addr().unwrap()))
+            .collect();
+
+        let senders: Vec<_> = sockets
+            .into_iter()
+            .enumerate()
+            .map(|(i, socket)| {
+                let id = i + 1;
+                let transport = UdpTransport::new(id, socket, addresses.clone()).unwrap();
+                let listener = transport.listener();
+                let (sender, _) = Player::spawn(id, transport);
+                listener.listen(sender.clone());
+
+                sender
+            })
+            .collect();
+
+        let dealer = Dealer::new(n, 3, 1234).unwrap();
+        let socket = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let dealer_transport = UdpTransport::new(DEALER_ID, socket, addresses).unwrap();
+        dealer.propagate(&dealer_transport);
+        thread::sleep(Duration::from_millis(200));
+
+        let mut router = Router::new();
+        senders.iter().for_each(|s| {
+            s.send(RPC::Reconstruct(dealer.session_id, router.request()))
+                .unwrap();
+        });
+
+        let (_, report) = router.recv_timeout(Duration::from_secs(5)).unwrap();
+        assert_eq!(BigUint::from(1234_u32), *report.secret.expose());
+    }
+}
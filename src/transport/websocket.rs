@@ -0,0 +1,290 @@
+use std::collections::hash_map::Entry;
+use std::collections::HashMap;
+use std::io;
+use std::net::{SocketAddr, TcpListener, TcpStream};
+use std::sync::mpsc::Sender;
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+
+use tracing::{debug, warn};
+use tungstenite::protocol::Role;
+use tungstenite::{Message, WebSocket};
+
+use crate::codec::{self, MAX_FRAME_LEN};
+use crate::rpc::{WireMessage, RPC};
+use crate::transport::Transport;
+
+/// Connections and inbox shared between a WebSocketTransport and its listener
+#[derive(Default)]
+struct Shared {
+    /// writing half of the connection to each peer, dialed or accepted
+    connections: Mutex<HashMap<usize, WebSocket<TcpStream>>>,
+    inbox: Mutex<Option<Sender<RPC>>>,
+}
+
+/// Transport between processes over WebSockets
+///
+/// Lets a player that cannot listen for connections, such as one running in a browser, take
+/// part by dialing the others: messages to a peer go over whichever connection exists between
+/// the two, dialed or accepted. A connection starts with a binary message holding the dialer's
+/// id as a big endian u64, followed by a binary message of CBOR per WireMessage
+pub struct WebSocketTransport {
+    id: usize,
+    addresses: HashMap<usize, SocketAddr>,
+    shared: Arc<Shared>,
+}
+
+/// Accepts the connections peers make to a WebSocketTransport
+pub struct WebSocketListener {
+    shared: Arc<Shared>,
+}
+
+impl WebSocketTransport {
+    /// Return a Transport sending as `id` to the players listed in `addresses`, and to any
+    /// player that connects to it
+    pub fn new(id: usize, addresses: HashMap<usize, SocketAddr>) -> WebSocketTransport {
+        WebSocketTransport {
+            id,
+            addresses,
+            shared: Arc::new(Shared::default()),
+        }
+    }
+
+    /// Return a listener delivering the messages of every connection into a Player's inbox
+    pub fn listener(&self) -> WebSocketListener {
+        WebSocketListener {
+            shared: Arc::clone(&self.shared),
+        }
+    }
+
+    fn connect(&self, to: usize) -> io::Result<WebSocket<TcpStream>> {
+        let addr = self.addresses.get(&to).ok_or_else(|| {
+            io::Error::new(io::ErrorKind::NotFound, format!("unknown player {}", to))
+        })?;
+
+        let stream = TcpStream::connect(addr)?;
+        let writer = stream.try_clone()?;
+        let (mut reader, _) = tungstenite::client(format!("ws://{}/", addr), stream)
+            .map_err(|err| io::Error::new(io::ErrorKind::ConnectionRefused, err.to_string()))?;
+        reader
+            .send(Message::Binary((self.id as u64).to_be_bytes().to_vec()))
+            .map_err(io::Error::other)?;
+
+        // replies may come back over the connection we dialed
+        let inbox = self.shared.inbox.lock().unwrap().clone();
+        if let Some(inbox) = inbox {
+            thread::spawn(move || {
+                if let Err(err) = receive(&mut reader, to, &inbox) {
+                    debug!(to, "connection closed: {}", err);
+                }
+            });
+        }
+
+        Ok(WebSocket::from_raw_socket(writer, Role::Client, None))
+    }
+}
+
+impl Transport for WebSocketTransport {
+    fn send(&self, to: usize, msg: WireMessage) -> io::Result<()> {
+        let frame = codec::encode(&msg)?;
+
+        let mut connections = self.shared.connections.lock().unwrap();
+        let ws = match connections.entry(to) {
+            Entry::Occupied(entry) => entry.into_mut(),
+            Entry::Vacant(entry) => entry.insert(self.connect(to)?),
+        };
+
+        let result = ws.send(Message::Binary(frame)).map_err(io::Error::other);
+        if result.is_err() {
+            // redial, or wait for the peer to reconnect, on the next send
+            connections.remove(&to);
+        }
+
+        result
+    }
+
+    fn peers(&self) -> Vec<usize> {
+        let connections = self.shared.connections.lock().unwrap();
+        let mut peers: Vec<usize> = self
+            .addresses
+            .keys()
+            .chain(connections.keys())
+            .copied()
+            .filter(|id| *id != self.id)
+            .collect();
+        peers.sort();
+        peers.dedup();
+
+        peers
+    }
+}
+
+impl WebSocketListener {
+    /// Deliver the messages peers send back over connections we dialed into `inbox`, without
+    /// accepting any connections, as a player that cannot listen would
+    pub fn receive(self, inbox: Sender<RPC>) {
+        *self.shared.inbox.lock().unwrap() = Some(inbox);
+    }
+
+    /// Accept connections on `listener` and deliver every message received into `inbox`
+    pub fn listen(self, listener: TcpListener, inbox: Sender<RPC>) -> JoinHandle<()> {
+        *self.shared.inbox.lock().unwrap() = Some(inbox.clone());
+
+        thread::spawn(move || {
+            for stream in listener.incoming() {
+                let shared = Arc::clone(&self.shared);
+                let inbox = inbox.clone();
+                match stream {
+                    Ok(stream) => {
+                        thread::spawn(move || {
+                            if let Err(err) = accept(stream, &shared, &inbox) {
+                                debug!("connection closed: {}", err);
+                            }
+                        });
+                    }
+                    Err(err) => warn!("error while accepting connection: {}", err),
+                }
+            }
+        })
+    }
+}
+
+fn accept(stream: TcpStream, shared: &Shared, inbox: &Sender<RPC>) -> io::Result<()> {
+    let writer = stream.try_clone()?;
+    let mut reader = tungstenite::accept(stream)
+        .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err.to_string()))?;
+
+    let from = match reader.read().map_err(io::Error::other)? {
+        Message::Binary(preamble) if preamble.len() == 8 => {
+            u64::from_be_bytes(preamble.try_into().unwrap()) as usize
+        }
+        _ => {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "expected the peer's id",
+            ))
+        }
+    };
+
+    // answer over this connection unless we already reach the peer over our own
+    shared
+        .connections
+        .lock()
+        .unwrap()
+        .entry(from)
+        .or_insert_with(|| WebSocket::from_raw_socket(writer, Role::Server, None));
+
+    receive(&mut reader, from, inbox)
+}
+
+fn receive(reader: &mut WebSocket<TcpStream>, from: usize, inbox: &Sender<RPC>) -> io::Result<()> {
+    loop {
+        let frame = match reader.read().map_err(io::Error::other)? {
+            Message::Binary(frame) => frame,
+            Message::Close(_) => return Ok(()),
+            _ => continue,
+        };
+        if frame.len() > MAX_FRAME_LEN {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "frame too large",
+            ));
+        }
+
+        let msg: WireMessage = codec::decode(&frame)?;
+        if inbox.send(RPC::Wire(from, msg)).is_err() {
+            // the player has stopped
+            return Ok(());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+    use std::net::TcpListener;
+    use std::sync::mpsc;
+    use std::thread;
+    use std::time::Duration;
+
+    use num_bigint::BigUint;
+
+    use crate::dealer::{Dealer, DEALER_ID};
+    use crate::player::Player;
+    use crate::rpc::{Payload, Router, WireMessage, NO_SESSION, RPC};
+    use crate::transport::Transport;
+
+    use super::WebSocketTransport;
+
+    fn ping() -> WireMessage {
+        WireMessage::new(NO_SESSION, 0, Payload::Ping)
+    }
+
+    #[test]
+    fn replies_over_accepted_connection() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addresses = HashMap::from([(1, listener.local_addr().unwrap())]);
+
+        let native = WebSocketTransport::new(1, HashMap::new());
+        let (native_inbox, native_rx) = mpsc::channel();
+        native.listener().listen(listener, native_inbox);
+
+        // the browser can only dial
+        let browser = WebSocketTransport::new(2, addresses);
+        let (browser_inbox, browser_rx) = mpsc::channel();
+        browser.listener().receive(browser_inbox);
+
+        browser.send(1, ping()).unwrap();
+        match native_rx.recv_timeout(Duration::from_secs(5)).unwrap() {
+            RPC::Wire(from, msg) => assert_eq!((2, ping()), (from, msg)),
+            rpc => panic!("unexpected {:?}", rpc),
+        }
+
+        assert_eq!(vec![2], native.peers());
+        native.send(2, ping()).unwrap();
+        match browser_rx.recv_timeout(Duration::from_secs(5)).unwrap() {
+            RPC::Wire(from, msg) => assert_eq!((1, ping()), (from, msg)),
+            rpc => panic!("unexpected {:?}", rpc),
+        }
+    }
+
+    #[test]
+    fn websocket_reconstruct() {
+        let n = 5;
+        let listeners: Vec<_> = (1..=n)
+            .map(|_| TcpListener::bind("127.0.0.1:0").unwrap())
+            .collect();
+        let addresses: HashMap<_, _> = listeners
+            .iter()
+            .enumerate()
+            .map(|(i, l)| (i + 1, l.local_addr().unwrap()))
+            .collect();
+
+        let senders: Vec<_> = listeners
+            .into_iter()
+            .enumerate()
+            .map(|(i, listener)| {
+                let id = i + 1;
+                let transport = WebSocketTransport::new(id, addresses.clone());
+                let ws_listener = transport.listener();
+                let (sender, _) = Player::spawn(id, transport);
+                ws_listener.listen(listener, sender.clone());
+
+                sender
+            })
+            .collect();
+
+        let dealer = Dealer::new(n, 3, 1234).unwrap();
+        dealer.propagate(&WebSocketTransport::new(DEALER_ID, addresses));
+        thread::sleep(Duration::from_millis(200));
+
+        let mut router = Router::new();
+        senders.iter().for_each(|s| {
+            s.send(RPC::Reconstruct(dealer.session_id, router.request()))
+                .unwrap();
+        });
+
+        let (_, report) = router.recv_timeout(Duration::from_secs(5)).unwrap();
+        assert_eq!(BigUint::from(1234_u32), *report.secret.expose());
+    }
+}
@@ -0,0 +1,164 @@
+//! Unseal key shares as HashiCorp Vault lays them out, to reconstruct or check a Vault's keys
+//! and to make look-alike ones for unseal drills
+//!
+//! Every byte of the secret is shared over GF(256) with the AES polynomial, and a share is the
+//! bytes of its value followed by its x coordinate, drawn at random without repeats. The shares
+//! carry no commitments, deal the secret they recover again for a verifiable sharing.
+
+use std::io;
+
+use rand::{CryptoRng, Rng, RngCore};
+
+use crate::slip39::interpolate;
+
+/// Most shares a secret can be split into, one per nonzero x coordinate
+const MAX_SHARES: usize = 255;
+
+fn invalid_input(msg: impl Into<String>) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidInput, msg.into())
+}
+
+fn invalid_data(msg: impl Into<String>) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg.into())
+}
+
+/// Split `secret` into `parts` shares as Vault does, any `threshold` of which recover it
+pub fn split(secret: &[u8], parts: usize, threshold: usize) -> io::Result<Vec<Vec<u8>>> {
+    split_with_rng(secret, parts, threshold, &mut rand::thread_rng())
+}
+
+/// Like `split` with the x coordinates and polynomial drawn from `rng`, so a seeded rng writes
+/// the same shares every time
+pub fn split_with_rng<R: RngCore + CryptoRng>(
+    secret: &[u8],
+    parts: usize,
+    threshold: usize,
+    rng: &mut R,
+) -> io::Result<Vec<Vec<u8>>> {
+    if secret.is_empty() {
+        return Err(invalid_input("cannot split an empty secret"));
+    }
+    if parts > MAX_SHARES || threshold < 2 || threshold > parts {
+        return Err(invalid_input(format!(
+            "the threshold must be between 2 and the parts, at most {}",
+            MAX_SHARES
+        )));
+    }
+
+    let mut xs: Vec<u8> = (1..=u8::MAX).collect();
+    rng.shuffle(&mut xs);
+
+    // the secret at zero and random values at the first threshold - 1 coordinates fix a
+    // random polynomial of degree threshold - 1
+    let mut base = vec![(0, secret.to_vec())];
+    for &x in &xs[..threshold - 1] {
+        let mut value = vec![0; secret.len()];
+        rng.fill(&mut value[..]);
+        base.push((x, value));
+    }
+
+    Ok(xs[..parts]
+        .iter()
+        .map(|&x| [interpolate(&base, x), vec![x]].concat())
+        .collect())
+}
+
+/// The x coordinate and value of every share, checked to be of one secret's length and
+/// without repeats
+fn points<B: AsRef<[u8]>>(shares: &[B]) -> io::Result<Vec<(u8, Vec<u8>)>> {
+    let mut points: Vec<(u8, Vec<u8>)> = vec![];
+    for share in shares {
+        let (&x, value) = match share.as_ref().split_last() {
+            Some((x, value)) if !value.is_empty() && *x != 0 => (x, value),
+            _ => return Err(invalid_data("a share is too short or at x = 0")),
+        };
+        if points.iter().any(|(other, _)| *other == x) {
+            return Err(invalid_data(format!("two shares at x = {}", x)));
+        }
+        if points
+            .first()
+            .is_some_and(|(_, first)| first.len() != value.len())
+        {
+            return Err(invalid_data("shares of different lengths"));
+        }
+        points.push((x, value.to_vec()));
+    }
+
+    Ok(points)
+}
+
+/// Recover the secret of `shares`, all of them used as Vault does
+///
+/// Too few shares are not detected, they give a different secret
+pub fn combine<B: AsRef<[u8]>>(shares: &[B]) -> io::Result<Vec<u8>> {
+    if shares.len() < 2 {
+        return Err(invalid_input("at least 2 shares are needed"));
+    }
+
+    Ok(interpolate(&points(shares)?, 0))
+}
+
+/// Check that `shares` are of one secret split with `threshold`, every share past the first
+/// `threshold` lying on their polynomial; the error names the first share that does not
+pub fn check<B: AsRef<[u8]>>(shares: &[B], threshold: usize) -> io::Result<()> {
+    if threshold < 2 || shares.len() < threshold {
+        return Err(invalid_input(format!(
+            "{} shares given, the threshold of at least 2 needs {}",
+            shares.len(),
+            threshold.max(2)
+        )));
+    }
+
+    let points = points(shares)?;
+    let (base, rest) = points.split_at(threshold);
+    for (i, (x, value)) in rest.iter().enumerate() {
+        if interpolate(base, *x) != *value {
+            return Err(invalid_data(format!(
+                "share {} does not lie on the polynomial of the first {}",
+                threshold + i + 1,
+                threshold
+            )));
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    use super::{check, combine, split, split_with_rng};
+
+    #[test]
+    fn shares_roundtrip_and_check() {
+        let secret = b"an unseal key of 32 bytes, or so";
+        let shares = split(secret, 5, 3).unwrap();
+        assert!(shares.iter().all(|share| share.len() == secret.len() + 1));
+
+        assert_eq!(secret.to_vec(), combine(&shares[2..]).unwrap());
+        assert_ne!(secret.to_vec(), combine(&shares[..2]).unwrap());
+        assert!(check(&shares, 3).is_ok());
+
+        let mut tampered = shares.clone();
+        tampered[4][0] ^= 1;
+        let err = check(&tampered, 3).unwrap_err();
+        assert!(err.to_string().starts_with("share 5"));
+    }
+
+    #[test]
+    fn seeded_rng_splits_the_same_shares() {
+        let split = |seed| split_with_rng(b"key", 5, 3, &mut StdRng::seed_from_u64(seed)).unwrap();
+
+        assert_eq!(split(7), split(7));
+        assert_ne!(split(7), split(8));
+    }
+
+    #[test]
+    fn known_share_recovers() {
+        // a 2 of n split of 0x2a over y = 0x2a + 0x07 x, at x = 1 and x = 2
+        let shares = [[0x2a ^ 0x07, 1], [0x2a ^ 0x0e, 2]];
+        assert_eq!(vec![0x2a], combine(&shares).unwrap());
+    }
+}
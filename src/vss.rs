@@ -1,31 +1,229 @@
-use num::Zero;
-use num_bigint::{BigInt, BigUint, ToBigInt, ToBigUint};
+use std::collections::{HashMap, HashSet};
+use std::thread;
+
+use num::{One, Zero};
+use num_bigint::{BigInt, BigUint, ToBigInt};
+use subtle::{ConditionallySelectable, ConstantTimeEq};
+use zeroize::Zeroizing;
+
+use crate::error::VssError;
+use crate::primes;
+use crate::rpc::Share;
+use crate::secret::{self, Secret};
 
 /// Given a polynomial constants a_0,a_1,...a_k, construct a polynomial P over prime field q
 /// and evaluate n unique shares
 ///
 /// Shares are in the form (1, P(1)),(2, P(2)),...(n, P(n))
-pub fn generate_shares(a: &Vec<BigUint>, n: usize, q: &BigUint) -> Vec<(BigUint, BigUint)> {
-    // for i = 1..=n, P(i) % q
-    (1..=n)
-        .into_iter()
-        .map(|i| (i.to_biguint().unwrap(), eval_poly_at(a, i) % q))
-        .collect()
+pub fn generate_shares(a: &[BigUint], n: usize, q: &BigUint) -> Vec<Share> {
+    generate_shares_iter(a, q).take(n).collect()
+}
+
+/// Lazily evaluates the shares (1, P(1)),(2, P(2)),... of the polynomial with constants a over
+/// prime field q
+///
+/// Nothing is evaluated before a share is asked for and the iterator never ends, take as many
+/// shares as there are players. A dealer of a huge n can send each share as it comes instead
+/// of holding them all
+pub fn generate_shares_iter<'a>(
+    a: &'a [BigUint],
+    q: &'a BigUint,
+) -> impl Iterator<Item = Share> + 'a {
+    // for i = 1.., P(i) % q
+    (1_u64..).map(move |i| {
+        let x = BigUint::from(i);
+        let y = a
+            .iter()
+            .rev()
+            .fold(BigUint::zero(), |y, a_i| (y * &x + a_i) % q);
+
+        Share::new(x, y)
+    })
 }
 
 /// Verify a particular share: (i, s) given generator g, commitments c, and p
 ///
 /// Verifies that g^s is congruent to product of c_0,c_1^(i^1),c_2^(i^2),...,c_n^(i^n) mod p
-pub fn verify_share(i: &BigUint, s: &BigUint, g: &BigUint, c: &Vec<BigUint>, p: &BigUint) -> bool {
-    let share_check = g.modpow(s, p);
-    let mut check = 1.to_biguint().unwrap();
+///
+/// Parameters from a peer may be anything, a p of zero fails rather than dividing by zero. g^s
+/// is compared in constant time
+pub fn verify_share(i: &BigUint, s: &BigUint, g: &BigUint, c: &[BigUint], p: &BigUint) -> bool {
+    !p.is_zero() && secret::ct_eq(&g.modpow(s, p), &commitment_at(i, c, p))
+}
 
-    for (j, c_i) in c.iter().enumerate() {
-        let exp = num::pow(i.clone(), j);
-        check = (check * c_i.modpow(&exp, p)) % p;
+/// Evaluates commitments c at i, which is g^P(i) mod p, without knowing the polynomial P
+///
+/// Computes the product of c_0,c_1^(i^1),c_2^(i^2),...,c_n^(i^n) mod p
+pub fn commitment_at(i: &BigUint, c: &[BigUint], p: &BigUint) -> BigUint {
+    evaluate(c, &powers(i, c.len()), p)
+}
+
+/// i^0, i^1, ..., i^(n - 1)
+fn powers(i: &BigUint, n: usize) -> Vec<BigUint> {
+    let mut powers = Vec::with_capacity(n);
+    extend_powers(&mut powers, i, n);
+
+    powers
+}
+
+/// Extends the powers i^0, i^1, ... to at least n of them
+fn extend_powers(powers: &mut Vec<BigUint>, i: &BigUint, n: usize) {
+    while powers.len() < n {
+        let next = powers.last().map_or_else(BigUint::one, |last| last * i);
+        powers.push(next);
     }
+}
+
+/// Product of c_0^e_0,c_1^e_1,... mod p of the commitments c and the powers e of an index
+fn evaluate(c: &[BigUint], powers: &[BigUint], p: &BigUint) -> BigUint {
+    c.iter()
+        .zip(powers)
+        .fold(BigUint::one(), |check, (c_j, e_j)| {
+            check * c_j.modpow(e_j, p) % p
+        })
+}
 
-    share_check == check
+/// Bits of the exponent each table of `Verifier::with_windows` covers
+const WINDOW_BITS: usize = 4;
+
+/// Verifies shares against commitments over one group, keeping work that repeats between shares
+///
+/// Keeps the powers i^0, i^1, ... of every index of the dealing it verified a share at, which
+/// every set of commitments is evaluated at, and optionally tables of powers of g that raise g
+/// to a share without squaring. Pays off for a player verifying many shares at its own index,
+/// such as the deal of every dealer of a DKG
+#[derive(Debug, Clone)]
+pub struct Verifier {
+    g: BigUint,
+    p: BigUint,
+    /// g^((d + 1) * 2^(WINDOW_BITS * k)) mod p of every digit d, by window k, as big endian
+    /// bytes of the length of p
+    windows: Vec<Vec<Vec<u8>>>,
+    /// g^-(2^0 + 2^WINDOW_BITS + ...) mod p, which takes the one added to every digit back out
+    offset: BigUint,
+    /// Indices up to this one have their powers kept
+    n: usize,
+    /// i^0, i^1, ... of every index i seen up to n
+    powers: HashMap<BigUint, Vec<BigUint>>,
+}
+
+impl Verifier {
+    /// Return a verifier of shares under generator g mod p, keeping the powers of the indices
+    /// 1 to n of the players of a dealing
+    pub fn new(g: &BigUint, p: &BigUint, n: usize) -> Verifier {
+        Verifier {
+            g: g.clone(),
+            p: p.clone(),
+            windows: vec![],
+            offset: BigUint::one(),
+            n,
+            powers: HashMap::new(),
+        }
+    }
+
+    /// Precompute g to every digit of exponents of up to `bits` bits, the bits of q for shares
+    ///
+    /// Costs about as much as raising g to three shares, each share verified after that skips
+    /// every squaring. Shares of more bits fall back to modpow. p must be a prime
+    pub fn with_windows(mut self, bits: usize) -> Verifier {
+        let p = &self.p;
+        if *p <= BigUint::from(2_u32) || (&self.g % p).is_zero() {
+            return self;
+        }
+        let mut base = &self.g % p;
+        let mut shift = BigUint::one();
+        let len = p.to_bytes_be().len();
+        self.windows = (0..bits.div_ceil(WINDOW_BITS))
+            .map(|_| {
+                let entries: Vec<BigUint> =
+                    std::iter::successors(Some(base.clone()), |x| Some(x * &base % p))
+                        .take(1 << WINDOW_BITS)
+                        .collect();
+                shift = &shift * &base % p;
+                // g^(2^(WINDOW_BITS * (k + 1))) for the next window
+                base = entries[entries.len() - 1].clone();
+
+                entries.iter().map(|x| padded(x, len)).collect()
+            })
+            .collect();
+        self.offset = shift.modpow(&(p - 2_u32), p);
+
+        self
+    }
+
+    /// g^s mod p, from the windows if they cover s
+    ///
+    /// s is a secret share, so every window is read whatever the digits of s are, each entry
+    /// by a scan of its whole table, and no entry is the 1 of a zero digit
+    fn pow_g(&self, s: &BigUint) -> BigUint {
+        if self.windows.is_empty() || s.bits() > self.windows.len() * WINDOW_BITS {
+            return self.g.modpow(s, &self.p);
+        }
+        let mut digits = s.to_radix_le(1 << WINDOW_BITS);
+        digits.resize(self.windows.len(), 0);
+
+        digits
+            .iter()
+            .zip(&self.windows)
+            .fold(self.offset.clone(), |y, (&digit, table)| {
+                y * BigUint::from_bytes_be(&lookup(table, digit)) % &self.p
+            })
+    }
+
+    /// Evaluates commitments c at i like `commitment_at`, with the powers of i kept if it is
+    /// one of the n indices of the dealing
+    pub fn commitment_at(&mut self, i: &BigUint, c: &[BigUint]) -> BigUint {
+        if i.is_zero() || *i > BigUint::from(self.n) {
+            return commitment_at(i, c, &self.p);
+        }
+        if !self.powers.contains_key(i) {
+            self.powers.insert(i.clone(), vec![]);
+        }
+        let powers = self.powers.get_mut(i).expect("inserted above");
+        extend_powers(powers, i, c.len());
+
+        evaluate(c, powers, &self.p)
+    }
+
+    /// Verify a share (i, s) against commitments c like `verify_share`
+    pub fn verify_share(&mut self, i: &BigUint, s: &BigUint, c: &[BigUint]) -> bool {
+        !self.p.is_zero() && secret::ct_eq(&self.pow_g(s), &self.commitment_at(i, c))
+    }
+}
+
+/// x as big endian bytes, padded with zeros to len
+fn padded(x: &BigUint, len: usize) -> Vec<u8> {
+    let bytes = x.to_bytes_be();
+    let mut padded = vec![0; len - bytes.len()];
+    padded.extend(bytes);
+
+    padded
+}
+
+/// The entry of `table` at `digit`, read by selecting every entry in constant time so the
+/// memory accessed does not depend on the digit
+fn lookup(table: &[Vec<u8>], digit: u8) -> Zeroizing<Vec<u8>> {
+    let mut entry = Zeroizing::new(vec![0; table[0].len()]);
+    for (d, candidate) in (0_u8..).zip(table) {
+        let choice = d.ct_eq(&digit);
+        for (byte, candidate) in entry.iter_mut().zip(candidate) {
+            byte.conditional_assign(candidate, choice);
+        }
+    }
+
+    entry
+}
+
+/// Checks a reconstructed secret against the commitment c_0 = g^a_0 mod p to the dealt one
+///
+/// Catches a quorum of the wrong dealing or threshold, or a corrupted share that was never
+/// verified, which interpolate to some other number without failing. g^secret is compared in
+/// constant time
+pub fn verify_secret(secret: &BigUint, g: &BigUint, c: &[BigUint], p: &BigUint) -> bool {
+    match c.first() {
+        Some(c_0) if !p.is_zero() => secret::ct_eq(&g.modpow(secret, p), c_0),
+        _ => false,
+    }
 }
 
 /// Reconstructs the polynomial, P, given shares and q and returns the secret which is P(0)
@@ -34,41 +232,256 @@ pub fn verify_share(i: &BigUint, s: &BigUint, g: &BigUint, c: &Vec<BigUint>, p:
 /// over prime field q
 /// https://en.wikipedia.org/wiki/Lagrange_polynomial
 /// https://en.wikipedia.org/wiki/Shamir%27s_secret_sharing
-pub fn reconstruct(shares: &Vec<(BigUint, BigUint)>, q: &BigUint) -> BigUint {
-    let mut secret = 0.to_bigint().unwrap();
+///
+/// Fails without shares, if two of them have the same index mod q or if q is not a prime
+pub fn reconstruct(shares: &[Share], q: &BigUint) -> Result<Secret, VssError> {
+    let xs = indices(shares, q)?;
 
-    for (x_j, y_j) in shares {
-        let mut prod = 1.to_bigint().unwrap();
+    Ok(interpolate(shares, &coefficients(&xs, q), q))
+}
 
-        for (x_m, _) in shares {
-            if x_m != x_j {
-                let delta = x_m.to_bigint().unwrap() - x_j.to_bigint().unwrap();
-                prod = (prod * div_mod_p(&x_m.to_bigint().unwrap(), &delta, &q))
-                    % q.to_bigint().unwrap();
-            }
+/// The sum of every share's y_j times its coefficient lambda_j over prime field q
+fn interpolate<'a, I>(shares: I, lambdas: &[BigUint], q: &BigUint) -> Secret
+where
+    I: IntoIterator<Item = &'a Share>,
+{
+    let secret = shares
+        .into_iter()
+        .zip(lambdas)
+        .fold(BigUint::zero(), |secret, (share_j, lambda_j)| {
+            (secret + share_j.value() * lambda_j) % q
+        });
+
+    Secret::new(secret)
+}
+
+/// Reconstructs the secret of every set of shares over its own prime field q, like
+/// `reconstruct` of each
+///
+/// Sets of shares at the same indices, as dealings to the same players have, share the
+/// products behind their Lagrange coefficients: each coefficient is computed once as a
+/// fraction over the integers up front, then the sets are checked, and their fractions reduced
+/// and inverted over their q, in parallel on scoped threads, one per available core
+pub fn reconstruct_many(sets: &[(&[Share], &BigUint)]) -> Vec<Result<Secret, VssError>> {
+    let threads = thread::available_parallelism().map_or(1, usize::from);
+
+    reconstruct_many_on(sets, threads)
+}
+
+/// `reconstruct_many` on up to `threads` threads, inline in the calling thread if one
+fn reconstruct_many_on(
+    sets: &[(&[Share], &BigUint)],
+    threads: usize,
+) -> Vec<Result<Secret, VssError>> {
+    // sorted, so the same players give the same fractions in whatever order their shares are
+    let sorted: Vec<Vec<&Share>> = sets
+        .iter()
+        .map(|(shares, _)| {
+            let mut shares: Vec<&Share> = shares.iter().collect();
+            shares.sort_by(|a, b| a.index().cmp(b.index()));
+            shares
+        })
+        .collect();
+    let mut fractions: HashMap<Vec<BigUint>, Vec<(BigInt, BigInt)>> = HashMap::new();
+    for shares in &sorted {
+        let xs = shares.iter().map(|share| share.index().clone()).collect();
+        fractions
+            .entry(xs)
+            .or_insert_with_key(|xs| lagrange_fractions(xs));
+    }
+
+    let fractions = &fractions;
+    let reconstruct = |(&(shares, q), sorted): (&(&[Share], &BigUint), &Vec<&Share>)| {
+        indices(shares, q)?;
+        let xs: Vec<BigUint> = sorted.iter().map(|share| share.index().clone()).collect();
+
+        Ok(interpolate(
+            sorted.iter().copied(),
+            &reduce_fractions(&fractions[&xs], q),
+            q,
+        ))
+    };
+    if threads <= 1 || sets.len() <= 1 {
+        return sets.iter().zip(&sorted).map(reconstruct).collect();
+    }
+
+    let chunk = sets.len().div_ceil(threads.min(sets.len()));
+    let reconstruct = &reconstruct;
+    thread::scope(|scope| {
+        let handles: Vec<_> = sets
+            .chunks(chunk)
+            .zip(sorted.chunks(chunk))
+            .map(|(sets, sorted)| {
+                scope.spawn(move || sets.iter().zip(sorted).map(reconstruct).collect::<Vec<_>>())
+            })
+            .collect();
+
+        handles
+            .into_iter()
+            .flat_map(|handle| handle.join().expect("reconstruction thread panicked"))
+            .collect()
+    })
+}
+
+/// The Lagrange basis polynomial of every point of xs at 0 as a fraction over the integers,
+/// the product of x_m over the product of x_m - x_j for m != j
+fn lagrange_fractions(xs: &[BigUint]) -> Vec<(BigInt, BigInt)> {
+    let xs: Vec<BigInt> = xs.iter().map(|x| x.to_bigint().unwrap()).collect();
+
+    xs.iter()
+        .enumerate()
+        .map(|(j, x_j)| {
+            xs.iter()
+                .enumerate()
+                .filter(|(m, _)| *m != j)
+                .fold((BigInt::one(), BigInt::one()), |(num, den), (_, x_m)| {
+                    (num * x_m, den * (x_m - x_j))
+                })
+        })
+        .collect()
+}
+
+/// Fractions of `lagrange_fractions` over prime field q, with a single modular inversion
+fn reduce_fractions(fractions: &[(BigInt, BigInt)], q: &BigUint) -> Vec<BigUint> {
+    let modulus = q.to_bigint().unwrap();
+    let reduce = |x: &BigInt| (x % &modulus + &modulus) % &modulus;
+    let dens: Vec<BigUint> = fractions
+        .iter()
+        .map(|(_, den)| reduce(den).to_biguint().unwrap())
+        .collect();
+
+    fractions
+        .iter()
+        .zip(batch_invert(&dens, q))
+        .map(|((num, _), inv)| reduce(num).to_biguint().unwrap() * inv % q)
+        .collect()
+}
+
+/// Fails unless q is a prime, which every x coordinate but its multiples has an inverse mod
+///
+/// q may come from a file or a peer, a q of zero fails rather than dividing by zero
+pub(crate) fn check_prime(q: &BigUint) -> Result<(), VssError> {
+    if primes::is_probable_prime(q) {
+        Ok(())
+    } else {
+        Err(VssError::InvalidParams(format!("q = {} is not a prime", q)))
+    }
+}
+
+/// The x coordinates of shares to interpolate, which must be at least one and differ mod a
+/// prime q
+pub(crate) fn indices(shares: &[Share], q: &BigUint) -> Result<Vec<BigUint>, VssError> {
+    check_prime(q)?;
+    if shares.is_empty() {
+        return Err(VssError::NoShares);
+    }
+    let mut seen = HashSet::new();
+    if let Some(share) = shares.iter().find(|share| !seen.insert(share.index() % q)) {
+        return Err(VssError::DuplicateIndex(share.index().clone()));
+    }
+
+    Ok(shares.iter().map(|share| share.index().clone()).collect())
+}
+
+/// Evaluates the Lagrange basis polynomial of x_j over the points xs at 0 over prime field q
+///
+/// The secret is the sum of every share's y_j times its coefficient. The points must differ
+/// mod q, which `reconstruct` checks
+pub fn lagrange_coefficient(x_j: &BigUint, xs: &[BigUint], q: &BigUint) -> BigUint {
+    let mut prod = 1.to_bigint().unwrap();
+
+    for x_m in xs {
+        if x_m != x_j {
+            let delta = x_m.to_bigint().unwrap() - x_j.to_bigint().unwrap();
+            prod =
+                (prod * div_mod_p(&x_m.to_bigint().unwrap(), &delta, q)) % q.to_bigint().unwrap();
         }
+    }
+
+    prod.to_biguint().unwrap()
+}
+
+/// Evaluates the Lagrange basis polynomial of every point of xs at 0 over prime field q
+///
+/// The same as `lagrange_coefficient` of each point, but with a single modular inversion: the
+/// numerators come from prefix and suffix products and the denominators are inverted together
+/// with Montgomery's trick, leaving O(t^2) multiplications. The points must differ mod q, and
+/// q must be a prime
+pub fn lagrange_coefficients(xs: &[BigUint], q: &BigUint) -> Result<Vec<BigUint>, VssError> {
+    check_prime(q)?;
+
+    Ok(coefficients(xs, q))
+}
 
-        secret = (secret + (y_j.to_bigint().unwrap() * prod)) % q.to_bigint().unwrap();
+/// `lagrange_coefficients` over a q already known to be prime
+fn coefficients(xs: &[BigUint], q: &BigUint) -> Vec<BigUint> {
+    let xs: Vec<BigUint> = xs.iter().map(|x| x % q).collect();
+
+    // product of x_m for m != j
+    let mut nums = Vec::with_capacity(xs.len());
+    let mut prefix = BigUint::one();
+    for x in &xs {
+        nums.push(prefix.clone());
+        prefix = prefix * x % q;
+    }
+    let mut suffix = BigUint::one();
+    for (num, x) in nums.iter_mut().zip(&xs).rev() {
+        *num = &*num * &suffix % q;
+        suffix = suffix * x % q;
+    }
+
+    // product of x_m - x_j for m != j
+    let dens: Vec<BigUint> = xs
+        .iter()
+        .enumerate()
+        .map(|(j, x_j)| {
+            xs.iter()
+                .enumerate()
+                .filter(|(m, _)| *m != j)
+                .fold(BigUint::one(), |den, (_, x_m)| {
+                    den * ((x_m + q - x_j) % q) % q
+                })
+        })
+        .collect();
+
+    nums.iter()
+        .zip(batch_invert(&dens, q))
+        .map(|(num, inv)| num * inv % q)
+        .collect()
+}
+
+/// Inverts every nonzero value mod q with one modular inversion, Montgomery's trick
+///
+/// Inverts the product of all values, then peels each value's inverse off it with the products
+/// of the values before it
+fn batch_invert(values: &[BigUint], q: &BigUint) -> Vec<BigUint> {
+    // v_0 * ... * v_(i-1)
+    let mut prefixes = Vec::with_capacity(values.len());
+    let mut product = BigUint::one();
+    for v in values {
+        prefixes.push(product.clone());
+        product = product * v % q;
     }
 
-    secret.to_biguint().unwrap()
+    let mut inv = div_mod_p(&BigInt::one(), &product.to_bigint().unwrap(), q)
+        .to_biguint()
+        .unwrap();
+    let mut inverses = vec![BigUint::zero(); values.len()];
+    for i in (0..values.len()).rev() {
+        inverses[i] = &inv * &prefixes[i] % q;
+        inv = inv * &values[i] % q;
+    }
+
+    inverses
 }
 
 /// Generate commitments c given polynomial and generator g of order q mod p
 ///
 /// Commitments are of the form g^a_0 mod p,g^a_1 mod p,...,g^a_n mod p
-pub fn generate_commitments(a: &Vec<BigUint>, g: &BigUint, p: &BigUint) -> Vec<BigUint> {
+pub fn generate_commitments(a: &[BigUint], g: &BigUint, p: &BigUint) -> Vec<BigUint> {
     a.iter().map(|a_i| g.modpow(a_i, p)).collect()
 }
 
-/// Evaluates a polynomial, P, from polynomial constants, a, and evaluates P(x)
-fn eval_poly_at(a: &Vec<BigUint>, x: usize) -> BigUint {
-    a.iter()
-        .enumerate()
-        .map(|(i, a_i)| a_i * num::pow(BigUint::from(x), i))
-        .sum::<BigUint>()
-}
-
 /// Evaluates a/b mod p
 ///
 /// Finds inverse of b mod p, t, then returns a*t
@@ -92,7 +505,7 @@ fn div_mod_p(a: &BigInt, b: &BigInt, m: &BigUint) -> BigInt {
 
     // ensure inverse is always positive
     if t_0 < BigInt::zero() {
-        t_0 = t_0 + m.to_bigint().unwrap();
+        t_0 += m.to_bigint().unwrap();
     }
 
     a * t_0
@@ -101,8 +514,12 @@ fn div_mod_p(a: &BigInt, b: &BigInt, m: &BigUint) -> BigInt {
 #[cfg(test)]
 mod tests {
     use num::ToPrimitive;
-    use num_bigint::ToBigUint;
+    use num_bigint::{BigUint, ToBigUint};
 
+    use crate::dealer::Dealer;
+    use crate::error::VssError;
+    use crate::rpc::Share;
+    use crate::secret::Secret;
     use crate::vss;
 
     #[test]
@@ -116,19 +533,66 @@ mod tests {
         let q = 5.to_biguint().unwrap();
         let expected_shares = vec![
             // 1 + 2(1) + 3(1^2) = 6 mod 5 = 1 mod 5
-            (1.to_biguint().unwrap(), 1.to_biguint().unwrap()),
+            Share::new(1.to_biguint().unwrap(), 1.to_biguint().unwrap()),
             // 1 + 2(2) + 3(2^2) = 17 mod 5 = 2 mod 5
-            (2.to_biguint().unwrap(), 2.to_biguint().unwrap()),
+            Share::new(2.to_biguint().unwrap(), 2.to_biguint().unwrap()),
             // 1 + 2(3) + 3(3^2) = 34 mod 5 = 4 mod 5
-            (3.to_biguint().unwrap(), 4.to_biguint().unwrap()),
+            Share::new(3.to_biguint().unwrap(), 4.to_biguint().unwrap()),
             // 1 + 2(4) + 3(4^2) = 57 mod 5 = 2 mod 5
-            (4.to_biguint().unwrap(), 2.to_biguint().unwrap()),
+            Share::new(4.to_biguint().unwrap(), 2.to_biguint().unwrap()),
         ];
         let actual_shares = vss::generate_shares(&a, n, &q);
 
         assert_eq!(expected_shares, actual_shares);
     }
 
+    #[test]
+    fn generate_shares_iter() {
+        let a = vec![
+            1.to_biguint().unwrap(),
+            2.to_biguint().unwrap(),
+            3.to_biguint().unwrap(),
+        ];
+        let q = 7919.to_biguint().unwrap();
+
+        let mut shares = vss::generate_shares_iter(&a, &q);
+        assert_eq!(
+            vss::generate_shares(&a, 3, &q),
+            shares.by_ref().take(3).collect::<Vec<_>>()
+        );
+        // far indices are evaluated one at a time, the earlier ones are never held
+        let share = shares.nth(199_995).unwrap();
+        let x = 199_999.to_biguint().unwrap();
+        assert_eq!(&x, share.index());
+        assert_eq!((&a[0] + &a[1] * &x + &a[2] * &x * &x) % &q, *share.value());
+    }
+
+    #[test]
+    fn lagrange_coefficients() {
+        let q = 7919.to_biguint().unwrap();
+        let xs: Vec<_> = [3, 1, 12, 7919 + 5, 40]
+            .iter()
+            .map(|x: &u32| x.to_biguint().unwrap())
+            .collect();
+        let expected: Vec<_> = xs
+            .iter()
+            .map(|x| vss::lagrange_coefficient(x, &xs, &q))
+            .collect();
+        assert_eq!(expected, vss::lagrange_coefficients(&xs, &q).unwrap());
+
+        // a threshold in the hundreds reconstructs with one inversion
+        let a: Vec<_> = (1..=300_u32).map(|a_i| a_i.to_biguint().unwrap()).collect();
+        let shares = vss::generate_shares(&a, 300, &q);
+        assert_eq!(
+            1,
+            vss::reconstruct(&shares, &q)
+                .unwrap()
+                .expose()
+                .to_u32()
+                .unwrap()
+        );
+    }
+
     #[test]
     fn verify() {
         let a = vec![
@@ -148,10 +612,14 @@ mod tests {
             4.to_biguint().unwrap(),
         ];
         let shares = vss::generate_shares(&a, 5, &q);
+        let secret = vss::reconstruct(&shares[..3], &q).unwrap();
+        assert!(vss::verify_secret(secret.expose(), &g, &c, &p));
+        assert!(!vss::verify_secret(&1.to_biguint().unwrap(), &g, &c, &p));
 
-        for (i, s_i) in shares {
+        for share in shares {
+            let (i, s_i) = (share.index(), share.value());
             assert!(
-                vss::verify_share(&i, &s_i, &g, &c, &p),
+                vss::verify_share(i, s_i, &g, &c, &p),
                 "failed {} {}",
                 i,
                 s_i
@@ -159,22 +627,145 @@ mod tests {
         }
     }
 
+    #[test]
+    fn verify_rejects_zero_modulus() {
+        let one = 1.to_biguint().unwrap();
+        let zero = 0.to_biguint().unwrap();
+
+        assert!(!vss::verify_share(
+            &one,
+            &one,
+            &one,
+            std::slice::from_ref(&one),
+            &zero
+        ));
+        assert!(!vss::Verifier::new(&one, &zero, 1)
+            .with_windows(8)
+            .verify_share(&one, &one, std::slice::from_ref(&one)));
+    }
+
+    #[test]
+    fn verifier_agrees_with_verify_share() {
+        let secret = Secret::new(BigUint::from(1234_u32));
+        let dealer = Dealer::with_bits(10, 4, secret, 64).unwrap();
+        let (g, c, p, q) = (&dealer.g, &dealer.c, &dealer.p, &dealer.q);
+
+        for mut verifier in [
+            vss::Verifier::new(g, p, 10),
+            vss::Verifier::new(g, p, 5).with_windows(q.bits()),
+        ] {
+            // twice over, the second time from the kept powers, of no more than n indices
+            for share in dealer.shares.iter().chain(&dealer.shares) {
+                let (i, s) = (share.index(), share.value());
+                assert!(verifier.verify_share(i, s, c));
+                assert_eq!(vss::commitment_at(i, c, p), verifier.commitment_at(i, c));
+                assert!(!verifier.verify_share(i, &(s + 1_u32), c));
+                // g has order q, a share past the windows still verifies
+                assert!(verifier.verify_share(i, &(s + q), c));
+            }
+            assert!(verifier.powers.len() <= verifier.n);
+            // a zero share has no digit to skip
+            let zero = 0.to_biguint().unwrap();
+            assert_eq!(g.modpow(&zero, p), verifier.pow_g(&zero));
+        }
+    }
+
     #[test]
     fn reconstruct() {
         let shares = vec![
-            (2.to_biguint().unwrap(), 1942.to_biguint().unwrap()),
-            (4.to_biguint().unwrap(), 3402.to_biguint().unwrap()),
-            (5.to_biguint().unwrap(), 4414.to_biguint().unwrap()),
+            Share::new(2.to_biguint().unwrap(), 1942.to_biguint().unwrap()),
+            Share::new(4.to_biguint().unwrap(), 3402.to_biguint().unwrap()),
+            Share::new(5.to_biguint().unwrap(), 4414.to_biguint().unwrap()),
         ];
         // random prime
         let q = 13931.to_biguint().unwrap();
 
         assert_eq!(
-            1234 as usize,
-            vss::reconstruct(&shares, &q).to_usize().unwrap()
+            1234_usize,
+            vss::reconstruct(&shares, &q)
+                .unwrap()
+                .expose()
+                .to_usize()
+                .unwrap()
         );
     }
 
+    #[test]
+    fn reconstruct_rejects_duplicate_indices() {
+        let q = 13931.to_biguint().unwrap();
+        let share = Share::new(2.to_biguint().unwrap(), 1942.to_biguint().unwrap());
+        let wrapped = Share::new(&q + 2_u32, 1942.to_biguint().unwrap());
+
+        assert!(matches!(
+            vss::reconstruct(&[share, wrapped], &q),
+            Err(VssError::DuplicateIndex(_))
+        ));
+        assert!(matches!(vss::reconstruct(&[], &q), Err(VssError::NoShares)));
+    }
+
+    #[test]
+    fn reconstruct_rejects_a_q_that_is_not_prime() {
+        let share = Share::new(2.to_biguint().unwrap(), 1942.to_biguint().unwrap());
+        for q in [0_u32, 1, 7917] {
+            let q = q.to_biguint().unwrap();
+            assert!(matches!(
+                vss::reconstruct(std::slice::from_ref(&share), &q),
+                Err(VssError::InvalidParams(_))
+            ));
+            assert!(vss::lagrange_coefficients(&[share.index().clone()], &q).is_err());
+            assert!(matches!(
+                vss::reconstruct_many(&[(std::slice::from_ref(&share), &q)])[0],
+                Err(VssError::InvalidParams(_))
+            ));
+        }
+    }
+
+    #[test]
+    fn reconstruct_many_matches_reconstruct() {
+        let dealers: Vec<Dealer> = [1234_u32, 5678, 91011]
+            .iter()
+            .map(|&secret| Dealer::with_bits(5, 3, Secret::new(BigUint::from(secret)), 64).unwrap())
+            .collect();
+        // the same players of every dealing, in any order, and other players of the last one
+        let same = |dealer: &Dealer| {
+            vec![
+                dealer.shares[3].clone(),
+                dealer.shares[0].clone(),
+                dealer.shares[1].clone(),
+            ]
+        };
+        let sets = [
+            same(&dealers[0]),
+            same(&dealers[1]),
+            dealers[2].shares[2..].to_vec(),
+            vec![dealers[2].shares[0].clone(); 2],
+        ];
+        let qs = [&dealers[0].q, &dealers[1].q, &dealers[2].q, &dealers[2].q];
+        let args: Vec<(&[Share], &BigUint)> = sets.iter().map(|set| &set[..]).zip(qs).collect();
+
+        let secrets = vss::reconstruct_many(&args);
+        for ((shares, q), secret) in args.iter().zip(&secrets).take(3) {
+            assert_eq!(
+                vss::reconstruct(shares, q).unwrap(),
+                *secret.as_ref().unwrap()
+            );
+        }
+        assert_eq!(Some(5678), secrets[1].as_ref().unwrap().expose().to_u32());
+        assert!(matches!(secrets[3], Err(VssError::DuplicateIndex(_))));
+
+        // however many threads share the sets, every secret comes back in its set's place
+        let exposed = |secrets: &[Result<Secret, VssError>]| {
+            secrets
+                .iter()
+                .map(|secret| secret.as_ref().ok().map(|secret| secret.expose().clone()))
+                .collect::<Vec<_>>()
+        };
+        for threads in [1, 2, 3, 8] {
+            let parallel = vss::reconstruct_many_on(&args, threads);
+            assert_eq!(exposed(&secrets), exposed(&parallel));
+        }
+    }
+
     #[test]
     fn generate_commitments() {
         let a = vec![
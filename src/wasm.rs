@@ -0,0 +1,90 @@
+//! JavaScript bindings through wasm-bindgen, so a browser can split a secret of bytes and verify
+//! or combine shares without sending them anywhere
+//!
+//! Shares cross the boundary as the JSON of share files, as `vss deal` writes them. Only
+//! `split` draws randomness, which rand 0.5 gets on wasm32-unknown-unknown through stdweb
+
+use std::io;
+use std::path::PathBuf;
+
+use wasm_bindgen::prelude::*;
+
+use crate::dealer::Dealer;
+use crate::format;
+use crate::rpc::ShareInfo;
+use crate::secret::SecretBytes;
+
+/// Split `secret` into `n` share files, any `t` of which reconstruct it
+#[wasm_bindgen]
+pub fn split(secret: &[u8], n: usize, t: usize) -> Result<Vec<String>, JsError> {
+    split_shares(secret, n, t).map_err(js)
+}
+
+/// Whether the share file verifies against the commitments it carries, an error if it is not
+/// a share file
+#[wasm_bindgen]
+pub fn verify(share: &str) -> Result<bool, JsError> {
+    Ok(read(share).map_err(js)?.verify())
+}
+
+/// The secret of the share files, which must all verify and come from the same dealing
+#[wasm_bindgen]
+pub fn reconstruct(shares: Vec<String>) -> Result<Vec<u8>, JsError> {
+    let secret = reconstruct_shares(&shares).map_err(js)?;
+
+    Ok(secret.expose().to_vec())
+}
+
+fn split_shares(secret: &[u8], n: usize, t: usize) -> io::Result<Vec<String>> {
+    let dealer = Dealer::with_secret(n, t, format::encode_secret(secret))?;
+    let params = dealer.params();
+
+    dealer
+        .shares
+        .iter()
+        .map(|share| {
+            let mut json = vec![];
+            format::write_share(
+                &mut json,
+                &ShareInfo::with_params(share.clone(), params.clone()),
+            )?;
+
+            String::from_utf8(json).map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))
+        })
+        .collect()
+}
+
+fn reconstruct_shares(shares: &[String]) -> io::Result<SecretBytes> {
+    // errors name a share by its position, as they would name its file
+    let infos = shares
+        .iter()
+        .enumerate()
+        .map(|(i, json)| Ok((PathBuf::from(format!("share {}", i)), read(json)?)))
+        .collect::<io::Result<Vec<_>>>()?;
+
+    format::decode_secret(&format::reconstruct_shares(&infos)?)
+}
+
+fn read(share: &str) -> io::Result<ShareInfo> {
+    format::read_share(share.as_bytes())
+}
+
+fn js(err: io::Error) -> JsError {
+    JsError::new(&err.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{read, reconstruct_shares, split_shares};
+
+    #[test]
+    fn split_shares_reconstruct_from_any_t() {
+        let secret = b"\x00wasm secret";
+        let shares = split_shares(secret, 5, 3).unwrap();
+
+        assert!(shares.iter().all(|share| read(share).unwrap().verify()));
+        assert_eq!(secret, reconstruct_shares(&shares[2..]).unwrap().expose());
+        assert!(reconstruct_shares(&shares[..2]).is_err());
+        assert!(split_shares(secret, 2, 3).is_err());
+    }
+}
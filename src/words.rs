@@ -0,0 +1,202 @@
+//! Shares as words of the BIP-39 English list, to be read out over the phone or written down
+//!
+//! A share is its value's length, its index and value, and a checksum of the three, packed 11
+//! bits to a word. Every word is known by its first four letters, so abbreviations are taken,
+//! and the checksum catches a word heard or written wrong, pointing at the likely fix.
+
+use std::io;
+
+use num::ToPrimitive;
+use sha2::{Digest, Sha256};
+
+use crate::codec;
+use crate::rpc::Share;
+
+const WORDS: &str = include_str!("words/bip39.txt");
+
+const WORD_BITS: usize = 11;
+/// Bytes of the value's length and of the index, before the value
+const HEADER_BYTES: usize = 3;
+const CHECKSUM_BYTES: usize = 4;
+/// Letters that tell every word apart
+const PREFIX_LEN: usize = 4;
+
+fn checksum(bytes: &[u8]) -> [u8; CHECKSUM_BYTES] {
+    let digest = Sha256::digest(bytes);
+
+    [digest[0], digest[1], digest[2], digest[3]]
+}
+
+/// The words standing for `share`, separated by spaces
+pub fn encode(share: &Share) -> io::Result<String> {
+    let index = share.index().to_u16().ok_or_else(|| {
+        io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "share index does not fit a u16",
+        )
+    })?;
+    let value = codec::to_bytes(share.value());
+    let len = u8::try_from(value.len()).map_err(|_| {
+        io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "share value longer than 255 bytes",
+        )
+    })?;
+
+    let mut bytes = [&[len], &index.to_be_bytes()[..], &value].concat();
+    bytes.extend(checksum(&bytes));
+
+    let words: Vec<&str> = WORDS.lines().collect();
+    Ok(to_indices(&bytes)
+        .into_iter()
+        .map(|i| words[i])
+        .collect::<Vec<_>>()
+        .join(" "))
+}
+
+/// The share `text` stands for, its words separated by whitespace in any case and each
+/// possibly cut short to its first four letters
+pub fn decode(text: &str) -> io::Result<Share> {
+    let words: Vec<&str> = WORDS.lines().collect();
+    let mut indices = text
+        .split_whitespace()
+        .map(|word| {
+            lookup(&words, &word.to_lowercase()).ok_or_else(|| {
+                io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("{} is not a BIP-39 word", word),
+                )
+            })
+        })
+        .collect::<io::Result<Vec<usize>>>()?;
+
+    if let Some(share) = from_indices(&indices) {
+        return Ok(share);
+    }
+
+    // look for the single word that makes the checksum match
+    let mut fixes = vec![];
+    for position in 0..indices.len() {
+        let heard = indices[position];
+        for candidate in (0..words.len()).filter(|c| *c != heard) {
+            indices[position] = candidate;
+            if from_indices(&indices).is_some() {
+                fixes.push((position, candidate));
+            }
+        }
+        indices[position] = heard;
+    }
+
+    let msg = match fixes[..] {
+        [(position, candidate)] => format!(
+            "share words do not check out, word {} may be {}",
+            position + 1,
+            words[candidate]
+        ),
+        _ => "share words do not check out".to_string(),
+    };
+    Err(io::Error::new(io::ErrorKind::InvalidData, msg))
+}
+
+/// Index of `word` in the list, or of the only word it is a prefix of at least four letters
+fn lookup(words: &[&str], word: &str) -> Option<usize> {
+    if let Ok(i) = words.binary_search(&word) {
+        return Some(i);
+    }
+    if word.len() < PREFIX_LEN {
+        return None;
+    }
+
+    let start = words.partition_point(|w| *w < word);
+    match words.get(start) {
+        Some(w) if w.starts_with(word) => Some(start),
+        _ => None,
+    }
+}
+
+/// 11 bit word indices of `bytes`, the last word padded with zero bits
+fn to_indices(bytes: &[u8]) -> Vec<usize> {
+    let mut indices = vec![];
+    let (mut acc, mut acc_bits) = (0_u32, 0);
+    for &byte in bytes {
+        acc = (acc << 8) | u32::from(byte);
+        acc_bits += 8;
+        if acc_bits >= WORD_BITS {
+            acc_bits -= WORD_BITS;
+            indices.push((acc >> acc_bits) as usize);
+            acc &= (1 << acc_bits) - 1;
+        }
+    }
+    if acc_bits > 0 {
+        indices.push((acc << (WORD_BITS - acc_bits)) as usize);
+    }
+
+    indices
+}
+
+/// The share of word `indices`, None unless they are exactly the words of one with a
+/// matching checksum
+fn from_indices(indices: &[usize]) -> Option<Share> {
+    let mut bytes = vec![];
+    let (mut acc, mut acc_bits) = (0_u32, 0);
+    for &index in indices {
+        acc = (acc << WORD_BITS) | index as u32;
+        acc_bits += WORD_BITS;
+        while acc_bits >= 8 {
+            acc_bits -= 8;
+            bytes.push((acc >> acc_bits) as u8);
+            acc &= (1 << acc_bits) - 1;
+        }
+    }
+
+    let len = HEADER_BYTES + usize::from(*bytes.first()?) + CHECKSUM_BYTES;
+    let padding_zero = acc == 0 && bytes.get(len..)?.iter().all(|b| *b == 0);
+    if to_indices(&bytes[..len]).len() != indices.len() || !padding_zero {
+        return None;
+    }
+
+    let (payload, sum) = bytes[..len].split_at(len - CHECKSUM_BYTES);
+    if checksum(payload) != sum {
+        return None;
+    }
+    let index = u16::from_be_bytes([payload[1], payload[2]]);
+    let value = codec::from_bytes(&payload[HEADER_BYTES..])?;
+
+    Some(Share::new(index.into(), value))
+}
+
+#[cfg(test)]
+mod tests {
+    use num_bigint::BigUint;
+
+    use crate::rpc::Share;
+
+    use super::{decode, encode};
+
+    #[test]
+    fn words_roundtrip_through_abbreviations() {
+        let share = Share::new(BigUint::from(3_u32), BigUint::from(0xdead_beef_u32));
+        let words = encode(&share).unwrap();
+        assert_eq!(8, words.split(' ').count());
+
+        let handwritten: Vec<String> = words
+            .split(' ')
+            .map(|word| word.chars().take(4).collect::<String>().to_uppercase())
+            .collect();
+        assert_eq!(share, decode(&handwritten.join(" ")).unwrap());
+    }
+
+    #[test]
+    fn wrong_word_is_pointed_at() {
+        let share = Share::new(BigUint::from(3_u32), BigUint::from(0xdead_beef_u32));
+        let words = encode(&share).unwrap();
+        let mut misheard: Vec<&str> = words.split(' ').collect();
+        let right = misheard[2];
+        misheard[2] = if right == "zoo" { "zone" } else { "zoo" };
+
+        let err = decode(&misheard.join(" ")).unwrap_err();
+        assert!(err
+            .to_string()
+            .contains(&format!("word 3 may be {}", right)));
+    }
+}
@@ -0,0 +1,86 @@
+use std::sync::mpsc::{self, Sender};
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+
+type Job = Box<dyn FnOnce() + Send + 'static>;
+
+/// Fixed size pool of threads used to run expensive work off of a Player's message loop
+pub struct WorkerPool {
+    jobs: Option<Sender<Job>>,
+    workers: Vec<JoinHandle<()>>,
+}
+
+impl WorkerPool {
+    /// Spawn a pool with `size` worker threads (at least one)
+    pub fn new(size: usize) -> WorkerPool {
+        let (jobs, rx) = mpsc::channel::<Job>();
+        let rx = Arc::new(Mutex::new(rx));
+        let workers = (0..size.max(1))
+            .map(|_| {
+                let rx = Arc::clone(&rx);
+                thread::spawn(move || loop {
+                    // hold the lock only while taking a job off of the queue
+                    let job = match rx.lock() {
+                        Ok(rx) => rx.recv(),
+                        Err(_) => return,
+                    };
+
+                    match job {
+                        Ok(job) => job(),
+                        Err(_) => return,
+                    }
+                })
+            })
+            .collect();
+
+        WorkerPool {
+            jobs: Some(jobs),
+            workers,
+        }
+    }
+
+    /// Queue `job` to run on the next free worker
+    pub fn execute<F>(&self, job: F)
+    where
+        F: FnOnce() + Send + 'static,
+    {
+        if let Some(jobs) = &self.jobs {
+            // workers only exit once `jobs` is dropped so this cannot fail
+            let _ = jobs.send(Box::new(job));
+        }
+    }
+}
+
+impl Drop for WorkerPool {
+    fn drop(&mut self) {
+        // closing the queue lets every worker finish its current job and exit
+        self.jobs.take();
+        self.workers.drain(..).for_each(|w| {
+            let _ = w.join();
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::mpsc;
+
+    use super::WorkerPool;
+
+    #[test]
+    fn executes_all_jobs() {
+        let pool = WorkerPool::new(4);
+        let (tx, rx) = mpsc::channel();
+
+        for i in 0..16 {
+            let tx = tx.clone();
+            pool.execute(move || tx.send(i).unwrap());
+        }
+        drop(tx);
+
+        let mut results: Vec<usize> = rx.iter().collect();
+        results.sort();
+
+        assert_eq!((0..16).collect::<Vec<usize>>(), results);
+    }
+}